@@ -61,7 +61,22 @@ fn build_jbig2enc_shim() {
     println!("cargo:rerun-if-env-changed=LEPTONICA_INCLUDE_PATH");
 }
 
+/// Link against the system OpenJPEG library for JPXDecode support.
+///
+/// `OPENJPEG_LIB_PATH` is optional; without it the linker's default search
+/// path is used.
+#[cfg(feature = "jpx")]
+fn link_openjpeg() {
+    if let Ok(lib_path) = std::env::var("OPENJPEG_LIB_PATH") {
+        println!("cargo:rustc-link-search=native={lib_path}");
+    }
+    println!("cargo:rustc-link-lib=openjp2");
+    println!("cargo:rerun-if-env-changed=OPENJPEG_LIB_PATH");
+}
+
 fn main() {
     #[cfg(feature = "mrc")]
     build_jbig2enc_shim();
+    #[cfg(feature = "jpx")]
+    link_openjpeg();
 }
@@ -34,6 +34,7 @@
             libjpeg
             libpng
             pdfium-binaries
+            openjpeg
 
             # Development tools
             markdownlint-cli
@@ -51,6 +52,7 @@
               pkgs.libpng
               pkgs.libclang.lib
               pkgs.pdfium-binaries
+              pkgs.openjpeg
             ]}:$LD_LIBRARY_PATH"
 
             # pdfium path for pdfium-render dynamic loading
@@ -65,6 +67,9 @@
 
             # leptonica paths for C++ shim compilation
             export LEPTONICA_INCLUDE_PATH="${pkgs.leptonica}/include"
+
+            # openjpeg path for the optional `jpx` feature
+            export OPENJPEG_LIB_PATH="${pkgs.openjpeg}/lib"
           '';
         };
       }
@@ -4,22 +4,43 @@
 // The key is a SHA-256 hash encoded as a lowercase hexadecimal string.
 
 use std::collections::BTreeMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use sha2::{Digest, Sha256};
 use tracing::debug;
 
-use crate::config::job::ColorMode;
+use crate::config::job::{
+    ColorMode, ForegroundMask, ImageColorSpace, JpegSizeMismatch, OutputColorSpace, ResampleFilter,
+    WidthSource,
+};
+use crate::config::settings::{ImageClassDpi, Jbig2Options, JpegEscalation, LineRemoval};
 
 /// MRC処理に影響する設定パラメータ。
 ///
 /// キャッシュキー計算時にハッシュに含める設定値のみを保持する。
+#[derive(Clone)]
 pub struct CacheSettings {
     pub dpi: u32,
     pub fg_dpi: u32,
     pub bg_quality: u8,
     pub fg_quality: u8,
     pub color_mode: ColorMode,
+    pub jbig2: Jbig2Options,
+    pub jpeg_escalation: JpegEscalation,
+    pub image_max_dpi: Option<u32>,
+    pub image_class_dpi: ImageClassDpi,
+    pub normalize_images_to: Option<ImageColorSpace>,
+    pub resample_filter: ResampleFilter,
+    pub max_image_pixels: u64,
+    pub jpeg_size_mismatch: JpegSizeMismatch,
+    pub output_colorspace: OutputColorSpace,
+    pub text_antialias: bool,
+    pub line_removal: LineRemoval,
+    pub width_source: WidthSource,
+    pub merge_white_fills: bool,
+    pub font_substitutions: BTreeMap<String, PathBuf>,
+    pub font_fallback: Option<PathBuf>,
+    pub use_system_fonts: bool,
 }
 
 /// 設定を正規化JSON形式に変換する（キーはアルファベット順で固定）。
@@ -31,6 +52,81 @@ fn settings_to_canonical_json(settings: &CacheSettings) -> String {
     map.insert("dpi", serde_json::json!(settings.dpi));
     map.insert("fg_dpi", serde_json::json!(settings.fg_dpi));
     map.insert("fg_quality", serde_json::json!(settings.fg_quality));
+    let font_fallback_str = settings
+        .font_fallback
+        .as_deref()
+        .map(|path| path.to_string_lossy());
+    map.insert("font_fallback", serde_json::json!(font_fallback_str));
+    let font_substitutions: BTreeMap<&str, _> = settings
+        .font_substitutions
+        .iter()
+        .map(|(name, path)| (name.as_str(), path.to_string_lossy()))
+        .collect();
+    map.insert("font_substitutions", serde_json::json!(font_substitutions));
+    map.insert("image_max_dpi", serde_json::json!(settings.image_max_dpi));
+    map.insert("jbig2_tpgdon", serde_json::json!(settings.jbig2.tpgdon));
+    map.insert(
+        "jpeg_max_quality",
+        serde_json::json!(settings.jpeg_escalation.max_quality),
+    );
+    map.insert(
+        "jpeg_min_psnr",
+        serde_json::json!(settings.jpeg_escalation.min_psnr),
+    );
+    map.insert(
+        "jpeg_quality_step",
+        serde_json::json!(settings.jpeg_escalation.step),
+    );
+    let jpeg_size_mismatch_str = super::jpeg_size_mismatch_to_str(settings.jpeg_size_mismatch);
+    map.insert(
+        "jpeg_size_mismatch",
+        serde_json::json!(jpeg_size_mismatch_str),
+    );
+    map.insert(
+        "line_art_max_dpi",
+        serde_json::json!(settings.image_class_dpi.line_art),
+    );
+    map.insert(
+        "max_image_pixels",
+        serde_json::json!(settings.max_image_pixels),
+    );
+    map.insert(
+        "merge_white_fills",
+        serde_json::json!(settings.merge_white_fills),
+    );
+    let normalize_images_to_str = settings
+        .normalize_images_to
+        .map(super::image_colorspace_to_str);
+    map.insert(
+        "normalize_images_to",
+        serde_json::json!(normalize_images_to_str),
+    );
+    let output_colorspace_str = super::output_colorspace_to_str(settings.output_colorspace);
+    map.insert(
+        "output_colorspace",
+        serde_json::json!(output_colorspace_str),
+    );
+    map.insert(
+        "photo_max_dpi",
+        serde_json::json!(settings.image_class_dpi.photo),
+    );
+    let resample_filter_str = super::resample_filter_to_str(settings.resample_filter);
+    map.insert("resample_filter", serde_json::json!(resample_filter_str));
+    map.insert(
+        "remove_hlines",
+        serde_json::json!(settings.line_removal.horizontal),
+    );
+    map.insert(
+        "remove_vlines",
+        serde_json::json!(settings.line_removal.vertical),
+    );
+    map.insert("text_antialias", serde_json::json!(settings.text_antialias));
+    map.insert(
+        "use_system_fonts",
+        serde_json::json!(settings.use_system_fonts),
+    );
+    let width_source_str = super::width_source_to_str(settings.width_source);
+    map.insert("width_source", serde_json::json!(width_source_str));
     serde_json::to_string(&map).expect("serializing primitive cache settings to JSON must not fail")
 }
 
@@ -63,26 +159,180 @@ pub fn compute_cache_key(
     key
 }
 
+/// マスクに影響する設定のみを正規化JSON形式に変換する（キーはアルファベット順で固定）。
+///
+/// カラーモード・JPEG品質・出力色空間はマスクに影響しないため含めない。
+fn mask_settings_to_canonical_json(settings: &CacheSettings) -> String {
+    let mut map = BTreeMap::new();
+    map.insert("dpi", serde_json::json!(settings.dpi));
+    map.insert("jbig2_tpgdon", serde_json::json!(settings.jbig2.tpgdon));
+    map.insert(
+        "remove_hlines",
+        serde_json::json!(settings.line_removal.horizontal),
+    );
+    map.insert(
+        "remove_vlines",
+        serde_json::json!(settings.line_removal.vertical),
+    );
+    map.insert("text_antialias", serde_json::json!(settings.text_antialias));
+    serde_json::to_string(&map).expect("serializing primitive cache settings to JSON must not fail")
+}
+
+/// JBIG2マスク用のキャッシュキー（SHA-256ハッシュ）を計算する。
+///
+/// マスクはDPI・二値化（JBIG2設定・罫線除去・アンチエイリアス）に依存するが
+/// カラーモードには依存しないため、[`compute_cache_key`]とは別に、カラーモードを
+/// 除いた設定からキーを作る。カラーモードだけを変えた再実行でマスクを再利用できる。
+pub fn compute_mask_cache_key(
+    content_stream: &[u8],
+    settings: &CacheSettings,
+    pdf_path: &Path,
+    page_index: u32,
+) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(pdf_path.as_os_str().as_encoded_bytes());
+    hasher.update(page_index.to_le_bytes());
+    hasher.update(content_stream);
+    // ページ全体のキーと衝突しないよう区切りを入れる
+    hasher.update(b"\0mask\0");
+    hasher.update(mask_settings_to_canonical_json(settings).as_bytes());
+
+    let key = hex::encode(hasher.finalize());
+    debug!(
+        key_prefix = &key[..16],
+        page = page_index,
+        "computed mask cache key"
+    );
+    key
+}
+
+/// 出力ページの書き出しに影響し、[`CacheSettings`]に含まれないオプション。
+///
+/// incrementalモードの元ページキー（[`compute_source_key`]）にだけ含める。
+pub struct WriterSettings {
+    pub foreground_mask: ForegroundMask,
+    pub interpolate_output: bool,
+    pub tag_srgb: bool,
+    pub text_region_padding: f64,
+    pub simplify_content: bool,
+    pub split_content_bytes: Option<u64>,
+    pub passthrough_full_page_images: bool,
+    pub retry_empty_at_dpi: Option<u32>,
+}
+
+/// 書き出しオプションを正規化JSON形式に変換する（キーはアルファベット順で固定）。
+fn writer_settings_to_canonical_json(settings: &WriterSettings) -> String {
+    let mut map = BTreeMap::new();
+    map.insert(
+        "foreground_mask",
+        serde_json::json!(settings.foreground_mask),
+    );
+    map.insert(
+        "interpolate_output",
+        serde_json::json!(settings.interpolate_output),
+    );
+    map.insert(
+        "passthrough_full_page_images",
+        serde_json::json!(settings.passthrough_full_page_images),
+    );
+    map.insert(
+        "retry_empty_at_dpi",
+        serde_json::json!(settings.retry_empty_at_dpi),
+    );
+    map.insert(
+        "simplify_content",
+        serde_json::json!(settings.simplify_content),
+    );
+    map.insert(
+        "split_content_bytes",
+        serde_json::json!(settings.split_content_bytes),
+    );
+    map.insert("tag_srgb", serde_json::json!(settings.tag_srgb));
+    map.insert(
+        "text_region_padding",
+        serde_json::json!(settings.text_region_padding),
+    );
+    serde_json::to_string(&map)
+        .expect("serializing primitive writer settings to JSON must not fail")
+}
+
+/// incrementalモードでマスク済みページに記録する元ページキーを計算する。
+///
+/// [`compute_cache_key`]の入力に加え、ページのResources（参照先のストリームを含む、
+/// [`PdfReader::page_resource_bytes`](crate::pdf::reader::PdfReader::page_resource_bytes)）と
+/// 書き出しオプションを含める。コンテンツストリームが同じでも、画像やフォントを
+/// 差し替えたページや書き出し方を変えた実行では別のキーになる。
+pub fn compute_source_key(
+    content_stream: &[u8],
+    page_resources: &[u8],
+    settings: &CacheSettings,
+    writer_settings: &WriterSettings,
+    pdf_path: &Path,
+    page_index: u32,
+) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(compute_cache_key(content_stream, settings, pdf_path, page_index).as_bytes());
+    // ページのキャッシュキーと衝突しないよう区切りを入れる
+    hasher.update(b"\0source\0");
+    hasher.update((page_resources.len() as u64).to_le_bytes());
+    hasher.update(page_resources);
+    hasher.update(writer_settings_to_canonical_json(writer_settings).as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// キャッシュキーに利用者指定のタグを結合した派生キーを計算する。
+///
+/// 前処理フックでビットマップを変更する場合など、同じ入力でも出力が変わる
+/// 処理を区別するために使う。
+pub fn tag_cache_key(cache_key: &str, tag: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(cache_key.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(tag.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
 
-    #[test]
-    fn test_settings_json_is_sorted_by_key() {
-        let settings = CacheSettings {
+    /// テスト用の設定（組み込みデフォルト相当）。テストごとに変える値だけ構造体更新構文で上書きする
+    pub(crate) fn test_settings() -> CacheSettings {
+        CacheSettings {
             dpi: 300,
             fg_dpi: 150,
             bg_quality: 50,
             fg_quality: 30,
             color_mode: ColorMode::Rgb,
-        };
+            jbig2: Jbig2Options::default(),
+            jpeg_escalation: JpegEscalation::default(),
+            image_max_dpi: None,
+            image_class_dpi: ImageClassDpi::default(),
+            normalize_images_to: None,
+            resample_filter: ResampleFilter::Triangle,
+            max_image_pixels: 268_435_456,
+            jpeg_size_mismatch: JpegSizeMismatch::Warn,
+            output_colorspace: OutputColorSpace::Rgb,
+            text_antialias: true,
+            line_removal: LineRemoval::default(),
+            width_source: WidthSource::Pdf,
+            merge_white_fills: false,
+            font_substitutions: BTreeMap::new(),
+            font_fallback: None,
+            use_system_fonts: true,
+        }
+    }
+
+    #[test]
+    fn test_settings_json_is_sorted_by_key() {
+        let settings = test_settings();
 
         let json = settings_to_canonical_json(&settings);
 
         // Verify the exact JSON output
         assert_eq!(
             json,
-            "{\"bg_quality\":50,\"color_mode\":\"rgb\",\"dpi\":300,\"fg_dpi\":150,\"fg_quality\":30}"
+            "{\"bg_quality\":50,\"color_mode\":\"rgb\",\"dpi\":300,\"fg_dpi\":150,\"fg_quality\":30,\"font_fallback\":null,\"font_substitutions\":{},\"image_max_dpi\":null,\"jbig2_tpgdon\":true,\"jpeg_max_quality\":90,\"jpeg_min_psnr\":null,\"jpeg_quality_step\":10,\"jpeg_size_mismatch\":\"warn\",\"line_art_max_dpi\":null,\"max_image_pixels\":268435456,\"merge_white_fills\":false,\"normalize_images_to\":null,\"output_colorspace\":\"rgb\",\"photo_max_dpi\":null,\"remove_hlines\":false,\"remove_vlines\":false,\"resample_filter\":\"triangle\",\"text_antialias\":true,\"use_system_fonts\":true,\"width_source\":\"pdf\"}"
         );
 
         // Verify keys are in alphabetical order by extracting them
@@ -107,14 +357,88 @@ mod tests {
             fg_dpi: 300,
             bg_quality: 80,
             fg_quality: 60,
-            color_mode: ColorMode::Rgb,
+            ..test_settings()
         };
 
         let json = settings_to_canonical_json(&settings);
 
         assert_eq!(
             json,
-            "{\"bg_quality\":80,\"color_mode\":\"rgb\",\"dpi\":600,\"fg_dpi\":300,\"fg_quality\":60}"
+            "{\"bg_quality\":80,\"color_mode\":\"rgb\",\"dpi\":600,\"fg_dpi\":300,\"fg_quality\":60,\"font_fallback\":null,\"font_substitutions\":{},\"image_max_dpi\":null,\"jbig2_tpgdon\":true,\"jpeg_max_quality\":90,\"jpeg_min_psnr\":null,\"jpeg_quality_step\":10,\"jpeg_size_mismatch\":\"warn\",\"line_art_max_dpi\":null,\"max_image_pixels\":268435456,\"merge_white_fills\":false,\"normalize_images_to\":null,\"output_colorspace\":\"rgb\",\"photo_max_dpi\":null,\"remove_hlines\":false,\"remove_vlines\":false,\"resample_filter\":\"triangle\",\"text_antialias\":true,\"use_system_fonts\":true,\"width_source\":\"pdf\"}"
         );
     }
+
+    #[test]
+    fn test_cache_key_changes_with_font_substitution() {
+        let path = Path::new("in.pdf");
+        let mut settings = test_settings();
+        settings
+            .font_substitutions
+            .insert("Arial".to_string(), PathBuf::from("/fonts/a.ttf"));
+        let base = compute_cache_key(b"q Q", &settings, path, 0);
+
+        settings
+            .font_substitutions
+            .insert("Arial".to_string(), PathBuf::from("/fonts/b.ttf"));
+        assert_ne!(base, compute_cache_key(b"q Q", &settings, path, 0));
+
+        settings.font_substitutions.clear();
+        settings.font_fallback = Some(PathBuf::from("/fonts/a.ttf"));
+        assert_ne!(base, compute_cache_key(b"q Q", &settings, path, 0));
+    }
+
+    #[test]
+    fn test_mask_settings_json_ignores_color_settings() {
+        let mut settings = test_settings();
+        let json = mask_settings_to_canonical_json(&settings);
+        assert_eq!(
+            json,
+            "{\"dpi\":300,\"jbig2_tpgdon\":true,\"remove_hlines\":false,\"remove_vlines\":false,\"text_antialias\":true}"
+        );
+
+        settings.color_mode = ColorMode::Grayscale;
+        settings.bg_quality = 80;
+        settings.output_colorspace = OutputColorSpace::Cmyk;
+        assert_eq!(mask_settings_to_canonical_json(&settings), json);
+    }
+
+    #[test]
+    fn test_source_key_covers_resources_and_writer_settings() {
+        let settings = CacheSettings {
+            fg_dpi: 300,
+            ..test_settings()
+        };
+        let mut writer_settings = WriterSettings {
+            foreground_mask: ForegroundMask::Smask,
+            interpolate_output: false,
+            tag_srgb: false,
+            text_region_padding: 0.0,
+            simplify_content: false,
+            split_content_bytes: None,
+            passthrough_full_page_images: false,
+            retry_empty_at_dpi: None,
+        };
+        let path = Path::new("in.pdf");
+        let key = |resources: &[u8], writer_settings: &WriterSettings| {
+            compute_source_key(b"q Q", resources, &settings, writer_settings, path, 0)
+        };
+
+        let base = key(b"image-a", &writer_settings);
+        assert_eq!(base, key(b"image-a", &writer_settings));
+        assert_ne!(base, compute_cache_key(b"q Q", &settings, path, 0));
+        assert_ne!(base, key(b"image-b", &writer_settings));
+        writer_settings.tag_srgb = true;
+        assert_ne!(base, key(b"image-a", &writer_settings));
+    }
+
+    #[test]
+    fn test_tag_cache_key_differs_per_tag() {
+        let key = "0".repeat(64);
+        let a = tag_cache_key(&key, "denoise-v1");
+        let b = tag_cache_key(&key, "denoise-v2");
+        assert_eq!(a.len(), 64);
+        assert_ne!(a, key);
+        assert_ne!(a, b);
+        assert_eq!(a, tag_cache_key(&key, "denoise-v1"));
+    }
 }
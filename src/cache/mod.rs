@@ -1,7 +1,9 @@
 pub mod hash;
 pub mod store;
 
-use crate::config::job::ColorMode;
+use crate::config::job::{
+    ColorMode, ImageColorSpace, JpegSizeMismatch, OutputColorSpace, ResampleFilter, WidthSource,
+};
 
 /// ColorMode を文字列に変換する。
 pub(crate) fn color_mode_to_str(mode: ColorMode) -> &'static str {
@@ -23,3 +25,55 @@ pub(crate) fn str_to_color_mode(s: &str) -> Option<ColorMode> {
         _ => None,
     }
 }
+
+/// OutputColorSpace を文字列に変換する。
+pub(crate) fn output_colorspace_to_str(space: OutputColorSpace) -> &'static str {
+    match space {
+        OutputColorSpace::Rgb => "rgb",
+        OutputColorSpace::Cmyk => "cmyk",
+    }
+}
+
+/// ImageColorSpace を文字列に変換する。
+pub(crate) fn image_colorspace_to_str(space: ImageColorSpace) -> &'static str {
+    match space {
+        ImageColorSpace::Rgb => "rgb",
+        ImageColorSpace::Gray => "gray",
+    }
+}
+
+/// ResampleFilter を文字列に変換する。
+pub(crate) fn resample_filter_to_str(filter: ResampleFilter) -> &'static str {
+    match filter {
+        ResampleFilter::Nearest => "nearest",
+        ResampleFilter::Triangle => "triangle",
+        ResampleFilter::Lanczos3 => "lanczos3",
+    }
+}
+
+/// JpegSizeMismatch を文字列に変換する。
+pub(crate) fn jpeg_size_mismatch_to_str(mode: JpegSizeMismatch) -> &'static str {
+    match mode {
+        JpegSizeMismatch::Warn => "warn",
+        JpegSizeMismatch::Error => "error",
+    }
+}
+
+/// WidthSource を文字列に変換する。
+pub(crate) fn width_source_to_str(source: WidthSource) -> &'static str {
+    match source {
+        WidthSource::Pdf => "pdf",
+        WidthSource::Font => "font",
+        WidthSource::Max => "max",
+    }
+}
+
+/// 文字列を OutputColorSpace に変換する。
+#[cfg(feature = "mrc")]
+pub(crate) fn str_to_output_colorspace(s: &str) -> Option<OutputColorSpace> {
+    match s {
+        "rgb" => Some(OutputColorSpace::Rgb),
+        "cmyk" => Some(OutputColorSpace::Cmyk),
+        _ => None,
+    }
+}
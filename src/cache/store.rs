@@ -4,6 +4,7 @@
 // MRC entries: mask.jbig2, foreground.jpg, background.jpg, metadata.json
 // BW entries: mask.jbig2, metadata.json
 // TextMasked entries: stripped_content.bin, region_*.jpg, modified_*.bin, metadata.json
+// Mask entries (color-mode independent): mask.jbig2, metadata.json
 
 use std::collections::HashMap;
 
@@ -14,12 +15,14 @@ use crate::error::PdfMaskError;
 #[cfg(feature = "mrc")]
 use crate::mrc::{BwLayers, MrcLayers};
 use crate::mrc::{ImageModification, PageOutput, TextMaskedData, TextRegionCrop};
-use crate::pdf::content_stream::BBox;
+use crate::pdf::content_stream::{BBox, Matrix};
 use serde_json;
 use std::fs;
 use std::path::{Path, PathBuf};
 
 use super::{color_mode_to_str, str_to_color_mode};
+#[cfg(feature = "mrc")]
+use super::{output_colorspace_to_str, str_to_output_colorspace};
 
 /// `std::io::Error` を `PdfMaskError::CacheError` に変換するための拡張トレイト。
 trait CacheResultExt<T> {
@@ -74,6 +77,9 @@ struct CacheMetadata {
     regions: Vec<TextRegionMeta>,
     #[serde(default)]
     modified_images: Vec<ModifiedImageMeta>,
+    /// MRCエントリの背景・前景JPEGの色空間（"rgb" / "cmyk"）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    output_colorspace: Option<String>,
 }
 
 /// テキスト領域のキャッシュメタデータ。
@@ -83,6 +89,9 @@ struct TextRegionMeta {
     pixel_width: u32,
     pixel_height: u32,
     file: String,
+    /// 回転したテキストの配置行列
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    placement: Option<Matrix>,
 }
 
 /// リダクション済み画像のキャッシュメタデータ。
@@ -92,7 +101,14 @@ struct ModifiedImageMeta {
     filter: String,
     color_space: String,
     bits_per_component: u8,
+    width: u32,
+    height: u32,
     file: String,
+    #[serde(default)]
+    redacted_regions: Vec<BBox>,
+    /// /SMaskとして出力するアルファのファイル名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    smask_file: Option<String>,
 }
 
 fn default_color_mode() -> String {
@@ -161,16 +177,20 @@ impl CacheStore {
             PageOutput::Mrc(_) => "mrc",
             PageOutput::BwMask(_) => "bw",
             PageOutput::TextMasked(_) => "text_masked",
+            PageOutput::Blank(_) => "blank",
+            PageOutput::Reused(_) => "reused",
         };
         #[cfg(not(feature = "mrc"))]
         let cache_type = match output {
             PageOutput::Skip(_) => "skip",
             PageOutput::TextMasked(_) => "text_masked",
+            PageOutput::Blank(_) => "blank",
+            PageOutput::Reused(_) => "reused",
         };
         debug!(cache_type, key_prefix = &key[..16], "cache store");
 
         match output {
-            PageOutput::Skip(_) => Ok(()),
+            PageOutput::Skip(_) | PageOutput::Blank(_) | PageOutput::Reused(_) => Ok(()),
             PageOutput::TextMasked(data) => {
                 let (w, h) = bitmap_dims.unwrap_or((0, 0));
                 self.store_text_masked(key, data, w, h)
@@ -183,7 +203,7 @@ impl CacheStore {
     /// MRC または BW の PageOutput をキャッシュに保存する。
     #[cfg(feature = "mrc")]
     fn store_mrc_or_bw(&self, key: &str, output: &PageOutput) -> crate::error::Result<()> {
-        let (mask_jbig2, fg, bg, width, height, page_width_pts, page_height_pts, mode) =
+        let (mask_jbig2, fg, bg, width, height, page_width_pts, page_height_pts, mode, space) =
             match output {
                 PageOutput::Mrc(layers) => (
                     &layers.mask_jbig2,
@@ -194,6 +214,7 @@ impl CacheStore {
                     layers.page_width_pts,
                     layers.page_height_pts,
                     layers.color_mode,
+                    Some(output_colorspace_to_str(layers.output_colorspace).to_string()),
                 ),
                 PageOutput::BwMask(layers) => (
                     &layers.mask_jbig2,
@@ -204,6 +225,7 @@ impl CacheStore {
                     layers.page_width_pts,
                     layers.page_height_pts,
                     ColorMode::Bw,
+                    None,
                 ),
                 _ => unreachable!(),
             };
@@ -240,6 +262,54 @@ impl CacheStore {
             page_index: 0,
             regions: vec![],
             modified_images: vec![],
+            output_colorspace: space,
+        };
+        let metadata_json = serde_json::to_string(&metadata)?;
+        fs::write(tmp_dir.join("metadata.json"), metadata_json.as_bytes()).cache_err()?;
+
+        if dir.exists() {
+            let _ = fs::remove_dir_all(&dir);
+        }
+
+        fs::rename(&tmp_dir, &dir).cache_err()?;
+
+        Ok(())
+    }
+
+    /// カラーモードに依存しないJBIG2マスクをキャッシュに保存する。
+    ///
+    /// `key` は [`compute_mask_cache_key`](super::hash::compute_mask_cache_key) で
+    /// 計算したマスク用キー。`width`/`height` はマスクのビットマップ寸法。
+    pub fn store_mask(
+        &self,
+        key: &str,
+        mask_jbig2: &[u8],
+        width: u32,
+        height: u32,
+    ) -> crate::error::Result<()> {
+        let dir = self.key_dir(key)?;
+        debug!(cache_type = "mask", key_prefix = &key[..16], "cache store");
+        let tmp_dir = dir.with_extension("tmp");
+
+        if tmp_dir.exists() {
+            let _ = fs::remove_dir_all(&tmp_dir);
+        }
+        fs::create_dir_all(&tmp_dir).cache_err()?;
+
+        fs::write(tmp_dir.join("mask.jbig2"), mask_jbig2).cache_err()?;
+
+        let metadata = CacheMetadata {
+            cache_key: key.to_string(),
+            cache_type: "mask".to_string(),
+            width,
+            height,
+            page_width_pts: 0.0,
+            page_height_pts: 0.0,
+            color_mode: default_color_mode(),
+            page_index: 0,
+            regions: vec![],
+            modified_images: vec![],
+            output_colorspace: None,
         };
         let metadata_json = serde_json::to_string(&metadata)?;
         fs::write(tmp_dir.join("metadata.json"), metadata_json.as_bytes()).cache_err()?;
@@ -253,6 +323,46 @@ impl CacheStore {
         Ok(())
     }
 
+    /// キャッシュからJBIG2マスクを取得する。キャッシュミスの場合は None を返す。
+    ///
+    /// マスクはカラーモードに依存しないため、カラーモードは検証しない。
+    /// キャッシュされたビットマップ寸法が `bitmap_dims` と異なればキャッシュミスとする。
+    pub fn retrieve_mask(
+        &self,
+        key: &str,
+        bitmap_dims: (u32, u32),
+    ) -> crate::error::Result<Option<Vec<u8>>> {
+        let dir = self.key_dir(key)?;
+        let metadata_path = dir.join("metadata.json");
+        if !metadata_path.exists() {
+            debug!(
+                key_prefix = &key[..16],
+                reason = "dir not found",
+                "mask cache miss"
+            );
+            return Ok(None);
+        }
+
+        let metadata_str = fs::read_to_string(metadata_path).cache_err()?;
+        let metadata: CacheMetadata = serde_json::from_str(&metadata_str)?;
+        if metadata.cache_key != key {
+            return Err(PdfMaskError::cache(format!(
+                "cache key mismatch: expected '{}', found '{}'",
+                key, metadata.cache_key
+            )));
+        }
+        if metadata.cache_type != "mask" || (metadata.width, metadata.height) != bitmap_dims {
+            debug!(
+                key_prefix = &key[..16],
+                reason = "metadata mismatch",
+                "mask cache miss"
+            );
+            return Ok(None);
+        }
+
+        Ok(Some(fs::read(dir.join("mask.jbig2")).cache_err()?))
+    }
+
     /// TextMaskedData をキャッシュに保存する。
     fn store_text_masked(
         &self,
@@ -286,6 +396,7 @@ impl CacheStore {
                 pixel_width: region.pixel_width,
                 pixel_height: region.pixel_height,
                 file: filename,
+                placement: region.placement.clone(),
             });
         }
 
@@ -295,12 +406,24 @@ impl CacheStore {
             let safe_name = sanitize_xobject_name(name);
             let filename = format!("modified_{}.bin", safe_name);
             fs::write(tmp_dir.join(&filename), &modification.data).cache_err()?;
+            let smask_file = match &modification.smask {
+                Some(smask) => {
+                    let smask_filename = format!("modified_{}.smask.bin", safe_name);
+                    fs::write(tmp_dir.join(&smask_filename), smask).cache_err()?;
+                    Some(smask_filename)
+                }
+                None => None,
+            };
             modified_metas.push(ModifiedImageMeta {
                 name: name.clone(),
                 filter: modification.filter.clone(),
                 color_space: modification.color_space.clone(),
                 bits_per_component: modification.bits_per_component,
+                width: modification.width,
+                height: modification.height,
                 file: filename,
+                redacted_regions: modification.redacted_regions.clone(),
+                smask_file,
             });
         }
 
@@ -315,6 +438,7 @@ impl CacheStore {
             page_index: data.page_index,
             regions: region_metas,
             modified_images: modified_metas,
+            output_colorspace: None,
         };
         let metadata_json = serde_json::to_string(&metadata)?;
         fs::write(tmp_dir.join("metadata.json"), metadata_json.as_bytes()).cache_err()?;
@@ -361,6 +485,10 @@ impl CacheStore {
         if metadata.cache_type == "text_masked" {
             return self.retrieve_text_masked(&dir, &metadata);
         }
+        // マスク単体のエントリはページ出力ではない
+        if metadata.cache_type == "mask" {
+            return Ok(None);
+        }
 
         #[cfg(feature = "mrc")]
         {
@@ -437,6 +565,11 @@ impl CacheStore {
                     page_width_pts: metadata.page_width_pts,
                     page_height_pts: metadata.page_height_pts,
                     color_mode: mode,
+                    output_colorspace: metadata
+                        .output_colorspace
+                        .as_deref()
+                        .and_then(str_to_output_colorspace)
+                        .unwrap_or_default(),
                 })))
             }
         }
@@ -458,12 +591,17 @@ impl CacheStore {
                 bbox_points: region_meta.bbox.clone(),
                 pixel_width: region_meta.pixel_width,
                 pixel_height: region_meta.pixel_height,
+                placement: region_meta.placement.clone(),
             });
         }
 
         let mut modified_images = HashMap::with_capacity(metadata.modified_images.len());
         for img_meta in &metadata.modified_images {
             let data = fs::read(dir.join(&img_meta.file)).cache_err()?;
+            let smask = match &img_meta.smask_file {
+                Some(file) => Some(fs::read(dir.join(file)).cache_err()?),
+                None => None,
+            };
             modified_images.insert(
                 img_meta.name.clone(),
                 ImageModification {
@@ -471,6 +609,10 @@ impl CacheStore {
                     filter: img_meta.filter.clone(),
                     color_space: img_meta.color_space.clone(),
                     bits_per_component: img_meta.bits_per_component,
+                    width: img_meta.width,
+                    height: img_meta.height,
+                    redacted_regions: img_meta.redacted_regions.clone(),
+                    smask,
                 },
             );
         }
@@ -521,12 +663,20 @@ impl CacheStore {
                 }
             }
             for img in &metadata.modified_images {
-                if !dir.join(&img.file).exists() {
+                if !dir.join(&img.file).exists()
+                    || img
+                        .smask_file
+                        .as_ref()
+                        .is_some_and(|file| !dir.join(file).exists())
+                {
                     return false;
                 }
             }
             return true;
         }
+        if metadata.cache_type == "mask" {
+            return dir.join("mask.jbig2").exists();
+        }
 
         #[cfg(feature = "mrc")]
         {
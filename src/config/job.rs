@@ -1,6 +1,6 @@
-use serde::Deserialize;
 use serde::de::{self, SeqAccess, Visitor};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct JobFile {
@@ -8,7 +8,7 @@ pub struct JobFile {
 }
 
 /// カラーモード: MRC処理の種類を指定
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ColorMode {
     Rgb,
@@ -17,6 +17,137 @@ pub enum ColorMode {
     Skip,
 }
 
+/// 白紙ページ（両面スキャンの裏面等）を検出したときの扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlankPageAction {
+    /// 通常のページと同様に処理する（検出しない）
+    #[default]
+    Keep,
+    /// 処理を省略し、同じサイズの空ページに置き換える
+    Skip,
+    /// 出力から取り除く（ページ数が減る）
+    Drop,
+}
+
+/// MRC背景・前景JPEGの出力色空間
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputColorSpace {
+    /// DeviceRGB（デフォルト）
+    #[default]
+    Rgb,
+    /// DeviceCMYK（印刷ワークフロー向け。RGBモードのページにのみ適用）
+    Cmyk,
+}
+
+/// 出力する画像XObjectをそろえる色空間
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageColorSpace {
+    /// DeviceRGB
+    Rgb,
+    /// DeviceGray（カラー画像は輝度に変換する）
+    Gray,
+}
+
+/// 画像を縮小するときのリサンプリングフィルタ
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResampleFilter {
+    /// 最近傍（輪郭がシャープ。文字の多い画像向け）
+    Nearest,
+    /// 線形補間（デフォルト）
+    #[default]
+    Triangle,
+    /// Lanczos（窓3。なめらかだが低速）
+    Lanczos3,
+}
+
+/// テキストのアウトライン化で字送りに使うグリフ幅の取得元
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WidthSource {
+    /// PDFの/Widths（仕様どおり。デフォルト）
+    #[default]
+    Pdf,
+    /// フォントのhorizontal advance（/Widthsが壊れたPDF向け）
+    Font,
+    /// /Widthsとフォントのadvanceの大きい方
+    Max,
+}
+
+/// JPEG画像の実際の寸法が画像辞書の/Width・/Heightと食い違うときの扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JpegSizeMismatch {
+    /// 警告を出し、JPEGの実際の寸法を使う（デフォルト）
+    #[default]
+    Warn,
+    /// 画像のデコードをエラーにする
+    Error,
+}
+
+/// MRCページで前景レイヤーにマスクを適用する方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ForegroundMask {
+    /// マスクを前景画像の/SMask（ソフトマスク）にする（デフォルト）
+    #[default]
+    Smask,
+    /// マスクをImageMaskのステンシルとして前景画像の/Maskにする（明示的マスキング）
+    Stencil,
+}
+
+/// 元PDFの文書情報（/Info）とXMPメタデータ（Catalogの/Metadata）の扱い
+///
+/// `reproducible`との優先順位: `preserve`でも`reproducible`が有効なら
+/// 実行ごとに変わりうる日付（CreationDate/ModDate）とXMPは出力しない。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetadataMode {
+    /// /InfoとXMPをそのまま引き継ぐ
+    Preserve,
+    /// 何も引き継がない（デフォルト）
+    #[default]
+    Strip,
+    /// /InfoのTitleとAuthorのみ引き継ぎ、日付とXMPは出力しない
+    Minimal,
+}
+
+/// `overlay_redactions`の矩形の座標系
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverlayCoordinates {
+    /// 入力ページのページ空間（/Rotateを適用する前の向き、デフォルト）
+    #[default]
+    Page,
+    /// ビューアで見た向き（/Rotateを適用した後）。回転したページで見た目どおりに矩形を置いたオーバーレイ向け
+    Visual,
+}
+
+/// `mark_redactions`の矩形の塗り方
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactionFill {
+    /// 単色で塗りつぶす（デフォルト）
+    #[default]
+    Solid,
+    /// 白地に斜線のタイリングパターン
+    Hatch,
+    /// 白地に"REDACTED"の文字を並べたタイリングパターン
+    Text,
+}
+
+/// ラスタライズせず、元のベクター・テキストのまま残すページ上の矩形
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct PreserveRegion {
+    /// ページ番号(1-based)
+    pub page: u32,
+    /// 入力ページのページ空間の矩形`[x_min, y_min, x_max, y_max]`(pt)
+    pub rect: [f64; 4],
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct Job {
     pub input: String,
@@ -30,11 +161,68 @@ pub struct Job {
     pub rgb_pages: Option<Vec<u32>>,
     #[serde(default, deserialize_with = "deserialize_optional_pages")]
     pub skip_pages: Option<Vec<u32>>,
+    pub preserve_images: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_optional_pages")]
+    pub preserve_images_pages: Option<Vec<u32>>,
+    #[serde(default, deserialize_with = "deserialize_optional_pages")]
+    pub no_preserve_images_pages: Option<Vec<u32>>,
+    pub text_to_outlines: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_optional_pages")]
+    pub text_to_outlines_pages: Option<Vec<u32>>,
+    #[serde(default, deserialize_with = "deserialize_optional_pages")]
+    pub no_text_to_outlines_pages: Option<Vec<u32>>,
     pub dpi: Option<u32>,
     pub fg_dpi: Option<u32>,
+    #[serde(default, deserialize_with = "deserialize_optional_quality")]
     pub bg_quality: Option<u8>,
+    #[serde(default, deserialize_with = "deserialize_optional_quality")]
     pub fg_quality: Option<u8>,
     pub linearize: Option<bool>,
+    pub image_max_dpi: Option<u32>,
+    pub photo_max_dpi: Option<u32>,
+    pub line_art_max_dpi: Option<u32>,
+    pub normalize_images_to: Option<ImageColorSpace>,
+    pub resample_filter: Option<ResampleFilter>,
+    pub blank_page_action: Option<BlankPageAction>,
+    pub output_colorspace: Option<OutputColorSpace>,
+    pub metadata: Option<MetadataMode>,
+    pub reproducible: Option<bool>,
+    pub fix_mirror: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_optional_pages")]
+    pub mirror_pages: Option<Vec<u32>>,
+    pub retry_empty_at_dpi: Option<u32>,
+    pub text_antialias: Option<bool>,
+    pub overlay_redactions: Option<String>,
+    pub overlay_coordinates: Option<OverlayCoordinates>,
+    pub preserve_regions: Option<Vec<PreserveRegion>>,
+    pub mark_redactions: Option<bool>,
+    pub redaction_mark_color: Option<[u8; 3]>,
+    pub redaction_mark_fill: Option<RedactionFill>,
+    pub redaction_manifest: Option<String>,
+    pub minimize_blank: Option<bool>,
+    pub flatten_forms: Option<bool>,
+    pub passthrough_full_page_images: Option<bool>,
+    pub optimize_skip_images: Option<bool>,
+    pub share_copied_objects: Option<bool>,
+    pub pdfa_output_intent: Option<bool>,
+    pub remove_hlines: Option<bool>,
+    pub remove_vlines: Option<bool>,
+    pub text_region_padding: Option<f64>,
+    pub foreground_mask: Option<ForegroundMask>,
+    pub interpolate_output: Option<bool>,
+    pub tag_srgb: Option<bool>,
+    pub simplify_content: Option<bool>,
+    pub split_content_bytes: Option<u64>,
+    pub verify_redaction: Option<bool>,
+    pub lang: Option<String>,
+    pub preserve_view: Option<bool>,
+    pub producer: Option<String>,
+    pub font_substitutions: Option<BTreeMap<String, String>>,
+    pub font_fallback: Option<String>,
+    pub use_system_fonts: Option<bool>,
+    pub width_source: Option<WidthSource>,
+    pub merge_white_fills: Option<bool>,
+    pub record_source_keys: Option<bool>,
 }
 
 /// ページ範囲文字列をパースしてページ番号のベクタに変換する。
@@ -207,6 +395,7 @@ impl Job {
     /// ページ→カラーモードのオーバーライドマップを構築する。
     ///
     /// - bw_pages, grayscale_pages, rgb_pages, skip_pages からオーバーライドを収集
+    ///   （`"50-200"` のような範囲指定はデシリアライズ時に個別ページへ展開済み）
     /// - 同一ページが複数リストに含まれる場合はエラー
     /// - リストに含まれないページは HashMap に含まれない
     ///   （= デフォルトカラーモードは呼び出し側で決定・適用する）
@@ -215,29 +404,218 @@ impl Job {
     /// ページ番号(1-based) → ColorMode のマップ。
     /// マップに含まれないページには、呼び出し側で用意したデフォルトモードを使うこと。
     pub fn resolve_page_modes(&self) -> crate::error::Result<HashMap<u32, ColorMode>> {
-        let mut page_to_mode: HashMap<u32, ColorMode> = HashMap::new();
+        // ページ番号 → (指定元リスト名, カラーモード)
+        let mut assigned: HashMap<u32, (&str, ColorMode)> = HashMap::new();
 
-        // 各 *_pages リストを処理
+        // 各 *_pages リストを処理（エラーメッセージ用にフィールド名も保持）
         let lists = [
-            (ColorMode::Bw, self.bw_pages.as_deref()),
-            (ColorMode::Grayscale, self.grayscale_pages.as_deref()),
-            (ColorMode::Rgb, self.rgb_pages.as_deref()),
-            (ColorMode::Skip, self.skip_pages.as_deref()),
+            ("bw_pages", ColorMode::Bw, self.bw_pages.as_deref()),
+            (
+                "grayscale_pages",
+                ColorMode::Grayscale,
+                self.grayscale_pages.as_deref(),
+            ),
+            ("rgb_pages", ColorMode::Rgb, self.rgb_pages.as_deref()),
+            ("skip_pages", ColorMode::Skip, self.skip_pages.as_deref()),
         ];
 
-        for (mode, pages_opt) in lists {
+        for (list_name, mode, pages_opt) in lists {
             if let Some(pages) = pages_opt {
                 for &page in pages {
-                    if let Some(existing_mode) = page_to_mode.insert(page, mode) {
+                    // 同一リスト内の重複は矛盾ではないので許容する
+                    if let Some((existing_list, existing_mode)) =
+                        assigned.insert(page, (list_name, mode))
+                        && existing_list != list_name
+                    {
                         return Err(crate::error::PdfMaskError::config(format!(
-                            "Page {} specified in multiple mode lists: {:?} and {:?}",
-                            page, existing_mode, mode
+                            "Page {} specified in multiple mode lists: {} ({:?}) and {} ({:?})",
+                            page, existing_list, existing_mode, list_name, mode
                         )));
                     }
                 }
             }
         }
 
-        Ok(page_to_mode)
+        Ok(assigned
+            .into_iter()
+            .map(|(page, (_, mode))| (page, mode))
+            .collect())
+    }
+
+    /// ページ→preserve_imagesのオーバーライドマップを構築する。
+    ///
+    /// preserve_images_pagesのページはtrue、no_preserve_images_pagesのページはfalse。
+    /// 両方に含まれるページはエラー。含まれないページにはジョブ全体の値を使うこと。
+    pub fn resolve_preserve_images_pages(&self) -> crate::error::Result<HashMap<u32, bool>> {
+        resolve_page_flags(
+            (
+                "preserve_images_pages",
+                self.preserve_images_pages.as_deref(),
+            ),
+            (
+                "no_preserve_images_pages",
+                self.no_preserve_images_pages.as_deref(),
+            ),
+        )
+    }
+
+    /// ページ→text_to_outlinesのオーバーライドマップを構築する。
+    ///
+    /// text_to_outlines_pagesのページはtrue、no_text_to_outlines_pagesのページはfalse。
+    /// 両方に含まれるページはエラー。含まれないページにはジョブ全体の値を使うこと。
+    pub fn resolve_text_to_outlines_pages(&self) -> crate::error::Result<HashMap<u32, bool>> {
+        resolve_page_flags(
+            (
+                "text_to_outlines_pages",
+                self.text_to_outlines_pages.as_deref(),
+            ),
+            (
+                "no_text_to_outlines_pages",
+                self.no_text_to_outlines_pages.as_deref(),
+            ),
+        )
+    }
+}
+
+/// オン・オフのページリストからページ→真偽値のマップを構築する。
+/// 同じページが両方のリストにあればエラー。
+fn resolve_page_flags(
+    (on_name, on_pages): (&str, Option<&[u32]>),
+    (off_name, off_pages): (&str, Option<&[u32]>),
+) -> crate::error::Result<HashMap<u32, bool>> {
+    let mut flags: HashMap<u32, bool> = on_pages
+        .unwrap_or_default()
+        .iter()
+        .map(|&page| (page, true))
+        .collect();
+    for &page in off_pages.unwrap_or_default() {
+        if flags.insert(page, false) == Some(true) {
+            return Err(crate::error::PdfMaskError::config(format!(
+                "Page {} specified in both {} and {}",
+                page, on_name, off_name
+            )));
+        }
+    }
+    Ok(flags)
+}
+
+/// JPEG品質のプリセット名と対応する品質値
+pub const QUALITY_PRESETS: [(&str, u8); 4] =
+    [("low", 30), ("medium", 50), ("high", 75), ("archival", 95)];
+
+/// serdeのdeserialize_withで使用するJPEG品質(1〜100)のデシリアライザ。
+///
+/// 以下の形式を受け付ける:
+/// - 整数: `bg_quality: 50`
+/// - 小数（四捨五入）: `bg_quality: 72.5`
+/// - プリセット名: `bg_quality: high`（[`QUALITY_PRESETS`]）
+pub(crate) fn deserialize_quality<'de, D>(deserializer: D) -> Result<u8, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    struct QualityVisitor;
+
+    impl<'de> Visitor<'de> for QualityVisitor {
+        type Value = u8;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str(
+                "a JPEG quality 1-100 or a preset (\"low\", \"medium\", \"high\", \"archival\")",
+            )
+        }
+
+        fn visit_u64<E>(self, value: u64) -> Result<u8, E>
+        where
+            E: de::Error,
+        {
+            match u8::try_from(value) {
+                Ok(quality) if (1..=100).contains(&quality) => Ok(quality),
+                _ => Err(de::Error::custom(format!(
+                    "JPEG quality must be 1-100, got {value}"
+                ))),
+            }
+        }
+
+        fn visit_i64<E>(self, value: i64) -> Result<u8, E>
+        where
+            E: de::Error,
+        {
+            match u64::try_from(value) {
+                Ok(value) => self.visit_u64(value),
+                Err(_) => Err(de::Error::custom(format!(
+                    "JPEG quality must be 1-100, got {value}"
+                ))),
+            }
+        }
+
+        fn visit_f64<E>(self, value: f64) -> Result<u8, E>
+        where
+            E: de::Error,
+        {
+            let rounded = value.round();
+            if !(1.0..=100.0).contains(&rounded) {
+                return Err(de::Error::custom(format!(
+                    "JPEG quality must be 1-100, got {value}"
+                )));
+            }
+            Ok(rounded as u8)
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<u8, E>
+        where
+            E: de::Error,
+        {
+            let name = value.trim().to_ascii_lowercase();
+            QUALITY_PRESETS
+                .iter()
+                .find(|(preset, _)| *preset == name)
+                .map(|&(_, quality)| quality)
+                .ok_or_else(|| {
+                    de::Error::custom(format!(
+                        "unknown JPEG quality preset '{value}' (expected low, medium, high or archival)"
+                    ))
+                })
+        }
+    }
+
+    deserializer.deserialize_any(QualityVisitor)
+}
+
+/// Option<u8>のJPEG品質用のデシリアライザ（Noneを許容）
+fn deserialize_optional_quality<'de, D>(deserializer: D) -> Result<Option<u8>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    struct OptionalQualityVisitor;
+
+    impl<'de> Visitor<'de> for OptionalQualityVisitor {
+        type Value = Option<u8>;
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            formatter.write_str("an optional JPEG quality or preset")
+        }
+
+        fn visit_none<E>(self) -> Result<Option<u8>, E>
+        where
+            E: de::Error,
+        {
+            Ok(None)
+        }
+
+        fn visit_some<D>(self, deserializer: D) -> Result<Option<u8>, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            deserialize_quality(deserializer).map(Some)
+        }
+
+        fn visit_unit<E>(self) -> Result<Option<u8>, E>
+        where
+            E: de::Error,
+        {
+            Ok(None)
+        }
     }
+
+    deserializer.deserialize_option(OptionalQualityVisitor)
 }
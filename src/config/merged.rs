@@ -1,9 +1,18 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
-use super::job::{ColorMode, Job};
-use super::settings::Settings;
+use serde::Serialize;
 
-#[derive(Debug, Clone)]
+use super::job::{
+    BlankPageAction, ColorMode, ForegroundMask, ImageColorSpace, Job, JpegSizeMismatch,
+    MetadataMode, OutputColorSpace, ResampleFilter, WidthSource,
+};
+use super::settings::{ImageClassDpi, Jbig2Options, JpegEscalation, LineRemoval, Settings};
+
+/// settings.yamlとジョブの値をマージした、1ジョブ分の最終的な設定。
+///
+/// `--print-config`でYAMLとして出力できるよう`Serialize`を実装する。
+#[derive(Debug, Clone, Serialize)]
 pub struct MergedConfig {
     pub color_mode: ColorMode,
     pub dpi: u32,
@@ -13,6 +22,50 @@ pub struct MergedConfig {
     pub parallel_workers: usize,
     pub cache_dir: PathBuf,
     pub linearize: bool,
+    pub jbig2: Jbig2Options,
+    pub jpeg_escalation: JpegEscalation,
+    pub image_max_dpi: Option<u32>,
+    #[serde(flatten)]
+    pub image_class_dpi: ImageClassDpi,
+    pub normalize_images_to: Option<ImageColorSpace>,
+    pub resample_filter: ResampleFilter,
+    pub max_image_pixels: u64,
+    pub jpeg_size_mismatch: JpegSizeMismatch,
+    pub max_content_stream_bytes: u64,
+    pub max_output_pages: Option<u32>,
+    pub blank_page_action: BlankPageAction,
+    pub output_colorspace: OutputColorSpace,
+    pub metadata: MetadataMode,
+    pub reproducible: bool,
+    pub fix_mirror: bool,
+    pub retry_empty_at_dpi: Option<u32>,
+    pub text_antialias: bool,
+    pub minimize_blank: bool,
+    pub flatten_forms: bool,
+    pub preserve_images: bool,
+    pub text_to_outlines: bool,
+    pub passthrough_full_page_images: bool,
+    pub optimize_skip_images: bool,
+    pub share_copied_objects: bool,
+    pub pdfa_output_intent: bool,
+    #[serde(flatten)]
+    pub line_removal: LineRemoval,
+    pub text_region_padding: f64,
+    pub foreground_mask: ForegroundMask,
+    pub interpolate_output: bool,
+    pub tag_srgb: bool,
+    pub simplify_content: bool,
+    pub split_content_bytes: Option<u64>,
+    pub verify_redaction: bool,
+    pub lang: Option<String>,
+    pub preserve_view: bool,
+    pub producer: Option<String>,
+    pub font_substitutions: BTreeMap<String, String>,
+    pub font_fallback: Option<String>,
+    pub use_system_fonts: bool,
+    pub width_source: WidthSource,
+    pub merge_white_fills: bool,
+    pub record_source_keys: bool,
 }
 
 impl MergedConfig {
@@ -27,6 +80,77 @@ impl MergedConfig {
             parallel_workers: settings.parallel_workers,
             cache_dir: settings.cache_dir.clone(),
             linearize: job.linearize.unwrap_or(settings.linearize),
+            jbig2: settings.jbig2,
+            jpeg_escalation: settings.jpeg_escalation,
+            image_max_dpi: job.image_max_dpi.or(settings.image_max_dpi),
+            image_class_dpi: ImageClassDpi {
+                photo: job.photo_max_dpi.or(settings.photo_max_dpi),
+                line_art: job.line_art_max_dpi.or(settings.line_art_max_dpi),
+            },
+            normalize_images_to: job.normalize_images_to.or(settings.normalize_images_to),
+            resample_filter: job.resample_filter.unwrap_or(settings.resample_filter),
+            max_image_pixels: settings.max_image_pixels,
+            jpeg_size_mismatch: settings.jpeg_size_mismatch,
+            max_content_stream_bytes: settings.max_content_stream_bytes,
+            max_output_pages: settings.max_output_pages,
+            blank_page_action: job.blank_page_action.unwrap_or(settings.blank_page_action),
+            output_colorspace: job.output_colorspace.unwrap_or(settings.output_colorspace),
+            metadata: job.metadata.unwrap_or(settings.metadata),
+            reproducible: job.reproducible.unwrap_or(settings.reproducible),
+            fix_mirror: job.fix_mirror.unwrap_or(settings.fix_mirror),
+            retry_empty_at_dpi: job.retry_empty_at_dpi.or(settings.retry_empty_at_dpi),
+            text_antialias: job.text_antialias.unwrap_or(settings.text_antialias),
+            minimize_blank: job.minimize_blank.unwrap_or(settings.minimize_blank),
+            flatten_forms: job.flatten_forms.unwrap_or(settings.flatten_forms),
+            preserve_images: job.preserve_images.unwrap_or(settings.preserve_images),
+            text_to_outlines: job.text_to_outlines.unwrap_or(settings.text_to_outlines),
+            passthrough_full_page_images: job
+                .passthrough_full_page_images
+                .unwrap_or(settings.passthrough_full_page_images),
+            optimize_skip_images: job
+                .optimize_skip_images
+                .unwrap_or(settings.optimize_skip_images),
+            share_copied_objects: job
+                .share_copied_objects
+                .unwrap_or(settings.share_copied_objects),
+            pdfa_output_intent: job
+                .pdfa_output_intent
+                .unwrap_or(settings.pdfa_output_intent),
+            line_removal: LineRemoval {
+                horizontal: job.remove_hlines.unwrap_or(settings.remove_hlines),
+                vertical: job.remove_vlines.unwrap_or(settings.remove_vlines),
+            },
+            text_region_padding: job
+                .text_region_padding
+                .unwrap_or(settings.text_region_padding),
+            foreground_mask: job.foreground_mask.unwrap_or(settings.foreground_mask),
+            interpolate_output: job
+                .interpolate_output
+                .unwrap_or(settings.interpolate_output),
+            tag_srgb: job.tag_srgb.unwrap_or(settings.tag_srgb),
+            simplify_content: job.simplify_content.unwrap_or(settings.simplify_content),
+            split_content_bytes: job.split_content_bytes.or(settings.split_content_bytes),
+            verify_redaction: job.verify_redaction.unwrap_or(settings.verify_redaction),
+            lang: job.lang.clone().or_else(|| settings.lang.clone()),
+            preserve_view: job.preserve_view.unwrap_or(settings.preserve_view),
+            producer: job.producer.clone().or_else(|| settings.producer.clone()),
+            // ジョブの指定はsettingsの指定にフォント名単位で上書き・追加する
+            font_substitutions: settings
+                .font_substitutions
+                .iter()
+                .chain(job.font_substitutions.iter().flatten())
+                .map(|(name, path)| (name.clone(), path.clone()))
+                .collect(),
+            font_fallback: job
+                .font_fallback
+                .clone()
+                .or_else(|| settings.font_fallback.clone()),
+            use_system_fonts: job.use_system_fonts.unwrap_or(settings.use_system_fonts),
+            width_source: job.width_source.unwrap_or(settings.width_source),
+            merge_white_fills: job.merge_white_fills.unwrap_or(settings.merge_white_fills),
+            record_source_keys: job
+                .record_source_keys
+                .unwrap_or(settings.record_source_keys),
         }
     }
 }
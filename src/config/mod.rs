@@ -1,24 +1,91 @@
 pub mod job;
 pub mod merged;
+pub mod output_template;
+pub mod overrides;
 pub mod settings;
 
 use settings::Settings;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-/// ジョブファイルのパスからsettings.yamlを自動検出して読み込む。
+/// ジョブファイルのパスから設定ファイルを自動検出して読み込む。
 ///
-/// ジョブファイルと同じディレクトリに `settings.yaml` が存在すれば読み込み、
-/// 存在しなければデフォルト設定を返す。
+/// グローバル設定（[`global_settings_path`]）とジョブファイルと同じディレクトリの
+/// `settings.yaml` を [`load_layered_settings`] で重ね合わせる。
+/// どちらも存在しなければデフォルト設定を返す。
 pub fn load_settings_for_job(job_file_path: &Path) -> crate::error::Result<Settings> {
+    load_layered_settings(global_settings_path().as_deref(), job_file_path)
+}
+
+/// マシン全体の設定ファイルのパスを返す。
+///
+/// `$XDG_CONFIG_HOME/pdf_masking/settings.yaml`。XDG_CONFIG_HOMEが未設定
+/// （または空）の場合は `$HOME/.config` を使う。どちらも無ければ `None`。
+pub fn global_settings_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .filter(|v| !v.is_empty())
+                .map(|home| PathBuf::from(home).join(".config"))
+        })?;
+    Some(config_home.join("pdf_masking").join("settings.yaml"))
+}
+
+/// 組み込みデフォルト ← グローバル設定 ← ジョブディレクトリの`settings.yaml`
+/// の順に設定を重ね合わせて読み込む。
+///
+/// 後の層に書かれたキーだけが前の層を上書きする（`jbig2`等のネストした
+/// マッピングもキー単位でマージする）。存在しないファイルは無視する。
+/// ジョブ単位のフィールドによる上書きは [`merged::MergedConfig`] が行う。
+pub fn load_layered_settings(
+    global_path: Option<&Path>,
+    job_file_path: &Path,
+) -> crate::error::Result<Settings> {
     let dir = job_file_path
         .parent()
         .ok_or_else(|| crate::error::PdfMaskError::config("Cannot determine job file directory"))?;
 
-    let settings_path = dir.join("settings.yaml");
+    let job_dir_path = dir.join("settings.yaml");
+    let layers = global_path
+        .into_iter()
+        .chain(std::iter::once(job_dir_path.as_path()))
+        .filter(|path| path.exists());
+
+    let mut merged = serde_yml::Value::Mapping(serde_yml::Mapping::new());
+    for path in layers {
+        let content = std::fs::read_to_string(path)?;
+        let layer: serde_yml::Value = serde_yml::from_str(&content).map_err(|e| {
+            crate::error::PdfMaskError::config(format!(
+                "Failed to parse settings YAML {}: {e}",
+                path.display()
+            ))
+        })?;
+        merge_yaml(&mut merged, layer);
+    }
+
+    serde_yml::from_value(merged).map_err(|e| {
+        crate::error::PdfMaskError::config(format!("Failed to parse settings YAML: {e}"))
+    })
+}
 
-    if settings_path.exists() {
-        Settings::from_file(&settings_path)
-    } else {
-        Ok(Settings::default())
+/// `overlay`のキーで`base`を上書きする。両方がマッピングの場合は再帰的にマージする。
+fn merge_yaml(base: &mut serde_yml::Value, overlay: serde_yml::Value) {
+    match (base, overlay) {
+        // 空ファイルは何も上書きしない
+        (_, serde_yml::Value::Null) => {}
+        (serde_yml::Value::Mapping(base_map), serde_yml::Value::Mapping(overlay_map)) => {
+            for (key, value) in overlay_map {
+                match base_map.get_mut(&key) {
+                    Some(existing) if existing.is_mapping() && value.is_mapping() => {
+                        merge_yaml(existing, value);
+                    }
+                    _ => {
+                        base_map.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
     }
 }
@@ -0,0 +1,122 @@
+// 出力パスのテンプレート: `{stem}`・`{date}`・`{index}`をジョブごとに展開する
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::PdfMaskError;
+
+/// テンプレートの展開に使うジョブごとの値
+#[derive(Debug, Clone)]
+pub struct OutputTemplateValues<'a> {
+    /// 入力ファイル名から拡張子を除いたもの
+    pub stem: &'a str,
+    /// 実行日（`YYYY-MM-DD`）
+    pub date: &'a str,
+    /// 呼び出し内でのジョブの通し番号(1-based)
+    pub index: usize,
+}
+
+/// 出力パスのプレースホルダを展開する。
+///
+/// `{stem}`・`{date}`・`{index}`に対応し、`{index:03}`のように幅を指定すると
+/// 0埋めする。`{{`・`}}`はそれぞれ`{`・`}`になる。1ジョブは常に1ファイルを
+/// 出力するため、ページごとに展開する`{page}`は使えない。
+pub fn expand_output_template(
+    template: &str,
+    values: &OutputTemplateValues,
+) -> crate::error::Result<String> {
+    let mut expanded = String::with_capacity(template.len());
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let rest = chars.as_str();
+                if let Some(after) = rest.strip_prefix('{') {
+                    expanded.push('{');
+                    chars = after.chars();
+                    continue;
+                }
+                let end = rest.find('}').ok_or_else(|| {
+                    PdfMaskError::config(format!("unclosed '{{' in output path: {template}"))
+                })?;
+                expanded.push_str(&expand_placeholder(&rest[..end], values, template)?);
+                chars = rest[end + 1..].chars();
+            }
+            '}' => {
+                let rest = chars.as_str();
+                let after = rest.strip_prefix('}').ok_or_else(|| {
+                    PdfMaskError::config(format!("unmatched '}}' in output path: {template}"))
+                })?;
+                expanded.push('}');
+                chars = after.chars();
+            }
+            _ => expanded.push(c),
+        }
+    }
+    Ok(expanded)
+}
+
+/// `{name}`または`{name:width}`の中身を展開する。
+fn expand_placeholder(
+    placeholder: &str,
+    values: &OutputTemplateValues,
+    template: &str,
+) -> crate::error::Result<String> {
+    let (name, spec) = match placeholder.split_once(':') {
+        Some((name, spec)) => (name, Some(spec)),
+        None => (placeholder, None),
+    };
+    let text = match name {
+        "stem" => values.stem.to_string(),
+        "date" => values.date.to_string(),
+        "index" => values.index.to_string(),
+        "page" => {
+            return Err(PdfMaskError::config(format!(
+                "'{{page}}' in output path requires split output, but each job writes a \
+                 single file: {template}"
+            )));
+        }
+        _ => {
+            return Err(PdfMaskError::config(format!(
+                "unknown placeholder '{{{placeholder}}}' in output path: {template}"
+            )));
+        }
+    };
+    let Some(spec) = spec else {
+        return Ok(text);
+    };
+    let width = match spec.parse::<usize>() {
+        Ok(width) if name == "index" => width,
+        _ => {
+            return Err(PdfMaskError::config(format!(
+                "invalid format '{{{placeholder}}}' in output path (only {{index}} takes a \
+                 width, e.g. {{index:03}}): {template}"
+            )));
+        }
+    };
+    Ok(format!("{text:0>width$}"))
+}
+
+/// 今日の日付（UTC）を`YYYY-MM-DD`で返す。
+pub fn today_utc() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() / 86_400)
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// 1970-01-01からの日数をグレゴリオ暦の年月日に変換する（Howard Hinnantのアルゴリズム）。
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
@@ -0,0 +1,152 @@
+// CLI引数・環境変数による設定の一時的な上書き
+
+use super::job::{ColorMode, QUALITY_PRESETS};
+use super::merged::MergedConfig;
+use crate::error::PdfMaskError;
+
+/// 呼び出し全体の全ジョブに適用する設定の上書き値。
+///
+/// 優先順位は CLI > 環境変数 > settings.yaml > 組み込みデフォルト。
+/// ジョブ単位のフィールドよりも優先される。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigOverrides {
+    pub dpi: Option<u32>,
+    pub bg_quality: Option<u8>,
+    pub fg_quality: Option<u8>,
+    pub color_mode: Option<ColorMode>,
+}
+
+/// 上書き可能な項目: (CLIフラグ, 環境変数名)
+const OVERRIDE_KEYS: [(&str, &str); 4] = [
+    ("--dpi", "PDF_MASKING_DPI"),
+    ("--bg-quality", "PDF_MASKING_BG_QUALITY"),
+    ("--fg-quality", "PDF_MASKING_FG_QUALITY"),
+    ("--color-mode", "PDF_MASKING_COLOR_MODE"),
+];
+
+impl ConfigOverrides {
+    /// CLI引数から上書きフラグを取り出す。
+    ///
+    /// `--dpi 150` と `--dpi=150` の両形式を受け付ける。
+    /// 返り値の2要素目はフラグ以外の引数（ジョブファイルのパス）。
+    pub fn from_args(args: &[String]) -> crate::error::Result<(Self, Vec<String>)> {
+        let mut overrides = ConfigOverrides::default();
+        let mut rest = Vec::new();
+        let mut iter = args.iter();
+
+        while let Some(arg) = iter.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+                _ => (arg.as_str(), None),
+            };
+            if !OVERRIDE_KEYS.iter().any(|(known, _)| *known == flag) {
+                rest.push(arg.clone());
+                continue;
+            }
+
+            let value = match inline_value {
+                Some(value) => value,
+                None => iter
+                    .next()
+                    .cloned()
+                    .ok_or_else(|| PdfMaskError::config(format!("{flag} requires a value")))?,
+            };
+            overrides.set(flag, &value)?;
+        }
+
+        Ok((overrides, rest))
+    }
+
+    /// `PDF_MASKING_*` 環境変数から上書き値を読み込む。
+    pub fn from_env() -> crate::error::Result<Self> {
+        Self::from_env_with(|name| std::env::var(name).ok())
+    }
+
+    /// 任意の変数ルックアップ関数から上書き値を読み込む（テスト用に分離）。
+    pub fn from_env_with(lookup: impl Fn(&str) -> Option<String>) -> crate::error::Result<Self> {
+        let mut overrides = ConfigOverrides::default();
+        for (flag, var) in OVERRIDE_KEYS {
+            if let Some(value) = lookup(var).filter(|v| !v.trim().is_empty()) {
+                overrides
+                    .set(flag, &value)
+                    .map_err(|e| PdfMaskError::config(format!("{var}: {e}")))?;
+            }
+        }
+        Ok(overrides)
+    }
+
+    /// `self` を優先し、未指定の項目を `fallback` で補う。
+    pub fn or(self, fallback: ConfigOverrides) -> ConfigOverrides {
+        ConfigOverrides {
+            dpi: self.dpi.or(fallback.dpi),
+            bg_quality: self.bg_quality.or(fallback.bg_quality),
+            fg_quality: self.fg_quality.or(fallback.fg_quality),
+            color_mode: self.color_mode.or(fallback.color_mode),
+        }
+    }
+
+    /// 指定された項目でマージ済み設定を上書きする。
+    pub fn apply(&self, merged: &mut MergedConfig) {
+        if let Some(dpi) = self.dpi {
+            merged.dpi = dpi;
+        }
+        if let Some(bg_quality) = self.bg_quality {
+            merged.bg_quality = bg_quality;
+        }
+        if let Some(fg_quality) = self.fg_quality {
+            merged.fg_quality = fg_quality;
+        }
+        if let Some(color_mode) = self.color_mode {
+            merged.color_mode = color_mode;
+        }
+    }
+
+    fn set(&mut self, flag: &str, value: &str) -> crate::error::Result<()> {
+        let value = value.trim();
+        match flag {
+            "--dpi" => self.dpi = Some(parse_dpi(value)?),
+            "--bg-quality" => self.bg_quality = Some(parse_quality(flag, value)?),
+            "--fg-quality" => self.fg_quality = Some(parse_quality(flag, value)?),
+            "--color-mode" => self.color_mode = Some(parse_color_mode(value)?),
+            _ => unreachable!("unknown override flag {flag}"),
+        }
+        Ok(())
+    }
+}
+
+fn parse_dpi(value: &str) -> crate::error::Result<u32> {
+    match value.parse::<u32>() {
+        Ok(dpi) if dpi > 0 => Ok(dpi),
+        _ => Err(PdfMaskError::config(format!(
+            "--dpi must be a positive integer, got '{value}'"
+        ))),
+    }
+}
+
+/// JPEG品質を1〜100の整数またはプリセット名（[`QUALITY_PRESETS`]）として解釈する。
+fn parse_quality(flag: &str, value: &str) -> crate::error::Result<u8> {
+    let name = value.to_ascii_lowercase();
+    let preset = QUALITY_PRESETS
+        .iter()
+        .find(|(preset, _)| *preset == name)
+        .map(|&(_, quality)| quality);
+    match (preset, value.parse::<u8>()) {
+        (Some(q), _) => Ok(q),
+        (None, Ok(q)) if (1..=100).contains(&q) => Ok(q),
+        _ => Err(PdfMaskError::config(format!(
+            "{flag} must be 1-100 or one of low, medium, high, archival, got '{value}'"
+        ))),
+    }
+}
+
+fn parse_color_mode(value: &str) -> crate::error::Result<ColorMode> {
+    match value.to_ascii_lowercase().as_str() {
+        "rgb" => Ok(ColorMode::Rgb),
+        "grayscale" => Ok(ColorMode::Grayscale),
+        "bw" => Ok(ColorMode::Bw),
+        "skip" => Ok(ColorMode::Skip),
+        _ => Err(PdfMaskError::config(format!(
+            "--color-mode must be one of rgb, grayscale, bw, skip, got '{value}'"
+        ))),
+    }
+}
@@ -1,7 +1,20 @@
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
-use crate::config::job::ColorMode;
-use serde::Deserialize;
+use crate::config::job::{
+    BlankPageAction, ColorMode, ForegroundMask, ImageColorSpace, JpegSizeMismatch, MetadataMode,
+    OutputColorSpace, ResampleFilter, WidthSource, deserialize_quality,
+};
+use serde::{Deserialize, Serialize};
+
+/// 画像XObjectの画素数上限の既定値（16384×16384）
+pub const DEFAULT_MAX_IMAGE_PIXELS: u64 = 16384 * 16384;
+
+/// ページのコンテンツストリーム（デコード後）のバイト数上限の既定値（256 MiB）
+pub const DEFAULT_MAX_CONTENT_STREAM_BYTES: u64 = 256 * 1024 * 1024;
+
+/// 出力/Infoの/Producerの既定値（クレート名とバージョン）
+pub const DEFAULT_PRODUCER: &str = concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"));
 
 #[derive(Debug, Clone, Deserialize)]
 #[serde(default)]
@@ -9,11 +22,182 @@ pub struct Settings {
     pub color_mode: ColorMode,
     pub dpi: u32,
     pub fg_dpi: u32,
+    #[serde(deserialize_with = "deserialize_quality")]
     pub bg_quality: u8,
+    #[serde(deserialize_with = "deserialize_quality")]
     pub fg_quality: u8,
     pub parallel_workers: usize,
     pub cache_dir: PathBuf,
     pub linearize: bool,
+    pub jbig2: Jbig2Options,
+    pub jpeg_escalation: JpegEscalation,
+    /// リダクション時に画像をダウンサンプリングする実効DPIの上限（未指定なら元解像度のまま）
+    pub image_max_dpi: Option<u32>,
+    /// 写真と判定した画像の実効DPIの上限（`image_max_dpi`より優先）。指定時は写真をJPEGで再エンコードする
+    pub photo_max_dpi: Option<u32>,
+    /// 線画と判定した画像の実効DPIの上限（`image_max_dpi`より優先）。指定時は線画をJBIG2/Flateで再エンコードする
+    pub line_art_max_dpi: Option<u32>,
+    /// 出力する画像XObjectをこの色空間にそろえる（未指定なら元の色空間のまま）
+    pub normalize_images_to: Option<ImageColorSpace>,
+    /// 画像をダウンサンプリングするときのリサンプリングフィルタ
+    pub resample_filter: ResampleFilter,
+    /// デコードを許可する画像XObjectの画素数(Width×Height)の上限（展開爆弾対策）
+    pub max_image_pixels: u64,
+    /// JPEG画像の実際の寸法が/Width・/Heightと食い違うときの扱い（warn: 実寸を使う、error: 失敗させる）
+    pub jpeg_size_mismatch: JpegSizeMismatch,
+    /// デコード後のページコンテンツストリームのバイト数の上限（展開爆弾対策）
+    pub max_content_stream_bytes: u64,
+    /// 1ジョブが出力するページ数の上限（未指定なら無制限）。超えるジョブは書き出す前に失敗させる
+    pub max_output_pages: Option<u32>,
+    pub blank_page_action: BlankPageAction,
+    pub output_colorspace: OutputColorSpace,
+    pub metadata: MetadataMode,
+    /// 同じ入力・設定から常に同一バイト列の出力を生成する
+    pub reproducible: bool,
+    /// 左右反転して描画されたページ（ミラー出力のスキャン等）を検出して元に戻す
+    pub fix_mirror: bool,
+    /// テキストが見つからなかったページを再ラスタライズするDPI（未指定なら再試行しない）
+    pub retry_empty_at_dpi: Option<u32>,
+    /// ラスタライズ時にテキスト（アウトライン化したパスを含む）をアンチエイリアスする
+    pub text_antialias: bool,
+    /// 何も描画しないページを画像化せず、白塗りだけの最小ページとして出力する
+    pub minimize_blank: bool,
+    /// AcroFormのフィールドを現在の値でページコンテンツに焼き込み、フォームを取り除く
+    pub flatten_forms: bool,
+    /// テキストだけを画像化し、ページ上の画像XObjectを元のまま残す。
+    /// falseならページ全体をラスタライズしてMRCレイヤーに分解する
+    pub preserve_images: bool,
+    /// 埋め込みフォントのテキストをアウトライン（パス）に変換し、ラスタライズせずに済ませる
+    pub text_to_outlines: bool,
+    /// 全面を1枚のJPEG画像で描くだけのページはMRC化せず、画像の再圧縮だけを行う
+    pub passthrough_full_page_images: bool,
+    /// Skipページはそのままコピーしつつ、埋め込み画像だけを小さくなる場合に再圧縮する
+    pub optimize_skip_images: bool,
+    /// Skipページのコピーで他のページとオブジェクト（フォント・画像等）を共有する。無効ならページごとに独立したコピーを作る
+    pub share_copied_objects: bool,
+    /// PDF/A用のOutputIntentを出力する（全ページがグレースケールならGray Gamma 2.2、それ以外はsRGB）
+    pub pdfa_output_intent: bool,
+    /// JBIG2マスクから長い水平線（表の罫線・下線）を取り除く
+    pub remove_hlines: bool,
+    /// JBIG2マスクから長い垂直線（表の罫線）を取り除く
+    pub remove_vlines: bool,
+    /// テキスト領域ImageMaskの配置を四辺に広げる量(pt)。グリフ端の欠けを防ぐ
+    pub text_region_padding: f64,
+    /// MRCページの前景マスクの形式: SMask（ソフトマスク）またはImageMaskのステンシル
+    pub foreground_mask: ForegroundMask,
+    /// MRCページの背景・前景画像に/Interpolate trueを設定し、ビューアに拡大時の補間を求める
+    pub interpolate_output: bool,
+    /// MRCページのRGBの背景・前景画像の色空間をsRGBのICCBasedにする
+    pub tag_srgb: bool,
+    /// 出力ページのコンテンツストリームから冗長なq/Qの組と重複したカラーオペレータを取り除く
+    pub simplify_content: bool,
+    /// 処理したページのコンテンツストリームを、デコード後このバイト数以下の複数のストリームに分ける（未指定なら分けない）
+    pub split_content_bytes: Option<u64>,
+    /// 書き出した出力を読み直し、リダクション領域にテキストや元の画像が残っていればジョブを失敗させる
+    pub verify_redaction: bool,
+    /// 出力Catalogに設定する言語タグ（例: "en-US"）。未指定ならソースの/Langを引き継ぐ
+    pub lang: Option<String>,
+    /// ソースCatalogの表示設定（/ViewerPreferences・/PageLayout・/PageMode・GoToの/OpenAction）を引き継ぐ
+    pub preserve_view: bool,
+    /// 出力/Infoの/Producer。空文字列なら出力せず、`null`なら`metadata`で引き継いだ値のまま
+    pub producer: Option<String>,
+    /// 非埋め込みフォントの代替: フォント名 → フォントファイル。システムフォントより優先する
+    pub font_substitutions: BTreeMap<String, String>,
+    /// 代替指定に無い非埋め込みフォントに使うフォントファイル。指定時はシステムフォントを参照しない
+    pub font_fallback: Option<String>,
+    /// 代替指定に無い非埋め込みフォントをシステムフォントから探す（無効ならシステムフォントを読み込まない）
+    pub use_system_fonts: bool,
+    /// テキストのアウトライン化で字送りに使う幅（pdf: /Widths、font: フォントのadvance、max: 大きい方）
+    pub width_source: WidthSource,
+    /// 画像のリダクション前に、和集合がちょうど矩形になる隣接・重複した白色fill矩形を1つにまとめる
+    pub merge_white_fills: bool,
+    /// マスクしたページに元ページのキー（/PieceInfo）を記録し、次回の`--incremental`で再利用できるようにする
+    pub record_source_keys: bool,
+}
+
+/// JBIG2汎用領域符号化のオプション。
+///
+/// jbig2encの汎用領域符号化はGBテンプレート0固定でテンプレートを選べないため、
+/// 調整できるのは典型予測(TPGDON)のみ。未知のキー（テンプレート指定など）はエラーにする。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Jbig2Options {
+    /// 典型予測（TPGDON）を有効にする
+    pub tpgdon: bool,
+}
+
+impl Default for Jbig2Options {
+    fn default() -> Self {
+        Jbig2Options { tpgdon: true }
+    }
+}
+
+/// JBIG2マスクから取り除く長い罫線の向き（`remove_hlines`/`remove_vlines`）。
+///
+/// 取り除いた罫線はRGB/グレースケールのページでは背景レイヤー（テキスト選択的
+/// ラスタライズでは元のベクター描画）に残る。背景を持たないBWモードでは消える。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct LineRemoval {
+    /// 水平線を取り除く
+    #[serde(rename = "remove_hlines")]
+    pub horizontal: bool,
+    /// 垂直線を取り除く
+    #[serde(rename = "remove_vlines")]
+    pub vertical: bool,
+}
+
+impl LineRemoval {
+    /// いずれかの向きを取り除くか
+    pub fn is_enabled(&self) -> bool {
+        self.horizontal || self.vertical
+    }
+}
+
+/// 画像の内容の種類ごとの実効DPIの上限（`photo_max_dpi`/`line_art_max_dpi`）。
+///
+/// いずれかを指定すると、画像を写真と線画に分類し、種類ごとの上限で
+/// ダウンサンプリングする。再エンコードの形式も種類で選ぶ（写真はJPEG、
+/// 線画はJBIG2またはFlate）。未指定の種類には`image_max_dpi`を使う。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct ImageClassDpi {
+    /// 写真の実効DPIの上限
+    #[serde(rename = "photo_max_dpi")]
+    pub photo: Option<u32>,
+    /// 線画の実効DPIの上限
+    #[serde(rename = "line_art_max_dpi")]
+    pub line_art: Option<u32>,
+}
+
+impl ImageClassDpi {
+    /// 画像を種類で区別するか
+    pub fn is_enabled(&self) -> bool {
+        self.photo.is_some() || self.line_art.is_some()
+    }
+}
+
+/// JPEG品質の自動引き上げ（低すぎる品質指定への安全策）のオプション。
+///
+/// MRCの背景・前景レイヤーを符号化した結果のPSNR（元画像との比較）が
+/// `min_psnr`を下回る場合、`step`ずつ品質を上げて再符号化する（`max_quality`まで）。
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct JpegEscalation {
+    /// 許容するPSNRの下限（dB）。未指定なら引き上げない
+    pub min_psnr: Option<f64>,
+    /// 1回の引き上げ幅
+    pub step: u8,
+    /// 引き上げ後の品質の上限
+    pub max_quality: u8,
+}
+
+impl Default for JpegEscalation {
+    fn default() -> Self {
+        JpegEscalation {
+            min_psnr: None,
+            step: 10,
+            max_quality: 90,
+        }
+    }
 }
 
 impl Default for Settings {
@@ -27,6 +211,50 @@ impl Default for Settings {
             parallel_workers: 0,
             cache_dir: PathBuf::from(".cache"),
             linearize: true,
+            jbig2: Jbig2Options::default(),
+            jpeg_escalation: JpegEscalation::default(),
+            image_max_dpi: None,
+            photo_max_dpi: None,
+            line_art_max_dpi: None,
+            normalize_images_to: None,
+            resample_filter: ResampleFilter::Triangle,
+            max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+            jpeg_size_mismatch: JpegSizeMismatch::Warn,
+            max_content_stream_bytes: DEFAULT_MAX_CONTENT_STREAM_BYTES,
+            max_output_pages: None,
+            blank_page_action: BlankPageAction::Keep,
+            output_colorspace: OutputColorSpace::Rgb,
+            metadata: MetadataMode::Strip,
+            reproducible: false,
+            fix_mirror: false,
+            retry_empty_at_dpi: None,
+            text_antialias: true,
+            minimize_blank: false,
+            flatten_forms: false,
+            preserve_images: true,
+            text_to_outlines: true,
+            passthrough_full_page_images: false,
+            optimize_skip_images: false,
+            share_copied_objects: true,
+            pdfa_output_intent: false,
+            remove_hlines: false,
+            remove_vlines: false,
+            text_region_padding: 0.0,
+            foreground_mask: ForegroundMask::Smask,
+            interpolate_output: false,
+            tag_srgb: false,
+            simplify_content: false,
+            split_content_bytes: None,
+            verify_redaction: false,
+            lang: None,
+            preserve_view: false,
+            producer: Some(DEFAULT_PRODUCER.to_string()),
+            font_substitutions: BTreeMap::new(),
+            font_fallback: None,
+            use_system_fonts: true,
+            width_source: WidthSource::Pdf,
+            merge_white_fills: false,
+            record_source_keys: false,
         }
     }
 }
@@ -35,6 +35,12 @@ pub enum PdfMaskError {
     #[error("Linearize error: {0}")]
     LinearizeError(String),
 
+    #[error("Limit exceeded: {0}")]
+    LimitExceeded(String),
+
+    #[error("Redaction leak: {0}")]
+    RedactionLeak(String),
+
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
 }
@@ -79,6 +85,10 @@ error_constructors! {
     cache => CacheError,
     /// Create a linearize error.
     linearize => LinearizeError,
+    /// Create a limit-exceeded error (input larger than a configured bound).
+    limit_exceeded => LimitExceeded,
+    /// Create a redaction-leak error (redacted content found in the output).
+    redaction_leak => RedactionLeak,
 }
 
 impl From<lopdf::Error> for PdfMaskError {
@@ -15,6 +15,21 @@ use crate::ffi::leptonica::Pix;
 /// # Returns
 /// `Ok(Vec<u8>)` containing the JBIG2 encoded data, or `Err` on failure.
 pub fn encode_generic(pix: &mut Pix) -> crate::error::Result<Vec<u8>> {
+    encode_generic_with(pix, true)
+}
+
+/// Encode a 1-bit PIX using JBIG2 generic region encoding with explicit parameters.
+///
+/// jbig2enc always codes generic regions with GB template 0 and places the
+/// adaptive template pixel automatically; only typical prediction can be chosen.
+///
+/// # Arguments
+/// * `pix` - A 1-bit leptonica Pix image
+/// * `duplicate_line_removal` - Enable typical prediction (TPGDON)
+pub fn encode_generic_with(
+    pix: &mut Pix,
+    duplicate_line_removal: bool,
+) -> crate::error::Result<Vec<u8>> {
     if pix.get_depth() != 1 {
         return Err(PdfMaskError::jbig2_encode(format!(
             "JBIG2 encoding requires 1-bit PIX, got {}-bit",
@@ -27,10 +42,10 @@ pub fn encode_generic(pix: &mut Pix) -> crate::error::Result<Vec<u8>> {
     let buf = unsafe {
         jbig2enc_sys::jbig2enc_encode_generic_c(
             pix.as_mut_ptr(), // SAFETY: pix is &mut, pointer not used after this block
-            1,                // duplicate_line_removal (TPGD)
-            -1,               // tpl_x (auto)
-            -1,               // tpl_y (auto)
-            0,                // use_refinement (off for generic encoding)
+            duplicate_line_removal as libc::c_int,
+            -1, // tpl_x (auto)
+            -1, // tpl_y (auto)
+            0,  // use_refinement (off for generic encoding)
             &mut length,
         )
     };
@@ -3,12 +3,33 @@
 use super::leptonica_sys::{
     BOX, BOXA, L_CLONE, PIX, boxCreate, boxDestroy, boxGetGeometry, boxaDestroy, boxaGetBox,
     boxaGetCount, pixClipRectangle, pixClone, pixConnCompBB, pixConvertRGBToGray, pixCreate,
-    pixDestroy, pixGetData, pixGetDepth, pixGetHeight, pixGetRegionsBinary, pixGetWidth, pixGetWpl,
-    pixOtsuAdaptiveThreshold, pixSetAll, pixSetPixel,
+    pixDestroy, pixGetData, pixGetDepth, pixGetHeight, pixGetPixel, pixGetRegionsBinary,
+    pixGetWidth, pixGetWpl, pixOpenBrick, pixOtsuAdaptiveThreshold, pixSetAll, pixSetPixel,
+    pixSubtract,
 };
 use crate::error::{PdfMaskError, Result};
 use std::ptr;
 
+/// Largest pixel buffer leptonica allocates for one image: `pixCreate`
+/// rejects requests of 2^31 bytes or more.
+const MAX_PIX_BYTES: u64 = (1 << 31) - 1;
+
+/// Check that a `width` x `height` image at `depth` bpp fits in one leptonica
+/// allocation, so oversized pages fail with a clear error up front instead of
+/// a NULL from `pixCreate`.
+fn check_pix_size(width: u32, height: u32, depth: u32) -> Result<()> {
+    // Rows are padded to whole 32-bit words
+    let words_per_line = (width as u64 * depth as u64).div_ceil(32);
+    let bytes = words_per_line * 4 * height as u64;
+    if bytes > MAX_PIX_BYTES {
+        return Err(PdfMaskError::segmentation(format!(
+            "Pix image {}x{}x{} needs {} bytes, exceeding leptonica's limit of {} bytes",
+            width, height, depth, bytes, MAX_PIX_BYTES
+        )));
+    }
+    Ok(())
+}
+
 /// Named result from `pixGetRegionsBinary`.
 ///
 /// Each field is `None` when leptonica returned a NULL pointer for that
@@ -46,12 +67,14 @@ impl Pix {
                 width, height, depth
             )));
         }
+        check_pix_size(width, height, depth)?;
 
         unsafe {
+            // NULL also covers a failed allocation of the pixel buffer
             let ptr = pixCreate(width as i32, height as i32, depth as i32);
             if ptr.is_null() {
                 Err(PdfMaskError::segmentation(format!(
-                    "Failed to create Pix image ({}x{}x{})",
+                    "Failed to create Pix image ({}x{}x{}): allocation failed",
                     width, height, depth
                 )))
             } else {
@@ -81,6 +104,9 @@ impl Pix {
                 ))
             })? as usize;
 
+        // Reject oversized images before looking at the data, as `pixCreate` would
+        check_pix_size(width, height, 32)?;
+
         if data.len() != expected_size {
             return Err(PdfMaskError::segmentation(format!(
                 "Data size mismatch: expected {} bytes, got {}",
@@ -102,7 +128,7 @@ impl Pix {
             let ptr = pixCreate(width as i32, height as i32, 32);
             if ptr.is_null() {
                 return Err(PdfMaskError::segmentation(format!(
-                    "Failed to create Pix image from RGBA ({}x{})",
+                    "Failed to create Pix image from RGBA ({}x{}): allocation failed",
                     width, height
                 )));
             }
@@ -274,6 +300,34 @@ impl Pix {
         Ok(())
     }
 
+    /// Get a single pixel value at the given coordinates.
+    ///
+    /// # Arguments
+    /// * `x` - X coordinate
+    /// * `y` - Y coordinate
+    pub fn get_pixel(&self, x: u32, y: u32) -> Result<u32> {
+        if x >= self.get_width() || y >= self.get_height() {
+            return Err(PdfMaskError::segmentation(format!(
+                "pixel ({}, {}) out of bounds for {}x{} image",
+                x,
+                y,
+                self.get_width(),
+                self.get_height()
+            )));
+        }
+        let mut val: u32 = 0;
+        unsafe {
+            let ret = pixGetPixel(self.ptr, x as i32, y as i32, &mut val);
+            if ret != 0 {
+                return Err(PdfMaskError::segmentation(format!(
+                    "pixGetPixel failed at ({}, {})",
+                    x, y
+                )));
+            }
+        }
+        Ok(val)
+    }
+
     /// Extract bounding boxes of connected components from a 1-bit image.
     ///
     /// Wraps leptonica's `pixConnCompBB`. Returns a list of `(x, y, w, h)`
@@ -536,6 +590,62 @@ impl Pix {
             }
         }
     }
+
+    /// Morphological opening with a `hsize` x `vsize` brick.
+    ///
+    /// Wraps leptonica's `pixOpenBrick`. On a 1-bit image this keeps only the
+    /// foreground that fits the brick, e.g. horizontal runs at least `hsize`
+    /// long for a `hsize` x 1 brick.
+    ///
+    /// # Errors
+    /// Returns an error if the image is not 1 bpp, a size is zero, or leptonica fails.
+    pub fn open_brick(&self, hsize: u32, vsize: u32) -> Result<Pix> {
+        if self.get_depth() != 1 {
+            return Err(PdfMaskError::segmentation(format!(
+                "open_brick requires 1-bit input, got {}-bit",
+                self.get_depth()
+            )));
+        }
+        if hsize == 0 || vsize == 0 || hsize > i32::MAX as u32 || vsize > i32::MAX as u32 {
+            return Err(PdfMaskError::segmentation(format!(
+                "invalid brick size {}x{}",
+                hsize, vsize
+            )));
+        }
+        unsafe {
+            let ptr = pixOpenBrick(ptr::null_mut(), self.ptr, hsize as i32, vsize as i32);
+            if ptr.is_null() {
+                Err(PdfMaskError::segmentation("pixOpenBrick failed"))
+            } else {
+                Ok(Pix { ptr })
+            }
+        }
+    }
+
+    /// Clear the foreground pixels of `other` from this 1-bit image.
+    ///
+    /// Wraps leptonica's `pixSubtract` and returns a new image
+    /// (`self & !other`).
+    ///
+    /// # Errors
+    /// Returns an error if either image is not 1 bpp or if leptonica fails.
+    pub fn subtract(&self, other: &Pix) -> Result<Pix> {
+        if self.get_depth() != 1 || other.get_depth() != 1 {
+            return Err(PdfMaskError::segmentation(format!(
+                "subtract requires 1-bit images, got {}-bit and {}-bit",
+                self.get_depth(),
+                other.get_depth()
+            )));
+        }
+        unsafe {
+            let ptr = pixSubtract(ptr::null_mut(), self.ptr, other.ptr);
+            if ptr.is_null() {
+                Err(PdfMaskError::segmentation("pixSubtract failed"))
+            } else {
+                Ok(Pix { ptr })
+            }
+        }
+    }
 }
 
 impl Drop for Pix {
@@ -576,6 +686,22 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_pix_create_beyond_allocation_limit() {
+        // 50000 x 50000 x 32bpp needs 10 GB: rejected without attempting it
+        let result = Pix::create(50_000, 50_000, 32);
+        let err = result.err().expect("oversized Pix should be an error");
+        assert!(err.to_string().contains("limit"), "got: {err}");
+    }
+
+    #[test]
+    fn test_pix_from_raw_rgba_beyond_allocation_limit() {
+        // 30000 x 30000 RGBA needs 3.6 GB, checked before the (empty) data
+        let result = Pix::from_raw_rgba(30_000, 30_000, &[]);
+        let err = result.err().expect("oversized Pix should be an error");
+        assert!(err.to_string().contains("limit"), "got: {err}");
+    }
+
     #[test]
     fn test_pix_from_raw_rgba_overflow() {
         // Dimensions that would overflow u32 when multiplied
@@ -1,4 +1,12 @@
+#[cfg(feature = "mrc")]
 pub mod jbig2enc;
+#[cfg(feature = "mrc")]
 pub mod jbig2enc_sys;
+#[cfg(feature = "mrc")]
 pub mod leptonica;
+#[cfg(feature = "mrc")]
 pub mod leptonica_sys;
+#[cfg(feature = "jpx")]
+pub mod openjpeg;
+#[cfg(feature = "jpx")]
+pub mod openjpeg_sys;
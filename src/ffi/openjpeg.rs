@@ -0,0 +1,380 @@
+// JPEG2000 (JPXDecode) decoding: safe wrapper around libopenjp2 (RAII Drop)
+
+use super::openjpeg_sys::{
+    OPJ_BOOL, OPJ_CLRSPC_CMYK, OPJ_CLRSPC_SYCC, OPJ_CODEC_FORMAT, OPJ_CODEC_J2K, OPJ_CODEC_JP2,
+    OPJ_J2K_STREAM_CHUNK_SIZE, opj_codec_t, opj_create_decompress, opj_decode, opj_destroy_codec,
+    opj_dparameters_t, opj_end_decompress, opj_image_comp_t, opj_image_destroy, opj_image_t,
+    opj_read_header, opj_set_default_decoder_parameters, opj_setup_decoder, opj_stream_create,
+    opj_stream_destroy, opj_stream_set_read_function, opj_stream_set_seek_function,
+    opj_stream_set_skip_function, opj_stream_set_user_data, opj_stream_set_user_data_length,
+    opj_stream_t,
+};
+use crate::error::{PdfMaskError, Result};
+use image::{DynamicImage, GrayImage, RgbImage};
+use std::ffi::c_void;
+use std::mem::MaybeUninit;
+use std::ptr;
+
+/// JP2 file signature box (`jP  \r\n\x87\n`).
+const JP2_SIGNATURE: &[u8] = &[
+    0x00, 0x00, 0x00, 0x0C, 0x6A, 0x50, 0x20, 0x20, 0x0D, 0x0A, 0x87, 0x0A,
+];
+
+/// Raw J2K codestream start (SOC marker followed by SIZ marker).
+const J2K_SIGNATURE: &[u8] = &[0xFF, 0x4F, 0xFF, 0x51];
+
+/// Decode a JP2 file or raw J2K codestream into an 8-bit image.
+///
+/// Images whose header dimensions exceed `max_width` x `max_height` are
+/// rejected before the (potentially large) tile data is decoded.
+///
+/// # Returns
+/// `DynamicImage::ImageLuma8` for 1-2 component images (alpha is dropped),
+/// `DynamicImage::ImageRgb8` otherwise (sYCC and CMYK are converted to RGB).
+pub fn decode(data: &[u8], max_width: u32, max_height: u32) -> Result<DynamicImage> {
+    decode_image(data, max_width, max_height)?.to_dynamic_image()
+}
+
+/// Decode like [`decode`], also returning the opacity channel as an 8-bit
+/// plane when the image carries one (PDF `/SMaskInData`).
+///
+/// The opacity channel is the component flagged as such by the JP2 channel
+/// definition box; without one, the trailing component of a gray+alpha
+/// (2 component) or RGB+alpha (4 component, non-CMYK) image is used.
+pub fn decode_with_alpha(
+    data: &[u8],
+    max_width: u32,
+    max_height: u32,
+) -> Result<(DynamicImage, Option<GrayImage>)> {
+    let image = decode_image(data, max_width, max_height)?;
+    Ok((image.to_dynamic_image()?, image.alpha_plane()?))
+}
+
+/// Read the header, check the dimensions and decode the codestream.
+fn decode_image(data: &[u8], max_width: u32, max_height: u32) -> Result<Image> {
+    let format: OPJ_CODEC_FORMAT = if data.starts_with(JP2_SIGNATURE) {
+        OPJ_CODEC_JP2
+    } else if data.starts_with(J2K_SIGNATURE) {
+        OPJ_CODEC_J2K
+    } else {
+        return Err(PdfMaskError::image_xobject(
+            "JPX decode error: data is neither a JP2 file nor a J2K codestream",
+        ));
+    };
+
+    let codec = Codec::new(format)?;
+
+    // The reader must outlive the stream that borrows it (dropped in reverse order).
+    let mut reader = MemoryReader { data, pos: 0 };
+    let stream = Stream::new(&mut reader)?;
+
+    let mut image_ptr: *mut opj_image_t = ptr::null_mut();
+    let ok = unsafe { opj_read_header(stream.ptr, codec.ptr, &mut image_ptr) };
+    // opj_read_header may allocate the image even on failure; take ownership first
+    let image = Image { ptr: image_ptr };
+    if ok == 0 || image.ptr.is_null() {
+        return Err(PdfMaskError::image_xobject(
+            "JPX decode error: failed to read header",
+        ));
+    }
+
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 || width > max_width || height > max_height {
+        return Err(PdfMaskError::image_xobject(format!(
+            "JPX image {}x{} exceeds declared size {}x{}",
+            width, height, max_width, max_height
+        )));
+    }
+
+    unsafe {
+        if opj_decode(codec.ptr, stream.ptr, image.ptr) == 0
+            || opj_end_decompress(codec.ptr, stream.ptr) == 0
+        {
+            return Err(PdfMaskError::image_xobject(
+                "JPX decode error: failed to decode codestream",
+            ));
+        }
+    }
+
+    Ok(image)
+}
+
+/// RAII wrapper for opj_codec_t
+struct Codec {
+    ptr: *mut opj_codec_t,
+}
+
+impl Codec {
+    fn new(format: OPJ_CODEC_FORMAT) -> Result<Self> {
+        unsafe {
+            let ptr = opj_create_decompress(format);
+            if ptr.is_null() {
+                return Err(PdfMaskError::image_xobject(
+                    "JPX decode error: failed to create decompressor",
+                ));
+            }
+            let codec = Codec { ptr };
+
+            let mut params = MaybeUninit::<opj_dparameters_t>::uninit();
+            opj_set_default_decoder_parameters(params.as_mut_ptr());
+            if opj_setup_decoder(codec.ptr, params.as_mut_ptr()) == 0 {
+                return Err(PdfMaskError::image_xobject(
+                    "JPX decode error: failed to set up decoder",
+                ));
+            }
+            Ok(codec)
+        }
+    }
+}
+
+impl Drop for Codec {
+    fn drop(&mut self) {
+        unsafe { opj_destroy_codec(self.ptr) }
+    }
+}
+
+/// RAII wrapper for an input opj_stream_t reading from a `MemoryReader`
+struct Stream {
+    ptr: *mut opj_stream_t,
+}
+
+impl Stream {
+    fn new(reader: &mut MemoryReader<'_>) -> Result<Self> {
+        unsafe {
+            let ptr = opj_stream_create(OPJ_J2K_STREAM_CHUNK_SIZE, 1);
+            if ptr.is_null() {
+                return Err(PdfMaskError::image_xobject(
+                    "JPX decode error: failed to create stream",
+                ));
+            }
+            opj_stream_set_user_data(ptr, reader as *mut MemoryReader<'_> as *mut c_void, None);
+            opj_stream_set_user_data_length(ptr, reader.data.len() as u64);
+            opj_stream_set_read_function(ptr, read_callback);
+            opj_stream_set_skip_function(ptr, skip_callback);
+            opj_stream_set_seek_function(ptr, seek_callback);
+            Ok(Stream { ptr })
+        }
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        unsafe { opj_stream_destroy(self.ptr) }
+    }
+}
+
+/// In-memory source for the stream callbacks
+struct MemoryReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+unsafe extern "C" fn read_callback(
+    buffer: *mut c_void,
+    nb_bytes: usize,
+    user_data: *mut c_void,
+) -> usize {
+    let reader = unsafe { &mut *(user_data as *mut MemoryReader<'_>) };
+    let remaining = reader.data.len() - reader.pos;
+    if remaining == 0 {
+        // OpenJPEG expects (OPJ_SIZE_T)-1 at end of stream
+        return usize::MAX;
+    }
+    let n = nb_bytes.min(remaining);
+    unsafe {
+        ptr::copy_nonoverlapping(reader.data.as_ptr().add(reader.pos), buffer as *mut u8, n);
+    }
+    reader.pos += n;
+    n
+}
+
+unsafe extern "C" fn skip_callback(nb_bytes: i64, user_data: *mut c_void) -> i64 {
+    let reader = unsafe { &mut *(user_data as *mut MemoryReader<'_>) };
+    let target = (reader.pos as i64)
+        .saturating_add(nb_bytes)
+        .clamp(0, reader.data.len() as i64);
+    let skipped = target - reader.pos as i64;
+    reader.pos = target as usize;
+    skipped
+}
+
+unsafe extern "C" fn seek_callback(offset: i64, user_data: *mut c_void) -> OPJ_BOOL {
+    let reader = unsafe { &mut *(user_data as *mut MemoryReader<'_>) };
+    if offset < 0 || offset as u64 > reader.data.len() as u64 {
+        return 0;
+    }
+    reader.pos = offset as usize;
+    1
+}
+
+/// RAII wrapper for opj_image_t
+struct Image {
+    ptr: *mut opj_image_t,
+}
+
+impl Drop for Image {
+    fn drop(&mut self) {
+        if !self.ptr.is_null() {
+            unsafe { opj_image_destroy(self.ptr) }
+        }
+    }
+}
+
+impl Image {
+    fn raw(&self) -> &opj_image_t {
+        // SAFETY: ptr is non-null once the header has been read successfully
+        unsafe { &*self.ptr }
+    }
+
+    /// Image area size on the reference grid.
+    fn dimensions(&self) -> (u32, u32) {
+        let img = self.raw();
+        (img.x1.saturating_sub(img.x0), img.y1.saturating_sub(img.y0))
+    }
+
+    fn components(&self) -> &[opj_image_comp_t] {
+        let img = self.raw();
+        if img.comps.is_null() || img.numcomps == 0 {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(img.comps, img.numcomps as usize) }
+    }
+
+    /// The opacity channel as an 8-bit plane, if the image has one.
+    fn alpha_plane(&self) -> Result<Option<GrayImage>> {
+        let comps = self.components();
+        let index = comps
+            .iter()
+            .position(|comp| comp.alpha != 0)
+            .or(match comps.len() {
+                2 => Some(1),
+                4 if self.raw().color_space != OPJ_CLRSPC_CMYK => Some(3),
+                _ => None,
+            });
+        let Some(index) = index else {
+            return Ok(None);
+        };
+        let plane = Plane::new(&comps[index])?;
+        let (width, height) = self.dimensions();
+        Ok(Some(GrayImage::from_fn(width, height, |x, y| {
+            image::Luma([plane.sample(x, y)])
+        })))
+    }
+
+    /// Convert the decoded components to an 8-bit image.
+    fn to_dynamic_image(&self) -> Result<DynamicImage> {
+        let (width, height) = self.dimensions();
+        let comps = self.components();
+        if comps.is_empty() {
+            return Err(PdfMaskError::image_xobject(
+                "JPX decode error: image has no components",
+            ));
+        }
+        if comps.len() == 4 && self.raw().color_space == OPJ_CLRSPC_CMYK {
+            let cmyk = (0..4)
+                .map(|i| Plane::new(&comps[i]))
+                .collect::<Result<Vec<_>>>()?;
+            let mut rgb = RgbImage::new(width, height);
+            for (x, y, pixel) in rgb.enumerate_pixels_mut() {
+                let k = cmyk[3].sample(x, y) as u32;
+                let to_rgb = |c: u8| ((255 - c as u32) * (255 - k) / 255) as u8;
+                pixel.0 = [
+                    to_rgb(cmyk[0].sample(x, y)),
+                    to_rgb(cmyk[1].sample(x, y)),
+                    to_rgb(cmyk[2].sample(x, y)),
+                ];
+            }
+            return Ok(DynamicImage::ImageRgb8(rgb));
+        }
+
+        // Gray+alpha drops the alpha; 4+ components use the first three as color
+        let channels = if comps.len() >= 3 { 3 } else { 1 };
+        let planes = comps[..channels]
+            .iter()
+            .map(Plane::new)
+            .collect::<Result<Vec<_>>>()?;
+
+        if channels == 1 {
+            let gray =
+                GrayImage::from_fn(width, height, |x, y| image::Luma([planes[0].sample(x, y)]));
+            return Ok(DynamicImage::ImageLuma8(gray));
+        }
+
+        let is_sycc = self.raw().color_space == OPJ_CLRSPC_SYCC;
+        let rgb = RgbImage::from_fn(width, height, |x, y| {
+            let (c0, c1, c2) = (
+                planes[0].sample(x, y),
+                planes[1].sample(x, y),
+                planes[2].sample(x, y),
+            );
+            if is_sycc {
+                image::Rgb(ycc_to_rgb(c0, c1, c2))
+            } else {
+                image::Rgb([c0, c1, c2])
+            }
+        });
+        Ok(DynamicImage::ImageRgb8(rgb))
+    }
+}
+
+/// A decoded component plane scaled to 8 bits on access.
+struct Plane<'a> {
+    data: &'a [i32],
+    w: u32,
+    h: u32,
+    dx: u32,
+    dy: u32,
+    prec: u32,
+    sgnd: bool,
+}
+
+impl<'a> Plane<'a> {
+    fn new(comp: &'a opj_image_comp_t) -> Result<Self> {
+        if comp.data.is_null() || comp.w == 0 || comp.h == 0 {
+            return Err(PdfMaskError::image_xobject(
+                "JPX decode error: component has no data",
+            ));
+        }
+        if !(1..=31).contains(&comp.prec) {
+            return Err(PdfMaskError::image_xobject(format!(
+                "JPX decode error: unsupported component precision {}",
+                comp.prec
+            )));
+        }
+        let len = comp.w as usize * comp.h as usize;
+        Ok(Plane {
+            data: unsafe { std::slice::from_raw_parts(comp.data, len) },
+            w: comp.w,
+            h: comp.h,
+            dx: comp.dx.max(1),
+            dy: comp.dy.max(1),
+            prec: comp.prec,
+            sgnd: comp.sgnd != 0,
+        })
+    }
+
+    /// Sample at image coordinates, honouring component subsampling.
+    fn sample(&self, x: u32, y: u32) -> u8 {
+        let cx = (x / self.dx).min(self.w - 1);
+        let cy = (y / self.dy).min(self.h - 1);
+        let mut v = self.data[cy as usize * self.w as usize + cx as usize] as i64;
+        if self.sgnd {
+            v += 1i64 << (self.prec - 1);
+        }
+        let max = (1i64 << self.prec) - 1;
+        (v.clamp(0, max) * 255 / max) as u8
+    }
+}
+
+/// Convert an sYCC sample (ITU-R BT.601, full range) to RGB.
+fn ycc_to_rgb(y: u8, cb: u8, cr: u8) -> [u8; 3] {
+    let y = y as f32;
+    let cb = cb as f32 - 128.0;
+    let cr = cr as f32 - 128.0;
+    let clamp = |v: f32| v.round().clamp(0.0, 255.0) as u8;
+    [
+        clamp(y + 1.402 * cr),
+        clamp(y - 0.344_136 * cb - 0.714_136 * cr),
+        clamp(y + 1.772 * cb),
+    ]
+}
@@ -0,0 +1,146 @@
+// JPEG2000 (JPXDecode) decoding: raw FFI declarations for libopenjp2
+//
+// Only the subset of the OpenJPEG 2.x decompression API needed to decode an
+// in-memory JP2 file or J2K codestream is declared here. Struct layouts mirror
+// openjpeg.h from OpenJPEG 2.3+.
+
+#![allow(non_camel_case_types, non_snake_case)]
+
+use std::ffi::{c_char, c_int, c_void};
+
+pub type OPJ_BOOL = c_int;
+pub type OPJ_CODEC_FORMAT = c_int;
+pub type OPJ_COLOR_SPACE = c_int;
+
+pub const OPJ_CODEC_J2K: OPJ_CODEC_FORMAT = 0;
+pub const OPJ_CODEC_JP2: OPJ_CODEC_FORMAT = 2;
+
+pub const OPJ_CLRSPC_SYCC: OPJ_COLOR_SPACE = 3;
+pub const OPJ_CLRSPC_CMYK: OPJ_COLOR_SPACE = 5;
+
+/// Default chunk size used by opj_stream_default_create (1 MiB).
+pub const OPJ_J2K_STREAM_CHUNK_SIZE: usize = 0x0010_0000;
+
+/// Maximum path length used by the fixed-size buffers in opj_dparameters_t.
+const OPJ_PATH_LEN: usize = 4096;
+
+/// Opaque codec handle (opj_codec_t).
+#[repr(C)]
+pub struct opj_codec_t {
+    _private: [u8; 0],
+}
+
+/// Opaque stream handle (opj_stream_t).
+#[repr(C)]
+pub struct opj_stream_t {
+    _private: [u8; 0],
+}
+
+/// Decompression parameters (opj_dparameters_t).
+#[repr(C)]
+pub struct opj_dparameters_t {
+    pub cp_reduce: u32,
+    pub cp_layer: u32,
+    pub infile: [c_char; OPJ_PATH_LEN],
+    pub outfile: [c_char; OPJ_PATH_LEN],
+    pub decod_format: c_int,
+    pub cod_format: c_int,
+    pub DA_x0: u32,
+    pub DA_x1: u32,
+    pub DA_y0: u32,
+    pub DA_y1: u32,
+    pub m_verbose: OPJ_BOOL,
+    pub tile_index: u32,
+    pub nb_tile_to_decode: u32,
+    pub jpwl_correct: OPJ_BOOL,
+    pub jpwl_exp_comps: c_int,
+    pub jpwl_max_tiles: c_int,
+    pub flags: u32,
+}
+
+/// A single decoded image component (opj_image_comp_t).
+#[repr(C)]
+pub struct opj_image_comp_t {
+    pub dx: u32,
+    pub dy: u32,
+    pub w: u32,
+    pub h: u32,
+    pub x0: u32,
+    pub y0: u32,
+    pub prec: u32,
+    pub bpp: u32,
+    pub sgnd: u32,
+    pub resno_decoded: u32,
+    pub factor: u32,
+    pub data: *mut i32,
+    pub alpha: u16,
+}
+
+/// Decoded image (opj_image_t).
+#[repr(C)]
+pub struct opj_image_t {
+    pub x0: u32,
+    pub y0: u32,
+    pub x1: u32,
+    pub y1: u32,
+    pub numcomps: u32,
+    pub color_space: OPJ_COLOR_SPACE,
+    pub comps: *mut opj_image_comp_t,
+    pub icc_profile_buf: *mut u8,
+    pub icc_profile_len: u32,
+}
+
+pub type opj_stream_read_fn = unsafe extern "C" fn(
+    p_buffer: *mut c_void,
+    p_nb_bytes: usize,
+    p_user_data: *mut c_void,
+) -> usize;
+pub type opj_stream_skip_fn =
+    unsafe extern "C" fn(p_nb_bytes: i64, p_user_data: *mut c_void) -> i64;
+pub type opj_stream_seek_fn =
+    unsafe extern "C" fn(p_nb_bytes: i64, p_user_data: *mut c_void) -> OPJ_BOOL;
+pub type opj_stream_free_user_data_fn = unsafe extern "C" fn(p_user_data: *mut c_void);
+
+unsafe extern "C" {
+    pub fn opj_set_default_decoder_parameters(parameters: *mut opj_dparameters_t);
+    pub fn opj_create_decompress(format: OPJ_CODEC_FORMAT) -> *mut opj_codec_t;
+    pub fn opj_setup_decoder(
+        p_codec: *mut opj_codec_t,
+        parameters: *mut opj_dparameters_t,
+    ) -> OPJ_BOOL;
+    pub fn opj_destroy_codec(p_codec: *mut opj_codec_t);
+
+    pub fn opj_stream_create(p_buffer_size: usize, p_is_input: OPJ_BOOL) -> *mut opj_stream_t;
+    pub fn opj_stream_destroy(p_stream: *mut opj_stream_t);
+    pub fn opj_stream_set_read_function(
+        p_stream: *mut opj_stream_t,
+        p_function: opj_stream_read_fn,
+    );
+    pub fn opj_stream_set_skip_function(
+        p_stream: *mut opj_stream_t,
+        p_function: opj_stream_skip_fn,
+    );
+    pub fn opj_stream_set_seek_function(
+        p_stream: *mut opj_stream_t,
+        p_function: opj_stream_seek_fn,
+    );
+    pub fn opj_stream_set_user_data(
+        p_stream: *mut opj_stream_t,
+        p_data: *mut c_void,
+        p_function: Option<opj_stream_free_user_data_fn>,
+    );
+    pub fn opj_stream_set_user_data_length(p_stream: *mut opj_stream_t, data_length: u64);
+
+    pub fn opj_read_header(
+        p_stream: *mut opj_stream_t,
+        p_codec: *mut opj_codec_t,
+        p_image: *mut *mut opj_image_t,
+    ) -> OPJ_BOOL;
+    pub fn opj_decode(
+        p_decompressor: *mut opj_codec_t,
+        p_stream: *mut opj_stream_t,
+        p_image: *mut opj_image_t,
+    ) -> OPJ_BOOL;
+    pub fn opj_end_decompress(p_codec: *mut opj_codec_t, p_stream: *mut opj_stream_t) -> OPJ_BOOL;
+    pub fn opj_image_destroy(image: *mut opj_image_t);
+}
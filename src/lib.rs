@@ -1,7 +1,7 @@
 pub mod cache;
 pub mod config;
 pub mod error;
-#[cfg(feature = "mrc")]
+#[cfg(any(feature = "mrc", feature = "jpx"))]
 pub mod ffi;
 pub mod linearize;
 pub mod mrc;
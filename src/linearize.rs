@@ -12,25 +12,39 @@ use tracing::{debug, warn};
 ///
 /// qpdf must be available in PATH (provided by nix develop environment).
 pub fn linearize(input_path: &Path, output_path: &Path) -> crate::error::Result<()> {
+    run_qpdf(input_path, output_path, false)
+}
+
+/// Linearize a PDF file with a content-derived `/ID` (`--deterministic-id`).
+///
+/// qpdf otherwise seeds the trailer `/ID` from the current time, so two runs
+/// over the same input differ. Used for `reproducible` jobs.
+pub fn linearize_deterministic(input_path: &Path, output_path: &Path) -> crate::error::Result<()> {
+    run_qpdf(input_path, output_path, true)
+}
+
+fn run_qpdf(
+    input_path: &Path,
+    output_path: &Path,
+    deterministic_id: bool,
+) -> crate::error::Result<()> {
     debug!(
         input = %input_path.display(),
         output = %output_path.display(),
+        deterministic_id,
         "linearizing PDF"
     );
+    let mut command = Command::new("qpdf");
+    command.arg("--linearize");
+    if deterministic_id {
+        command.arg("--deterministic-id");
+    }
     let output = if input_path == output_path {
         // In-place mode: qpdf --linearize --replace-input <path>
-        Command::new("qpdf")
-            .arg("--linearize")
-            .arg("--replace-input")
-            .arg(input_path)
-            .output()
+        command.arg("--replace-input").arg(input_path).output()
     } else {
         // Separate output: qpdf --linearize <input> <output>
-        Command::new("qpdf")
-            .arg("--linearize")
-            .arg(input_path)
-            .arg(output_path)
-            .output()
+        command.arg(input_path).arg(output_path).output()
     };
 
     match output {
@@ -62,3 +76,8 @@ pub fn linearize(input_path: &Path, output_path: &Path) -> crate::error::Result<
 pub fn linearize_in_place(path: &Path) -> crate::error::Result<()> {
     linearize(path, path)
 }
+
+/// Linearize a PDF in-place with a content-derived `/ID`.
+pub fn linearize_deterministic_in_place(path: &Path) -> crate::error::Result<()> {
+    linearize_deterministic(path, path)
+}
@@ -1,37 +1,95 @@
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
-use pdf_masking::config::job::JobFile;
+use pdf_masking::config::job::{ColorMode, JobFile};
 use pdf_masking::config::merged::MergedConfig;
+use pdf_masking::config::output_template::{
+    OutputTemplateValues, expand_output_template, today_utc,
+};
+use pdf_masking::config::overrides::ConfigOverrides;
 use pdf_masking::config::{self};
 use pdf_masking::error::PdfMaskError;
 use pdf_masking::linearize;
 use pdf_masking::pipeline::job_runner::{JobConfig, JobResult};
 use pdf_masking::pipeline::orchestrator::run_all_jobs;
-use tracing::{error, info};
+use serde::Serialize;
+use tracing::{Event, Level, Subscriber, debug, error, info};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// Exit code when one or more (but not all) jobs failed.
+const EXIT_PARTIAL_FAILURE: u8 = 1;
+/// Exit code for usage, configuration and job file parse errors.
+const EXIT_CONFIG_ERROR: u8 = 2;
+/// Exit code when every job failed.
+const EXIT_ALL_FAILED: u8 = 3;
+/// Exit code under `--strict` when every job succeeded but warnings were logged.
+const EXIT_STRICT_WARNINGS: u8 = 4;
+
+/// Number of WARN events logged so far, regardless of `RUST_LOG`.
+static WARNINGS: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts WARN events into [`WARNINGS`] for the end-of-run report and `--strict`.
+struct WarningCounter;
+
+impl<S: Subscriber> Layer<S> for WarningCounter {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if *event.metadata().level() == Level::WARN {
+            WARNINGS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
 
 fn main() -> ExitCode {
     // Initialize tracing subscriber first so --help/--version output also goes
     // through the structured logging pipeline.
     // Default to INFO level; override via RUST_LOG environment variable.
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
+    // The filter applies to the log output only, so warnings are still counted
+    // when RUST_LOG hides them.
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_target(false)
+                .with_level(true)
+                .without_time()
+                .with_writer(std::io::stderr)
+                .with_filter(
+                    tracing_subscriber::EnvFilter::try_from_default_env()
+                        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info")),
+                ),
         )
-        .with_target(false)
-        .with_level(true)
-        .without_time()
-        .with_writer(std::io::stderr)
+        .with(WarningCounter.with_filter(tracing_subscriber::filter::LevelFilter::WARN))
         .init();
 
-    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
 
     if args.is_empty() || args.iter().any(|a| a == "--help" || a == "-h") {
-        info!("Usage: pdf_masking <jobs.yaml>...");
+        info!("Usage: pdf_masking [OPTIONS] <jobs.yaml>...");
         info!("  Process PDF files according to job specifications.");
+        info!("Options (override settings for all jobs; also PDF_MASKING_* env vars):");
+        info!("  --dpi <N>            Rendering resolution (PDF_MASKING_DPI)");
+        info!(
+            "  --bg-quality <Q>     Background JPEG quality, 1-100 or a preset (PDF_MASKING_BG_QUALITY)"
+        );
+        info!(
+            "  --fg-quality <Q>     Foreground JPEG quality, 1-100 or a preset (PDF_MASKING_FG_QUALITY)"
+        );
+        info!("                       Presets: low, medium, high, archival");
+        info!("  --color-mode <MODE>  rgb, grayscale, bw or skip (PDF_MASKING_COLOR_MODE)");
+        info!(
+            "  --incremental <PDF>  Reuse unchanged masked pages from a prior output (one job only)"
+        );
+        info!("  --compare <DIR>      Write a side-by-side before/after PDF per job into DIR");
+        info!("  --print-config       Print each job's resolved configuration as YAML and exit");
+        info!(
+            "  --bench              Process all jobs without writing output and report throughput"
+        );
+        info!("  --strict             Exit with code 4 if any warning was logged");
         return if args.is_empty() {
-            ExitCode::FAILURE
+            ExitCode::from(EXIT_CONFIG_ERROR)
         } else {
             ExitCode::SUCCESS
         };
@@ -42,27 +100,225 @@ fn main() -> ExitCode {
         return ExitCode::SUCCESS;
     }
 
-    // Collect job configs and their linearize flags from all job files.
-    let (job_configs, linearize_flags) = match collect_jobs(&args) {
+    let print_config = args.iter().any(|a| a == "--print-config");
+    args.retain(|a| a != "--print-config");
+    let bench = args.iter().any(|a| a == "--bench");
+    args.retain(|a| a != "--bench");
+    let strict = args.iter().any(|a| a == "--strict");
+    args.retain(|a| a != "--strict");
+
+    let (incremental_from, args) = match split_path_flag(&args, "--incremental") {
+        Ok(pair) => pair,
+        Err(e) => {
+            error!("{e}");
+            return ExitCode::from(EXIT_CONFIG_ERROR);
+        }
+    };
+    let (compare_dir, args) = match split_path_flag(&args, "--compare") {
+        Ok(pair) => pair,
+        Err(e) => {
+            error!("{e}");
+            return ExitCode::from(EXIT_CONFIG_ERROR);
+        }
+    };
+
+    // CLI flags take precedence over PDF_MASKING_* environment variables.
+    let (job_files, overrides) = match parse_overrides(&args) {
+        Ok(pair) => pair,
+        Err(e) => {
+            error!("{e}");
+            return ExitCode::from(EXIT_CONFIG_ERROR);
+        }
+    };
+    if job_files.is_empty() {
+        error!("No job files given. Usage: pdf_masking [OPTIONS] <jobs.yaml>...");
+        return ExitCode::from(EXIT_CONFIG_ERROR);
+    }
+
+    // Collect job configs and their merged settings from all job files.
+    let (mut job_configs, merged_configs) = match collect_jobs(&job_files, &overrides) {
         Ok(pair) => pair,
         Err(e) => {
             error!("{e}");
-            return ExitCode::FAILURE;
+            return ExitCode::from(EXIT_CONFIG_ERROR);
         }
     };
 
+    if print_config {
+        return match print_configs(&job_configs, &merged_configs) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                error!("{e}");
+                ExitCode::from(EXIT_CONFIG_ERROR)
+            }
+        };
+    }
+
+    // The prior output belongs to a single job, so it cannot be spread over several.
+    if let Some(prior) = incremental_from {
+        if job_configs.len() != 1 {
+            error!(
+                "--incremental requires exactly one job, got {}",
+                job_configs.len()
+            );
+            return ExitCode::from(EXIT_CONFIG_ERROR);
+        }
+        job_configs[0].incremental_from = Some(prior);
+    }
+
+    if let Some(dir) = &compare_dir {
+        for job_config in &mut job_configs {
+            job_config.compare_dir = Some(dir.clone());
+        }
+    }
+
+    // Benchmark mode runs the same pipeline but discards every output.
+    if bench {
+        for job_config in &mut job_configs {
+            job_config.discard_output = true;
+        }
+    }
+
     // Run all jobs through the pipeline.
+    let started = Instant::now();
     let results = run_all_jobs(&job_configs);
+    let elapsed = started.elapsed();
 
-    // Report results and optionally linearize.
-    report_results(&results, &job_configs, &linearize_flags)
+    // Report results and optionally linearize (there is nothing to linearize
+    // when outputs are discarded).
+    let linearize_flags: Vec<bool> = merged_configs
+        .iter()
+        .map(|m| m.linearize && !bench)
+        .collect();
+    let exit_code = report_results(&results, &job_configs, &linearize_flags);
+    if bench {
+        report_throughput(&results, &job_configs, elapsed);
+    }
+    report_warnings(exit_code, strict)
+}
+
+/// Log how many warnings the run produced. Under `--strict`, turn an
+/// otherwise successful run with warnings into [`EXIT_STRICT_WARNINGS`];
+/// job failure codes take precedence.
+fn report_warnings(exit_code: ExitCode, strict: bool) -> ExitCode {
+    let warnings = WARNINGS.load(Ordering::Relaxed);
+    if warnings == 0 {
+        return exit_code;
+    }
+    info!("{} warning(s) logged", warnings);
+    if strict && exit_code == ExitCode::SUCCESS {
+        error!("--strict: failing because warnings were logged");
+        return ExitCode::from(EXIT_STRICT_WARNINGS);
+    }
+    exit_code
+}
+
+/// Log the `--bench` summary: pages and input megabytes of the successful
+/// jobs per second of wall-clock time for the whole run.
+fn report_throughput(
+    results: &[pdf_masking::error::Result<JobResult>],
+    job_configs: &[JobConfig],
+    elapsed: Duration,
+) {
+    let mut pages = 0;
+    let mut input_bytes = 0;
+    for (result, job_config) in results.iter().zip(job_configs) {
+        if let Ok(job_result) = result {
+            pages += job_result.pages_processed;
+            input_bytes += std::fs::metadata(&job_config.input_path).map_or(0, |m| m.len());
+        }
+    }
+    let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+    let megabytes = input_bytes as f64 / 1_000_000.0;
+    info!(
+        "bench: {} pages, {:.2} MB in {:.3} s ({:.2} pages/s, {:.2} MB/s)",
+        pages,
+        megabytes,
+        elapsed.as_secs_f64(),
+        pages as f64 / secs,
+        megabytes / secs
+    );
+}
+
+/// One job's fully resolved configuration, as printed by `--print-config`.
+#[derive(Serialize)]
+struct PrintedConfig<'a> {
+    input: &'a Path,
+    output: &'a Path,
+    #[serde(flatten)]
+    merged: &'a MergedConfig,
+    /// 1-based page number to color mode, from the job's page lists.
+    color_mode_overrides: BTreeMap<u32, ColorMode>,
+}
+
+/// Print the resolved configuration of every job to stdout as a YAML sequence.
+fn print_configs(
+    job_configs: &[JobConfig],
+    merged_configs: &[MergedConfig],
+) -> Result<(), PdfMaskError> {
+    let printed: Vec<PrintedConfig> = job_configs
+        .iter()
+        .zip(merged_configs)
+        .map(|(job, merged)| PrintedConfig {
+            input: &job.input_path,
+            output: &job.output_path,
+            merged,
+            color_mode_overrides: job
+                .color_mode_overrides
+                .iter()
+                .map(|(&page, &mode)| (page, mode))
+                .collect(),
+        })
+        .collect();
+    let yaml = serde_yml::to_string(&printed)
+        .map_err(|e| PdfMaskError::config(format!("Failed to serialize configuration: {e}")))?;
+    print!("{yaml}");
+    Ok(())
+}
+
+/// Take `<flag> <path>` (or `<flag>=<path>`) out of the arguments, returning
+/// the path and the other arguments. Used for `--incremental` and `--compare`.
+fn split_path_flag(
+    args: &[String],
+    flag: &str,
+) -> Result<(Option<PathBuf>, Vec<String>), PdfMaskError> {
+    let mut path = None;
+    let mut rest = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let value = match arg.strip_prefix(flag) {
+            Some("") => iter
+                .next()
+                .cloned()
+                .ok_or_else(|| PdfMaskError::config(format!("{flag} requires a value")))?,
+            Some(inline) if inline.starts_with('=') => inline[1..].to_string(),
+            _ => {
+                rest.push(arg.clone());
+                continue;
+            }
+        };
+        path = Some(PathBuf::from(value));
+    }
+    Ok((path, rest))
+}
+
+/// Split override flags from job file arguments and layer them over the
+/// `PDF_MASKING_*` environment variables.
+fn parse_overrides(args: &[String]) -> Result<(Vec<String>, ConfigOverrides), PdfMaskError> {
+    let (cli, job_files) = ConfigOverrides::from_args(args)?;
+    let env = ConfigOverrides::from_env()?;
+    Ok((job_files, cli.or(env)))
 }
 
 /// Parse all YAML job file arguments and build the corresponding [`JobConfig`]s
-/// along with per-job linearize flags.
-fn collect_jobs(args: &[String]) -> Result<(Vec<JobConfig>, Vec<bool>), PdfMaskError> {
+/// along with each job's [`MergedConfig`].
+fn collect_jobs(
+    args: &[String],
+    overrides: &ConfigOverrides,
+) -> Result<(Vec<JobConfig>, Vec<MergedConfig>), PdfMaskError> {
     let mut job_configs: Vec<JobConfig> = Vec::new();
-    let mut linearize_flags: Vec<bool> = Vec::new();
+    let mut merged_configs: Vec<MergedConfig> = Vec::new();
+    let date = today_utc();
 
     for job_file_arg in args {
         let job_file_path = Path::new(job_file_arg);
@@ -89,17 +345,29 @@ fn collect_jobs(args: &[String]) -> Result<(Vec<JobConfig>, Vec<bool>), PdfMaskE
 
         // Merge settings with each job and construct JobConfig.
         for job in &job_file.jobs {
-            let merged = MergedConfig::new(&settings, job);
+            let mut merged = MergedConfig::new(&settings, job);
+            overrides.apply(&mut merged);
 
             let input_path = resolve_path(&job_dir, &job.input);
-            let output_path = resolve_path(&job_dir, &job.output);
+            // Expand {stem}/{date}/{index} placeholders in the output path.
+            let stem = Path::new(&job.input)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy())
+                .unwrap_or_default();
+            let output = expand_output_template(
+                &job.output,
+                &OutputTemplateValues {
+                    stem: &stem,
+                    date: &date,
+                    index: job_configs.len() + 1,
+                },
+            )?;
+            let output_path = resolve_path(&job_dir, &output);
 
             // Resolve per-page color mode overrides (1-based)
             let default_color_mode = merged.color_mode;
             let color_mode_overrides = job.resolve_page_modes()?;
 
-            linearize_flags.push(merged.linearize);
-
             job_configs.push(JobConfig {
                 input_path,
                 output_path,
@@ -108,59 +376,175 @@ fn collect_jobs(args: &[String]) -> Result<(Vec<JobConfig>, Vec<bool>), PdfMaskE
                 dpi: merged.dpi,
                 bg_quality: merged.bg_quality,
                 fg_quality: merged.fg_quality,
-                cache_dir: Some(merged.cache_dir),
+                cache_dir: Some(merged.cache_dir.clone()),
+                jbig2: merged.jbig2,
+                jpeg_escalation: merged.jpeg_escalation,
+                image_max_dpi: merged.image_max_dpi,
+                image_class_dpi: merged.image_class_dpi,
+                normalize_images_to: merged.normalize_images_to,
+                resample_filter: merged.resample_filter,
+                max_image_pixels: merged.max_image_pixels,
+                jpeg_size_mismatch: merged.jpeg_size_mismatch,
+                max_content_stream_bytes: merged.max_content_stream_bytes,
+                max_output_pages: merged.max_output_pages,
+                blank_page_action: merged.blank_page_action,
+                output_colorspace: merged.output_colorspace,
+                metadata: merged.metadata,
+                reproducible: merged.reproducible,
+                fix_mirror: merged.fix_mirror,
+                mirror_pages: job.mirror_pages.clone().unwrap_or_default(),
+                retry_empty_at_dpi: merged.retry_empty_at_dpi,
+                text_antialias: merged.text_antialias,
+                overlay_redactions: job
+                    .overlay_redactions
+                    .as_deref()
+                    .map(|path| resolve_path(&job_dir, path)),
+                overlay_coordinates: job.overlay_coordinates.unwrap_or_default(),
+                preserve_regions: job.preserve_regions.clone().unwrap_or_default(),
+                minimize_blank: merged.minimize_blank,
+                flatten_forms: merged.flatten_forms,
+                preserve_images: merged.preserve_images,
+                preserve_images_overrides: job.resolve_preserve_images_pages()?,
+                text_to_outlines: merged.text_to_outlines,
+                text_to_outlines_overrides: job.resolve_text_to_outlines_pages()?,
+                passthrough_full_page_images: merged.passthrough_full_page_images,
+                optimize_skip_images: merged.optimize_skip_images,
+                share_copied_objects: merged.share_copied_objects,
+                pdfa_output_intent: merged.pdfa_output_intent,
+                line_removal: merged.line_removal,
+                text_region_padding: merged.text_region_padding,
+                foreground_mask: merged.foreground_mask,
+                interpolate_output: merged.interpolate_output,
+                tag_srgb: merged.tag_srgb,
+                simplify_content: merged.simplify_content,
+                split_content_bytes: merged.split_content_bytes,
+                verify_redaction: merged.verify_redaction,
+                lang: merged.lang.clone(),
+                preserve_view: merged.preserve_view,
+                producer: merged.producer.clone(),
+                font_substitutions: merged
+                    .font_substitutions
+                    .iter()
+                    .map(|(name, path)| (name.clone(), resolve_path(&job_dir, path)))
+                    .collect(),
+                font_fallback: merged
+                    .font_fallback
+                    .as_deref()
+                    .map(|path| resolve_path(&job_dir, path)),
+                use_system_fonts: merged.use_system_fonts,
+                width_source: merged.width_source,
+                merge_white_fills: merged.merge_white_fills,
+                record_source_keys: merged.record_source_keys,
+                incremental_from: None,
+                compare_dir: None,
+                discard_output: false,
+                redaction_mark: job
+                    .mark_redactions
+                    .unwrap_or(false)
+                    .then(|| job.redaction_mark_color.unwrap_or([0, 0, 0])),
+                redaction_mark_fill: job.redaction_mark_fill.unwrap_or_default(),
+                redaction_manifest: job
+                    .redaction_manifest
+                    .as_deref()
+                    .map(|path| resolve_path(&job_dir, path)),
             });
+            merged_configs.push(merged);
         }
     }
 
-    Ok((job_configs, linearize_flags))
+    Ok((job_configs, merged_configs))
 }
 
-/// Print per-job results, perform post-processing (linearize), and return
-/// the appropriate [`ExitCode`].
+/// Print per-job results, perform post-processing (linearize), print a
+/// summary line, and return the appropriate [`ExitCode`].
+///
+/// Exit codes: 0 when every job succeeded, [`EXIT_PARTIAL_FAILURE`] when some
+/// failed, and [`EXIT_ALL_FAILED`] when none succeeded. A job whose output
+/// could not be linearized counts as failed.
 fn report_results(
     results: &[pdf_masking::error::Result<JobResult>],
     job_configs: &[JobConfig],
     linearize_flags: &[bool],
 ) -> ExitCode {
-    let mut has_error = false;
+    let mut failed = 0;
     for (i, result) in results.iter().enumerate() {
-        match result {
-            Ok(job_result) => {
-                info!(
-                    "OK: {} -> {} ({} pages)",
-                    job_result.input_path.display(),
-                    job_result.output_path.display(),
-                    job_result.pages_processed
-                );
-
-                // Linearize output if configured.
-                if linearize_flags[i]
-                    && let Err(e) = linearize::linearize_in_place(&job_result.output_path)
-                {
-                    error!(
-                        "Failed to linearize {}: {e}",
-                        job_result.output_path.display()
-                    );
-                    has_error = true;
-                }
-            }
-            Err(e) => {
-                error!(
-                    "{} -> {}: {e}",
-                    job_configs[i].input_path.display(),
-                    job_configs[i].output_path.display()
-                );
-                has_error = true;
-            }
+        if !report_job(result, &job_configs[i], linearize_flags[i]) {
+            failed += 1;
         }
     }
 
-    if has_error {
-        ExitCode::FAILURE
-    } else {
+    let total = results.len();
+    info!("{} jobs: {} ok, {} failed", total, total - failed, failed);
+
+    if failed == 0 {
         ExitCode::SUCCESS
+    } else if failed == total {
+        ExitCode::from(EXIT_ALL_FAILED)
+    } else {
+        ExitCode::from(EXIT_PARTIAL_FAILURE)
+    }
+}
+
+/// Report a single job result and linearize its output if configured.
+/// Returns `true` if the job succeeded.
+fn report_job(
+    result: &pdf_masking::error::Result<JobResult>,
+    job_config: &JobConfig,
+    linearize: bool,
+) -> bool {
+    let job_result = match result {
+        Ok(job_result) => job_result,
+        Err(e) => {
+            error!(
+                "{} -> {}: {e}",
+                job_config.input_path.display(),
+                job_config.output_path.display()
+            );
+            return false;
+        }
+    };
+
+    info!(
+        "OK: {} -> {} ({} pages)",
+        job_result.input_path.display(),
+        job_result.output_path.display(),
+        job_result.pages_processed
+    );
+    if job_result.pages_dropped > 0 {
+        info!("  dropped {} blank page(s)", job_result.pages_dropped);
+    }
+    let sizes = job_result.layer_sizes;
+    if sizes.total() > 0 {
+        info!(
+            "  layers: mask {} B, fg {} B, bg {} B",
+            sizes.mask, sizes.fg, sizes.bg
+        );
+    }
+    for page in &job_result.pages {
+        debug!(
+            page = page.page_index + 1,
+            dpi = ?page.effective_dpi,
+            mask = page.layer_sizes.mask,
+            fg = page.layer_sizes.fg,
+            bg = page.layer_sizes.bg,
+            "layer sizes"
+        );
+    }
+
+    // Linearize output if configured.
+    let linearize_output = if job_config.reproducible {
+        linearize::linearize_deterministic_in_place
+    } else {
+        linearize::linearize_in_place
+    };
+    if linearize && let Err(e) = linearize_output(&job_result.output_path) {
+        error!(
+            "Failed to linearize {}: {e}",
+            job_result.output_path.display()
+        );
+        return false;
     }
+    true
 }
 
 /// Resolve a potentially relative path against a base directory.
@@ -2,37 +2,93 @@
 
 use std::collections::HashMap;
 
-use tracing::debug;
+use tracing::{debug, warn};
 
 // 常時有効
 use super::{ImageModification, TextMaskedData, TextRegionCrop, jpeg};
-use crate::config::job::ColorMode;
+use crate::config::job::{
+    ColorMode, ImageColorSpace, JpegSizeMismatch, OutputColorSpace, ResampleFilter, WidthSource,
+};
+use crate::config::settings::{ImageClassDpi, Jbig2Options, JpegEscalation, LineRemoval};
 use crate::error::PdfMaskError;
 use crate::pdf::content_stream::{
-    extract_white_fill_rects, extract_xobject_placements, strip_text_operators,
+    BBox, FillResources, ImagePlacement, extract_white_fill_rects_with_resources,
+    extract_xobject_placements_with_resources, merge_adjacent_rects, strip_text_operators,
+    subtract_bbox,
 };
 use crate::pdf::font::ParsedFont;
-use crate::pdf::image_xobject::{bbox_overlaps, redact_image_regions};
+use crate::pdf::image_xobject::{bbox_overlaps, redact_image_regions_with_size_check};
 
 // MRC専用
 #[cfg(feature = "mrc")]
 use super::{BwLayers, MrcLayers, jbig2, segmenter};
 #[cfg(feature = "mrc")]
-use crate::mrc::segmenter::PixelBBox;
+use crate::mrc::segmenter::{LeptonicaSegmenter, PixelBBox, Segmenter};
 #[cfg(feature = "mrc")]
-use crate::pdf::content_stream::pixel_to_page_coords;
+use crate::pdf::content_stream::{Matrix, ctm_to_bbox, pixel_to_page_coords_with_origin};
 #[cfg(feature = "mrc")]
 use image::{DynamicImage, RgbaImage};
 
 /// テキスト領域のマージ距離（px）。近接する矩形を結合してXObject数を削減する。
 const TEXT_BBOX_MERGE_DISTANCE: u32 = 5;
 
+/// マスクから取り除く罫線の最短の長さ（インチ）。文字のストロークはこれより十分短い。
+#[cfg(feature = "mrc")]
+const MIN_LINE_LENGTH_INCHES: f64 = 1.0;
+
+/// `line_removal`が有効なら、ビットマップの解像度で1インチ以上の罫線をマスクから取り除く。
+#[cfg(feature = "mrc")]
+fn remove_mask_lines(
+    mask: crate::ffi::leptonica::Pix,
+    page_width_pts: f64,
+    line_removal: LineRemoval,
+) -> crate::error::Result<crate::ffi::leptonica::Pix> {
+    if !line_removal.is_enabled() || page_width_pts <= 0.0 {
+        return Ok(mask);
+    }
+    let px_per_inch = f64::from(mask.get_width()) * 72.0 / page_width_pts;
+    let min_length = (px_per_inch * MIN_LINE_LENGTH_INCHES).round().max(1.0) as u32;
+    segmenter::remove_lines(&mask, min_length, line_removal)
+}
+
 /// Configuration for MRC layer generation.
 pub struct MrcConfig {
     /// JPEG quality for the background layer (1-100)
     pub bg_quality: u8,
     /// JPEG quality for the foreground layer (1-100)
     pub fg_quality: u8,
+    /// JBIG2 encoding options for the mask layer
+    pub jbig2: Jbig2Options,
+    /// Quality escalation for JPEG layers that come out too lossy
+    pub jpeg_escalation: JpegEscalation,
+    /// Color space of the JPEG layers for RGB pages (CMYK for print workflows)
+    pub output_colorspace: OutputColorSpace,
+    /// Long lines to drop from the mask before JBIG2 encoding
+    pub line_removal: LineRemoval,
+}
+
+/// Generate MRC layers from an RGBA bitmap, segmenting text with the default
+/// [`LeptonicaSegmenter`] (see [`compose_with_segmenter`]).
+#[cfg(feature = "mrc")]
+pub fn compose(
+    rgba_data: &[u8],
+    width: u32,
+    height: u32,
+    page_width_pts: f64,
+    page_height_pts: f64,
+    config: &MrcConfig,
+    color_mode: ColorMode,
+) -> crate::error::Result<MrcLayers> {
+    compose_with_segmenter(
+        rgba_data,
+        width,
+        height,
+        page_width_pts,
+        page_height_pts,
+        config,
+        color_mode,
+        &LeptonicaSegmenter,
+    )
 }
 
 /// Generate MRC layers from an RGBA bitmap.
@@ -50,10 +106,12 @@ pub struct MrcConfig {
 /// * `height`    - Image height in pixels
 /// * `page_width_pts` - Original page width in PDF points
 /// * `page_height_pts` - Original page height in PDF points
-/// * `config`    - Quality settings for the output layers
+/// * `config`    - Quality and color space settings for the output layers
 /// * `color_mode` - RGB, Grayscale, or Bw
+/// * `segmenter` - Produces the 1-bit text mask
 #[cfg(feature = "mrc")]
-pub fn compose(
+#[allow(clippy::too_many_arguments)]
+pub fn compose_with_segmenter(
     rgba_data: &[u8],
     width: u32,
     height: u32,
@@ -61,30 +119,105 @@ pub fn compose(
     page_height_pts: f64,
     config: &MrcConfig,
     color_mode: ColorMode,
+    segmenter: &dyn Segmenter,
 ) -> crate::error::Result<MrcLayers> {
-    // 1. Segment: RGBA -> 1-bit text mask
-    let mut text_mask = segmenter::segment_text_mask(rgba_data, width, height)?;
+    // 1-2. Segment and JBIG2-encode the 1-bit text mask
+    let mask_jbig2 = segment_mask_jbig2(
+        rgba_data,
+        width,
+        height,
+        page_width_pts,
+        &config.jbig2,
+        config.line_removal,
+        segmenter,
+    )?;
+    compose_with_mask(
+        rgba_data,
+        width,
+        height,
+        page_width_pts,
+        page_height_pts,
+        config,
+        color_mode,
+        mask_jbig2,
+    )
+}
 
-    // 2. Mask layer: JBIG2-encode the 1-bit mask
-    let mask_jbig2 = jbig2::encode_mask(&mut text_mask)?;
+/// RGBAビットマップからテキストマスクを作り、罫線を除いてJBIG2エンコードする。
+///
+/// マスクはDPI・JBIG2設定・罫線除去にのみ依存し、カラーモードには依存しない。
+#[cfg(feature = "mrc")]
+pub fn segment_mask_jbig2(
+    rgba_data: &[u8],
+    width: u32,
+    height: u32,
+    page_width_pts: f64,
+    jbig2_options: &Jbig2Options,
+    line_removal: LineRemoval,
+    segmenter: &dyn Segmenter,
+) -> crate::error::Result<Vec<u8>> {
+    let text_mask = segmenter.segment_text_mask(rgba_data, width, height)?;
+    let mut text_mask = remove_mask_lines(text_mask, page_width_pts, line_removal)?;
+    jbig2::encode_mask(&mut text_mask, jbig2_options)
+}
 
+/// Generate MRC layers from an RGBA bitmap and an already encoded JBIG2 mask
+/// (e.g. one reused from the cache after a color mode change).
+///
+/// Runs steps 3-5 of [`compose_with_segmenter`].
+#[cfg(feature = "mrc")]
+#[allow(clippy::too_many_arguments)]
+pub fn compose_with_mask(
+    rgba_data: &[u8],
+    width: u32,
+    height: u32,
+    page_width_pts: f64,
+    page_height_pts: f64,
+    config: &MrcConfig,
+    color_mode: ColorMode,
+    mask_jbig2: Vec<u8>,
+) -> crate::error::Result<MrcLayers> {
     // 3. Convert RGBA -> image
     let img = RgbaImage::from_raw(width, height, rgba_data.to_vec())
         .ok_or_else(|| PdfMaskError::jpeg_encode("Failed to create image from RGBA data"))?;
     let dynamic = DynamicImage::ImageRgba8(img);
 
+    let mut output_colorspace = OutputColorSpace::Rgb;
     let (background_jpeg, foreground_jpeg) = match color_mode {
         ColorMode::Grayscale => {
             let gray = dynamic.to_luma8();
-            let bg = jpeg::encode_gray_to_jpeg(&gray, config.bg_quality)?;
-            let fg = jpeg::encode_gray_to_jpeg(&gray, config.fg_quality)?;
+            let escalation = &config.jpeg_escalation;
+            let (bg, _) =
+                jpeg::encode_gray_to_jpeg_escalated(&gray, config.bg_quality, escalation)?;
+            let (fg, _) =
+                jpeg::encode_gray_to_jpeg_escalated(&gray, config.fg_quality, escalation)?;
+            (bg, fg)
+        }
+        _ if config.output_colorspace == OutputColorSpace::Cmyk => {
+            let rgb = dynamic.to_rgb8();
+            // CMYK JPEGs can't be decoded back for the PSNR check, so the quality
+            // is chosen on the RGB encoding (same quantization scaling)
+            let (bg_quality, fg_quality) = if config.jpeg_escalation.min_psnr.is_some() {
+                let escalation = &config.jpeg_escalation;
+                let (_, bg_quality) =
+                    jpeg::encode_rgb_to_jpeg_escalated(&rgb, config.bg_quality, escalation)?;
+                let (_, fg_quality) =
+                    jpeg::encode_rgb_to_jpeg_escalated(&rgb, config.fg_quality, escalation)?;
+                (bg_quality, fg_quality)
+            } else {
+                (config.bg_quality, config.fg_quality)
+            };
+            let bg = jpeg::encode_rgb_to_cmyk_jpeg(&rgb, bg_quality)?;
+            let fg = jpeg::encode_rgb_to_cmyk_jpeg(&rgb, fg_quality)?;
+            output_colorspace = OutputColorSpace::Cmyk;
             (bg, fg)
         }
         _ => {
             // Rgb (default)
             let rgb = dynamic.to_rgb8();
-            let bg = jpeg::encode_rgb_to_jpeg(&rgb, config.bg_quality)?;
-            let fg = jpeg::encode_rgb_to_jpeg(&rgb, config.fg_quality)?;
+            let escalation = &config.jpeg_escalation;
+            let (bg, _) = jpeg::encode_rgb_to_jpeg_escalated(&rgb, config.bg_quality, escalation)?;
+            let (fg, _) = jpeg::encode_rgb_to_jpeg_escalated(&rgb, config.fg_quality, escalation)?;
             (bg, fg)
         }
     };
@@ -104,6 +237,7 @@ pub fn compose(
         page_width_pts,
         page_height_pts,
         color_mode,
+        output_colorspace,
     })
 }
 
@@ -115,9 +249,43 @@ pub fn compose_bw(
     height: u32,
     page_width_pts: f64,
     page_height_pts: f64,
+    jbig2_options: &Jbig2Options,
+    line_removal: LineRemoval,
+) -> crate::error::Result<BwLayers> {
+    compose_bw_with_segmenter(
+        rgba_data,
+        width,
+        height,
+        page_width_pts,
+        page_height_pts,
+        jbig2_options,
+        line_removal,
+        &LeptonicaSegmenter,
+    )
+}
+
+/// [`compose_bw`]と同じだが、テキストマスクを`segmenter`で作る。
+#[cfg(feature = "mrc")]
+#[allow(clippy::too_many_arguments)]
+pub fn compose_bw_with_segmenter(
+    rgba_data: &[u8],
+    width: u32,
+    height: u32,
+    page_width_pts: f64,
+    page_height_pts: f64,
+    jbig2_options: &Jbig2Options,
+    line_removal: LineRemoval,
+    segmenter: &dyn Segmenter,
 ) -> crate::error::Result<BwLayers> {
-    let mut text_mask = segmenter::segment_text_mask(rgba_data, width, height)?;
-    let mask_jbig2 = jbig2::encode_mask(&mut text_mask)?;
+    let mask_jbig2 = segment_mask_jbig2(
+        rgba_data,
+        width,
+        height,
+        page_width_pts,
+        jbig2_options,
+        line_removal,
+        segmenter,
+    )?;
 
     debug!(mask_bytes = mask_jbig2.len(), "compose BW layers");
     Ok(BwLayers {
@@ -140,6 +308,7 @@ pub type CroppedJbig2Region = (Vec<u8>, PixelBBox);
 /// # Arguments
 /// * `text_mask` - 1ビットのテキストマスク（segmenter::segment_text_maskの出力）
 /// * `bboxes` - クロップ対象の矩形リスト（ピクセル座標）
+/// * `options` - JBIG2符号化オプション
 ///
 /// # Returns
 /// 各領域のJBIG2データとピクセルBBoxのペアリスト
@@ -147,6 +316,7 @@ pub type CroppedJbig2Region = (Vec<u8>, PixelBBox);
 pub fn crop_text_regions_jbig2(
     text_mask: &crate::ffi::leptonica::Pix,
     bboxes: &[PixelBBox],
+    options: &Jbig2Options,
 ) -> crate::error::Result<Vec<CroppedJbig2Region>> {
     let mut results = Vec::with_capacity(bboxes.len());
 
@@ -156,7 +326,7 @@ pub fn crop_text_regions_jbig2(
 
         // JBIG2エンコード
         let mut clipped_mut = clipped;
-        let jbig2_data = jbig2::encode_mask(&mut clipped_mut)?;
+        let jbig2_data = jbig2::encode_mask(&mut clipped_mut, options)?;
 
         results.push((jbig2_data, bbox.clone()));
     }
@@ -179,33 +349,250 @@ pub struct TextMaskedParams<'a> {
     pub page_width_pts: f64,
     /// ページ高さ(pt)
     pub page_height_pts: f64,
+    /// MediaBox左下隅のページ座標(pt)。ビットマップ左下隅に対応する
+    pub page_origin_pts: (f64, f64),
     /// XObject名 → lopdf::Stream のマップ
     pub image_streams: &'a HashMap<String, lopdf::Stream>,
+    /// XObject名 → /Matte付きの/SMask のマップ
+    pub matte_smasks: &'a HashMap<String, lopdf::Stream>,
+    /// 白色fill判定に使うページリソース（名前付き色空間・ExtGState・Form XObject）
+    pub fill_resources: &'a FillResources,
     /// RGB, Grayscale, or Bw
     pub color_mode: ColorMode,
     /// ページ番号(0-based)
     pub page_index: u32,
+    /// テキスト領域のJBIG2符号化オプション
+    pub jbig2: Jbig2Options,
+    /// テキストマスクから取り除く罫線
+    pub line_removal: LineRemoval,
+    /// リダクション画像の実効DPI上限（縮小のみ）
+    pub image_max_dpi: Option<u32>,
+    /// 写真・線画ごとの実効DPI上限（`image_max_dpi`より優先）
+    pub image_class_dpi: ImageClassDpi,
+    /// 出力する画像をそろえる色空間
+    pub normalize_images_to: Option<ImageColorSpace>,
+    /// 画像を縮小するときのリサンプリングフィルタ
+    pub resample_filter: ResampleFilter,
+    /// デコードを許可する画像の画素数上限
+    pub max_image_pixels: u64,
+    /// JPEG画像の実寸が/Width・/Heightと食い違うときの扱い
+    pub jpeg_size_mismatch: JpegSizeMismatch,
+    /// 隣接・重複した白色fill矩形をまとめてからリダクションする
+    pub merge_white_fills: bool,
+    /// テキストマスクを作る分離器
+    pub segmenter: &'a dyn Segmenter,
+    /// ページのフォントマップ。指定すると回転したテキストを向きどおりに切り出す
+    pub fonts: Option<&'a HashMap<String, ParsedFont>>,
+}
+
+/// 回転したテキストの矩形（単位正方形→ページ座標の行列）を集める。
+///
+/// 軸平行のテキストはビットマップ上の外接矩形で十分なので含めない。
+#[cfg(feature = "mrc")]
+fn rotated_text_quads(
+    content_bytes: &[u8],
+    fonts: &HashMap<String, ParsedFont>,
+) -> crate::error::Result<Vec<Matrix>> {
+    let ops = crate::pdf::text_state::parse_content_operations(content_bytes, Some(fonts))?;
+    Ok(ops
+        .text_commands
+        .iter()
+        .filter_map(|cmd| {
+            let font = fonts.get(&cmd.font_name)?;
+            let quad = cmd.page_quad(font);
+            let degenerate = quad.a.hypot(quad.b) == 0.0 || quad.c.hypot(quad.d) == 0.0;
+            (!degenerate && !quad.is_axis_aligned()).then_some(quad)
+        })
+        .collect())
+}
+
+/// テキストマスクから回転した矩形（単位正方形→ピクセル座標の行列）の内側を、
+/// 文字列の向きをそろえた1-bit画像として切り出し、マスクからはその画素を消す。
+///
+/// 切り出した画像の先頭行が矩形の上辺（v=1）に対応する。黒画素が無ければ`None`。
+#[cfg(feature = "mrc")]
+fn crop_rotated_region(
+    text_mask: &mut crate::ffi::leptonica::Pix,
+    quad_px: &Matrix,
+) -> crate::error::Result<Option<crate::ffi::leptonica::Pix>> {
+    let Some(inverse) = quad_px.inverse() else {
+        return Ok(None);
+    };
+    let (mask_w, mask_h) = (text_mask.get_width(), text_mask.get_height());
+    let width = quad_px.a.hypot(quad_px.b).round().max(1.0) as u32;
+    let height = quad_px.c.hypot(quad_px.d).round().max(1.0) as u32;
+    let source_pixel = |x: f64, y: f64| -> Option<(u32, u32)> {
+        let (x, y) = (x.floor(), y.floor());
+        (x >= 0.0 && y >= 0.0 && x < f64::from(mask_w) && y < f64::from(mask_h))
+            .then_some((x as u32, y as u32))
+    };
+
+    let mut crop = crate::ffi::leptonica::Pix::create(width, height, 1)?;
+    let mut has_ink = false;
+    for row in 0..height {
+        let v = 1.0 - (f64::from(row) + 0.5) / f64::from(height);
+        for col in 0..width {
+            let u = (f64::from(col) + 0.5) / f64::from(width);
+            let x = quad_px.a * u + quad_px.c * v + quad_px.e;
+            let y = quad_px.b * u + quad_px.d * v + quad_px.f;
+            if let Some((sx, sy)) = source_pixel(x, y)
+                && text_mask.get_pixel(sx, sy)? != 0
+            {
+                crop.set_pixel(col, row, 1)?;
+                has_ink = true;
+            }
+        }
+    }
+    if !has_ink {
+        return Ok(None);
+    }
+
+    // 切り出した画素を軸平行の領域抽出に重ねて拾わないよう、マスクから消す
+    let bounds = ctm_to_bbox(quad_px);
+    let x_range =
+        bounds.x_min.floor().max(0.0) as u32..(bounds.x_max.ceil().max(0.0) as u32).min(mask_w);
+    for y in bounds.y_min.floor().max(0.0) as u32..(bounds.y_max.ceil().max(0.0) as u32).min(mask_h)
+    {
+        for x in x_range.clone() {
+            let (px, py) = (f64::from(x) + 0.5, f64::from(y) + 0.5);
+            let u = inverse.a * px + inverse.c * py + inverse.e;
+            let v = inverse.b * px + inverse.d * py + inverse.f;
+            if (0.0..=1.0).contains(&u) && (0.0..=1.0).contains(&v) {
+                text_mask.set_pixel(x, y, 0)?;
+            }
+        }
+    }
+    Ok(Some(crop))
+}
+
+/// 配置された画像が`bbox`全体を不透明に覆うか。
+///
+/// ページ直下に軸平行で描かれ、クリップ・半透明のExtGState・/SMask・/Mask・
+/// /ImageMaskのいずれも無い画像XObjectだけを遮蔽物とみなす。
+fn is_opaque_occluder(
+    placement: &ImagePlacement,
+    image_streams: &HashMap<String, lopdf::Stream>,
+) -> bool {
+    if !placement.form_path.is_empty() || placement.partial_cover {
+        return false;
+    }
+    let m = &placement.ctm;
+    let axis_aligned = (m.b == 0.0 && m.c == 0.0) || (m.a == 0.0 && m.d == 0.0);
+    if !axis_aligned {
+        return false;
+    }
+    let Some(stream) = image_streams.get(&placement.name) else {
+        return false;
+    };
+    let dict = &stream.dict;
+    let image_mask = dict
+        .get(b"ImageMask")
+        .and_then(|o| o.as_bool())
+        .unwrap_or(false);
+    let smask_in_data = dict
+        .get(b"SMaskInData")
+        .and_then(|o| o.as_i64())
+        .unwrap_or(0);
+    !image_mask && !dict.has(b"SMask") && !dict.has(b"Mask") && smask_in_data == 0
+}
+
+/// 白色fill矩形から、`index`番目の配置より後に描かれる不透明な画像で隠れる部分を除く。
+fn visible_white_rects(
+    white_rects: &[BBox],
+    placements: &[ImagePlacement],
+    index: usize,
+    image_streams: &HashMap<String, lopdf::Stream>,
+) -> Vec<BBox> {
+    let mut visible: Vec<BBox> = white_rects.to_vec();
+    for occluder in placements[index + 1..]
+        .iter()
+        .filter(|p| is_opaque_occluder(p, image_streams))
+    {
+        visible = visible
+            .iter()
+            .flat_map(|rect| subtract_bbox(rect, &occluder.bbox))
+            .collect();
+    }
+    visible
 }
 
 /// 白色fill矩形と重なる画像XObjectを検出し、リダクションを適用する。
+#[allow(clippy::too_many_arguments)]
 fn detect_and_redact_images(
     content_bytes: &[u8],
     image_streams: &HashMap<String, lopdf::Stream>,
+    matte_smasks: &HashMap<String, lopdf::Stream>,
+    fill_resources: &FillResources,
+    image_max_dpi: Option<u32>,
+    image_class_dpi: ImageClassDpi,
+    normalize_images_to: Option<ImageColorSpace>,
+    resample_filter: ResampleFilter,
+    max_image_pixels: u64,
+    jpeg_size_mismatch: JpegSizeMismatch,
+    merge_white_fills: bool,
 ) -> crate::error::Result<HashMap<String, ImageModification>> {
-    let white_rects = extract_white_fill_rects(content_bytes)?;
-    let placements = extract_xobject_placements(content_bytes)?;
+    let mut white_rects = extract_white_fill_rects_with_resources(content_bytes, fill_resources)?;
+    if merge_white_fills {
+        let count = white_rects.len();
+        white_rects = merge_adjacent_rects(&white_rects);
+        debug!(
+            from = count,
+            to = white_rects.len(),
+            "merged adjacent white fill rects"
+        );
+    }
+    let placements = extract_xobject_placements_with_resources(content_bytes, fill_resources)?;
 
     let mut modified_images: HashMap<String, ImageModification> = HashMap::new();
-    for placement in &placements {
+    for (index, placement) in placements.iter().enumerate() {
+        // Form XObject内のXObjectはページのリソース名では差し替えられない
+        if !placement.form_path.is_empty() {
+            if white_rects
+                .iter()
+                .any(|wr| bbox_overlaps(wr, &placement.bbox))
+            {
+                warn!(
+                    name = %placement.name,
+                    forms = %placement.form_path.join("/"),
+                    "XObject inside a form XObject overlaps a white fill and is not redacted"
+                );
+            }
+            continue;
+        }
         if let Some(stream) = image_streams.get(&placement.name) {
             let overlapping: Vec<_> = white_rects
                 .iter()
                 .filter(|wr| bbox_overlaps(wr, &placement.bbox))
                 .cloned()
                 .collect();
-
-            if !overlapping.is_empty()
-                && let Some(redacted) = redact_image_regions(stream, &overlapping, &placement.bbox)?
+            // 後から描かれる不透明な画像に隠れる部分はリダクションしない
+            let visible: Vec<_> =
+                visible_white_rects(&overlapping, &placements, index, image_streams)
+                    .into_iter()
+                    .filter(|wr| bbox_overlaps(wr, &placement.bbox))
+                    .collect();
+            if visible.is_empty() && !overlapping.is_empty() {
+                debug!(
+                    name = %placement.name,
+                    "redacted area is fully occluded by later images; skipping redaction"
+                );
+            }
+            let overlapping = visible;
+
+            // 色空間をそろえる場合は重ならない画像も再エンコードする
+            if (!overlapping.is_empty() || normalize_images_to.is_some())
+                && let Some(redacted) = redact_image_regions_with_size_check(
+                    stream,
+                    &overlapping,
+                    placement,
+                    matte_smasks.get(&placement.name),
+                    image_max_dpi,
+                    image_class_dpi,
+                    normalize_images_to,
+                    resample_filter,
+                    max_image_pixels,
+                    jpeg_size_mismatch,
+                )?
             {
                 modified_images.insert(
                     placement.name.clone(),
@@ -214,6 +601,18 @@ fn detect_and_redact_images(
                         filter: redacted.filter,
                         color_space: redacted.color_space,
                         bits_per_component: redacted.bits_per_component,
+                        width: redacted.width,
+                        height: redacted.height,
+                        redacted_regions: overlapping
+                            .iter()
+                            .map(|wr| BBox {
+                                x_min: wr.x_min.max(placement.bbox.x_min),
+                                y_min: wr.y_min.max(placement.bbox.y_min),
+                                x_max: wr.x_max.min(placement.bbox.x_max),
+                                y_max: wr.y_max.min(placement.bbox.y_max),
+                            })
+                            .collect(),
+                        smask: redacted.smask,
                     },
                 );
             }
@@ -235,18 +634,67 @@ pub fn compose_text_masked(params: &TextMaskedParams) -> crate::error::Result<Te
     let stripped_content_stream = strip_text_operators(params.content_bytes)?;
 
     // 2. 白色fill矩形と重なる画像をリダクション
-    let modified_images = detect_and_redact_images(params.content_bytes, params.image_streams)?;
+    let modified_images = detect_and_redact_images(
+        params.content_bytes,
+        params.image_streams,
+        params.matte_smasks,
+        params.fill_resources,
+        params.image_max_dpi,
+        params.image_class_dpi,
+        params.normalize_images_to,
+        params.resample_filter,
+        params.max_image_pixels,
+        params.jpeg_size_mismatch,
+        params.merge_white_fills,
+    )?;
 
     // 3. ビットマップからテキスト領域を抽出・JBIG2化
-    let text_mask =
-        segmenter::segment_text_mask(params.rgba_data, params.bitmap_width, params.bitmap_height)?;
+    //    罫線は元のコンテンツストリームに残るため、マスクからは取り除いてよい
+    let text_mask = params.segmenter.segment_text_mask(
+        params.rgba_data,
+        params.bitmap_width,
+        params.bitmap_height,
+    )?;
+    let mut text_mask = remove_mask_lines(text_mask, params.page_width_pts, params.line_removal)?;
+
+    // 回転したテキストは外接矩形だと周囲を大きく巻き込むため、向きどおりに切り出す
+    let mut text_regions: Vec<TextRegionCrop> = Vec::new();
+    if let Some(fonts) = params.fonts {
+        let (origin_x, origin_y) = params.page_origin_pts;
+        let scale_x = f64::from(params.bitmap_width) / params.page_width_pts;
+        let scale_y = f64::from(params.bitmap_height) / params.page_height_pts;
+        // ページ座標→ピクセル座標（y軸は下向き）
+        let page_to_pixel = Matrix {
+            a: scale_x,
+            b: 0.0,
+            c: 0.0,
+            d: -scale_y,
+            e: -origin_x * scale_x,
+            f: (params.page_height_pts + origin_y) * scale_y,
+        };
+        for quad in rotated_text_quads(params.content_bytes, fonts)? {
+            let quad_px = quad.multiply(&page_to_pixel);
+            let Some(mut crop) = crop_rotated_region(&mut text_mask, &quad_px)? else {
+                continue;
+            };
+            let (pixel_width, pixel_height) = (crop.get_width(), crop.get_height());
+            text_regions.push(TextRegionCrop {
+                jbig2_data: jbig2::encode_mask(&mut crop, &params.jbig2)?,
+                bbox_points: ctm_to_bbox(&quad),
+                pixel_width,
+                pixel_height,
+                placement: Some(quad),
+            });
+        }
+    }
+
     let bboxes = segmenter::extract_text_bboxes(&text_mask, TEXT_BBOX_MERGE_DISTANCE)?;
 
     // テキスト領域が無い場合は早期リターン
     if bboxes.is_empty() {
         return Ok(TextMaskedData {
             stripped_content_stream,
-            text_regions: Vec::new(),
+            text_regions,
             modified_images,
             page_index: params.page_index,
             page_width_pts: params.page_width_pts,
@@ -256,13 +704,14 @@ pub fn compose_text_masked(params: &TextMaskedParams) -> crate::error::Result<Te
     }
 
     // テキスト領域をJBIG2エンコード
-    let crops = crop_text_regions_jbig2(&text_mask, &bboxes)?;
+    let crops = crop_text_regions_jbig2(&text_mask, &bboxes, &params.jbig2)?;
 
-    let text_regions: Vec<TextRegionCrop> = crops
+    let axis_aligned_regions = crops
         .into_iter()
         .map(|(jbig2_data, pixel_bbox)| {
-            let bbox_points = pixel_to_page_coords(
+            let bbox_points = pixel_to_page_coords_with_origin(
                 &pixel_bbox,
+                params.page_origin_pts,
                 params.page_width_pts,
                 params.page_height_pts,
                 params.bitmap_width,
@@ -273,9 +722,11 @@ pub fn compose_text_masked(params: &TextMaskedParams) -> crate::error::Result<Te
                 bbox_points,
                 pixel_width: pixel_bbox.width,
                 pixel_height: pixel_bbox.height,
+                placement: None,
             })
         })
         .collect::<crate::error::Result<Vec<_>>>()?;
+    text_regions.extend(axis_aligned_regions);
 
     debug!(
         text_regions = text_regions.len(),
@@ -301,6 +752,10 @@ pub struct TextOutlinesParams<'a> {
     pub fonts: &'a HashMap<String, ParsedFont>,
     /// XObject名 → lopdf::Stream のマップ
     pub image_streams: &'a HashMap<String, lopdf::Stream>,
+    /// XObject名 → /Matte付きの/SMask のマップ
+    pub matte_smasks: &'a HashMap<String, lopdf::Stream>,
+    /// 白色fill判定に使うページリソース（名前付き色空間・ExtGState・Form XObject）
+    pub fill_resources: &'a FillResources,
     /// ページ幅(pt)
     pub page_width_pts: f64,
     /// ページ高さ(pt)
@@ -309,6 +764,22 @@ pub struct TextOutlinesParams<'a> {
     pub color_mode: ColorMode,
     /// ページ番号(0-based)
     pub page_index: u32,
+    /// リダクション画像の実効DPI上限（縮小のみ）
+    pub image_max_dpi: Option<u32>,
+    /// 写真・線画ごとの実効DPI上限（`image_max_dpi`より優先）
+    pub image_class_dpi: ImageClassDpi,
+    /// 出力する画像をそろえる色空間
+    pub normalize_images_to: Option<ImageColorSpace>,
+    /// 画像を縮小するときのリサンプリングフィルタ
+    pub resample_filter: ResampleFilter,
+    /// デコードを許可する画像の画素数上限
+    pub max_image_pixels: u64,
+    /// JPEG画像の実寸が/Width・/Heightと食い違うときの扱い
+    pub jpeg_size_mismatch: JpegSizeMismatch,
+    /// 隣接・重複した白色fill矩形をまとめてからリダクションする
+    pub merge_white_fills: bool,
+    /// 字送りに使うグリフ幅の取得元
+    pub width_source: WidthSource,
 }
 
 /// テキスト→アウトライン変換: BT...ETをベクターパスに変換し、画像リダクションも行う。
@@ -317,14 +788,28 @@ pub struct TextOutlinesParams<'a> {
 /// コンテンツストリームに残す。text_regionsは空になる。
 pub fn compose_text_outlines(params: &TextOutlinesParams) -> crate::error::Result<TextMaskedData> {
     // 1. テキスト→アウトライン変換（フォント未発見時はErrをそのまま返す）
-    let outlines_content = crate::pdf::text_to_outlines::convert_text_to_outlines(
-        params.content_bytes,
-        params.fonts,
-        params.color_mode == ColorMode::Bw,
-    )?;
+    let outlines_content =
+        crate::pdf::text_to_outlines::convert_text_to_outlines_with_width_source(
+            params.content_bytes,
+            params.fonts,
+            params.color_mode == ColorMode::Bw,
+            params.width_source,
+        )?;
 
     // 2. 白色fill矩形と重なる画像をリダクション
-    let modified_images = detect_and_redact_images(params.content_bytes, params.image_streams)?;
+    let modified_images = detect_and_redact_images(
+        params.content_bytes,
+        params.image_streams,
+        params.matte_smasks,
+        params.fill_resources,
+        params.image_max_dpi,
+        params.image_class_dpi,
+        params.normalize_images_to,
+        params.resample_filter,
+        params.max_image_pixels,
+        params.jpeg_size_mismatch,
+        params.merge_white_fills,
+    )?;
 
     debug!(
         outlines_bytes = outlines_content.len(),
@@ -1,14 +1,246 @@
 // Phase 5: jbig2enc wrapper: 1-bit mask -> JBIG2 bytes
 
+#[cfg(feature = "mrc")]
+use crate::config::settings::Jbig2Options;
+use crate::error::PdfMaskError;
+#[cfg(feature = "mrc")]
 use crate::ffi::jbig2enc;
+#[cfg(feature = "mrc")]
 use crate::ffi::leptonica::Pix;
 
+/// JBIG2ファイルヘッダの識別子（ITU-T T.88 D.4.1）
+pub const JBIG2_FILE_ID: [u8; 8] = [0x97, 0x4A, 0x42, 0x32, 0x0D, 0x0A, 0x1A, 0x0A];
+
+/// end of pageセグメントの型番号
+const SEGMENT_END_OF_PAGE: u8 = 49;
+/// end of fileセグメントの型番号
+const SEGMENT_END_OF_FILE: u8 = 51;
+/// データ長が未知（終端マーカーで終わる）ことを表す値
+const UNKNOWN_DATA_LENGTH: u32 = 0xFFFF_FFFF;
+
 /// Encode a 1-bit text mask into JBIG2 format.
 ///
 /// Delegates to the jbig2enc FFI binding for generic-region encoding.
+/// The result always uses the PDF-embedded organization (see
+/// [`to_embedded_organization`]), so it can go straight into a
+/// `/JBIG2Decode` stream.
 ///
 /// # Arguments
 /// * `mask` - A mutable reference to a 1-bit `Pix` (required by the FFI layer)
-pub fn encode_mask(mask: &mut Pix) -> crate::error::Result<Vec<u8>> {
-    jbig2enc::encode_generic(mask)
+/// * `options` - TPGDON flag
+#[cfg(feature = "mrc")]
+pub fn encode_mask(mask: &mut Pix, options: &Jbig2Options) -> crate::error::Result<Vec<u8>> {
+    let data = jbig2enc::encode_generic_with(mask, options.tpgdon)?;
+    to_embedded_organization(&data)
+}
+
+/// JBIG2データをPDFに埋め込める形（embedded organization）にそろえる。
+///
+/// PDFの`/JBIG2Decode`ストリームにはファイルヘッダ・end of page・end of fileの
+/// 各セグメントを含めてはならない（PDF 32000-1 7.4.7）。ファイルヘッダがあれば
+/// 取り除き、ランダムアクセス構成ならヘッダとデータを交互に並べ直したうえで、
+/// end of page・end of fileセグメントを除く。既に埋め込み形式のデータも受け付ける。
+pub fn to_embedded_organization(data: &[u8]) -> crate::error::Result<Vec<u8>> {
+    let (mut pos, sequential) = if data.starts_with(&JBIG2_FILE_ID) {
+        let flags = byte_at(data, JBIG2_FILE_ID.len())?;
+        // ビット1が0ならページ数（4バイト）が続く
+        let header_len = JBIG2_FILE_ID.len() + 1 + if flags & 0x02 == 0 { 4 } else { 0 };
+        (header_len, flags & 0x01 != 0)
+    } else {
+        (0, true)
+    };
+
+    // (セグメントヘッダ, データ部, 型番号)
+    let mut segments: Vec<(&[u8], &[u8], u8)> = Vec::new();
+    if sequential {
+        while pos < data.len() {
+            let header = SegmentHeader::parse(data, pos)?;
+            let data_start = pos + header.header_len;
+            let end = header.data_end(data_start, data.len())?;
+            segments.push((
+                &data[pos..data_start],
+                &data[data_start..end],
+                header.segment_type,
+            ));
+            pos = end;
+        }
+    } else {
+        // ランダムアクセス構成: 全セグメントヘッダの後に各データが同じ順で続く
+        let mut headers: Vec<(usize, SegmentHeader)> = Vec::new();
+        while pos < data.len() {
+            let header = SegmentHeader::parse(data, pos)?;
+            let is_end_of_file = header.segment_type == SEGMENT_END_OF_FILE;
+            pos += header.header_len;
+            headers.push((pos - header.header_len, header));
+            if is_end_of_file {
+                break;
+            }
+        }
+        for (start, header) in headers {
+            let end = header.data_end(pos, data.len())?;
+            segments.push((
+                &data[start..start + header.header_len],
+                &data[pos..end],
+                header.segment_type,
+            ));
+            pos = end;
+        }
+    }
+
+    let mut embedded = Vec::with_capacity(data.len());
+    for (header, body, segment_type) in segments {
+        if !matches!(segment_type, SEGMENT_END_OF_PAGE | SEGMENT_END_OF_FILE) {
+            embedded.extend_from_slice(header);
+            embedded.extend_from_slice(body);
+        }
+    }
+    Ok(embedded)
+}
+
+/// セグメントヘッダのうち、並べ替えと取捨に必要な項目（T.88 7.2）
+#[derive(Debug)]
+struct SegmentHeader {
+    segment_type: u8,
+    header_len: usize,
+    data_len: u32,
+}
+
+impl SegmentHeader {
+    fn parse(data: &[u8], start: usize) -> crate::error::Result<Self> {
+        let number = u32_at(data, start)?;
+        let flags = byte_at(data, start + 4)?;
+        let mut pos = start + 5;
+
+        // 参照先セグメント数（上位3ビット、7なら4バイトの長形式）と保持フラグ
+        let short_count = byte_at(data, pos)? >> 5;
+        let referred_count = match short_count {
+            0..=4 => {
+                pos += 1;
+                short_count as usize
+            }
+            7 => {
+                let count = (u32_at(data, pos)? & 0x1FFF_FFFF) as usize;
+                pos += 4 + (count + 1).div_ceil(8);
+                count
+            }
+            _ => {
+                return Err(PdfMaskError::jbig2_encode(format!(
+                    "invalid JBIG2 referred-to segment count {short_count} in segment {number}"
+                )));
+            }
+        };
+        let referred_size = match number {
+            0..=256 => 1,
+            257..=65536 => 2,
+            _ => 4,
+        };
+        pos += referred_count * referred_size;
+        // ページ関連付けはフラグのビット6が立っていれば4バイト
+        pos += if flags & 0x40 != 0 { 4 } else { 1 };
+
+        let data_len = u32_at(data, pos)?;
+        if data_len == UNKNOWN_DATA_LENGTH {
+            return Err(PdfMaskError::jbig2_encode(format!(
+                "JBIG2 segment {number} has an unknown data length"
+            )));
+        }
+        Ok(SegmentHeader {
+            segment_type: flags & 0x3F,
+            header_len: pos + 4 - start,
+            data_len,
+        })
+    }
+
+    /// データ部が`start`から始まるときの終端位置（範囲外ならエラー）
+    fn data_end(&self, start: usize, total: usize) -> crate::error::Result<usize> {
+        let end = start + self.data_len as usize;
+        if end > total {
+            return Err(PdfMaskError::jbig2_encode(format!(
+                "JBIG2 segment data runs past the end of the stream ({end} > {total})"
+            )));
+        }
+        Ok(end)
+    }
+}
+
+fn byte_at(data: &[u8], pos: usize) -> crate::error::Result<u8> {
+    data.get(pos).copied().ok_or_else(|| {
+        PdfMaskError::jbig2_encode(format!("truncated JBIG2 segment header at byte {pos}"))
+    })
+}
+
+fn u32_at(data: &[u8], pos: usize) -> crate::error::Result<u32> {
+    data.get(pos..pos + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| {
+            PdfMaskError::jbig2_encode(format!("truncated JBIG2 segment header at byte {pos}"))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// セグメントヘッダ（参照なし・ページ1に関連付け）とデータ部を組み立てる
+    fn segment(number: u32, segment_type: u8, body: &[u8]) -> (Vec<u8>, Vec<u8>) {
+        let mut header = number.to_be_bytes().to_vec();
+        header.extend_from_slice(&[segment_type, 0x00, 0x01]);
+        header.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        (header, body.to_vec())
+    }
+
+    fn joined(parts: &[&(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+        parts
+            .iter()
+            .flat_map(|(header, body)| header.iter().chain(body).copied())
+            .collect()
+    }
+
+    #[test]
+    fn test_sequential_file_is_stripped_to_embedded_organization() {
+        let page_info = segment(0, 48, &[0; 19]);
+        let region = segment(1, 38, &[0xAA; 30]);
+        let end_of_page = segment(2, SEGMENT_END_OF_PAGE, &[]);
+        let end_of_file = segment(3, SEGMENT_END_OF_FILE, &[]);
+
+        // 逐次構成・ページ数あり
+        let mut file = JBIG2_FILE_ID.to_vec();
+        file.push(0x01);
+        file.extend_from_slice(&1u32.to_be_bytes());
+        file.extend(joined(&[&page_info, &region, &end_of_page, &end_of_file]));
+
+        let embedded = to_embedded_organization(&file).expect("strip file header");
+        assert_eq!(embedded, joined(&[&page_info, &region]));
+        // 既に埋め込み形式なら変わらない
+        assert_eq!(to_embedded_organization(&embedded).unwrap(), embedded);
+    }
+
+    #[test]
+    fn test_random_access_file_is_interleaved() {
+        let page_info = segment(0, 48, &[0x11; 19]);
+        let region = segment(1, 38, &[0x22; 7]);
+        let end_of_file = segment(2, SEGMENT_END_OF_FILE, &[]);
+
+        // ランダムアクセス構成・ページ数未知: ヘッダを全部並べてからデータ部
+        let mut file = JBIG2_FILE_ID.to_vec();
+        file.push(0x02);
+        for (header, _) in [&page_info, &region, &end_of_file] {
+            file.extend_from_slice(header);
+        }
+        for (_, body) in [&page_info, &region, &end_of_file] {
+            file.extend_from_slice(body);
+        }
+
+        let embedded = to_embedded_organization(&file).expect("reorganize");
+        assert_eq!(embedded, joined(&[&page_info, &region]));
+    }
+
+    #[test]
+    fn test_truncated_segment_is_rejected() {
+        let region = segment(0, 38, &[0xAA; 30]);
+        let mut data = joined(&[&region]);
+        data.truncate(data.len() - 1);
+        assert!(to_embedded_organization(&data).is_err());
+        assert!(to_embedded_organization(&data[..6]).is_err());
+    }
 }
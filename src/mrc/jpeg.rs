@@ -1,8 +1,10 @@
 // Phase 5: image crate: fg/bg -> JPEG bytes
 
+use crate::config::settings::JpegEscalation;
 use crate::error::PdfMaskError;
-use image::{DynamicImage, GrayImage, RgbImage, RgbaImage};
+use image::{DynamicImage, GrayImage, ImageFormat, Luma, RgbImage, RgbaImage};
 use std::io::Cursor;
+use tracing::debug;
 
 /// Encode raw RGBA pixel data to JPEG bytes.
 ///
@@ -78,3 +80,193 @@ pub fn encode_gray_to_jpeg(gray: &GrayImage, quality: u8) -> crate::error::Resul
 
     Ok(buf.into_inner())
 }
+
+/// Encode an RGB image to JPEG, raising the quality while the result is too lossy.
+///
+/// Starting at `quality`, the image is re-encoded `escalation.step` higher each
+/// time the PSNR of the decoded JPEG against `rgb` is below
+/// `escalation.min_psnr`, up to `escalation.max_quality`. A requested quality
+/// above the cap is never lowered. Returns the JPEG bytes and the quality used.
+pub fn encode_rgb_to_jpeg_escalated(
+    rgb: &RgbImage,
+    quality: u8,
+    escalation: &JpegEscalation,
+) -> crate::error::Result<(Vec<u8>, u8)> {
+    escalate_quality(
+        quality,
+        escalation,
+        |q| encode_rgb_to_jpeg(rgb, q),
+        |jpeg| Ok(psnr(rgb.as_raw(), decode_jpeg(jpeg)?.to_rgb8().as_raw())),
+    )
+}
+
+/// Encode a grayscale image to JPEG, raising the quality while the result is
+/// too lossy (see [`encode_rgb_to_jpeg_escalated`]).
+pub fn encode_gray_to_jpeg_escalated(
+    gray: &GrayImage,
+    quality: u8,
+    escalation: &JpegEscalation,
+) -> crate::error::Result<(Vec<u8>, u8)> {
+    escalate_quality(
+        quality,
+        escalation,
+        |q| encode_gray_to_jpeg(gray, q),
+        |jpeg| Ok(psnr(gray.as_raw(), decode_jpeg(jpeg)?.to_luma8().as_raw())),
+    )
+}
+
+/// Re-encode with `encode` at increasing quality until `measure` reports a PSNR
+/// of at least `escalation.min_psnr` or the quality cap is reached.
+fn escalate_quality(
+    quality: u8,
+    escalation: &JpegEscalation,
+    mut encode: impl FnMut(u8) -> crate::error::Result<Vec<u8>>,
+    measure: impl Fn(&[u8]) -> crate::error::Result<f64>,
+) -> crate::error::Result<(Vec<u8>, u8)> {
+    let mut quality = quality;
+    loop {
+        let jpeg = encode(quality)?;
+        let Some(min_psnr) = escalation.min_psnr else {
+            return Ok((jpeg, quality));
+        };
+        if escalation.step == 0 || quality >= escalation.max_quality.min(100) {
+            return Ok((jpeg, quality));
+        }
+        let psnr = measure(&jpeg)?;
+        if psnr >= min_psnr {
+            return Ok((jpeg, quality));
+        }
+        let next = quality
+            .saturating_add(escalation.step)
+            .min(escalation.max_quality.min(100));
+        debug!(quality, next, psnr, min_psnr, "escalating JPEG quality");
+        quality = next;
+    }
+}
+
+fn decode_jpeg(jpeg: &[u8]) -> crate::error::Result<DynamicImage> {
+    Ok(image::load_from_memory_with_format(
+        jpeg,
+        ImageFormat::Jpeg,
+    )?)
+}
+
+/// Peak signal-to-noise ratio (dB) between two 8-bit sample buffers.
+fn psnr(original: &[u8], decoded: &[u8]) -> f64 {
+    if original.is_empty() || original.len() != decoded.len() {
+        return 0.0;
+    }
+    let sum_sq: f64 = original
+        .iter()
+        .zip(decoded)
+        .map(|(&a, &b)| (a as f64 - b as f64).powi(2))
+        .sum();
+    let mse = sum_sq / original.len() as f64;
+    if mse == 0.0 {
+        return f64::INFINITY;
+    }
+    10.0 * (255.0 * 255.0 / mse).log10()
+}
+
+/// Adobe APP14 segment payload: version 100, no flags, transform 0 (CMYK stored as-is).
+const ADOBE_APP14: [u8; 12] = *b"Adobe\x00\x64\x00\x00\x00\x00\x00";
+
+/// Encode an RGB image as a 4-component CMYK JPEG for print workflows.
+///
+/// RGB is converted with the naive K-extraction formula. Following the Adobe
+/// convention the samples are stored inverted (0 = full ink) behind an APP14
+/// marker, so PDF consumers need `/Decode [1 0 1 0 1 0 1 0]`.
+///
+/// The `image` encoder cannot write CMYK, so each ink plane is encoded as a
+/// grayscale JPEG and the four entropy-coded scans are combined into a single
+/// baseline JPEG with non-interleaved scans sharing the luma tables.
+pub fn encode_rgb_to_cmyk_jpeg(rgb: &RgbImage, quality: u8) -> crate::error::Result<Vec<u8>> {
+    let (width, height) = rgb.dimensions();
+    let mut planes: [GrayImage; 4] = std::array::from_fn(|_| GrayImage::new(width, height));
+    for (x, y, pixel) in rgb.enumerate_pixels() {
+        for (plane, ink) in planes.iter_mut().zip(rgb_to_cmyk(pixel.0)) {
+            plane.put_pixel(x, y, Luma([255 - ink]));
+        }
+    }
+
+    let mut tables = Vec::new();
+    let mut scans = Vec::with_capacity(planes.len());
+    for (i, plane) in planes.iter().enumerate() {
+        let gray_jpeg = encode_gray_to_jpeg(plane, quality)?;
+        let (plane_tables, scan) = split_gray_jpeg(&gray_jpeg)?;
+        if i == 0 {
+            tables = plane_tables;
+        }
+        scans.push(scan);
+    }
+
+    let mut out = vec![0xFF, 0xD8];
+    push_segment(&mut out, 0xEE, &ADOBE_APP14);
+
+    // SOF0: 8-bit, 4 components, 1x1 sampling, quantization table 0
+    let mut frame = vec![8];
+    frame.extend_from_slice(&(height as u16).to_be_bytes());
+    frame.extend_from_slice(&(width as u16).to_be_bytes());
+    frame.push(4);
+    for id in 1..=4u8 {
+        frame.extend_from_slice(&[id, 0x11, 0]);
+    }
+    push_segment(&mut out, 0xC0, &frame);
+    out.extend_from_slice(&tables);
+
+    for (id, scan) in (1..=4u8).zip(&scans) {
+        push_segment(&mut out, 0xDA, &[1, id, 0x00, 0, 63, 0]);
+        out.extend_from_slice(scan);
+    }
+    out.extend_from_slice(&[0xFF, 0xD9]);
+    Ok(out)
+}
+
+/// Convert an RGB sample to CMYK ink amounts (0 = no ink, 255 = full ink).
+fn rgb_to_cmyk([r, g, b]: [u8; 3]) -> [u8; 4] {
+    let max = r.max(g).max(b) as u32;
+    if max == 0 {
+        return [0, 0, 0, 255];
+    }
+    let ink = |c: u8| (((max - c as u32) * 255 + max / 2) / max) as u8;
+    [ink(r), ink(g), ink(b), 255 - max as u8]
+}
+
+/// Append a marker segment (`FF marker`, big-endian length, payload).
+fn push_segment(out: &mut Vec<u8>, marker: u8, payload: &[u8]) {
+    out.extend_from_slice(&[0xFF, marker]);
+    out.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+    out.extend_from_slice(payload);
+}
+
+/// Split a single-component baseline JPEG into its DQT/DHT segments and the
+/// entropy-coded data of its only scan.
+fn split_gray_jpeg(jpeg: &[u8]) -> crate::error::Result<(Vec<u8>, Vec<u8>)> {
+    let malformed = || PdfMaskError::jpeg_encode("unexpected grayscale JPEG layout");
+    let mut tables = Vec::new();
+    let mut pos = 2;
+    while pos + 4 <= jpeg.len() {
+        if jpeg[pos] != 0xFF {
+            return Err(malformed());
+        }
+        let marker = jpeg[pos + 1];
+        let len = u16::from_be_bytes([jpeg[pos + 2], jpeg[pos + 3]]) as usize;
+        let end = pos + 2 + len;
+        if end > jpeg.len() {
+            return Err(malformed());
+        }
+        match marker {
+            0xDB | 0xC4 => tables.extend_from_slice(&jpeg[pos..end]),
+            0xDA => {
+                let scan_end = jpeg.len().checked_sub(2).ok_or_else(malformed)?;
+                if !jpeg.ends_with(&[0xFF, 0xD9]) || scan_end < end {
+                    return Err(malformed());
+                }
+                return Ok((tables, jpeg[end..scan_end].to_vec()));
+            }
+            _ => {}
+        }
+        pos = end;
+    }
+    Err(malformed())
+}
@@ -1,5 +1,4 @@
 pub mod compositor;
-#[cfg(feature = "mrc")]
 pub mod jbig2;
 pub mod jpeg;
 #[cfg(feature = "mrc")]
@@ -8,7 +7,9 @@ pub mod segmenter;
 use std::collections::HashMap;
 
 use crate::config::job::ColorMode;
-use crate::pdf::content_stream::BBox;
+#[cfg(feature = "mrc")]
+use crate::config::job::OutputColorSpace;
+use crate::pdf::content_stream::{BBox, Matrix};
 
 #[cfg(feature = "mrc")]
 #[derive(Debug)]
@@ -21,6 +22,8 @@ pub struct MrcLayers {
     pub page_width_pts: f64,
     pub page_height_pts: f64,
     pub color_mode: ColorMode,
+    /// 背景・前景JPEGの色空間（CMYKはRGBモードでのみ使われる）
+    pub output_colorspace: OutputColorSpace,
 }
 
 /// JBIG2マスクのみ（BWモード用）
@@ -40,6 +43,24 @@ pub struct SkipData {
     pub page_index: u32,
 }
 
+/// 白紙ページを置き換える空ページのデータ
+#[derive(Debug)]
+pub struct BlankData {
+    pub page_index: u32,
+    pub page_width_pts: f64,
+    pub page_height_pts: f64,
+    /// 空ページをMediaBoxの白塗りだけで描画する（minimize_blank）
+    pub fill_white: bool,
+}
+
+/// 前回の出力PDFから再利用するページのデータ（incrementalモード）
+#[derive(Debug)]
+pub struct ReusedData {
+    pub page_index: u32,
+    /// 前回の出力PDFでのページ番号（1始まり）
+    pub prior_page_num: u32,
+}
+
 /// テキスト領域のクロップ結果
 #[derive(Debug)]
 pub struct TextRegionCrop {
@@ -47,6 +68,9 @@ pub struct TextRegionCrop {
     pub bbox_points: BBox,
     pub pixel_width: u32,
     pub pixel_height: u32,
+    /// 回転したテキストの配置行列（単位正方形→ページ座標）。
+    /// Noneなら`bbox_points`に軸平行に配置する。Someのとき`bbox_points`はその外接矩形
+    pub placement: Option<Matrix>,
 }
 
 /// 画像XObjectの変更内容
@@ -56,6 +80,15 @@ pub struct ImageModification {
     pub filter: String,
     pub color_space: String,
     pub bits_per_component: u8,
+    /// 差し替え後の/Width(px)
+    pub width: u32,
+    /// 差し替え後の/Height(px)
+    pub height: u32,
+    /// 白で塗りつぶした領域（ページ座標。画像の配置範囲に切り詰め済み）
+    pub redacted_regions: Vec<BBox>,
+    /// 画像の/SMaskとして出力するアルファ（8bit DeviceGray、FlateDecode圧縮済み）。
+    /// JPXの/SMaskInDataを再エンコード後も保つために使う
+    pub smask: Option<Vec<u8>>,
 }
 
 /// テキスト選択的ラスタライズの処理結果
@@ -83,4 +116,63 @@ pub enum PageOutput {
     Skip(SkipData),
     /// テキストのみ画像化、画像XObjectは保持
     TextMasked(TextMaskedData),
+    /// 白紙と判定されたページを同サイズの空ページに置換
+    Blank(BlankData),
+    /// 元ページが前回から変わっていないため、前回の出力のページをそのままコピー
+    Reused(ReusedData),
+}
+
+/// ページ出力のレイヤー別バイト数（品質・DPIのチューニング用）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LayerSizes {
+    /// JBIG2マスク（テキストマスク処理ではテキスト領域のJBIG2の合計）
+    pub mask: usize,
+    /// 前景JPEG
+    pub fg: usize,
+    /// 背景JPEG（テキストマスク処理ではリダクションした画像の合計）
+    pub bg: usize,
+}
+
+impl LayerSizes {
+    /// 全レイヤーの合計バイト数
+    pub fn total(&self) -> usize {
+        self.mask + self.fg + self.bg
+    }
+}
+
+impl std::ops::AddAssign for LayerSizes {
+    fn add_assign(&mut self, other: Self) {
+        self.mask += other.mask;
+        self.fg += other.fg;
+        self.bg += other.bg;
+    }
+}
+
+impl PageOutput {
+    /// 出力に含まれる符号化済みレイヤーのバイト数を返す。
+    ///
+    /// 元ページをそのまま使うSkip・空ページのBlank・前回の出力を使うReusedはすべて0。
+    pub fn layer_sizes(&self) -> LayerSizes {
+        match self {
+            #[cfg(feature = "mrc")]
+            PageOutput::Mrc(layers) => LayerSizes {
+                mask: layers.mask_jbig2.len(),
+                fg: layers.foreground_jpeg.len(),
+                bg: layers.background_jpeg.len(),
+            },
+            #[cfg(feature = "mrc")]
+            PageOutput::BwMask(bw) => LayerSizes {
+                mask: bw.mask_jbig2.len(),
+                ..LayerSizes::default()
+            },
+            PageOutput::TextMasked(data) => LayerSizes {
+                mask: data.text_regions.iter().map(|r| r.jbig2_data.len()).sum(),
+                fg: 0,
+                bg: data.modified_images.values().map(|m| m.data.len()).sum(),
+            },
+            PageOutput::Skip(_) | PageOutput::Blank(_) | PageOutput::Reused(_) => {
+                LayerSizes::default()
+            }
+        }
+    }
 }
@@ -2,6 +2,7 @@
 
 use tracing::debug;
 
+use crate::config::settings::LineRemoval;
 use crate::ffi::leptonica::Pix;
 
 /// テキスト領域のピクセル座標バウンディングボックス。
@@ -153,3 +154,71 @@ pub fn segment_text_mask(rgba_data: &[u8], width: u32, height: u32) -> crate::er
         None => Pix::create(width, height, 1),
     }
 }
+
+/// Text/background separation strategy used to build the MRC text mask.
+///
+/// Implement this to plug a custom segmenter into the pipeline (see
+/// [`crate::mrc::compositor::compose_with_segmenter`] and
+/// `ProcessPageParams::segmenter`). [`LeptonicaSegmenter`] is the default.
+pub trait Segmenter: Sync {
+    /// Segment an RGBA bitmap (4 bytes per pixel) into a `width` x `height`
+    /// 1-bit mask where text is set (1).
+    fn segment_text_mask(
+        &self,
+        rgba_data: &[u8],
+        width: u32,
+        height: u32,
+    ) -> crate::error::Result<Pix>;
+
+    /// Identifies this segmenter in cache keys. Pages segmented by a custom
+    /// segmenter without a tag bypass the cache, because cached layers made
+    /// by another segmenter would be returned otherwise.
+    fn cache_tag(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// The default [`Segmenter`]: Otsu binarization and leptonica's textline
+/// mask ([`segment_text_mask`]).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LeptonicaSegmenter;
+
+impl Segmenter for LeptonicaSegmenter {
+    fn segment_text_mask(
+        &self,
+        rgba_data: &[u8],
+        width: u32,
+        height: u32,
+    ) -> crate::error::Result<Pix> {
+        segment_text_mask(rgba_data, width, height)
+    }
+}
+
+/// Remove long horizontal and/or vertical lines (table rules, underlines)
+/// from a 1-bit mask.
+///
+/// A morphological opening with a `min_length` x 1 (or 1 x `min_length`)
+/// brick keeps only runs at least `min_length` pixels long; those are then
+/// subtracted from the mask. Text strokes are much shorter and stay intact.
+///
+/// # Arguments
+/// * `mask`       - 1-bit mask (`segment_text_mask` output)
+/// * `min_length` - Shortest run, in pixels, treated as a line
+/// * `removal`    - Which directions to remove
+pub fn remove_lines(
+    mask: &Pix,
+    min_length: u32,
+    removal: LineRemoval,
+) -> crate::error::Result<Pix> {
+    let mut result = mask.leptonica_clone()?;
+    if removal.horizontal {
+        let lines = result.open_brick(min_length, 1)?;
+        result = result.subtract(&lines)?;
+    }
+    if removal.vertical {
+        let lines = result.open_brick(1, min_length)?;
+        result = result.subtract(&lines)?;
+    }
+    debug!(min_length, ?removal, "removed lines from mask");
+    Ok(result)
+}
@@ -0,0 +1,99 @@
+// tint変換を持つ名前付き色空間（DeviceN/Separation）の解析
+
+use lopdf::{Document, Object};
+
+use crate::error::PdfMaskError;
+use crate::pdf::function::PdfFunction;
+
+/// tint値をtint変換で代替色空間の色に変換する色空間（DeviceN/Separation）
+#[derive(Debug, Clone)]
+pub struct TintColorSpace {
+    /// 色成分（tint）の数
+    pub components: usize,
+    /// 代替色空間の成分数（1: Gray, 3: RGB, 4: CMYK）
+    pub alternate_components: usize,
+    tint_transform: PdfFunction,
+}
+
+impl TintColorSpace {
+    /// 色空間オブジェクトを解析する。
+    ///
+    /// `[/DeviceN names alternate tintTransform ...]`と
+    /// `[/Separation name alternate tintTransform]`以外は`Ok(None)`を返す。
+    pub fn parse(doc: &Document, obj: &Object) -> crate::error::Result<Option<Self>> {
+        let Object::Array(arr) = doc.dereference(obj)?.1 else {
+            return Ok(None);
+        };
+        let family = arr.first().and_then(|o| o.as_name().ok());
+        let components = match (family, arr.get(1).map(|o| doc.dereference(o))) {
+            (Some(b"DeviceN"), Some(Ok((_, Object::Array(names))))) => names.len(),
+            (Some(b"Separation"), Some(_)) => 1,
+            _ => return Ok(None),
+        };
+        let (Some(alternate), Some(tint_transform)) = (arr.get(2), arr.get(3)) else {
+            return Err(PdfMaskError::pdf_read(
+                "DeviceN/Separation color space is missing entries",
+            ));
+        };
+
+        Ok(Some(TintColorSpace {
+            components,
+            alternate_components: alternate_components(doc, alternate)?,
+            tint_transform: PdfFunction::parse(doc, tint_transform)?,
+        }))
+    }
+
+    /// tint値を代替色空間の色成分に変換する。
+    pub fn to_alternate(&self, tints: &[f64]) -> crate::error::Result<Vec<f64>> {
+        if tints.len() != self.components {
+            return Err(PdfMaskError::content_stream(format!(
+                "color space takes {} tint(s), got {}",
+                self.components,
+                tints.len()
+            )));
+        }
+        let color = self.tint_transform.evaluate(tints)?;
+        if color.len() != self.alternate_components {
+            return Err(PdfMaskError::pdf_read(format!(
+                "tint transform returned {} component(s), alternate space has {}",
+                color.len(),
+                self.alternate_components
+            )));
+        }
+        Ok(color)
+    }
+}
+
+/// 代替色空間の成分数を返す（Device系、Cal系、ICCBased）。
+fn alternate_components(doc: &Document, obj: &Object) -> crate::error::Result<usize> {
+    let obj = doc.dereference(obj)?.1;
+    let (family, arr) = match obj {
+        Object::Name(name) => (name.as_slice(), None),
+        Object::Array(arr) => match arr.first().and_then(|o| o.as_name().ok()) {
+            Some(name) => (name, Some(arr)),
+            None => return Err(PdfMaskError::pdf_read("invalid alternate color space")),
+        },
+        _ => return Err(PdfMaskError::pdf_read("invalid alternate color space")),
+    };
+    match family {
+        b"DeviceGray" | b"CalGray" => Ok(1),
+        b"DeviceRGB" | b"CalRGB" => Ok(3),
+        b"DeviceCMYK" => Ok(4),
+        b"ICCBased" => {
+            let stream = arr
+                .and_then(|arr| arr.get(1))
+                .map(|o| doc.dereference(o))
+                .transpose()?
+                .and_then(|(_, o)| o.as_stream().ok())
+                .ok_or_else(|| PdfMaskError::pdf_read("ICCBased without a profile stream"))?;
+            match stream.dict.get(b"N").and_then(Object::as_i64) {
+                Ok(n @ (1 | 3 | 4)) => Ok(n as usize),
+                _ => Err(PdfMaskError::pdf_read("ICCBased profile with invalid /N")),
+            }
+        }
+        other => Err(PdfMaskError::pdf_read(format!(
+            "unsupported alternate color space: {}",
+            String::from_utf8_lossy(other)
+        ))),
+    }
+}
@@ -0,0 +1,329 @@
+// コンテンツストリームの逐次字句解析
+//
+// lopdfの`Content::decode`は全オペレーションを`Vec<Operation>`として保持するため、
+// 巨大なコンテンツストリームではメモリを大きく消費する。ここでは元のバイト列を
+// 先頭から走査し、オペレーション（オペランド列 + オペレータ）ごとのバイト範囲だけを返す。
+
+use crate::error::PdfMaskError;
+
+/// 1つのオペレーションが占めるバイト範囲
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct OperationSpan<'a> {
+    /// 最初のオペランド（無ければオペレータ）の開始位置
+    pub start: usize,
+    /// オペレータの終了位置（排他的）
+    pub end: usize,
+    /// オペレータ。インライン画像（BI … ID … EI）は全体で1つの`BI`になる
+    pub operator: &'a [u8],
+}
+
+/// コンテンツストリームのオペレーションを先頭から順に返すイテレータ。
+///
+/// オペランドの値は解釈せず、文字列・配列・辞書の区切りだけを追跡する。
+/// オペレータの無い末尾のオペランドは無視する。
+pub(crate) struct OperationSpans<'a> {
+    data: &'a [u8],
+    pos: usize,
+    failed: bool,
+}
+
+impl<'a> OperationSpans<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        OperationSpans {
+            data,
+            pos: 0,
+            failed: false,
+        }
+    }
+
+    fn peek(&self, offset: usize) -> Option<u8> {
+        self.data.get(self.pos + offset).copied()
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        while let Some(b) = self.peek(0) {
+            if is_whitespace(b) {
+                self.pos += 1;
+            } else if b == b'%' {
+                while self.peek(0).is_some_and(|b| b != b'\r' && b != b'\n') {
+                    self.pos += 1;
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// 通常文字の並び（数値・キーワード・名前の本体）を読み飛ばして返す。
+    fn read_regular(&mut self) -> &'a [u8] {
+        let start = self.pos;
+        while self.peek(0).is_some_and(is_regular) {
+            self.pos += 1;
+        }
+        &self.data[start..self.pos]
+    }
+
+    /// `(`から対応する`)`までを読み飛ばす（括弧のネストとエスケープを考慮）。
+    fn skip_literal_string(&mut self) -> crate::error::Result<()> {
+        let mut depth = 0usize;
+        while let Some(b) = self.peek(0) {
+            self.pos += 1;
+            match b {
+                b'\\' => self.pos += 1,
+                b'(' => depth += 1,
+                b')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                _ => {}
+            }
+        }
+        Err(PdfMaskError::content_stream(
+            "unterminated literal string in content stream",
+        ))
+    }
+
+    /// `<`から`>`までの16進文字列を読み飛ばす。
+    fn skip_hex_string(&mut self) -> crate::error::Result<()> {
+        match self.data[self.pos..].iter().position(|&b| b == b'>') {
+            Some(offset) => {
+                self.pos += offset + 1;
+                Ok(())
+            }
+            None => Err(PdfMaskError::content_stream(
+                "unterminated hex string in content stream",
+            )),
+        }
+    }
+
+    /// `ID`の直後からインライン画像データを読み飛ばし、`EI`の直後へ進む。
+    ///
+    /// データ中の`EI`と区別するため、前後が空白（または末尾・区切り文字）のものを終端とみなす。
+    fn skip_inline_image_data(&mut self) -> crate::error::Result<()> {
+        // IDの後の空白1文字はデータに含まれない
+        if self.peek(0).is_some_and(is_whitespace) {
+            self.pos += 1;
+        }
+        let data = &self.data[self.pos..];
+        let end = (0..data.len().saturating_sub(1)).find(|&i| {
+            data[i] == b'E'
+                && data[i + 1] == b'I'
+                && (i == 0 || is_whitespace(data[i - 1]))
+                && data.get(i + 2).is_none_or(|&b| !is_regular(b))
+        });
+        match end {
+            Some(i) => {
+                self.pos += i + 2;
+                Ok(())
+            }
+            None => Err(PdfMaskError::content_stream(
+                "inline image without EI in content stream",
+            )),
+        }
+    }
+
+    /// `BI`の直後から画像辞書・`ID`・画像データ・`EI`までを読み飛ばす。
+    fn skip_inline_image(&mut self) -> crate::error::Result<()> {
+        loop {
+            self.skip_whitespace_and_comments();
+            match self.peek(0) {
+                None => {
+                    return Err(PdfMaskError::content_stream(
+                        "inline image without ID in content stream",
+                    ));
+                }
+                Some(b'(') => self.skip_literal_string()?,
+                Some(b'<') if self.peek(1) != Some(b'<') => self.skip_hex_string()?,
+                Some(b) if is_regular(b) => {
+                    if self.read_regular() == b"ID" {
+                        return self.skip_inline_image_data();
+                    }
+                }
+                Some(_) => self.pos += 1,
+            }
+        }
+    }
+
+    /// 次のオペレーションを読む。
+    fn next_operation(&mut self) -> crate::error::Result<Option<OperationSpan<'a>>> {
+        let mut start = None;
+        // 配列・辞書のネスト深度（中の通常文字列はオペランドの一部）
+        let mut nesting = 0usize;
+        loop {
+            self.skip_whitespace_and_comments();
+            let Some(b) = self.peek(0) else {
+                return Ok(None);
+            };
+            let token_start = self.pos;
+            start.get_or_insert(token_start);
+            match b {
+                b'(' => self.skip_literal_string()?,
+                b'<' if self.peek(1) == Some(b'<') => {
+                    self.pos += 2;
+                    nesting += 1;
+                }
+                b'>' if self.peek(1) == Some(b'>') => {
+                    self.pos += 2;
+                    nesting = nesting.saturating_sub(1);
+                }
+                b'<' => self.skip_hex_string()?,
+                b'[' | b'{' => {
+                    self.pos += 1;
+                    nesting += 1;
+                }
+                b']' | b'}' => {
+                    self.pos += 1;
+                    nesting = nesting.saturating_sub(1);
+                }
+                b'/' => {
+                    self.pos += 1;
+                    self.read_regular();
+                }
+                b')' | b'>' => {
+                    return Err(PdfMaskError::content_stream(format!(
+                        "unexpected '{}' at offset {} in content stream",
+                        b as char, token_start
+                    )));
+                }
+                _ => {
+                    let token = self.read_regular();
+                    if nesting > 0 || is_operand_keyword(token) {
+                        continue;
+                    }
+                    if token == b"BI" {
+                        self.skip_inline_image()?;
+                    }
+                    return Ok(start.map(|start| OperationSpan {
+                        start,
+                        end: self.pos,
+                        operator: token,
+                    }));
+                }
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for OperationSpans<'a> {
+    type Item = crate::error::Result<OperationSpan<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let result = self.next_operation().transpose();
+        if matches!(result, Some(Err(_))) {
+            self.failed = true;
+        }
+        result
+    }
+}
+
+/// コンテンツストリームをオペレーションの区切りで、各`max_bytes`以下の断片に分ける。
+///
+/// 断片を順に連結すると元のバイト列に戻る。1つで`max_bytes`を超えるオペレーションは
+/// そのまま1つの断片にする。
+pub(crate) fn split_at_operations(
+    data: &[u8],
+    max_bytes: usize,
+) -> crate::error::Result<Vec<&[u8]>> {
+    let mut chunks = Vec::new();
+    let mut chunk_start = 0;
+    let mut last_end = 0;
+    for span in OperationSpans::new(data) {
+        let span = span?;
+        if span.end - chunk_start > max_bytes && last_end > chunk_start {
+            chunks.push(&data[chunk_start..last_end]);
+            chunk_start = last_end;
+        }
+        last_end = span.end;
+    }
+    if chunk_start < data.len() {
+        chunks.push(&data[chunk_start..]);
+    }
+    Ok(chunks)
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\r' | b'\n' | b'\x0c' | b'\0')
+}
+
+fn is_delimiter(b: u8) -> bool {
+    matches!(
+        b,
+        b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%'
+    )
+}
+
+fn is_regular(b: u8) -> bool {
+    !is_whitespace(b) && !is_delimiter(b)
+}
+
+/// オペレータではなくオペランドになるトークン（数値・真偽値・null）か。
+fn is_operand_keyword(token: &[u8]) -> bool {
+    matches!(token, b"true" | b"false" | b"null")
+        || (token.iter().any(u8::is_ascii_digit)
+            && token
+                .iter()
+                .all(|b| b.is_ascii_digit() || matches!(b, b'+' | b'-' | b'.')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn operators(data: &[u8]) -> Vec<String> {
+        OperationSpans::new(data)
+            .map(|op| String::from_utf8_lossy(op.expect("lex").operator).into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_spans_cover_operands_and_operator() {
+        let data = b"q 1 0 0 1 10 20 cm\n/Im1 Do Q";
+        let spans: Vec<_> = OperationSpans::new(data)
+            .collect::<crate::error::Result<_>>()
+            .expect("lex");
+        let texts: Vec<&[u8]> = spans.iter().map(|s| &data[s.start..s.end]).collect();
+        assert_eq!(
+            texts,
+            vec![&b"q"[..], b"1 0 0 1 10 20 cm", b"/Im1 Do", b"Q"]
+        );
+    }
+
+    #[test]
+    fn test_strings_arrays_and_comments_are_operands() {
+        let data = b"BT (a\\) ET \\( (ET)) Tj [(ET) -120 <4554>] TJ % ET q\n<< /MCID 0 >> BDC ET";
+        assert_eq!(operators(data), vec!["BT", "Tj", "TJ", "BDC", "ET"]);
+    }
+
+    #[test]
+    fn test_inline_image_is_one_operation() {
+        let data = b"q BI /W 2 /H 1 /BPC 8 /CS /G ID \x00EI\xff EI Q";
+        assert_eq!(operators(data), vec!["q", "BI", "Q"]);
+    }
+
+    #[test]
+    fn test_split_at_operations_keeps_operations_whole() {
+        let data = b"q 1 0 0 1 10 20 cm /Im1 Do Q (a b c) Tj";
+        let chunks = split_at_operations(data, 20).expect("split");
+        assert_eq!(chunks.concat(), data.to_vec());
+        assert_eq!(
+            chunks,
+            vec![&b"q 1 0 0 1 10 20 cm"[..], b" /Im1 Do Q", b" (a b c) Tj"]
+        );
+        // 上限より長いオペレーションは分けない
+        let chunks = split_at_operations(data, 4).expect("split");
+        assert_eq!(chunks[1], b" 1 0 0 1 10 20 cm");
+        assert_eq!(chunks.concat(), data.to_vec());
+    }
+
+    #[test]
+    fn test_unterminated_string_errors() {
+        let mut spans = OperationSpans::new(b"(abc Tj");
+        assert!(spans.next().expect("item").is_err());
+        assert!(spans.next().is_none());
+    }
+}
@@ -1,11 +1,17 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+
 use lopdf::content::Content;
 use tracing::debug;
 
+use crate::pdf::color_space::TintColorSpace;
+use crate::pdf::content_lexer::OperationSpans;
+
 /// 6要素アフィン変換行列 [a, b, c, d, e, f]
 /// PDF仕様: [ a b 0 ]
 ///          [ c d 0 ]
 ///          [ e f 1 ]
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct Matrix {
     pub a: f64,
     pub b: f64,
@@ -39,6 +45,32 @@ impl Matrix {
             f: self.e * other.b + self.f * other.d + other.f,
         }
     }
+
+    /// 逆行列を返す。特異行列（面積0に潰す変換）ならNone。
+    pub fn inverse(&self) -> Option<Matrix> {
+        let det = self.a * self.d - self.b * self.c;
+        if det.abs() < f64::EPSILON {
+            return None;
+        }
+        Some(Matrix {
+            a: self.d / det,
+            b: -self.b / det,
+            c: -self.c / det,
+            d: self.a / det,
+            e: (self.c * self.f - self.d * self.e) / det,
+            f: (self.b * self.e - self.a * self.f) / det,
+        })
+    }
+
+    /// 座標軸を軸方向に写す（拡大縮小・反転・90度単位の回転だけの）行列か。
+    ///
+    /// 90度の回転でも三角関数の丸めで0にならない成分があるため、
+    /// 行列の大きさに対する相対誤差で判定する。
+    pub fn is_axis_aligned(&self) -> bool {
+        let tolerance = 1e-6 * (self.a.abs() + self.b.abs() + self.c.abs() + self.d.abs());
+        (self.b.abs() <= tolerance && self.c.abs() <= tolerance)
+            || (self.a.abs() <= tolerance && self.d.abs() <= tolerance)
+    }
 }
 
 /// CTM（Current Transformation Matrix）スタック管理。
@@ -56,6 +88,11 @@ impl CtmStack {
         }
     }
 
+    /// 指定の行列を初期CTMとするスタックを作成する（Form XObjectの走査用）。
+    pub(crate) fn with_initial(ctm: Matrix) -> Self {
+        Self { stack: vec![ctm] }
+    }
+
     /// 現在のCTMをスタックにpush（qオペレータ）。
     pub(crate) fn push(&mut self) {
         let current = self.stack.last().cloned().unwrap_or_else(Matrix::identity);
@@ -113,8 +150,67 @@ pub struct ImagePlacement {
     pub name: String,
     /// 描画時のCTM
     pub ctm: Matrix,
-    /// CTMから計算したBBox
+    /// CTMから計算したBBox（Form内の配置はフォームの/BBoxでクリップ済み）
+    pub bbox: BBox,
+    /// このXObjectを描いたForm XObjectの名前（外側から順）。ページ直下なら空。
+    /// `name`はこの最も内側のフォームのリソースでの名前になる
+    pub form_path: Vec<String>,
+    /// 描画時にクリッピングパス（W/W*）か半透明になりうるExtGStateが有効だった。
+    /// その場合、描画は`bbox`全体を不透明に覆うとは限らない
+    pub partial_cover: bool,
+}
+
+/// 白色fill判定でコンテンツストリームから参照されるリソース。
+#[derive(Debug, Clone, Default)]
+pub struct FillResources {
+    /// 色空間リソース名 → tint変換を持つ色空間（DeviceN/Separation）
+    pub color_spaces: HashMap<String, TintColorSpace>,
+    /// ExtGStateリソース名 → 塗りの不透明度に関わるエントリ
+    pub ext_g_states: HashMap<String, ExtGStateOpacity>,
+    /// XObjectリソース名 → Form XObject
+    pub forms: HashMap<String, FormXObject>,
+}
+
+/// ExtGStateのうち塗りの不透明度に関わるエントリ（指定が無いものは`None`）。
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ExtGStateOpacity {
+    /// /ca（塗りの定数アルファ）
+    pub fill_alpha: Option<f64>,
+    /// /SMaskが/None以外
+    pub soft_mask: Option<bool>,
+    /// /BMがNormal（Compatible）以外
+    pub blend: Option<bool>,
+}
+
+/// 白色fill判定のために読み込んだForm XObject。
+#[derive(Debug, Clone)]
+pub struct FormXObject {
+    /// デコード済みのコンテンツストリーム
+    pub content: Vec<u8>,
+    /// /Matrix（フォーム空間 → 呼び出し側のユーザ空間）
+    pub matrix: Matrix,
+    /// /BBox（フォーム空間、描画はこの範囲にクリップされる）
     pub bbox: BBox,
+    /// /Group /S /Transparency を宣言している
+    pub transparency_group: bool,
+    /// フォーム自身の/Resources
+    pub resources: FillResources,
+}
+
+impl FormXObject {
+    /// `ctm`の時点でDoしたときの、フォーム内容の実効CTM（`/Matrix × ctm`）と、
+    /// /BBoxによるクリップ領域（呼び出し側の空間）を返す。
+    pub fn effective_transform(&self, ctm: &Matrix) -> (Matrix, BBox) {
+        let form_ctm = self.matrix.multiply(ctm);
+        let clip = rect_to_bbox(
+            &form_ctm,
+            self.bbox.x_min,
+            self.bbox.y_min,
+            self.bbox.x_max - self.bbox.x_min,
+            self.bbox.y_max - self.bbox.y_min,
+        );
+        (form_ctm, clip)
+    }
 }
 
 /// コンテンツストリームを解析し、全XObjectの配置情報を抽出する。
@@ -122,26 +218,193 @@ pub struct ImagePlacement {
 /// CTMスタック(q/Q)を追跡し、cmオペレータでCTMを更新する。
 /// DoオペレータでXObject名とその時点のCTM・BBoxを記録する。
 /// Image XObjectだけでなくForm XObjectも含む全XObjectの配置を返す。
+/// Form XObjectの中には立ち入らない（[`extract_xobject_placements_with_resources`]を参照）。
 pub fn extract_xobject_placements(
     content_bytes: &[u8],
 ) -> crate::error::Result<Vec<ImagePlacement>> {
+    extract_xobject_placements_with_resources(content_bytes, &FillResources::default())
+}
+
+/// [`extract_xobject_placements`] と同じだが、`resources`のForm XObjectの中も走査する。
+///
+/// フォーム内で描かれたXObjectは、フォームの/Matrixと呼び出し時のCTMを合成した
+/// ページ座標の配置で返し、`form_path`にフォーム名を記録する。フォーム自身の配置も含む。
+pub fn extract_xobject_placements_with_resources(
+    content_bytes: &[u8],
+    resources: &FillResources,
+) -> crate::error::Result<Vec<ImagePlacement>> {
+    let mut placements: Vec<ImagePlacement> = Vec::new();
+    let mut forms_scanned = 0;
+    collect_xobject_placements(
+        content_bytes,
+        resources,
+        &[],
+        &mut forms_scanned,
+        &mut placements,
+    )?;
+    debug!(count = placements.len(), "extracted XObject placements");
+    Ok(placements)
+}
+
+/// [`extract_xobject_placements_with_resources`] と同じだが、配置を`orientation`の
+/// 表示上の座標（/Rotateを適用した向き）で返す。
+pub fn extract_xobject_placements_visual(
+    content_bytes: &[u8],
+    resources: &FillResources,
+    orientation: &PageOrientation,
+) -> crate::error::Result<Vec<ImagePlacement>> {
+    let to_visual = orientation.to_visual_matrix();
+    let mut placements = extract_xobject_placements_with_resources(content_bytes, resources)?;
+    for placement in &mut placements {
+        placement.ctm = placement.ctm.multiply(&to_visual);
+        placement.bbox = orientation.to_visual(&placement.bbox);
+    }
+    Ok(placements)
+}
+
+/// ページの表示向き（/Rotate）と表示領域。ページ空間と表示上の座標を相互に変換する。
+///
+/// 表示上の座標は、ビューアで見た向きのまま左下隅を`origin`に置いた座標系。
+/// /Rotateが0なら表示上の座標はページ空間と一致する。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageOrientation {
+    /// 時計回りの回転角（0/90/180/270に正規化済み）
+    pub rotate: i64,
+    /// 表示領域の左下隅（ページ座標）
+    pub origin: (f64, f64),
+    /// 回転前の表示領域の幅（pt）
+    pub width: f64,
+    /// 回転前の表示領域の高さ（pt）
+    pub height: f64,
+}
+
+impl PageOrientation {
+    /// ページ空間の点を表示上の座標に写す。
+    fn point_to_visual(&self, x: f64, y: f64) -> (f64, f64) {
+        let (ox, oy) = self.origin;
+        let (u, v) = (x - ox, y - oy);
+        let (u, v) = match self.rotate {
+            90 => (v, self.width - u),
+            180 => (self.width - u, self.height - v),
+            270 => (self.height - v, u),
+            _ => (u, v),
+        };
+        (u + ox, v + oy)
+    }
+
+    /// 表示上の座標の点をページ空間に戻す。
+    fn point_from_visual(&self, x: f64, y: f64) -> (f64, f64) {
+        let (ox, oy) = self.origin;
+        let (u, v) = (x - ox, y - oy);
+        let (u, v) = match self.rotate {
+            90 => (self.width - v, u),
+            180 => (self.width - u, self.height - v),
+            270 => (v, self.height - u),
+            _ => (u, v),
+        };
+        (u + ox, v + oy)
+    }
+
+    /// ページ空間 → 表示上の座標のアフィン変換行列。
+    pub fn to_visual_matrix(&self) -> Matrix {
+        let (e, f) = self.point_to_visual(0.0, 0.0);
+        let (ax, ay) = self.point_to_visual(1.0, 0.0);
+        let (cx, cy) = self.point_to_visual(0.0, 1.0);
+        Matrix {
+            a: ax - e,
+            b: ay - f,
+            c: cx - e,
+            d: cy - f,
+            e,
+            f,
+        }
+    }
+
+    /// ページ空間のBBoxを表示上の座標に写す。
+    pub fn to_visual(&self, bbox: &BBox) -> BBox {
+        map_bbox_corners(bbox, |x, y| self.point_to_visual(x, y))
+    }
+
+    /// 表示上の座標で指定されたBBox（利用者が見た向きで指定したリダクション矩形等）を
+    /// ページ空間に戻す。
+    pub fn from_visual(&self, bbox: &BBox) -> BBox {
+        map_bbox_corners(bbox, |x, y| self.point_from_visual(x, y))
+    }
+}
+
+/// BBoxの対角の2隅を`map`で写し、それらを囲むBBoxを返す（90°単位の回転・平行移動用）。
+fn map_bbox_corners(bbox: &BBox, map: impl Fn(f64, f64) -> (f64, f64)) -> BBox {
+    let (x0, y0) = map(bbox.x_min, bbox.y_min);
+    let (x1, y1) = map(bbox.x_max, bbox.y_max);
+    BBox {
+        x_min: x0.min(x1),
+        y_min: y0.min(y1),
+        x_max: x0.max(x1),
+        y_max: y0.max(y1),
+    }
+}
+
+/// 1つのコンテンツストリームのXObject配置を`placements`に追加する。Form XObjectでは再帰する。
+///
+/// Form内の配置はForm空間で求めてから[`FormXObject::effective_transform`]で親の空間へ写す。
+fn collect_xobject_placements(
+    content_bytes: &[u8],
+    resources: &FillResources,
+    form_path: &[String],
+    forms_scanned: &mut usize,
+    placements: &mut Vec<ImagePlacement>,
+) -> crate::error::Result<()> {
     // 空バイト列の場合、lopdfのパーサがエラーを返す可能性があるため特別扱い
     if content_bytes.is_empty() {
-        return Ok(Vec::new());
+        return Ok(());
     }
 
     let content = Content::decode(content_bytes)
         .map_err(|e| crate::error::PdfMaskError::content_stream(e.to_string()))?;
 
     let mut ctm = CtmStack::new();
-    let mut placements: Vec<ImagePlacement> = Vec::new();
+    // q/Qごとの「クリップまたは半透明の状態が有効か」
+    let mut partial_cover = vec![false];
 
     let operations: &[lopdf::content::Operation] = content.operations.as_ref();
     for op in operations {
         match op.operator.as_str() {
-            "q" => ctm.push(),
-            "Q" => ctm.pop(),
+            "q" => {
+                ctm.push();
+                partial_cover.push(partial_cover.last().copied().unwrap_or(false));
+            }
+            "Q" => {
+                ctm.pop();
+                if partial_cover.len() > 1 {
+                    partial_cover.pop();
+                }
+            }
             "cm" => ctm.apply_cm(&op.operands)?,
+            "W" | "W*" => {
+                if let Some(state) = partial_cover.last_mut() {
+                    *state = true;
+                }
+            }
+            "gs" => {
+                // リソースに無いExtGStateは半透明とみなす
+                let translucent = op
+                    .operands
+                    .first()
+                    .and_then(|operand| operand.as_name().ok())
+                    .and_then(|name| {
+                        resources
+                            .ext_g_states
+                            .get(String::from_utf8_lossy(name).as_ref())
+                    })
+                    .is_none_or(|gs| {
+                        gs.fill_alpha.is_some_and(|alpha| alpha < 1.0)
+                            || gs.soft_mask == Some(true)
+                            || gs.blend == Some(true)
+                    });
+                if translucent && let Some(state) = partial_cover.last_mut() {
+                    *state = true;
+                }
+            }
             "Do" => {
                 // XObjectを描画: 名前とCTMを記録
                 if let Some(operand) = op.operands.first() {
@@ -151,11 +414,54 @@ pub fn extract_xobject_placements(
                     let name = String::from_utf8_lossy(name_bytes).into_owned();
                     let current_ctm = ctm.current();
                     let bbox = ctm_to_bbox(&current_ctm);
+                    let current_partial = partial_cover.last().copied().unwrap_or(false);
                     placements.push(ImagePlacement {
-                        name,
-                        ctm: current_ctm,
+                        name: name.clone(),
+                        ctm: current_ctm.clone(),
                         bbox,
+                        form_path: form_path.to_vec(),
+                        partial_cover: current_partial,
                     });
+
+                    if let Some(form) = resources.forms.get(&name) {
+                        if form_path.len() >= MAX_FORM_DEPTH || *forms_scanned >= MAX_FORM_SCANS {
+                            debug!(
+                                depth = form_path.len(),
+                                "form XObject limit reached; not scanned"
+                            );
+                        } else {
+                            *forms_scanned += 1;
+                            let mut inner_path = form_path.to_vec();
+                            inner_path.push(name.clone());
+                            let mut inner = Vec::new();
+                            collect_xobject_placements(
+                                &form.content,
+                                &form.resources,
+                                &inner_path,
+                                forms_scanned,
+                                &mut inner,
+                            )?;
+                            // 配置はForm空間で求めたので、親の空間へ写して/BBoxでクリップする。
+                            // /BBoxの外に出る配置は描画されない
+                            let (to_parent, clip) = form.effective_transform(&current_ctm);
+                            for mut placement in inner {
+                                let b = &placement.bbox;
+                                let bbox = rect_to_bbox(
+                                    &to_parent,
+                                    b.x_min,
+                                    b.y_min,
+                                    b.x_max - b.x_min,
+                                    b.y_max - b.y_min,
+                                );
+                                if let Some(clipped) = intersect_bbox(&bbox, &clip) {
+                                    placement.ctm = placement.ctm.multiply(&to_parent);
+                                    placement.bbox = clipped;
+                                    placement.partial_cover |= current_partial;
+                                    placements.push(placement);
+                                }
+                            }
+                        }
+                    }
                 }
             }
             _ => {
@@ -164,8 +470,44 @@ pub fn extract_xobject_placements(
         }
     }
 
-    debug!(count = placements.len(), "extracted XObject placements");
-    Ok(placements)
+    Ok(())
+}
+
+/// 全面配置とみなすときのページ境界との許容誤差(pt)
+const FULL_PAGE_TOLERANCE_PTS: f64 = 1.0;
+
+/// コンテンツストリームがXObjectを1つ描くだけで、その配置がページ全体（`page_box`）を
+/// 覆う場合に、そのXObject名を返す。
+///
+/// 状態の保存・変更（q/Q/cm/gs）とマーク付きコンテンツ以外のオペレータがあれば`None`。
+pub fn full_page_xobject(
+    content_bytes: &[u8],
+    page_box: &BBox,
+) -> crate::error::Result<Option<String>> {
+    if content_bytes.is_empty() {
+        return Ok(None);
+    }
+    let content = Content::decode(content_bytes)
+        .map_err(|e| crate::error::PdfMaskError::content_stream(e.to_string()))?;
+    let only_draws_xobjects = content.operations.iter().all(|op| {
+        matches!(
+            op.operator.as_str(),
+            "q" | "Q" | "cm" | "gs" | "Do" | "BMC" | "BDC" | "EMC" | "MP" | "DP"
+        )
+    });
+    if !only_draws_xobjects {
+        return Ok(None);
+    }
+
+    let placements = extract_xobject_placements(content_bytes)?;
+    let [placement] = placements.as_slice() else {
+        return Ok(None);
+    };
+    let covers = placement.bbox.x_min <= page_box.x_min + FULL_PAGE_TOLERANCE_PTS
+        && placement.bbox.y_min <= page_box.y_min + FULL_PAGE_TOLERANCE_PTS
+        && placement.bbox.x_max >= page_box.x_max - FULL_PAGE_TOLERANCE_PTS
+        && placement.bbox.y_max >= page_box.y_max - FULL_PAGE_TOLERANCE_PTS;
+    Ok(covers.then(|| placement.name.clone()))
 }
 
 /// lopdfのObjectから数値をf64として取得する。
@@ -182,7 +524,7 @@ pub(crate) fn operand_to_f64(obj: &lopdf::Object) -> crate::error::Result<f64> {
 
 /// CTMからBBoxを計算する。
 /// 単位正方形 [0,0]-[1,1] の4頂点をCTMで変換し、min/maxを取る。
-fn ctm_to_bbox(ctm: &Matrix) -> BBox {
+pub(crate) fn ctm_to_bbox(ctm: &Matrix) -> BBox {
     let corners = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)];
     let transformed: Vec<(f64, f64)> = corners
         .iter()
@@ -222,7 +564,10 @@ fn ctm_to_bbox(ctm: &Matrix) -> BBox {
 ///
 /// BTオペレータでテキストブロックが開始され、ETオペレータで終了する。
 /// このネスト深度を追跡し、深度>0の全オペレーションを除去する。
-/// 非テキストオペレーション（グラフィックス、XObject描画等）はそのまま保持する。
+/// 非テキストオペレーション（グラフィックス、XObject描画等）は元のバイト列のまま保持する。
+///
+/// オペレーション列全体を構築せず、[`OperationSpans`]で逐次走査するため、
+/// 巨大なコンテンツストリームでも追加のメモリは出力分だけで済む。
 ///
 /// # 引数
 /// * `content_bytes` - 元のコンテンツストリームバイト列
@@ -230,52 +575,126 @@ fn ctm_to_bbox(ctm: &Matrix) -> BBox {
 /// # 戻り値
 /// テキストオペレーションを除去したコンテンツストリーム
 pub fn strip_text_operators(content_bytes: &[u8]) -> crate::error::Result<Vec<u8>> {
-    // 空バイト列の場合は空を返す
+    strip_operations(content_bytes, None)
+}
+
+/// コンテンツストリームからテキストブロックと、`xobjects`に無いXObjectの描画（Do）を除去する。
+///
+/// [`strip_text_operators`]と同様に元のバイト列のまま保持し、`xobjects`に含まれる
+/// 名前の`Do`だけを残す。
+pub fn strip_text_and_xobjects(
+    content_bytes: &[u8],
+    xobjects: &[String],
+) -> crate::error::Result<Vec<u8>> {
+    strip_operations(content_bytes, Some(xobjects))
+}
+
+fn strip_operations(
+    content_bytes: &[u8],
+    xobjects: Option<&[String]>,
+) -> crate::error::Result<Vec<u8>> {
+    let mut depth = 0_u32;
+    let mut output = Vec::new();
+    let mut original = 0_usize;
+    let mut remaining = 0_usize;
+
+    for span in OperationSpans::new(content_bytes) {
+        let span = span?;
+        original += 1;
+        match span.operator {
+            // テキストブロック開始・終了（BT/ET自体も除去）
+            b"BT" => depth = depth.saturating_add(1),
+            b"ET" => depth = depth.saturating_sub(1),
+            b"Do"
+                if depth == 0
+                    && xobjects.is_some_and(|names| {
+                        !do_operand_name(&content_bytes[span.start..span.end])
+                            .is_some_and(|name| names.contains(&name))
+                    }) => {}
+            // 深度0（テキストブロック外）のオペレーションのみ保持
+            _ if depth == 0 => {
+                output.extend_from_slice(&content_bytes[span.start..span.end]);
+                output.push(b'\n');
+                remaining += 1;
+            }
+            _ => {}
+        }
+    }
+
+    debug!(original, remaining, "stripped text operators");
+    Ok(output)
+}
+
+/// `/Name Do`オペレーションのバイト列からXObject名を取り出す。
+fn do_operand_name(operation: &[u8]) -> Option<String> {
+    let content = Content::decode(operation).ok()?;
+    let op = content.operations.first()?;
+    let name = op.operands.first()?.as_name().ok()?;
+    Some(String::from_utf8_lossy(name).into_owned())
+}
+
+/// ページ全体が左右反転して描画されているかを判定する。
+///
+/// 描画オペレータ（XObject・インライン画像・パス塗り/線・シェーディング・テキスト）
+/// 実行時のCTMを追跡し、すべてが水平反転（a < 0 かつ行列式 < 0）であれば
+/// `true`を返す。ミラー出力するスキャナの`q -W 0 0 H W 0 cm /Im0 Do Q`等を想定する。
+/// 何も描画しないページは`false`。
+pub fn is_horizontally_mirrored(content_bytes: &[u8]) -> crate::error::Result<bool> {
+    const PAINT_OPS: &[&str] = &[
+        "Do", "BI", "sh", "f", "F", "f*", "B", "B*", "b", "b*", "S", "s", "Tj", "TJ", "'", "\"",
+    ];
+
     if content_bytes.is_empty() {
-        return Ok(Vec::new());
+        return Ok(false);
     }
 
     let content = Content::decode(content_bytes)
         .map_err(|e| crate::error::PdfMaskError::content_stream(e.to_string()))?;
 
-    let mut depth = 0_u32;
-    let mut filtered_operations = Vec::new();
-
+    let mut ctm = CtmStack::new();
+    let mut painted = false;
     for op in &content.operations {
         match op.operator.as_str() {
-            "BT" => {
-                // テキストブロック開始
-                depth = depth.saturating_add(1);
-                // BTオペレータ自体も除去
-            }
-            "ET" => {
-                // テキストブロック終了
-                depth = depth.saturating_sub(1);
-                // ETオペレータ自体も除去
-            }
-            _ => {
-                // 深度0（テキストブロック外）のオペレーションのみ保持
-                if depth == 0 {
-                    filtered_operations.push(op.clone());
+            "q" => ctm.push(),
+            "Q" => ctm.pop(),
+            "cm" => ctm.apply_cm(&op.operands)?,
+            operator if PAINT_OPS.contains(&operator) => {
+                let m = ctm.current();
+                if m.a >= 0.0 || m.a * m.d - m.b * m.c >= 0.0 {
+                    return Ok(false);
                 }
+                painted = true;
             }
+            _ => {}
         }
     }
 
-    debug!(
-        original = content.operations.len(),
-        remaining = filtered_operations.len(),
-        "stripped text operators"
-    );
+    debug!(mirrored = painted, "checked page mirroring");
+    Ok(painted)
+}
 
-    // 再エンコード
-    let filtered_content = Content {
-        operations: filtered_operations,
-    };
+/// コンテンツストリームがシェーディング（`sh`オペレータ）を描画するかを判定する。
+///
+/// グラデーションはラスタライズしてMRC分解するとバンディングが出やすいため、
+/// 全面MRCになるページで警告するために使う。
+pub fn contains_shading(content_bytes: &[u8]) -> crate::error::Result<bool> {
+    if content_bytes.is_empty() {
+        return Ok(false);
+    }
 
-    filtered_content
-        .encode()
-        .map_err(|e| crate::error::PdfMaskError::content_stream(e.to_string()))
+    let content = Content::decode(content_bytes)
+        .map_err(|e| crate::error::PdfMaskError::content_stream(e.to_string()))?;
+    Ok(content.operations.iter().any(|op| op.operator == "sh"))
+}
+
+/// コンテンツストリームがテキストオブジェクト（`BT`オペレータ）を含むかを判定する。
+pub fn contains_text(content_bytes: &[u8]) -> crate::error::Result<bool> {
+    for span in OperationSpans::new(content_bytes) {
+        if span?.operator == b"BT" {
+            return Ok(true);
+        }
+    }
+    Ok(false)
 }
 
 /// ピクセル座標をPDFページ座標（ポイント）に変換する。
@@ -299,6 +718,37 @@ pub fn pixel_to_page_coords(
     page_height_pts: f64,
     bitmap_width_px: u32,
     bitmap_height_px: u32,
+) -> crate::error::Result<BBox> {
+    pixel_to_page_coords_with_origin(
+        pixel_bbox,
+        (0.0, 0.0),
+        page_width_pts,
+        page_height_pts,
+        bitmap_width_px,
+        bitmap_height_px,
+    )
+}
+
+/// ピクセル座標を、MediaBox左下隅を原点とするPDFページ座標（ポイント）に変換する。
+///
+/// `[-10 -10 605 802]`のようにMediaBoxの原点が(0, 0)でない場合、
+/// ビットマップ左下隅はページ座標の`origin`に対応する。
+///
+/// # Arguments
+/// * `pixel_bbox` - ピクセル座標のバウンディングボックス
+/// * `origin` - MediaBox左下隅のページ座標 (x, y)
+/// * `page_width_pts` - ページ幅（ポイント）
+/// * `page_height_pts` - ページ高さ（ポイント）
+/// * `bitmap_width_px` - ビットマップ幅（ピクセル）
+/// * `bitmap_height_px` - ビットマップ高さ（ピクセル）
+#[cfg(feature = "mrc")]
+pub fn pixel_to_page_coords_with_origin(
+    pixel_bbox: &crate::mrc::segmenter::PixelBBox,
+    origin: (f64, f64),
+    page_width_pts: f64,
+    page_height_pts: f64,
+    bitmap_width_px: u32,
+    bitmap_height_px: u32,
 ) -> crate::error::Result<BBox> {
     if bitmap_width_px == 0 || bitmap_height_px == 0 {
         return Err(crate::error::PdfMaskError::content_stream(
@@ -321,11 +771,12 @@ pub fn pixel_to_page_coords(
     let y_max = page_height_pts - (pixel_bbox.y as f64 * scale_y);
     let y_min = page_height_pts - (pixel_bottom as f64 * scale_y);
 
+    let (origin_x, origin_y) = origin;
     Ok(BBox {
-        x_min,
-        y_min,
-        x_max,
-        y_max,
+        x_min: x_min + origin_x,
+        y_min: y_min + origin_y,
+        x_max: x_max + origin_x,
+        y_max: y_max + origin_y,
     })
 }
 
@@ -334,91 +785,123 @@ pub fn pixel_to_page_coords(
 /// `text_state::FillColor`（色値を保持するenum）とは異なり、
 /// 白色判定結果のみを保持する軽量トラッカー。
 #[derive(Debug, Clone)]
-struct FillColorTracker {
+struct FillColorTracker<'a> {
     /// 白色 (RGB: 1,1,1 / Gray: 1 / CMYK: 0,0,0,0) かどうか
     is_white: bool,
+    /// `cs`で選択された、tint変換を持つ色空間（DeviceN/Separation）
+    tint_space: Option<&'a TintColorSpace>,
+    /// 塗りの定数アルファ（ExtGStateの/ca）
+    fill_alpha: f64,
+    /// ソフトマスクが有効
+    soft_mask: bool,
+    /// ブレンドモードがNormal以外
+    blend: bool,
 }
 
-impl FillColorTracker {
+impl FillColorTracker<'_> {
     fn default_black() -> Self {
-        FillColorTracker { is_white: false }
+        FillColorTracker {
+            is_white: false,
+            tint_space: None,
+            fill_alpha: 1.0,
+            soft_mask: false,
+            blend: false,
+        }
+    }
+
+    /// 塗りが下のものを完全に覆うか（アルファ1・ソフトマスク無し・Normalブレンド）。
+    fn is_opaque(&self) -> bool {
+        self.fill_alpha >= 1.0 - 1e-6 && !self.soft_mask && !self.blend
+    }
+
+    /// `gs`オペレータでExtGStateの不透明度エントリを適用する。
+    fn apply_ext_g_state(&mut self, state: &ExtGStateOpacity) {
+        if let Some(alpha) = state.fill_alpha {
+            self.fill_alpha = alpha;
+        }
+        if let Some(soft_mask) = state.soft_mask {
+            self.soft_mask = soft_mask;
+        }
+        if let Some(blend) = state.blend {
+            self.blend = blend;
+        }
     }
 }
 
 /// fill colorオペレータに基づいてFillColorTrackerを更新する。
 ///
-/// 対象オペレータ: `rg`(RGB), `g`(Gray), `k`(CMYK), `sc`/`scn`(汎用)
-fn update_fill_color(op: &lopdf::content::Operation, tracker: &mut FillColorTracker) {
+/// 対象オペレータ: `rg`(RGB), `g`(Gray), `k`(CMYK), `cs`(色空間選択), `sc`/`scn`(汎用)
+///
+/// `color_spaces`にある色空間が`cs`で選択されていれば、`sc`/`scn`のtint値を
+/// tint変換で代替色空間の色に変換して判定する。
+fn update_fill_color<'a>(
+    op: &lopdf::content::Operation,
+    tracker: &mut FillColorTracker<'a>,
+    color_spaces: &'a HashMap<String, TintColorSpace>,
+) {
     match op.operator.as_str() {
-        "rg" => {
-            // RGB fill color: r g b rg
-            if op.operands.len() == 3
-                && let (Ok(r), Ok(g), Ok(b)) = (
-                    operand_to_f64(&op.operands[0]),
-                    operand_to_f64(&op.operands[1]),
-                    operand_to_f64(&op.operands[2]),
-                )
+        "rg" | "g" | "k" => {
+            // デバイス色空間を直接指定: r g b rg / gray g / c m y k k
+            let expected = match op.operator.as_str() {
+                "rg" => 3,
+                "g" => 1,
+                _ => 4,
+            };
+            tracker.tint_space = None;
+            if op.operands.len() == expected
+                && let Some(color) = operands_to_f64(&op.operands)
             {
-                tracker.is_white = is_white_rgb(r, g, b);
+                tracker.is_white = is_white_components(&color);
             }
         }
-        "g" => {
-            // Gray fill color: gray g
-            if op.operands.len() == 1
-                && let Ok(gray) = operand_to_f64(&op.operands[0])
-            {
-                tracker.is_white = is_white_gray(gray);
-            }
-        }
-        "k" => {
-            // CMYK fill color: c m y k k
-            if op.operands.len() == 4
-                && let (Ok(c), Ok(m), Ok(y), Ok(k)) = (
-                    operand_to_f64(&op.operands[0]),
-                    operand_to_f64(&op.operands[1]),
-                    operand_to_f64(&op.operands[2]),
-                    operand_to_f64(&op.operands[3]),
-                )
-            {
-                tracker.is_white = is_white_cmyk(c, m, y, k);
-            }
+        "cs" => {
+            // 色空間を選択すると初期色になる（Device系は黒、DeviceN/Separationはtint 1.0）
+            let name = op.operands.first().and_then(|o| o.as_name().ok());
+            tracker.tint_space =
+                name.and_then(|name| color_spaces.get(String::from_utf8_lossy(name).as_ref()));
+            tracker.is_white = tracker
+                .tint_space
+                .is_some_and(|space| is_white_tint(space, &vec![1.0; space.components]));
         }
         "sc" | "scn" => {
-            // Generic fill color: 値の数で判定
-            tracker.is_white = match op.operands.len() {
-                1 => operand_to_f64(&op.operands[0])
-                    .map(is_white_gray)
-                    .unwrap_or(false),
-                3 => {
-                    if let (Ok(r), Ok(g), Ok(b)) = (
-                        operand_to_f64(&op.operands[0]),
-                        operand_to_f64(&op.operands[1]),
-                        operand_to_f64(&op.operands[2]),
-                    ) {
-                        is_white_rgb(r, g, b)
-                    } else {
-                        false
-                    }
-                }
-                4 => {
-                    if let (Ok(c), Ok(m), Ok(y), Ok(k)) = (
-                        operand_to_f64(&op.operands[0]),
-                        operand_to_f64(&op.operands[1]),
-                        operand_to_f64(&op.operands[2]),
-                        operand_to_f64(&op.operands[3]),
-                    ) {
-                        is_white_cmyk(c, m, y, k)
-                    } else {
-                        false
-                    }
-                }
-                _ => false,
+            tracker.is_white = match (tracker.tint_space, operands_to_f64(&op.operands)) {
+                (Some(space), Some(tints)) => is_white_tint(space, &tints),
+                // Generic fill color: 値の数で判定
+                (None, Some(color)) => is_white_components(&color),
+                // パターン名などの非数値オペランド
+                (_, None) => false,
             };
         }
         _ => {}
     }
 }
 
+/// 全オペランドを数値に変換する（1つでも数値でなければ`None`）。
+fn operands_to_f64(operands: &[lopdf::Object]) -> Option<Vec<f64>> {
+    operands.iter().map(|o| operand_to_f64(o).ok()).collect()
+}
+
+/// 成分数（1: Gray, 3: RGB, 4: CMYK）に応じて白色かどうかを判定する。
+fn is_white_components(color: &[f64]) -> bool {
+    match *color {
+        [gray] => is_white_gray(gray),
+        [r, g, b] => is_white_rgb(r, g, b),
+        [c, m, y, k] => is_white_cmyk(c, m, y, k),
+        _ => false,
+    }
+}
+
+/// tint値を代替色空間の色に変換し、白色かどうかを判定する。
+fn is_white_tint(space: &TintColorSpace, tints: &[f64]) -> bool {
+    match space.to_alternate(tints) {
+        Ok(color) => is_white_components(&color),
+        Err(e) => {
+            debug!(error = %e, "tint transform failed; treating fill as non-white");
+            false
+        }
+    }
+}
+
 /// パス構築オペレータに基づいてrectバッファを更新する。
 ///
 /// `re`の場合は矩形を追加、それ以外のパスオペレータ(`m`/`l`/`c`/`v`/`y`/`h`)
@@ -444,14 +927,14 @@ fn update_path_rects(op: &lopdf::content::Operation, rects: &mut Vec<(f64, f64,
     }
 }
 
-/// 白色fill矩形のBBoxをresultsに追加する。
-fn collect_white_fill_bboxes(
-    is_white: bool,
+/// 対象のfill矩形のBBoxをresultsに追加する。
+fn collect_fill_bboxes(
+    matches: bool,
     ctm: &Matrix,
     rects: &[(f64, f64, f64, f64)],
     results: &mut Vec<BBox>,
 ) {
-    if is_white {
+    if matches {
         for &(x, y, w, h) in rects {
             results.push(rect_to_bbox(ctm, x, y, w, h));
         }
@@ -461,7 +944,7 @@ fn collect_white_fill_bboxes(
 /// コンテンツストリームから白色fill矩形の位置を抽出する。
 ///
 /// 追跡するオペレータ:
-/// - 色設定: `rg`/`g`/`k`/`sc`/`scn` (fill color)
+/// - 色設定: `rg`/`g`/`k`/`cs`/`sc`/`scn` (fill color)
 /// - パス構築: `re` (rectangle)
 /// - fill: `f`/`F`/`f*`
 /// - CTMスタック: `q`/`Q`/`cm`
@@ -472,68 +955,348 @@ fn collect_white_fill_bboxes(
 /// # Returns
 /// CTM適用済みのページ座標BBoxリスト（白色fill矩形のみ）
 pub fn extract_white_fill_rects(content_bytes: &[u8]) -> crate::error::Result<Vec<BBox>> {
-    if content_bytes.is_empty() {
-        return Ok(Vec::new());
+    extract_white_fill_rects_with_color_spaces(content_bytes, &HashMap::new())
+}
+
+/// [`extract_white_fill_rects`] と同じだが、ページリソースの名前付き色空間
+/// （DeviceN/Separation）で指定された塗り色もtint変換で解決して判定する。
+///
+/// # Arguments
+/// * `content_bytes` - コンテンツストリームのバイト列
+/// * `color_spaces` - リソース名 → tint変換を持つ色空間
+pub fn extract_white_fill_rects_with_color_spaces(
+    content_bytes: &[u8],
+    color_spaces: &HashMap<String, TintColorSpace>,
+) -> crate::error::Result<Vec<BBox>> {
+    let resources = FillResources {
+        color_spaces: color_spaces.clone(),
+        ..FillResources::default()
+    };
+    extract_white_fill_rects_with_resources(content_bytes, &resources)
+}
+
+/// [`extract_white_fill_rects`] と同じだが、ページリソースを使って
+/// 名前付き色空間・ExtGStateの不透明度・Form XObjectの中身も考慮する。
+///
+/// - アルファ1未満・ソフトマスク・Normal以外のブレンドで描かれた白は覆いとみなさない
+/// - `Do`で描かれるForm XObjectは/Matrixを適用して再帰的に走査し、/BBoxでクリップする
+/// - 透明グループ（/Group /S /Transparency）のフォームは一体として合成される。
+///   呼び出し側のアルファ等はグループ全体にかかり、グループ内は不透明な初期状態から始まる
+///
+/// # Arguments
+/// * `content_bytes` - コンテンツストリームのバイト列
+/// * `resources` - コンテンツストリームのリソース
+pub fn extract_white_fill_rects_with_resources(
+    content_bytes: &[u8],
+    resources: &FillResources,
+) -> crate::error::Result<Vec<BBox>> {
+    let rects = scan_fill_rects(content_bytes, &["f", "F", "f*"], true, resources)?;
+    debug!(count = rects.len(), "extracted white fill rects");
+    Ok(rects)
+}
+
+/// [`merge_adjacent_rects`]で座標を同じとみなす誤差(pt)
+const MERGE_EPSILON: f64 = 0.01;
+
+/// 隣接・重複していて和集合がちょうど矩形になるBBox同士を1つにまとめる。
+///
+/// 表のセルのように細かく分かれた白色fillを、覆う範囲を変えずに少数の矩形にする。
+/// 縦範囲が同じで横に接する（または重なる）組、横範囲が同じで縦に接する組、
+/// 一方が他方に含まれる組をまとめ、まとめられる組がなくなるまで繰り返す。
+pub fn merge_adjacent_rects(rects: &[BBox]) -> Vec<BBox> {
+    let mut merged = rects.to_vec();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        let mut i = 0;
+        while i < merged.len() {
+            let mut j = i + 1;
+            while j < merged.len() {
+                if let Some(union) = rect_union(&merged[i], &merged[j]) {
+                    merged[i] = union;
+                    merged.swap_remove(j);
+                    changed = true;
+                } else {
+                    j += 1;
+                }
+            }
+            i += 1;
+        }
     }
+    merged
+}
 
-    let content = Content::decode(content_bytes)
-        .map_err(|e| crate::error::PdfMaskError::content_stream(e.to_string()))?;
+/// 2つのBBoxの和集合が矩形になるならそれを返す。
+fn rect_union(a: &BBox, b: &BBox) -> Option<BBox> {
+    let close = |p: f64, q: f64| (p - q).abs() <= MERGE_EPSILON;
+    let contains = |outer: &BBox, inner: &BBox| {
+        outer.x_min <= inner.x_min + MERGE_EPSILON
+            && outer.y_min <= inner.y_min + MERGE_EPSILON
+            && inner.x_max <= outer.x_max + MERGE_EPSILON
+            && inner.y_max <= outer.y_max + MERGE_EPSILON
+    };
+    if contains(a, b) {
+        return Some(a.clone());
+    }
+    if contains(b, a) {
+        return Some(b.clone());
+    }
+    let same_rows = close(a.y_min, b.y_min) && close(a.y_max, b.y_max);
+    let same_columns = close(a.x_min, b.x_min) && close(a.x_max, b.x_max);
+    let touch_x = a.x_min <= b.x_max + MERGE_EPSILON && b.x_min <= a.x_max + MERGE_EPSILON;
+    let touch_y = a.y_min <= b.y_max + MERGE_EPSILON && b.y_min <= a.y_max + MERGE_EPSILON;
+    ((same_rows && touch_x) || (same_columns && touch_y)).then(|| BBox {
+        x_min: a.x_min.min(b.x_min),
+        y_min: a.y_min.min(b.y_min),
+        x_max: a.x_max.max(b.x_max),
+        y_max: a.y_max.max(b.y_max),
+    })
+}
 
-    let mut ctm = CtmStack::new();
-    let mut fill_color_stack: Vec<FillColorTracker> = vec![FillColorTracker::default_black()];
+/// コンテンツストリームから白以外で塗りつぶされた矩形の位置を抽出する。
+///
+/// 別ツールで赤枠などを描いたオーバーレイPDFからリダクション領域を読み取る用途。
+/// `extract_white_fill_rects`と同じオペレータを追跡し、fill+stroke
+/// (`B`/`B*`/`b`/`b*`)も塗りつぶしとして扱う。
+///
+/// # Returns
+/// CTM適用済みのページ座標BBoxリスト
+pub fn extract_filled_rects(content_bytes: &[u8]) -> crate::error::Result<Vec<BBox>> {
+    scan_fill_rects(
+        content_bytes,
+        &["f", "F", "f*", "B", "B*", "b", "b*"],
+        false,
+        &FillResources::default(),
+    )
+}
+
+/// `fill_ops`で塗りつぶされた矩形のうち、fill colorの白判定が`white`と一致するものを返す。
+///
+/// 白を探す場合は不透明な塗りだけを対象にする。
+fn scan_fill_rects(
+    content_bytes: &[u8],
+    fill_ops: &[&str],
+    white: bool,
+    resources: &FillResources,
+) -> crate::error::Result<Vec<BBox>> {
     let mut results: Vec<BBox> = Vec::new();
+    let scan = FillScan {
+        fill_ops,
+        white,
+        forms_scanned: Cell::new(0),
+    };
+    scan.run(
+        content_bytes,
+        resources,
+        Matrix::identity(),
+        FillColorTracker::default_black(),
+        0,
+        &mut results,
+    )?;
+    Ok(results)
+}
 
-    // 現在のパス上の矩形（reオペレータで蓄積、fillで一括処理）
-    let mut current_rects: Vec<(f64, f64, f64, f64)> = Vec::new();
+/// Form XObjectの再帰の上限
+pub(crate) const MAX_FORM_DEPTH: usize = 8;
+/// 1つのコンテンツストリームから走査するForm XObjectの延べ数の上限
+const MAX_FORM_SCANS: usize = 4096;
 
-    for op in &content.operations {
-        match op.operator.as_str() {
-            "q" => {
-                ctm.push();
-                let current_fill = fill_color_stack
-                    .last()
-                    .cloned()
-                    .unwrap_or_else(FillColorTracker::default_black);
-                fill_color_stack.push(current_fill);
-            }
-            "Q" => {
-                ctm.pop();
-                if fill_color_stack.len() > 1 {
-                    fill_color_stack.pop();
+/// [`scan_fill_rects`] の走査条件。
+struct FillScan<'o> {
+    fill_ops: &'o [&'o str],
+    white: bool,
+    /// 走査したForm XObjectの延べ数（同じフォームの繰り返し描画も数える）
+    forms_scanned: Cell<usize>,
+}
+
+impl FillScan<'_> {
+    /// 1つのコンテンツストリームを走査する。Form XObjectでは再帰する。
+    fn run<'a>(
+        &self,
+        content_bytes: &[u8],
+        resources: &'a FillResources,
+        initial_ctm: Matrix,
+        initial_fill: FillColorTracker<'a>,
+        depth: usize,
+        results: &mut Vec<BBox>,
+    ) -> crate::error::Result<()> {
+        if content_bytes.is_empty() {
+            return Ok(());
+        }
+
+        let content = Content::decode(content_bytes)
+            .map_err(|e| crate::error::PdfMaskError::content_stream(e.to_string()))?;
+
+        let mut ctm = CtmStack::with_initial(initial_ctm);
+        let mut fill_color_stack: Vec<FillColorTracker> = vec![initial_fill];
+
+        // 現在のパス上の矩形（reオペレータで蓄積、fillで一括処理）
+        let mut current_rects: Vec<(f64, f64, f64, f64)> = Vec::new();
+
+        for op in &content.operations {
+            match op.operator.as_str() {
+                "q" => {
+                    ctm.push();
+                    let current_fill = fill_color_stack
+                        .last()
+                        .cloned()
+                        .unwrap_or_else(FillColorTracker::default_black);
+                    fill_color_stack.push(current_fill);
                 }
-            }
-            "cm" => {
-                ctm.apply_cm(&op.operands)?;
-            }
-            // Fill color operators
-            "rg" | "g" | "k" | "sc" | "scn" => {
-                if let Some(fc) = fill_color_stack.last_mut() {
-                    update_fill_color(op, fc);
+                "Q" => {
+                    ctm.pop();
+                    if fill_color_stack.len() > 1 {
+                        fill_color_stack.pop();
+                    }
                 }
+                "cm" => {
+                    ctm.apply_cm(&op.operands)?;
+                }
+                // Fill color operators
+                "rg" | "g" | "k" | "cs" | "sc" | "scn" => {
+                    if let Some(fc) = fill_color_stack.last_mut() {
+                        update_fill_color(op, fc, &resources.color_spaces);
+                    }
+                }
+                "gs" => {
+                    if let Some(state) =
+                        resource_name(op).and_then(|n| resources.ext_g_states.get(&n))
+                        && let Some(fc) = fill_color_stack.last_mut()
+                    {
+                        fc.apply_ext_g_state(state);
+                    }
+                }
+                "Do" => {
+                    if let Some(form) = resource_name(op).and_then(|n| resources.forms.get(&n))
+                        && let Some(fc) = fill_color_stack.last()
+                    {
+                        self.run_form(form, &ctm.current(), fc, depth, results)?;
+                    }
+                }
+                // Path construction
+                "re" | "m" | "l" | "c" | "v" | "y" | "h" => {
+                    update_path_rects(op, &mut current_rects);
+                }
+                // Fill operators
+                operator if self.fill_ops.contains(&operator) => {
+                    let matches = fill_color_stack.last().is_some_and(|fc| {
+                        if self.white {
+                            fc.is_white && fc.is_opaque()
+                        } else {
+                            !fc.is_white
+                        }
+                    });
+                    collect_fill_bboxes(matches, &ctm.current(), &current_rects, results);
+                    current_rects.clear();
+                }
+                // Path end without fill
+                "S" | "s" | "B" | "B*" | "b" | "b*" | "n" | "W" | "W*" => {
+                    current_rects.clear();
+                }
+                _ => {}
             }
-            // Path construction
-            "re" | "m" | "l" | "c" | "v" | "y" | "h" => {
-                update_path_rects(op, &mut current_rects);
-            }
-            // Fill operators
-            "f" | "F" | "f*" => {
-                let is_white = fill_color_stack
-                    .last()
-                    .map(|fc| fc.is_white)
-                    .unwrap_or(false);
-                collect_white_fill_bboxes(is_white, &ctm.current(), &current_rects, &mut results);
-                current_rects.clear();
-            }
-            // Path end without fill
-            "S" | "s" | "B" | "B*" | "b" | "b*" | "n" | "W" | "W*" => {
-                current_rects.clear();
+        }
+
+        Ok(())
+    }
+
+    /// Form XObjectの中身を走査し、見つかった矩形をフォームの/BBoxでクリップして追加する。
+    fn run_form<'a>(
+        &self,
+        form: &'a FormXObject,
+        ctm: &Matrix,
+        fill: &FillColorTracker<'a>,
+        depth: usize,
+        results: &mut Vec<BBox>,
+    ) -> crate::error::Result<()> {
+        if depth >= MAX_FORM_DEPTH || self.forms_scanned.get() >= MAX_FORM_SCANS {
+            debug!(depth, "form XObject limit reached; not scanned");
+            return Ok(());
+        }
+        self.forms_scanned.set(self.forms_scanned.get() + 1);
+        let mut fill = fill.clone();
+        if form.transparency_group {
+            // 透明グループは一体として合成される: 呼び出し側のアルファ・ソフトマスク・
+            // ブレンドはグループの合成結果にかかり、グループ内は初期状態から始まる
+            if self.white && !fill.is_opaque() {
+                return Ok(());
             }
-            _ => {}
+            fill.fill_alpha = 1.0;
+            fill.soft_mask = false;
+            fill.blend = false;
         }
+
+        let (form_ctm, clip) = form.effective_transform(ctm);
+        let mut form_results = Vec::new();
+        self.run(
+            &form.content,
+            &form.resources,
+            form_ctm,
+            fill,
+            depth + 1,
+            &mut form_results,
+        )?;
+        results.extend(
+            form_results
+                .iter()
+                .filter_map(|bbox| intersect_bbox(bbox, &clip)),
+        );
+        Ok(())
     }
+}
 
-    debug!(count = results.len(), "extracted white fill rects");
-    Ok(results)
+/// オペレータの先頭オペランドをリソース名として返す。
+fn resource_name(op: &lopdf::content::Operation) -> Option<String> {
+    op.operands
+        .first()
+        .and_then(|o| o.as_name().ok())
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+}
+
+/// `a`から`b`と重なる部分を除いた残りを、重ならない矩形（最大4つ）に分けて返す。
+pub fn subtract_bbox(a: &BBox, b: &BBox) -> Vec<BBox> {
+    let Some(overlap) = intersect_bbox(a, b) else {
+        return vec![a.clone()];
+    };
+    let candidates = [
+        // 下・上の帯（全幅）
+        BBox {
+            y_max: overlap.y_min,
+            ..a.clone()
+        },
+        BBox {
+            y_min: overlap.y_max,
+            ..a.clone()
+        },
+        // 重なりの高さの左・右
+        BBox {
+            x_max: overlap.x_min,
+            y_min: overlap.y_min,
+            y_max: overlap.y_max,
+            ..a.clone()
+        },
+        BBox {
+            x_min: overlap.x_max,
+            y_min: overlap.y_min,
+            y_max: overlap.y_max,
+            ..a.clone()
+        },
+    ];
+    candidates
+        .into_iter()
+        .filter(|r| r.x_min < r.x_max && r.y_min < r.y_max)
+        .collect()
+}
+
+/// 2つのBBoxの共通部分（重ならなければ`None`）。
+fn intersect_bbox(a: &BBox, b: &BBox) -> Option<BBox> {
+    let bbox = BBox {
+        x_min: a.x_min.max(b.x_min),
+        y_min: a.y_min.max(b.y_min),
+        x_max: a.x_max.min(b.x_max),
+        y_max: a.y_max.min(b.y_max),
+    };
+    (bbox.x_min < bbox.x_max && bbox.y_min < bbox.y_max).then_some(bbox)
 }
 
 /// 矩形(x, y, w, h)をCTMで変換しBBoxを返す。
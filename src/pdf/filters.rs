@@ -0,0 +1,333 @@
+// ストリームフィルタのデコード（フィルタ連鎖 + RunLengthDecode）
+
+use std::io::Read;
+
+use flate2::read::ZlibDecoder;
+use lopdf::{Dictionary, Object, Stream, dictionary};
+use tracing::warn;
+
+use crate::error::PdfMaskError;
+
+/// 画像形式そのものを表すフィルタ。デコードせず画像デコーダへ渡す。
+const IMAGE_FILTERS: &[&str] = &["DCTDecode", "JPXDecode", "JBIG2Decode", "CCITTFaxDecode"];
+
+/// 画像形式フィルタ（DCTDecode等）かどうか。
+pub fn is_image_filter(filter: &str) -> bool {
+    IMAGE_FILTERS.contains(&filter)
+}
+
+/// ストリーム辞書の/Filterを適用順のフィルタ名リストとして返す。
+pub fn stream_filters(dict: &Dictionary) -> Vec<String> {
+    match dict.get(b"Filter") {
+        Ok(Object::Name(name)) => vec![String::from_utf8_lossy(name).to_string()],
+        Ok(Object::Array(arr)) => arr
+            .iter()
+            .filter_map(|obj| obj.as_name().ok())
+            .map(|name| String::from_utf8_lossy(name).to_string())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// i番目のフィルタに対応する/DecodeParmsを返す（単一辞書または配列）。
+fn decode_parms_at(dict: &Dictionary, index: usize) -> Option<&Dictionary> {
+    match dict.get(b"DecodeParms") {
+        Ok(Object::Dictionary(parms)) if index == 0 => Some(parms),
+        Ok(Object::Array(arr)) => arr.get(index).and_then(|obj| obj.as_dict().ok()),
+        _ => None,
+    }
+}
+
+/// フィルタ連鎖を先頭から順に適用する。
+///
+/// 画像形式フィルタ（DCTDecode等）に到達した時点で停止し、
+/// それまでにデコードしたデータと画像形式フィルタ名を返す。
+/// 全フィルタを適用した場合は`None`を返す。
+pub fn decode_filter_chain(stream: &Stream) -> crate::error::Result<(Vec<u8>, Option<String>)> {
+    decode_filter_chain_limited(stream, usize::MAX)
+}
+
+/// [`decode_filter_chain`] と同じだが、各フィルタの出力を`max_len`バイトに制限する。
+///
+/// FlateDecodeとRunLengthDecodeは展開中に上限を検査するため、
+/// 展開爆弾でもメモリを確保する前にエラーとなる。
+pub fn decode_filter_chain_limited(
+    stream: &Stream,
+    max_len: usize,
+) -> crate::error::Result<(Vec<u8>, Option<String>)> {
+    let filters = stream_filters(&stream.dict);
+    let mut data = stream.content.clone();
+
+    for (i, filter) in filters.iter().enumerate() {
+        if is_image_filter(filter) {
+            return Ok((data, Some(filter.clone())));
+        }
+        data = apply_filter(filter, &data, decode_parms_at(&stream.dict, i), max_len)?;
+        if data.len() > max_len {
+            return Err(decoded_size_error(filter, max_len));
+        }
+    }
+
+    Ok((data, None))
+}
+
+fn decoded_size_error(filter: &str, max_len: usize) -> PdfMaskError {
+    PdfMaskError::limit_exceeded(format!(
+        "{} output exceeds the decoded size limit of {} bytes",
+        filter, max_len
+    ))
+}
+
+/// コンテンツストリーム等の非画像ストリームを完全にデコードする。
+pub fn decode_stream(stream: &Stream) -> crate::error::Result<Vec<u8>> {
+    match decode_filter_chain(stream)? {
+        (data, None) => Ok(data),
+        (_, Some(filter)) => Err(PdfMaskError::pdf_read(format!(
+            "image filter {} is not valid for a non-image stream",
+            filter
+        ))),
+    }
+}
+
+/// 単一フィルタを適用する。
+///
+/// FlateDecode/RunLengthDecodeは出力上限付きの自前実装、それ以外はlopdfに委譲する。
+fn apply_filter(
+    filter: &str,
+    data: &[u8],
+    parms: Option<&Dictionary>,
+    max_len: usize,
+) -> crate::error::Result<Vec<u8>> {
+    match filter {
+        "RunLengthDecode" => return run_length_decode_limited(data, max_len),
+        "FlateDecode" => return flate_decode_limited(data, parms, max_len),
+        _ => {}
+    }
+
+    let mut dict = dictionary! {
+        "Filter" => Object::Name(filter.as_bytes().to_vec()),
+    };
+    if let Some(parms) = parms {
+        dict.set("DecodeParms", Object::Dictionary(parms.clone()));
+    }
+    Stream::new(dict, data.to_vec())
+        .decompressed_content()
+        .map_err(|e| PdfMaskError::pdf_read(format!("{} decode error: {}", filter, e)))
+}
+
+/// FlateDecodeを出力`max_len`バイトまでに制限して展開し、PNG予測子を戻す。
+///
+/// 破損ストリームはlopdfと同様に展開できた部分までを返す。
+fn flate_decode_limited(
+    data: &[u8],
+    parms: Option<&Dictionary>,
+    max_len: usize,
+) -> crate::error::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    let limit = (max_len as u64).saturating_add(1);
+    if let Err(e) = ZlibDecoder::new(data).take(limit).read_to_end(&mut output) {
+        warn!("FlateDecode error, using partially decoded data: {}", e);
+    }
+    if output.len() > max_len {
+        return Err(decoded_size_error("FlateDecode", max_len));
+    }
+
+    let Some(parms) = parms else {
+        return Ok(output);
+    };
+    let predictor = parms
+        .get(b"Predictor")
+        .and_then(Object::as_i64)
+        .unwrap_or(1);
+    if !(10..=15).contains(&predictor) {
+        return Ok(output);
+    }
+    let param = |key: &[u8], default: i64| {
+        parms
+            .get(key)
+            .and_then(Object::as_i64)
+            .unwrap_or(default)
+            .max(1) as usize
+    };
+    let columns = param(b"Columns", 1);
+    let colors = param(b"Colors", 1);
+    let bits = param(b"BitsPerComponent", 8).max(8);
+    lopdf::filters::png::decode_frame(&output, colors * bits / 8, columns)
+        .map_err(|e| PdfMaskError::pdf_read(format!("FlateDecode predictor error: {}", e)))
+}
+
+/// RunLengthDecode (PDF 7.4.5) でデータをデコードする。
+///
+/// 長さバイトLが0〜127なら続くL+1バイトをそのままコピー、
+/// 129〜255なら次の1バイトを257-L回繰り返す。128はEOD。
+pub fn run_length_decode(data: &[u8]) -> crate::error::Result<Vec<u8>> {
+    run_length_decode_limited(data, usize::MAX)
+}
+
+/// [`run_length_decode`] の出力を`max_len`バイトに制限する版。
+fn run_length_decode_limited(data: &[u8], max_len: usize) -> crate::error::Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(data.len().saturating_mul(2).min(max_len));
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let length = data[pos];
+        pos += 1;
+        match length {
+            0..=127 => {
+                let count = length as usize + 1;
+                let end = pos + count;
+                if end > data.len() {
+                    return Err(PdfMaskError::pdf_read(
+                        "RunLengthDecode: literal run exceeds input",
+                    ));
+                }
+                output.extend_from_slice(&data[pos..end]);
+                pos = end;
+            }
+            128 => return Ok(output),
+            129..=255 => {
+                let byte = *data.get(pos).ok_or_else(|| {
+                    PdfMaskError::pdf_read("RunLengthDecode: missing byte for repeat run")
+                })?;
+                pos += 1;
+                let count = 257 - length as usize;
+                output.extend(std::iter::repeat_n(byte, count));
+            }
+        }
+        if output.len() > max_len {
+            return Err(decoded_size_error("RunLengthDecode", max_len));
+        }
+    }
+
+    // EODが無くてもデータ終端で終了する（寛容に扱う）
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_length_decode_known_sequence() {
+        // literal "ABC" + 'x' × 4 + EOD
+        let encoded = [2, b'A', b'B', b'C', 253, b'x', 128];
+        let decoded = run_length_decode(&encoded).expect("decode");
+        assert_eq!(decoded, b"ABCxxxx");
+    }
+
+    #[test]
+    fn test_run_length_decode_stops_at_eod() {
+        let encoded = [0, b'A', 128, 0, b'B'];
+        let decoded = run_length_decode(&encoded).expect("decode");
+        assert_eq!(decoded, b"A");
+    }
+
+    #[test]
+    fn test_run_length_decode_truncated_literal_errors() {
+        let encoded = [5, b'A', b'B'];
+        assert!(run_length_decode(&encoded).is_err());
+    }
+
+    #[test]
+    fn test_decode_filter_chain_flate_then_run_length() {
+        use flate2::Compression;
+        use flate2::write::ZlibEncoder;
+        use std::io::Write;
+
+        let rle = [255u8, 7, 1, 9, 9, 128];
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&rle).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let stream = Stream::new(
+            dictionary! {
+                "Filter" => vec![
+                    Object::Name(b"FlateDecode".to_vec()),
+                    Object::Name(b"RunLengthDecode".to_vec()),
+                ],
+            },
+            compressed,
+        );
+        let decoded = decode_stream(&stream).expect("decode chain");
+        let mut expected = vec![7u8; 2];
+        expected.extend_from_slice(&[9, 9]);
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn test_decode_filter_chain_stops_at_image_filter() {
+        let stream = Stream::new(
+            dictionary! {
+                "Filter" => vec![
+                    Object::Name(b"RunLengthDecode".to_vec()),
+                    Object::Name(b"DCTDecode".to_vec()),
+                ],
+            },
+            vec![1, 0xFF, 0xD8, 128],
+        );
+        let (data, terminal) = decode_filter_chain(&stream).expect("decode chain");
+        assert_eq!(data, vec![0xFF, 0xD8]);
+        assert_eq!(terminal.as_deref(), Some("DCTDecode"));
+    }
+
+    #[test]
+    fn test_decode_run_length_gray_image_data() {
+        // 4x2 DeviceGray: 1行目は黒4画素、2行目は 0,64,128,255
+        let encoded = [253u8, 0, 3, 0, 64, 128, 255, 128];
+        let stream = Stream::new(
+            dictionary! {
+                "Filter" => "RunLengthDecode",
+            },
+            encoded.to_vec(),
+        );
+        let decoded = decode_stream(&stream).expect("decode");
+        assert_eq!(decoded, vec![0, 0, 0, 0, 0, 64, 128, 255]);
+    }
+
+    fn zlib(data: &[u8]) -> Vec<u8> {
+        use flate2::Compression;
+        use flate2::write::ZlibEncoder;
+        use std::io::Write;
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_decode_filter_chain_limited_rejects_flate_bomb() {
+        let stream = Stream::new(
+            dictionary! { "Filter" => "FlateDecode" },
+            zlib(&vec![0u8; 1 << 20]),
+        );
+        let err = decode_filter_chain_limited(&stream, 4096).expect_err("must exceed limit");
+        assert!(err.to_string().contains("FlateDecode"), "{err}");
+
+        let (data, _) = decode_filter_chain_limited(&stream, 1 << 20).expect("within limit");
+        assert_eq!(data.len(), 1 << 20);
+    }
+
+    #[test]
+    fn test_decode_filter_chain_limited_rejects_run_length_bomb() {
+        // 1レコード128バイト × 1000 = 128000バイト
+        let mut encoded = [129u8, 0].repeat(1000);
+        encoded.push(128);
+        let stream = Stream::new(dictionary! { "Filter" => "RunLengthDecode" }, encoded);
+        let err = decode_filter_chain_limited(&stream, 1000).expect_err("must exceed limit");
+        assert!(err.to_string().contains("RunLengthDecode"), "{err}");
+    }
+
+    #[test]
+    fn test_decode_filter_chain_limited_applies_png_predictor() {
+        // Columns=2の2行、各行先頭はPNGフィルタ種別0(None)
+        let stream = Stream::new(
+            dictionary! {
+                "Filter" => "FlateDecode",
+                "DecodeParms" => dictionary! { "Predictor" => 12, "Columns" => 2 },
+            },
+            zlib(&[0, 1, 2, 0, 3, 4]),
+        );
+        let (data, _) = decode_filter_chain_limited(&stream, 1024).expect("decode");
+        assert_eq!(data, vec![1, 2, 3, 4]);
+    }
+}
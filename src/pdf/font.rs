@@ -1,11 +1,14 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
 
 use lopdf::{Document, Object, ObjectId};
 use tracing::{debug, warn};
 use ttf_parser::GlyphId;
 
+use crate::config::job::WidthSource;
 use crate::error::PdfMaskError;
+use crate::pdf::content_stream::operand_to_f64;
 
 /// グリフアウトラインのパス操作
 #[derive(Debug, Clone)]
@@ -21,8 +24,35 @@ pub enum PathOp {
 /// フォントエンコーディング
 #[derive(Debug, Clone)]
 pub enum FontEncoding {
-    WinAnsi { differences: HashMap<u8, String> },
+    WinAnsi {
+        differences: HashMap<u8, String>,
+    },
     IdentityH,
+    /// 埋め込みCMapストリーム。文字列は`codespace`の範囲で1〜2バイトずつ区切り、
+    /// 得た文字コードを`map`でCIDに変換する（対応の無いコードはCID 0）。
+    Cmap {
+        codespace: Vec<CodespaceRange>,
+        map: HashMap<u16, u16>,
+    },
+}
+
+/// CMapのcodespace range（`begincodespacerange`の1エントリ）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodespaceRange {
+    pub low: Vec<u8>,
+    pub high: Vec<u8>,
+}
+
+impl CodespaceRange {
+    /// `bytes`がこの範囲のバイト長で、各バイトが上下限の間にあるか。
+    fn matches(&self, bytes: &[u8]) -> bool {
+        bytes.len() == self.low.len()
+            && bytes.len() == self.high.len()
+            && bytes
+                .iter()
+                .zip(self.low.iter().zip(&self.high))
+                .all(|(b, (lo, hi))| (lo..=hi).contains(&b))
+    }
 }
 
 impl FontEncoding {
@@ -43,6 +73,32 @@ impl FontEncoding {
                     .collect()
             }
             FontEncoding::WinAnsi { .. } => bytes.iter().map(|&b| b as u16).collect(),
+            FontEncoding::Cmap { codespace, .. } => {
+                let mut codes = Vec::with_capacity(bytes.len());
+                let mut i = 0;
+                while i < bytes.len() {
+                    // 短い順に試し、どの範囲にも入らなければ1バイトを読み飛ばす（PDF §9.7.6.3）
+                    let len = (1..=2)
+                        .take_while(|&len| i + len <= bytes.len())
+                        .find(|&len| codespace.iter().any(|r| r.matches(&bytes[i..i + len])))
+                        .unwrap_or(1);
+                    codes.push(
+                        bytes[i..i + len]
+                            .iter()
+                            .fold(0u16, |code, &b| (code << 8) | b as u16),
+                    );
+                    i += len;
+                }
+                codes
+            }
+        }
+    }
+
+    /// 文字コードをCIDに変換する。Cmap以外では文字コードがそのままCID。
+    fn cid(&self, code: u16) -> u16 {
+        match self {
+            FontEncoding::Cmap { map, .. } => map.get(&code).copied().unwrap_or(0),
+            _ => code,
         }
     }
 }
@@ -55,6 +111,10 @@ pub struct ParsedFont {
     widths: HashMap<u16, f64>,
     default_width: f64,
     units_per_em: u16,
+    /// /ToUnicode CMapの文字コード→Unicode文字列（無ければ空）
+    to_unicode: HashMap<u16, String>,
+    /// 埋め込みフォントが解析できず、システムフォントで代替した
+    substituted: bool,
 }
 
 impl ParsedFont {
@@ -66,6 +126,31 @@ impl ParsedFont {
         self.units_per_em
     }
 
+    /// 埋め込みフォントが壊れていたため、BaseFontからシステムフォントで代替したか。
+    pub fn is_substituted(&self) -> bool {
+        self.substituted
+    }
+
+    /// グリフアウトラインを持つか。`false`は/Widthsの送り幅だけを持つ幅専用フォント
+    /// （Type3や解決できない非埋め込みフォント）で、グリフは描けないが字送りは正しく進められる。
+    pub fn has_outlines(&self) -> bool {
+        !self.font_data.is_empty()
+    }
+
+    /// フォントのascent（1/1000テキスト空間単位）。フォントが解析できない場合は0。
+    pub fn ascent(&self) -> f64 {
+        ttf_parser::Face::parse(&self.font_data, self.face_index)
+            .map(|face| face.ascender() as f64 * 1000.0 / self.units_per_em as f64)
+            .unwrap_or(0.0)
+    }
+
+    /// フォントのdescent（1/1000テキスト空間単位、通常は負の値）。フォントが解析できない場合は0。
+    pub fn descent(&self) -> f64 {
+        ttf_parser::Face::parse(&self.font_data, self.face_index)
+            .map(|face| face.descender() as f64 * 1000.0 / self.units_per_em as f64)
+            .unwrap_or(0.0)
+    }
+
     /// 文字コード→グリフIDを解決
     pub fn char_code_to_glyph_id(&self, code: u16) -> Option<GlyphId> {
         let face = ttf_parser::Face::parse(&self.font_data, self.face_index).ok()?;
@@ -85,17 +170,60 @@ impl ParsedFont {
                 // Identity-H + CIDToGIDMap=Identity: CID = GID
                 Some(GlyphId(code))
             }
+            FontEncoding::Cmap { .. } => Some(GlyphId(self.encoding.cid(code))),
+        }
+    }
+
+    /// 文字コード→Unicode文字列を解決する（テキスト抽出用）。
+    ///
+    /// /ToUnicode CMapがあればそれを優先する。無ければWinAnsiではDifferencesの
+    /// グリフ名、次いでWinAnsiの対応表を使う。Identity-HのCIDはUnicodeと
+    /// 無関係なためCMapが無ければ`None`を返す。
+    pub fn char_code_to_unicode(&self, code: u16) -> Option<String> {
+        if let Some(text) = self.to_unicode.get(&code) {
+            return Some(text.clone());
+        }
+        match &self.encoding {
+            FontEncoding::WinAnsi { differences } => {
+                let code = u8::try_from(code).ok()?;
+                differences
+                    .get(&code)
+                    .and_then(|name| glyph_name_to_unicode(name))
+                    .or_else(|| win_ansi_to_unicode(code))
+                    .map(String::from)
+            }
+            FontEncoding::IdentityH | FontEncoding::Cmap { .. } => None,
         }
     }
 
     /// 文字コードの幅を返す（1/1000テキスト空間単位）
     pub fn glyph_width(&self, code: u16) -> f64 {
+        // CIDフォントの/WはCIDで引く
         self.widths
-            .get(&code)
+            .get(&self.encoding.cid(code))
             .copied()
             .unwrap_or(self.default_width)
     }
 
+    /// フォント自身のhorizontal advanceを返す（1/1000テキスト空間単位）。
+    /// グリフが解決できない場合は`None`。
+    pub fn font_advance(&self, code: u16) -> Option<f64> {
+        let face = ttf_parser::Face::parse(&self.font_data, self.face_index).ok()?;
+        let advance = face.glyph_hor_advance(self.char_code_to_glyph_id(code)?)?;
+        Some(advance as f64 * 1000.0 / self.units_per_em as f64)
+    }
+
+    /// 字送りに使う幅を`source`に従って返す（1/1000テキスト空間単位）。
+    /// フォントのadvanceが取れない文字は/Widthsの幅を使う。
+    pub fn advance_width(&self, code: u16, source: WidthSource) -> f64 {
+        let pdf_width = self.glyph_width(code);
+        match (source, self.font_advance(code)) {
+            (WidthSource::Pdf, _) | (_, None) => pdf_width,
+            (WidthSource::Font, Some(advance)) => advance,
+            (WidthSource::Max, Some(advance)) => pdf_width.max(advance),
+        }
+    }
+
     /// グリフIDからアウトラインを取得
     pub fn glyph_outline(&self, glyph_id: GlyphId) -> Option<Vec<PathOp>> {
         let face = ttf_parser::Face::parse(&self.font_data, self.face_index).ok()?;
@@ -148,6 +276,89 @@ static SYSTEM_FONT_DB: LazyLock<fontdb::Database> = LazyLock::new(|| {
     db
 });
 
+/// 非埋め込みフォントの代替フォントの指定。システムフォントより優先する。
+///
+/// 環境ごとにインストール済みフォントが異なっても同じ出力になるよう、
+/// フォント名ごとの代替ファイルと、それ以外すべてに使うフォールバックを固定する。
+#[derive(Debug, Clone)]
+pub struct FontSubstitutions {
+    /// フォント名 → フォントファイルのデータ
+    by_name: HashMap<String, Vec<u8>>,
+    /// 名前の指定に当たらないフォントに使うフォントデータ。指定時はシステムフォントを参照しない
+    fallback: Option<Vec<u8>>,
+    /// 代替指定に無いフォントをシステムフォントから探す。無効ならシステムフォントを読み込まない
+    use_system_fonts: bool,
+}
+
+impl Default for FontSubstitutions {
+    fn default() -> Self {
+        FontSubstitutions {
+            by_name: HashMap::new(),
+            fallback: None,
+            use_system_fonts: true,
+        }
+    }
+}
+
+impl FontSubstitutions {
+    /// 代替フォントのファイルを読み込む。読めない・フォントとして解析できないファイルはエラー。
+    pub fn load(
+        by_name: &BTreeMap<String, PathBuf>,
+        fallback: Option<&Path>,
+    ) -> crate::error::Result<Self> {
+        let read_font = |path: &Path| -> crate::error::Result<Vec<u8>> {
+            let data = std::fs::read(path).map_err(|e| {
+                PdfMaskError::config(format!(
+                    "cannot read substitute font {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            ttf_parser::Face::parse(&data, 0).map_err(|e| {
+                PdfMaskError::config(format!(
+                    "substitute font {} is not a usable font: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+            Ok(data)
+        };
+        let by_name = by_name
+            .iter()
+            .map(|(name, path)| Ok((name.clone(), read_font(path)?)))
+            .collect::<crate::error::Result<HashMap<_, _>>>()?;
+        let fallback = fallback.map(read_font).transpose()?;
+        Ok(FontSubstitutions {
+            by_name,
+            fallback,
+            use_system_fonts: true,
+        })
+    }
+
+    /// システムフォントの使用可否を設定する。
+    ///
+    /// 無効にすると代替指定に無いフォントは即座に未解決となり、
+    /// システムフォントのデータベース（初回に全フォントを走査する）を初期化しない。
+    pub fn set_use_system_fonts(&mut self, enabled: bool) {
+        self.use_system_fonts = enabled;
+    }
+
+    /// BaseFont名（サブセット接頭辞を除いたもの）に対応する代替フォントを返す。
+    ///
+    /// 名前の完全一致、PostScript名から推定したファミリ名（`TimesNewRomanPS-BoldMT` →
+    /// `Times New Roman`、空白なしの`TimesNewRoman`も可）、フォールバックの順に探す。
+    fn lookup(&self, base_font_name: &str) -> Option<&[u8]> {
+        let (family, _, _) = parse_ps_name_to_query(base_font_name);
+        let compact_family: String = family.split_whitespace().collect();
+        self.by_name
+            .get(base_font_name)
+            .or_else(|| self.by_name.get(&family))
+            .or_else(|| self.by_name.get(&compact_family))
+            .or(self.fallback.as_ref())
+            .map(Vec::as_slice)
+    }
+}
+
 /// PostScript フォント名からファミリ名とスタイルを推定して fontdb::Query を構築
 fn parse_ps_name_to_query(ps_name: &str) -> (String, fontdb::Weight, bool) {
     let mut family = ps_name.to_string();
@@ -189,9 +400,23 @@ fn parse_ps_name_to_query(ps_name: &str) -> (String, fontdb::Weight, bool) {
     (result, weight, is_italic)
 }
 
-/// 非埋め込みフォントをシステムフォントから解決
+/// 非埋め込みフォントを代替フォントの指定、無ければシステムフォントから解決
 /// Returns: (font_data, face_index)
-fn resolve_system_font(base_font_name: &str) -> crate::error::Result<(Vec<u8>, u32)> {
+fn resolve_system_font(
+    base_font_name: &str,
+    substitutions: &FontSubstitutions,
+) -> crate::error::Result<(Vec<u8>, u32)> {
+    if let Some(font_data) = substitutions.lookup(base_font_name) {
+        debug!(base_font_name, "using configured substitute font");
+        return Ok((font_data.to_vec(), 0));
+    }
+    if !substitutions.use_system_fonts {
+        return Err(PdfMaskError::pdf_read(format!(
+            "system font not found: {} (system fonts disabled)",
+            base_font_name
+        )));
+    }
+
     let db = &*SYSTEM_FONT_DB;
 
     // Helper to load font data from face info
@@ -277,6 +502,7 @@ fn resolve_system_font(base_font_name: &str) -> crate::error::Result<(Vec<u8>, u
 /// フォント辞書から BaseFont を取得してシステムフォント解決
 fn resolve_system_font_from_dict(
     font_dict: &lopdf::Dictionary,
+    substitutions: &FontSubstitutions,
 ) -> crate::error::Result<(Vec<u8>, u32)> {
     let base_font = font_dict
         .get(b"BaseFont")
@@ -285,14 +511,34 @@ fn resolve_system_font_from_dict(
         .map(|n| String::from_utf8_lossy(n).into_owned())
         .ok_or_else(|| PdfMaskError::pdf_read("no BaseFont in font dictionary"))?;
 
-    resolve_system_font(&base_font)
+    resolve_system_font(strip_subset_prefix(&base_font), substitutions)
+}
+
+/// サブセットフォント名の接頭辞（"ABCDEF+"）を除去する。
+fn strip_subset_prefix(base_font: &str) -> &str {
+    match base_font.split_once('+') {
+        Some((tag, name)) if tag.len() == 6 && tag.bytes().all(|b| b.is_ascii_uppercase()) => name,
+        _ => base_font,
+    }
 }
 
 /// ページのフォントリソースを解析し、ParsedFontのマップを返す。
 /// 埋込フォントデータが無いフォントはシステムフォントから解決を試みる。
+/// アウトラインを得られないフォントも、送り幅が分かれば幅専用フォント
+/// （[`ParsedFont::has_outlines`]が`false`）として含める。
 pub fn parse_page_fonts(
     doc: &Document,
     page_num: u32,
+) -> crate::error::Result<HashMap<String, ParsedFont>> {
+    parse_page_fonts_with_substitutions(doc, page_num, &FontSubstitutions::default())
+}
+
+/// [`parse_page_fonts`]と同じだが、埋込フォントデータが無いフォントは
+/// `substitutions`の指定をシステムフォントより優先して解決する。
+pub fn parse_page_fonts_with_substitutions(
+    doc: &Document,
+    page_num: u32,
+    substitutions: &FontSubstitutions,
 ) -> crate::error::Result<HashMap<String, ParsedFont>> {
     if page_num == 0 {
         return Err(PdfMaskError::pdf_read("page_num must be >= 1 (1-based)"));
@@ -308,19 +554,19 @@ pub fn parse_page_fonts(
 
     for (name_bytes, font_ref) in &font_dict {
         let name = String::from_utf8_lossy(name_bytes).into_owned();
-        match parse_single_font(doc, font_ref) {
+        match parse_single_font(doc, font_ref, substitutions) {
             Ok(parsed) => {
                 fonts.insert(name, parsed);
             }
             Err(e) => {
-                let msg = e.to_string();
-                if msg.contains("FontFile2")
-                    || msg.contains("FontDescriptor")
-                    || msg.contains("system font not found")
-                    || msg.contains("unsupported font subtype")
-                {
-                    // 埋込データなし、システムフォント未検出、非対応形式はスキップ
-                    // 呼び出し元が不足フォントを処理する（例: pdfium フォールバック）
+                if is_skippable_font_error(&e) {
+                    // 埋込データなし、システムフォント未検出、非対応形式はアウトライン化できない。
+                    // 送り幅が分かれば幅専用フォントとして登録し、後続テキストの位置を保つ。
+                    // グリフの描画は呼び出し元が処理する（例: pdfium フォールバック）
+                    if let Some(parsed) = parse_width_only_font(doc, font_ref) {
+                        debug!(font = %name, reason = %e, "no glyph outlines; using widths only");
+                        fonts.insert(name, parsed);
+                    }
                     continue;
                 }
                 return Err(e);
@@ -332,6 +578,67 @@ pub fn parse_page_fonts(
     Ok(fonts)
 }
 
+/// アウトライン化できないフォント
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UnsupportedFont {
+    /// /BaseFont（Type3等で無い場合は空文字列）
+    pub base_font: String,
+    /// /Subtype
+    pub subtype: String,
+}
+
+/// ページのフォントリソースのうち、[`parse_page_fonts`] がスキップするフォントを返す。
+///
+/// Type3等の非対応形式や、埋め込みデータもシステムフォントも見つからないフォントが該当する。
+pub fn unsupported_page_fonts(
+    doc: &Document,
+    page_num: u32,
+) -> crate::error::Result<Vec<UnsupportedFont>> {
+    if page_num == 0 {
+        return Err(PdfMaskError::pdf_read("page_num must be >= 1 (1-based)"));
+    }
+
+    let page_id = doc
+        .page_iter()
+        .nth((page_num - 1) as usize)
+        .ok_or_else(|| PdfMaskError::pdf_read(format!("page {} not found", page_num)))?;
+
+    let mut unsupported = Vec::new();
+    for font_ref in get_font_dict(doc, page_id)?.values() {
+        let Err(e) = parse_single_font(doc, font_ref, &FontSubstitutions::default()) else {
+            continue;
+        };
+        if !is_skippable_font_error(&e) {
+            return Err(e);
+        }
+        let font_dict = doc
+            .dereference(font_ref)
+            .ok()
+            .and_then(|(_, obj)| obj.as_dict().ok());
+        let name_of = |key: &[u8]| {
+            font_dict
+                .and_then(|dict| dict.get(key).ok())
+                .and_then(|obj| obj.as_name().ok())
+                .map(|name| String::from_utf8_lossy(name).into_owned())
+                .unwrap_or_default()
+        };
+        unsupported.push(UnsupportedFont {
+            base_font: name_of(b"BaseFont"),
+            subtype: name_of(b"Subtype"),
+        });
+    }
+    Ok(unsupported)
+}
+
+/// フォント解析エラーのうち、ページ全体を失敗させずにフォント単位でスキップするものか。
+fn is_skippable_font_error(e: &PdfMaskError) -> bool {
+    let msg = e.to_string();
+    msg.contains("FontFile2")
+        || msg.contains("FontDescriptor")
+        || msg.contains("system font not found")
+        || msg.contains("unsupported font subtype")
+}
+
 /// ページのフォントリソース辞書を取得
 fn get_font_dict(
     doc: &Document,
@@ -345,8 +652,11 @@ fn get_font_dict(
         .as_dict()
         .map_err(|e| PdfMaskError::pdf_read(e.to_string()))?;
 
-    // Resources を取得（ページ直接 or 親からの継承）
-    let resources = get_resources(doc, page_dict)?;
+    // Resources を取得（ページ直接 or 親からの継承）。
+    // どこにも宣言されていないページは空のリソース（フォントなし）として扱う。
+    let Some(resources) = get_resources(doc, page_dict)? else {
+        return Ok(HashMap::new());
+    };
     let resources_dict = resources
         .as_dict()
         .map_err(|e| PdfMaskError::pdf_read(e.to_string()))?;
@@ -372,16 +682,19 @@ fn get_font_dict(
 }
 
 /// Resources辞書を取得（ページ直接またはPages親から継承）
+///
+/// ページにも祖先にもResourcesが無い場合は`None`を返す。
 fn get_resources<'a>(
     doc: &'a Document,
     page_dict: &'a lopdf::Dictionary,
-) -> crate::error::Result<&'a Object> {
+) -> crate::error::Result<Option<&'a Object>> {
     if let Ok(res) = page_dict.get(b"Resources") {
         return match res {
             Object::Reference(id) => doc
                 .get_object(*id)
+                .map(Some)
                 .map_err(|e| PdfMaskError::pdf_read(e.to_string())),
-            _ => Ok(res),
+            _ => Ok(Some(res)),
         };
     }
 
@@ -397,11 +710,15 @@ fn get_resources<'a>(
         }
     }
 
-    Err(PdfMaskError::pdf_read("no Resources found"))
+    Ok(None)
 }
 
 /// 単一フォント辞書からParsedFontを構築
-fn parse_single_font(doc: &Document, font_ref: &Object) -> crate::error::Result<ParsedFont> {
+fn parse_single_font(
+    doc: &Document,
+    font_ref: &Object,
+    substitutions: &FontSubstitutions,
+) -> crate::error::Result<ParsedFont> {
     let font_obj = match font_ref {
         Object::Reference(id) => doc
             .get_object(*id)
@@ -420,16 +737,324 @@ fn parse_single_font(doc: &Document, font_ref: &Object) -> crate::error::Result<
         .map(|n| String::from_utf8_lossy(n).into_owned())
         .unwrap_or_default();
 
-    match subtype.as_str() {
-        "TrueType" | "Type1" | "MMType1" => parse_truetype_font(doc, font_dict),
-        "Type0" => parse_type0_font(doc, font_dict),
-        _ => Err(PdfMaskError::pdf_read(format!(
-            "unsupported font subtype: {}",
-            subtype
-        ))),
+    let mut font = match subtype.as_str() {
+        "TrueType" | "Type1" | "MMType1" => parse_truetype_font(doc, font_dict, substitutions)?,
+        "Type0" => parse_type0_font(doc, font_dict, substitutions)?,
+        _ => {
+            return Err(PdfMaskError::pdf_read(format!(
+                "unsupported font subtype: {}",
+                subtype
+            )));
+        }
+    };
+    font.to_unicode = parse_to_unicode(doc, font_dict);
+    Ok(font)
+}
+
+/// フォント辞書の/ToUnicodeストリームを解析する。
+///
+/// ToUnicodeはテキスト抽出にのみ使うため、無い・読めない場合は空のマップを返す。
+fn parse_to_unicode(doc: &Document, font_dict: &lopdf::Dictionary) -> HashMap<u16, String> {
+    let Ok(obj) = font_dict.get(b"ToUnicode") else {
+        return HashMap::new();
+    };
+    let stream = match doc.dereference(obj) {
+        Ok((_, Object::Stream(stream))) => stream,
+        // /Identity-H 等の名前は文字コード自体を意味しないため無視する
+        _ => return HashMap::new(),
+    };
+    match crate::pdf::filters::decode_stream(stream) {
+        Ok(data) => parse_to_unicode_cmap(&data),
+        Err(e) => {
+            warn!("failed to decode ToUnicode stream: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+/// ToUnicode CMapの`beginbfchar`/`beginbfrange`を文字コード→Unicode文字列に展開する。
+///
+/// 宛先はUTF-16BEの16進文字列。bfrangeの宛先が文字列の場合は末尾のコード単位を
+/// 1ずつ増やし、配列の場合は要素を順に割り当てる。解釈できないエントリは無視する。
+pub fn parse_to_unicode_cmap(data: &[u8]) -> HashMap<u16, String> {
+    let tokens = tokenize_cmap(data);
+    let mut map = HashMap::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        match &tokens[i] {
+            CMapToken::Keyword(k) if k == "beginbfchar" => {
+                i += 1;
+                while i + 1 < tokens.len() && !tokens[i].is_keyword("endbfchar") {
+                    if let (CMapToken::Hex(src), CMapToken::Hex(dst)) = (&tokens[i], &tokens[i + 1])
+                        && let Some(code) = hex_to_code(src)
+                    {
+                        map.insert(code, utf16be_to_string(dst));
+                    }
+                    i += 2;
+                }
+            }
+            CMapToken::Keyword(k) if k == "beginbfrange" => {
+                i += 1;
+                while i + 2 < tokens.len() && !tokens[i].is_keyword("endbfrange") {
+                    let (CMapToken::Hex(lo), CMapToken::Hex(hi)) = (&tokens[i], &tokens[i + 1])
+                    else {
+                        i += 1;
+                        continue;
+                    };
+                    let (Some(lo), Some(hi)) = (hex_to_code(lo), hex_to_code(hi)) else {
+                        i += 3;
+                        continue;
+                    };
+                    match &tokens[i + 2] {
+                        CMapToken::Hex(dst) => {
+                            for (offset, code) in (lo..=hi).enumerate() {
+                                map.insert(code, offset_utf16be(dst, offset as u16));
+                            }
+                            i += 3;
+                        }
+                        CMapToken::ArrayStart => {
+                            i += 3;
+                            let mut code = lo;
+                            while i < tokens.len() && tokens[i] != CMapToken::ArrayEnd {
+                                if let CMapToken::Hex(dst) = &tokens[i]
+                                    && code <= hi
+                                {
+                                    map.insert(code, utf16be_to_string(dst));
+                                    code = code.saturating_add(1);
+                                }
+                                i += 1;
+                            }
+                            i += 1;
+                        }
+                        _ => i += 3,
+                    }
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    map
+}
+
+/// 埋め込みCMapの`begincodespacerange`・`begincidrange`・`begincidchar`から
+/// [`FontEncoding::Cmap`]を組み立てる。
+///
+/// codespace rangeが1つも無いCMapは文字列を区切れないため`None`を返す。
+/// 3バイト以上のコードや解釈できないエントリは無視する。
+pub fn parse_cmap_encoding(data: &[u8]) -> Option<FontEncoding> {
+    let tokens = tokenize_cmap(data);
+    let mut codespace = Vec::new();
+    let mut map = HashMap::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        match &tokens[i] {
+            CMapToken::Keyword(k) if k == "begincodespacerange" => {
+                i += 1;
+                while i + 1 < tokens.len() && !tokens[i].is_keyword("endcodespacerange") {
+                    if let (CMapToken::Hex(low), CMapToken::Hex(high)) =
+                        (&tokens[i], &tokens[i + 1])
+                        && (1..=2).contains(&low.len())
+                        && low.len() == high.len()
+                    {
+                        codespace.push(CodespaceRange {
+                            low: low.clone(),
+                            high: high.clone(),
+                        });
+                    }
+                    i += 2;
+                }
+            }
+            CMapToken::Keyword(k) if k == "begincidrange" => {
+                i += 1;
+                while i + 2 < tokens.len() && !tokens[i].is_keyword("endcidrange") {
+                    if let (CMapToken::Hex(lo), CMapToken::Hex(hi), CMapToken::Keyword(cid)) =
+                        (&tokens[i], &tokens[i + 1], &tokens[i + 2])
+                        && let (Some(lo), Some(hi), Ok(cid)) =
+                            (hex_to_code(lo), hex_to_code(hi), cid.parse::<u16>())
+                    {
+                        for (offset, code) in (lo..=hi).enumerate() {
+                            map.insert(code, cid.saturating_add(offset as u16));
+                        }
+                    }
+                    i += 3;
+                }
+            }
+            CMapToken::Keyword(k) if k == "begincidchar" => {
+                i += 1;
+                while i + 1 < tokens.len() && !tokens[i].is_keyword("endcidchar") {
+                    if let (CMapToken::Hex(src), CMapToken::Keyword(cid)) =
+                        (&tokens[i], &tokens[i + 1])
+                        && let (Some(code), Ok(cid)) = (hex_to_code(src), cid.parse::<u16>())
+                    {
+                        map.insert(code, cid);
+                    }
+                    i += 2;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    (!codespace.is_empty()).then_some(FontEncoding::Cmap { codespace, map })
+}
+
+/// Type0フォントの/Encodingを解析する。
+///
+/// 埋め込みCMapストリームなら[`parse_cmap_encoding`]で読み、名前（Identity-H等）や
+/// 読めないストリームはIdentity-Hとして扱う。
+fn parse_type0_encoding(doc: &Document, font_dict: &lopdf::Dictionary) -> FontEncoding {
+    let stream = match font_dict.get(b"Encoding").map(|obj| doc.dereference(obj)) {
+        Ok(Ok((_, Object::Stream(stream)))) => stream,
+        _ => return FontEncoding::IdentityH,
+    };
+    match crate::pdf::filters::decode_stream(stream) {
+        Ok(data) => parse_cmap_encoding(&data).unwrap_or_else(|| {
+            warn!("embedded CMap has no codespace range; falling back to Identity-H");
+            FontEncoding::IdentityH
+        }),
+        Err(e) => {
+            warn!("failed to decode embedded CMap stream: {}", e);
+            FontEncoding::IdentityH
+        }
     }
 }
 
+/// CMapの字句（16進文字列・配列括弧・キーワード）
+#[derive(Debug, PartialEq)]
+enum CMapToken {
+    Hex(Vec<u8>),
+    ArrayStart,
+    ArrayEnd,
+    Keyword(String),
+}
+
+impl CMapToken {
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self, CMapToken::Keyword(k) if k == keyword)
+    }
+}
+
+/// CMapを字句に分割する（コメント・辞書区切り・リテラル文字列は読み飛ばす）。
+fn tokenize_cmap(data: &[u8]) -> Vec<CMapToken> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < data.len() {
+        match data[i] {
+            b'%' => {
+                while i < data.len() && data[i] != b'\n' && data[i] != b'\r' {
+                    i += 1;
+                }
+            }
+            b'<' if data.get(i + 1) == Some(&b'<') => i += 2,
+            b'>' if data.get(i + 1) == Some(&b'>') => i += 2,
+            b'<' => {
+                let start = i + 1;
+                let end = data[start..]
+                    .iter()
+                    .position(|&b| b == b'>')
+                    .map_or(data.len(), |p| start + p);
+                let digits: Vec<u8> = data[start..end]
+                    .iter()
+                    .copied()
+                    .filter(u8::is_ascii_hexdigit)
+                    .collect();
+                let bytes = digits
+                    .chunks(2)
+                    .map(|pair| {
+                        // 奇数桁の末尾は0を補う（PDF §7.3.4.3）
+                        let hi = hex_digit(pair[0]);
+                        let lo = pair.get(1).map_or(0, |&d| hex_digit(d));
+                        (hi << 4) | lo
+                    })
+                    .collect();
+                tokens.push(CMapToken::Hex(bytes));
+                i = end + 1;
+            }
+            b'[' => {
+                tokens.push(CMapToken::ArrayStart);
+                i += 1;
+            }
+            b']' => {
+                tokens.push(CMapToken::ArrayEnd);
+                i += 1;
+            }
+            b'(' => {
+                // リテラル文字列はbfchar/bfrangeでは使わないため読み飛ばす
+                let mut depth = 0;
+                while i < data.len() {
+                    match data[i] {
+                        b'\\' => i += 1,
+                        b'(' => depth += 1,
+                        b')' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                i += 1;
+            }
+            b if b.is_ascii_whitespace() || b == b'/' || b == b'{' || b == b'}' => i += 1,
+            _ => {
+                let start = i;
+                while i < data.len()
+                    && !data[i].is_ascii_whitespace()
+                    && !b"<>[]()/%{}".contains(&data[i])
+                {
+                    i += 1;
+                }
+                tokens.push(CMapToken::Keyword(
+                    String::from_utf8_lossy(&data[start..i]).into_owned(),
+                ));
+            }
+        }
+    }
+
+    tokens
+}
+
+fn hex_digit(d: u8) -> u8 {
+    (d as char).to_digit(16).unwrap_or(0) as u8
+}
+
+/// 1〜2バイトの16進文字列を文字コードに変換する。
+fn hex_to_code(bytes: &[u8]) -> Option<u16> {
+    match bytes {
+        [b] => Some(*b as u16),
+        [hi, lo] => Some(u16::from_be_bytes([*hi, *lo])),
+        _ => None,
+    }
+}
+
+fn utf16be_to_string(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// bfrangeの宛先: 末尾のUTF-16コード単位に`offset`を加えた文字列を返す。
+fn offset_utf16be(bytes: &[u8], offset: u16) -> String {
+    let mut units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+        .collect();
+    if let Some(last) = units.last_mut() {
+        *last = last.wrapping_add(offset);
+    }
+    String::from_utf16_lossy(&units)
+}
+
 /// フォントfaceからグリフ幅を導出（Widths省略時用）
 /// ttf_parserのhorizontal advanceを1000単位に正規化して返す
 fn derive_widths_from_font_face(
@@ -443,8 +1068,8 @@ fn derive_widths_from_font_face(
     // エンコーディングに応じて文字コード範囲を決定
     let char_codes: Vec<u16> = match encoding {
         FontEncoding::WinAnsi { .. } => (0x00..=0xFF).collect(),
-        FontEncoding::IdentityH => {
-            // IdentityHの場合は全グリフを対象とする（0x0000-0xFFFF）
+        FontEncoding::IdentityH | FontEncoding::Cmap { .. } => {
+            // CIDフォントの場合は全グリフを対象とする（0x0000-0xFFFF、CIDで引く）
             // 実際にはCIDフォントでは使わないが、念のため実装
             (0x0000..=0xFFFF).collect()
         }
@@ -464,7 +1089,7 @@ fn derive_widths_from_font_face(
                     win_ansi_to_unicode(code as u8).and_then(|ch| face.glyph_index(ch))
                 }
             }
-            FontEncoding::IdentityH => Some(GlyphId(code)),
+            FontEncoding::IdentityH | FontEncoding::Cmap { .. } => Some(GlyphId(code)),
         };
 
         // グリフIDからhorizontal advanceを取得して1000単位に正規化
@@ -483,14 +1108,41 @@ fn derive_widths_from_font_face(
 fn parse_truetype_font(
     doc: &Document,
     font_dict: &lopdf::Dictionary,
+    substitutions: &FontSubstitutions,
 ) -> crate::error::Result<ParsedFont> {
-    // 埋め込みフォントデータが無ければシステムフォント解決
-    let (font_data, face_index) = extract_font_file2(doc, font_dict)
-        .map(|data| (data, 0u32))
-        .or_else(|_| {
+    // 埋め込みフォントデータが無ければシステムフォント解決。
+    // 埋め込みデータがttf-parserで解析できない（サブセットのテーブル欠損等）場合も
+    // BaseFontでシステムフォントに代替する（/Widthsは元のものを使うため字送りは保たれる）
+    let (font_data, face_index, substituted) = match extract_font_file2(doc, font_dict) {
+        Ok(data) => match ttf_parser::Face::parse(&data, 0) {
+            Ok(_) => (data, 0u32, false),
+            Err(parse_err) => {
+                let (data, index) = resolve_system_font_from_dict(font_dict, substitutions)
+                    .map_err(|e| {
+                        PdfMaskError::pdf_read(format!(
+                            "failed to parse TrueType: {} (no system substitute: {})",
+                            parse_err, e
+                        ))
+                    })?;
+                let base_font = font_dict
+                    .get(b"BaseFont")
+                    .and_then(Object::as_name)
+                    .map(|n| String::from_utf8_lossy(n).into_owned())
+                    .unwrap_or_default();
+                warn!(
+                    base_font,
+                    error = %parse_err,
+                    "embedded font is malformed; substituting a system font"
+                );
+                (data, index, true)
+            }
+        },
+        Err(_) => {
             debug!("embedded font data not found, trying system font resolution");
-            resolve_system_font_from_dict(font_dict)
-        })?;
+            let (data, index) = resolve_system_font_from_dict(font_dict, substitutions)?;
+            (data, index, false)
+        }
+    };
 
     let encoding = parse_encoding(doc, font_dict)?;
     let mut widths = parse_truetype_widths(doc, font_dict)?;
@@ -511,6 +1163,8 @@ fn parse_truetype_font(
         widths,
         default_width: 1000.0,
         units_per_em,
+        to_unicode: HashMap::new(),
+        substituted,
     })
 }
 
@@ -518,6 +1172,7 @@ fn parse_truetype_font(
 fn parse_type0_font(
     doc: &Document,
     font_dict: &lopdf::Dictionary,
+    substitutions: &FontSubstitutions,
 ) -> crate::error::Result<ParsedFont> {
     // DescendantFonts 配列を取得
     let descendants = font_dict
@@ -570,19 +1225,11 @@ fn parse_type0_font(
         .map(|data| (data, 0u32))
         .or_else(|_| {
             debug!("embedded CID font data not found, trying system font resolution");
-            resolve_system_font_from_dict(cid_font_dict)
+            resolve_system_font_from_dict(cid_font_dict, substitutions)
         })?;
 
     let widths = parse_cid_widths(doc, cid_font_dict)?;
-    let default_width = cid_font_dict
-        .get(b"DW")
-        .ok()
-        .and_then(|o| match o {
-            Object::Integer(i) => Some(*i as f64),
-            Object::Real(r) => Some(*r as f64),
-            _ => None,
-        })
-        .unwrap_or(1000.0);
+    let default_width = cid_default_width(cid_font_dict);
 
     let face = ttf_parser::Face::parse(&font_data, face_index)
         .map_err(|e| PdfMaskError::pdf_read(format!("failed to parse CID TrueType: {}", e)))?;
@@ -591,13 +1238,95 @@ fn parse_type0_font(
     Ok(ParsedFont {
         font_data,
         face_index,
-        encoding: FontEncoding::IdentityH,
+        encoding: parse_type0_encoding(doc, font_dict),
         widths,
         default_width,
         units_per_em,
+        to_unicode: HashMap::new(),
+        substituted: false,
+    })
+}
+
+/// アウトラインを持たない幅専用フォントを構築する。
+///
+/// 単純フォント（Type3を含む）は/Widths、Type0は子孫CIDフォントの/W・/DWを使う。
+/// Type3の幅はグリフ空間の値なので/FontMatrixで1/1000テキスト空間単位に換算する。
+/// 送り幅が分からない（/Widthsが無い単純フォント等）場合は`None`を返す。
+fn parse_width_only_font(doc: &Document, font_ref: &Object) -> Option<ParsedFont> {
+    let font_dict = doc.dereference(font_ref).ok()?.1.as_dict().ok()?;
+    let subtype = font_dict.get(b"Subtype").and_then(Object::as_name).ok()?;
+
+    let (encoding, widths, default_width) = if subtype == b"Type0" {
+        let descendants = doc
+            .dereference(font_dict.get(b"DescendantFonts").ok()?)
+            .ok()?
+            .1
+            .as_array()
+            .ok()?;
+        let cid_font_dict = doc
+            .dereference(descendants.first()?)
+            .ok()?
+            .1
+            .as_dict()
+            .ok()?;
+        let widths = parse_cid_widths(doc, cid_font_dict).ok()?;
+        (
+            parse_type0_encoding(doc, font_dict),
+            widths,
+            cid_default_width(cid_font_dict),
+        )
+    } else {
+        let mut widths = parse_truetype_widths(doc, font_dict).ok()?;
+        if widths.is_empty() {
+            return None;
+        }
+        if subtype == b"Type3" {
+            let scale = font_dict
+                .get(b"FontMatrix")
+                .and_then(Object::as_array)
+                .ok()
+                .and_then(|m| m.first())
+                .and_then(|a| operand_to_f64(a).ok())
+                .unwrap_or(0.001)
+                * 1000.0;
+            widths.values_mut().for_each(|w| *w *= scale);
+        }
+        let missing_width = font_dict
+            .get(b"FontDescriptor")
+            .ok()
+            .and_then(|obj| doc.dereference(obj).ok())
+            .and_then(|(_, obj)| obj.as_dict().ok())
+            .and_then(|descriptor| descriptor.get(b"MissingWidth").ok())
+            .and_then(|w| operand_to_f64(w).ok())
+            .unwrap_or(0.0);
+        (parse_encoding(doc, font_dict).ok()?, widths, missing_width)
+    };
+
+    Some(ParsedFont {
+        font_data: Vec::new(),
+        face_index: 0,
+        encoding,
+        widths,
+        default_width,
+        units_per_em: 1000,
+        to_unicode: parse_to_unicode(doc, font_dict),
+        substituted: false,
     })
 }
 
+/// CIDフォントの/DW（既定の送り幅）。省略時は1000。
+fn cid_default_width(cid_font_dict: &lopdf::Dictionary) -> f64 {
+    cid_font_dict
+        .get(b"DW")
+        .ok()
+        .and_then(|o| match o {
+            Object::Integer(i) => Some(*i as f64),
+            Object::Real(r) => Some(*r as f64),
+            _ => None,
+        })
+        .unwrap_or(1000.0)
+}
+
 /// FontDescriptorからFontFile2ストリームを取得・解凍
 fn extract_font_file2(
     doc: &Document,
@@ -628,13 +1357,9 @@ fn extract_font_file2(
         .map_err(|e| PdfMaskError::pdf_read(e.to_string()))?;
 
     match stream_obj {
-        Object::Stream(stream) => {
-            let mut stream = stream.clone();
-            stream.decompress().map_err(|e| {
-                PdfMaskError::pdf_read(format!("FontFile2 decompress failed: {}", e))
-            })?;
-            Ok(stream.content)
-        }
+        // フィルタ無しのストリームはそのまま返す（lopdfのdecompressは空を返すため使わない）
+        Object::Stream(stream) => crate::pdf::filters::decode_stream(stream)
+            .map_err(|e| PdfMaskError::pdf_read(format!("FontFile2 decompress failed: {}", e))),
         _ => Err(PdfMaskError::pdf_read("FontFile2 is not a stream")),
     }
 }
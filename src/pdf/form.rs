@@ -0,0 +1,542 @@
+// AcroFormフィールドの平坦化
+//
+// ウィジェット注釈の通常外観（/AP /N）をページコンテンツに焼き込み、ウィジェットと
+// /AcroFormを取り除く。外観ストリームの無いフィールドは、現在の値（/V）から外観を生成する。
+
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream, dictionary};
+use tracing::debug;
+
+use crate::pdf::reader::{PdfReader, inherited_page_attribute};
+
+/// 注釈フラグ: Hidden
+const ANNOT_FLAG_HIDDEN: i64 = 1 << 1;
+/// 注釈フラグ: NoView
+const ANNOT_FLAG_NO_VIEW: i64 = 1 << 5;
+/// フィールドフラグ: Multiline（テキスト）
+const FIELD_FLAG_MULTILINE: i64 = 1 << 12;
+/// フィールドフラグ: Radio（ボタン）
+const FIELD_FLAG_RADIO: i64 = 1 << 15;
+/// フィールドフラグ: Pushbutton（ボタン）
+const FIELD_FLAG_PUSHBUTTON: i64 = 1 << 16;
+/// フィールドの/Parentをたどる深さの上限（循環参照対策）
+const MAX_FIELD_DEPTH: usize = 32;
+/// /DAのフォントサイズが0（自動）のときの上限
+const AUTO_FONT_SIZE: f64 = 12.0;
+/// 生成する外観の枠からの余白(pt)
+const TEXT_PADDING: f64 = 2.0;
+
+/// 生成した外観で使うフォントのリソース名
+const TEXT_FONT_NAME: &str = "Helv";
+const SYMBOL_FONT_NAME: &str = "ZaDb";
+
+impl PdfReader {
+    /// 文書のAcroFormフィールドを平坦化し、ページに焼き込んだウィジェット数を返す。
+    ///
+    /// 以降のページ解析・書き出しは平坦化後の文書に対して行われる。
+    pub fn flatten_form_fields(&mut self) -> crate::error::Result<usize> {
+        flatten_form_fields(self.document_mut())
+    }
+}
+
+/// 文書のAcroFormフィールドを平坦化し、ページに焼き込んだウィジェット数を返す。
+///
+/// 各ページのウィジェット注釈の通常外観をページコンテンツの末尾に描画し、
+/// ウィジェットを/Annotsから、/AcroFormをCatalogから取り除く。
+/// 外観ストリームが無い場合は、テキスト・選択フィールドは値の文字列を、
+/// チェックボックス・ラジオボタンはオンのときにチェック記号を描く外観を生成する。
+/// 非表示（Hidden/NoView）のウィジェットは描画せずに取り除く。
+pub fn flatten_form_fields(doc: &mut Document) -> crate::error::Result<usize> {
+    let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    let mut flattened = 0;
+    for page_id in pages {
+        flattened += flatten_page_widgets(doc, page_id)?;
+    }
+    if let Ok(catalog) = doc.catalog_mut() {
+        catalog.remove(b"AcroForm");
+    }
+    debug!(widgets = flattened, "flattened form fields");
+    Ok(flattened)
+}
+
+/// ページに描画するウィジェット外観
+struct WidgetDraw {
+    /// 既存の外観ストリームへの参照、または生成した外観
+    appearance: Appearance,
+    /// 外観を配置する矩形 [x0, y0, x1, y1]
+    rect: [f64; 4],
+}
+
+enum Appearance {
+    Existing(ObjectId),
+    Generated(Stream),
+}
+
+/// 1ページのウィジェットを平坦化し、描画した数を返す。
+fn flatten_page_widgets(doc: &mut Document, page_id: ObjectId) -> crate::error::Result<usize> {
+    let page = doc.get_dictionary(page_id)?;
+    let Some(Object::Array(annots)) = page
+        .get(b"Annots")
+        .ok()
+        .and_then(|obj| doc.dereference(obj).ok())
+        .map(|(_, obj)| obj)
+    else {
+        return Ok(0);
+    };
+
+    let mut kept = Vec::new();
+    let mut draws = Vec::new();
+    for annot in annots {
+        let Some(widget) = resolve_dict(doc, annot).filter(|dict| is_widget(dict)) else {
+            kept.push(annot.clone());
+            continue;
+        };
+        let flags = widget.get(b"F").and_then(Object::as_i64).unwrap_or(0);
+        if flags & (ANNOT_FLAG_HIDDEN | ANNOT_FLAG_NO_VIEW) != 0 {
+            continue;
+        }
+        let Some(rect) = annot_rect(doc, widget) else {
+            continue;
+        };
+        let appearance = match normal_appearance(doc, widget) {
+            NormalAppearance::Stream(id) => Appearance::Existing(id),
+            NormalAppearance::Off => continue,
+            NormalAppearance::Missing => match generate_appearance(doc, widget, rect) {
+                Some(stream) => Appearance::Generated(stream),
+                None => continue,
+            },
+        };
+        draws.push(WidgetDraw { appearance, rect });
+    }
+    if kept.len() == annots.len() {
+        return Ok(0);
+    }
+
+    let mut resources = page_resources(doc, page_id);
+    let mut xobjects = resources
+        .get(b"XObject")
+        .ok()
+        .and_then(|obj| resolve_dict(doc, obj))
+        .cloned()
+        .unwrap_or_default();
+    let mut content = String::from("Q\n");
+    let mut drawn = 0;
+    for draw in draws {
+        let (form_id, bbox, matrix) = match draw.appearance {
+            Appearance::Existing(id) => {
+                let Some(dict) = doc
+                    .get_object(id)
+                    .ok()
+                    .and_then(|obj| obj.as_stream().ok())
+                    .map(|stream| &stream.dict)
+                else {
+                    continue;
+                };
+                let Some(bbox) = number_array::<4>(doc, dict.get(b"BBox").ok()) else {
+                    continue;
+                };
+                let matrix = number_array::<6>(doc, dict.get(b"Matrix").ok())
+                    .unwrap_or([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+                (id, bbox, matrix)
+            }
+            Appearance::Generated(stream) => {
+                let [x0, y0, x1, y1] = draw.rect;
+                let bbox = [0.0, 0.0, x1 - x0, y1 - y0];
+                (doc.add_object(stream), bbox, [1.0, 0.0, 0.0, 1.0, 0.0, 0.0])
+            }
+        };
+        let Some([a, d, e, f]) = appearance_placement(bbox, matrix, draw.rect) else {
+            continue;
+        };
+        let name = unused_name(&xobjects, "FlatForm");
+        xobjects.set(name.as_bytes(), Object::Reference(form_id));
+        content.push_str(&format!("q {a} 0 0 {d} {e} {f} cm /{name} Do Q\n"));
+        drawn += 1;
+    }
+    resources.set("XObject", xobjects);
+
+    // 元のコンテンツがグラフィックス状態を戻さずに終わっても外観の位置がずれないよう、
+    // q … Qで囲んでから外観を描く
+    let open_id = doc.add_object(Stream::new(dictionary! {}, b"q\n".to_vec()));
+    let close_id = doc.add_object(Stream::new(dictionary! {}, content.into_bytes()));
+    let page = doc.get_dictionary_mut(page_id)?;
+    let mut contents: Vec<Object> = vec![open_id.into()];
+    match page.get(b"Contents").ok().cloned() {
+        Some(Object::Array(streams)) => contents.extend(streams),
+        Some(existing @ Object::Reference(_)) => contents.push(existing),
+        _ => {}
+    }
+    contents.push(close_id.into());
+    page.set("Contents", contents);
+    page.set("Resources", resources);
+    if kept.is_empty() {
+        page.remove(b"Annots");
+    } else {
+        page.set("Annots", kept);
+    }
+
+    debug!(?page_id, widgets = drawn, "flattened page widgets");
+    Ok(drawn)
+}
+
+fn is_widget(dict: &Dictionary) -> bool {
+    dict.get(b"Subtype")
+        .and_then(Object::as_name)
+        .is_ok_and(|subtype| subtype == b"Widget")
+}
+
+/// 注釈の/Rectを正規化して返す。
+fn annot_rect(doc: &Document, annot: &Dictionary) -> Option<[f64; 4]> {
+    let [x1, y1, x2, y2] = number_array::<4>(doc, annot.get(b"Rect").ok())?;
+    let rect = [x1.min(x2), y1.min(y2), x1.max(x2), y1.max(y2)];
+    (rect[2] > rect[0] && rect[3] > rect[1]).then_some(rect)
+}
+
+enum NormalAppearance {
+    /// 描画する外観ストリーム
+    Stream(ObjectId),
+    /// 外観はあるが現在の状態（/AS）に対応するものが無い（チェックボックスのOff等）
+    Off,
+    /// /AP /Nが無い
+    Missing,
+}
+
+/// ウィジェットの通常外観を求める。状態別の外観辞書は/AS（無ければフィールドの/V）で選ぶ。
+fn normal_appearance(doc: &Document, widget: &Dictionary) -> NormalAppearance {
+    let Some(normal) = widget
+        .get(b"AP")
+        .ok()
+        .and_then(|ap| resolve_dict(doc, ap))
+        .and_then(|ap| ap.get(b"N").ok())
+    else {
+        return NormalAppearance::Missing;
+    };
+    let Object::Reference(id) = normal else {
+        // 状態別の外観辞書（直接辞書）
+        return match normal
+            .as_dict()
+            .ok()
+            .and_then(|states| appearance_state(doc, widget).and_then(|s| states.get(&s).ok()))
+        {
+            Some(Object::Reference(id)) => NormalAppearance::Stream(*id),
+            _ => NormalAppearance::Off,
+        };
+    };
+    match doc.get_object(*id) {
+        Ok(Object::Stream(_)) => NormalAppearance::Stream(*id),
+        Ok(Object::Dictionary(states)) => {
+            match appearance_state(doc, widget).and_then(|state| states.get(&state).ok()) {
+                Some(Object::Reference(id)) => NormalAppearance::Stream(*id),
+                _ => NormalAppearance::Off,
+            }
+        }
+        _ => NormalAppearance::Missing,
+    }
+}
+
+/// ウィジェットの外観状態名（/AS、無ければフィールドの/V）。
+fn appearance_state(doc: &Document, widget: &Dictionary) -> Option<Vec<u8>> {
+    widget
+        .get(b"AS")
+        .ok()
+        .or_else(|| field_attribute(doc, widget, b"V"))
+        .and_then(|obj| obj.as_name().ok())
+        .map(<[u8]>::to_vec)
+}
+
+/// フィールドの継承可能な属性（/FT、/V、/DA、/Ff）を/Parentをたどって取得する。
+fn field_attribute<'a>(doc: &'a Document, field: &'a Dictionary, key: &[u8]) -> Option<&'a Object> {
+    let mut current = field;
+    for _ in 0..MAX_FIELD_DEPTH {
+        if let Ok(value) = current.get(key) {
+            return doc.dereference(value).ok().map(|(_, obj)| obj);
+        }
+        current = resolve_dict(doc, current.get(b"Parent").ok()?)?;
+    }
+    None
+}
+
+/// 外観ストリームの無いウィジェットに、フィールドの現在の値から外観を生成する。
+///
+/// 値が無い・オフのボタン・プッシュボタンは`None`。
+fn generate_appearance(doc: &Document, widget: &Dictionary, rect: [f64; 4]) -> Option<Stream> {
+    let (width, height) = (rect[2] - rect[0], rect[3] - rect[1]);
+    let flags = field_attribute(doc, widget, b"Ff")
+        .and_then(|ff| ff.as_i64().ok())
+        .unwrap_or(0);
+    let field_type = field_attribute(doc, widget, b"FT")?.as_name().ok()?;
+    let da = field_attribute(doc, widget, b"DA")
+        .and_then(|da| da.as_str().ok())
+        .map(|da| String::from_utf8_lossy(da).into_owned())
+        .unwrap_or_default();
+    let appearance = DefaultAppearance::parse(&da);
+
+    let (content, font_name, base_font) = match field_type {
+        b"Tx" | b"Ch" => {
+            let value = match field_attribute(doc, widget, b"V")? {
+                // 複数選択のリストボックスは先頭の値を表示する
+                Object::Array(values) => values.first().and_then(|v| doc.dereference(v).ok())?.1,
+                value => value,
+            };
+            let text = decode_text_string(value.as_str().ok()?);
+            if text.is_empty() {
+                return None;
+            }
+            let multiline = field_type == b"Tx" && flags & FIELD_FLAG_MULTILINE != 0;
+            (
+                text_appearance(&text, &appearance, width, height, multiline),
+                TEXT_FONT_NAME,
+                "Helvetica",
+            )
+        }
+        b"Btn" if flags & FIELD_FLAG_PUSHBUTTON == 0 => {
+            let state = match widget.get(b"AS") {
+                Ok(state) => state.as_name().ok()?,
+                // ラジオボタンは/ASが無いとどのウィジェットがオンか分からない
+                Err(_) if flags & FIELD_FLAG_RADIO != 0 => return None,
+                Err(_) => field_attribute(doc, widget, b"V")?.as_name().ok()?,
+            };
+            if state == b"Off" {
+                return None;
+            }
+            let radio = flags & FIELD_FLAG_RADIO != 0;
+            (
+                check_appearance(radio, &appearance, width, height),
+                SYMBOL_FONT_NAME,
+                "ZapfDingbats",
+            )
+        }
+        _ => return None,
+    };
+
+    let mut font = dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => base_font,
+    };
+    if base_font == "Helvetica" {
+        font.set("Encoding", "WinAnsiEncoding");
+    }
+    Some(Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Form",
+            "BBox" => vec![0.into(), 0.into(), width.into(), height.into()],
+            "Resources" => dictionary! {
+                "Font" => dictionary! { font_name => font },
+            },
+        },
+        content.into_bytes(),
+    ))
+}
+
+/// /DA（既定の外観文字列）のうち、生成する外観で使うフォントサイズと色
+struct DefaultAppearance {
+    /// 0は自動
+    font_size: f64,
+    /// 色設定オペレータ（例: `0 0 1 rg`）。未指定なら黒
+    color: String,
+}
+
+impl DefaultAppearance {
+    /// `/Helv 12 Tf 0 g`形式の/DAを解析する。フォント名は使わない（標準フォントで描く）。
+    fn parse(da: &str) -> Self {
+        let tokens: Vec<&str> = da.split_whitespace().collect();
+        let mut font_size = 0.0;
+        let mut color = String::from("0 g");
+        for (i, token) in tokens.iter().enumerate() {
+            let operands = match *token {
+                "Tf" => {
+                    font_size = i
+                        .checked_sub(1)
+                        .and_then(|j| tokens[j].parse::<f64>().ok())
+                        .filter(|size| size.is_finite() && *size >= 0.0)
+                        .unwrap_or(0.0);
+                    continue;
+                }
+                "g" => 1,
+                "rg" => 3,
+                "k" => 4,
+                _ => continue,
+            };
+            if let Some(start) = i.checked_sub(operands)
+                && tokens[start..i].iter().all(|t| t.parse::<f64>().is_ok())
+            {
+                color = tokens[start..=i].join(" ");
+            }
+        }
+        DefaultAppearance { font_size, color }
+    }
+}
+
+/// テキスト・選択フィールドの外観（左寄せ。複数行フィールドは改行で折り返す）。
+fn text_appearance(
+    text: &str,
+    appearance: &DefaultAppearance,
+    width: f64,
+    height: f64,
+    multiline: bool,
+) -> String {
+    let lines: Vec<&str> = if multiline {
+        text.lines().collect()
+    } else {
+        vec![text.lines().next().unwrap_or_default()]
+    };
+    let font_size = if appearance.font_size > 0.0 {
+        appearance.font_size
+    } else if multiline {
+        AUTO_FONT_SIZE
+    } else {
+        ((height - 2.0 * TEXT_PADDING) * 0.8).clamp(4.0, AUTO_FONT_SIZE)
+    };
+    let leading = font_size * 1.15;
+    // 1行なら縦中央、複数行なら上から並べる（0.22はHelveticaの下がり幅の近似）
+    let first_baseline = if multiline {
+        height - TEXT_PADDING - font_size
+    } else {
+        (height - font_size) / 2.0 + 0.22 * font_size
+    };
+
+    let mut content = format!(
+        "/Tx BMC q {TEXT_PADDING} {TEXT_PADDING} {} {} re W n BT /{TEXT_FONT_NAME} {font_size} Tf {} {leading} TL {TEXT_PADDING} {first_baseline} Td",
+        (width - 2.0 * TEXT_PADDING).max(0.0),
+        (height - 2.0 * TEXT_PADDING).max(0.0),
+        appearance.color,
+    );
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            content.push_str(" T*");
+        }
+        content.push_str(&format!(" {} Tj", pdf_literal(&encode_win_ansi(line))));
+    }
+    content.push_str(" ET Q EMC");
+    content
+}
+
+/// オンのチェックボックス（✔）・ラジオボタン（●）の外観。ZapfDingbatsで中央に描く。
+fn check_appearance(
+    radio: bool,
+    appearance: &DefaultAppearance,
+    width: f64,
+    height: f64,
+) -> String {
+    // ZapfDingbatsの'4'(✔)と'l'(●)のグリフ幅（1000分率）
+    let (glyph, glyph_width) = if radio { ("l", 0.791) } else { ("4", 0.846) };
+    let font_size = if appearance.font_size > 0.0 {
+        appearance.font_size
+    } else {
+        width.min(height) * 0.8
+    };
+    let x = (width - font_size * glyph_width) / 2.0;
+    // 記号の高さは約0.7em
+    let y = (height - font_size * 0.7) / 2.0;
+    format!(
+        "q BT /{SYMBOL_FONT_NAME} {font_size} Tf {} {x} {y} Td ({glyph}) Tj ET Q",
+        appearance.color
+    )
+}
+
+/// 外観のBBox（/Matrixで変換後）を注釈の矩形に合わせる変換`[a, d, e, f]`を返す
+/// （PDF 32000-1 12.5.5）。
+fn appearance_placement(bbox: [f64; 4], matrix: [f64; 6], rect: [f64; 4]) -> Option<[f64; 4]> {
+    let [a, b, c, d, e, f] = matrix;
+    let corners = [
+        (bbox[0], bbox[1]),
+        (bbox[2], bbox[1]),
+        (bbox[0], bbox[3]),
+        (bbox[2], bbox[3]),
+    ]
+    .map(|(x, y)| (a * x + c * y + e, b * x + d * y + f));
+    let x_min = corners.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+    let x_max = corners
+        .iter()
+        .map(|p| p.0)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let y_min = corners.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+    let y_max = corners
+        .iter()
+        .map(|p| p.1)
+        .fold(f64::NEG_INFINITY, f64::max);
+    if x_max - x_min <= 0.0 || y_max - y_min <= 0.0 {
+        return None;
+    }
+    let sx = (rect[2] - rect[0]) / (x_max - x_min);
+    let sy = (rect[3] - rect[1]) / (y_max - y_min);
+    Some([sx, sy, rect[0] - x_min * sx, rect[1] - y_min * sy])
+}
+
+/// ページのResources（継承分を含む）を直接辞書として複製する。
+fn page_resources(doc: &Document, page_id: ObjectId) -> Dictionary {
+    doc.get_dictionary(page_id)
+        .ok()
+        .and_then(|page| inherited_page_attribute(doc, page, b"Resources"))
+        .and_then(|resources| resolve_dict(doc, &resources).cloned())
+        .unwrap_or_default()
+}
+
+/// 辞書に無い`{prefix}{n}`形式の名前を返す。
+fn unused_name(dict: &Dictionary, prefix: &str) -> String {
+    (0..)
+        .map(|n| format!("{prefix}{n}"))
+        .find(|name| !dict.has(name.as_bytes()))
+        .expect("unbounded name search")
+}
+
+/// 数値配列（間接参照を含む）を固定長の配列として読む。
+fn number_array<const N: usize>(doc: &Document, obj: Option<&Object>) -> Option<[f64; N]> {
+    let values = doc.dereference(obj?).ok()?.1.as_array().ok()?;
+    let values: Vec<f64> = values
+        .iter()
+        .map(|v| {
+            doc.dereference(v)
+                .ok()
+                .and_then(|(_, v)| v.as_float().ok())
+                .map(f64::from)
+        })
+        .collect::<Option<_>>()?;
+    values.try_into().ok()
+}
+
+/// テキスト文字列（UTF-16BE BOM付き、またはPDFDocEncoding）を復号する。
+///
+/// PDFDocEncodingはLatin-1として扱う（0x80〜0x9Fの差異は無視する）。
+fn decode_text_string(bytes: &[u8]) -> String {
+    match bytes.strip_prefix(&[0xFE, 0xFF]) {
+        Some(utf16) => {
+            let units: Vec<u16> = utf16
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        None => bytes.iter().map(|&b| b as char).collect(),
+    }
+}
+
+/// WinAnsiEncoding（Latin-1の範囲）に変換する。範囲外の文字は`?`にする。
+fn encode_win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+        .collect()
+}
+
+/// バイト列をPDFのリテラル文字列`(...)`として書く。
+fn pdf_literal(bytes: &[u8]) -> String {
+    let mut out = String::from("(");
+    for &b in bytes {
+        match b {
+            b'(' | b')' | b'\\' => {
+                out.push('\\');
+                out.push(b as char);
+            }
+            0x20..=0x7E => out.push(b as char),
+            _ => out.push_str(&format!("\\{b:03o}")),
+        }
+    }
+    out.push(')');
+    out
+}
+
+/// 間接参照を解決して辞書として返す。
+fn resolve_dict<'a>(doc: &'a Document, obj: &'a Object) -> Option<&'a Dictionary> {
+    doc.dereference(obj).ok()?.1.as_dict().ok()
+}
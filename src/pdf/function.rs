@@ -0,0 +1,793 @@
+// PDF関数（PDF仕様 §7.10）の評価
+//
+// 色空間のtint変換やシェーディングで使う。対応する関数タイプ:
+// - タイプ0: サンプリング（線形補間。/Order 3も線形で近似する）
+// - タイプ2: 指数補間
+// - タイプ3: 区分（stitching）
+// - タイプ4: PostScript計算機
+
+use lopdf::{Dictionary, Document, Object};
+
+use crate::error::PdfMaskError;
+
+/// 関数のネスト（タイプ3）とPostScriptスタックの上限
+const MAX_DEPTH: usize = 16;
+const MAX_STACK: usize = 100;
+/// サンプリング関数の入力数とサンプル数の上限
+const MAX_SAMPLED_INPUTS: usize = 8;
+const MAX_SAMPLES: usize = 1 << 24;
+
+/// 解析済みのPDF関数
+#[derive(Debug, Clone)]
+pub struct PdfFunction {
+    domain: Vec<(f64, f64)>,
+    range: Option<Vec<(f64, f64)>>,
+    kind: FunctionKind,
+}
+
+#[derive(Debug, Clone)]
+enum FunctionKind {
+    Sampled {
+        size: Vec<usize>,
+        bits_per_sample: u32,
+        encode: Vec<(f64, f64)>,
+        decode: Vec<(f64, f64)>,
+        /// 格子点ごとに出力数ぶん並んだ生のサンプル値（最初の入力が最も速く変化する）
+        samples: Vec<u32>,
+    },
+    Exponential {
+        c0: Vec<f64>,
+        c1: Vec<f64>,
+        n: f64,
+    },
+    Stitching {
+        functions: Vec<PdfFunction>,
+        bounds: Vec<f64>,
+        encode: Vec<(f64, f64)>,
+    },
+    PostScript(Vec<PsOp>),
+}
+
+/// PostScript計算機の命令
+#[derive(Debug, Clone, PartialEq)]
+enum PsOp {
+    Number(f64),
+    Bool(bool),
+    Operator(String),
+    If(Vec<PsOp>),
+    IfElse(Vec<PsOp>, Vec<PsOp>),
+}
+
+/// PostScriptスタックの値
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PsValue {
+    Number(f64),
+    Bool(bool),
+}
+
+impl PdfFunction {
+    /// 関数オブジェクト（辞書またはストリーム、間接参照可）を解析する。
+    pub fn parse(doc: &Document, obj: &Object) -> crate::error::Result<Self> {
+        Self::parse_at_depth(doc, obj, 0)
+    }
+
+    fn parse_at_depth(doc: &Document, obj: &Object, depth: usize) -> crate::error::Result<Self> {
+        if depth > MAX_DEPTH {
+            return Err(PdfMaskError::pdf_read("PDF function nesting too deep"));
+        }
+        let (_, obj) = doc.dereference(obj)?;
+        let (dict, stream) = match obj {
+            Object::Dictionary(dict) => (dict, None),
+            Object::Stream(stream) => (&stream.dict, Some(stream)),
+            _ => return Err(PdfMaskError::pdf_read("PDF function is not a dictionary")),
+        };
+
+        let domain = pairs(doc, dict, b"Domain")?
+            .ok_or_else(|| PdfMaskError::pdf_read("PDF function without /Domain"))?;
+        let range = pairs(doc, dict, b"Range")?;
+
+        let kind = match dict.get(b"FunctionType").and_then(Object::as_i64) {
+            Ok(0) => {
+                let stream = stream
+                    .ok_or_else(|| PdfMaskError::pdf_read("sampled function is not a stream"))?;
+                let range = range
+                    .as_ref()
+                    .ok_or_else(|| PdfMaskError::pdf_read("sampled function without /Range"))?;
+                parse_sampled(doc, stream, &domain, range)?
+            }
+            Ok(2) => FunctionKind::Exponential {
+                c0: numbers(doc, dict, b"C0")?.unwrap_or_else(|| vec![0.0]),
+                c1: numbers(doc, dict, b"C1")?.unwrap_or_else(|| vec![1.0]),
+                n: number(doc, dict.get(b"N")?)?,
+            },
+            Ok(3) => {
+                let functions = match doc.dereference(dict.get(b"Functions")?)?.1 {
+                    Object::Array(arr) => arr
+                        .iter()
+                        .map(|f| Self::parse_at_depth(doc, f, depth + 1))
+                        .collect::<crate::error::Result<Vec<_>>>()?,
+                    _ => return Err(PdfMaskError::pdf_read("invalid /Functions")),
+                };
+                let bounds = numbers(doc, dict, b"Bounds")?.unwrap_or_default();
+                let encode = pairs(doc, dict, b"Encode")?.unwrap_or_default();
+                if functions.is_empty()
+                    || bounds.len() + 1 != functions.len()
+                    || encode.len() != functions.len()
+                {
+                    return Err(PdfMaskError::pdf_read(
+                        "stitching function /Functions, /Bounds and /Encode disagree",
+                    ));
+                }
+                FunctionKind::Stitching {
+                    functions,
+                    bounds,
+                    encode,
+                }
+            }
+            Ok(4) => {
+                let stream = stream.ok_or_else(|| {
+                    PdfMaskError::pdf_read("PostScript calculator function is not a stream")
+                })?;
+                let program = crate::pdf::filters::decode_stream(stream)?;
+                FunctionKind::PostScript(parse_postscript(&program)?)
+            }
+            Ok(other) => {
+                return Err(PdfMaskError::pdf_read(format!(
+                    "unsupported PDF function type: {}",
+                    other
+                )));
+            }
+            Err(e) => return Err(PdfMaskError::pdf_read(e.to_string())),
+        };
+
+        Ok(PdfFunction {
+            domain,
+            range,
+            kind,
+        })
+    }
+
+    /// 入力値を評価して出力値を返す。入力は/Domain、出力は/Rangeに切り詰める。
+    pub fn evaluate(&self, input: &[f64]) -> crate::error::Result<Vec<f64>> {
+        if input.len() != self.domain.len() {
+            return Err(PdfMaskError::pdf_read(format!(
+                "PDF function takes {} input(s), got {}",
+                self.domain.len(),
+                input.len()
+            )));
+        }
+        let input: Vec<f64> = input
+            .iter()
+            .zip(&self.domain)
+            .map(|(&x, &(lo, hi))| clip(x, lo, hi))
+            .collect();
+
+        let mut output = match &self.kind {
+            FunctionKind::Sampled {
+                size,
+                bits_per_sample,
+                encode,
+                decode,
+                samples,
+            } => evaluate_sampled(
+                &input,
+                &self.domain,
+                size,
+                *bits_per_sample,
+                encode,
+                decode,
+                samples,
+            ),
+            FunctionKind::Exponential { c0, c1, n } => {
+                let x = input[0];
+                c0.iter()
+                    .zip(c1)
+                    .map(|(a, b)| a + x.powf(*n) * (b - a))
+                    .collect()
+            }
+            FunctionKind::Stitching {
+                functions,
+                bounds,
+                encode,
+            } => {
+                let x = input[0];
+                let (lo, hi) = self.domain[0];
+                let k = bounds.iter().take_while(|&&b| x >= b).count();
+                let start = if k == 0 { lo } else { bounds[k - 1] };
+                let end = if k == bounds.len() { hi } else { bounds[k] };
+                let (e0, e1) = encode[k];
+                let t = if end > start {
+                    e0 + (x - start) * (e1 - e0) / (end - start)
+                } else {
+                    e0
+                };
+                functions[k].evaluate(&[t])?
+            }
+            FunctionKind::PostScript(program) => {
+                let mut stack: Vec<PsValue> = input.iter().map(|&x| PsValue::Number(x)).collect();
+                run_postscript(program, &mut stack)?;
+                stack
+                    .into_iter()
+                    .map(|v| match v {
+                        PsValue::Number(x) => Ok(x),
+                        PsValue::Bool(_) => Err(PdfMaskError::pdf_read(
+                            "PostScript function left a boolean on the stack",
+                        )),
+                    })
+                    .collect::<crate::error::Result<Vec<f64>>>()?
+            }
+        };
+
+        if let Some(range) = &self.range {
+            if output.len() != range.len() {
+                return Err(PdfMaskError::pdf_read(format!(
+                    "PDF function returned {} output(s), /Range declares {}",
+                    output.len(),
+                    range.len()
+                )));
+            }
+            for (y, &(lo, hi)) in output.iter_mut().zip(range) {
+                *y = clip(*y, lo, hi);
+            }
+        }
+        Ok(output)
+    }
+}
+
+fn clip(x: f64, lo: f64, hi: f64) -> f64 {
+    x.max(lo).min(hi)
+}
+
+/// `x`を`[x_min, x_max]`から`[y_min, y_max]`へ線形に写す（PDF仕様のInterpolate）。
+fn interpolate(x: f64, (x_min, x_max): (f64, f64), (y_min, y_max): (f64, f64)) -> f64 {
+    if x_max == x_min {
+        return y_min;
+    }
+    y_min + (x - x_min) * (y_max - y_min) / (x_max - x_min)
+}
+
+/// タイプ0（サンプリング）関数の/Size・/BitsPerSample・/Encode・/Decodeとサンプル列を読む。
+fn parse_sampled(
+    doc: &Document,
+    stream: &lopdf::Stream,
+    domain: &[(f64, f64)],
+    range: &[(f64, f64)],
+) -> crate::error::Result<FunctionKind> {
+    let dict = &stream.dict;
+    let size: Vec<usize> = numbers(doc, dict, b"Size")?
+        .ok_or_else(|| PdfMaskError::pdf_read("sampled function without /Size"))?
+        .into_iter()
+        .map(|n| n as usize)
+        .collect();
+    if size.len() != domain.len() || size.len() > MAX_SAMPLED_INPUTS || size.contains(&0) {
+        return Err(PdfMaskError::pdf_read("invalid /Size in sampled function"));
+    }
+    let bits_per_sample = match dict.get(b"BitsPerSample").and_then(Object::as_i64) {
+        Ok(bps @ (1 | 2 | 4 | 8 | 12 | 16 | 24 | 32)) => bps as u32,
+        _ => {
+            return Err(PdfMaskError::pdf_read(
+                "invalid /BitsPerSample in sampled function",
+            ));
+        }
+    };
+    let encode = match pairs(doc, dict, b"Encode")? {
+        Some(encode) if encode.len() == size.len() => encode,
+        Some(_) => return Err(PdfMaskError::pdf_read("/Encode does not match /Size")),
+        None => size.iter().map(|&n| (0.0, (n - 1) as f64)).collect(),
+    };
+    let decode = match pairs(doc, dict, b"Decode")? {
+        Some(decode) if decode.len() == range.len() => decode,
+        Some(_) => return Err(PdfMaskError::pdf_read("/Decode does not match /Range")),
+        None => range.to_vec(),
+    };
+
+    let count = size
+        .iter()
+        .try_fold(range.len(), |acc, &n| acc.checked_mul(n))
+        .filter(|&count| count <= MAX_SAMPLES)
+        .ok_or_else(|| PdfMaskError::pdf_read("sampled function has too many samples"))?;
+    let data = crate::pdf::filters::decode_stream(stream)?;
+    let samples = unpack_samples(&data, bits_per_sample, count).ok_or_else(|| {
+        PdfMaskError::pdf_read("sampled function data is shorter than /Size requires")
+    })?;
+
+    Ok(FunctionKind::Sampled {
+        size,
+        bits_per_sample,
+        encode,
+        decode,
+        samples,
+    })
+}
+
+/// MSBファーストのビット列から`count`個のサンプルを取り出す。足りなければ`None`。
+fn unpack_samples(data: &[u8], bits_per_sample: u32, count: usize) -> Option<Vec<u32>> {
+    let bits = bits_per_sample as usize;
+    if data.len() * 8 < count * bits {
+        return None;
+    }
+    let samples = (0..count)
+        .map(|i| {
+            let start = i * bits;
+            (start..start + bits).fold(0u32, |acc, bit| {
+                let byte = data[bit / 8];
+                (acc << 1) | u32::from((byte >> (7 - bit % 8)) & 1)
+            })
+        })
+        .collect();
+    Some(samples)
+}
+
+/// サンプル格子を多重線形補間して評価する。
+fn evaluate_sampled(
+    input: &[f64],
+    domain: &[(f64, f64)],
+    size: &[usize],
+    bits_per_sample: u32,
+    encode: &[(f64, f64)],
+    decode: &[(f64, f64)],
+    samples: &[u32],
+) -> Vec<f64> {
+    let outputs = decode.len();
+    // 各入力の格子上の位置（下側の格子点と、上側への重み）
+    let cells: Vec<(usize, f64)> = input
+        .iter()
+        .zip(domain)
+        .zip(encode.iter().zip(size))
+        .map(|((&x, &domain), (&encode, &n))| {
+            let e = clip(interpolate(x, domain, encode), 0.0, (n - 1) as f64);
+            let lower = (e.floor() as usize).min(n - 1);
+            (lower, e - lower as f64)
+        })
+        .collect();
+
+    let mut sums = vec![0.0; outputs];
+    for corner in 0..1usize << cells.len() {
+        let mut weight = 1.0;
+        let mut index = 0;
+        let mut stride = 1;
+        for (i, (&(lower, frac), &n)) in cells.iter().zip(size).enumerate() {
+            let upper = corner >> i & 1 == 1;
+            weight *= if upper { frac } else { 1.0 - frac };
+            index += (if upper { (lower + 1).min(n - 1) } else { lower }) * stride;
+            stride *= n;
+        }
+        if weight == 0.0 {
+            continue;
+        }
+        for (j, sum) in sums.iter_mut().enumerate() {
+            *sum += weight * f64::from(samples[index * outputs + j]);
+        }
+    }
+
+    let max_sample = ((1u64 << bits_per_sample) - 1) as f64;
+    sums.into_iter()
+        .zip(decode)
+        .map(|(s, &decode)| interpolate(s, (0.0, max_sample), decode))
+        .collect()
+}
+
+/// 数値（整数・実数、間接参照可）を読む。
+fn number(doc: &Document, obj: &Object) -> crate::error::Result<f64> {
+    match doc.dereference(obj)?.1 {
+        Object::Integer(i) => Ok(*i as f64),
+        Object::Real(r) => Ok(*r as f64),
+        _ => Err(PdfMaskError::pdf_read("expected a number in PDF function")),
+    }
+}
+
+/// 数値配列を読む（キーが無ければ`None`）。
+fn numbers(
+    doc: &Document,
+    dict: &Dictionary,
+    key: &[u8],
+) -> crate::error::Result<Option<Vec<f64>>> {
+    let Ok(obj) = dict.get(key) else {
+        return Ok(None);
+    };
+    match doc.dereference(obj)?.1 {
+        Object::Array(arr) => arr
+            .iter()
+            .map(|v| number(doc, v))
+            .collect::<crate::error::Result<Vec<f64>>>()
+            .map(Some),
+        _ => Err(PdfMaskError::pdf_read(format!(
+            "/{} is not an array",
+            String::from_utf8_lossy(key)
+        ))),
+    }
+}
+
+/// `[min0 max0 min1 max1 ...]`形式の配列を組にして読む。
+fn pairs(
+    doc: &Document,
+    dict: &Dictionary,
+    key: &[u8],
+) -> crate::error::Result<Option<Vec<(f64, f64)>>> {
+    let Some(values) = numbers(doc, dict, key)? else {
+        return Ok(None);
+    };
+    if values.len() % 2 != 0 {
+        return Err(PdfMaskError::pdf_read(format!(
+            "/{} has an odd number of values",
+            String::from_utf8_lossy(key)
+        )));
+    }
+    Ok(Some(values.chunks(2).map(|c| (c[0], c[1])).collect()))
+}
+
+/// `{ ... }`で囲まれたPostScript計算機プログラムを命令列に変換する。
+fn parse_postscript(program: &[u8]) -> crate::error::Result<Vec<PsOp>> {
+    let text = String::from_utf8_lossy(program);
+    let mut tokens = text
+        .replace('{', " { ")
+        .replace('}', " } ")
+        .split_whitespace()
+        .map(str::to_string)
+        .collect::<Vec<_>>()
+        .into_iter();
+    if tokens.next().as_deref() != Some("{") {
+        return Err(PdfMaskError::pdf_read(
+            "PostScript function must start with '{'",
+        ));
+    }
+    parse_ps_block(&mut tokens, 0)
+}
+
+/// 対応する`}`までの命令列を読む。`if`/`ifelse`は直前のブロックを取り込む。
+fn parse_ps_block(
+    tokens: &mut impl Iterator<Item = String>,
+    depth: usize,
+) -> crate::error::Result<Vec<PsOp>> {
+    if depth > MAX_DEPTH {
+        return Err(PdfMaskError::pdf_read(
+            "PostScript function nesting too deep",
+        ));
+    }
+    let mut ops = Vec::new();
+    let mut blocks: Vec<Vec<PsOp>> = Vec::new();
+    while let Some(token) = tokens.next() {
+        match token.as_str() {
+            "}" => {
+                if !blocks.is_empty() {
+                    return Err(PdfMaskError::pdf_read(
+                        "PostScript block not followed by if/ifelse",
+                    ));
+                }
+                return Ok(ops);
+            }
+            "{" => blocks.push(parse_ps_block(tokens, depth + 1)?),
+            "if" => {
+                let block = blocks
+                    .pop()
+                    .filter(|_| blocks.is_empty())
+                    .ok_or_else(|| PdfMaskError::pdf_read("'if' without one block"))?;
+                ops.push(PsOp::If(block));
+            }
+            "ifelse" => {
+                if blocks.len() != 2 {
+                    return Err(PdfMaskError::pdf_read("'ifelse' without two blocks"));
+                }
+                let else_block = blocks.pop().unwrap_or_default();
+                let then_block = blocks.pop().unwrap_or_default();
+                ops.push(PsOp::IfElse(then_block, else_block));
+            }
+            _ if !blocks.is_empty() => {
+                return Err(PdfMaskError::pdf_read(
+                    "PostScript block not followed by if/ifelse",
+                ));
+            }
+            "true" => ops.push(PsOp::Bool(true)),
+            "false" => ops.push(PsOp::Bool(false)),
+            other => match other.parse::<f64>() {
+                Ok(x) => ops.push(PsOp::Number(x)),
+                Err(_) => ops.push(PsOp::Operator(other.to_string())),
+            },
+        }
+    }
+    Err(PdfMaskError::pdf_read("unterminated PostScript function"))
+}
+
+fn run_postscript(program: &[PsOp], stack: &mut Vec<PsValue>) -> crate::error::Result<()> {
+    for op in program {
+        if stack.len() > MAX_STACK {
+            return Err(PdfMaskError::pdf_read("PostScript function stack overflow"));
+        }
+        match op {
+            PsOp::Number(x) => stack.push(PsValue::Number(*x)),
+            PsOp::Bool(b) => stack.push(PsValue::Bool(*b)),
+            PsOp::If(block) => {
+                if pop_bool(stack)? {
+                    run_postscript(block, stack)?;
+                }
+            }
+            PsOp::IfElse(then_block, else_block) => {
+                let block = if pop_bool(stack)? {
+                    then_block
+                } else {
+                    else_block
+                };
+                run_postscript(block, stack)?;
+            }
+            PsOp::Operator(name) => apply_ps_operator(name, stack)?,
+        }
+    }
+    Ok(())
+}
+
+fn ps_underflow() -> PdfMaskError {
+    PdfMaskError::pdf_read("PostScript function stack underflow")
+}
+
+fn pop(stack: &mut Vec<PsValue>) -> crate::error::Result<PsValue> {
+    stack.pop().ok_or_else(ps_underflow)
+}
+
+fn pop_num(stack: &mut Vec<PsValue>) -> crate::error::Result<f64> {
+    match pop(stack)? {
+        PsValue::Number(x) => Ok(x),
+        PsValue::Bool(_) => Err(PdfMaskError::pdf_read(
+            "PostScript operator expected a number",
+        )),
+    }
+}
+
+fn pop_bool(stack: &mut Vec<PsValue>) -> crate::error::Result<bool> {
+    match pop(stack)? {
+        PsValue::Bool(b) => Ok(b),
+        PsValue::Number(_) => Err(PdfMaskError::pdf_read(
+            "PostScript operator expected a boolean",
+        )),
+    }
+}
+
+fn apply_ps_operator(name: &str, stack: &mut Vec<PsValue>) -> crate::error::Result<()> {
+    use PsValue::{Bool, Number};
+
+    let unary = |stack: &mut Vec<PsValue>, f: fn(f64) -> f64| -> crate::error::Result<()> {
+        let x = pop_num(stack)?;
+        stack.push(Number(f(x)));
+        Ok(())
+    };
+    let binary = |stack: &mut Vec<PsValue>, f: fn(f64, f64) -> f64| -> crate::error::Result<()> {
+        let b = pop_num(stack)?;
+        let a = pop_num(stack)?;
+        stack.push(Number(f(a, b)));
+        Ok(())
+    };
+    let compare = |stack: &mut Vec<PsValue>, f: fn(f64, f64) -> bool| -> crate::error::Result<()> {
+        let b = pop_num(stack)?;
+        let a = pop_num(stack)?;
+        stack.push(Bool(f(a, b)));
+        Ok(())
+    };
+
+    match name {
+        // 算術
+        "abs" => unary(stack, f64::abs)?,
+        "neg" => unary(stack, |x| -x)?,
+        "ceiling" => unary(stack, f64::ceil)?,
+        "floor" => unary(stack, f64::floor)?,
+        "round" => unary(stack, |x| (x + 0.5).floor())?,
+        "truncate" | "cvi" => unary(stack, f64::trunc)?,
+        "cvr" => unary(stack, |x| x)?,
+        "sqrt" => unary(stack, f64::sqrt)?,
+        "sin" => unary(stack, |x| x.to_radians().sin())?,
+        "cos" => unary(stack, |x| x.to_radians().cos())?,
+        "ln" => unary(stack, f64::ln)?,
+        "log" => unary(stack, f64::log10)?,
+        "add" => binary(stack, |a, b| a + b)?,
+        "sub" => binary(stack, |a, b| a - b)?,
+        "mul" => binary(stack, |a, b| a * b)?,
+        "div" => binary(stack, |a, b| if b == 0.0 { 0.0 } else { a / b })?,
+        "idiv" => binary(stack, |a, b| {
+            if b.trunc() == 0.0 {
+                0.0
+            } else {
+                (a.trunc() / b.trunc()).trunc()
+            }
+        })?,
+        "mod" => binary(stack, |a, b| {
+            if b.trunc() == 0.0 {
+                0.0
+            } else {
+                a.trunc() % b.trunc()
+            }
+        })?,
+        "exp" => binary(stack, f64::powf)?,
+        "atan" => binary(stack, |num, den| {
+            let deg = num.atan2(den).to_degrees();
+            if deg < 0.0 { deg + 360.0 } else { deg }
+        })?,
+        // 比較
+        "eq" | "ne" => {
+            let b = pop(stack)?;
+            let a = pop(stack)?;
+            stack.push(Bool((a == b) == (name == "eq")));
+        }
+        "gt" => compare(stack, |a, b| a > b)?,
+        "ge" => compare(stack, |a, b| a >= b)?,
+        "lt" => compare(stack, |a, b| a < b)?,
+        "le" => compare(stack, |a, b| a <= b)?,
+        // 論理・ビット演算（真偽値か整数）
+        "not" => match pop(stack)? {
+            Bool(b) => stack.push(Bool(!b)),
+            Number(x) => stack.push(Number(!(x as i64) as f64)),
+        },
+        "and" | "or" | "xor" => {
+            let b = pop(stack)?;
+            let a = pop(stack)?;
+            let result = match (a, b) {
+                (Bool(a), Bool(b)) => Bool(match name {
+                    "and" => a && b,
+                    "or" => a || b,
+                    _ => a ^ b,
+                }),
+                (Number(a), Number(b)) => {
+                    let (a, b) = (a as i64, b as i64);
+                    Number(match name {
+                        "and" => a & b,
+                        "or" => a | b,
+                        _ => a ^ b,
+                    } as f64)
+                }
+                _ => {
+                    return Err(PdfMaskError::pdf_read(format!(
+                        "PostScript '{}' operand types differ",
+                        name
+                    )));
+                }
+            };
+            stack.push(result);
+        }
+        "bitshift" => {
+            let shift = pop_num(stack)? as i64;
+            let x = pop_num(stack)? as i64;
+            let shifted = if shift >= 0 {
+                x.checked_shl(shift as u32).unwrap_or(0)
+            } else {
+                x.checked_shr(shift.unsigned_abs() as u32).unwrap_or(0)
+            };
+            stack.push(Number(shifted as f64));
+        }
+        // スタック操作
+        "pop" => {
+            pop(stack)?;
+        }
+        "dup" => {
+            let top = *stack.last().ok_or_else(ps_underflow)?;
+            stack.push(top);
+        }
+        "exch" => {
+            let b = pop(stack)?;
+            let a = pop(stack)?;
+            stack.push(b);
+            stack.push(a);
+        }
+        "copy" => {
+            let n = pop_num(stack)? as usize;
+            if n > stack.len() {
+                return Err(ps_underflow());
+            }
+            stack.extend_from_within(stack.len() - n..);
+        }
+        "index" => {
+            let n = pop_num(stack)? as usize;
+            let value = *stack
+                .len()
+                .checked_sub(n + 1)
+                .and_then(|i| stack.get(i))
+                .ok_or_else(ps_underflow)?;
+            stack.push(value);
+        }
+        "roll" => {
+            let j = pop_num(stack)? as i64;
+            let n = pop_num(stack)? as usize;
+            if n > stack.len() {
+                return Err(ps_underflow());
+            }
+            if n > 0 {
+                let start = stack.len() - n;
+                let shift = j.rem_euclid(n as i64) as usize;
+                stack[start..].rotate_right(shift);
+            }
+        }
+        other => {
+            return Err(PdfMaskError::pdf_read(format!(
+                "unsupported PostScript operator: {}",
+                other
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{Stream, dictionary};
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len(), "{actual:?} vs {expected:?}");
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-6, "{actual:?} vs {expected:?}");
+        }
+    }
+
+    #[test]
+    fn test_exponential_function_interpolates_c0_to_c1() {
+        let doc = Document::with_version("1.5");
+        let obj = Object::Dictionary(dictionary! {
+            "FunctionType" => 2,
+            "Domain" => vec![0.into(), 1.into()],
+            "C0" => vec![1.into(), 1.into(), 1.into()],
+            "C1" => vec![0.into(), 0.5.into(), 1.into()],
+            "N" => 2,
+        });
+        let f = PdfFunction::parse(&doc, &obj).expect("parse");
+
+        assert_close(&f.evaluate(&[0.0]).expect("eval"), &[1.0, 1.0, 1.0]);
+        assert_close(&f.evaluate(&[0.5]).expect("eval"), &[0.75, 0.875, 1.0]);
+        assert_close(&f.evaluate(&[1.0]).expect("eval"), &[0.0, 0.5, 1.0]);
+        // 入力は/Domainに切り詰める
+        assert_close(&f.evaluate(&[2.0]).expect("eval"), &[0.0, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn test_sampled_function_interpolates_between_samples() {
+        // 2x2格子・8bit・出力1: (0,0)=0, (1,0)=255, (0,1)=255, (1,1)=0
+        let doc = Document::with_version("1.5");
+        let obj = Object::Stream(Stream::new(
+            dictionary! {
+                "FunctionType" => 0,
+                "Domain" => vec![0.into(), 1.into(), 0.into(), 1.into()],
+                "Range" => vec![0.into(), 1.into()],
+                "Size" => vec![2.into(), 2.into()],
+                "BitsPerSample" => 8,
+            },
+            vec![0, 255, 255, 0],
+        ));
+        let f = PdfFunction::parse(&doc, &obj).expect("parse");
+
+        assert_close(&f.evaluate(&[0.0, 0.0]).expect("eval"), &[0.0]);
+        assert_close(&f.evaluate(&[1.0, 0.0]).expect("eval"), &[1.0]);
+        assert_close(&f.evaluate(&[0.0, 1.0]).expect("eval"), &[1.0]);
+        assert_close(&f.evaluate(&[1.0, 1.0]).expect("eval"), &[0.0]);
+        assert_close(&f.evaluate(&[0.5, 0.0]).expect("eval"), &[0.5]);
+        assert_close(&f.evaluate(&[0.5, 0.5]).expect("eval"), &[0.5]);
+        assert_close(&f.evaluate(&[0.25, 0.0]).expect("eval"), &[0.25]);
+    }
+
+    #[test]
+    fn test_sampled_function_with_short_data_errors() {
+        let doc = Document::with_version("1.5");
+        let obj = Object::Stream(Stream::new(
+            dictionary! {
+                "FunctionType" => 0,
+                "Domain" => vec![0.into(), 1.into()],
+                "Range" => vec![0.into(), 1.into(), 0.into(), 1.into()],
+                "Size" => vec![4.into()],
+                "BitsPerSample" => 16,
+            },
+            vec![0; 15],
+        ));
+        assert!(PdfFunction::parse(&doc, &obj).is_err());
+    }
+
+    #[test]
+    fn test_postscript_function_evaluates_ifelse() {
+        let doc = Document::with_version("1.5");
+        let obj = Object::Stream(Stream::new(
+            dictionary! {
+                "FunctionType" => 4,
+                "Domain" => vec![0.into(), 1.into()],
+                "Range" => vec![0.into(), 1.into()],
+            },
+            b"{ dup 0.5 gt { pop 1 } { 2 mul } ifelse }".to_vec(),
+        ));
+        let f = PdfFunction::parse(&doc, &obj).expect("parse");
+
+        assert_close(&f.evaluate(&[0.25]).expect("eval"), &[0.5]);
+        assert_close(&f.evaluate(&[0.75]).expect("eval"), &[1.0]);
+    }
+}
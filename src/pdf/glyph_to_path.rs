@@ -29,20 +29,6 @@ pub fn glyph_to_pdf_path(params: &GlyphPathParams) -> Vec<u8> {
         return Vec::new();
     }
 
-    let combined = params.text_matrix.multiply(params.ctm);
-    let scale = params.font_size / params.units_per_em as f64;
-    let tz = params.horizontal_scaling / 100.0;
-    let text_rise = params.text_rise;
-
-    let transform = |x: f64, y: f64| -> (f64, f64) {
-        // PDF §9.4.4: Trm = [Tfs×Th 0 0; 0 Tfs 0; 0 Trise 1] × Tm × CTM
-        let sx = x * scale * tz;
-        let sy = y * scale + text_rise;
-        let px = combined.a * sx + combined.c * sy + combined.e;
-        let py = combined.b * sx + combined.d * sy + combined.f;
-        (px, py)
-    };
-
     let mut buf = String::new();
 
     // NOTE: `String` への `fmt::Write` は失敗しないため、以下の `.unwrap()` は安全。
@@ -84,6 +70,43 @@ pub fn glyph_to_pdf_path(params: &GlyphPathParams) -> Vec<u8> {
         }
     }
 
+    write_path_ops(&mut buf, params);
+
+    // fill
+    buf.push_str("f\n");
+
+    // Q: グラフィックス状態を復元
+    buf.push_str("Q\n");
+
+    buf.into_bytes()
+}
+
+/// グリフアウトラインのパス構築演算子（m/l/c/h）だけを返す。
+///
+/// 塗り・色設定・q/Qを含まないため、テキストのクリップ（Tr 4〜7）のように
+/// 複数のグリフをまとめて1つのパスにする用途に使う。座標変換は[`glyph_to_pdf_path`]と同じ。
+pub fn glyph_to_path_ops(params: &GlyphPathParams) -> Vec<u8> {
+    let mut buf = String::new();
+    write_path_ops(&mut buf, params);
+    buf.into_bytes()
+}
+
+/// グリフアウトラインを変換したパス構築演算子を`buf`に書き込む。
+fn write_path_ops(buf: &mut String, params: &GlyphPathParams) {
+    let combined = params.text_matrix.multiply(params.ctm);
+    let scale = params.font_size / params.units_per_em as f64;
+    let tz = params.horizontal_scaling / 100.0;
+    let text_rise = params.text_rise;
+
+    let transform = |x: f64, y: f64| -> (f64, f64) {
+        // PDF §9.4.4: Trm = [Tfs×Th 0 0; 0 Tfs 0; 0 Trise 1] × Tm × CTM
+        let sx = x * scale * tz;
+        let sy = y * scale + text_rise;
+        let px = combined.a * sx + combined.c * sy + combined.e;
+        let py = combined.b * sx + combined.d * sy + combined.f;
+        (px, py)
+    };
+
     // パス演算子を生成（current pointを追跡してQuad→Cubic変換に使用）
     let mut current_x = 0.0_f64;
     let mut current_y = 0.0_f64;
@@ -94,13 +117,13 @@ pub fn glyph_to_pdf_path(params: &GlyphPathParams) -> Vec<u8> {
                 current_x = *x;
                 current_y = *y;
                 let (px, py) = transform(*x, *y);
-                write_point_op(&mut buf, px, py, "m");
+                write_point_op(buf, px, py, "m");
             }
             PathOp::LineTo(x, y) => {
                 current_x = *x;
                 current_y = *y;
                 let (px, py) = transform(*x, *y);
-                write_point_op(&mut buf, px, py, "l");
+                write_point_op(buf, px, py, "l");
             }
             PathOp::QuadTo(x1, y1, x2, y2) => {
                 // 二次ベジェ(p0, p1, p2) → 三次ベジェ(p0, cp1, cp2, p2)
@@ -119,7 +142,7 @@ pub fn glyph_to_pdf_path(params: &GlyphPathParams) -> Vec<u8> {
                 let (px1, py1) = transform(cp1x, cp1y);
                 let (px2, py2) = transform(cp2x, cp2y);
                 let (px3, py3) = transform(*x2, *y2);
-                write_curve_op(&mut buf, px1, py1, px2, py2, px3, py3, "c");
+                write_curve_op(buf, px1, py1, px2, py2, px3, py3, "c");
             }
             PathOp::CubicTo(x1, y1, x2, y2, x, y) => {
                 // CFF/CFF2の3次ベジェ: そのままPDF `c` 演算子に出力（変換不要）
@@ -128,21 +151,13 @@ pub fn glyph_to_pdf_path(params: &GlyphPathParams) -> Vec<u8> {
                 let (px3, py3) = transform(*x, *y);
                 current_x = *x;
                 current_y = *y;
-                write_curve_op(&mut buf, px1, py1, px2, py2, px3, py3, "c");
+                write_curve_op(buf, px1, py1, px2, py2, px3, py3, "c");
             }
             PathOp::Close => {
                 buf.push_str("h\n");
             }
         }
     }
-
-    // fill
-    buf.push_str("f\n");
-
-    // Q: グラフィックス状態を復元
-    buf.push_str("Q\n");
-
-    buf.into_bytes()
 }
 
 /// FillColor の輝度を計算する (BW閾値判定用)
@@ -0,0 +1,221 @@
+// PDF/AのOutputIntentに埋め込むICCプロファイルの生成
+//
+// 外部ファイルに依存しないよう、ディスプレイクラス（mntr）のICC v2.1プロファイルを
+// その場で組み立てる。生成結果は常に同じバイト列になる（日付・IDは固定）。
+
+/// D50白色点（PCSイルミナント）
+const D50: [f64; 3] = [0.9642, 1.0, 0.8249];
+
+/// D50に色順応したsRGBの原色（ICC sRGBプロファイルのrXYZ/gXYZ/bXYZ）
+const SRGB_PRIMARIES: [[f64; 3]; 3] = [
+    [0.4361, 0.2225, 0.0139],
+    [0.3851, 0.7169, 0.0971],
+    [0.1431, 0.0606, 0.7141],
+];
+
+/// sRGBのトーンカーブを近似する曲線の点数
+const SRGB_CURVE_POINTS: usize = 1024;
+
+/// 出力インテントの色空間
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputIntentProfile {
+    /// Gray Gamma 2.2（1成分）
+    GrayGamma22,
+    /// sRGB IEC61966-2.1（3成分）
+    Srgb,
+}
+
+impl OutputIntentProfile {
+    /// プロファイルの成分数（ICCBasedストリームの/N）
+    pub fn components(self) -> u8 {
+        match self {
+            OutputIntentProfile::GrayGamma22 => 1,
+            OutputIntentProfile::Srgb => 3,
+        }
+    }
+
+    /// プロファイルの説明（OutputIntentの/OutputConditionIdentifierにも使う）
+    pub fn description(self) -> &'static str {
+        match self {
+            OutputIntentProfile::GrayGamma22 => "Gray Gamma 2.2",
+            OutputIntentProfile::Srgb => "sRGB IEC61966-2.1",
+        }
+    }
+
+    /// ICCプロファイルのバイト列を生成する。
+    pub fn icc_profile(self) -> Vec<u8> {
+        let mut tags: Vec<([u8; 4], Vec<u8>)> = vec![
+            (*b"desc", text_description(self.description())),
+            (*b"cprt", text("No copyright, use freely")),
+            (*b"wtpt", xyz(D50)),
+        ];
+        let color_space = match self {
+            OutputIntentProfile::GrayGamma22 => {
+                tags.push((*b"kTRC", gamma_curve(2.2)));
+                *b"GRAY"
+            }
+            OutputIntentProfile::Srgb => {
+                let curve = srgb_curve();
+                for (sig, primary) in [*b"rXYZ", *b"gXYZ", *b"bXYZ"]
+                    .into_iter()
+                    .zip(SRGB_PRIMARIES)
+                {
+                    tags.push((sig, xyz(primary)));
+                }
+                for sig in [*b"rTRC", *b"gTRC", *b"bTRC"] {
+                    tags.push((sig, curve.clone()));
+                }
+                *b"RGB "
+            }
+        };
+        build_profile(color_space, &tags)
+    }
+}
+
+/// ヘッダ・タグテーブル・タグデータを並べてプロファイルを組み立てる。
+fn build_profile(color_space: [u8; 4], tags: &[([u8; 4], Vec<u8>)]) -> Vec<u8> {
+    const HEADER_LEN: usize = 128;
+    let table_len = 4 + 12 * tags.len();
+
+    let mut table = Vec::with_capacity(table_len);
+    table.extend_from_slice(&(tags.len() as u32).to_be_bytes());
+    let mut data = Vec::new();
+    for (sig, tag) in tags {
+        let offset = HEADER_LEN + table_len + data.len();
+        table.extend_from_slice(sig);
+        table.extend_from_slice(&(offset as u32).to_be_bytes());
+        table.extend_from_slice(&(tag.len() as u32).to_be_bytes());
+        data.extend_from_slice(tag);
+        // タグデータは4バイト境界に揃える
+        data.resize(data.len().next_multiple_of(4), 0);
+    }
+
+    let size = HEADER_LEN + table.len() + data.len();
+    let mut profile = Vec::with_capacity(size);
+    profile.extend_from_slice(&(size as u32).to_be_bytes());
+    profile.extend_from_slice(&[0; 4]); // 優先CMM
+    profile.extend_from_slice(&[0x02, 0x10, 0x00, 0x00]); // バージョン2.1
+    profile.extend_from_slice(b"mntr");
+    profile.extend_from_slice(&color_space);
+    profile.extend_from_slice(b"XYZ ");
+    for part in [2000u16, 1, 1, 0, 0, 0] {
+        profile.extend_from_slice(&part.to_be_bytes()); // 作成日時（固定）
+    }
+    profile.extend_from_slice(b"acsp");
+    profile.extend_from_slice(&[0; 24]); // プラットフォーム・フラグ・製造元・モデル・属性
+    profile.extend_from_slice(&[0; 4]); // レンダリングインテント: perceptual
+    for value in D50 {
+        profile.extend_from_slice(&s15_fixed16(value));
+    }
+    profile.extend_from_slice(&[0; 4]); // 作成者
+    profile.resize(HEADER_LEN, 0);
+    profile.extend_from_slice(&table);
+    profile.extend_from_slice(&data);
+    profile
+}
+
+fn s15_fixed16(value: f64) -> [u8; 4] {
+    ((value * 65536.0).round() as i32).to_be_bytes()
+}
+
+fn xyz(value: [f64; 3]) -> Vec<u8> {
+    let mut tag = b"XYZ \0\0\0\0".to_vec();
+    for v in value {
+        tag.extend_from_slice(&s15_fixed16(v));
+    }
+    tag
+}
+
+fn text(value: &str) -> Vec<u8> {
+    let mut tag = b"text\0\0\0\0".to_vec();
+    tag.extend_from_slice(value.as_bytes());
+    tag.push(0);
+    tag
+}
+
+/// textDescriptionType（ASCIIのみ。Unicode・ScriptCodeは空）
+fn text_description(value: &str) -> Vec<u8> {
+    let mut tag = b"desc\0\0\0\0".to_vec();
+    tag.extend_from_slice(&(value.len() as u32 + 1).to_be_bytes());
+    tag.extend_from_slice(value.as_bytes());
+    tag.push(0);
+    tag.extend_from_slice(&[0; 8]); // Unicode言語コード・文字数
+    tag.extend_from_slice(&[0; 3]); // ScriptCodeコード・文字数
+    tag.extend_from_slice(&[0; 67]);
+    tag
+}
+
+/// 単一のガンマ値で表すcurveType（u8Fixed8Number）
+fn gamma_curve(gamma: f64) -> Vec<u8> {
+    let mut tag = b"curv\0\0\0\0".to_vec();
+    tag.extend_from_slice(&1u32.to_be_bytes());
+    tag.extend_from_slice(&((gamma * 256.0).round() as u16).to_be_bytes());
+    tag
+}
+
+/// sRGBのトーンカーブ（線形部分を含む）をサンプリングしたcurveType
+fn srgb_curve() -> Vec<u8> {
+    let mut tag = b"curv\0\0\0\0".to_vec();
+    tag.extend_from_slice(&(SRGB_CURVE_POINTS as u32).to_be_bytes());
+    for i in 0..SRGB_CURVE_POINTS {
+        let v = i as f64 / (SRGB_CURVE_POINTS - 1) as f64;
+        let linear = if v <= 0.04045 {
+            v / 12.92
+        } else {
+            ((v + 0.055) / 1.055).powf(2.4)
+        };
+        tag.extend_from_slice(&((linear * 65535.0).round() as u16).to_be_bytes());
+    }
+    tag
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_u32(data: &[u8], offset: usize) -> u32 {
+        u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    /// タグテーブルから署名の一覧を読む。
+    fn tag_signatures(profile: &[u8]) -> Vec<[u8; 4]> {
+        let count = read_u32(profile, 128) as usize;
+        (0..count)
+            .map(|i| {
+                let entry = 132 + 12 * i;
+                let offset = read_u32(profile, entry + 4) as usize;
+                let size = read_u32(profile, entry + 8) as usize;
+                assert!(offset.is_multiple_of(4) && offset + size <= profile.len());
+                profile[entry..entry + 4].try_into().unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_gray_profile_header_and_tags() {
+        let profile = OutputIntentProfile::GrayGamma22.icc_profile();
+        assert_eq!(read_u32(&profile, 0) as usize, profile.len());
+        assert_eq!(&profile[12..20], b"mntrGRAY");
+        assert_eq!(&profile[36..40], b"acsp");
+        assert_eq!(
+            tag_signatures(&profile),
+            vec![*b"desc", *b"cprt", *b"wtpt", *b"kTRC"]
+        );
+    }
+
+    #[test]
+    fn test_srgb_profile_has_colorants_and_curves() {
+        let profile = OutputIntentProfile::Srgb.icc_profile();
+        assert_eq!(read_u32(&profile, 0) as usize, profile.len());
+        assert_eq!(&profile[16..20], b"RGB ");
+        let tags = tag_signatures(&profile);
+        for sig in [b"rXYZ", b"gXYZ", b"bXYZ", b"rTRC", b"gTRC", b"bTRC"] {
+            assert!(tags.contains(sig));
+        }
+        assert_eq!(
+            OutputIntentProfile::Srgb.icc_profile(),
+            profile,
+            "profile bytes should be deterministic"
+        );
+    }
+}
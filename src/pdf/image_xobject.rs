@@ -1,16 +1,32 @@
 // Phase 7: 画像XObjectのデコード/再エンコード、重なり検出・塗りつぶし
 
+use crate::config::job::{ImageColorSpace, JpegSizeMismatch, ResampleFilter};
+use crate::config::settings::ImageClassDpi;
+#[cfg(feature = "mrc")]
+use crate::config::settings::Jbig2Options;
 use crate::error::PdfMaskError;
 #[cfg(feature = "mrc")]
 use crate::mrc::jbig2;
 use crate::mrc::jpeg;
-use crate::pdf::content_stream::BBox;
-use flate2::read::ZlibDecoder;
-use image::{DynamicImage, GrayImage, RgbImage};
+use crate::pdf::content_stream::{BBox, ImagePlacement, Matrix, operand_to_f64};
+use crate::pdf::filters;
+use image::metadata::Orientation;
+use image::{DynamicImage, GrayImage, ImageDecoder, RgbImage};
 use lopdf::Object;
-use std::io::Read;
 use tracing::{debug, warn};
 
+/// 非圧縮サイズに対する展開上限の余裕（バイト）
+const DECODE_SIZE_SLACK: u64 = 4096;
+
+/// 線画とみなす画像で、主要な輝度レベルが占める画素の割合の下限
+const LINE_ART_COVERAGE: f64 = 0.95;
+
+/// 線画とみなす画像の主要な輝度レベル数の上限
+const LINE_ART_MAX_LEVELS: usize = 8;
+
+/// 主要な輝度レベルとみなす画素の割合の下限
+const SIGNIFICANT_LEVEL_SHARE: f64 = 0.005;
+
 /// 2つのBBoxの重なりを判定する。
 ///
 /// 辺が接しているだけの場合は重ならないと判定する（strict inequality）。
@@ -18,6 +34,52 @@ pub fn bbox_overlaps(a: &BBox, b: &BBox) -> bool {
     !(a.x_max <= b.x_min || b.x_max <= a.x_min || a.y_max <= b.y_min || b.y_max <= a.y_min)
 }
 
+/// 画像の内容の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageClass {
+    /// 写真・スキャン画像など連続階調の画像
+    Photo,
+    /// 図・グラフ・文字など少数の色で描かれた画像
+    LineArt,
+}
+
+impl ImageClassDpi {
+    /// `class`の画像の実効DPIの上限。種類ごとの指定が無ければ`default`。
+    pub fn max_dpi(&self, class: ImageClass, default: Option<u32>) -> Option<u32> {
+        match class {
+            ImageClass::Photo => self.photo.or(default),
+            ImageClass::LineArt => self.line_art.or(default),
+        }
+    }
+}
+
+/// 画像を写真と線画に分類する。
+///
+/// 輝度のヒストグラムで、画素の0.5%以上を占めるレベルを主要なレベルとする。
+/// 主要なレベルが8個以下で、それらが画素の95%以上を占めれば線画（アンチエイリアスの
+/// 縁などの中間調はわずか）、そうでなければ写真とする。
+pub fn classify_image(img: &DynamicImage) -> ImageClass {
+    let gray = img.to_luma8();
+    let total = gray.width() as u64 * gray.height() as u64;
+    if total == 0 {
+        return ImageClass::LineArt;
+    }
+    let mut histogram = [0u64; 256];
+    for pixel in gray.pixels() {
+        histogram[pixel.0[0] as usize] += 1;
+    }
+    let significant: Vec<u64> = histogram
+        .into_iter()
+        .filter(|&count| count as f64 >= total as f64 * SIGNIFICANT_LEVEL_SHARE)
+        .collect();
+    let coverage = significant.iter().sum::<u64>() as f64 / total as f64;
+    if significant.len() <= LINE_ART_MAX_LEVELS && coverage >= LINE_ART_COVERAGE {
+        ImageClass::LineArt
+    } else {
+        ImageClass::Photo
+    }
+}
+
 /// 画像XObjectのメタデータ
 #[derive(Debug, Clone)]
 pub struct ImageMeta {
@@ -26,6 +88,18 @@ pub struct ImageMeta {
     pub bits_per_component: u8,
     pub color_space: String,
     pub filter: Option<String>,
+    /// JPXデータに埋め込まれたアルファの扱い（/SMaskInData）。0=使わない、
+    /// 1=アルファを含む、2=色成分がアルファで乗算済み。/SMaskがあれば0とする
+    pub smask_in_data: i64,
+    /// デコードを許可する画素数の上限。宣言と実寸が異なるJPEGの実寸にも適用する
+    pub max_pixels: u64,
+    /// /ImageMask trueのステンシルマスク。1bitのDeviceGrayとしてデコードし、
+    /// 0（黒）が描画する画素、255（白）が描画しない画素になる
+    pub image_mask: bool,
+    /// ステンシルマスクの/Decodeが[1 0]（サンプル値1が描画する画素）
+    pub mask_decode_inverted: bool,
+    /// /ImageMaskと矛盾するため無視した/ColorSpace（不正な画像辞書）
+    pub ignored_color_space: Option<String>,
 }
 
 /// リダクション済み画像データ
@@ -35,6 +109,13 @@ pub struct RedactedImage {
     pub filter: String,
     pub color_space: String,
     pub bits_per_component: u8,
+    /// 再エンコード後の画像幅(px)。ダウンサンプリング時は元の/Widthと異なる
+    pub width: u32,
+    /// 再エンコード後の画像高さ(px)
+    pub height: u32,
+    /// JPXの/SMaskInDataから取り出したアルファ（8bit DeviceGray、FlateDecode圧縮済み）。
+    /// 再エンコード後の画像と同じ寸法で、別の/SMaskとして出力する
+    pub smask: Option<Vec<u8>>,
 }
 
 /// 最適圧縮済み画像データ
@@ -44,21 +125,35 @@ pub struct OptimizedImage {
     pub filter: &'static str,
     pub color_space: &'static str,
     pub bits_per_component: u8,
+    /// 画像幅(px)
+    pub width: u32,
+    /// 画像高さ(px)
+    pub height: u32,
 }
 
 /// 画像XObjectのストリームから画像メタデータを読み取る。
-fn read_image_meta(stream: &lopdf::Stream) -> crate::error::Result<ImageMeta> {
+///
+/// 宣言された画素数(Width×Height)が`max_pixels`を超える画像は、
+/// デコード（メモリ確保）前にエラーとする。
+fn read_image_meta(stream: &lopdf::Stream, max_pixels: u64) -> crate::error::Result<ImageMeta> {
     let dict = &stream.dict;
 
     let width = dict_get_u32(dict, b"Width")?;
     let height = dict_get_u32(dict, b"Height")?;
+    let pixels = width as u64 * height as u64;
+    if pixels > max_pixels {
+        return Err(PdfMaskError::image_xobject(format!(
+            "Image {}x{} ({} pixels) exceeds max_image_pixels limit of {}",
+            width, height, pixels, max_pixels
+        )));
+    }
     // BitsPerComponent: missing keyの場合のみデフォルト8、型エラーは伝播
-    let bits_per_component = match dict.get(b"BitsPerComponent") {
+    let mut bits_per_component = match dict.get(b"BitsPerComponent") {
         Ok(_) => dict_get_u32(dict, b"BitsPerComponent")? as u8,
         Err(_) => 8,
     };
 
-    let color_space = match dict.get(b"ColorSpace") {
+    let mut color_space = match dict.get(b"ColorSpace") {
         Ok(obj) => match obj {
             Object::Name(name) => String::from_utf8_lossy(name).to_string(),
             _ => "DeviceRGB".to_string(),
@@ -66,6 +161,36 @@ fn read_image_meta(stream: &lopdf::Stream) -> crate::error::Result<ImageMeta> {
         Err(_) => "DeviceRGB".to_string(),
     };
 
+    // /ImageMask trueなら/ColorSpaceと/BitsPerComponentの宣言より優先する
+    // （PDF 32000-1 8.9.6.2: ステンシルマスクは色空間を持たず1bit）
+    let image_mask = matches!(dict.get(b"ImageMask"), Ok(Object::Boolean(true)));
+    let mut ignored_color_space = None;
+    let mut mask_decode_inverted = false;
+    if image_mask {
+        if dict.has(b"ColorSpace") {
+            warn!(
+                color_space = %color_space,
+                "image has both /ImageMask true and /ColorSpace; treating it as a stencil mask"
+            );
+            ignored_color_space = Some(color_space);
+        }
+        if dict.has(b"BitsPerComponent") && bits_per_component != 1 {
+            warn!(
+                bits_per_component,
+                "stencil mask declares /BitsPerComponent other than 1; using 1"
+            );
+        }
+        bits_per_component = 1;
+        color_space = "DeviceGray".to_string();
+        mask_decode_inverted = dict
+            .get(b"Decode")
+            .and_then(Object::as_array)
+            .ok()
+            .and_then(|decode| decode.first())
+            .and_then(|first| operand_to_f64(first).ok())
+            .is_some_and(|first| first == 1.0);
+    }
+
     let filter = match dict.get(b"Filter") {
         Ok(Object::Name(name)) => Some(String::from_utf8_lossy(name).to_string()),
         Ok(Object::Array(arr)) => {
@@ -81,15 +206,39 @@ fn read_image_meta(stream: &lopdf::Stream) -> crate::error::Result<ImageMeta> {
         _ => None,
     };
 
+    // /SMaskが指定されていれば/SMaskInDataは無視される（PDF 32000-1 8.9.5.4）
+    let smask_in_data = match (dict.get(b"SMask"), dict.get(b"SMaskInData")) {
+        (Err(_), Ok(Object::Integer(value))) if matches!(value, 1 | 2) => *value,
+        _ => 0,
+    };
+
     Ok(ImageMeta {
         width,
         height,
         bits_per_component,
         color_space,
         filter,
+        smask_in_data,
+        max_pixels,
+        image_mask,
+        mask_decode_inverted,
+        ignored_color_space,
     })
 }
 
+impl ImageMeta {
+    /// 宣言どおりの非圧縮画素データのバイト数。
+    fn raw_len(&self) -> u64 {
+        let components: u64 = match self.color_space.as_str() {
+            "DeviceGray" => 1,
+            "DeviceCMYK" => 4,
+            _ => 3,
+        };
+        let row_bits = self.width as u64 * components * self.bits_per_component.max(1) as u64;
+        row_bits.div_ceil(8) * self.height as u64
+    }
+}
+
 /// 辞書からu32値を取得するヘルパー（負の値はエラー）
 fn dict_get_u32(dict: &lopdf::Dictionary, key: &[u8]) -> crate::error::Result<u32> {
     match dict.get(key) {
@@ -133,18 +282,57 @@ fn dict_get_u32(dict: &lopdf::Dictionary, key: &[u8]) -> crate::error::Result<u3
 ///
 /// 対応フィルタ:
 /// - DCTDecode (JPEG)
-/// - FlateDecode (raw pixels + zlib)
+/// - JPXDecode (JPEG2000、`jpx` feature有効時のみ)
+/// - FlateDecode / RunLengthDecode 等の汎用フィルタ（連鎖可）+ raw pixels
 /// - 非圧縮 (raw pixels)
+///
+/// 汎用フィルタの展開結果は宣言寸法の非圧縮サイズ（予測子のバイト分の余裕込み）
+/// を上限とし、小さな画像に巨大なストリームを持たせた展開爆弾を拒否する。
+/// JPEGの実寸が宣言と異なる場合は`size_mismatch`に従う（[`decode_jpeg`]）。
+#[cfg(any(feature = "mrc", test))]
 fn decode_image_stream(
     stream: &lopdf::Stream,
     meta: &ImageMeta,
+    size_mismatch: JpegSizeMismatch,
 ) -> crate::error::Result<DynamicImage> {
-    let raw = &stream.content;
+    decode_image_stream_with_alpha(stream, meta, size_mismatch).map(|(img, _)| img)
+}
 
-    match meta.filter.as_deref() {
-        Some("DCTDecode") => decode_jpeg(raw),
-        Some("FlateDecode") => decode_flate(raw, meta),
-        None => decode_raw(raw, meta),
+/// [`decode_image_stream`]と同様にデコードし、`/SMaskInData`が指定されたJPX画像では
+/// データに埋め込まれたアルファも返す。乗算済み(2)の色成分は乗算前に戻す。
+fn decode_image_stream_with_alpha(
+    stream: &lopdf::Stream,
+    meta: &ImageMeta,
+    size_mismatch: JpegSizeMismatch,
+) -> crate::error::Result<(DynamicImage, Option<GrayImage>)> {
+    let max_decoded_len = meta
+        .raw_len()
+        .saturating_mul(2)
+        .saturating_add(DECODE_SIZE_SLACK)
+        .min(usize::MAX as u64) as usize;
+
+    // 汎用フィルタを順に適用し、画像形式フィルタの手前で止める
+    let (data, image_filter) = filters::decode_filter_chain_limited(stream, max_decoded_len)
+        .map_err(|e| PdfMaskError::image_xobject(e.to_string()))?;
+
+    match image_filter.as_deref() {
+        Some("DCTDecode") => decode_jpeg(&data, meta, size_mismatch).map(|img| (img, None)),
+        #[cfg(feature = "jpx")]
+        Some("JPXDecode") if meta.smask_in_data != 0 => {
+            let (mut img, alpha) =
+                crate::ffi::openjpeg::decode_with_alpha(&data, meta.width, meta.height)?;
+            if meta.smask_in_data == 2
+                && let Some(alpha) = &alpha
+            {
+                unpremultiply(&mut img, alpha);
+            }
+            Ok((img, alpha))
+        }
+        #[cfg(feature = "jpx")]
+        Some("JPXDecode") => {
+            crate::ffi::openjpeg::decode(&data, meta.width, meta.height).map(|img| (img, None))
+        }
+        None => decode_raw(&data, meta).map(|img| (img, None)),
         Some(other) => Err(PdfMaskError::image_xobject(format!(
             "Unsupported image filter: {}",
             other
@@ -152,24 +340,218 @@ fn decode_image_stream(
     }
 }
 
+/// アルファで乗算済みの色成分を乗算前の値に戻す。
+#[cfg(feature = "jpx")]
+fn unpremultiply(img: &mut DynamicImage, alpha: &GrayImage) {
+    let restore = |c: u8, a: u8| -> u8 {
+        if a == 0 {
+            c
+        } else {
+            (c as u32 * 255 / a as u32).min(255) as u8
+        }
+    };
+    match img {
+        DynamicImage::ImageRgb8(rgb) => {
+            for (x, y, pixel) in rgb.enumerate_pixels_mut() {
+                let a = alpha.get_pixel(x, y).0[0];
+                pixel.0 = pixel.0.map(|c| restore(c, a));
+            }
+        }
+        DynamicImage::ImageLuma8(gray) => {
+            for (x, y, pixel) in gray.enumerate_pixels_mut() {
+                let a = alpha.get_pixel(x, y).0[0];
+                pixel.0 = [restore(pixel.0[0], a)];
+            }
+        }
+        _ => {}
+    }
+}
+
+/// /Matte付きの/SMask（色成分があらかじめマット色と合成済み）。
+///
+/// [`crate::pdf::reader::PdfReader::page_matte_smasks`]が解決した/SMaskのストリームから読む。
+struct Matte {
+    /// マット色（親画像の色空間の成分、0.0〜1.0）
+    color: Vec<f64>,
+    /// /SMaskのアルファ（親画像と同じ寸法）
+    alpha: GrayImage,
+}
+
+/// /SMaskの/Matteの色を返す。
+///
+/// マット色の成分数が画像の色空間（DeviceGray・DeviceRGB）と合わなければ`None`。
+fn matte_color(smask: &lopdf::Stream, meta: &ImageMeta) -> Option<Vec<f64>> {
+    let matte = smask.dict.get(b"Matte").and_then(Object::as_array).ok()?;
+    let color: Vec<f64> = matte
+        .iter()
+        .map(|c| operand_to_f64(c).map(|c| c.clamp(0.0, 1.0)))
+        .collect::<crate::error::Result<_>>()
+        .ok()?;
+    let components = match meta.color_space.as_str() {
+        "DeviceGray" => 1,
+        "DeviceRGB" => 3,
+        _ => 0,
+    };
+    if color.len() != components {
+        warn!(
+            color_space = %meta.color_space,
+            matte = ?color,
+            "ignoring /Matte that does not match the image color space"
+        );
+        return None;
+    }
+    Some(color)
+}
+
+/// /SMaskのアルファをデコードし、`width`×`height`にそろえて[`Matte`]にする。
+fn read_matte(
+    smask: &lopdf::Stream,
+    color: Vec<f64>,
+    (width, height): (u32, u32),
+    max_pixels: u64,
+    size_mismatch: JpegSizeMismatch,
+) -> crate::error::Result<Matte> {
+    let meta = read_image_meta(smask, max_pixels)?;
+    let (img, _) = decode_image_stream_with_alpha(smask, &meta, size_mismatch)?;
+    let mut alpha = img.to_luma8();
+    if alpha.dimensions() != (width, height) {
+        alpha =
+            image::imageops::resize(&alpha, width, height, image::imageops::FilterType::Triangle);
+    }
+    Ok(Matte { color, alpha })
+}
+
+/// マット色との合成を戻す（c = m + (c' - m) / α）。
+///
+/// 戻した画像は[`rematte`]で再び合成する。色成分数に合わせて8bitのRGB・グレーにそろえる。
+fn unmatte(img: &mut DynamicImage, matte: &Matte) {
+    apply_matte(
+        img,
+        matte,
+        |c, m, a| if a == 0.0 { m } else { m + (c - m) / a },
+    );
+}
+
+/// マット色と合成する（c' = m + α(c - m)）。
+fn rematte(img: &mut DynamicImage, matte: &Matte) {
+    apply_matte(img, matte, |c, m, a| m + a * (c - m));
+}
+
+/// 各画素の色成分`c`・マット色`m`・アルファ`a`（いずれも0.0〜1.0）を`f`で変換する。
+fn apply_matte(img: &mut DynamicImage, matte: &Matte, f: impl Fn(f64, f64, f64) -> f64) {
+    let convert = |c: u8, m: f64, a: u8| -> u8 {
+        (f(c as f64 / 255.0, m, a as f64 / 255.0).clamp(0.0, 1.0) * 255.0).round() as u8
+    };
+    if matte.color.len() == 3 {
+        let mut rgb = img.to_rgb8();
+        for (x, y, pixel) in rgb.enumerate_pixels_mut() {
+            let a = matte.alpha.get_pixel(x, y).0[0];
+            for (c, &m) in pixel.0.iter_mut().zip(&matte.color) {
+                *c = convert(*c, m, a);
+            }
+        }
+        *img = DynamicImage::ImageRgb8(rgb);
+    } else {
+        let mut gray = img.to_luma8();
+        for (x, y, pixel) in gray.enumerate_pixels_mut() {
+            let a = matte.alpha.get_pixel(x, y).0[0];
+            pixel.0 = [convert(pixel.0[0], matte.color[0], a)];
+        }
+        *img = DynamicImage::ImageLuma8(gray);
+    }
+}
+
+/// 画像XObjectをデコードし、8bit RGBの画素にそろえて返す。
+///
+/// フィルタ・色空間・ビット深度を変えた再エンコードの前後で画素を比べるのに使う
+/// （[`crate::pipeline::redaction_verify`]）。
+pub fn decode_image_rgb(stream: &lopdf::Stream, max_pixels: u64) -> crate::error::Result<RgbImage> {
+    let meta = read_image_meta(stream, max_pixels)?;
+    let (img, _) = decode_image_stream_with_alpha(stream, &meta, JpegSizeMismatch::Warn)?;
+    Ok(img.to_rgb8())
+}
+
+/// 画像XObjectのフィルタのうち、リダクションできない（デコードまたは再エンコードできない）
+/// ものを返す。すべて対応していれば`None`。
+pub fn unsupported_image_filter(stream: &lopdf::Stream) -> Option<String> {
+    let chain = filters::stream_filters(&stream.dict);
+    // 再エンコード形式は先頭のフィルタで決まる（encode_image）
+    if let Some(first) = chain.first()
+        && !matches!(
+            first.as_str(),
+            "DCTDecode" | "JPXDecode" | "FlateDecode" | "RunLengthDecode"
+        )
+    {
+        return Some(first.clone());
+    }
+    // デコードは画像形式フィルタの手前まで汎用フィルタを適用する（decode_image_stream）
+    chain.into_iter().find(|filter| {
+        filters::is_image_filter(filter)
+            && !(filter == "DCTDecode" || (filter == "JPXDecode" && cfg!(feature = "jpx")))
+    })
+}
+
 /// JPEGデータをデコード
-fn decode_jpeg(data: &[u8]) -> crate::error::Result<DynamicImage> {
-    let reader = image::ImageReader::new(std::io::Cursor::new(data))
-        .with_guessed_format()
+///
+/// デコード前にJPEGヘッダ（SOF）の寸法だけを読み、PDF辞書の/Width・/Heightと
+/// 比べる。食い違う場合、`Warn`では警告して実寸でデコードし（実寸も画素数の
+/// 上限で検査する）、`Error`ではエラーとする。デコーダにも同じ寸法を上限として渡す。
+///
+/// カメラ写真のEXIF orientationはデコード後の画素に反映する（90°系では幅と高さが入れ替わる）。
+/// 再エンコードした出力はEXIFを持たないため、回転が二重に掛かることはない。
+fn decode_jpeg(
+    data: &[u8],
+    meta: &ImageMeta,
+    size_mismatch: JpegSizeMismatch,
+) -> crate::error::Result<DynamicImage> {
+    let open = || {
+        image::ImageReader::new(std::io::Cursor::new(data))
+            .with_guessed_format()
+            .map_err(|e| PdfMaskError::image_xobject(format!("JPEG decode error: {}", e)))
+    };
+    let (width, height) = open()?
+        .into_dimensions()
         .map_err(|e| PdfMaskError::image_xobject(format!("JPEG decode error: {}", e)))?;
-    reader
-        .decode()
-        .map_err(|e| PdfMaskError::image_xobject(format!("JPEG decode error: {}", e)))
-}
+    if (width, height) != (meta.width, meta.height) {
+        if size_mismatch == JpegSizeMismatch::Error {
+            return Err(PdfMaskError::image_xobject(format!(
+                "JPEG is {}x{} but the image dictionary declares {}x{}",
+                width, height, meta.width, meta.height
+            )));
+        }
+        let pixels = width as u64 * height as u64;
+        if pixels > meta.max_pixels {
+            return Err(PdfMaskError::image_xobject(format!(
+                "JPEG {}x{} ({} pixels) exceeds max_image_pixels limit of {}",
+                width, height, pixels, meta.max_pixels
+            )));
+        }
+        warn!(
+            declared_width = meta.width,
+            declared_height = meta.height,
+            width,
+            height,
+            "JPEG size differs from /Width and /Height; using the actual size"
+        );
+    }
 
-/// FlateDecode (zlib) で圧縮されたraw pixelデータをデコード
-fn decode_flate(data: &[u8], meta: &ImageMeta) -> crate::error::Result<DynamicImage> {
-    let mut decoder = ZlibDecoder::new(data);
-    let mut decompressed = Vec::new();
-    decoder
-        .read_to_end(&mut decompressed)
-        .map_err(|e| PdfMaskError::image_xobject(format!("FlateDecode error: {}", e)))?;
-    decode_raw(&decompressed, meta)
+    let mut limits = image::Limits::default();
+    limits.max_image_width = Some(width);
+    limits.max_image_height = Some(height);
+
+    let mut reader = open()?;
+    reader.limits(limits);
+    let mut decoder = reader
+        .into_decoder()
+        .map_err(|e| PdfMaskError::image_xobject(format!("JPEG decode error: {}", e)))?;
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut img = DynamicImage::from_decoder(decoder)
+        .map_err(|e| PdfMaskError::image_xobject(format!("JPEG decode error: {}", e)))?;
+    if orientation != Orientation::NoTransforms {
+        debug!(?orientation, "applying JPEG EXIF orientation");
+        img.apply_orientation(orientation);
+    }
+    Ok(img)
 }
 
 /// Raw pixelデータからDynamicImageを構築
@@ -206,6 +588,29 @@ fn decode_raw(data: &[u8], meta: &ImageMeta) -> crate::error::Result<DynamicImag
             })?;
             Ok(DynamicImage::ImageLuma8(img))
         }
+        ("DeviceGray", 1) => {
+            let row_len = (w as usize).div_ceil(8);
+            let expected = row_len * (h as usize);
+            if data.len() < expected {
+                return Err(PdfMaskError::image_xobject(format!(
+                    "1-bit data too short: expected {}, got {}",
+                    expected,
+                    data.len()
+                )));
+            }
+            // ステンシルマスクの/Decode [1 0]ではサンプル値1が描画する（黒の）画素
+            let (zero, one) = if meta.mask_decode_inverted {
+                (255, 0)
+            } else {
+                (0, 255)
+            };
+            let img = GrayImage::from_fn(w, h, |x, y| {
+                let byte = data[y as usize * row_len + x as usize / 8];
+                let bit = (byte >> (7 - x % 8)) & 1;
+                image::Luma([if bit == 1 { one } else { zero }])
+            });
+            Ok(DynamicImage::ImageLuma8(img))
+        }
         (cs, bpc) => Err(PdfMaskError::image_xobject(format!(
             "Unsupported color space / BPC combination: {} / {}",
             cs, bpc
@@ -266,32 +671,93 @@ fn page_to_image_coords(
 /// * `image_stream` - PDF画像XObjectのストリーム
 /// * `redact_bboxes` - 白塗り対象領域（ページ座標）
 /// * `image_placement` - 画像のページ上での配置BBox
+/// * `max_dpi` - 配置サイズに対する実効DPIの上限。超える場合のみ縮小する
+/// * `normalize_to` - 出力する色空間。元と異なれば重なりが無くても変換して再エンコードする
+/// * `resample_filter` - 縮小に使うリサンプリングフィルタ
+/// * `max_pixels` - デコードを許可する画素数(Width×Height)の上限
 ///
 /// # Returns
-/// * `None` - 重なりなし（変更不要）
+/// * `None` - 重なりも色空間の変換も無い（変更不要）
 /// * `Some(RedactedImage)` - リダクション済み画像データ
 pub fn redact_image_regions(
     image_stream: &lopdf::Stream,
     redact_bboxes: &[BBox],
     image_placement: &BBox,
+    max_dpi: Option<u32>,
+    normalize_to: Option<ImageColorSpace>,
+    resample_filter: ResampleFilter,
+    max_pixels: u64,
 ) -> crate::error::Result<Option<RedactedImage>> {
-    let meta = read_image_meta(image_stream)?;
+    redact_image_regions_with_size_check(
+        image_stream,
+        redact_bboxes,
+        &axis_aligned_placement(image_placement),
+        None,
+        max_dpi,
+        ImageClassDpi::default(),
+        normalize_to,
+        resample_filter,
+        max_pixels,
+        JpegSizeMismatch::Warn,
+    )
+}
+
+/// [`redact_image_regions`]と同じだが、JPEGの実寸が/Width・/Heightと食い違うときの
+/// 扱いを指定できる。`Warn`では塗りつぶす画素領域を実寸で計算し直す。
+///
+/// `class_dpi`を指定すると画像を[`classify_image`]で分類し、種類ごとの実効DPI上限で
+/// ダウンサンプリングして、写真はDCTDecode、線画はFlateDecodeで再エンコードする。
+///
+/// `matte_smask`には画像の/SMaskが/Matteを持つときにそのストリームを渡す
+/// （[`crate::pdf::reader::PdfReader::page_matte_smasks`]）。
+///
+/// 実効DPIは`image_placement`のCTMから求めた画像の幅・高さ方向の配置サイズで計算する
+/// （回転した配置を外接矩形の寸法で縮小しすぎない）。
+#[allow(clippy::too_many_arguments)]
+pub fn redact_image_regions_with_size_check(
+    image_stream: &lopdf::Stream,
+    redact_bboxes: &[BBox],
+    image_placement: &ImagePlacement,
+    matte_smask: Option<&lopdf::Stream>,
+    max_dpi: Option<u32>,
+    class_dpi: ImageClassDpi,
+    normalize_to: Option<ImageColorSpace>,
+    resample_filter: ResampleFilter,
+    max_pixels: u64,
+    size_mismatch: JpegSizeMismatch,
+) -> crate::error::Result<Option<RedactedImage>> {
+    let mut meta = read_image_meta(image_stream, max_pixels)?;
+    // /Matte付きの/SMaskは親画像と同じ寸法・色成分数でなければならないため、
+    // 元の/SMaskを保つ画像は縮小も色空間の変換もしない
+    let matte_smask =
+        matte_smask.and_then(|smask| matte_color(smask, &meta).map(|color| (smask, color)));
+    let (max_dpi, class_dpi, normalize_to) = if matte_smask.is_some() {
+        (None, ImageClassDpi::default(), None)
+    } else {
+        (max_dpi, class_dpi, normalize_to)
+    };
+    // ステンシルマスクは色を持たないため、色空間をそろえる対象にしない
+    let needs_conversion = !meta.image_mask
+        && normalize_to.is_some_and(|space| meta.color_space != color_space_name(space));
 
     // 重なり判定: いずれかのredact_bboxが画像と重なるか
     let overlapping: Vec<&BBox> = redact_bboxes
         .iter()
-        .filter(|rb| bbox_overlaps(rb, image_placement))
+        .filter(|rb| bbox_overlaps(rb, &image_placement.bbox))
         .collect();
 
-    if overlapping.is_empty() {
+    if overlapping.is_empty() && !needs_conversion {
         return Ok(None);
     }
 
     // ピクセル領域に変換可能な重なりがあるか確認
-    let pixel_regions: Vec<(u32, u32, u32, u32)> = overlapping
-        .iter()
-        .filter_map(|rb| page_to_image_coords(rb, image_placement, meta.width, meta.height))
-        .collect();
+    let pixel_regions_for = |width: u32, height: u32| -> Vec<(u32, u32, u32, u32)> {
+        overlapping
+            .iter()
+            .filter_map(|rb| page_to_image_coords(rb, &image_placement.bbox, width, height))
+            .collect()
+    };
+    let mut pixel_regions = pixel_regions_for(meta.width, meta.height);
 
     debug!(
         overlapping = overlapping.len(),
@@ -299,18 +765,109 @@ pub fn redact_image_regions(
         "redact_image_regions"
     );
 
-    if pixel_regions.is_empty() {
+    if pixel_regions.is_empty() && !needs_conversion {
         return Ok(None);
     }
 
-    // 画像デコード
-    let mut img = decode_image_stream(image_stream, &meta)?;
+    // 画像デコード（JPXに埋め込まれたアルファは別の/SMaskとして出力し直す）
+    let (mut img, mut alpha) = decode_image_stream_with_alpha(image_stream, &meta, size_mismatch)?;
+    // JPEGの実寸が宣言と異なれば、塗りつぶす画素領域も実寸に合わせる
+    if (img.width(), img.height()) != (meta.width, meta.height) {
+        pixel_regions = pixel_regions_for(img.width(), img.height());
+    }
+
+    // マット色と合成済みの画像は、合成前の色に戻してから塗りつぶす
+    // （合成済みのまま白にすると、半透明の縁がビューアで戻すときに色ずれする）
+    let matte = matte_smask
+        .map(|(smask, color)| {
+            read_matte(
+                smask,
+                color,
+                (img.width(), img.height()),
+                max_pixels,
+                size_mismatch,
+            )
+        })
+        .transpose()?;
+    if let Some(matte) = &matte {
+        unmatte(&mut img, matte);
+    }
 
     // 各重なり領域を白で塗りつぶし
     for (x, y, w, h) in &pixel_regions {
         fill_white(&mut img, *x, *y, *w, *h);
     }
 
+    if let Some(matte) = &matte {
+        rematte(&mut img, matte);
+    }
+
+    // ステンシルマスクは色を持たないため分類しない
+    let class = (class_dpi.is_enabled() && !meta.image_mask).then(|| classify_image(&img));
+    let max_dpi = match class {
+        Some(class) => class_dpi.max_dpi(class, max_dpi),
+        None => max_dpi,
+    };
+
+    // 配置サイズに対する実効DPIが上限を超える場合はダウンサンプリング
+    if let Some(max_dpi) = max_dpi
+        && let Some((w, h)) = downsampled_size(
+            img.width(),
+            img.height(),
+            placement_extents(&image_placement.ctm),
+            max_dpi,
+        )
+    {
+        debug!(
+            from_width = img.width(),
+            from_height = img.height(),
+            to_width = w,
+            to_height = h,
+            max_dpi,
+            ?resample_filter,
+            "downsampling redacted image"
+        );
+        img = img.resize_exact(w, h, filter_type(resample_filter));
+        alpha = alpha.map(|a| image::imageops::resize(&a, w, h, filter_type(resample_filter)));
+    }
+
+    // 色空間をそろえる（カラー→グレーは輝度に変換）。再エンコードは8bit
+    if let Some(space) = normalize_to
+        && !meta.image_mask
+    {
+        img = match space {
+            ImageColorSpace::Rgb => DynamicImage::ImageRgb8(img.to_rgb8()),
+            ImageColorSpace::Gray => DynamicImage::ImageLuma8(img.to_luma8()),
+        };
+        meta.color_space = color_space_name(space).to_string();
+        meta.bits_per_component = 8;
+    }
+
+    // 分類した画像は種類に合った形式で再エンコードする
+    let class_filter = match class {
+        Some(ImageClass::Photo) => Some("DCTDecode"),
+        Some(ImageClass::LineArt) => Some("FlateDecode"),
+        None => None,
+    };
+    if let Some(filter) = class_filter
+        && meta.filter.as_deref() != Some(filter)
+    {
+        debug!(
+            from = ?meta.filter,
+            to = filter,
+            ?class,
+            "re-encoding classified image"
+        );
+        meta.filter = Some(filter.to_string());
+        meta.color_space = if img.color().has_color() {
+            "DeviceRGB"
+        } else {
+            "DeviceGray"
+        }
+        .to_string();
+        meta.bits_per_component = 8;
+    }
+
     // 元のフィルタ形式で再エンコード
     let (data, filter) = encode_image(&img, &meta)?;
 
@@ -324,10 +881,92 @@ pub fn redact_image_regions(
         data,
         filter,
         color_space,
-        bits_per_component: meta.bits_per_component,
+        // JPXの/BitsPerComponentは省略・16bitがあり得るが、デコード結果は常に8bit
+        bits_per_component: if meta.filter.as_deref() == Some("JPXDecode") {
+            8
+        } else {
+            meta.bits_per_component
+        },
+        width: img.width(),
+        height: img.height(),
+        smask: alpha.map(|a| flate_encode(a.as_raw())).transpose()?,
     }))
 }
 
+/// ResampleFilterに対応するimageクレートのフィルタ
+fn filter_type(filter: ResampleFilter) -> image::imageops::FilterType {
+    match filter {
+        ResampleFilter::Nearest => image::imageops::FilterType::Nearest,
+        ResampleFilter::Triangle => image::imageops::FilterType::Triangle,
+        ResampleFilter::Lanczos3 => image::imageops::FilterType::Lanczos3,
+    }
+}
+
+/// ImageColorSpaceに対応するPDFの色空間名
+fn color_space_name(space: ImageColorSpace) -> &'static str {
+    match space {
+        ImageColorSpace::Rgb => "DeviceRGB",
+        ImageColorSpace::Gray => "DeviceGray",
+    }
+}
+
+/// 配置のBBoxに軸をそろえて画像を置くCTMの[`ImagePlacement`]を作る。
+fn axis_aligned_placement(bbox: &BBox) -> ImagePlacement {
+    ImagePlacement {
+        name: String::new(),
+        ctm: Matrix {
+            a: bbox.x_max - bbox.x_min,
+            b: 0.0,
+            c: 0.0,
+            d: bbox.y_max - bbox.y_min,
+            e: bbox.x_min,
+            f: bbox.y_min,
+        },
+        bbox: bbox.clone(),
+        form_path: Vec::new(),
+        partial_cover: false,
+    }
+}
+
+/// 配置のCTMから、画像の幅方向・高さ方向それぞれの配置サイズ(pt)を返す。
+///
+/// 画像空間の単位正方形の辺がCTMで写る長さ（列ベクトルの長さ）。回転・傾斜した配置でも
+/// 外接矩形の寸法より大きくならない。
+fn placement_extents(ctm: &Matrix) -> (f64, f64) {
+    (ctm.a.hypot(ctm.b), ctm.c.hypot(ctm.d))
+}
+
+/// 画像の幅・高さ方向の配置サイズ(pt)と上限DPIから縮小後のピクセル寸法を計算する。
+///
+/// 実効DPIが上限以下の軸は元の寸法を保つ（アップサンプリングはしない）。
+/// どちらの軸も縮小不要なら`None`を返す。
+fn downsampled_size(
+    width: u32,
+    height: u32,
+    (width_pts, height_pts): (f64, f64),
+    max_dpi: u32,
+) -> Option<(u32, u32)> {
+    if max_dpi == 0 {
+        return None;
+    }
+    let target = |pixels: u32, extent_pts: f64| -> u32 {
+        let max_pixels = (extent_pts.abs() / 72.0 * max_dpi as f64).round().max(1.0);
+        if (pixels as f64) > max_pixels {
+            max_pixels as u32
+        } else {
+            pixels
+        }
+    };
+    let new_width = target(width, width_pts);
+    let new_height = target(height, height_pts);
+
+    if new_width == width && new_height == height {
+        None
+    } else {
+        Some((new_width, new_height))
+    }
+}
+
 /// 画像の指定領域を白で塗りつぶす
 fn fill_white(img: &mut DynamicImage, x: u32, y: u32, w: u32, h: u32) {
     match img {
@@ -370,24 +1009,23 @@ fn encode_image(img: &DynamicImage, meta: &ImageMeta) -> crate::error::Result<(V
             };
             Ok((data, "DCTDecode".to_string()))
         }
-        Some("FlateDecode") => {
-            let raw = if meta.color_space == "DeviceGray" {
-                img.to_luma8().into_raw()
+        // JPEG2000のエンコーダは持たないため、DCTDecodeで再エンコードする。
+        // 色空間は/ColorSpaceの宣言ではなくデコード結果から決める（JPXでは省略可能）
+        Some("JPXDecode") => {
+            let data = if img.color().has_color() {
+                jpeg::encode_rgb_to_jpeg(&img.to_rgb8(), 85)?
             } else {
-                img.to_rgb8().into_raw()
+                jpeg::encode_gray_to_jpeg(&img.to_luma8(), 85)?
             };
-            let compressed = flate_encode(&raw)?;
-            Ok((compressed, "FlateDecode".to_string()))
+            Ok((data, "DCTDecode".to_string()))
         }
-        None => {
-            // 元が非圧縮の場合はそのまま非圧縮で返す
-            let raw = if meta.color_space == "DeviceGray" {
-                img.to_luma8().into_raw()
-            } else {
-                img.to_rgb8().into_raw()
-            };
-            Ok((raw, String::new()))
+        // RunLengthDecodeのエンコーダは持たないため、FlateDecodeで再エンコードする
+        Some("FlateDecode") | Some("RunLengthDecode") => {
+            let compressed = flate_encode(&raw_samples(img, meta))?;
+            Ok((compressed, "FlateDecode".to_string()))
         }
+        // 元が非圧縮の場合はそのまま非圧縮で返す
+        None => Ok((raw_samples(img, meta), String::new())),
         Some(other) => {
             warn!(filter = other, "unsupported image filter for re-encoding");
             Err(PdfMaskError::image_xobject(format!(
@@ -398,6 +1036,29 @@ fn encode_image(img: &DynamicImage, meta: &ImageMeta) -> crate::error::Result<(V
     }
 }
 
+/// 画像を`meta`の色空間・ビット深度の非圧縮サンプル列にする。
+///
+/// 1bitのDeviceGray（ステンシルマスクを含む）は輝度128以上を白として
+/// 行ごとにバイト境界へそろえて詰める。
+fn raw_samples(img: &DynamicImage, meta: &ImageMeta) -> Vec<u8> {
+    if meta.color_space != "DeviceGray" {
+        return img.to_rgb8().into_raw();
+    }
+    let gray = img.to_luma8();
+    if meta.bits_per_component != 1 {
+        return gray.into_raw();
+    }
+    let row_len = (gray.width() as usize).div_ceil(8);
+    let mut packed = vec![0u8; row_len * gray.height() as usize];
+    for (x, y, pixel) in gray.enumerate_pixels() {
+        // /Decode [1 0]のステンシルマスクでは白（描画しない画素）がサンプル値0
+        if (pixel.0[0] >= 128) != meta.mask_decode_inverted {
+            packed[y as usize * row_len + x as usize / 8] |= 0x80 >> (x % 8);
+        }
+    }
+    packed
+}
+
 /// zlibで圧縮
 fn flate_encode(data: &[u8]) -> crate::error::Result<Vec<u8>> {
     use flate2::Compression;
@@ -419,6 +1080,8 @@ fn flate_encode(data: &[u8]) -> crate::error::Result<Vec<u8>> {
 /// * `decoded` - デコード済み画像
 /// * `original_size` - 元のストリームサイズ（比較用）
 /// * `quality` - JPEG品質 (1-100)
+/// * `normalize_to` - 指定時はこの色空間の候補だけを試す（カラー→グレーは輝度に変換）
+/// * `class` - 指定時は画像の種類に合った候補だけを試す（写真はJPEG、線画はJBIG2・Flate）
 ///
 /// # Returns
 /// * `None` - 元のサイズより小さくならない
@@ -428,6 +1091,8 @@ pub fn optimize_image_encoding(
     decoded: &DynamicImage,
     original_size: usize,
     quality: u8,
+    normalize_to: Option<ImageColorSpace>,
+    class: Option<ImageClass>,
 ) -> crate::error::Result<Option<OptimizedImage>> {
     if !(1..=100).contains(&quality) {
         return Err(PdfMaskError::image_xobject(format!(
@@ -437,10 +1102,15 @@ pub fn optimize_image_encoding(
     }
 
     let mut candidates: Vec<OptimizedImage> = Vec::new();
-    let is_color = decoded.color().has_color();
+    let is_color = match normalize_to {
+        Some(ImageColorSpace::Rgb) => true,
+        Some(ImageColorSpace::Gray) => false,
+        None => decoded.color().has_color(),
+    };
+    let try_jpeg = class != Some(ImageClass::LineArt);
 
     // 候補A: B&W JBIG2（グレースケール画像のみ。カラー画像のJBIG2化は意味的に不適切）
-    if !is_color {
+    if !is_color && class != Some(ImageClass::Photo) {
         let gray = decoded.to_luma8();
         let (w, h) = gray.dimensions();
         let rgba_for_binarize: Vec<u8> = gray
@@ -451,31 +1121,37 @@ pub fn optimize_image_encoding(
             let sx = w.clamp(16, 2000);
             let sy = h.clamp(16, 2000);
             if let Ok(mut binary) = pix.otsu_adaptive_threshold(sx, sy)
-                && let Ok(jbig2_data) = jbig2::encode_mask(&mut binary)
+                && let Ok(jbig2_data) = jbig2::encode_mask(&mut binary, &Jbig2Options::default())
             {
                 candidates.push(OptimizedImage {
                     data: jbig2_data,
                     filter: "JBIG2Decode",
                     color_space: "DeviceGray",
                     bits_per_component: 1,
+                    width: w,
+                    height: h,
                 });
             }
         }
     }
 
-    // 候補B: グレースケールJPEG
-    let gray = decoded.to_luma8();
-    if let Ok(gray_jpeg) = jpeg::encode_gray_to_jpeg(&gray, quality) {
+    // 候補B: グレースケールJPEG（RGBにそろえる場合は除く）
+    if try_jpeg
+        && normalize_to != Some(ImageColorSpace::Rgb)
+        && let Ok(gray_jpeg) = jpeg::encode_gray_to_jpeg(&decoded.to_luma8(), quality)
+    {
         candidates.push(OptimizedImage {
             data: gray_jpeg,
             filter: "DCTDecode",
             color_space: "DeviceGray",
             bits_per_component: 8,
+            width: decoded.width(),
+            height: decoded.height(),
         });
     }
 
     // 候補C: RGB JPEG（元がカラーの場合）
-    if is_color {
+    if try_jpeg && is_color {
         let rgb = decoded.to_rgb8();
         if let Ok(rgb_jpeg) = jpeg::encode_rgb_to_jpeg(&rgb, quality) {
             candidates.push(OptimizedImage {
@@ -483,27 +1159,79 @@ pub fn optimize_image_encoding(
                 filter: "DCTDecode",
                 color_space: "DeviceRGB",
                 bits_per_component: 8,
+                width: decoded.width(),
+                height: decoded.height(),
+            });
+        }
+    }
+
+    // 候補D: 8bit Flate（線画のみ。JPEGのように縁がにじまない）
+    if class == Some(ImageClass::LineArt) {
+        let (samples, color_space) = if is_color {
+            (decoded.to_rgb8().into_raw(), "DeviceRGB")
+        } else {
+            (decoded.to_luma8().into_raw(), "DeviceGray")
+        };
+        if let Ok(data) = flate_encode(&samples) {
+            candidates.push(OptimizedImage {
+                data,
+                filter: "FlateDecode",
+                color_space,
+                bits_per_component: 8,
+                width: decoded.width(),
+                height: decoded.height(),
             });
         }
     }
 
     // 最小サイズの候補を選択（元のサイズ以下のもの）
+    let candidates_tried = candidates.len();
     candidates.sort_by_key(|c| c.data.len());
 
     let result = candidates
         .into_iter()
         .find(|c| c.data.len() <= original_size);
     debug!(
-        candidates_tried = if is_color { 2 } else { 3 },
+        candidates_tried,
         selected = result.as_ref().map(|r| r.filter),
         "optimize_image_encoding"
     );
     Ok(result)
 }
 
+/// 画像XObjectのストリームをデコードし、[`optimize_image_encoding`]で再エンコードする。
+///
+/// `classify`のときは画像を[`classify_image`]で分類し、種類に合った形式だけを試す。
+/// 元のストリームより小さくならなければ`None`。`matte_smask`（/Matte付きの/SMask）を
+/// 渡した画像も`None`。
+#[cfg(feature = "mrc")]
+pub fn optimize_image_stream(
+    stream: &lopdf::Stream,
+    matte_smask: Option<&lopdf::Stream>,
+    quality: u8,
+    normalize_to: Option<ImageColorSpace>,
+    classify: bool,
+    max_pixels: u64,
+    size_mismatch: JpegSizeMismatch,
+) -> crate::error::Result<Option<OptimizedImage>> {
+    let meta = read_image_meta(stream, max_pixels)?;
+    // ステンシルマスクは1bitのままが最小で、JPEG等に変えると描画の意味が変わる
+    if meta.image_mask {
+        return Ok(None);
+    }
+    // /Matte付きの/SMaskは親画像と同じ寸法・色成分数でなければならない
+    if matte_smask.is_some() {
+        return Ok(None);
+    }
+    let decoded = decode_image_stream(stream, &meta, size_mismatch)?;
+    let class = classify.then(|| classify_image(&decoded));
+    optimize_image_encoding(&decoded, stream.content.len(), quality, normalize_to, class)
+}
+
 #[cfg(all(test, feature = "mrc"))]
 mod tests {
     use super::*;
+    use crate::config::settings::DEFAULT_MAX_IMAGE_PIXELS;
     use lopdf::{Stream, dictionary};
 
     /// テスト用: 指定サイズのRGB画像データを持つJPEGストリームを作成
@@ -568,7 +1296,16 @@ mod tests {
             y_max: 300.0,
         }];
 
-        let result = redact_image_regions(&stream, &redact, &image_placement).expect("redact");
+        let result = redact_image_regions(
+            &stream,
+            &redact,
+            &image_placement,
+            None,
+            None,
+            ResampleFilter::Triangle,
+            DEFAULT_MAX_IMAGE_PIXELS,
+        )
+        .expect("redact");
         assert!(result.is_none(), "No overlap should return None");
     }
 
@@ -589,7 +1326,16 @@ mod tests {
             y_max: 100.0,
         }];
 
-        let result = redact_image_regions(&stream, &redact, &image_placement).expect("redact");
+        let result = redact_image_regions(
+            &stream,
+            &redact,
+            &image_placement,
+            None,
+            None,
+            ResampleFilter::Triangle,
+            DEFAULT_MAX_IMAGE_PIXELS,
+        )
+        .expect("redact");
         assert!(result.is_some(), "Overlap should return Some");
         let redacted = result.unwrap();
         assert_eq!(redacted.filter, "DCTDecode");
@@ -613,7 +1359,16 @@ mod tests {
             y_max: 75.0,
         }];
 
-        let result = redact_image_regions(&stream, &redact, &image_placement).expect("redact");
+        let result = redact_image_regions(
+            &stream,
+            &redact,
+            &image_placement,
+            None,
+            None,
+            ResampleFilter::Triangle,
+            DEFAULT_MAX_IMAGE_PIXELS,
+        )
+        .expect("redact");
         assert!(result.is_some(), "Overlap should return Some");
         let redacted = result.unwrap();
         assert_eq!(redacted.filter, "FlateDecode");
@@ -649,7 +1404,16 @@ mod tests {
             }, // 重ならない
         ];
 
-        let result = redact_image_regions(&stream, &redact, &image_placement).expect("redact");
+        let result = redact_image_regions(
+            &stream,
+            &redact,
+            &image_placement,
+            None,
+            None,
+            ResampleFilter::Triangle,
+            DEFAULT_MAX_IMAGE_PIXELS,
+        )
+        .expect("redact");
         assert!(result.is_some(), "Should redact overlapping regions");
     }
 
@@ -664,7 +1428,16 @@ mod tests {
         };
         let redact: Vec<BBox> = vec![];
 
-        let result = redact_image_regions(&stream, &redact, &image_placement).expect("redact");
+        let result = redact_image_regions(
+            &stream,
+            &redact,
+            &image_placement,
+            None,
+            None,
+            ResampleFilter::Triangle,
+            DEFAULT_MAX_IMAGE_PIXELS,
+        )
+        .expect("redact");
         assert!(result.is_none(), "Empty redact list should return None");
     }
 
@@ -685,9 +1458,17 @@ mod tests {
             y_max: 10.0,
         }];
 
-        let result = redact_image_regions(&stream, &redact, &image_placement)
-            .expect("redact")
-            .expect("should produce redacted image");
+        let result = redact_image_regions(
+            &stream,
+            &redact,
+            &image_placement,
+            None,
+            None,
+            ResampleFilter::Triangle,
+            DEFAULT_MAX_IMAGE_PIXELS,
+        )
+        .expect("redact")
+        .expect("should produce redacted image");
 
         // 結果をデコードして白であることを確認
         let reader = image::ImageReader::new(std::io::Cursor::new(&result.data))
@@ -706,6 +1487,304 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_redact_downsamples_to_max_dpi() {
+        // 1200x1200pxの画像を2インチ角(144pt)に配置 → 実効600DPI
+        let stream = make_flate_rgb_stream(1200, 1200, [10, 200, 30]);
+        let image_placement = BBox {
+            x_min: 100.0,
+            y_min: 100.0,
+            x_max: 244.0,
+            y_max: 244.0,
+        };
+        let redact = vec![BBox {
+            x_min: 100.0,
+            y_min: 100.0,
+            x_max: 150.0,
+            y_max: 150.0,
+        }];
+
+        let result = redact_image_regions(
+            &stream,
+            &redact,
+            &image_placement,
+            Some(150),
+            None,
+            ResampleFilter::Triangle,
+            DEFAULT_MAX_IMAGE_PIXELS,
+        )
+        .expect("redact")
+        .expect("should produce redacted image");
+
+        // 2インチ × 150DPI = 300px
+        assert_eq!((result.width, result.height), (300, 300));
+        assert_eq!(result.filter, "FlateDecode");
+        let encoded = Stream::new(dictionary! { "Filter" => "FlateDecode" }, result.data);
+        let raw = filters::decode_stream(&encoded).expect("decode result");
+        assert_eq!(raw.len(), 300 * 300 * 3);
+    }
+
+    #[test]
+    fn test_redact_downsamples_rotated_placement_by_ctm_extents() {
+        // 1200x600pxの画像を90°回転して配置: 幅方向は上向きに4インチ(288pt)、
+        // 高さ方向は左向きに2インチ(144pt)。外接矩形は2×4インチ
+        let stream = make_flate_rgb_stream(1200, 600, [10, 200, 30]);
+        let placement = ImagePlacement {
+            name: "Im1".to_string(),
+            ctm: Matrix {
+                a: 0.0,
+                b: 288.0,
+                c: -144.0,
+                d: 0.0,
+                e: 244.0,
+                f: 100.0,
+            },
+            bbox: BBox {
+                x_min: 100.0,
+                y_min: 100.0,
+                x_max: 244.0,
+                y_max: 388.0,
+            },
+            form_path: Vec::new(),
+            partial_cover: false,
+        };
+        let redact = vec![BBox {
+            x_min: 100.0,
+            y_min: 100.0,
+            x_max: 150.0,
+            y_max: 150.0,
+        }];
+
+        let result = redact_image_regions_with_size_check(
+            &stream,
+            &redact,
+            &placement,
+            None,
+            Some(150),
+            ImageClassDpi::default(),
+            None,
+            ResampleFilter::Triangle,
+            DEFAULT_MAX_IMAGE_PIXELS,
+            JpegSizeMismatch::Warn,
+        )
+        .expect("redact")
+        .expect("should produce redacted image");
+
+        // 4インチ × 150DPI = 600px、2インチ × 150DPI = 300px（外接矩形なら300×600px）
+        assert_eq!((result.width, result.height), (600, 300));
+    }
+
+    #[test]
+    fn test_redact_never_upsamples() {
+        // 100x100pxを2インチ角に配置 → 実効50DPI、上限300DPIでも拡大しない
+        let stream = make_flate_rgb_stream(100, 100, [10, 200, 30]);
+        let image_placement = BBox {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 144.0,
+            y_max: 144.0,
+        };
+        let redact = vec![BBox {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 72.0,
+            y_max: 72.0,
+        }];
+
+        let result = redact_image_regions(
+            &stream,
+            &redact,
+            &image_placement,
+            Some(300),
+            None,
+            ResampleFilter::Triangle,
+            DEFAULT_MAX_IMAGE_PIXELS,
+        )
+        .expect("redact")
+        .expect("should produce redacted image");
+        assert_eq!((result.width, result.height), (100, 100));
+    }
+
+    #[test]
+    fn test_redact_downsample_depends_on_resample_filter() {
+        // 1px幅の白黒縦縞（400x400px）を2インチ角に配置 → 実効200DPI、上限50DPIで縮小
+        let raw: Vec<u8> = (0..400 * 400)
+            .flat_map(|i| if i % 2 == 0 { [0u8; 3] } else { [255u8; 3] })
+            .collect();
+        let dict = dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => 400,
+            "Height" => 400,
+            "ColorSpace" => "DeviceRGB",
+            "BitsPerComponent" => 8,
+            "Filter" => "FlateDecode",
+        };
+        let stream = Stream::new(dict, flate_encode(&raw).expect("compress test data"));
+        let image_placement = BBox {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 144.0,
+            y_max: 144.0,
+        };
+        let redact = vec![BBox {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 10.0,
+            y_max: 10.0,
+        }];
+
+        let downsample = |filter| {
+            let result = redact_image_regions(
+                &stream,
+                &redact,
+                &image_placement,
+                Some(50),
+                None,
+                filter,
+                DEFAULT_MAX_IMAGE_PIXELS,
+            )
+            .expect("redact")
+            .expect("should produce redacted image");
+            assert_eq!((result.width, result.height), (100, 100));
+            result.data
+        };
+
+        let nearest = downsample(ResampleFilter::Nearest);
+        let lanczos = downsample(ResampleFilter::Lanczos3);
+        assert_ne!(nearest, lanczos);
+    }
+
+    #[test]
+    fn test_redact_normalizes_rgb_image_to_gray() {
+        // 重なりが無くても、グレーにそろえる指定ならDeviceRGB画像を輝度に変換する
+        let stream = make_flate_rgb_stream(10, 10, [200, 50, 50]);
+        let image_placement = BBox {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 10.0,
+            y_max: 10.0,
+        };
+
+        let result = redact_image_regions(
+            &stream,
+            &[],
+            &image_placement,
+            None,
+            Some(ImageColorSpace::Gray),
+            ResampleFilter::Triangle,
+            DEFAULT_MAX_IMAGE_PIXELS,
+        )
+        .expect("redact")
+        .expect("RGB image should be converted");
+        assert_eq!(result.color_space, "DeviceGray");
+        assert_eq!(result.filter, "FlateDecode");
+        assert_eq!(result.bits_per_component, 8);
+
+        let encoded = Stream::new(dictionary! { "Filter" => "FlateDecode" }, result.data);
+        let raw = filters::decode_stream(&encoded).expect("decode result");
+        assert_eq!(raw.len(), 10 * 10);
+        // 輝度: 0.2126*200 + 0.7152*50 + 0.0722*50 ≈ 82
+        assert!(raw.iter().all(|&v| v.abs_diff(82) <= 1), "{:?}", &raw[..3]);
+
+        // 元と同じ色空間にそろえる指定なら変更しない
+        let gray = redact_image_regions(
+            &stream,
+            &[],
+            &image_placement,
+            None,
+            Some(ImageColorSpace::Rgb),
+            ResampleFilter::Triangle,
+            DEFAULT_MAX_IMAGE_PIXELS,
+        )
+        .expect("redact");
+        assert!(gray.is_none(), "DeviceRGB image is already RGB");
+    }
+
+    #[test]
+    fn test_redact_matted_smask_image_keeps_edge_colors() {
+        // 黒をマット色として合成済みの8x8画像。左半分は不透明、右半分（縁）はα=128
+        let alpha = |x: u32| if x < 4 { 255u32 } else { 128 };
+        let mut raw = Vec::new();
+        for _y in 0..8 {
+            for x in 0..8 {
+                raw.extend([200u32, 100, 50].map(|c| ((c * alpha(x) + 127) / 255) as u8));
+            }
+        }
+        let smask_data: Vec<u8> = (0..64).map(|i| alpha(i % 8) as u8).collect();
+        let smask = Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => 8,
+                "Height" => 8,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+                "Matte" => vec![0.into(), 0.into(), 0.into()],
+            },
+            smask_data,
+        );
+        let stream = Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => 8,
+                "Height" => 8,
+                "ColorSpace" => "DeviceRGB",
+                "BitsPerComponent" => 8,
+                "Filter" => "FlateDecode",
+                "SMask" => Object::Reference((7, 0)),
+            },
+            flate_encode(&raw).expect("compress test data"),
+        );
+        let image_placement = BBox {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 80.0,
+            y_max: 80.0,
+        };
+        // 縁の上2行（画素の列4..8・行0..2）だけをリダクション
+        let redact = vec![BBox {
+            x_min: 40.0,
+            y_min: 60.0,
+            x_max: 80.0,
+            y_max: 80.0,
+        }];
+
+        let result = redact_image_regions_with_size_check(
+            &stream,
+            &redact,
+            &axis_aligned_placement(&image_placement),
+            Some(&smask),
+            None,
+            ImageClassDpi::default(),
+            None,
+            ResampleFilter::Triangle,
+            DEFAULT_MAX_IMAGE_PIXELS,
+            JpegSizeMismatch::Warn,
+        )
+        .expect("redact")
+        .expect("should produce redacted image");
+
+        // 元の/SMask（/Matte）をそのまま使う
+        assert!(result.smask.is_none());
+        assert_eq!((result.width, result.height), (8, 8));
+        let encoded = Stream::new(dictionary! { "Filter" => "FlateDecode" }, result.data);
+        let out = filters::decode_stream(&encoded).expect("decode result");
+        for y in 0..8 {
+            for x in 0..8 {
+                let i = ((y * 8 + x) * 3) as usize;
+                let expected = if y < 2 && x >= 4 {
+                    // 白をマット色と合成し直した値（ビューアが戻すと白になる）
+                    [128; 3]
+                } else {
+                    [raw[i], raw[i + 1], raw[i + 2]]
+                };
+                assert_eq!(out[i..i + 3], expected, "pixel ({x}, {y})");
+            }
+        }
+    }
+
     // ============================================================
     // optimize_image_encoding テスト
     // ============================================================
@@ -714,7 +1793,7 @@ mod tests {
     fn test_optimize_returns_none_if_larger() {
         // 非常に小さい画像 → 最適化しても元より小さくならない場合None
         let img = DynamicImage::ImageRgb8(RgbImage::new(2, 2));
-        let result = optimize_image_encoding(&img, 1, 85).expect("optimize");
+        let result = optimize_image_encoding(&img, 1, 85, None, None).expect("optimize");
         assert!(
             result.is_none(),
             "Should return None if no candidate is smaller"
@@ -730,12 +1809,56 @@ mod tests {
         }
         let img = DynamicImage::ImageRgb8(rgb);
 
-        let result = optimize_image_encoding(&img, 1_000_000, 85).expect("optimize");
+        let result = optimize_image_encoding(&img, 1_000_000, 85, None, None).expect("optimize");
         assert!(result.is_some(), "Should find a smaller encoding");
         let optimized = result.unwrap();
         assert!(optimized.data.len() <= 1_000_000);
     }
 
+    #[test]
+    fn test_optimize_line_art_skips_jpeg() {
+        // 白地に黒い縦縞の線画 → 縁がにじむJPEGは候補にしない
+        let mut gray = image::GrayImage::new(100, 100);
+        for (x, _, pixel) in gray.enumerate_pixels_mut() {
+            *pixel = image::Luma([if x % 10 < 2 { 0 } else { 255 }]);
+        }
+        let img = DynamicImage::ImageLuma8(gray);
+
+        let result = optimize_image_encoding(&img, 1_000_000, 85, None, Some(ImageClass::LineArt))
+            .expect("optimize")
+            .expect("line art compresses well");
+        assert_ne!(result.filter, "DCTDecode");
+    }
+
+    // ============================================================
+    // classify_image テスト
+    // ============================================================
+
+    #[test]
+    fn test_classify_two_tone_image_as_line_art() {
+        let mut gray = image::GrayImage::new(64, 64);
+        for (x, y, pixel) in gray.enumerate_pixels_mut() {
+            *pixel = image::Luma([if (x + y) % 16 < 3 { 0 } else { 255 }]);
+        }
+        assert_eq!(
+            classify_image(&DynamicImage::ImageLuma8(gray)),
+            ImageClass::LineArt
+        );
+    }
+
+    #[test]
+    fn test_classify_continuous_tone_image_as_photo() {
+        // 輝度が全域に散らばるグラデーション
+        let mut rgb = RgbImage::new(64, 64);
+        for (x, y, pixel) in rgb.enumerate_pixels_mut() {
+            *pixel = image::Rgb([(x * 4) as u8, (y * 4) as u8, ((x + y) * 2) as u8]);
+        }
+        assert_eq!(
+            classify_image(&DynamicImage::ImageRgb8(rgb)),
+            ImageClass::Photo
+        );
+    }
+
     // ============================================================
     // page_to_image_coords テスト
     // ============================================================
@@ -802,7 +1925,7 @@ mod tests {
     #[test]
     fn test_read_image_meta_jpeg() {
         let stream = make_jpeg_stream(50, 30, [0, 0, 0]);
-        let meta = read_image_meta(&stream).expect("read meta");
+        let meta = read_image_meta(&stream, DEFAULT_MAX_IMAGE_PIXELS).expect("read meta");
         assert_eq!(meta.width, 50);
         assert_eq!(meta.height, 30);
         assert_eq!(meta.bits_per_component, 8);
@@ -813,17 +1936,186 @@ mod tests {
     #[test]
     fn test_decode_jpeg_roundtrip() {
         let stream = make_jpeg_stream(20, 20, [128, 64, 32]);
-        let meta = read_image_meta(&stream).expect("read meta");
-        let img = decode_image_stream(&stream, &meta).expect("decode");
+        let meta = read_image_meta(&stream, DEFAULT_MAX_IMAGE_PIXELS).expect("read meta");
+        let img = decode_image_stream(&stream, &meta, JpegSizeMismatch::Warn).expect("decode");
         assert_eq!(img.width(), 20);
         assert_eq!(img.height(), 20);
     }
 
+    /// 実際は`width`x`height`のJPEGに、8x2の/Width・/Heightを宣言したストリーム
+    fn make_mismatched_jpeg_stream(width: u32, height: u32) -> Stream {
+        let mut stream = make_jpeg_stream(width, height, [128, 64, 32]);
+        stream.dict.set("Width", 8);
+        stream.dict.set("Height", 2);
+        stream
+    }
+
+    #[test]
+    fn test_decode_jpeg_size_mismatch_uses_actual_size() {
+        let stream = make_mismatched_jpeg_stream(4, 4);
+        let meta = read_image_meta(&stream, DEFAULT_MAX_IMAGE_PIXELS).expect("read meta");
+        let img = decode_image_stream(&stream, &meta, JpegSizeMismatch::Warn).expect("decode");
+        assert_eq!((img.width(), img.height()), (4, 4));
+    }
+
+    #[test]
+    fn test_decode_jpeg_size_mismatch_error() {
+        let stream = make_mismatched_jpeg_stream(4, 4);
+        let meta = read_image_meta(&stream, DEFAULT_MAX_IMAGE_PIXELS).expect("read meta");
+        let err = decode_image_stream(&stream, &meta, JpegSizeMismatch::Error)
+            .expect_err("mismatched size must be rejected");
+        assert!(err.to_string().contains("declares 8x2"), "{err}");
+    }
+
+    #[test]
+    fn test_decode_jpeg_actual_size_respects_max_pixels() {
+        // 宣言(8x2=16画素)は上限内でも、実寸(40x40)が上限を超えれば拒否する
+        let stream = make_mismatched_jpeg_stream(40, 40);
+        let meta = read_image_meta(&stream, 100).expect("read meta");
+        let err = decode_image_stream(&stream, &meta, JpegSizeMismatch::Warn)
+            .expect_err("oversized actual JPEG must be rejected");
+        assert!(err.to_string().contains("max_image_pixels"), "{err}");
+    }
+
+    #[test]
+    fn test_redact_mismatched_jpeg_uses_actual_size() {
+        let stream = make_mismatched_jpeg_stream(40, 40);
+        let placement = BBox {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 100.0,
+            y_max: 100.0,
+        };
+        // 左半分をリダクション
+        let redact = BBox {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 50.0,
+            y_max: 100.0,
+        };
+        let redacted = redact_image_regions(
+            &stream,
+            &[redact],
+            &placement,
+            None,
+            None,
+            ResampleFilter::Triangle,
+            DEFAULT_MAX_IMAGE_PIXELS,
+        )
+        .expect("redact")
+        .expect("image overlaps the redaction");
+        assert_eq!((redacted.width, redacted.height), (40, 40));
+
+        let img = image::load_from_memory(&redacted.data)
+            .expect("decode redacted JPEG")
+            .to_rgb8();
+        // 宣言寸法(8x2)で計算すると実寸の左端数列しか塗られない
+        let left = img.get_pixel(15, 30).0;
+        let right = img.get_pixel(30, 30).0;
+        assert!(
+            left.iter().all(|&c| c > 240),
+            "left half should be white: {left:?}"
+        );
+        assert!(
+            right[0] < 200,
+            "right half should keep the image: {right:?}"
+        );
+    }
+
+    /// 左半分が赤・右半分が青の32x16のJPEGに、EXIF orientation 6（時計回りに90°回転）を付けたストリーム
+    fn make_exif_rotated_jpeg_stream() -> Stream {
+        let rgb = RgbImage::from_fn(32, 16, |x, _| {
+            if x < 16 {
+                image::Rgb([255, 0, 0])
+            } else {
+                image::Rgb([0, 0, 255])
+            }
+        });
+        let jpeg_data = jpeg::encode_rgb_to_jpeg(&rgb, 95).expect("encode test JPEG");
+        // APP1: "Exif\0\0" + ビッグエンディアンのTIFFヘッダ + Orientation(0x0112)=6の1エントリ
+        let mut exif = vec![0xFF, 0xE1, 0x00, 0x22];
+        exif.extend_from_slice(b"Exif\0\0MM\0\x2A\0\0\0\x08");
+        exif.extend_from_slice(&[0x00, 0x01, 0x01, 0x12, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01]);
+        exif.extend_from_slice(&[0x00, 0x06, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+        let mut data = jpeg_data[..2].to_vec();
+        data.extend_from_slice(&exif);
+        data.extend_from_slice(&jpeg_data[2..]);
+
+        let mut stream = make_jpeg_stream(32, 16, [0, 0, 0]);
+        stream.set_content(data);
+        stream
+    }
+
+    #[test]
+    fn test_decode_jpeg_applies_exif_orientation() {
+        let stream = make_exif_rotated_jpeg_stream();
+        let meta = read_image_meta(&stream, DEFAULT_MAX_IMAGE_PIXELS).expect("read meta");
+        // /Width・/Heightは回転前の寸法なので、Errorでも食い違いにならない
+        let img = decode_image_stream(&stream, &meta, JpegSizeMismatch::Error)
+            .expect("decode")
+            .to_rgb8();
+        assert_eq!((img.width(), img.height()), (16, 32));
+        // 元の左半分（赤）が上、右半分（青）が下になる
+        let top = img.get_pixel(8, 4).0;
+        let bottom = img.get_pixel(8, 28).0;
+        assert!(top[0] > 200 && top[2] < 60, "top should be red: {top:?}");
+        assert!(
+            bottom[2] > 200 && bottom[0] < 60,
+            "bottom should be blue: {bottom:?}"
+        );
+    }
+
+    #[test]
+    fn test_redact_exif_rotated_jpeg_bakes_rotation_and_drops_exif() {
+        let stream = make_exif_rotated_jpeg_stream();
+        let placement = BBox {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 100.0,
+            y_max: 100.0,
+        };
+        // 回転後の画像の下半分（青）をリダクション
+        let redact = BBox {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 100.0,
+            y_max: 50.0,
+        };
+        let redacted = redact_image_regions(
+            &stream,
+            &[redact],
+            &placement,
+            None,
+            None,
+            ResampleFilter::Triangle,
+            DEFAULT_MAX_IMAGE_PIXELS,
+        )
+        .expect("redact")
+        .expect("image overlaps the redaction");
+        assert_eq!((redacted.width, redacted.height), (16, 32));
+        assert!(
+            !redacted.data.windows(6).any(|w| w == b"Exif\0\0"),
+            "re-encoded JPEG must not carry the stale EXIF orientation"
+        );
+
+        let img = image::load_from_memory(&redacted.data)
+            .expect("decode redacted JPEG")
+            .to_rgb8();
+        assert_eq!((img.width(), img.height()), (16, 32));
+        let top = img.get_pixel(8, 4).0;
+        let bottom = img.get_pixel(8, 28).0;
+        assert!(top[0] > 200 && top[2] < 60, "top should stay red: {top:?}");
+        assert!(
+            bottom.iter().all(|&c| c > 240),
+            "bottom should be white: {bottom:?}"
+        );
+    }
+
     #[test]
     fn test_decode_flate_roundtrip() {
         let stream = make_flate_rgb_stream(30, 30, [100, 150, 200]);
-        let meta = read_image_meta(&stream).expect("read meta");
-        let img = decode_image_stream(&stream, &meta).expect("decode");
+        let meta = read_image_meta(&stream, DEFAULT_MAX_IMAGE_PIXELS).expect("read meta");
+        let img = decode_image_stream(&stream, &meta, JpegSizeMismatch::Warn).expect("decode");
         assert_eq!(img.width(), 30);
         assert_eq!(img.height(), 30);
         // Raw pixelなので色が正確に保持される
@@ -831,4 +2123,385 @@ mod tests {
         let pixel = rgb.get_pixel(0, 0);
         assert_eq!(pixel.0, [100, 150, 200]);
     }
+
+    #[test]
+    fn test_decode_run_length_gray_image() {
+        // 4x2 DeviceGray: 1行目は全画素0、2行目は 0,64,128,255
+        let stream = lopdf::Stream::new(
+            lopdf::dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => 4,
+                "Height" => 2,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+                "Filter" => "RunLengthDecode",
+            },
+            vec![253, 0, 3, 0, 64, 128, 255, 128],
+        );
+        let meta = read_image_meta(&stream, DEFAULT_MAX_IMAGE_PIXELS).expect("read meta");
+        let img = decode_image_stream(&stream, &meta, JpegSizeMismatch::Warn).expect("decode");
+        let gray = img.to_luma8();
+        assert_eq!(gray.into_raw(), vec![0, 0, 0, 0, 0, 64, 128, 255]);
+    }
+
+    #[test]
+    fn test_read_image_meta_rejects_oversized_image() {
+        // 100000x100000を宣言しつつデータは数バイトしか持たない画像
+        let stream = Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => 100_000,
+                "Height" => 100_000,
+                "ColorSpace" => "DeviceRGB",
+                "BitsPerComponent" => 8,
+                "Filter" => "FlateDecode",
+            },
+            flate_encode(&[0; 16]).expect("compress test data"),
+        );
+        let err = read_image_meta(&stream, DEFAULT_MAX_IMAGE_PIXELS)
+            .expect_err("oversized image must be rejected");
+        assert!(err.to_string().contains("max_image_pixels"), "{err}");
+
+        let placement = BBox {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 100.0,
+            y_max: 100.0,
+        };
+        let result = redact_image_regions(
+            &stream,
+            std::slice::from_ref(&placement),
+            &placement,
+            None,
+            None,
+            ResampleFilter::Triangle,
+            DEFAULT_MAX_IMAGE_PIXELS,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_decode_rejects_flate_bomb() {
+        // 10x10 DeviceGray（100バイト）を宣言しつつ、16MiBに展開されるストリーム
+        let stream = Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => 10,
+                "Height" => 10,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+                "Filter" => "FlateDecode",
+            },
+            flate_encode(&vec![0; 16 << 20]).expect("compress test data"),
+        );
+        let meta = read_image_meta(&stream, DEFAULT_MAX_IMAGE_PIXELS).expect("read meta");
+        let err = decode_image_stream(&stream, &meta, JpegSizeMismatch::Warn)
+            .expect_err("bomb must be rejected");
+        assert!(err.to_string().contains("decoded size limit"), "{err}");
+    }
+
+    /// テスト用: /ImageMask trueなのに/ColorSpace /DeviceRGBも持つ不正な8x2のステンシル。
+    /// 全画素がサンプル値0（描画する画素）
+    fn make_malformed_stencil_stream() -> Stream {
+        Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => 8,
+                "Height" => 2,
+                "ImageMask" => true,
+                "ColorSpace" => "DeviceRGB",
+                "BitsPerComponent" => 8,
+                "Filter" => "FlateDecode",
+            },
+            flate_encode(&[0x00, 0x00]).expect("compress test data"),
+        )
+    }
+
+    #[test]
+    fn test_image_mask_takes_precedence_over_color_space() {
+        let stream = make_malformed_stencil_stream();
+        let meta = read_image_meta(&stream, DEFAULT_MAX_IMAGE_PIXELS).expect("read meta");
+        assert!(meta.image_mask);
+        assert_eq!(meta.bits_per_component, 1);
+        assert_eq!(meta.color_space, "DeviceGray");
+        assert_eq!(meta.ignored_color_space.as_deref(), Some("DeviceRGB"));
+
+        let img =
+            decode_image_stream(&stream, &meta, JpegSizeMismatch::Warn).expect("decode stencil");
+        assert_eq!((img.width(), img.height()), (8, 2));
+        assert!(img.to_luma8().pixels().all(|p| p.0[0] == 0));
+    }
+
+    #[test]
+    fn test_redact_malformed_stencil_keeps_1bit_mask() {
+        let stream = make_malformed_stencil_stream();
+        // 配置(0,0)-(8,2)の左半分をリダクション
+        let redact = BBox {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 4.0,
+            y_max: 2.0,
+        };
+        let placement = BBox {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 8.0,
+            y_max: 2.0,
+        };
+        let result = redact_image_regions(
+            &stream,
+            &[redact],
+            &placement,
+            None,
+            None,
+            ResampleFilter::Triangle,
+            DEFAULT_MAX_IMAGE_PIXELS,
+        )
+        .expect("redact stencil")
+        .expect("should produce redacted image");
+        assert_eq!(result.bits_per_component, 1);
+        assert_eq!(result.filter, "FlateDecode");
+        // 左4画素がサンプル値1（描画しない）になり、右4画素は描画したまま
+        let mut raw = Vec::new();
+        std::io::Read::read_to_end(
+            &mut flate2::read::ZlibDecoder::new(&result.data[..]),
+            &mut raw,
+        )
+        .expect("inflate");
+        assert_eq!(raw, vec![0xF0, 0xF0]);
+    }
+
+    #[test]
+    fn test_optimize_skips_stencil_mask() {
+        let stream = make_malformed_stencil_stream();
+        let result = optimize_image_stream(
+            &stream,
+            None,
+            50,
+            None,
+            false,
+            DEFAULT_MAX_IMAGE_PIXELS,
+            JpegSizeMismatch::Warn,
+        )
+        .expect("optimize");
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_optimize_skips_matted_smask_image() {
+        let stream = make_flate_rgb_stream(64, 64, [200, 100, 50]);
+        let smask = Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => 64,
+                "Height" => 64,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+                "Matte" => vec![0.into(), 0.into(), 0.into()],
+            },
+            vec![128; 64 * 64],
+        );
+        let result = optimize_image_stream(
+            &stream,
+            Some(&smask),
+            50,
+            None,
+            false,
+            DEFAULT_MAX_IMAGE_PIXELS,
+            JpegSizeMismatch::Warn,
+        )
+        .expect("optimize");
+        assert!(result.is_none());
+    }
+}
+
+#[cfg(all(test, feature = "jpx"))]
+mod jpx_tests {
+    use super::*;
+    use crate::config::settings::DEFAULT_MAX_IMAGE_PIXELS;
+    use lopdf::{Stream, dictionary};
+
+    /// JP2のボックス（長さ + 種別 + 内容）を組み立てる
+    fn jp2_box(kind: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut out = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(payload);
+        out
+    }
+
+    /// テスト用: 8bitグレースケール1成分のJP2データを作成する。
+    ///
+    /// 分解レベル0・1レイヤーで、唯一のパケットを空にしたコードストリーム。
+    /// 係数はすべて0となり、DCレベルシフトで全画素128にデコードされる。
+    fn make_gray_jp2(width: u32, height: u32) -> Vec<u8> {
+        make_jp2(width, height, false)
+    }
+
+    /// テスト用: JP2データを作成する。`alpha`ならcdefボックスで不透明度と宣言した
+    /// 2つ目の成分（グレーと同じく全画素128）を持つ。
+    fn make_jp2(width: u32, height: u32, alpha: bool) -> Vec<u8> {
+        let components: u16 = if alpha { 2 } else { 1 };
+        let mut cs: Vec<u8> = vec![0xFF, 0x4F]; // SOC
+        // SIZ: Lsiz=38+3*成分数, Rsiz=0, 画像・タイルとも width x height, 各成分 8bit unsigned
+        cs.extend_from_slice(&[0xFF, 0x51]);
+        cs.extend_from_slice(&(38 + 3 * components).to_be_bytes());
+        cs.extend_from_slice(&[0x00, 0x00]);
+        for v in [width, height, 0, 0, width, height, 0, 0] {
+            cs.extend_from_slice(&v.to_be_bytes());
+        }
+        cs.extend_from_slice(&components.to_be_bytes());
+        for _ in 0..components {
+            cs.extend_from_slice(&[0x07, 0x01, 0x01]);
+        }
+        // COD: LRCP, 1レイヤー, MCTなし, 分解0, 64x64コードブロック, 5/3可逆
+        cs.extend_from_slice(&[
+            0xFF, 0x52, 0x00, 0x0C, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x04, 0x04, 0x00, 0x01,
+        ]);
+        // QCD: 量子化なし, ガードビット2, LLの指数8
+        cs.extend_from_slice(&[0xFF, 0x5C, 0x00, 0x04, 0x40, 0x40]);
+        // SOT: タイル0, Psot=14+成分数 (SOT 12 + SOD 2 + 成分ごとの空パケット 1)
+        cs.extend_from_slice(&[0xFF, 0x90, 0x00, 0x0A, 0x00, 0x00]);
+        cs.extend_from_slice(&(14 + components as u32).to_be_bytes());
+        cs.extend_from_slice(&[0x00, 0x01]);
+        cs.extend_from_slice(&[0xFF, 0x93]); // SOD
+        cs.extend(std::iter::repeat_n(0x00, components as usize)); // 空パケットヘッダ
+        cs.extend_from_slice(&[0xFF, 0xD9]); // EOC
+
+        let mut ihdr = height.to_be_bytes().to_vec();
+        ihdr.extend_from_slice(&width.to_be_bytes());
+        ihdr.extend_from_slice(&components.to_be_bytes());
+        ihdr.extend_from_slice(&[0x07, 0x07, 0x00, 0x00]);
+        // colr: 列挙型色空間 17 (greyscale)
+        let colr = [0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x11];
+        let mut jp2h = jp2_box(b"ihdr", &ihdr);
+        jp2h.extend(jp2_box(b"colr", &colr));
+        if alpha {
+            // cdef: 成分0=色（画像全体に対応）, 成分1=不透明度
+            let cdef = [0x00, 0x02, 0, 0, 0, 0, 0, 1, 0, 1, 0, 1, 0, 0];
+            jp2h.extend(jp2_box(b"cdef", &cdef));
+        }
+
+        let mut data = jp2_box(b"jP  ", &[0x0D, 0x0A, 0x87, 0x0A]);
+        data.extend(jp2_box(b"ftyp", b"jp2 \x00\x00\x00\x00jp2 "));
+        data.extend(jp2_box(b"jp2h", &jp2h));
+        data.extend(jp2_box(b"jp2c", &cs));
+        data
+    }
+
+    fn make_jpx_stream(width: u32, height: u32) -> Stream {
+        // JPXDecodeでは/ColorSpaceと/BitsPerComponentを省略できる
+        let dict = dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => width as i64,
+            "Height" => height as i64,
+            "Filter" => "JPXDecode",
+        };
+        Stream::new(dict, make_gray_jp2(width, height))
+    }
+
+    #[test]
+    fn test_decode_jpx_dimensions() {
+        let stream = make_jpx_stream(4, 3);
+        let meta = read_image_meta(&stream, DEFAULT_MAX_IMAGE_PIXELS).expect("read meta");
+        let img = decode_image_stream(&stream, &meta, JpegSizeMismatch::Warn).expect("decode JPX");
+        assert_eq!((img.width(), img.height()), (4, 3));
+        assert_eq!(img.to_luma8().get_pixel(0, 0).0, [128]);
+    }
+
+    #[test]
+    fn test_decode_jpx_larger_than_declared_rejected() {
+        let mut stream = make_jpx_stream(4, 3);
+        stream.dict.set("Width", 2);
+        let meta = read_image_meta(&stream, DEFAULT_MAX_IMAGE_PIXELS).expect("read meta");
+        assert!(decode_image_stream(&stream, &meta, JpegSizeMismatch::Warn).is_err());
+    }
+
+    #[test]
+    fn test_redact_jpx_reencodes_as_dct() {
+        let stream = make_jpx_stream(4, 3);
+        let placement = BBox {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 4.0,
+            y_max: 3.0,
+        };
+        let redact = [BBox {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 2.0,
+            y_max: 3.0,
+        }];
+        let redacted = redact_image_regions(
+            &stream,
+            &redact,
+            &placement,
+            None,
+            None,
+            ResampleFilter::Triangle,
+            DEFAULT_MAX_IMAGE_PIXELS,
+        )
+        .expect("redact")
+        .expect("overlap");
+        assert_eq!(redacted.filter, "DCTDecode");
+        assert_eq!(redacted.color_space, "DeviceGray");
+        assert_eq!(redacted.bits_per_component, 8);
+        assert_eq!((redacted.width, redacted.height), (4, 3));
+        assert!(redacted.smask.is_none());
+    }
+
+    #[test]
+    fn test_redact_jpx_keeps_smask_in_data_alpha() {
+        let mut stream = make_jpx_stream(4, 3);
+        stream.content = make_jp2(4, 3, true);
+        stream.dict.set("SMaskInData", 1);
+        let placement = BBox {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 4.0,
+            y_max: 3.0,
+        };
+        let redact = [BBox {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 2.0,
+            y_max: 3.0,
+        }];
+        let redacted = redact_image_regions(
+            &stream,
+            &redact,
+            &placement,
+            None,
+            None,
+            ResampleFilter::Triangle,
+            DEFAULT_MAX_IMAGE_PIXELS,
+        )
+        .expect("redact")
+        .expect("overlap");
+        assert_eq!(redacted.filter, "DCTDecode");
+        assert_eq!(redacted.color_space, "DeviceGray");
+
+        // 半透明のアルファが8bitグレーの/SMaskとして残る
+        let smask = redacted.smask.expect("alpha should be kept as SMask");
+        let mut alpha = Vec::new();
+        std::io::Read::read_to_end(&mut flate2::read::ZlibDecoder::new(&smask[..]), &mut alpha)
+            .expect("inflate SMask");
+        assert_eq!(alpha, vec![128; 4 * 3]);
+    }
+
+    #[test]
+    fn test_jpx_alpha_ignored_without_smask_in_data() {
+        let mut stream = make_jpx_stream(4, 3);
+        stream.content = make_jp2(4, 3, true);
+        let meta = read_image_meta(&stream, DEFAULT_MAX_IMAGE_PIXELS).expect("read meta");
+        let (img, alpha) = decode_image_stream_with_alpha(&stream, &meta, JpegSizeMismatch::Warn)
+            .expect("decode JPX");
+        assert_eq!(img.to_luma8().get_pixel(0, 0).0, [128]);
+        assert!(alpha.is_none());
+    }
 }
@@ -1,9 +1,18 @@
+pub mod color_space;
+pub(crate) mod content_lexer;
 pub mod content_stream;
+pub mod filters;
 pub mod font;
+pub mod form;
+pub mod function;
 pub mod glyph_to_path;
+pub mod icc;
 pub mod image_xobject;
 pub mod optimizer;
+pub mod overlay;
+pub mod preflight;
 pub mod reader;
+pub mod text_extract;
 pub mod text_state;
 pub mod text_to_outlines;
 pub mod writer;
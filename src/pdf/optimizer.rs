@@ -1,11 +1,12 @@
-// Phase 9: FlateDecode圧縮、孤立オブジェクト除去、フォント削除
+// Phase 9: FlateDecode圧縮、孤立オブジェクト除去、フォント削除、コンテンツストリームの簡約・分割
 
 use std::collections::HashSet;
 use std::io::Write;
 
 use flate2::Compression;
 use flate2::write::ZlibEncoder;
-use lopdf::{Document, Object, ObjectId};
+use lopdf::content::{Content, Operation};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
 
 use tracing::debug;
 
@@ -115,6 +116,185 @@ pub fn compress_streams(doc: &mut Document) -> crate::error::Result<()> {
     Ok(())
 }
 
+/// 塗り・線の色を設定するオペレーションの直近の値（未知ならNone）
+#[derive(Clone, Default)]
+struct ColorOps {
+    fill: Option<Operation>,
+    stroke: Option<Operation>,
+}
+
+/// 2つのオペレーションがオペレータ・オペランドとも同一か。
+fn same_operation(a: &Operation, b: &Operation) -> bool {
+    a.operator == b.operator && a.operands == b.operands
+}
+
+/// 描画を伴わず、グラフィックス状態だけを変えるオペレータ
+const STATE_ONLY_OPERATORS: &[&str] = &[
+    "cm", "w", "J", "j", "M", "d", "ri", "i", "gs", "g", "rg", "k", "cs", "sc", "scn", "G", "RG",
+    "K", "CS", "SC", "SCN",
+];
+
+/// 描画結果を変えない冗長なグラフィックス状態の操作を取り除く（peephole最適化）。
+///
+/// - 内側に状態の変更しか無い`q … Q`（`q Q`を含む）。`Q`で変更はすべて捨てられる
+/// - 現在の色と同じ色を設定し直すカラーオペレータ（`g`/`rg`/`k`/`cs`/`sc`/`scn`と
+///   線の色の同等品）。`q`/`Q`に合わせて色の状態も保存・復元する
+///
+/// `cs`は色を初期値に戻すため、直前の色設定が同じ`cs`の場合に限り冗長とみなす。
+pub fn simplify_operations(operations: Vec<Operation>) -> Vec<Operation> {
+    let mut output: Vec<Operation> = Vec::with_capacity(operations.len());
+    let mut colors = ColorOps::default();
+    // 開いている`q`ごとの、出力中の位置と保存した色の状態
+    let mut saved: Vec<(usize, ColorOps)> = Vec::new();
+    for op in operations {
+        match op.operator.as_str() {
+            "q" => saved.push((output.len(), colors.clone())),
+            "Q" => {
+                // 対応するqの無いQの後は色の状態を未知とする
+                let Some((start, restored)) = saved.pop() else {
+                    colors = ColorOps::default();
+                    output.push(op);
+                    continue;
+                };
+                colors = restored;
+                if output[start + 1..]
+                    .iter()
+                    .all(|inner| STATE_ONLY_OPERATORS.contains(&inner.operator.as_str()))
+                {
+                    output.truncate(start);
+                    continue;
+                }
+            }
+            "g" | "rg" | "k" | "cs" | "sc" | "scn" => {
+                if colors.fill.as_ref().is_some_and(|c| same_operation(c, &op)) {
+                    continue;
+                }
+                colors.fill = Some(op.clone());
+            }
+            "G" | "RG" | "K" | "CS" | "SC" | "SCN" => {
+                if colors
+                    .stroke
+                    .as_ref()
+                    .is_some_and(|c| same_operation(c, &op))
+                {
+                    continue;
+                }
+                colors.stroke = Some(op.clone());
+            }
+            _ => {}
+        }
+        output.push(op);
+    }
+    output
+}
+
+/// 指定ページのコンテンツストリームに[`simplify_operations`]を適用する。
+///
+/// 冗長な操作があったページだけ、コンテンツを1本の新しいストリームに置き換える
+/// （共有されたストリームは書き換えない）。インライン画像を含むページと、
+/// 解析できないページはそのままにする。
+pub fn simplify_content_streams(
+    doc: &mut Document,
+    page_ids: &[ObjectId],
+) -> crate::error::Result<()> {
+    for &page_id in page_ids {
+        let mut bytes = Vec::new();
+        for stream_id in doc.get_page_contents(page_id) {
+            let Ok(stream) = doc.get_object(stream_id).and_then(Object::as_stream) else {
+                continue;
+            };
+            let data = if stream.dict.get(b"Filter").is_ok() {
+                match stream.decompressed_content() {
+                    Ok(data) => data,
+                    Err(_) => {
+                        bytes.clear();
+                        break;
+                    }
+                }
+            } else {
+                stream.content.clone()
+            };
+            // ストリームの境界はトークンの区切りになる
+            bytes.extend_from_slice(&data);
+            bytes.push(b'\n');
+        }
+        if bytes.is_empty() {
+            continue;
+        }
+        let Ok(content) = Content::decode(&bytes) else {
+            debug!(?page_id, "content stream not parsable; left unsimplified");
+            continue;
+        };
+        if content.operations.iter().any(|op| op.operator == "BI") {
+            continue;
+        }
+        let original = content.operations.len();
+        let operations = simplify_operations(content.operations);
+        if operations.len() == original {
+            continue;
+        }
+        let encoded = Content { operations }
+            .encode()
+            .map_err(|e| PdfMaskError::pdf_write(format!("content stream encode failed: {e}")))?;
+        let content_id = doc.add_object(Stream::new(lopdf::Dictionary::new(), encoded));
+        if let Ok(page) = doc.get_dictionary_mut(page_id) {
+            page.set("Contents", Object::Reference(content_id));
+        }
+        debug!(?page_id, original, "simplified content stream");
+    }
+    Ok(())
+}
+
+/// 指定ページのコンテンツストリームのうち、デコード後`max_bytes`を超えるものを
+/// オペレーションの区切りで複数のストリームに分け、`/Contents`を配列にする。
+///
+/// ストリームの連結は元のコンテンツと同じになる（PDFは`/Contents`配列の各ストリームを
+/// 連結したものとして解釈する）。デコード・字句解析できないストリームはそのままにする。
+pub fn split_content_streams(
+    doc: &mut Document,
+    page_ids: &[ObjectId],
+    max_bytes: u64,
+) -> crate::error::Result<()> {
+    let max_bytes = usize::try_from(max_bytes).unwrap_or(usize::MAX);
+    for &page_id in page_ids {
+        let mut contents: Vec<Object> = Vec::new();
+        let mut split = false;
+        for stream_id in doc.get_page_contents(page_id) {
+            let data = match doc.get_object(stream_id).and_then(Object::as_stream) {
+                Ok(stream) if stream.dict.get(b"Filter").is_ok() => {
+                    stream.decompressed_content().ok()
+                }
+                Ok(stream) => Some(stream.content.clone()),
+                Err(_) => None,
+            };
+            let chunks = match &data {
+                Some(data) if data.len() > max_bytes => {
+                    crate::pdf::content_lexer::split_at_operations(data, max_bytes).ok()
+                }
+                _ => None,
+            };
+            match chunks {
+                Some(chunks) if chunks.len() > 1 => {
+                    for chunk in chunks {
+                        let id = doc.add_object(Stream::new(Dictionary::new(), chunk.to_vec()));
+                        contents.push(Object::Reference(id));
+                    }
+                    split = true;
+                }
+                _ => contents.push(Object::Reference(stream_id)),
+            }
+        }
+        if !split {
+            continue;
+        }
+        debug!(?page_id, streams = contents.len(), "split content streams");
+        if let Ok(page) = doc.get_dictionary_mut(page_id) {
+            page.set("Contents", Object::Array(contents));
+        }
+    }
+    Ok(())
+}
+
 /// 孤立オブジェクト（どこからも参照されていないオブジェクト）を除去する。
 pub fn delete_unused_objects(doc: &mut Document) {
     doc.prune_objects();
@@ -0,0 +1,90 @@
+// リダクション指定用オーバーレイPDFの読み取り
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use lopdf::{Document, Object};
+use tracing::debug;
+
+use crate::pdf::content_stream::{BBox, extract_filled_rects};
+use crate::pdf::reader::PdfReader;
+
+/// リダクション領域として扱わない注釈サブタイプ（リンクやフォーム部品等）
+const IGNORED_ANNOT_SUBTYPES: &[&[u8]] = &[b"Link", b"Popup", b"Widget"];
+
+/// オーバーレイPDFからページごとのリダクション矩形を読み取る。
+///
+/// 注釈の/Rectと、コンテンツストリーム中の白以外で塗りつぶされた矩形を
+/// リダクション領域とみなす。座標はオーバーレイのページ空間のまま返すため、
+/// 入力PDFと同じページサイズで書き出されたオーバーレイを前提とする。
+///
+/// # Returns
+/// ページ番号(1-indexed) → ページ座標のBBoxリスト。矩形の無いページは含まない。
+pub fn read_overlay_redactions(
+    path: impl AsRef<Path>,
+) -> crate::error::Result<HashMap<u32, Vec<BBox>>> {
+    let reader = PdfReader::open(path)?;
+    let mut redactions: HashMap<u32, Vec<BBox>> = HashMap::new();
+
+    for page_num in 1..=reader.page_count() {
+        let mut rects = annotation_rects(reader.document(), page_num);
+        let content = reader.page_content_stream(page_num)?;
+        rects.extend(extract_filled_rects(&content)?);
+        rects.retain(|r| r.x_max > r.x_min && r.y_max > r.y_min);
+
+        if !rects.is_empty() {
+            debug!(page = page_num, count = rects.len(), "overlay redactions");
+            redactions.insert(page_num, rects);
+        }
+    }
+    Ok(redactions)
+}
+
+/// ページの注釈から/Rectを取り出す（正規化済み）。
+fn annotation_rects(doc: &Document, page_num: u32) -> Vec<BBox> {
+    let Some(&page_id) = doc.get_pages().get(&page_num) else {
+        return Vec::new();
+    };
+    let Ok(annots) = doc
+        .get_dictionary(page_id)
+        .and_then(|page| page.get(b"Annots"))
+        .and_then(|obj| resolve(doc, obj).as_array())
+    else {
+        return Vec::new();
+    };
+
+    annots
+        .iter()
+        .filter_map(|annot| resolve(doc, annot).as_dict().ok())
+        .filter(|annot| {
+            !annot
+                .get(b"Subtype")
+                .and_then(Object::as_name)
+                .is_ok_and(|subtype| IGNORED_ANNOT_SUBTYPES.contains(&subtype))
+        })
+        .filter_map(|annot| {
+            let rect = resolve(doc, annot.get(b"Rect").ok()?).as_array().ok()?;
+            let values: Vec<f64> = rect
+                .iter()
+                .filter_map(|v| resolve(doc, v).as_float().ok().map(f64::from))
+                .collect();
+            let [x1, y1, x2, y2] = values[..] else {
+                return None;
+            };
+            Some(BBox {
+                x_min: x1.min(x2),
+                y_min: y1.min(y2),
+                x_max: x1.max(x2),
+                y_max: y1.max(y2),
+            })
+        })
+        .collect()
+}
+
+/// 間接参照を解決する（解決できなければ元のオブジェクトを返す）。
+fn resolve<'a>(doc: &'a Document, obj: &'a Object) -> &'a Object {
+    match obj {
+        Object::Reference(id) => doc.get_object(*id).unwrap_or(obj),
+        _ => obj,
+    }
+}
@@ -0,0 +1,139 @@
+// 処理前の対応状況チェック（プリフライト）
+
+use std::collections::BTreeSet;
+
+use lopdf::{Dictionary, Document, Object};
+use tracing::debug;
+
+use crate::pdf::font::{UnsupportedFont, unsupported_page_fonts};
+use crate::pdf::image_xobject::unsupported_image_filter;
+use crate::pdf::reader::PdfReader;
+
+/// 文書のうち、このツールで扱えない・扱いに注意が必要な機能の一覧
+///
+/// 連携側がジョブを流す前に、処理経路（text-to-outlinesで済むか、
+/// pdfiumでのラスタライズが必要か、そもそも対象外とするか）を判断するために使う。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Preflight {
+    /// 暗号化されている（または読み込み時に復号された）
+    pub encrypted: bool,
+    /// 非対応のフォントや画像を含み、pdfiumでのレンダリングが必要なページ(1-indexed、昇順)
+    pub pages_needing_pdfium: Vec<u32>,
+    /// リダクションできない画像フィルタ名（重複なし、昇順）
+    pub unsupported_image_filters: Vec<String>,
+    /// アウトライン化できないフォント（重複なし、昇順）
+    pub unsupported_fonts: Vec<UnsupportedFont>,
+    /// オプショナルコンテンツ（レイヤー）を持つ
+    pub has_optional_content: bool,
+    /// AcroFormフォームを持つ
+    pub has_forms: bool,
+    /// JavaScriptアクションを持つ
+    pub has_javascript: bool,
+}
+
+impl PdfReader {
+    /// 文書全体をプリフライトし、非対応の機能を列挙する。
+    ///
+    /// 各ページのフォントと画像XObjectは、処理時と同じフォント解析・画像フィルタ判定で調べる。
+    pub fn preflight(&self) -> crate::error::Result<Preflight> {
+        let doc = self.document();
+        let mut image_filters = BTreeSet::new();
+        let mut fonts = BTreeSet::new();
+        let mut pages_needing_pdfium = Vec::new();
+
+        for page_num in 1..=self.page_count() {
+            let page_fonts = unsupported_page_fonts(doc, page_num)?;
+            let page_filters: Vec<String> = self
+                .page_image_streams(page_num)?
+                .values()
+                .filter_map(unsupported_image_filter)
+                .collect();
+
+            if !page_fonts.is_empty() || !page_filters.is_empty() {
+                pages_needing_pdfium.push(page_num);
+            }
+            fonts.extend(page_fonts);
+            image_filters.extend(page_filters);
+        }
+
+        let catalog = doc.catalog().ok();
+        let preflight = Preflight {
+            encrypted: doc.is_encrypted() || doc.was_encrypted(),
+            pages_needing_pdfium,
+            unsupported_image_filters: image_filters.into_iter().collect(),
+            unsupported_fonts: fonts.into_iter().collect(),
+            has_optional_content: self.has_optional_content(),
+            has_forms: catalog.is_some_and(|catalog| catalog.has(b"AcroForm")),
+            has_javascript: has_javascript(doc, catalog),
+        };
+        debug!(?preflight, "preflight");
+        Ok(preflight)
+    }
+}
+
+/// 文書レベルのJavaScript（/Names /JavaScript、/OpenAction、/AA）か、
+/// 注釈のアクションにJavaScriptがあるか。
+fn has_javascript(doc: &Document, catalog: Option<&Dictionary>) -> bool {
+    if let Some(catalog) = catalog {
+        let names_js = catalog
+            .get(b"Names")
+            .ok()
+            .and_then(|names| resolve_dict(doc, names))
+            .is_some_and(|names| names.has(b"JavaScript"));
+        if names_js
+            || action_is_javascript(doc, catalog.get(b"OpenAction").ok())
+            || additional_actions_have_javascript(doc, catalog)
+        {
+            return true;
+        }
+    }
+
+    doc.get_pages().values().any(|&page_id| {
+        let Ok(page) = doc.get_dictionary(page_id) else {
+            return false;
+        };
+        if additional_actions_have_javascript(doc, page) {
+            return true;
+        }
+        let Some(Object::Array(annots)) = page
+            .get(b"Annots")
+            .ok()
+            .and_then(|obj| doc.dereference(obj).ok())
+            .map(|(_, obj)| obj)
+        else {
+            return false;
+        };
+        annots
+            .iter()
+            .filter_map(|annot| resolve_dict(doc, annot))
+            .any(|annot| {
+                action_is_javascript(doc, annot.get(b"A").ok())
+                    || additional_actions_have_javascript(doc, annot)
+            })
+    })
+}
+
+/// 辞書の/AA（追加アクション）のいずれかがJavaScriptか。
+fn additional_actions_have_javascript(doc: &Document, dict: &Dictionary) -> bool {
+    dict.get(b"AA")
+        .ok()
+        .and_then(|aa| resolve_dict(doc, aa))
+        .is_some_and(|aa| {
+            aa.iter()
+                .any(|(_, action)| action_is_javascript(doc, Some(action)))
+        })
+}
+
+/// アクション辞書の/SがJavaScriptか。
+fn action_is_javascript(doc: &Document, action: Option<&Object>) -> bool {
+    action
+        .and_then(|action| resolve_dict(doc, action))
+        .and_then(|action| action.get(b"S").ok())
+        .and_then(|s| s.as_name().ok())
+        .is_some_and(|s| s == b"JavaScript")
+}
+
+/// 間接参照を解決して辞書として返す。
+fn resolve_dict<'a>(doc: &'a Document, obj: &'a Object) -> Option<&'a Dictionary> {
+    doc.dereference(obj).ok()?.1.as_dict().ok()
+}
@@ -1,9 +1,30 @@
 use std::collections::HashMap;
 use std::path::Path;
 
-use lopdf::Document;
+use lopdf::{Document, Object};
 use tracing::debug;
 
+use crate::error::PdfMaskError;
+use crate::pdf::color_space::TintColorSpace;
+use crate::pdf::content_stream::{
+    BBox, ExtGStateOpacity, FillResources, FormXObject, MAX_FORM_DEPTH, Matrix, PageOrientation,
+    operand_to_f64,
+};
+
+/// ページあたりに読み込むForm XObjectの上限（ネストしたものを含む）
+const MAX_FORMS_PER_PAGE: usize = 256;
+
+/// ページのResourcesからたどれる画像XObject（[`PdfReader::page_reachable_images`]）。
+#[derive(Debug, Clone)]
+pub struct ReachableImage {
+    /// 画像を描くForm XObjectの名前（外側から順）。ページ直下なら空。
+    /// タイリングパターン経由の画像はパターン名も含むため、配置とは一致しない
+    pub form_path: Vec<String>,
+    /// 最も内側のリソースでの名前
+    pub name: String,
+    pub stream: lopdf::Stream,
+}
+
 pub struct PdfReader {
     doc: Document,
 }
@@ -21,35 +42,124 @@ impl PdfReader {
         &self.doc
     }
 
+    /// 内部のlopdf Documentへの可変参照を返す（平坦化等の前処理用）。
+    pub(crate) fn document_mut(&mut self) -> &mut Document {
+        &mut self.doc
+    }
+
     /// ページ数を返す。
     pub fn page_count(&self) -> u32 {
         self.doc.get_pages().len() as u32
     }
 
-    /// 指定ページ辞書からMediaBoxを取得する（Parent経由の継承も考慮）。
-    fn get_media_box(&self, dict: &lopdf::Dictionary) -> crate::error::Result<lopdf::Object> {
-        // まず現在の辞書からMediaBoxを探す
-        if let Ok(obj) = dict.get(b"MediaBox") {
-            return Ok(obj.clone());
+    /// Catalogに`/OCProperties`（オプショナルコンテンツ/レイヤー構成）があるかを返す。
+    pub fn has_optional_content(&self) -> bool {
+        self.doc
+            .catalog()
+            .map(|catalog| catalog.has(b"OCProperties"))
+            .unwrap_or(false)
+    }
+
+    /// 前回の出力で記録した元ページのキーから、ページ番号(1-indexed)へのマップを返す。
+    ///
+    /// キーは[`MrcPageWriter::set_page_source_key`](crate::pdf::writer::MrcPageWriter::set_page_source_key)
+    /// がマスク済みページの/PieceInfoに書き込んだもの。キーのないページは含まない。
+    pub fn page_source_keys(&self) -> HashMap<String, u32> {
+        let mut keys = HashMap::new();
+        for (page_num, page_id) in self.doc.get_pages() {
+            let key = self
+                .doc
+                .get_dictionary(page_id)
+                .and_then(|page| page.get_deref(b"PieceInfo", &self.doc))
+                .and_then(Object::as_dict)
+                .and_then(|info| info.get_deref(b"PdfMasking", &self.doc))
+                .and_then(Object::as_dict)
+                .and_then(|data| data.get_deref(b"Private", &self.doc))
+                .and_then(Object::as_dict)
+                .and_then(|private| private.get(b"SourceKey"))
+                .and_then(Object::as_str);
+            if let Ok(key) = key {
+                keys.insert(String::from_utf8_lossy(key).into_owned(), page_num);
+            }
         }
+        keys
+    }
 
-        // 見つからなければParentをたどって継承を確認する
-        if let Ok(lopdf::Object::Reference(parent_id)) = dict.get(b"Parent") {
-            let parent_dict = self.doc.get_dictionary(*parent_id)?;
-            return self.get_media_box(parent_dict);
+    /// ページの出力を左右する、コンテンツストリーム以外の入力をバイト列にまとめる。
+    ///
+    /// 継承を含むResources（参照先の画像・フォント・Form XObjectのストリームまで）と
+    /// MediaBox・CropBox・Rotate・UserUnitを決まった順で直列化する。
+    /// incrementalモードの元ページキーに含め、画像やフォントだけを差し替えたページも
+    /// 変更として検出するために使う。
+    pub fn page_resource_bytes(&self, page_num: u32) -> crate::error::Result<Vec<u8>> {
+        let page_id = self.get_page_id(page_num)?;
+        let page_dict = self
+            .doc
+            .get_dictionary(page_id)
+            .map_err(|e| PdfMaskError::pdf_read(e.to_string()))?;
+        let mut out = Vec::new();
+        let mut visited = HashMap::new();
+        for key in [
+            b"Resources".as_slice(),
+            b"MediaBox",
+            b"CropBox",
+            b"Rotate",
+            b"UserUnit",
+        ] {
+            out.extend_from_slice(key);
+            match self.get_inherited_attribute(page_dict, key) {
+                Some(value) => append_object_bytes(&self.doc, &value, &mut out, &mut visited),
+                None => out.push(b'-'),
+            }
         }
+        Ok(out)
+    }
 
-        Err(crate::error::PdfMaskError::pdf_read("MediaBox not found"))
+    /// 指定ページ辞書からMediaBoxを取得する（Parent経由の継承も考慮）。
+    fn get_media_box(&self, dict: &lopdf::Dictionary) -> crate::error::Result<lopdf::Object> {
+        self.get_inherited_attribute(dict, b"MediaBox")
+            .ok_or_else(|| crate::error::PdfMaskError::pdf_read("MediaBox not found"))
     }
 
-    /// 指定ページ(1-indexed)のMediaBoxからページ寸法(width_pts, height_pts)を返す。
-    pub fn page_dimensions(&self, page_num: u32) -> crate::error::Result<(f64, f64)> {
+    /// 継承可能なページ属性を取得する。見つからなければParentをたどる。
+    fn get_inherited_attribute(
+        &self,
+        dict: &lopdf::Dictionary,
+        key: &[u8],
+    ) -> Option<lopdf::Object> {
+        inherited_page_attribute(&self.doc, dict, key)
+    }
+
+    /// 指定ページ(1-indexed)の/Rotateを0/90/180/270に正規化して返す。
+    ///
+    /// 中間のPagesノードで指定された値も継承する。未指定や不正な値は0。
+    pub fn page_rotation(&self, page_num: u32) -> crate::error::Result<i64> {
+        let page_id = self.get_page_id(page_num)?;
+        let page_dict = self.doc.get_dictionary(page_id)?;
+        Ok(match self.get_inherited_attribute(page_dict, b"Rotate") {
+            Some(lopdf::Object::Integer(r)) if r % 90 == 0 => r.rem_euclid(360),
+            _ => 0,
+        })
+    }
+
+    /// 指定ページ(1-indexed)のMediaBoxを(x0, y0, x1, y1)として返す。
+    ///
+    /// 値は整数・実数のどちらでもよく、記述順のまま返す（正規化しない）。
+    /// 配列や各要素が間接参照でもよい（共有MediaBoxを親Pagesノードに置くPDF等）。
+    fn media_box_rect(&self, page_num: u32) -> crate::error::Result<(f64, f64, f64, f64)> {
         let page_id = self.get_page_id(page_num)?;
         let page_dict = self.doc.get_dictionary(page_id)?;
 
         // MediaBoxを取得（継承も考慮）
         let media_box = self.get_media_box(page_dict)?;
+        let resolve = |obj: &lopdf::Object| -> crate::error::Result<lopdf::Object> {
+            match obj {
+                lopdf::Object::Reference(id) => Ok(self.doc.get_object(*id)?.clone()),
+                _ => Ok(obj.clone()),
+            }
+        };
 
+        let media_box = resolve(&media_box)?;
         let media_box_array = media_box.as_array()?;
         if media_box_array.len() < 4 {
             return Err(crate::error::PdfMaskError::pdf_read("Invalid MediaBox"));
@@ -57,28 +167,111 @@ impl PdfReader {
 
         // MediaBoxの値は整数または実数の可能性がある
         let to_f64 = |obj: &lopdf::Object| -> crate::error::Result<f64> {
-            match obj {
-                lopdf::Object::Integer(i) => Ok(*i as f64),
-                lopdf::Object::Real(f) => Ok(*f as f64),
+            match resolve(obj)? {
+                lopdf::Object::Integer(i) => Ok(i as f64),
+                lopdf::Object::Real(f) => Ok(f as f64),
                 _ => Err(crate::error::PdfMaskError::pdf_read(
                     "Invalid MediaBox value",
                 )),
             }
         };
 
-        let x0 = to_f64(&media_box_array[0])?;
-        let y0 = to_f64(&media_box_array[1])?;
-        let x1 = to_f64(&media_box_array[2])?;
-        let y1 = to_f64(&media_box_array[3])?;
+        Ok((
+            to_f64(&media_box_array[0])?,
+            to_f64(&media_box_array[1])?,
+            to_f64(&media_box_array[2])?,
+            to_f64(&media_box_array[3])?,
+        ))
+    }
+
+    /// 指定ページ(1-indexed)の表示領域を正規化した(x_min, y_min, x_max, y_max)として返す。
+    ///
+    /// 表示領域はCropBoxとMediaBoxの共通部分（ビューアやpdfiumが描く範囲）。CropBoxが
+    /// 無い・不正・MediaBoxと重ならない場合はMediaBox全体とする。
+    fn visible_box_rect(&self, page_num: u32) -> crate::error::Result<(f64, f64, f64, f64)> {
+        let (x0, y0, x1, y1) = self.media_box_rect(page_num)?;
+        let media = (x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1));
+        let Some((cx0, cy0, cx1, cy1)) = self.crop_box_rect(page_num) else {
+            return Ok(media);
+        };
+        let visible = (
+            media.0.max(cx0.min(cx1)),
+            media.1.max(cy0.min(cy1)),
+            media.2.min(cx0.max(cx1)),
+            media.3.min(cy0.max(cy1)),
+        );
+        if visible.0 < visible.2 && visible.1 < visible.3 {
+            Ok(visible)
+        } else {
+            Ok(media)
+        }
+    }
+
+    /// 指定ページ(1-indexed)のCropBoxを(x0, y0, x1, y1)として返す（継承も考慮）。
+    /// 無い場合や数値4つの配列でない場合は`None`。
+    fn crop_box_rect(&self, page_num: u32) -> Option<(f64, f64, f64, f64)> {
+        let page_id = self.get_page_id(page_num).ok()?;
+        let page_dict = self.doc.get_dictionary(page_id).ok()?;
+        let crop_box = self.get_inherited_attribute(page_dict, b"CropBox")?;
+        let resolve = |obj: &lopdf::Object| match obj {
+            lopdf::Object::Reference(id) => self.doc.get_object(*id).ok().cloned(),
+            _ => Some(obj.clone()),
+        };
+        let crop_box = resolve(&crop_box)?;
+        let values: Vec<f64> = crop_box
+            .as_array()
+            .ok()?
+            .iter()
+            .map(|obj| match resolve(obj)? {
+                lopdf::Object::Integer(i) => Some(i as f64),
+                lopdf::Object::Real(f) => Some(f as f64),
+                _ => None,
+            })
+            .collect::<Option<_>>()?;
+        match values[..] {
+            [x0, y0, x1, y1] => Some((x0, y0, x1, y1)),
+            _ => None,
+        }
+    }
+
+    /// 指定ページ(1-indexed)の表示領域（CropBox、無ければMediaBox）の左下隅(x, y)を
+    /// ページ座標系の原点として返す。
+    ///
+    /// `[-10 -10 605 802]`のように原点が(0, 0)でないMediaBoxや、MediaBoxより小さい
+    /// CropBoxでは、ビットマップ座標をページ座標に戻す際にこの値だけずらす必要がある。
+    pub fn page_origin(&self, page_num: u32) -> crate::error::Result<(f64, f64)> {
+        let (x_min, y_min, _, _) = self.visible_box_rect(page_num)?;
+        Ok((x_min, y_min))
+    }
+
+    /// 指定ページ(1-indexed)の/Rotateと表示領域から、ページ空間と表示上の座標の
+    /// 変換に使う[`PageOrientation`]を返す。
+    pub fn page_orientation(&self, page_num: u32) -> crate::error::Result<PageOrientation> {
+        let (x_min, y_min, x_max, y_max) = self.visible_box_rect(page_num)?;
+        Ok(PageOrientation {
+            rotate: self.page_rotation(page_num)?,
+            origin: (x_min, y_min),
+            width: x_max - x_min,
+            height: y_max - y_min,
+        })
+    }
+
+    /// 指定ページ(1-indexed)の表示領域（CropBox、無ければMediaBox）からページ寸法
+    /// (width_pts, height_pts)を返す。
+    ///
+    /// ラスタライズはこの範囲だけを対象にし、CropBoxの外にある内容はビットマップに含めない。
+    pub fn page_dimensions(&self, page_num: u32) -> crate::error::Result<(f64, f64)> {
+        let (x0, y0, x1, y1) = self.visible_box_rect(page_num)?;
 
         let width = (x1 - x0).abs();
         let height = (y1 - y0).abs();
 
         // Validate that the computed page dimensions are positive and reasonable.
-        if width <= 0.0 || height <= 0.0 {
-            return Err(crate::error::PdfMaskError::pdf_read(
-                "Invalid MediaBox: non-positive page dimensions",
-            ));
+        if !(width.is_finite() && height.is_finite()) || width <= 0.0 || height <= 0.0 {
+            return Err(crate::error::PdfMaskError::pdf_read(format!(
+                "Invalid MediaBox on page {page_num}: non-positive page dimensions \
+                 ({width} x {height} pt)"
+            )));
         }
 
         // Optionally enforce an upper bound based on typical PDF limits (14,400 pt ≈ 200 in).
@@ -92,11 +285,77 @@ impl PdfReader {
         Ok((width, height))
     }
 
+    /// 指定ページ(1-indexed)の/UserUnit（1ユーザー空間単位あたりの1/72インチ数）を返す。
+    ///
+    /// /UserUnitは継承されない属性で、無いか正でなければ1とする。
+    pub fn page_user_unit(&self, page_num: u32) -> crate::error::Result<f32> {
+        let page_id = self.get_page_id(page_num)?;
+        let page_dict = self.doc.get_dictionary(page_id)?;
+        Ok(match page_dict.get(b"UserUnit") {
+            Ok(lopdf::Object::Integer(i)) if *i > 0 => *i as f32,
+            Ok(lopdf::Object::Real(f)) if *f > 0.0 => *f,
+            _ => 1.0,
+        })
+    }
+
+    /// 指定ページ(1-indexed)を`dpi`でラスタライズした際のビットマップ寸法(width_px, height_px)を返す。
+    ///
+    /// /Rotate（90°/270°で幅と高さを入れ替え）と/UserUnitを考慮する。
+    /// 丸めは`render_page_with_options`と同じくf32で`(pts * user_unit * dpi / 72).round()`とする。
+    pub fn page_pixel_size(&self, page_num: u32, dpi: u32) -> crate::error::Result<(u32, u32)> {
+        if dpi == 0 {
+            return Err(crate::error::PdfMaskError::pdf_read(
+                "dpi must be greater than 0",
+            ));
+        }
+
+        let (width, height) = self.page_dimensions(page_num)?;
+        let user_unit = self.page_user_unit(page_num)?;
+        let rotate = self.page_rotation(page_num)?;
+
+        let (width, height) = if rotate == 90 || rotate == 270 {
+            (height, width)
+        } else {
+            (width, height)
+        };
+
+        let width_px = (width as f32 * user_unit * dpi as f32 / 72.0).round() as u32;
+        let height_px = (height as f32 * user_unit * dpi as f32 / 72.0).round() as u32;
+        if width_px == 0 || height_px == 0 {
+            return Err(crate::error::PdfMaskError::pdf_read(format!(
+                "page {page_num} is smaller than one pixel at {dpi} DPI \
+                 ({width} x {height} pt)"
+            )));
+        }
+        Ok((width_px, height_px))
+    }
+
     /// 指定ページ(1-indexed)のコンテンツストリームをバイト列として返す。
     /// 複数のContentストリームがある場合は結合して返す。
+    /// フィルタ連鎖（RunLengthDecodeを含む）はストリームごとにデコードする。
     pub fn page_content_stream(&self, page_num: u32) -> crate::error::Result<Vec<u8>> {
         let page_id = self.get_page_id(page_num)?;
-        Ok(self.doc.get_page_content(page_id)?)
+        Ok(page_content_bytes(&self.doc, page_id))
+    }
+
+    /// [`page_content_stream`](Self::page_content_stream)と同じだが、
+    /// デコード後の合計が`max_bytes`を超えるページは`LimitExceeded`エラーにする。
+    ///
+    /// 上限はフィルタの展開中に検査するため、巨大なストリームを確保する前に打ち切られる。
+    pub fn page_content_stream_limited(
+        &self,
+        page_num: u32,
+        max_bytes: u64,
+    ) -> crate::error::Result<Vec<u8>> {
+        let page_id = self.get_page_id(page_num)?;
+        let max_len = usize::try_from(max_bytes).unwrap_or(usize::MAX);
+        page_content_bytes_limited(&self.doc, page_id, max_len).map_err(|e| match e {
+            PdfMaskError::LimitExceeded(_) => PdfMaskError::limit_exceeded(format!(
+                "page {} content stream exceeds max_content_stream_bytes limit of {} bytes",
+                page_num, max_bytes
+            )),
+            other => other,
+        })
     }
 
     /// 指定ページ(1-indexed)のXObjectリソースのうち、Subtype=ImageのXObject名一覧を返す。
@@ -179,6 +438,92 @@ impl PdfReader {
         Ok(names)
     }
 
+    /// 指定ページ(1-indexed)のResourcesからたどれる画像XObjectをすべて返す。
+    ///
+    /// Form XObjectとタイリングパターンの/Resourcesを再帰的にたどる。読めないフォーム・
+    /// パターンや、ネスト・個数の上限を超えるフォームがあればエラーにする。
+    /// 見落とした画像があるまま成功とせず、リダクション検証を失敗させるため。
+    pub fn page_reachable_images(
+        &self,
+        page_num: u32,
+    ) -> crate::error::Result<Vec<ReachableImage>> {
+        let page_id = self.get_page_id(page_num)?;
+        let (resource_dict, resource_ids) = self.doc.get_page_resources(page_id)?;
+
+        let mut images = Vec::new();
+        let mut form_budget = MAX_FORMS_PER_PAGE;
+        if let Some(dict) = resource_dict {
+            self.collect_reachable_images(dict, &mut Vec::new(), &mut images, &mut form_budget)?;
+        }
+        for res_id in resource_ids {
+            let dict = self.doc.get_dictionary(res_id)?;
+            self.collect_reachable_images(dict, &mut Vec::new(), &mut images, &mut form_budget)?;
+        }
+        debug!(
+            page = page_num,
+            count = images.len(),
+            "collected reachable images"
+        );
+        Ok(images)
+    }
+
+    /// リソース辞書のXObject・Patternから画像を集め、フォームとパターンの中へ再帰する。
+    fn collect_reachable_images(
+        &self,
+        dict: &lopdf::Dictionary,
+        form_path: &mut Vec<String>,
+        images: &mut Vec<ReachableImage>,
+        form_budget: &mut usize,
+    ) -> crate::error::Result<()> {
+        fn unreadable(kind: &str, name: &str, form_path: &[String]) -> PdfMaskError {
+            PdfMaskError::pdf_read(format!(
+                "{kind} {name} at {form_path:?} cannot be inspected"
+            ))
+        }
+        let mut nested: Vec<(String, &lopdf::Stream)> = Vec::new();
+        if let Some(xobjects) = self.resolve_resource_dict(dict, b"XObject") {
+            for (name, obj) in xobjects.iter() {
+                let name = String::from_utf8_lossy(name).into_owned();
+                let Ok((_, lopdf::Object::Stream(stream))) = self.doc.dereference(obj) else {
+                    return Err(unreadable("XObject", &name, form_path));
+                };
+                match stream.dict.get(b"Subtype").and_then(lopdf::Object::as_name) {
+                    Ok(b"Image") => images.push(ReachableImage {
+                        form_path: form_path.clone(),
+                        name,
+                        stream: stream.clone(),
+                    }),
+                    Ok(b"Form") => nested.push((name, stream)),
+                    _ => {}
+                }
+            }
+        }
+        if let Some(patterns) = self.resolve_resource_dict(dict, b"Pattern") {
+            for (name, obj) in patterns.iter() {
+                // シェーディングパターン（辞書）は画像を持たない
+                if let Ok((_, lopdf::Object::Stream(stream))) = self.doc.dereference(obj) {
+                    nested.push((String::from_utf8_lossy(name).into_owned(), stream));
+                }
+            }
+        }
+
+        for (name, stream) in nested {
+            if form_path.len() >= MAX_FORM_DEPTH || *form_budget == 0 {
+                return Err(unreadable("form XObject or pattern", &name, form_path));
+            }
+            *form_budget -= 1;
+            let Some(resources) = self.resolve_resource_dict(&stream.dict, b"Resources") else {
+                // /Resourcesの無いフォームはページのリソースを使う（古いPDF）。画像はページ側で集める
+                continue;
+            };
+            form_path.push(name);
+            let result = self.collect_reachable_images(resources, form_path, images, form_budget);
+            form_path.pop();
+            result?;
+        }
+        Ok(())
+    }
+
     /// 指定ページ(1-indexed)のXObjectリソースから画像Streamオブジェクトを取得する。
     ///
     /// XObject名をキー、lopdf::Streamを値とするHashMapを返す。
@@ -220,6 +565,246 @@ impl PdfReader {
         Ok(())
     }
 
+    /// 指定ページ(1-indexed)の画像XObjectのうち、/SMaskが/Matteを持つものの/SMaskを取得する。
+    ///
+    /// XObject名をキー、解決した/SMaskのStreamを値とするHashMapを返す。
+    /// リダクション時にマット色との合成を戻すためにアルファが要る。
+    pub fn page_matte_smasks(
+        &self,
+        page_num: u32,
+    ) -> crate::error::Result<HashMap<String, lopdf::Stream>> {
+        let page_id = self.get_page_id(page_num)?;
+        let (resource_dict, resource_ids) = self.doc.get_page_resources(page_id)?;
+
+        let mut smasks = HashMap::new();
+        let mut collect = |dict: &lopdf::Dictionary| {
+            self.for_each_image_xobject(dict, |name, stream| {
+                if let Some(smask) = self.matte_smask(stream) {
+                    smasks.insert(name, smask.clone());
+                }
+            })
+        };
+
+        if let Some(dict) = resource_dict {
+            collect(dict)?;
+        }
+        for res_id in resource_ids {
+            collect(self.doc.get_dictionary(res_id)?)?;
+        }
+        Ok(smasks)
+    }
+
+    /// 画像の/SMaskが/Matteを持つストリームなら返す。
+    fn matte_smask(&self, stream: &lopdf::Stream) -> Option<&lopdf::Stream> {
+        let id = stream
+            .dict
+            .get(b"SMask")
+            .and_then(lopdf::Object::as_reference)
+            .ok()?;
+        let smask = self
+            .doc
+            .get_object(id)
+            .and_then(lopdf::Object::as_stream)
+            .ok()?;
+        smask.dict.has(b"Matte").then_some(smask)
+    }
+
+    /// ページのリソースからtint変換を持つ名前付き色空間（DeviceN/Separation）を収集する。
+    ///
+    /// キーは/ColorSpaceのリソース名。解析できない色空間はスキップする。
+    pub fn page_tint_color_spaces(
+        &self,
+        page_num: u32,
+    ) -> crate::error::Result<HashMap<String, TintColorSpace>> {
+        let page_id = self.get_page_id(page_num)?;
+        let (resource_dict, resource_ids) = self.doc.get_page_resources(page_id)?;
+
+        let mut spaces = HashMap::new();
+
+        if let Some(dict) = resource_dict {
+            self.collect_tint_color_spaces_from_dict(dict, &mut spaces);
+        }
+        for res_id in resource_ids {
+            let dict = self.doc.get_dictionary(res_id)?;
+            self.collect_tint_color_spaces_from_dict(dict, &mut spaces);
+        }
+
+        debug!(
+            page = page_num,
+            count = spaces.len(),
+            "collected tint color spaces"
+        );
+        Ok(spaces)
+    }
+
+    /// リソース辞書の/ColorSpaceからDeviceN/Separation色空間を収集する。
+    fn collect_tint_color_spaces_from_dict(
+        &self,
+        dict: &lopdf::Dictionary,
+        spaces: &mut HashMap<String, TintColorSpace>,
+    ) {
+        let Some(Ok(color_spaces)) = dict
+            .get(b"ColorSpace")
+            .ok()
+            .map(|obj| self.doc.dereference(obj).and_then(|(_, obj)| obj.as_dict()))
+        else {
+            return;
+        };
+        for (name, obj) in color_spaces.iter() {
+            let name = String::from_utf8_lossy(name).into_owned();
+            match TintColorSpace::parse(&self.doc, obj) {
+                Ok(Some(space)) => {
+                    spaces.insert(name, space);
+                }
+                Ok(None) => {}
+                Err(e) => debug!(name, error = %e, "skipping unparsable color space"),
+            }
+        }
+    }
+
+    /// ページの白色fill判定用リソース（名前付き色空間・ExtGState・Form XObject）を収集する。
+    ///
+    /// Form XObjectは自身の/Resourcesも含めて再帰的に読み込む。
+    /// 読み込めないフォームはスキップする。
+    pub fn page_fill_resources(&self, page_num: u32) -> crate::error::Result<FillResources> {
+        let page_id = self.get_page_id(page_num)?;
+        let (resource_dict, resource_ids) = self.doc.get_page_resources(page_id)?;
+
+        let mut resources = FillResources::default();
+        let mut form_budget = MAX_FORMS_PER_PAGE;
+
+        if let Some(dict) = resource_dict {
+            self.collect_fill_resources_from_dict(dict, &mut resources, 0, &mut form_budget);
+        }
+        for res_id in resource_ids {
+            let dict = self.doc.get_dictionary(res_id)?;
+            self.collect_fill_resources_from_dict(dict, &mut resources, 0, &mut form_budget);
+        }
+
+        debug!(
+            page = page_num,
+            color_spaces = resources.color_spaces.len(),
+            ext_g_states = resources.ext_g_states.len(),
+            forms = resources.forms.len(),
+            "collected fill resources"
+        );
+        Ok(resources)
+    }
+
+    /// リソース辞書から白色fill判定用のリソースを収集する。
+    fn collect_fill_resources_from_dict(
+        &self,
+        dict: &lopdf::Dictionary,
+        resources: &mut FillResources,
+        depth: usize,
+        form_budget: &mut usize,
+    ) {
+        self.collect_tint_color_spaces_from_dict(dict, &mut resources.color_spaces);
+
+        if let Some(states) = self.resolve_resource_dict(dict, b"ExtGState") {
+            for (name, obj) in states.iter() {
+                if let Ok((_, lopdf::Object::Dictionary(state))) = self.doc.dereference(obj) {
+                    resources.ext_g_states.insert(
+                        String::from_utf8_lossy(name).into_owned(),
+                        ext_g_state_opacity(state),
+                    );
+                }
+            }
+        }
+
+        if depth >= MAX_FORM_DEPTH {
+            return;
+        }
+        let Some(xobjects) = self.resolve_resource_dict(dict, b"XObject") else {
+            return;
+        };
+        for (name, obj) in xobjects.iter() {
+            let Ok((_, lopdf::Object::Stream(stream))) = self.doc.dereference(obj) else {
+                continue;
+            };
+            if !matches!(
+                stream.dict.get(b"Subtype").and_then(lopdf::Object::as_name),
+                Ok(b"Form")
+            ) {
+                continue;
+            }
+            let name = String::from_utf8_lossy(name).into_owned();
+            if *form_budget == 0 {
+                debug!(
+                    name,
+                    "too many form XObjects; remaining forms are not scanned"
+                );
+                return;
+            }
+            *form_budget -= 1;
+            match self.load_form_xobject(stream, depth, form_budget) {
+                Ok(form) => {
+                    resources.forms.insert(name, form);
+                }
+                Err(e) => debug!(name, error = %e, "skipping unreadable form XObject"),
+            }
+        }
+    }
+
+    /// Form XObjectのコンテンツ・/Matrix・/BBox・透明グループ・リソースを読み込む。
+    fn load_form_xobject(
+        &self,
+        stream: &lopdf::Stream,
+        depth: usize,
+        form_budget: &mut usize,
+    ) -> crate::error::Result<FormXObject> {
+        let content = crate::pdf::filters::decode_stream(stream)?;
+        let numbers = |key: &[u8]| -> Option<Vec<f64>> {
+            let (_, obj) = self.doc.dereference(stream.dict.get(key).ok()?).ok()?;
+            obj.as_array()
+                .ok()?
+                .iter()
+                .map(|v| operand_to_f64(v).ok())
+                .collect()
+        };
+
+        let matrix = match numbers(b"Matrix").as_deref() {
+            Some(&[a, b, c, d, e, f]) => Matrix { a, b, c, d, e, f },
+            _ => Matrix::identity(),
+        };
+        let bbox = match numbers(b"BBox").as_deref() {
+            Some(&[x0, y0, x1, y1]) => BBox {
+                x_min: x0.min(x1),
+                y_min: y0.min(y1),
+                x_max: x0.max(x1),
+                y_max: y0.max(y1),
+            },
+            _ => return Err(PdfMaskError::pdf_read("form XObject without a valid /BBox")),
+        };
+        let transparency_group = self
+            .resolve_resource_dict(&stream.dict, b"Group")
+            .and_then(|group| group.get(b"S").and_then(lopdf::Object::as_name).ok())
+            .is_some_and(|s| s == b"Transparency");
+
+        let mut resources = FillResources::default();
+        if let Some(dict) = self.resolve_resource_dict(&stream.dict, b"Resources") {
+            self.collect_fill_resources_from_dict(dict, &mut resources, depth + 1, form_budget);
+        }
+
+        Ok(FormXObject {
+            content,
+            matrix,
+            bbox,
+            transparency_group,
+            resources,
+        })
+    }
+
+    /// 辞書のエントリを（間接参照を解決して）辞書として返す。
+    fn resolve_resource_dict<'a>(
+        &'a self,
+        dict: &'a lopdf::Dictionary,
+        key: &[u8],
+    ) -> Option<&'a lopdf::Dictionary> {
+        let (_, obj) = self.doc.dereference(dict.get(key).ok()?).ok()?;
+        obj.as_dict().ok()
+    }
+
     /// ページ番号(1-indexed)からObjectIdを取得する。
     fn get_page_id(&self, page_num: u32) -> crate::error::Result<lopdf::ObjectId> {
         let pages = self.doc.get_pages();
@@ -228,3 +813,185 @@ impl PdfReader {
         })
     }
 }
+
+/// ページのContentストリームをデコードして結合したバイト列を返す。
+///
+/// デコードできないストリームは生データを使う。
+pub(crate) fn page_content_bytes(doc: &Document, page_id: lopdf::ObjectId) -> Vec<u8> {
+    // 上限なしではLimitExceededにならない
+    page_content_bytes_limited(doc, page_id, usize::MAX).unwrap_or_default()
+}
+
+/// [`page_content_bytes`]と同じだが、結合後のバイト数が`max_len`を超えたら
+/// `LimitExceeded`エラーを返す。
+fn page_content_bytes_limited(
+    doc: &Document,
+    page_id: lopdf::ObjectId,
+    max_len: usize,
+) -> crate::error::Result<Vec<u8>> {
+    let mut content = Vec::new();
+    for stream_id in doc.get_page_contents(page_id) {
+        if let Ok(stream) = doc.get_object(stream_id).and_then(|o| o.as_stream()) {
+            let remaining = max_len - content.len();
+            let decoded = match crate::pdf::filters::decode_filter_chain_limited(stream, remaining)
+            {
+                Ok((data, None)) => Ok(data),
+                Ok((_, Some(filter))) => Err(PdfMaskError::pdf_read(format!(
+                    "image filter {} is not valid for a non-image stream",
+                    filter
+                ))),
+                Err(e) => Err(e),
+            };
+            match decoded {
+                Ok(data) => content.extend_from_slice(&data),
+                Err(e @ PdfMaskError::LimitExceeded(_)) => return Err(e),
+                Err(e) => {
+                    // lopdfと同様、デコードできないストリームは生データを使う
+                    debug!(?page_id, error = %e, "content stream decode failed");
+                    content.extend_from_slice(&stream.content);
+                }
+            }
+            if content.len() > max_len {
+                return Err(PdfMaskError::limit_exceeded(format!(
+                    "content stream exceeds the size limit of {} bytes",
+                    max_len
+                )));
+            }
+        }
+    }
+    Ok(content)
+}
+
+/// オブジェクトを参照先までたどってバイト列に直列化する（[`PdfReader::page_resource_bytes`]用）。
+///
+/// 参照は初出の順番で番号を振り、2度目以降は番号だけを書くため、循環参照でも停止し、
+/// オブジェクト番号が振り直されても同じ内容なら同じバイト列になる。ストリームは
+/// 辞書と（デコードしない）データを書く。/Parentはページツリーに戻るため含めない。
+fn append_object_bytes(
+    doc: &Document,
+    obj: &Object,
+    out: &mut Vec<u8>,
+    visited: &mut HashMap<lopdf::ObjectId, usize>,
+) {
+    match obj {
+        Object::Null => out.push(b'n'),
+        Object::Boolean(value) => out.push(if *value { b't' } else { b'f' }),
+        Object::Integer(value) => {
+            out.push(b'i');
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        Object::Real(value) => {
+            out.push(b'r');
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        Object::Name(name) => {
+            out.push(b'/');
+            append_len(out, name.len());
+            out.extend_from_slice(name);
+        }
+        Object::String(bytes, _) => {
+            out.push(b'(');
+            append_len(out, bytes.len());
+            out.extend_from_slice(bytes);
+        }
+        Object::Array(items) => {
+            out.push(b'[');
+            append_len(out, items.len());
+            for item in items {
+                append_object_bytes(doc, item, out, visited);
+            }
+        }
+        Object::Dictionary(dict) => append_dict_bytes(doc, dict, out, visited),
+        Object::Stream(stream) => {
+            out.push(b's');
+            append_dict_bytes(doc, &stream.dict, out, visited);
+            append_len(out, stream.content.len());
+            out.extend_from_slice(&stream.content);
+        }
+        Object::Reference(id) => {
+            if let Some(&index) = visited.get(id) {
+                out.push(b'R');
+                append_len(out, index);
+                return;
+            }
+            visited.insert(*id, visited.len());
+            out.push(b'o');
+            match doc.get_object(*id) {
+                Ok(target) => append_object_bytes(doc, target, out, visited),
+                Err(_) => out.push(b'n'),
+            }
+        }
+    }
+}
+
+/// 辞書を[`append_object_bytes`]の形式で直列化する。
+fn append_dict_bytes(
+    doc: &Document,
+    dict: &lopdf::Dictionary,
+    out: &mut Vec<u8>,
+    visited: &mut HashMap<lopdf::ObjectId, usize>,
+) {
+    out.push(b'<');
+    append_len(out, dict.len());
+    for (key, value) in dict.iter() {
+        if key == b"Parent" {
+            continue;
+        }
+        append_len(out, key.len());
+        out.extend_from_slice(key);
+        append_object_bytes(doc, value, out, visited);
+    }
+}
+
+/// 長さを固定幅で書く。可変長の値の境界があいまいにならないようにする。
+fn append_len(out: &mut Vec<u8>, len: usize) {
+    out.extend_from_slice(&(len as u64).to_le_bytes());
+}
+
+/// 継承可能なページ属性（Resources/MediaBox/CropBox/Rotate）を取得する。
+///
+/// ページ辞書に無ければParentをたどり、中間のPagesノードで指定された値を返す。
+/// 循環したParent参照でも停止するよう、たどる深さに上限を設ける。
+pub(crate) fn inherited_page_attribute(
+    doc: &Document,
+    dict: &lopdf::Dictionary,
+    key: &[u8],
+) -> Option<lopdf::Object> {
+    const MAX_DEPTH: usize = 64;
+
+    let mut current = dict;
+    for _ in 0..MAX_DEPTH {
+        // まず現在の辞書から探す
+        if let Ok(obj) = current.get(key) {
+            return Some(obj.clone());
+        }
+        // 見つからなければParentをたどって継承を確認する
+        let lopdf::Object::Reference(parent_id) = current.get(b"Parent").ok()? else {
+            return None;
+        };
+        current = doc.get_dictionary(*parent_id).ok()?;
+    }
+    None
+}
+
+/// ExtGStateから塗りの不透明度に関わるエントリ（/ca・/SMask・/BM）を読む。
+fn ext_g_state_opacity(state: &lopdf::Dictionary) -> ExtGStateOpacity {
+    let fill_alpha = state.get(b"ca").ok().and_then(|v| operand_to_f64(v).ok());
+    let soft_mask = state
+        .get(b"SMask")
+        .ok()
+        .map(|v| !matches!(v.as_name(), Ok(b"None")));
+    let blend = state.get(b"BM").ok().map(|v| {
+        // 配列の場合は先頭（最優先）のブレンドモード
+        let mode = match v {
+            lopdf::Object::Array(modes) => modes.first().and_then(|m| m.as_name().ok()),
+            other => other.as_name().ok(),
+        };
+        !matches!(mode, Some(b"Normal" | b"Compatible"))
+    });
+    ExtGStateOpacity {
+        fill_alpha,
+        soft_mask,
+        blend,
+    }
+}
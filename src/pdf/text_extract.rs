@@ -0,0 +1,202 @@
+// ページテキスト抽出: 文字コードをUnicodeに戻し、単語単位の位置付きテキストを返す
+
+use lopdf::Document;
+use tracing::debug;
+
+use crate::pdf::content_stream::{BBox, Matrix, ctm_to_bbox};
+use crate::pdf::font::{ParsedFont, parse_page_fonts};
+use crate::pdf::reader::page_content_bytes;
+use crate::pdf::text_state::{TextDrawCommand, TjArrayEntry, parse_content_operations};
+
+/// 単語間とみなす送り幅の閾値（em単位）。
+///
+/// TJの位置調整やTj同士の間隔がこれを超えると単語を区切る。
+const WORD_GAP_EM: f64 = 0.2;
+
+/// Unicodeに変換できない文字コードの代替文字。
+const REPLACEMENT_CHAR: char = '\u{FFFD}';
+
+/// ページ上の単語とそのバウンディングボックス（ページ空間）。
+#[derive(Debug, Clone)]
+pub struct PositionedText {
+    pub text: String,
+    pub bbox: BBox,
+}
+
+/// 1つの描画コマンド内で切り出した単語片。
+struct Fragment {
+    text: String,
+    bbox: BBox,
+    /// 単語片の開始点・終了点（ページ空間のベースライン上）
+    start: (f64, f64),
+    end: (f64, f64),
+    /// コマンドの先頭から始まる／末尾まで続くか（コマンドをまたぐ結合の判定用）
+    at_command_start: bool,
+    at_command_end: bool,
+    /// 単語区切りとみなすページ空間での距離
+    gap_threshold: f64,
+}
+
+/// 指定ページ(1-indexed)のテキストを単語単位で抽出する。
+///
+/// 文字コードはフォントの/ToUnicode CMap（無ければエンコーディング）でUnicodeに
+/// 変換し、空白とTJの大きな位置調整で単語を区切る。直前の描画コマンドの終端から
+/// 続けて描かれたTjは同じ単語として結合する。フォントを解決できない描画コマンドはスキップする。
+pub fn extract_page_text(
+    doc: &Document,
+    page_num: u32,
+) -> crate::error::Result<Vec<PositionedText>> {
+    let page_id = doc.get_pages().get(&page_num).copied().ok_or_else(|| {
+        crate::error::PdfMaskError::pdf_read(format!("page {} not found", page_num))
+    })?;
+    let content = page_content_bytes(doc, page_id);
+    let fonts = parse_page_fonts(doc, page_num)?;
+    let operations = parse_content_operations(&content, Some(&fonts))?;
+
+    let mut words: Vec<PositionedText> = Vec::new();
+    // 直前の単語が描画コマンドの末尾まで続いていた場合の終了点と閾値
+    let mut open_end: Option<((f64, f64), f64)> = None;
+
+    for cmd in &operations.text_commands {
+        let Some(font) = fonts.get(&cmd.font_name) else {
+            debug!(page = page_num, font = %cmd.font_name, "font not resolved; text skipped");
+            open_end = None;
+            continue;
+        };
+
+        for fragment in command_fragments(cmd, font) {
+            let continues = fragment.at_command_start
+                && open_end.is_some_and(|(end, threshold)| {
+                    distance(end, fragment.start) <= threshold.max(fragment.gap_threshold)
+                });
+            open_end = fragment
+                .at_command_end
+                .then_some((fragment.end, fragment.gap_threshold));
+
+            match words.last_mut() {
+                Some(last) if continues => {
+                    last.text.push_str(&fragment.text);
+                    last.bbox = union(&last.bbox, &fragment.bbox);
+                }
+                _ => words.push(PositionedText {
+                    text: fragment.text,
+                    bbox: fragment.bbox,
+                }),
+            }
+        }
+    }
+
+    Ok(words)
+}
+
+/// 描画コマンドを空白とTJの位置調整で単語片に分割する。
+fn command_fragments(cmd: &TextDrawCommand, font: &ParsedFont) -> Vec<Fragment> {
+    let scale = cmd.horizontal_scaling / 100.0;
+    let word_gap = WORD_GAP_EM * cmd.font_size * scale.abs();
+    let to_page = cmd.text_matrix.multiply(&cmd.ctm);
+    let y_min = font.descent() / 1000.0 * cmd.font_size + cmd.text_rise;
+    let y_max = font.ascent() / 1000.0 * cmd.font_size + cmd.text_rise;
+    // テキスト空間の距離をページ空間に換算する係数
+    let page_scale = (to_page.a * to_page.d - to_page.b * to_page.c).abs().sqrt();
+
+    let mut fragments = Vec::new();
+    let mut x = 0.0;
+    // 現在の単語の (テキスト, 開始x, 終了x)
+    let mut current: Option<(String, f64, f64)> = None;
+    let mut at_command_start = true;
+
+    let mut flush = |current: &mut Option<(String, f64, f64)>,
+                     at_command_start: &mut bool,
+                     at_command_end: bool| {
+        if let Some((text, x_start, x_end)) = current.take() {
+            fragments.push(Fragment {
+                text,
+                bbox: text_space_rect_to_page(x_start, x_end, y_min, y_max, &to_page),
+                start: apply(&to_page, x_start, 0.0),
+                end: apply(&to_page, x_end, 0.0),
+                at_command_start: *at_command_start,
+                at_command_end,
+                gap_threshold: word_gap * page_scale,
+            });
+        }
+        *at_command_start = false;
+    };
+
+    let single = [TjArrayEntry::Text(cmd.char_codes.clone())];
+    let entries = cmd.tj_array.as_deref().unwrap_or(&single);
+
+    for entry in entries {
+        match entry {
+            TjArrayEntry::Adjustment(adj) => {
+                let dx = -(adj / 1000.0) * cmd.font_size * scale;
+                if dx > word_gap {
+                    flush(&mut current, &mut at_command_start, false);
+                }
+                x += dx;
+            }
+            TjArrayEntry::Text(codes) => {
+                for &code in codes {
+                    let mut tx =
+                        (font.glyph_width(code) / 1000.0) * cmd.font_size + cmd.char_spacing;
+                    // スペース文字の場合はword_spacingも追加
+                    if code == 0x20 {
+                        tx += cmd.word_spacing;
+                    }
+                    let advance = tx * scale;
+
+                    let text = font
+                        .char_code_to_unicode(code)
+                        .unwrap_or_else(|| REPLACEMENT_CHAR.to_string());
+                    if text.chars().all(char::is_whitespace) {
+                        flush(&mut current, &mut at_command_start, false);
+                    } else {
+                        let (word, _, x_end) = current.get_or_insert_with(|| (String::new(), x, x));
+                        word.push_str(&text);
+                        *x_end = x + advance;
+                    }
+                    x += advance;
+                }
+            }
+        }
+    }
+    flush(&mut current, &mut at_command_start, true);
+
+    fragments
+}
+
+/// テキスト空間の矩形 [x_start, x_end] × [y_min, y_max] をページ空間のBBoxに変換する。
+fn text_space_rect_to_page(
+    x_start: f64,
+    x_end: f64,
+    y_min: f64,
+    y_max: f64,
+    to_page: &Matrix,
+) -> BBox {
+    // 単位正方形をテキスト空間の矩形に写す行列
+    let rect = Matrix {
+        a: x_end - x_start,
+        b: 0.0,
+        c: 0.0,
+        d: y_max - y_min,
+        e: x_start,
+        f: y_min,
+    };
+    ctm_to_bbox(&rect.multiply(to_page))
+}
+
+fn apply(m: &Matrix, x: f64, y: f64) -> (f64, f64) {
+    (m.a * x + m.c * y + m.e, m.b * x + m.d * y + m.f)
+}
+
+fn distance(p: (f64, f64), q: (f64, f64)) -> f64 {
+    (p.0 - q.0).hypot(p.1 - q.1)
+}
+
+fn union(a: &BBox, b: &BBox) -> BBox {
+    BBox {
+        x_min: a.x_min.min(b.x_min),
+        y_min: a.y_min.min(b.y_min),
+        x_max: a.x_max.max(b.x_max),
+        y_max: a.y_max.max(b.y_max),
+    }
+}
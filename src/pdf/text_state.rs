@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use lopdf::content::Content;
 
-use crate::pdf::content_stream::{Matrix, operand_to_f64};
+use crate::pdf::content_stream::{BBox, Matrix, ctm_to_bbox, operand_to_f64};
 use crate::pdf::font::{FontEncoding, ParsedFont};
 
 /// fill colorの状態（テキスト描画用）
@@ -35,6 +35,68 @@ pub struct TextDrawCommand {
     pub text_rise: f64,
 }
 
+impl TextDrawCommand {
+    /// テキスト空間での送り幅を計算する（Tc/Tw/Tz/TJ位置調整を含む）。
+    fn text_space_advance(&self, font: &ParsedFont) -> f64 {
+        let scale = self.horizontal_scaling / 100.0;
+        let glyphs_advance = |codes: &[u16]| -> f64 {
+            codes
+                .iter()
+                .map(|&code| {
+                    let mut tx =
+                        (font.glyph_width(code) / 1000.0) * self.font_size + self.char_spacing;
+                    // スペース文字の場合はword_spacingも追加
+                    if code == 0x20 {
+                        tx += self.word_spacing;
+                    }
+                    tx * scale
+                })
+                .sum()
+        };
+
+        match &self.tj_array {
+            Some(entries) => entries
+                .iter()
+                .map(|entry| match entry {
+                    TjArrayEntry::Text(codes) => glyphs_advance(codes),
+                    TjArrayEntry::Adjustment(adj) => -(adj / 1000.0) * self.font_size * scale,
+                })
+                .sum(),
+            None => glyphs_advance(&self.char_codes),
+        }
+    }
+
+    /// ページ空間でのテキストのバウンディングボックスを計算する。
+    ///
+    /// 幅はグリフ送り幅、高さはフォントのascent/descentをfont_sizeでスケールして求め、
+    /// text_rise・テキストマトリクス・CTMを適用する。回転したテキストでは
+    /// 軸平行に外接する矩形になる。向きを保った矩形は[`Self::page_quad`]。
+    pub fn page_bbox(&self, font: &ParsedFont) -> BBox {
+        ctm_to_bbox(&self.page_quad(font))
+    }
+
+    /// ページ空間でのテキストの矩形を、文字列の向きのまま返す。
+    ///
+    /// 単位正方形をテキストの矩形（左下が原点側、x軸が字送り方向）に写す行列。
+    /// 矩形の大きさは[`Self::page_bbox`]と同じ求め方による。
+    pub fn page_quad(&self, font: &ParsedFont) -> Matrix {
+        let width = self.text_space_advance(font);
+        let y_min = font.descent() / 1000.0 * self.font_size + self.text_rise;
+        let y_max = font.ascent() / 1000.0 * self.font_size + self.text_rise;
+
+        // 単位正方形をテキスト空間の矩形に写す行列
+        let rect = Matrix {
+            a: width,
+            b: 0.0,
+            c: 0.0,
+            d: y_max - y_min,
+            e: 0.0,
+            f: y_min,
+        };
+        rect.multiply(&self.text_matrix).multiply(&self.ctm)
+    }
+}
+
 /// TJ配列の要素（文字列または位置調整値）
 #[derive(Debug, Clone)]
 pub enum TjArrayEntry {
@@ -60,6 +122,8 @@ pub(crate) struct TextState {
     pub(crate) horizontal_scaling: f64,
     pub(crate) text_rise: f64,
     pub(crate) text_leading: f64,
+    /// テキスト描画モード（Tr）。3はグリフを描かない不可視テキスト
+    pub(crate) render_mode: i64,
     pub(crate) text_matrix: Matrix,
     pub(crate) text_line_matrix: Matrix,
 }
@@ -74,6 +138,7 @@ impl TextState {
             horizontal_scaling: 100.0,
             text_rise: 0.0,
             text_leading: 0.0,
+            render_mode: 0,
             text_matrix: Matrix::identity(),
             text_line_matrix: Matrix::identity(),
         }
@@ -229,7 +294,14 @@ impl TextState {
                 }
                 Ok(true)
             }
-            "Tr" => Ok(true),
+            "Tr" => {
+                if operands.len() == 1
+                    && let Ok(mode) = operands[0].as_i64()
+                {
+                    self.render_mode = mode;
+                }
+                Ok(true)
+            }
             _ => Ok(false),
         }
     }
@@ -593,7 +665,7 @@ fn apply_text_show_op(
                 let encoding = lookup_encoding(&ts.font_name, fonts);
                 let codes = extract_char_codes_for_encoding(operand, encoding);
                 let cmd = build_text_command(ts, codes, None, ctm_stack, fill_color_stack);
-                text_commands.push(cmd);
+                push_text_command(ts, cmd, fonts, text_commands);
             }
         }
         "TJ" => {
@@ -602,7 +674,7 @@ fn apply_text_show_op(
                 let (codes, tj_array) = extract_tj_array_for_encoding(operand, encoding);
                 let cmd =
                     build_text_command(ts, codes, Some(tj_array), ctm_stack, fill_color_stack);
-                text_commands.push(cmd);
+                push_text_command(ts, cmd, fonts, text_commands);
             }
         }
         "'" => {
@@ -612,29 +684,68 @@ fn apply_text_show_op(
                 let encoding = lookup_encoding(&ts.font_name, fonts);
                 let codes = extract_char_codes_for_encoding(operand, encoding);
                 let cmd = build_text_command(ts, codes, None, ctm_stack, fill_color_stack);
-                text_commands.push(cmd);
+                push_text_command(ts, cmd, fonts, text_commands);
             }
         }
         "\"" => {
             // aw ac string " = aw Tw ac Tc T* string Tj
-            if op.operands.len() == 3 {
-                if let Ok(aw) = operand_to_f64(&op.operands[0]) {
+            if let Some((aw, ac, string)) = quote_operands(op) {
+                if let Some(aw) = aw {
                     ts.word_spacing = aw;
                 }
-                if let Ok(ac) = operand_to_f64(&op.operands[1]) {
+                if let Some(ac) = ac {
                     ts.char_spacing = ac;
                 }
                 ts.apply_t_star();
                 let encoding = lookup_encoding(&ts.font_name, fonts);
-                let codes = extract_char_codes_for_encoding(&op.operands[2], encoding);
-                let cmd = build_text_command(ts, codes, None, ctm_stack, fill_color_stack);
-                text_commands.push(cmd);
+                let codes = extract_char_codes_for_encoding(string, encoding);
+                if !codes.is_empty() {
+                    let cmd = build_text_command(ts, codes, None, ctm_stack, fill_color_stack);
+                    push_text_command(ts, cmd, fonts, text_commands);
+                }
             }
         }
         _ => {}
     }
 }
 
+/// テキスト描画コマンドを追加し、テキスト位置を描画した分だけ進める。
+///
+/// フォントが解決できない場合は送り幅が分からないため位置を進めない。
+/// アウトラインの無い幅専用フォントでも/Widthsで位置を進める。
+fn push_text_command(
+    ts: &mut TextState,
+    cmd: TextDrawCommand,
+    fonts: Option<&HashMap<String, ParsedFont>>,
+    text_commands: &mut Vec<TextDrawCommand>,
+) {
+    if let Some(font) = fonts.and_then(|f| f.get(&cmd.font_name)) {
+        let translate = Matrix {
+            a: 1.0,
+            b: 0.0,
+            c: 0.0,
+            d: 1.0,
+            e: cmd.text_space_advance(font),
+            f: 0.0,
+        };
+        ts.text_matrix = translate.multiply(&ts.text_matrix);
+    }
+    text_commands.push(cmd);
+}
+
+/// `"`オペレータ (aw ac string ") のオペランドを検証して取り出す。
+///
+/// オペランドが3つでなければ`None`（オペレータ全体を無視する）。
+/// aw/acが数値でなければ対応する要素を`None`とし、間隔は変更しない。
+pub(crate) fn quote_operands(
+    op: &lopdf::content::Operation,
+) -> Option<(Option<f64>, Option<f64>, &lopdf::Object)> {
+    match op.operands.as_slice() {
+        [aw, ac, string] => Some((operand_to_f64(aw).ok(), operand_to_f64(ac).ok(), string)),
+        _ => None,
+    }
+}
+
 fn build_text_command(
     ts: &TextState,
     char_codes: Vec<u16>,
@@ -3,25 +3,54 @@ use std::collections::HashMap;
 use lopdf::content::Content;
 use tracing::debug;
 
+use crate::config::job::WidthSource;
 use crate::error::{PdfMaskError, Result};
 use crate::pdf::content_stream::{Matrix, operand_to_f64};
 use crate::pdf::font::{FontEncoding, ParsedFont};
-use crate::pdf::glyph_to_path::{GlyphPathParams, glyph_to_pdf_path};
+use crate::pdf::glyph_to_path::{GlyphPathParams, glyph_to_path_ops, glyph_to_pdf_path};
 use crate::pdf::text_state::{
     FillColor, TextState, TjArrayEntry, extract_tj_array_for_encoding, lookup_encoding,
+    quote_operands,
 };
 
 // Re-export for backward compatibility (tests import from here)
 pub use crate::pdf::text_state::extract_char_codes_for_encoding;
 
+/// 1つのBT...ETブロックから生成した出力
+#[derive(Default)]
+struct TextBlockOutput {
+    /// 塗るグリフのパス（ページ空間の座標）
+    paths: Vec<u8>,
+    /// クリップに加えるグリフのパス構築演算子（Tr 4〜7、ブロック位置のユーザー空間の座標）
+    clip: Vec<u8>,
+}
+
 /// BT...ETブロックをベクターパスに変換したコンテンツストリームを返す。
 ///
-/// フォントが見つからない場合はErrを返し、呼び出し元でpdfiumフォールバックに切り替える。
+/// フォントが見つからない場合や、幅専用フォントで可視テキストを描く場合はErrを返し、
+/// 呼び出し元でpdfiumフォールバックに切り替える。
 pub fn convert_text_to_outlines(
     content_bytes: &[u8],
     fonts: &HashMap<String, ParsedFont>,
     force_bw: bool,
 ) -> Result<Vec<u8>> {
+    convert_text_to_outlines_with_width_source(content_bytes, fonts, force_bw, WidthSource::Pdf)
+}
+
+/// [`convert_text_to_outlines`]と同じだが、字送りに使うグリフ幅の取得元を指定できる。
+///
+/// 仕様どおり/Widthsを使うのは`WidthSource::Pdf`。/Widthsが壊れていて文字が
+/// ずれるPDFでは`WidthSource::Font`でフォント自身のadvanceを使う。
+pub fn convert_text_to_outlines_with_width_source(
+    content_bytes: &[u8],
+    fonts: &HashMap<String, ParsedFont>,
+    force_bw: bool,
+    width_source: WidthSource,
+) -> Result<Vec<u8>> {
+    let opts = GlyphOptions {
+        force_bw,
+        width_source,
+    };
     if content_bytes.is_empty() {
         return Ok(Vec::new());
     }
@@ -39,7 +68,7 @@ pub fn convert_text_to_outlines(
     let mut ts = TextState::new();
 
     // BT...ETブロック内のパスバイトをバッファリングし、ETで出力に挿入
-    let mut text_path_buf: Vec<u8> = Vec::new();
+    let mut block = TextBlockOutput::default();
 
     for op in &content.operations {
         match op.operator.as_str() {
@@ -103,14 +132,21 @@ pub fn convert_text_to_outlines(
             "BT" => {
                 in_text = true;
                 ts = TextState::new();
-                text_path_buf.clear();
+                block = TextBlockOutput::default();
             }
             "ET" => {
                 in_text = false;
                 // BT...ETブロック内で生成されたパスバイトを出力に追加
-                if !text_path_buf.is_empty() {
-                    path_bytes.extend_from_slice(&text_path_buf);
-                    text_path_buf.clear();
+                path_bytes.append(&mut block.paths);
+                // テキストのクリップはETの位置で現在のクリップに加わり、同じq/Qの範囲で
+                // 後続の描画（画像のDo等）を文字の形に切り抜く
+                if !block.clip.is_empty() {
+                    let clip = Content::decode(&block.clip)
+                        .map_err(|e| PdfMaskError::content_stream(e.to_string()))?;
+                    output_ops.extend(clip.operations);
+                    output_ops.push(lopdf::content::Operation::new("W", vec![]));
+                    output_ops.push(lopdf::content::Operation::new("n", vec![]));
+                    block.clip.clear();
                 }
             }
 
@@ -130,8 +166,8 @@ pub fn convert_text_to_outlines(
                         &ctm_stack,
                         &fill_color_stack,
                         fonts,
-                        &mut text_path_buf,
-                        force_bw,
+                        &mut block,
+                        opts,
                     )?;
                 }
             }
@@ -143,8 +179,8 @@ pub fn convert_text_to_outlines(
                         &ctm_stack,
                         &fill_color_stack,
                         fonts,
-                        &mut text_path_buf,
-                        force_bw,
+                        &mut block,
+                        opts,
                     )?;
                 }
             }
@@ -157,28 +193,28 @@ pub fn convert_text_to_outlines(
                         &ctm_stack,
                         &fill_color_stack,
                         fonts,
-                        &mut text_path_buf,
-                        force_bw,
+                        &mut block,
+                        opts,
                     )?;
                 }
             }
             "\"" if in_text => {
-                if op.operands.len() == 3 {
-                    if let Ok(aw) = operand_to_f64(&op.operands[0]) {
+                if let Some((aw, ac, string)) = quote_operands(op) {
+                    if let Some(aw) = aw {
                         ts.word_spacing = aw;
                     }
-                    if let Ok(ac) = operand_to_f64(&op.operands[1]) {
+                    if let Some(ac) = ac {
                         ts.char_spacing = ac;
                     }
                     ts.apply_t_star();
                     render_show_text(
-                        &op.operands[2],
+                        string,
                         &mut ts,
                         &ctm_stack,
                         &fill_color_stack,
                         fonts,
-                        &mut text_path_buf,
-                        force_bw,
+                        &mut block,
+                        opts,
                     )?;
                 }
             }
@@ -282,6 +318,15 @@ fn apply_fill_color(
     }
 }
 
+/// グリフの描き方のオプション
+#[derive(Clone, Copy)]
+struct GlyphOptions {
+    /// 塗り色を輝度の閾値で0/1にする（BWモード）
+    force_bw: bool,
+    /// 字送りに使うグリフ幅の取得元
+    width_source: WidthSource,
+}
+
 /// Tj型テキスト描画（文字列からコードを抽出してレンダリング）
 fn render_show_text(
     operand: &lopdf::Object,
@@ -289,8 +334,8 @@ fn render_show_text(
     ctm_stack: &[Matrix],
     fill_color_stack: &[FillColor],
     fonts: &HashMap<String, ParsedFont>,
-    output: &mut Vec<u8>,
-    force_bw: bool,
+    output: &mut TextBlockOutput,
+    opts: GlyphOptions,
 ) -> Result<()> {
     let encoding = lookup_encoding(&ts.font_name, Some(fonts));
     let codes = extract_char_codes_for_encoding(operand, encoding);
@@ -299,7 +344,7 @@ fn render_show_text(
         .last()
         .cloned()
         .unwrap_or_else(FillColor::default_black);
-    render_text_codes(&codes, ts, &ctm, &fill_color, fonts, output, force_bw)
+    render_text_codes(&codes, ts, &ctm, &fill_color, fonts, output, opts)
 }
 
 /// TJ型テキスト描画（配列からコードと位置調整を処理）
@@ -309,8 +354,8 @@ fn render_show_text_array(
     ctm_stack: &[Matrix],
     fill_color_stack: &[FillColor],
     fonts: &HashMap<String, ParsedFont>,
-    output: &mut Vec<u8>,
-    force_bw: bool,
+    output: &mut TextBlockOutput,
+    opts: GlyphOptions,
 ) -> Result<()> {
     let encoding = lookup_encoding(&ts.font_name, Some(fonts));
     let (_, entries) = extract_tj_array_for_encoding(operand, encoding);
@@ -322,7 +367,7 @@ fn render_show_text_array(
     for entry in &entries {
         match entry {
             TjArrayEntry::Text(codes) => {
-                render_text_codes(codes, ts, &ctm, &fill_color, fonts, output, force_bw)?;
+                render_text_codes(codes, ts, &ctm, &fill_color, fonts, output, opts)?;
             }
             TjArrayEntry::Adjustment(val) => {
                 ts.advance_by_tj_adjustment(*val, ts.font_size);
@@ -339,19 +384,34 @@ fn render_text_codes(
     ctm: &Matrix,
     fill_color: &FillColor,
     fonts: &HashMap<String, ParsedFont>,
-    output: &mut Vec<u8>,
-    force_bw: bool,
+    output: &mut TextBlockOutput,
+    opts: GlyphOptions,
 ) -> Result<()> {
+    // 空文字列は何も描画しないので、フォントの有無を問わない
+    if codes.is_empty() {
+        return Ok(());
+    }
     let font = fonts
         .get(&ts.font_name)
         .ok_or_else(|| PdfMaskError::content_stream(format!("font not found: {}", ts.font_name)))?;
+    // 幅専用フォントはグリフを描けない。不可視テキスト（Tr 3）なら描くものが無いので
+    // 送り幅だけ進めて後続テキストの位置を保ち、可視テキストは呼び出し元のフォールバックに任せる
+    if !font.has_outlines() && ts.render_mode != 3 {
+        return Err(PdfMaskError::content_stream(format!(
+            "font has no glyph outlines: {}",
+            ts.font_name
+        )));
+    }
+    // Tr 4〜7はクリップにも加える。Tr 7はクリップのみで塗らない
+    let clips = (4..=7).contains(&ts.render_mode);
+    let paints = ts.render_mode != 7;
 
     for &code in codes {
         // グリフ解決
         if let Some(glyph_id) = font.char_code_to_glyph_id(code)
             && let Some(outline) = font.glyph_outline(glyph_id)
         {
-            let path_bytes = glyph_to_pdf_path(&GlyphPathParams {
+            let params = GlyphPathParams {
                 outline: &outline,
                 font_size: ts.font_size,
                 units_per_em: font.units_per_em(),
@@ -360,13 +420,25 @@ fn render_text_codes(
                 fill_color,
                 horizontal_scaling: ts.horizontal_scaling,
                 text_rise: ts.text_rise,
-                force_bw,
-            });
-            output.extend_from_slice(&path_bytes);
+                force_bw: opts.force_bw,
+            };
+            if paints {
+                output.paths.extend_from_slice(&glyph_to_pdf_path(&params));
+            }
+            if clips {
+                // クリップはブロックの位置にそのまま書くので、CTMを掛けないユーザー空間の座標にする
+                let identity = Matrix::identity();
+                output
+                    .clip
+                    .extend_from_slice(&glyph_to_path_ops(&GlyphPathParams {
+                        ctm: &identity,
+                        ..params
+                    }));
+            }
         }
 
         // グリフ幅で位置を進める
-        let width = font.glyph_width(code);
+        let width = font.advance_width(code, opts.width_source);
         ts.advance_by_glyph(width, ts.font_size);
 
         // スペース文字の場合はword_spacingも追加
@@ -3,13 +3,20 @@
 use std::collections::HashMap;
 
 use lopdf::{Document, Object, Stream, dictionary};
-use tracing::debug;
+#[cfg(feature = "mrc")]
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
 
-use crate::config::job::ColorMode;
+use crate::config::job::{ColorMode, MetadataMode, RedactionFill};
+#[cfg(feature = "mrc")]
+use crate::config::job::{ForegroundMask, OutputColorSpace};
 use crate::error::PdfMaskError;
 #[cfg(feature = "mrc")]
 use crate::mrc::{BwLayers, MrcLayers};
 use crate::mrc::{ImageModification, TextMaskedData, TextRegionCrop};
+use crate::pdf::content_stream::{BBox, Matrix, contains_text, strip_text_and_xobjects};
+use crate::pdf::icc::OutputIntentProfile;
+use crate::pdf::reader::{PdfReader, inherited_page_attribute, page_content_bytes};
 
 /// PDF Name仕様 (PDF Reference 7.3.5) に従い、名前をエスケープする。
 ///
@@ -35,6 +42,80 @@ fn escape_pdf_name(name: &str) -> String {
     escaped
 }
 
+/// 単位正方形→ページ座標の配置行列を、写した先の矩形が四辺に`padding`(pt)ずつ
+/// 広がるように調整する。
+fn padded_placement(placement: &Matrix, padding: f64) -> Matrix {
+    let width = placement.a.hypot(placement.b);
+    let height = placement.c.hypot(placement.d);
+    if padding <= 0.0 || width == 0.0 || height == 0.0 {
+        return placement.clone();
+    }
+    let (pu, pv) = (padding / width, padding / height);
+    let grow = Matrix {
+        a: 1.0 + 2.0 * pu,
+        b: 0.0,
+        c: 0.0,
+        d: 1.0 + 2.0 * pv,
+        e: -pu,
+        f: -pv,
+    };
+    grow.multiply(placement)
+}
+
+/// 2つの矩形が正の面積で重なるかを返す（辺が接するだけなら重ならない）。
+fn rects_overlap(a: &BBox, b: &BBox) -> bool {
+    a.x_min < b.x_max && b.x_min < a.x_max && a.y_min < b.y_max && b.y_min < a.y_max
+}
+
+/// `rect`から`others`の各矩形を除いた残りを、互いに重ならない矩形の集まりとして返す。
+///
+/// 完全に覆われていれば空になる。
+fn subtract_rects(rect: &BBox, others: &[BBox]) -> Vec<BBox> {
+    let mut pieces = vec![rect.clone()];
+    for other in others {
+        let mut remaining = Vec::with_capacity(pieces.len());
+        for piece in pieces {
+            if !rects_overlap(&piece, other) {
+                remaining.push(piece);
+                continue;
+            }
+            // 重なりの上下は全幅の帯、重なりと同じ高さの左右は残りの帯として切り出す
+            let y_lo = piece.y_min.max(other.y_min);
+            let y_hi = piece.y_max.min(other.y_max);
+            if other.y_min > piece.y_min {
+                remaining.push(BBox {
+                    y_max: other.y_min,
+                    ..piece.clone()
+                });
+            }
+            if other.y_max < piece.y_max {
+                remaining.push(BBox {
+                    y_min: other.y_max,
+                    ..piece.clone()
+                });
+            }
+            if other.x_min > piece.x_min {
+                remaining.push(BBox {
+                    x_min: piece.x_min,
+                    y_min: y_lo,
+                    x_max: other.x_min,
+                    y_max: y_hi,
+                });
+            }
+            if other.x_max < piece.x_max {
+                remaining.push(BBox {
+                    x_min: other.x_max,
+                    y_min: y_lo,
+                    x_max: piece.x_max,
+                    y_max: y_hi,
+                });
+            }
+        }
+        pieces = remaining;
+    }
+    pieces
+}
+
 /// MrcLayersからPDF XObjectを作成し、ページに追加する。
 ///
 /// 複数ページをサポートする。最初の`write_mrc_page`呼び出しでPages/Catalog構造を作成し、
@@ -46,8 +127,38 @@ pub struct MrcPageWriter {
     /// ソースPDFオブジェクトIDから出力PDFオブジェクトIDへのマッピング。
     /// ページコピー間で共有し、同一オブジェクト（フォント、画像等）の重複を防ぐ。
     copy_id_map: HashMap<lopdf::ObjectId, lopdf::ObjectId>,
+    /// 前回の出力PDFからのコピー用のIDマッピング。ソースPDFとはID空間が異なるため分けて持つ。
+    prior_copy_id_map: HashMap<lopdf::ObjectId, lopdf::ObjectId>,
+    /// `copy_page_from`でコピーしたページ間でオブジェクトを共有する。
+    /// `false`ではページごとに独立したコピーを作る（オプショナルコンテンツグループは除く）。
+    share_copied_objects: bool,
+    /// 背景XObjectの内容キーから出力PDFオブジェクトIDへのマッピング。
+    /// スライド資料のように背景レイヤーが同一のページ間で1つのBgImgを共有する。
+    /// 共有するのはJPEGのバイト列まで一致する背景だけで、見た目が近いだけの背景は共有しない。
+    #[cfg(feature = "mrc")]
+    shared_backgrounds: HashMap<BackgroundKey, lopdf::ObjectId>,
+    /// テキスト領域ImageMaskの配置を四辺に広げる量(pt)。JBIG2の画素丸めによるグリフ端の欠けを防ぐ
+    text_region_padding: f64,
+    /// MRCページの前景画像にマスクを適用する方法
+    #[cfg(feature = "mrc")]
+    foreground_mask: ForegroundMask,
+    /// MRCページの背景・前景画像に/Interpolate trueを設定する
+    #[cfg(feature = "mrc")]
+    interpolate_output: bool,
+    /// MRCページのRGBの背景・前景画像をsRGBのICCBased色空間にする
+    #[cfg(feature = "mrc")]
+    tag_srgb: bool,
+    /// sRGBのICCプロファイルストリーム。最初に必要になったときに1つだけ埋め込む
+    #[cfg(feature = "mrc")]
+    srgb_profile_id: Option<lopdf::ObjectId>,
+    /// リダクションマークのタイリングパターン（塗り方と色ごとに1つ作って共有する）
+    redaction_patterns: HashMap<(RedactionFill, [u8; 3]), lopdf::ObjectId>,
 }
 
+/// 背景XObjectの同一性判定キー（JPEGデータのSHA-256、画素寸法、色空間）
+#[cfg(feature = "mrc")]
+type BackgroundKey = ([u8; 32], u32, u32, &'static str);
+
 impl Default for MrcPageWriter {
     fn default() -> Self {
         Self::new()
@@ -60,9 +171,67 @@ impl MrcPageWriter {
             doc: Document::with_version("1.5"),
             pages_id: None,
             copy_id_map: HashMap::new(),
+            prior_copy_id_map: HashMap::new(),
+            share_copied_objects: true,
+            #[cfg(feature = "mrc")]
+            shared_backgrounds: HashMap::new(),
+            text_region_padding: 0.0,
+            #[cfg(feature = "mrc")]
+            foreground_mask: ForegroundMask::Smask,
+            #[cfg(feature = "mrc")]
+            interpolate_output: false,
+            #[cfg(feature = "mrc")]
+            tag_srgb: false,
+            #[cfg(feature = "mrc")]
+            srgb_profile_id: None,
+            redaction_patterns: HashMap::new(),
         }
     }
 
+    /// テキスト領域の配置を四辺に`padding_pts`ずつ広げる（負の値は0として扱う）。
+    ///
+    /// ImageMaskは広げた矩形に合わせてわずかに拡大される。隣の領域と目に見えて
+    /// 重ならないよう、1pt未満の小さな値を想定している。
+    pub fn set_text_region_padding(&mut self, padding_pts: f64) {
+        self.text_region_padding = padding_pts.max(0.0);
+    }
+
+    /// `copy_page_from`でコピーするページが、他のページとオブジェクト（フォント・画像等）を
+    /// 共有するかどうかを設定する（デフォルト: 共有する）。
+    ///
+    /// 共有しない場合はページごとに独立したコピーを作るため、出力は大きくなるが、
+    /// 後からの書き換えが他のページに及ばない。レイヤーの表示切り替えが効くよう、
+    /// オプショナルコンテンツグループ（OCG・OCMD）だけは共有したままにする。
+    pub fn set_share_copied_objects(&mut self, share: bool) {
+        self.share_copied_objects = share;
+    }
+
+    /// MRCページの前景画像にマスクを適用する方法を設定する。
+    ///
+    /// `Stencil`ではマスクをImageMaskとして前景画像の/Maskに置き、前景は
+    /// マスクの1の画素だけに描かれる。ソフトマスクの合成で色がにじむビューア向け。
+    #[cfg(feature = "mrc")]
+    pub fn set_foreground_mask(&mut self, foreground_mask: ForegroundMask) {
+        self.foreground_mask = foreground_mask;
+    }
+
+    /// MRCページの背景・前景画像に`/Interpolate true`を設定するかどうかを設定する。
+    ///
+    /// 縮小した背景を拡大表示するときに、ビューアがブロック状ではなく補間して描くようにする。
+    #[cfg(feature = "mrc")]
+    pub fn set_interpolate_output(&mut self, interpolate: bool) {
+        self.interpolate_output = interpolate;
+    }
+
+    /// MRCページのRGBの背景・前景画像に`[/ICCBased <sRGB>]`の色空間を設定するかどうかを設定する。
+    ///
+    /// タグの無いDeviceRGBはビューアごとに色の解釈が異なるため、sRGBとして明示する。
+    /// プロファイルは出力PDFに1つだけ埋め込み、全ページで共有する。
+    #[cfg(feature = "mrc")]
+    pub fn set_tag_srgb(&mut self, tag_srgb: bool) {
+        self.tag_srgb = tag_srgb;
+    }
+
     /// 内部のlopdf::Documentへの可変参照を返す。
     /// PDF最適化などの後処理に使用する。
     pub fn document_mut(&mut self) -> &mut Document {
@@ -108,6 +277,68 @@ impl MrcPageWriter {
         self.add_image_xobject(jpeg_data, width, height, color_space, 8, "DCTDecode", None)
     }
 
+    /// 背景JPEG XObjectを追加する。既にバイト列が同一の背景があればそれを再利用する。
+    #[cfg(feature = "mrc")]
+    fn shared_background_xobject(
+        &mut self,
+        jpeg_data: &[u8],
+        width: u32,
+        height: u32,
+        color_space: &'static str,
+    ) -> lopdf::ObjectId {
+        let key: BackgroundKey = (Sha256::digest(jpeg_data).into(), width, height, color_space);
+        if let Some(&id) = self.shared_backgrounds.get(&key) {
+            debug!(?id, "reusing shared background XObject");
+            return id;
+        }
+        let id = self.add_background_xobject(jpeg_data, width, height, color_space);
+        self.shared_backgrounds.insert(key, id);
+        id
+    }
+
+    /// 画像XObjectに`/Interpolate true`を設定する。
+    #[cfg(feature = "mrc")]
+    fn set_interpolate(&mut self, id: lopdf::ObjectId) {
+        if let Ok(Object::Stream(stream)) = self.doc.get_object_mut(id) {
+            stream.dict.set("Interpolate", true);
+        }
+    }
+
+    /// 画像XObjectの色空間を共有のsRGBプロファイルによる`[/ICCBased ...]`にする。
+    #[cfg(feature = "mrc")]
+    fn set_srgb_color_space(&mut self, id: lopdf::ObjectId) {
+        let profile_id = match self.srgb_profile_id {
+            Some(profile_id) => profile_id,
+            None => {
+                let profile = OutputIntentProfile::Srgb;
+                let profile_id = self.doc.add_object(Stream::new(
+                    dictionary! {
+                        "N" => i64::from(profile.components()),
+                        "Alternate" => "DeviceRGB",
+                    },
+                    profile.icc_profile(),
+                ));
+                self.srgb_profile_id = Some(profile_id);
+                profile_id
+            }
+        };
+        if let Ok(Object::Stream(stream)) = self.doc.get_object_mut(id) {
+            stream.dict.set(
+                "ColorSpace",
+                vec![Object::Name(b"ICCBased".to_vec()), profile_id.into()],
+            );
+        }
+    }
+
+    /// 画像XObjectに反転CMYK用の`/Decode [1 0 1 0 1 0 1 0]`を設定する。
+    #[cfg(feature = "mrc")]
+    fn set_inverted_cmyk_decode(&mut self, id: lopdf::ObjectId) {
+        if let Ok(Object::Stream(stream)) = self.doc.get_object_mut(id) {
+            let decode = [1, 0, 1, 0, 1, 0, 1, 0].map(Object::Integer).to_vec();
+            stream.dict.set("Decode", Object::Array(decode));
+        }
+    }
+
     /// マスクJBIG2 XObjectを追加する。
     pub(crate) fn add_mask_xobject(
         &mut self,
@@ -170,6 +401,24 @@ impl MrcPageWriter {
         )
     }
 
+    /// 前景JPEG XObjectを追加する（ステンシルのImageMaskとしてmask_idを/Maskで参照）。
+    #[cfg(feature = "mrc")]
+    pub(crate) fn add_stenciled_foreground_xobject(
+        &mut self,
+        jpeg_data: &[u8],
+        width: u32,
+        height: u32,
+        mask_id: lopdf::ObjectId,
+        color_space: &str,
+    ) -> lopdf::ObjectId {
+        let fg_id =
+            self.add_image_xobject(jpeg_data, width, height, color_space, 8, "DCTDecode", None);
+        if let Ok(Object::Stream(stream)) = self.doc.get_object_mut(fg_id) {
+            stream.dict.set("Mask", Object::Reference(mask_id));
+        }
+        fg_id
+    }
+
     /// MRC用のコンテンツストリームバイト列を生成する。
     pub fn build_mrc_content_stream(
         bg_name: &str,
@@ -237,21 +486,54 @@ impl MrcPageWriter {
         let height = layers.height;
         let page_width_pts = layers.page_width_pts;
         let page_height_pts = layers.page_height_pts;
+        let cmyk = layers.output_colorspace == OutputColorSpace::Cmyk;
         let color_space = match layers.color_mode {
             ColorMode::Grayscale => "DeviceGray",
+            _ if cmyk => "DeviceCMYK",
             _ => "DeviceRGB",
         };
 
         let bg_id =
-            self.add_background_xobject(&layers.background_jpeg, width, height, color_space);
-        let mask_id = self.add_mask_xobject(&layers.mask_jbig2, width, height);
-        let fg_id = self.add_foreground_xobject(
-            &layers.foreground_jpeg,
-            width,
-            height,
-            mask_id,
-            color_space,
-        );
+            self.shared_background_xobject(&layers.background_jpeg, width, height, color_space);
+        let fg_id = match self.foreground_mask {
+            ForegroundMask::Smask => {
+                let mask_id = self.add_mask_xobject(&layers.mask_jbig2, width, height);
+                self.add_foreground_xobject(
+                    &layers.foreground_jpeg,
+                    width,
+                    height,
+                    mask_id,
+                    color_space,
+                )
+            }
+            ForegroundMask::Stencil => {
+                // ImageMaskの/Decode [1 0]により、マスクの1（テキスト）の画素だけに前景を描く
+                let mask_id = self.add_text_mask_xobject(&layers.mask_jbig2, width, height);
+                self.add_stenciled_foreground_xobject(
+                    &layers.foreground_jpeg,
+                    width,
+                    height,
+                    mask_id,
+                    color_space,
+                )
+            }
+        };
+        if cmyk {
+            // Adobe形式のCMYK JPEGはインク量を反転して格納している
+            for id in [bg_id, fg_id] {
+                self.set_inverted_cmyk_decode(id);
+            }
+        }
+        if self.interpolate_output {
+            for id in [bg_id, fg_id] {
+                self.set_interpolate(id);
+            }
+        }
+        if self.tag_srgb && color_space == "DeviceRGB" {
+            for id in [bg_id, fg_id] {
+                self.set_srgb_color_space(id);
+            }
+        }
 
         let pages_id = self.ensure_pages_id();
 
@@ -341,6 +623,331 @@ impl MrcPageWriter {
         Ok(page_id)
     }
 
+    /// 何も描画しない空ページを追加する（白紙ページの置き換え用）。
+    ///
+    /// `fill_white`がtrueなら、MediaBox全体を白で塗りつぶすだけの
+    /// コンテンツストリームを付ける（画像XObjectは作らない）。
+    pub fn write_blank_page(
+        &mut self,
+        page_width_pts: f64,
+        page_height_pts: f64,
+        fill_white: bool,
+    ) -> crate::error::Result<lopdf::ObjectId> {
+        let pages_id = self.ensure_pages_id();
+
+        let mut page = dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![
+                Object::Integer(0),
+                Object::Integer(0),
+                Object::Real(page_width_pts as f32),
+                Object::Real(page_height_pts as f32),
+            ],
+            "Resources" => dictionary! {},
+        };
+        if fill_white {
+            let content_bytes =
+                format!("1 g 0 0 {page_width_pts} {page_height_pts} re f").into_bytes();
+            let content_id = self
+                .doc
+                .add_object(Stream::new(dictionary! {}, content_bytes));
+            page.set("Contents", content_id);
+        }
+        let page_id = self.doc.add_object(page);
+
+        self.append_page_to_kids(pages_id, page_id);
+
+        debug!("write_blank_page complete");
+        Ok(page_id)
+    }
+
+    /// ページのコンテンツの最後に、指定色で塗りつぶした矩形を追加する。
+    ///
+    /// リダクション領域を黒塗り等で見える形に残すために使う。矩形は出力ページの
+    /// 座標系（MediaBox左下原点）で指定し、色は0〜255のRGB。
+    pub fn add_redaction_marks(
+        &mut self,
+        page_id: lopdf::ObjectId,
+        rects: &[BBox],
+        color: [u8; 3],
+    ) -> crate::error::Result<()> {
+        self.add_redaction_marks_with_fill(page_id, rects, color, RedactionFill::Solid)
+    }
+
+    /// [`Self::add_redaction_marks`]と同じだが、塗り方を指定できる。
+    ///
+    /// `Solid`以外では`color`で描いたタイリングパターンを/Patternリソース
+    /// `RedactMark`としてページに追加し、矩形をそのパターンで塗る。
+    /// パターンのセルは白地を塗るため、マークは単色と同じく不透明になる。
+    pub fn add_redaction_marks_with_fill(
+        &mut self,
+        page_id: lopdf::ObjectId,
+        rects: &[BBox],
+        color: [u8; 3],
+        fill: RedactionFill,
+    ) -> crate::error::Result<()> {
+        if rects.is_empty() {
+            return Ok(());
+        }
+        let mut content = match self.redaction_pattern(fill, color) {
+            Some(pattern_id) => {
+                let resources_id = self.ensure_resources_as_object(page_id)?;
+                let patterns_id = self.ensure_dict_entry_as_object(resources_id, b"Pattern")?;
+                if let Some(Object::Dictionary(patterns)) = self.doc.objects.get_mut(&patterns_id) {
+                    patterns.set("RedactMark", Object::Reference(pattern_id));
+                }
+                "q /Pattern cs /RedactMark scn".to_string()
+            }
+            None => {
+                let [r, g, b] = color.map(|c| c as f64 / 255.0);
+                format!("q {r} {g} {b} rg")
+            }
+        };
+        for rect in rects {
+            let (w, h) = (rect.x_max - rect.x_min, rect.y_max - rect.y_min);
+            content.push_str(&format!(" {} {} {w} {h} re", rect.x_min, rect.y_min));
+        }
+        content.push_str(" f Q");
+        let content_id = self
+            .doc
+            .add_object(Stream::new(dictionary! {}, content.into_bytes()));
+
+        let Some(Object::Dictionary(page)) = self.doc.objects.get_mut(&page_id) else {
+            return Err(PdfMaskError::pdf_write(format!(
+                "page object {page_id:?} not found"
+            )));
+        };
+        let contents = match page.get(b"Contents").ok().cloned() {
+            Some(Object::Array(mut streams)) => {
+                streams.push(content_id.into());
+                streams
+            }
+            Some(existing @ Object::Reference(_)) => vec![existing, content_id.into()],
+            _ => vec![content_id.into()],
+        };
+        page.set("Contents", contents);
+
+        debug!(count = rects.len(), ?fill, "add_redaction_marks complete");
+        Ok(())
+    }
+
+    /// リダクションマーク用のタイリングパターン（PaintType 1）を返す。`Solid`は`None`。
+    ///
+    /// パターン空間はページの既定座標系なので、マークの矩形とセルの位置は
+    /// ページごとに揃う。同じ塗り方と色のパターンは出力PDF内で共有する。
+    fn redaction_pattern(
+        &mut self,
+        fill: RedactionFill,
+        color: [u8; 3],
+    ) -> Option<lopdf::ObjectId> {
+        if let Some(&id) = self.redaction_patterns.get(&(fill, color)) {
+            return Some(id);
+        }
+        let [r, g, b] = color.map(|c| c as f64 / 255.0);
+        let (cell_w, cell_h, cell, resources) = match fill {
+            RedactionFill::Solid => return None,
+            // 隣のセルの線とつながるよう、対角線をセルの外まで伸ばしてBBoxで切る
+            RedactionFill::Hatch => (
+                8,
+                8,
+                format!(
+                    "1 g 0 0 8 8 re f {r} {g} {b} RG 1 w \
+                     -1 -1 m 9 9 l -1 7 m 1 9 l 7 -1 m 9 1 l S"
+                ),
+                dictionary! {},
+            ),
+            RedactionFill::Text => (
+                56,
+                16,
+                format!("1 g 0 0 56 16 re f BT /F1 8 Tf {r} {g} {b} rg 4 5 Td (REDACTED) Tj ET"),
+                dictionary! {
+                    "Font" => dictionary! {
+                        "F1" => dictionary! {
+                            "Type" => "Font",
+                            "Subtype" => "Type1",
+                            "BaseFont" => "Helvetica",
+                        },
+                    },
+                },
+            ),
+        };
+        let id = self.doc.add_object(Stream::new(
+            dictionary! {
+                "Type" => "Pattern",
+                "PatternType" => 1,
+                "PaintType" => 1,
+                "TilingType" => 1,
+                "BBox" => vec![0.into(), 0.into(), cell_w.into(), cell_h.into()],
+                "XStep" => cell_w,
+                "YStep" => cell_h,
+                "Resources" => resources,
+            },
+            cell.into_bytes(),
+        ));
+        self.redaction_patterns.insert((fill, color), id);
+        Some(id)
+    }
+
+    /// ラスタライズしたページの上に、元ページの内容を`rects`でクリップして描き戻す。
+    ///
+    /// 元ページのコンテンツ（BT...ETを除く）をForm XObjectとしてコピーし、
+    /// その領域のパスや画像をラスタの上にベクターのまま重ねる。テキストはコピーせず、
+    /// ラスタライズした層にだけ残る。
+    /// XObjectは`xobjects`に挙げたもの（領域内に収まる配置）だけをコピーし、
+    /// 他のXObjectの描画はコンテンツからも除く。フォントはコピーしない。
+    /// `rects`は元ページの座標系で指定し、`origin`（元ページの表示領域の左下）を
+    /// 原点に移した出力ページに配置する。
+    pub fn add_preserved_regions(
+        &mut self,
+        page_id: lopdf::ObjectId,
+        source: &Document,
+        page_num: u32,
+        rects: &[BBox],
+        xobjects: &[String],
+        origin: (f64, f64),
+    ) -> crate::error::Result<()> {
+        if rects.is_empty() {
+            return Ok(());
+        }
+        let source_page_id = *source.get_pages().get(&page_num).ok_or_else(|| {
+            PdfMaskError::pdf_read(format!("page {} not found in source document", page_num))
+        })?;
+        let source_content =
+            strip_text_and_xobjects(&page_content_bytes(source, source_page_id), xobjects)?;
+        let source_dict = source
+            .get_dictionary(source_page_id)
+            .map_err(|e| PdfMaskError::pdf_read(e.to_string()))?;
+        let resources = match inherited_page_attribute(source, source_dict, b"Resources") {
+            Some(resources) => self.copy_preserved_resources(source, &resources, xobjects)?,
+            None => lopdf::Dictionary::new(),
+        };
+
+        let (origin_x, origin_y) = origin;
+        let clip = rects
+            .iter()
+            .skip(1)
+            .fold(rects[0].clone(), |acc, rect| BBox {
+                x_min: acc.x_min.min(rect.x_min),
+                y_min: acc.y_min.min(rect.y_min),
+                x_max: acc.x_max.max(rect.x_max),
+                y_max: acc.y_max.max(rect.y_max),
+            });
+        let form_id = self.doc.add_object(Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Form",
+                "BBox" => vec![
+                    Object::Real(clip.x_min as f32),
+                    Object::Real(clip.y_min as f32),
+                    Object::Real(clip.x_max as f32),
+                    Object::Real(clip.y_max as f32),
+                ],
+                "Resources" => resources,
+            },
+            source_content,
+        ));
+
+        let mut path = String::new();
+        for rect in rects {
+            let (w, h) = (rect.x_max - rect.x_min, rect.y_max - rect.y_min);
+            path.push_str(&format!(
+                "{} {} {w} {h} re ",
+                rect.x_min - origin_x,
+                rect.y_min - origin_y
+            ));
+        }
+        let shift = if (origin_x, origin_y) == (0.0, 0.0) {
+            String::new()
+        } else {
+            format!("1 0 0 1 {} {} cm ", -origin_x, -origin_y)
+        };
+        let content = format!("q {path}W n {shift}/Preserved Do Q");
+        let content_id = self
+            .doc
+            .add_object(Stream::new(dictionary! {}, content.into_bytes()));
+
+        let resources_id = self.ensure_resources_as_object(page_id)?;
+        let xobject_id = self.ensure_xobject_dict_as_object(resources_id)?;
+        if let Some(Object::Dictionary(xobjects)) = self.doc.objects.get_mut(&xobject_id) {
+            xobjects.set("Preserved", Object::Reference(form_id));
+        }
+        let Some(Object::Dictionary(page)) = self.doc.objects.get_mut(&page_id) else {
+            return Err(PdfMaskError::pdf_write(format!(
+                "page object {page_id:?} not found"
+            )));
+        };
+        let contents = match page.get(b"Contents").ok().cloned() {
+            Some(Object::Array(mut streams)) => {
+                streams.push(content_id.into());
+                streams
+            }
+            Some(existing @ Object::Reference(_)) => vec![existing, content_id.into()],
+            _ => vec![content_id.into()],
+        };
+        page.set("Contents", contents);
+
+        debug!(
+            page = page_num,
+            count = rects.len(),
+            "add_preserved_regions complete"
+        );
+        Ok(())
+    }
+
+    /// 保持領域のForm XObject用に、元ページのResourcesから必要なものだけをコピーする。
+    ///
+    /// Fontは除き、XObjectは`xobjects`に挙げた名前だけを残す。XObject・Pattern・
+    /// ExtGStateのうち、テキストを描くもの（BT...ETを含むForm、タイリングパターン、
+    /// SMaskのグループ）はコピーしない。コピーするとテキストがベクターのまま戻るため。
+    fn copy_preserved_resources(
+        &mut self,
+        source: &Document,
+        resources: &Object,
+        xobjects: &[String],
+    ) -> crate::error::Result<lopdf::Dictionary> {
+        let mut copied = lopdf::Dictionary::new();
+        let Ok((_, Object::Dictionary(resources))) = source.dereference(resources) else {
+            return Ok(copied);
+        };
+        for (key, value) in resources.iter() {
+            let draws_text: fn(&Document, &Object, &mut Vec<lopdf::ObjectId>) -> bool =
+                match key.as_slice() {
+                    b"Font" => continue,
+                    b"XObject" => xobject_draws_text,
+                    b"Pattern" => pattern_draws_text,
+                    b"ExtGState" => ext_gstate_draws_text,
+                    _ => {
+                        copied.set(key.clone(), self.deep_copy_value(source, value)?);
+                        continue;
+                    }
+                };
+            let Ok((_, Object::Dictionary(dict))) = source.dereference(value) else {
+                continue;
+            };
+            let mut kept = lopdf::Dictionary::new();
+            for (name, entry) in dict.iter() {
+                if key.as_slice() == b"XObject"
+                    && !xobjects
+                        .iter()
+                        .any(|keep| keep.as_bytes() == name.as_slice())
+                {
+                    continue;
+                }
+                if draws_text(source, entry, &mut Vec::new()) {
+                    warn!(
+                        resource = %String::from_utf8_lossy(name),
+                        "resource drawing text is not copied into preserved regions"
+                    );
+                    continue;
+                }
+                kept.set(name.clone(), self.deep_copy_value(source, entry)?);
+            }
+            copied.set(key.clone(), kept);
+        }
+        Ok(copied)
+    }
+
     /// TextMaskedDataからPDFページを構築する。
     ///
     /// ソースPDFからページをdeep copyし、以下を変更する:
@@ -359,6 +966,7 @@ impl MrcPageWriter {
 
         let pages_id = self.ensure_pages_id();
         let new_page_id = self.deep_copy_object(source, *source_page_id)?;
+        self.copy_inherited_page_attributes(source, *source_page_id, new_page_id)?;
 
         // Parentを出力PDFのPagesに差し替え
         if let Some(Object::Dictionary(dict)) = self.doc.objects.get_mut(&new_page_id) {
@@ -373,6 +981,7 @@ impl MrcPageWriter {
             &data.stripped_content_stream,
             &data.text_regions,
             &text_xobjects,
+            self.text_region_padding,
         );
         let content_stream = Stream::new(dictionary! {}, content);
         let content_id = self.doc.add_object(Object::Stream(content_stream));
@@ -398,6 +1007,24 @@ impl MrcPageWriter {
         Ok(new_page_id)
     }
 
+    /// 書き込み済みページのResources/XObjectの画像を`modified_images`の内容に差し替える。
+    ///
+    /// 共有された画像は複製してから差し替えるため、他のページには影響しない。
+    #[cfg(feature = "mrc")]
+    pub fn replace_page_images(
+        &mut self,
+        page_id: lopdf::ObjectId,
+        modified_images: &HashMap<String, ImageModification>,
+    ) -> crate::error::Result<()> {
+        if modified_images.is_empty() {
+            return Ok(());
+        }
+        let resources_obj_id = self.ensure_resources_as_object(page_id)?;
+        let xobj_dict_id = self.ensure_xobject_dict_as_object(resources_obj_id)?;
+        self.replace_modified_images(xobj_dict_id, modified_images);
+        Ok(())
+    }
+
     /// テキスト領域ごとにImageMask XObjectを作成し、名前とIDのペアを返す。
     fn create_text_region_xobjects(
         &mut self,
@@ -419,21 +1046,69 @@ impl MrcPageWriter {
     }
 
     /// テキスト除去済みコンテンツにImageMask描画オペレータを追加して最終コンテンツを構築する。
+    ///
+    /// 各領域は`padding`(pt)だけ四辺に広げて配置する。先に描いた領域と重なる領域は
+    /// 重なり部分をクリップで除いて描く。ImageMaskを二重に描くと重なりが濃くにじむため。
     fn build_text_masked_content(
         stripped: &[u8],
         text_regions: &[TextRegionCrop],
         text_xobjects: &[(String, lopdf::ObjectId)],
+        padding: f64,
     ) -> Vec<u8> {
         let mut content = stripped.to_vec();
+        let mut placed: Vec<BBox> = Vec::with_capacity(text_xobjects.len());
+        let mut clipped = 0usize;
         for (i, (name, _)) in text_xobjects.iter().enumerate() {
             let region = &text_regions[i];
-            let w = region.bbox_points.x_max - region.bbox_points.x_min;
-            let h = region.bbox_points.y_max - region.bbox_points.y_min;
-            let x = region.bbox_points.x_min;
-            let y = region.bbox_points.y_min;
             let escaped = escape_pdf_name(name);
+            // 回転したテキストはマスクから切り離して切り出してあり、他の領域と画素を
+            // 共有しないため、重なりをクリップせずに向きどおりに配置する
+            if let Some(placement) = &region.placement {
+                let m = padded_placement(placement, padding);
+                content.extend_from_slice(
+                    format!(
+                        "\nq 0 g {} {} {} {} {} {} cm /{escaped} Do Q",
+                        m.a, m.b, m.c, m.d, m.e, m.f
+                    )
+                    .as_bytes(),
+                );
+                continue;
+            }
+            let rect = BBox {
+                x_min: region.bbox_points.x_min - padding,
+                y_min: region.bbox_points.y_min - padding,
+                x_max: region.bbox_points.x_max + padding,
+                y_max: region.bbox_points.y_max + padding,
+            };
+            let w = rect.x_max - rect.x_min;
+            let h = rect.y_max - rect.y_min;
+            let (x, y) = (rect.x_min, rect.y_min);
+
+            let mut clip = String::new();
+            if placed.iter().any(|other| rects_overlap(&rect, other)) {
+                clipped += 1;
+                let visible = subtract_rects(&rect, &placed);
+                if visible.is_empty() {
+                    // 先に描いた領域に完全に覆われている
+                    placed.push(rect);
+                    continue;
+                }
+                for piece in &visible {
+                    let (pw, ph) = (piece.x_max - piece.x_min, piece.y_max - piece.y_min);
+                    clip.push_str(&format!(" {} {} {pw} {ph} re", piece.x_min, piece.y_min));
+                }
+                clip.push_str(" W n");
+            }
+
             content.extend_from_slice(
-                format!("\nq 0 g {w} 0 0 {h} {x} {y} cm /{escaped} Do Q").as_bytes(),
+                format!("\nq{clip} 0 g {w} 0 0 {h} {x} {y} cm /{escaped} Do Q").as_bytes(),
+            );
+            placed.push(rect);
+        }
+        if clipped > 0 {
+            warn!(
+                regions = clipped,
+                "overlapping text regions clipped to avoid drawing the overlap twice"
             );
         }
         content
@@ -456,6 +1131,30 @@ impl MrcPageWriter {
                 }
             };
 
+            // 共有された画像は複製してから差し替える（他のページの画像は変えない）
+            let img_obj_id = img_obj_id.map(|img_id| {
+                let private_id = self.detach_shared_object(img_id);
+                if private_id != img_id
+                    && let Some(Object::Dictionary(dict)) = self.doc.objects.get_mut(&xobj_dict_id)
+                {
+                    dict.set(name.as_bytes(), Object::Reference(private_id));
+                }
+                private_id
+            });
+
+            // 再エンコードで失われる/SMaskInDataのアルファは別の/SMaskにする
+            let smask_id = img_obj_id.and(modification.smask.as_ref()).map(|smask| {
+                self.add_image_xobject(
+                    smask,
+                    modification.width,
+                    modification.height,
+                    "DeviceGray",
+                    8,
+                    "FlateDecode",
+                    None,
+                )
+            });
+
             if let Some(img_id) = img_obj_id
                 && let Some(Object::Stream(stream)) = self.doc.objects.get_mut(&img_id)
             {
@@ -469,21 +1168,38 @@ impl MrcPageWriter {
                         Object::Name(modification.filter.as_bytes().to_vec()),
                     );
                 }
-                stream.dict.set(
-                    "ColorSpace",
-                    Object::Name(modification.color_space.as_bytes().to_vec()),
-                );
+                // /SMaskInDataはJPXDecode専用のため、再エンコード後は無効
+                stream.dict.remove(b"SMaskInData");
+                if let Some(smask_id) = smask_id {
+                    stream.dict.set("SMask", Object::Reference(smask_id));
+                }
+                // ステンシルマスクは/ColorSpaceを持てない（不正な入力にあれば取り除く）
+                if matches!(stream.dict.get(b"ImageMask"), Ok(Object::Boolean(true))) {
+                    stream.dict.remove(b"ColorSpace");
+                } else {
+                    stream.dict.set(
+                        "ColorSpace",
+                        Object::Name(modification.color_space.as_bytes().to_vec()),
+                    );
+                }
                 stream.dict.set(
                     "BitsPerComponent",
                     Object::Integer(modification.bits_per_component as i64),
                 );
+                stream
+                    .dict
+                    .set("Width", Object::Integer(modification.width as i64));
+                stream
+                    .dict
+                    .set("Height", Object::Integer(modification.height as i64));
                 stream.dict.remove(b"Length");
             }
         }
     }
 
     /// 親オブジェクト内の辞書エントリをインラインから独立オブジェクトに昇格させる。
-    /// 既に参照の場合はそのIDを返す。
+    /// 既に参照の場合はそのIDを返す。参照先がソースPDFからのコピー（他のページと
+    /// 共有されうる）なら、書き換えに備えて複製し参照を差し替える（copy-on-write）。
     fn ensure_dict_entry_as_object(
         &mut self,
         parent_id: lopdf::ObjectId,
@@ -502,7 +1218,14 @@ impl MrcPageWriter {
                 .doc
                 .get_dictionary(parent_id)
                 .map_err(|e| PdfMaskError::pdf_write(e.to_string()))?;
-            Ok(parent_dict.get(key).unwrap().as_reference().unwrap())
+            let id = parent_dict.get(key).unwrap().as_reference().unwrap();
+            let private_id = self.detach_shared_object(id);
+            if private_id != id
+                && let Some(Object::Dictionary(parent_dict)) = self.doc.objects.get_mut(&parent_id)
+            {
+                parent_dict.set(key.to_vec(), Object::Reference(private_id));
+            }
+            Ok(private_id)
         } else {
             let dict = {
                 let parent_dict = self
@@ -522,6 +1245,25 @@ impl MrcPageWriter {
         }
     }
 
+    /// ソースPDFからコピーしたオブジェクトなら複製を作ってそのIDを返す。
+    ///
+    /// `copy_id_map`経由のコピーは、同じソースオブジェクト（共有された/Contentsや
+    /// Resources、画像等）を参照する全ページで共有される。その場で書き換えると
+    /// Skipページなど他のページまで変わってしまうため、書き換え前に本関数で切り離す。
+    /// 出力側で新規作成したオブジェクトはそのまま返す。
+    fn detach_shared_object(&mut self, id: lopdf::ObjectId) -> lopdf::ObjectId {
+        if !self.copy_id_map.values().any(|&copied| copied == id) {
+            return id;
+        }
+        match self.doc.objects.get(&id).cloned() {
+            Some(obj) => {
+                debug!(?id, "copy-on-write for shared object");
+                self.doc.add_object(obj)
+            }
+            None => id,
+        }
+    }
+
     /// ページのResourcesをインライン辞書から独立オブジェクトに昇格させる。
     fn ensure_resources_as_object(
         &mut self,
@@ -556,7 +1298,12 @@ impl MrcPageWriter {
 
         let pages_id = self.ensure_pages_id();
 
-        let new_page_id = self.deep_copy_object(source, *source_page_id)?;
+        let new_page_id = if self.share_copied_objects {
+            self.deep_copy_object(source, *source_page_id)?
+        } else {
+            self.deep_copy_page_isolated(source, *source_page_id)?
+        };
+        self.copy_inherited_page_attributes(source, *source_page_id, new_page_id)?;
 
         // Parentを出力PDFのPagesに差し替え
         if let Some(Object::Dictionary(dict)) = self.doc.objects.get_mut(&new_page_id) {
@@ -569,31 +1316,236 @@ impl MrcPageWriter {
         Ok(new_page_id)
     }
 
-    /// ソースPDFのオブジェクトを再帰的に深コピーする。
+    /// ページ専用のIDマッピングでページを深コピーする（`share_copied_objects`が`false`のとき）。
     ///
-    /// `self.copy_id_map` を使い、ページ間で共有されるオブジェクトの重複コピーを防ぐ。
-    fn deep_copy_object(
+    /// OCG・OCMDだけは共有のマッピングを引き継ぎ、コピーした分も共有側に戻す。
+    fn deep_copy_page_isolated(
         &mut self,
         source: &Document,
-        source_id: lopdf::ObjectId,
+        source_page_id: lopdf::ObjectId,
     ) -> crate::error::Result<lopdf::ObjectId> {
-        // 既にコピー済みならマッピングを返す
-        if let Some(&mapped_id) = self.copy_id_map.get(&source_id) {
-            return Ok(mapped_id);
+        let shared = std::mem::take(&mut self.copy_id_map);
+        self.copy_id_map = shared
+            .iter()
+            .filter(|(source_id, _)| is_optional_content(source, **source_id))
+            .map(|(&source_id, &new_id)| (source_id, new_id))
+            .collect();
+        let result = self.deep_copy_object(source, source_page_id);
+        let page_map = std::mem::replace(&mut self.copy_id_map, shared);
+        for (source_id, new_id) in page_map {
+            if is_optional_content(source, source_id) {
+                self.copy_id_map.insert(source_id, new_id);
+            }
         }
+        result
+    }
 
-        // 先にIDを予約して循環参照を防ぐ
-        let new_id = self.doc.new_object_id();
-        self.copy_id_map.insert(source_id, new_id);
-
-        // 処理を試行し、エラー時はマップをクリーンアップ
-        let result = (|| -> crate::error::Result<lopdf::ObjectId> {
-            let source_obj = source
-                .get_object(source_id)
-                .map_err(|e| crate::error::PdfMaskError::pdf_read(e.to_string()))?;
-
-            let new_obj = self.deep_copy_value(source, source_obj)?;
-            self.doc.objects.insert(new_id, new_obj);
+    /// 前回の出力PDFからマスク済みページをコピーする（incrementalモード用）。
+    ///
+    /// ソースPDFとはオブジェクトIDの空間が異なるため、専用のIDマッピングで深コピーする。
+    pub fn copy_prior_page(
+        &mut self,
+        prior: &Document,
+        page_num: u32,
+    ) -> crate::error::Result<lopdf::ObjectId> {
+        std::mem::swap(&mut self.copy_id_map, &mut self.prior_copy_id_map);
+        let result = self.copy_page_from(prior, page_num);
+        std::mem::swap(&mut self.copy_id_map, &mut self.prior_copy_id_map);
+        result
+    }
+
+    /// 元ページとマスク後のページを左右に並べた比較ページを追加する（`--compare`用）。
+    ///
+    /// 両ページをそれぞれ表示向き（/Rotate適用後）のForm XObjectにし、左に元ページ、
+    /// 右にマスク後のページを上端をそろえて置く。ページ幅は両者の表示幅の和になる。
+    /// マスク後のPDFは元PDFとID空間が異なるため、前回出力用のIDマッピングで深コピーする。
+    pub fn write_comparison_page(
+        &mut self,
+        before: &PdfReader,
+        before_page: u32,
+        after: &PdfReader,
+        after_page: u32,
+    ) -> crate::error::Result<lopdf::ObjectId> {
+        let (before_form, before_width, before_height) = self.page_as_form(before, before_page)?;
+        std::mem::swap(&mut self.copy_id_map, &mut self.prior_copy_id_map);
+        let after_form = self.page_as_form(after, after_page);
+        std::mem::swap(&mut self.copy_id_map, &mut self.prior_copy_id_map);
+        let (after_form, after_width, after_height) = after_form?;
+
+        let width = before_width + after_width;
+        let height = before_height.max(after_height);
+        let content = format!(
+            "q 1 0 0 1 0 {} cm /Before Do Q q 1 0 0 1 {before_width} {} cm /After Do Q",
+            height - before_height,
+            height - after_height
+        );
+        let content_id = self
+            .doc
+            .add_object(Stream::new(dictionary! {}, content.into_bytes()));
+
+        let pages_id = self.ensure_pages_id();
+        let page_id = self.doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![
+                0.into(),
+                0.into(),
+                Object::Real(width as f32),
+                Object::Real(height as f32),
+            ],
+            "Contents" => content_id,
+            "Resources" => dictionary! {
+                "XObject" => dictionary! {
+                    "Before" => before_form,
+                    "After" => after_form,
+                },
+            },
+        });
+        self.append_page_to_kids(pages_id, page_id);
+
+        debug!(before_page, after_page, "write_comparison_page complete");
+        Ok(page_id)
+    }
+
+    /// ページの内容とResourcesを、表示向きで左下を原点とするForm XObjectにコピーする。
+    ///
+    /// 戻り値は(Form XObjectのID, 表示上の幅, 表示上の高さ)。
+    fn page_as_form(
+        &mut self,
+        reader: &PdfReader,
+        page_num: u32,
+    ) -> crate::error::Result<(lopdf::ObjectId, f64, f64)> {
+        let source = reader.document();
+        let source_page_id = *source.get_pages().get(&page_num).ok_or_else(|| {
+            PdfMaskError::pdf_read(format!("page {} not found in source document", page_num))
+        })?;
+        let orientation = reader.page_orientation(page_num)?;
+        let source_dict = source
+            .get_dictionary(source_page_id)
+            .map_err(|e| PdfMaskError::pdf_read(e.to_string()))?;
+        let resources = match inherited_page_attribute(source, source_dict, b"Resources") {
+            Some(resources) => self.deep_copy_value(source, &resources)?,
+            None => Object::Dictionary(lopdf::Dictionary::new()),
+        };
+
+        // 表示上の座標は左下隅をoriginに置くので、さらに原点へ平行移動する
+        let (origin_x, origin_y) = orientation.origin;
+        let matrix = orientation.to_visual_matrix().multiply(&Matrix {
+            a: 1.0,
+            b: 0.0,
+            c: 0.0,
+            d: 1.0,
+            e: -origin_x,
+            f: -origin_y,
+        });
+        let form_id = self.doc.add_object(Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Form",
+                "BBox" => vec![
+                    Object::Real(origin_x as f32),
+                    Object::Real(origin_y as f32),
+                    Object::Real((origin_x + orientation.width) as f32),
+                    Object::Real((origin_y + orientation.height) as f32),
+                ],
+                "Matrix" => [matrix.a, matrix.b, matrix.c, matrix.d, matrix.e, matrix.f]
+                    .into_iter()
+                    .map(|v| Object::Real(v as f32))
+                    .collect::<Vec<_>>(),
+                "Resources" => resources,
+            },
+            page_content_bytes(source, source_page_id),
+        ));
+
+        let (width, height) = if orientation.rotate % 180 == 0 {
+            (orientation.width, orientation.height)
+        } else {
+            (orientation.height, orientation.width)
+        };
+        Ok((form_id, width, height))
+    }
+
+    /// マスク済みページに元ページのキーを記録する（/PieceInfoの/PdfMasking辞書）。
+    ///
+    /// 次回のincrementalモードで、元ページが変わっていないページを見分けるのに使う。
+    pub fn set_page_source_key(
+        &mut self,
+        page_id: lopdf::ObjectId,
+        key: &str,
+    ) -> crate::error::Result<()> {
+        let Some(Object::Dictionary(page)) = self.doc.objects.get_mut(&page_id) else {
+            return Err(PdfMaskError::pdf_write(format!(
+                "page object {page_id:?} not found"
+            )));
+        };
+        page.set(
+            "PieceInfo",
+            dictionary! {
+                "PdfMasking" => dictionary! {
+                    "Private" => dictionary! {
+                        "SourceKey" => Object::string_literal(key),
+                    },
+                },
+            },
+        );
+        Ok(())
+    }
+
+    /// 元のページツリーから継承していた属性をコピー先のページ辞書に直接設定する。
+    ///
+    /// コピーしたページは出力PDFのPagesノードにぶら下がるため、中間のPagesノードで
+    /// 指定された/Rotate・/MediaBox・/CropBox・/Resourcesはそのままでは失われる。
+    fn copy_inherited_page_attributes(
+        &mut self,
+        source: &Document,
+        source_page_id: lopdf::ObjectId,
+        new_page_id: lopdf::ObjectId,
+    ) -> crate::error::Result<()> {
+        const INHERITABLE: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+        let source_dict = source
+            .get_dictionary(source_page_id)
+            .map_err(|e| PdfMaskError::pdf_read(e.to_string()))?;
+        for key in INHERITABLE {
+            if source_dict.has(key) {
+                continue;
+            }
+            let Some(value) = inherited_page_attribute(source, source_dict, key) else {
+                continue;
+            };
+            let value = self.deep_copy_value(source, &value)?;
+            if let Some(Object::Dictionary(dict)) = self.doc.objects.get_mut(&new_page_id) {
+                dict.set(key, value);
+            }
+        }
+        Ok(())
+    }
+
+    /// ソースPDFのオブジェクトを再帰的に深コピーする。
+    ///
+    /// `self.copy_id_map` を使い、ページ間で共有されるオブジェクトの重複コピーを防ぐ。
+    fn deep_copy_object(
+        &mut self,
+        source: &Document,
+        source_id: lopdf::ObjectId,
+    ) -> crate::error::Result<lopdf::ObjectId> {
+        // 既にコピー済みならマッピングを返す
+        if let Some(&mapped_id) = self.copy_id_map.get(&source_id) {
+            return Ok(mapped_id);
+        }
+
+        // 先にIDを予約して循環参照を防ぐ
+        let new_id = self.doc.new_object_id();
+        self.copy_id_map.insert(source_id, new_id);
+
+        // 処理を試行し、エラー時はマップをクリーンアップ
+        let result = (|| -> crate::error::Result<lopdf::ObjectId> {
+            let source_obj = source
+                .get_object(source_id)
+                .map_err(|e| crate::error::PdfMaskError::pdf_read(e.to_string()))?;
+
+            let new_obj = self.deep_copy_value(source, source_obj)?;
+            self.doc.objects.insert(new_id, new_obj);
 
             Ok(new_id)
         })();
@@ -650,6 +1602,283 @@ impl MrcPageWriter {
         }
     }
 
+    /// ソースPDFのCatalogにある`/OCProperties`（オプショナルコンテンツ構成）を出力PDFへコピーする。
+    ///
+    /// OCGはページのResources経由で既にコピーされている場合があるため、
+    /// `copy_id_map`を共有して同一オブジェクトを指すようにする。
+    /// ソースに`/OCProperties`が無い場合は`Ok(false)`を返す。
+    /// 出力PDFのCatalogはページ追加時に作成されるため、ページ書き込み後に呼び出すこと。
+    pub fn copy_optional_content_from(&mut self, source: &Document) -> crate::error::Result<bool> {
+        let source_oc = match source
+            .catalog()
+            .ok()
+            .and_then(|catalog| catalog.get(b"OCProperties").ok())
+        {
+            Some(obj) => obj,
+            None => return Ok(false),
+        };
+
+        let new_oc = self.deep_copy_value(source, source_oc)?;
+
+        let catalog_id = self
+            .doc
+            .trailer
+            .get(b"Root")
+            .and_then(Object::as_reference)
+            .map_err(|_| PdfMaskError::pdf_write("missing Catalog (Root) in trailer"))?;
+        let catalog = self
+            .doc
+            .get_dictionary_mut(catalog_id)
+            .map_err(|_| PdfMaskError::pdf_write("Catalog object not found"))?;
+        catalog.set("OCProperties", new_oc);
+
+        debug!("copied /OCProperties from source document");
+        Ok(true)
+    }
+
+    /// ソースPDFの文書情報（/Info）とXMPメタデータ（Catalogの/Metadata）を`mode`に従って出力PDFへコピーする。
+    ///
+    /// - `Strip`: 何もコピーしない
+    /// - `Minimal`: /InfoのTitleとAuthorのみコピーする
+    /// - `Preserve`: /InfoとXMPをそのままコピーする。ただし`reproducible`なら
+    ///   出力のバイト安定性を優先し、CreationDate/ModDateとXMPはコピーしない
+    ///
+    /// 出力PDFのCatalogはページ追加時に作成されるため、ページ書き込み後に呼び出すこと。
+    pub fn copy_metadata_from(
+        &mut self,
+        source: &Document,
+        mode: MetadataMode,
+        reproducible: bool,
+    ) -> crate::error::Result<()> {
+        if mode == MetadataMode::Strip {
+            return Ok(());
+        }
+
+        let source_info = source.trailer.get(b"Info").ok().and_then(|obj| match obj {
+            Object::Reference(id) => source.get_dictionary(*id).ok(),
+            Object::Dictionary(dict) => Some(dict),
+            _ => None,
+        });
+        if let Some(info) = source_info {
+            let mut new_info = lopdf::Dictionary::new();
+            for (key, value) in info.iter() {
+                let keep = match mode {
+                    MetadataMode::Minimal => matches!(key.as_slice(), b"Title" | b"Author"),
+                    _ => !(reproducible && matches!(key.as_slice(), b"CreationDate" | b"ModDate")),
+                };
+                if keep {
+                    let new_value = self.deep_copy_value(source, value)?;
+                    new_info.set(key.clone(), new_value);
+                }
+            }
+            if !new_info.is_empty() {
+                let info_id = self.doc.add_object(Object::Dictionary(new_info));
+                self.doc.trailer.set("Info", Object::Reference(info_id));
+            }
+        }
+
+        // XMPには日付が埋め込まれているため、reproducibleでは正規化せず丸ごと除外する
+        if mode == MetadataMode::Preserve && !reproducible {
+            let source_xmp = source
+                .catalog()
+                .ok()
+                .and_then(|catalog| catalog.get(b"Metadata").ok());
+            if let Some(xmp) = source_xmp {
+                let new_xmp = self.deep_copy_value(source, xmp)?;
+                let catalog_id = self
+                    .doc
+                    .trailer
+                    .get(b"Root")
+                    .and_then(Object::as_reference)
+                    .map_err(|_| PdfMaskError::pdf_write("missing Catalog (Root) in trailer"))?;
+                let catalog = self
+                    .doc
+                    .get_dictionary_mut(catalog_id)
+                    .map_err(|_| PdfMaskError::pdf_write("Catalog object not found"))?;
+                catalog.set("Metadata", new_xmp);
+            }
+        }
+
+        debug!(?mode, reproducible, "copied document metadata");
+        Ok(())
+    }
+
+    /// 出力PDFの/Infoの/Producerを`producer`にする。空文字列なら/Producerを取り除き、
+    /// /Infoが空になれば/Info自体を出力しない。
+    ///
+    /// [`copy_metadata_from`](Self::copy_metadata_from)で引き継いだ値を上書きするため、その後に呼び出すこと。
+    pub fn set_producer(&mut self, producer: &str) -> crate::error::Result<()> {
+        let info_id = self
+            .doc
+            .trailer
+            .get(b"Info")
+            .and_then(Object::as_reference)
+            .ok();
+
+        if producer.is_empty() {
+            let Some(info_id) = info_id else {
+                return Ok(());
+            };
+            let info = self
+                .doc
+                .get_dictionary_mut(info_id)
+                .map_err(|_| PdfMaskError::pdf_write("Info object not found"))?;
+            info.remove(b"Producer");
+            if info.is_empty() {
+                self.doc.objects.remove(&info_id);
+                self.doc.trailer.remove(b"Info");
+            }
+            return Ok(());
+        }
+
+        let value = Object::string_literal(producer);
+        match info_id {
+            Some(info_id) => {
+                self.doc
+                    .get_dictionary_mut(info_id)
+                    .map_err(|_| PdfMaskError::pdf_write("Info object not found"))?
+                    .set("Producer", value);
+            }
+            None => {
+                let info_id = self.doc.add_object(dictionary! { "Producer" => value });
+                self.doc.trailer.set("Info", Object::Reference(info_id));
+            }
+        }
+        Ok(())
+    }
+
+    /// PDF/A用のOutputIntent（/S /GTS_PDFA1）を`profile`のICCプロファイル付きでCatalogに設定する。
+    ///
+    /// 出力PDFのCatalogはページ追加時に作成されるため、ページ書き込み後に呼び出すこと。
+    pub fn add_output_intent(&mut self, profile: OutputIntentProfile) -> crate::error::Result<()> {
+        let alternate = match profile {
+            OutputIntentProfile::GrayGamma22 => "DeviceGray",
+            OutputIntentProfile::Srgb => "DeviceRGB",
+        };
+        let icc = Stream::new(
+            dictionary! {
+                "N" => i64::from(profile.components()),
+                "Alternate" => alternate,
+            },
+            profile.icc_profile(),
+        );
+        let icc_id = self.doc.add_object(icc);
+        let intent = dictionary! {
+            "Type" => "OutputIntent",
+            "S" => "GTS_PDFA1",
+            "OutputConditionIdentifier" => Object::string_literal(profile.description()),
+            "Info" => Object::string_literal(profile.description()),
+            "DestOutputProfile" => icc_id,
+        };
+
+        let catalog_id = self
+            .doc
+            .trailer
+            .get(b"Root")
+            .and_then(Object::as_reference)
+            .map_err(|_| PdfMaskError::pdf_write("missing Catalog (Root) in trailer"))?;
+        let catalog = self
+            .doc
+            .get_dictionary_mut(catalog_id)
+            .map_err(|_| PdfMaskError::pdf_write("Catalog object not found"))?;
+        catalog.set("OutputIntents", vec![Object::Dictionary(intent)]);
+
+        debug!(profile = profile.description(), "added PDF/A output intent");
+        Ok(())
+    }
+
+    /// 出力PDFのCatalogに言語タグ（/Lang）を設定する。
+    ///
+    /// `lang_override`が指定されていればその値を、なければソースCatalogの/Langをコピーする。
+    /// どちらもなければ何もしない。
+    ///
+    /// 出力PDFのCatalogはページ追加時に作成されるため、ページ書き込み後に呼び出すこと。
+    pub fn copy_lang_from(
+        &mut self,
+        source: &Document,
+        lang_override: Option<&str>,
+    ) -> crate::error::Result<()> {
+        let lang = match lang_override {
+            Some(lang) => Object::string_literal(lang),
+            None => {
+                let source_lang = source
+                    .catalog()
+                    .ok()
+                    .and_then(|catalog| catalog.get(b"Lang").ok());
+                match source_lang {
+                    Some(lang) => self.deep_copy_value(source, lang)?,
+                    None => return Ok(()),
+                }
+            }
+        };
+
+        let catalog_id = self
+            .doc
+            .trailer
+            .get(b"Root")
+            .and_then(Object::as_reference)
+            .map_err(|_| PdfMaskError::pdf_write("missing Catalog (Root) in trailer"))?;
+        let catalog = self
+            .doc
+            .get_dictionary_mut(catalog_id)
+            .map_err(|_| PdfMaskError::pdf_write("Catalog object not found"))?;
+        catalog.set("Lang", lang);
+
+        debug!(overridden = lang_override.is_some(), "set document /Lang");
+        Ok(())
+    }
+
+    /// ソースCatalogの初期表示の設定を出力PDFのCatalogへコピーする。
+    ///
+    /// /ViewerPreferences・/PageLayout・/PageModeはそのままコピーする。/OpenActionは
+    /// 明示的な宛先（配列）か/S /GoToのアクションで、宛先ページが`page_map`（ソースの
+    /// ページID → 出力のページID）にある場合だけ、宛先ページを出力のページに付け替えて
+    /// コピーする。JavaScriptなど他のアクションと、/Nextで連なるアクションはコピーしない。
+    ///
+    /// 出力PDFのCatalogはページ追加時に作成されるため、ページ書き込み後に呼び出すこと。
+    pub fn copy_view_from(
+        &mut self,
+        source: &Document,
+        page_map: &HashMap<lopdf::ObjectId, lopdf::ObjectId>,
+    ) -> crate::error::Result<()> {
+        let Ok(source_catalog) = source.catalog() else {
+            return Ok(());
+        };
+        let mut entries: Vec<(&[u8], Object)> = Vec::new();
+        for key in [&b"ViewerPreferences"[..], b"PageLayout", b"PageMode"] {
+            if let Ok(value) = source_catalog.get(key) {
+                entries.push((key, self.deep_copy_value(source, value)?));
+            }
+        }
+        if let Ok(action) = source_catalog.get(b"OpenAction") {
+            match goto_destination(source, action, page_map) {
+                Some(dest) => entries.push((b"OpenAction", dest)),
+                None => debug!("dropped /OpenAction that is not a GoTo to an output page"),
+            }
+        }
+        if entries.is_empty() {
+            return Ok(());
+        }
+
+        let catalog_id = self
+            .doc
+            .trailer
+            .get(b"Root")
+            .and_then(Object::as_reference)
+            .map_err(|_| PdfMaskError::pdf_write("missing Catalog (Root) in trailer"))?;
+        let catalog = self
+            .doc
+            .get_dictionary_mut(catalog_id)
+            .map_err(|_| PdfMaskError::pdf_write("Catalog object not found"))?;
+        let copied = entries.len();
+        for (key, value) in entries {
+            catalog.set(key, value);
+        }
+
+        debug!(copied, "copied initial view settings");
+        Ok(())
+    }
+
     /// PDFドキュメントをバイト列として出力する。
     pub fn save_to_bytes(&mut self) -> crate::error::Result<Vec<u8>> {
         let root_ref = self.doc.trailer.get(b"Root").map_err(|_| {
@@ -681,6 +1910,148 @@ impl MrcPageWriter {
     }
 }
 
+/// /OpenActionが明示的な宛先（配列）か/S /GoToのアクションなら、宛先ページを
+/// `page_map`で出力のページに付け替えた宛先配列を返す。
+///
+/// 宛先の残り（/Fit、/XYZの座標など）は数値と名前だけのためそのまま使う。
+/// 名前付きの宛先は/Namesや/Destsをコピーしないため解決できず、`None`を返す。
+fn goto_destination(
+    source: &Document,
+    action: &Object,
+    page_map: &HashMap<lopdf::ObjectId, lopdf::ObjectId>,
+) -> Option<Object> {
+    let action = source.dereference(action).ok()?.1;
+    let dest = match action {
+        Object::Array(_) => action,
+        Object::Dictionary(dict) => {
+            if dict.get(b"S").and_then(Object::as_name).ok()? != b"GoTo" {
+                return None;
+            }
+            source.dereference(dict.get(b"D").ok()?).ok()?.1
+        }
+        _ => return None,
+    };
+    let (page, rest) = dest.as_array().ok()?.split_first()?;
+    let new_page_id = page_map.get(&page.as_reference().ok()?)?;
+    let rest = rest
+        .iter()
+        .map(|value| match value {
+            Object::Integer(_) | Object::Real(_) | Object::Name(_) | Object::Null => {
+                Some(value.clone())
+            }
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let mut new_dest = vec![Object::Reference(*new_page_id)];
+    new_dest.extend(rest);
+    Some(Object::Array(new_dest))
+}
+
+/// ソースPDFのコンテンツストリーム（Form XObject・タイリングパターン・SMaskのグループ）が
+/// テキストを描くか。自身のResourcesから描くXObject・Pattern・ExtGStateもたどる。
+///
+/// 解析できないコンテンツはテキストを描くものとみなす。`visited`で循環参照を断つ。
+fn content_draws_text(
+    source: &Document,
+    stream: &Object,
+    visited: &mut Vec<lopdf::ObjectId>,
+) -> bool {
+    if let Object::Reference(id) = stream {
+        if visited.contains(id) {
+            return false;
+        }
+        visited.push(*id);
+    }
+    let Ok((_, Object::Stream(stream))) = source.dereference(stream) else {
+        return false;
+    };
+    let content = stream
+        .decompressed_content()
+        .unwrap_or_else(|_| stream.content.clone());
+    if contains_text(&content).unwrap_or(true) {
+        return true;
+    }
+    let Some(resources) = stream
+        .dict
+        .get(b"Resources")
+        .ok()
+        .and_then(|r| source.dereference(r).ok())
+        .and_then(|(_, r)| r.as_dict().ok())
+    else {
+        return false;
+    };
+    let entries = |key: &[u8]| -> Vec<&Object> {
+        resources
+            .get(key)
+            .ok()
+            .and_then(|d| source.dereference(d).ok())
+            .and_then(|(_, d)| d.as_dict().ok())
+            .map(|d| d.iter().map(|(_, v)| v).collect())
+            .unwrap_or_default()
+    };
+    entries(b"XObject")
+        .into_iter()
+        .any(|x| xobject_draws_text(source, x, visited))
+        || entries(b"Pattern")
+            .into_iter()
+            .any(|p| pattern_draws_text(source, p, visited))
+        || entries(b"ExtGState")
+            .into_iter()
+            .any(|gs| ext_gstate_draws_text(source, gs, visited))
+}
+
+/// XObjectがテキストを描くForm XObjectか。画像XObjectは描かないものとする。
+fn xobject_draws_text(
+    source: &Document,
+    xobject: &Object,
+    visited: &mut Vec<lopdf::ObjectId>,
+) -> bool {
+    let is_form = source
+        .dereference(xobject)
+        .ok()
+        .and_then(|(_, x)| x.as_stream().ok())
+        .and_then(|x| x.dict.get(b"Subtype").ok())
+        .and_then(|s| s.as_name().ok())
+        .is_some_and(|s| s == b"Form");
+    is_form && content_draws_text(source, xobject, visited)
+}
+
+/// パターンがテキストを描くタイリングパターンか。シェーディングパターンは描かない。
+fn pattern_draws_text(
+    source: &Document,
+    pattern: &Object,
+    visited: &mut Vec<lopdf::ObjectId>,
+) -> bool {
+    let is_tiling = matches!(source.dereference(pattern), Ok((_, Object::Stream(_))));
+    is_tiling && content_draws_text(source, pattern, visited)
+}
+
+/// グラフィックス状態の/SMaskが、テキストを描くグループ（/G）を持つか。
+fn ext_gstate_draws_text(
+    source: &Document,
+    gs: &Object,
+    visited: &mut Vec<lopdf::ObjectId>,
+) -> bool {
+    let group = source
+        .dereference(gs)
+        .ok()
+        .and_then(|(_, gs)| gs.as_dict().ok())
+        .and_then(|gs| gs.get(b"SMask").ok())
+        .and_then(|smask| source.dereference(smask).ok())
+        .and_then(|(_, smask)| smask.as_dict().ok())
+        .and_then(|smask| smask.get(b"G").ok());
+    group.is_some_and(|g| content_draws_text(source, g, visited))
+}
+
+/// ソースPDFのオブジェクトがオプショナルコンテンツグループ（/Type /OCGまたは/OCMD）か。
+fn is_optional_content(source: &Document, id: lopdf::ObjectId) -> bool {
+    source
+        .get_dictionary(id)
+        .and_then(|dict| dict.get(b"Type"))
+        .and_then(Object::as_name)
+        .is_ok_and(|name| name == b"OCG" || name == b"OCMD")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -709,6 +2080,160 @@ mod tests {
         assert_eq!(escape_pdf_name("A\x7FB"), "A#7FB");
     }
 
+    #[test]
+    fn test_text_masked_content_pads_region_placement() {
+        let regions = vec![TextRegionCrop {
+            jbig2_data: Vec::new(),
+            bbox_points: BBox {
+                x_min: 100.0,
+                y_min: 200.0,
+                x_max: 150.0,
+                y_max: 210.0,
+            },
+            pixel_width: 208,
+            pixel_height: 42,
+            placement: None,
+        }];
+        let xobjects = vec![("TxtRgn0".to_string(), (1, 0))];
+
+        let content = MrcPageWriter::build_text_masked_content(b"", &regions, &xobjects, 0.0);
+        assert_eq!(
+            String::from_utf8(content).unwrap(),
+            "\nq 0 g 50 0 0 10 100 200 cm /TxtRgn0 Do Q"
+        );
+
+        // 四辺に0.5ptずつ広げる: 幅・高さは+1pt、原点は-0.5pt
+        let content = MrcPageWriter::build_text_masked_content(b"", &regions, &xobjects, 0.5);
+        assert_eq!(
+            String::from_utf8(content).unwrap(),
+            "\nq 0 g 51 0 0 11 99.5 199.5 cm /TxtRgn0 Do Q"
+        );
+    }
+
+    #[test]
+    fn test_text_masked_content_places_rotated_region_with_rotation() {
+        // 45度回転した100pt×10ptのテキスト矩形
+        let (cos, sin) = (
+            std::f64::consts::FRAC_1_SQRT_2,
+            std::f64::consts::FRAC_1_SQRT_2,
+        );
+        let placement = Matrix {
+            a: 100.0 * cos,
+            b: 100.0 * sin,
+            c: -10.0 * sin,
+            d: 10.0 * cos,
+            e: 200.0,
+            f: 300.0,
+        };
+        let regions = vec![TextRegionCrop {
+            jbig2_data: Vec::new(),
+            bbox_points: crate::pdf::content_stream::ctm_to_bbox(&placement),
+            pixel_width: 417,
+            pixel_height: 42,
+            placement: Some(placement.clone()),
+        }];
+        let xobjects = vec![("TxtRgn0".to_string(), (1, 0))];
+
+        let content = MrcPageWriter::build_text_masked_content(b"", &regions, &xobjects, 0.0);
+        let content = String::from_utf8(content).unwrap();
+        let cm: Vec<f64> = content
+            .split_whitespace()
+            .skip_while(|token| *token != "g")
+            .skip(1)
+            .take(6)
+            .map(|token| token.parse().expect("cm operand"))
+            .collect();
+        assert!(content.ends_with("cm /TxtRgn0 Do Q"), "{content}");
+        assert!(cm[1] > 0.0 && cm[2] < 0.0, "cm should rotate: {content}");
+        assert_eq!(
+            cm,
+            [
+                placement.a,
+                placement.b,
+                placement.c,
+                placement.d,
+                placement.e,
+                placement.f
+            ]
+        );
+
+        // 余白は文字列の向きに沿って四辺に広げる
+        let content = MrcPageWriter::build_text_masked_content(b"", &regions, &xobjects, 0.5);
+        let cm: Vec<f64> = String::from_utf8(content)
+            .unwrap()
+            .split_whitespace()
+            .skip_while(|token| *token != "g")
+            .skip(1)
+            .take(6)
+            .map(|token| token.parse().expect("cm operand"))
+            .collect();
+        assert!((cm[0].hypot(cm[1]) - 101.0).abs() < 1e-9);
+        assert!((cm[2].hypot(cm[3]) - 11.0).abs() < 1e-9);
+        // 原点は字送り方向と行方向にそれぞれ0.5pt戻る
+        let expected_e = 200.0 - 0.5 * cos + 0.5 * sin;
+        let expected_f = 300.0 - 0.5 * sin - 0.5 * cos;
+        assert!((cm[4] - expected_e).abs() < 1e-9);
+        assert!((cm[5] - expected_f).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_text_masked_content_clips_overlapping_regions() {
+        let region = |y_min: f64, y_max: f64| TextRegionCrop {
+            jbig2_data: Vec::new(),
+            bbox_points: BBox {
+                x_min: 100.0,
+                y_min,
+                x_max: 150.0,
+                y_max,
+            },
+            pixel_width: 208,
+            pixel_height: 42,
+            placement: None,
+        };
+        // 2行目が1行目に5pt重なり、3行目は1行目に完全に含まれる
+        let regions = vec![
+            region(200.0, 210.0),
+            region(205.0, 215.0),
+            region(202.0, 208.0),
+        ];
+        let xobjects = vec![
+            ("TxtRgn0".to_string(), (1, 0)),
+            ("TxtRgn1".to_string(), (2, 0)),
+            ("TxtRgn2".to_string(), (3, 0)),
+        ];
+
+        let content = MrcPageWriter::build_text_masked_content(b"", &regions, &xobjects, 0.0);
+        assert_eq!(
+            String::from_utf8(content).unwrap(),
+            "\nq 0 g 50 0 0 10 100 200 cm /TxtRgn0 Do Q\
+             \nq 100 210 50 5 re W n 0 g 50 0 0 10 100 205 cm /TxtRgn1 Do Q"
+        );
+    }
+
+    #[test]
+    fn test_subtract_rects_splits_around_overlap() {
+        let rect = BBox {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 10.0,
+            y_max: 10.0,
+        };
+        let hole = BBox {
+            x_min: 4.0,
+            y_min: 4.0,
+            x_max: 6.0,
+            y_max: 6.0,
+        };
+        let pieces = subtract_rects(&rect, &[hole]);
+        let area: f64 = pieces
+            .iter()
+            .map(|p| (p.x_max - p.x_min) * (p.y_max - p.y_min))
+            .sum();
+        assert_eq!(pieces.len(), 4);
+        assert_eq!(area, 96.0);
+        assert!(subtract_rects(&rect, std::slice::from_ref(&rect)).is_empty());
+    }
+
     #[cfg(feature = "mrc")]
     #[test]
     fn test_create_background_xobject() {
@@ -767,6 +2292,7 @@ mod tests {
             page_width_pts: 595.276,
             page_height_pts: 841.89,
             color_mode: ColorMode::Rgb,
+            output_colorspace: OutputColorSpace::Rgb,
         };
         let mut writer = MrcPageWriter::new();
         writer.write_mrc_page(&layers).expect("write MRC page");
@@ -787,6 +2313,7 @@ mod tests {
             page_width_pts: 595.276,
             page_height_pts: 841.89,
             color_mode: ColorMode::Rgb,
+            output_colorspace: OutputColorSpace::Rgb,
         };
         let layers2 = crate::mrc::MrcLayers {
             background_jpeg: vec![0xFF, 0xD8, 0xFF, 0xE0, 0x01],
@@ -797,6 +2324,7 @@ mod tests {
             page_width_pts: 595.276,
             page_height_pts: 841.89,
             color_mode: ColorMode::Rgb,
+            output_colorspace: OutputColorSpace::Rgb,
         };
         let layers3 = crate::mrc::MrcLayers {
             background_jpeg: vec![0xFF, 0xD8, 0xFF, 0xE0, 0x02],
@@ -807,6 +2335,7 @@ mod tests {
             page_width_pts: 595.276,
             page_height_pts: 841.89,
             color_mode: ColorMode::Rgb,
+            output_colorspace: OutputColorSpace::Rgb,
         };
 
         let mut writer = MrcPageWriter::new();
@@ -823,6 +2352,54 @@ mod tests {
         assert_eq!(doc.get_pages().len(), 3, "should have 3 pages");
     }
 
+    #[cfg(feature = "mrc")]
+    #[test]
+    fn test_identical_backgrounds_share_one_xobject() {
+        let layers_with_fg = |fg: u8| crate::mrc::MrcLayers {
+            background_jpeg: vec![0xFF, 0xD8, 0xFF, 0xE0, 0x42],
+            foreground_jpeg: vec![0xFF, 0xD8, 0xFF, 0xE1, fg],
+            mask_jbig2: vec![0x97, 0x4A, 0x42, 0x32, fg],
+            width: 640,
+            height: 480,
+            page_width_pts: 595.276,
+            page_height_pts: 841.89,
+            color_mode: ColorMode::Rgb,
+            output_colorspace: OutputColorSpace::Rgb,
+        };
+
+        let mut writer = MrcPageWriter::new();
+        for fg in 0..3 {
+            writer
+                .write_mrc_page(&layers_with_fg(fg))
+                .expect("write page");
+        }
+
+        let pdf_bytes = writer.save_to_bytes().expect("save to bytes");
+        let doc = Document::load_mem(&pdf_bytes).expect("load PDF from memory");
+        let xobject_ref = |page_id: lopdf::ObjectId, name: &[u8]| {
+            let resources_ref = doc
+                .get_dictionary(page_id)
+                .and_then(|d| d.get(b"Resources"))
+                .and_then(Object::as_reference)
+                .expect("Resources ref");
+            doc.get_dictionary(resources_ref)
+                .and_then(|r| r.get(b"XObject"))
+                .and_then(Object::as_dict)
+                .and_then(|x| x.get(name))
+                .and_then(Object::as_reference)
+                .expect("XObject ref")
+        };
+
+        let pages: Vec<_> = doc.get_pages().into_values().collect();
+        assert_eq!(pages.len(), 3);
+        let bg_ids: std::collections::HashSet<_> =
+            pages.iter().map(|&p| xobject_ref(p, b"BgImg")).collect();
+        let fg_ids: std::collections::HashSet<_> =
+            pages.iter().map(|&p| xobject_ref(p, b"FgImg")).collect();
+        assert_eq!(bg_ids.len(), 1, "background should be shared");
+        assert_eq!(fg_ids.len(), 3, "foregrounds stay per page");
+    }
+
     #[cfg(feature = "mrc")]
     #[test]
     fn test_write_bw_page() {
@@ -852,6 +2429,7 @@ mod tests {
             page_width_pts: 595.276,
             page_height_pts: 841.89,
             color_mode: ColorMode::Grayscale,
+            output_colorspace: OutputColorSpace::Rgb,
         };
         let mut writer = MrcPageWriter::new();
         let page_id = writer
@@ -954,10 +2532,391 @@ mod tests {
         assert_eq!(arr.len(), 4);
     }
 
-    #[cfg(feature = "mrc")]
     #[test]
-    fn test_copy_shared_resources_deduplication() {
-        // 2ページが同一フォントオブジェクトを共有するソースPDFを作成
+    fn test_add_redaction_marks_appends_fill_after_page_content() {
+        let mut writer = MrcPageWriter::new();
+        let page_id = writer
+            .write_blank_page(200.0, 100.0, true)
+            .expect("write blank page");
+        let rect = BBox {
+            x_min: 10.0,
+            y_min: 20.0,
+            x_max: 40.0,
+            y_max: 60.0,
+        };
+        writer
+            .add_redaction_marks(page_id, &[rect], [0, 0, 0])
+            .expect("add marks");
+
+        let page = writer.doc.get_dictionary(page_id).expect("page dict");
+        let contents = page
+            .get(b"Contents")
+            .and_then(Object::as_array)
+            .expect("Contents array");
+        assert_eq!(contents.len(), 2, "mark must follow the page content");
+        let content = writer.doc.get_page_content(page_id).expect("page content");
+        let content = String::from_utf8(content).expect("utf-8 content");
+        assert!(
+            content.ends_with("q 0 0 0 rg 10 20 30 40 re f Q"),
+            "got {content:?}"
+        );
+    }
+
+    #[test]
+    fn test_add_redaction_marks_with_pattern_fills_with_tiling_pattern() {
+        let mut writer = MrcPageWriter::new();
+        let rect = BBox {
+            x_min: 10.0,
+            y_min: 20.0,
+            x_max: 40.0,
+            y_max: 60.0,
+        };
+        let page_ids: Vec<lopdf::ObjectId> = (0..2)
+            .map(|_| {
+                let page_id = writer
+                    .write_blank_page(200.0, 100.0, true)
+                    .expect("write blank page");
+                writer
+                    .add_redaction_marks_with_fill(
+                        page_id,
+                        std::slice::from_ref(&rect),
+                        [0, 0, 0],
+                        RedactionFill::Hatch,
+                    )
+                    .expect("add marks");
+                page_id
+            })
+            .collect();
+
+        let content = writer
+            .doc
+            .get_page_content(page_ids[0])
+            .expect("page content");
+        let content = String::from_utf8(content).expect("utf-8 content");
+        assert!(
+            content.ends_with("q /Pattern cs /RedactMark scn 10 20 30 40 re f Q"),
+            "got {content:?}"
+        );
+
+        let pattern_of = |page_id| {
+            let page = writer.doc.get_dictionary(page_id).expect("page dict");
+            let resources = page
+                .get(b"Resources")
+                .and_then(|r| writer.doc.dereference(r))
+                .and_then(|(_, r)| r.as_dict())
+                .expect("Resources");
+            resources
+                .get(b"Pattern")
+                .and_then(|p| writer.doc.dereference(p))
+                .and_then(|(_, p)| p.as_dict())
+                .and_then(|p| p.get(b"RedactMark"))
+                .and_then(Object::as_reference)
+                .expect("/Pattern resource RedactMark")
+        };
+        let pattern_id = pattern_of(page_ids[0]);
+        assert_eq!(
+            pattern_of(page_ids[1]),
+            pattern_id,
+            "pattern should be shared"
+        );
+        let pattern = writer
+            .doc
+            .get_object(pattern_id)
+            .and_then(Object::as_stream)
+            .expect("pattern stream");
+        assert_eq!(
+            pattern
+                .dict
+                .get(b"PatternType")
+                .and_then(Object::as_i64)
+                .ok(),
+            Some(1)
+        );
+        assert_eq!(
+            pattern.dict.get(b"PaintType").and_then(Object::as_i64).ok(),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_text_masked_page_does_not_mutate_shared_objects() {
+        // 2ページが同じ/Contentsストリームと同じResources（画像Im1を含む）を共有する
+        let mut source = Document::with_version("1.4");
+        let pages_id = source.new_object_id();
+        let original_content = b"q 100 0 0 100 0 0 cm /Im1 Do Q BT ET".to_vec();
+        let content_id = source.add_object(Stream::new(dictionary! {}, original_content.clone()));
+        let image_id = source.add_object(Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => 1,
+                "Height" => 1,
+                "ColorSpace" => "DeviceGray",
+                "BitsPerComponent" => 8,
+            },
+            vec![0x00],
+        ));
+        let resources_id = source.add_object(dictionary! {
+            "XObject" => dictionary! { "Im1" => image_id },
+        });
+        let page_ids: Vec<lopdf::ObjectId> = (0..2)
+            .map(|_| {
+                source.add_object(dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                    "MediaBox" => vec![0.into(), 0.into(), 100.into(), 100.into()],
+                    "Contents" => content_id,
+                    "Resources" => resources_id,
+                })
+            })
+            .collect();
+        source.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => page_ids.iter().map(|&id| id.into()).collect::<Vec<Object>>(),
+                "Count" => 2,
+            }),
+        );
+        let catalog_id = source.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        source.trailer.set("Root", catalog_id);
+
+        let mut modified_images = HashMap::new();
+        modified_images.insert(
+            "Im1".to_string(),
+            ImageModification {
+                data: vec![0xFF],
+                filter: String::new(),
+                color_space: "DeviceGray".to_string(),
+                bits_per_component: 8,
+                width: 1,
+                height: 1,
+                redacted_regions: Vec::new(),
+                smask: None,
+            },
+        );
+        let data = TextMaskedData {
+            stripped_content_stream: b"q 100 0 0 100 0 0 cm /Im1 Do Q".to_vec(),
+            text_regions: Vec::new(),
+            modified_images,
+            page_index: 0,
+            page_width_pts: 100.0,
+            page_height_pts: 100.0,
+            color_mode: ColorMode::Rgb,
+        };
+
+        // ページ1をテキストマスク処理し、ページ2はそのままコピーする
+        let mut writer = MrcPageWriter::new();
+        writer
+            .write_text_masked_page(&source, 1, &data)
+            .expect("write text-masked page 1");
+        writer.copy_page_from(&source, 2).expect("copy page 2");
+
+        let doc = &writer.doc;
+        let pages = doc.get_pages();
+        let image_data = |page_num: u32| {
+            let page = doc.get_dictionary(pages[&page_num]).expect("page dict");
+            let resources = match page.get(b"Resources").expect("Resources") {
+                Object::Reference(id) => doc.get_dictionary(*id).expect("Resources dict"),
+                Object::Dictionary(dict) => dict,
+                other => panic!("unexpected Resources: {other:?}"),
+            };
+            let xobjects = match resources.get(b"XObject").expect("XObject") {
+                Object::Reference(id) => doc.get_dictionary(*id).expect("XObject dict"),
+                Object::Dictionary(dict) => dict,
+                other => panic!("unexpected XObject: {other:?}"),
+            };
+            let image_id = xobjects.get(b"Im1").and_then(Object::as_reference).unwrap();
+            doc.get_object(image_id)
+                .and_then(Object::as_stream)
+                .expect("image stream")
+                .content
+                .clone()
+        };
+
+        assert_eq!(image_data(1), vec![0xFF], "page 1 image should be redacted");
+        assert_eq!(image_data(2), vec![0x00], "page 2 image must be unaffected");
+        assert_eq!(
+            doc.get_page_content(pages[&2]).expect("page 2 content"),
+            original_content,
+            "page 2 content must be unaffected"
+        );
+        assert_ne!(
+            doc.get_page_content(pages[&1]).expect("page 1 content"),
+            original_content
+        );
+    }
+
+    #[test]
+    fn test_modified_image_smask_replaces_smask_in_data() {
+        let mut source = Document::with_version("1.5");
+        let pages_id = source.new_object_id();
+        let content_id = source.add_object(Stream::new(
+            dictionary! {},
+            b"q 100 0 0 100 0 0 cm /Im1 Do Q".to_vec(),
+        ));
+        let image_id = source.add_object(Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Image",
+                "Width" => 2,
+                "Height" => 1,
+                "Filter" => "JPXDecode",
+                "SMaskInData" => 1,
+            },
+            vec![0x00],
+        ));
+        let page_id = source.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 100.into(), 100.into()],
+            "Contents" => content_id,
+            "Resources" => dictionary! {
+                "XObject" => dictionary! { "Im1" => image_id },
+            },
+        });
+        source.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page_id.into()],
+                "Count" => 1,
+            }),
+        );
+        let catalog_id = source.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        source.trailer.set("Root", catalog_id);
+
+        let mut modified_images = HashMap::new();
+        modified_images.insert(
+            "Im1".to_string(),
+            ImageModification {
+                data: vec![0xFF, 0xD8],
+                filter: "DCTDecode".to_string(),
+                color_space: "DeviceGray".to_string(),
+                bits_per_component: 8,
+                width: 2,
+                height: 1,
+                redacted_regions: Vec::new(),
+                smask: Some(vec![0x78, 0x9C]),
+            },
+        );
+        let data = TextMaskedData {
+            stripped_content_stream: b"q 100 0 0 100 0 0 cm /Im1 Do Q".to_vec(),
+            text_regions: Vec::new(),
+            modified_images,
+            page_index: 0,
+            page_width_pts: 100.0,
+            page_height_pts: 100.0,
+            color_mode: ColorMode::Rgb,
+        };
+
+        let mut writer = MrcPageWriter::new();
+        let page_id = writer
+            .write_text_masked_page(&source, 1, &data)
+            .expect("write text-masked page");
+
+        let doc = &writer.doc;
+        let page = doc.get_dictionary(page_id).expect("page dict");
+        let resources = match page.get(b"Resources").expect("Resources") {
+            Object::Reference(id) => doc.get_dictionary(*id).expect("Resources dict"),
+            Object::Dictionary(dict) => dict,
+            other => panic!("unexpected Resources: {other:?}"),
+        };
+        let xobjects = match resources.get(b"XObject").expect("XObject") {
+            Object::Reference(id) => doc.get_dictionary(*id).expect("XObject dict"),
+            Object::Dictionary(dict) => dict,
+            other => panic!("unexpected XObject: {other:?}"),
+        };
+        let image_id = xobjects.get(b"Im1").and_then(Object::as_reference).unwrap();
+        let image = doc
+            .get_object(image_id)
+            .and_then(Object::as_stream)
+            .expect("image stream");
+        assert!(image.dict.get(b"SMaskInData").is_err());
+
+        let smask_id = image
+            .dict
+            .get(b"SMask")
+            .and_then(Object::as_reference)
+            .expect("SMask ref");
+        let smask = doc
+            .get_object(smask_id)
+            .and_then(Object::as_stream)
+            .expect("SMask stream");
+        assert_eq!(smask.content, vec![0x78, 0x9C]);
+        assert_eq!(
+            smask.dict.get(b"ColorSpace").and_then(Object::as_name).ok(),
+            Some(&b"DeviceGray"[..])
+        );
+        assert_eq!(
+            smask.dict.get(b"Width").and_then(Object::as_i64).ok(),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn test_copy_page_from_materializes_inherited_rotate() {
+        // /Rotateと/MediaBoxを中間のPagesノードで指定し、ページ自身は持たない
+        let mut source = Document::with_version("1.4");
+        let root_pages_id = source.new_object_id();
+        let mid_pages_id = source.new_object_id();
+
+        let content_id = source.add_object(Stream::new(dictionary! {}, b"q Q".to_vec()));
+        let page_id = source.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => mid_pages_id,
+            "Contents" => content_id,
+        });
+        source.objects.insert(
+            mid_pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Parent" => root_pages_id,
+                "Kids" => vec![page_id.into()],
+                "Count" => 1,
+                "Rotate" => 90,
+            }),
+        );
+        source.objects.insert(
+            root_pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![mid_pages_id.into()],
+                "Count" => 1,
+                "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+            }),
+        );
+        let catalog_id = source.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => root_pages_id,
+        });
+        source.trailer.set("Root", catalog_id);
+
+        let mut writer = MrcPageWriter::new();
+        writer.copy_page_from(&source, 1).expect("copy page 1");
+
+        let pdf_bytes = writer.save_to_bytes().expect("save to bytes");
+        let doc = Document::load_mem(&pdf_bytes).expect("load output PDF");
+        let out_page_id = *doc.get_pages().get(&1).expect("page 1");
+        let out_page = doc.get_dictionary(out_page_id).expect("page dict");
+        assert_eq!(
+            out_page.get(b"Rotate").and_then(Object::as_i64).ok(),
+            Some(90)
+        );
+        assert!(
+            out_page.has(b"MediaBox"),
+            "inherited MediaBox should be copied"
+        );
+    }
+
+    /// 2ページが同一フォントオブジェクトを共有するソースPDFを作成する
+    fn two_pages_sharing_font() -> Document {
         let mut source = Document::with_version("1.4");
         let pages_id = source.new_object_id();
 
@@ -1016,6 +2975,13 @@ mod tests {
             "Pages" => pages_id,
         });
         source.trailer.set("Root", catalog_id);
+        source
+    }
+
+    #[cfg(feature = "mrc")]
+    #[test]
+    fn test_copy_shared_resources_deduplication() {
+        let source = two_pages_sharing_font();
 
         // 両ページをコピー
         let mut writer = MrcPageWriter::new();
@@ -1049,6 +3015,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_copy_without_sharing_gives_each_page_its_own_font() {
+        let source = two_pages_sharing_font();
+
+        let mut writer = MrcPageWriter::new();
+        writer.set_share_copied_objects(false);
+        let page1 = writer.copy_page_from(&source, 1).expect("copy page 1");
+        let page2 = writer.copy_page_from(&source, 2).expect("copy page 2");
+
+        let font_of = |page_id| {
+            let resources_id = writer
+                .doc
+                .get_dictionary(page_id)
+                .and_then(|page| page.get(b"Resources"))
+                .and_then(Object::as_reference)
+                .expect("Resources reference");
+            writer
+                .doc
+                .get_dictionary(resources_id)
+                .and_then(|resources| resources.get(b"Font"))
+                .and_then(Object::as_dict)
+                .and_then(|fonts| fonts.get(b"F1"))
+                .and_then(Object::as_reference)
+                .expect("F1 reference")
+        };
+        let (font1, font2) = (font_of(page1), font_of(page2));
+        assert_ne!(font1, font2, "each page should get its own font copy");
+
+        let pdf_bytes = writer.save_to_bytes().expect("save to bytes");
+        let doc = Document::load_mem(&pdf_bytes).expect("load output PDF");
+        let font_count = doc
+            .objects
+            .values()
+            .filter_map(|obj| obj.as_dict().ok())
+            .filter(|dict| dict.get(b"Type").and_then(Object::as_name).ok() == Some(b"Font"))
+            .count();
+        assert_eq!(font_count, 2);
+    }
+
     #[cfg(feature = "mrc")]
     #[test]
     fn test_mixed_mode_pages() {
@@ -1061,6 +3066,7 @@ mod tests {
             page_width_pts: 595.276,
             page_height_pts: 841.89,
             color_mode: ColorMode::Rgb,
+            output_colorspace: OutputColorSpace::Rgb,
         };
         let bw_layers = crate::mrc::BwLayers {
             mask_jbig2: vec![0x97, 0x4A, 0x42, 0x32],
@@ -0,0 +1,144 @@
+// 白紙ページ検出（コンテンツストリーム / レンダリング済みビットマップ）
+
+use image::{DynamicImage, GrayImage};
+use lopdf::content::Content;
+
+/// ビットマップ判定で「インク」とみなす輝度の上限（これ未満を暗画素とする）
+const INK_LUMA_THRESHOLD: u8 = 160;
+
+/// 描画を行うオペレータ（パス描画・シェーディング・XObject/インライン画像・テキスト表示）。
+/// これを1つも含まないページには何も描かれない。
+const PAINTING_OPERATORS: &[&str] = &[
+    "S", "s", "f", "F", "f*", "B", "B*", "b", "b*", "sh", "Do", "BI", "EI", "Tj", "TJ", "'", "\"",
+];
+
+/// テキスト表示またはXObject/インライン画像を描画するオペレータ。
+const TEXT_OR_IMAGE_OPERATORS: &[&str] = &["Do", "BI", "EI", "Tj", "TJ", "'", "\""];
+
+/// コンテンツストリームが何も描画しないかを判定する。
+///
+/// パス描画・テキスト表示・XObject/インライン画像・シェーディングのいずれの
+/// オペレータも含まない場合に`true`を返す。デコードできないストリームは
+/// 安全側に倒して白紙とみなさない。
+pub fn is_blank_content(content_bytes: &[u8]) -> bool {
+    if content_bytes.iter().all(u8::is_ascii_whitespace) {
+        return true;
+    }
+    match Content::decode(content_bytes) {
+        Ok(content) => !content
+            .operations
+            .iter()
+            .any(|op| PAINTING_OPERATORS.contains(&op.operator.as_str())),
+        Err(_) => false,
+    }
+}
+
+/// コンテンツストリームがテキストまたは画像を描画するかを判定する。
+///
+/// ビットマップからテキストが見つからなかったときに、高DPIで再試行する価値が
+/// あるかの判断に使う。デコードできないストリームは安全側に倒して`true`とする。
+pub fn has_text_or_images(content_bytes: &[u8]) -> bool {
+    if content_bytes.iter().all(u8::is_ascii_whitespace) {
+        return false;
+    }
+    match Content::decode(content_bytes) {
+        Ok(content) => content
+            .operations
+            .iter()
+            .any(|op| TEXT_OR_IMAGE_OPERATORS.contains(&op.operator.as_str())),
+        Err(_) => true,
+    }
+}
+
+/// レンダリング済みビットマップが実質的に白紙かを判定する。
+///
+/// 暗画素の8連結成分のうち、外接矩形の幅・高さがともに`despeckle_px`未満の
+/// ものはスキャンのゴミとして無視する。それ以上の成分が1つも無ければ白紙とみなす。
+pub fn is_blank_bitmap(bitmap: &DynamicImage, despeckle_px: u32) -> bool {
+    let gray = bitmap.to_luma8();
+    !has_component_at_least(&gray, despeckle_px.max(1))
+}
+
+/// 外接矩形の幅または高さが`min_size`以上の暗画素連結成分が存在するか。
+fn has_component_at_least(gray: &GrayImage, min_size: u32) -> bool {
+    let (width, height) = gray.dimensions();
+    let is_ink = |x: u32, y: u32| gray.get_pixel(x, y).0[0] < INK_LUMA_THRESHOLD;
+    let mut visited = vec![false; width as usize * height as usize];
+    let mut stack: Vec<(u32, u32)> = Vec::new();
+
+    for start_y in 0..height {
+        for start_x in 0..width {
+            let start_idx = (start_y * width + start_x) as usize;
+            if visited[start_idx] || !is_ink(start_x, start_y) {
+                continue;
+            }
+
+            visited[start_idx] = true;
+            stack.push((start_x, start_y));
+            let (mut x_min, mut x_max, mut y_min, mut y_max) = (start_x, start_x, start_y, start_y);
+
+            while let Some((x, y)) = stack.pop() {
+                x_min = x_min.min(x);
+                x_max = x_max.max(x);
+                y_min = y_min.min(y);
+                y_max = y_max.max(y);
+                if x_max - x_min + 1 >= min_size || y_max - y_min + 1 >= min_size {
+                    return true;
+                }
+
+                for ny in y.saturating_sub(1)..=(y + 1).min(height - 1) {
+                    for nx in x.saturating_sub(1)..=(x + 1).min(width - 1) {
+                        let idx = (ny * width + nx) as usize;
+                        if !visited[idx] && is_ink(nx, ny) {
+                            visited[idx] = true;
+                            stack.push((nx, ny));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Luma, RgbImage};
+
+    #[test]
+    fn test_empty_and_state_only_content_is_blank() {
+        assert!(is_blank_content(b""));
+        assert!(is_blank_content(b"q 1 0 0 1 0 0 cm 0 g Q"));
+    }
+
+    #[test]
+    fn test_painting_content_is_not_blank() {
+        assert!(!is_blank_content(b"0 0 m 100 100 l S"));
+        assert!(!is_blank_content(b"q 100 0 0 100 0 0 cm /Im1 Do Q"));
+        assert!(!is_blank_content(b"BT /F1 12 Tf (Hi) Tj ET"));
+    }
+
+    #[test]
+    fn test_bitmap_with_specks_is_blank() {
+        let mut gray = GrayImage::from_pixel(200, 200, Luma([255]));
+        // 2x2pxのゴミが数点
+        for (x, y) in [(10, 10), (150, 40), (90, 180)] {
+            for dy in 0..2 {
+                for dx in 0..2 {
+                    gray.put_pixel(x + dx, y + dy, Luma([0]));
+                }
+            }
+        }
+        assert!(is_blank_bitmap(&DynamicImage::ImageLuma8(gray), 8));
+    }
+
+    #[test]
+    fn test_bitmap_with_stroke_is_not_blank() {
+        let mut rgb = RgbImage::from_pixel(200, 200, image::Rgb([255, 255, 255]));
+        for x in 20..60 {
+            rgb.put_pixel(x, 100, image::Rgb([0, 0, 0]));
+        }
+        assert!(!is_blank_bitmap(&DynamicImage::ImageRgb8(rgb), 8));
+    }
+}
@@ -0,0 +1,45 @@
+// 比較PDF: 元ページとマスク後のページを左右に並べたQA用のPDFを書き出す
+
+use std::path::{Path, PathBuf};
+
+use tracing::debug;
+
+use crate::pdf::reader::PdfReader;
+use crate::pdf::writer::MrcPageWriter;
+use crate::pipeline::job_runner::PageReport;
+
+/// `dir`に置く比較PDFのパス（`<出力ファイル名の語幹>_compare.pdf`）を返す。
+pub fn comparison_path(dir: &Path, output: &Path) -> PathBuf {
+    let stem = output
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "output".to_string());
+    dir.join(format!("{stem}_compare.pdf"))
+}
+
+/// 出力PDFの各ページについて、左に元ページ、右にマスク後のページを並べた比較PDFを
+/// `path`に書き出す。
+///
+/// `pages`は出力順のページ報告で、`page_index`から対応する元ページを引く。
+/// `path`の親ディレクトリが無ければ作成する。
+pub fn write_comparison(
+    input: &Path,
+    output: &Path,
+    pages: &[PageReport],
+    path: &Path,
+) -> crate::error::Result<()> {
+    let before = PdfReader::open(input)?;
+    let after = PdfReader::open(output)?;
+
+    let mut writer = MrcPageWriter::new();
+    for (i, page) in pages.iter().enumerate() {
+        writer.write_comparison_page(&before, page.page_index + 1, &after, i as u32 + 1)?;
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    debug!(path = %path.display(), pages = pages.len(), "writing comparison PDF");
+    std::fs::write(path, writer.save_to_bytes()?)?;
+    Ok(())
+}
@@ -1,25 +1,60 @@
 // Phase 10: ジョブ単位: PDF読込 -> 並列ページ処理 -> 出力PDF組立
 
+use std::collections::BTreeMap;
+#[cfg(feature = "mrc")]
+use std::path::Path;
 use std::path::PathBuf;
 
 #[cfg(feature = "mrc")]
 use rayon::prelude::*;
-use tracing::debug;
+use tracing::{debug, warn};
 
-use crate::cache::hash::CacheSettings;
+use crate::cache::hash::{CacheSettings, WriterSettings, compute_source_key};
 use crate::cache::store::CacheStore;
-use crate::config::job::ColorMode;
+use crate::config::job::{
+    BlankPageAction, ColorMode, ForegroundMask, ImageColorSpace, JpegSizeMismatch, MetadataMode,
+    OutputColorSpace, OverlayCoordinates, PreserveRegion, RedactionFill, ResampleFilter,
+    WidthSource,
+};
+use crate::config::settings::{
+    DEFAULT_MAX_CONTENT_STREAM_BYTES, DEFAULT_MAX_IMAGE_PIXELS, ImageClassDpi, Jbig2Options,
+    JpegEscalation, LineRemoval,
+};
 use crate::error::PdfMaskError;
 #[cfg(feature = "mrc")]
 use crate::mrc::compositor::MrcConfig;
-use crate::mrc::{PageOutput, SkipData};
+use crate::mrc::{BlankData, LayerSizes, PageOutput, ReusedData, SkipData};
+#[cfg(feature = "mrc")]
+use crate::mrc::{ImageModification, TextMaskedData};
+#[cfg(feature = "mrc")]
+use crate::pdf::content_stream::full_page_xobject;
+use crate::pdf::content_stream::{
+    BBox, FillResources, contains_shading, contains_text, extract_white_fill_rects_with_resources,
+    extract_xobject_placements_with_resources, is_horizontally_mirrored,
+};
+#[cfg(feature = "mrc")]
+use crate::pdf::filters::stream_filters;
+use crate::pdf::font::{FontSubstitutions, parse_page_fonts_with_substitutions};
+use crate::pdf::icc::OutputIntentProfile;
+use crate::pdf::image_xobject::bbox_overlaps;
+#[cfg(feature = "mrc")]
+use crate::pdf::image_xobject::{OptimizedImage, optimize_image_stream};
+use crate::pdf::overlay::read_overlay_redactions;
 use crate::pdf::reader::PdfReader;
 use crate::pdf::writer::MrcPageWriter;
 #[cfg(feature = "mrc")]
-use crate::pipeline::page_processor::ProcessPageParams;
+use crate::pipeline::blank_page::is_blank_bitmap;
+use crate::pipeline::blank_page::is_blank_content;
+use crate::pipeline::compare::{comparison_path, write_comparison};
+#[cfg(feature = "mrc")]
+use crate::pipeline::page_processor::{
+    PreprocessHook, ProcessPageParams, should_retry_at_higher_dpi,
+};
 use crate::pipeline::page_processor::{ProcessPageOutlinesParams, ProcessedPage};
+use crate::pipeline::redaction_manifest::{build_manifest, write_manifest};
+use crate::pipeline::redaction_verify::{RedactionCheck, verify_redaction};
 #[cfg(feature = "mrc")]
-use crate::render::pdfium::render_page;
+use crate::render::pdfium::{RenderOptions, render_page_with_options};
 
 /// Configuration for a single job.
 pub struct JobConfig {
@@ -33,6 +68,259 @@ pub struct JobConfig {
     pub bg_quality: u8,
     pub fg_quality: u8,
     pub cache_dir: Option<PathBuf>,
+    /// JBIG2 generic-region encoding options for text masks.
+    pub jbig2: Jbig2Options,
+    /// Re-encode MRC JPEG layers at a higher quality when they come out too lossy.
+    pub jpeg_escalation: JpegEscalation,
+    /// Upper bound on the effective DPI of redacted images (downsample only).
+    pub image_max_dpi: Option<u32>,
+    /// Per-class (photo / line art) overrides of `image_max_dpi`. Setting
+    /// either also picks re-encodings by class: JPEG for photos, JBIG2 or
+    /// Flate for line art.
+    pub image_class_dpi: ImageClassDpi,
+    /// Re-encode image XObjects in this color space (`None` keeps each source's).
+    pub normalize_images_to: Option<ImageColorSpace>,
+    /// Resampling filter used when downsampling images.
+    pub resample_filter: ResampleFilter,
+    /// Largest image XObject (Width x Height) that will be decoded.
+    pub max_image_pixels: u64,
+    /// What to do when a JPEG image's actual size differs from its
+    /// `/Width`/`/Height`: warn and use the actual size, or fail the page.
+    pub jpeg_size_mismatch: JpegSizeMismatch,
+    /// Largest decoded page content stream, in bytes.
+    pub max_content_stream_bytes: u64,
+    /// Largest number of pages a job may write (`None` for no limit). A job
+    /// that would exceed it fails before the output is written.
+    pub max_output_pages: Option<u32>,
+    /// What to do with pages detected as blank.
+    pub blank_page_action: BlankPageAction,
+    /// Color space of MRC background/foreground JPEGs for RGB pages.
+    pub output_colorspace: OutputColorSpace,
+    /// How the source document's /Info and XMP metadata are carried over.
+    pub metadata: MetadataMode,
+    /// Produce byte-identical output for identical input and settings.
+    pub reproducible: bool,
+    /// Detect pages drawn with a horizontal flip and un-mirror them when rasterizing.
+    pub fix_mirror: bool,
+    /// 1-based pages to un-mirror regardless of detection.
+    pub mirror_pages: Vec<u32>,
+    /// Re-rasterize once at this DPI when no text is found on a page that draws
+    /// text or images. Ignored unless higher than `dpi`.
+    pub retry_empty_at_dpi: Option<u32>,
+    /// Anti-alias text when rasterizing. Off gives hard edges for the text mask.
+    pub text_antialias: bool,
+    /// PDF whose annotations and filled rectangles mark regions to black out
+    /// on the corresponding (same-numbered) input pages.
+    pub overlay_redactions: Option<PathBuf>,
+    /// Coordinate system of the `overlay_redactions` rectangles: page space,
+    /// or the page as displayed (with /Rotate applied).
+    pub overlay_coordinates: OverlayCoordinates,
+    /// Page-space rectangles whose original paths and images are drawn back
+    /// as vectors over the rasterized page (text stays rasterized). Their
+    /// pages are rasterized as full-page MRC. Pages with redactions keep
+    /// none, and rectangles overlapping a form XObject that draws text are
+    /// dropped.
+    pub preserve_regions: Vec<PreserveRegion>,
+    /// Draw the redacted regions as opaque rectangles of this RGB color on the
+    /// output pages, so the redaction stays visible. `None` leaves no mark.
+    pub redaction_mark: Option<[u8; 3]>,
+    /// How the redaction marks are painted: solid, or a tiling pattern of
+    /// diagonal lines or repeated "REDACTED" text in the mark color.
+    pub redaction_mark_fill: RedactionFill,
+    /// Sidecar file (JSON, or YAML for `.yaml`/`.yml`) listing per page the
+    /// redactions applied: overlay rectangles and scrubbed image regions.
+    pub redaction_manifest: Option<PathBuf>,
+    /// Replace pages whose content paints nothing with a minimal white page
+    /// instead of rasterizing them (applies when blank pages are kept).
+    pub minimize_blank: bool,
+    /// Bake AcroForm field values into the page content and drop the form,
+    /// so filled-in data shows in the masked output.
+    pub flatten_forms: bool,
+    /// Rasterize only the text and keep the page's image XObjects as they are.
+    /// When off, the whole page is rasterized into MRC layers (and
+    /// text-to-outlines is not attempted). See [`JobConfig::preserve_images_on`].
+    pub preserve_images: bool,
+    /// Per-page (1-based) overrides of `preserve_images`.
+    pub preserve_images_overrides: std::collections::HashMap<u32, bool>,
+    /// Convert text in embedded fonts to outlines instead of rasterizing it.
+    /// See [`JobConfig::text_to_outlines_on`].
+    pub text_to_outlines: bool,
+    /// Per-page (1-based) overrides of `text_to_outlines`.
+    pub text_to_outlines_overrides: std::collections::HashMap<u32, bool>,
+    /// Keep pages that are a single full-page JPEG image as they are and only
+    /// re-encode that image (see `optimize_image_encoding`) instead of
+    /// rasterizing them. Requires the `mrc` feature.
+    pub passthrough_full_page_images: bool,
+    /// Copy `Skip` pages as they are but re-encode their image XObjects
+    /// (see `optimize_image_encoding`) when that makes them smaller. Text and
+    /// vector content are untouched. Requires the `mrc` feature.
+    pub optimize_skip_images: bool,
+    /// Let `Skip` pages share copied objects (fonts, images, ...) with other
+    /// pages. When `false`, each `Skip` page gets its own copies, so a later
+    /// change to one page's objects cannot reach another page.
+    pub share_copied_objects: bool,
+    /// Add a PDF/A output intent whose ICC profile matches the output:
+    /// Gray Gamma 2.2 when no page uses color, sRGB otherwise.
+    pub pdfa_output_intent: bool,
+    /// Long horizontal/vertical lines to drop from the JBIG2 text mask.
+    pub line_removal: LineRemoval,
+    /// Points by which each text region's image mask placement is enlarged on
+    /// every side, to hide glyph edges clipped by JBIG2 pixel rounding.
+    pub text_region_padding: f64,
+    /// How MRC pages apply the text mask to the foreground image: as its
+    /// soft mask (/SMask) or as an ImageMask stencil (/Mask).
+    pub foreground_mask: ForegroundMask,
+    /// Set `/Interpolate true` on MRC background and foreground images so
+    /// viewers smooth them when upscaling instead of showing blocky pixels.
+    pub interpolate_output: bool,
+    /// Give RGB MRC background and foreground images an `/ICCBased` sRGB
+    /// color space (one shared profile) instead of untagged DeviceRGB.
+    pub tag_srgb: bool,
+    /// Drop redundant `q`/`Q` pairs and repeated color operators from the
+    /// content streams of pages masked in this run.
+    pub simplify_content: bool,
+    /// Split content streams of pages masked in this run into several stream
+    /// objects of at most this many decoded bytes each.
+    pub split_content_bytes: Option<u64>,
+    /// After writing, re-read the output and fail the job if text or
+    /// unredacted image data is still found in a redacted region.
+    pub verify_redaction: bool,
+    /// Language tag written to the output Catalog's /Lang. When `None`, the
+    /// source Catalog's /Lang is carried over.
+    pub lang: Option<String>,
+    /// Carry the source Catalog's `/ViewerPreferences`, `/PageLayout`,
+    /// `/PageMode` and a `GoTo` `/OpenAction` over to the output. Other
+    /// open actions (such as JavaScript) are never copied.
+    pub preserve_view: bool,
+    /// Value written to the output /Info /Producer. An empty string removes
+    /// the entry; `None` leaves whatever `metadata` carried over.
+    pub producer: Option<String>,
+    /// Font files used for non-embedded fonts by name, ahead of system fonts.
+    pub font_substitutions: BTreeMap<String, PathBuf>,
+    /// Font file used for non-embedded fonts not in `font_substitutions`.
+    /// When set, system fonts are never consulted.
+    pub font_fallback: Option<PathBuf>,
+    /// Look up non-embedded fonts among system fonts. Off skips loading the
+    /// system font database; unresolved fonts then fall back to rendering.
+    pub use_system_fonts: bool,
+    /// Where text-to-outlines takes glyph advances from: the PDF `/Widths`
+    /// (per spec), the font's own advances (for files with broken Widths),
+    /// or the larger of the two.
+    pub width_source: WidthSource,
+    /// Merge adjacent or overlapping white-fill rectangles whose union is a
+    /// rectangle before redacting images with them, so a grid of white
+    /// cells is applied as one region.
+    pub merge_white_fills: bool,
+    /// Record on each masked page a key of its source page (/PieceInfo), so
+    /// a later `--incremental` run can reuse the page.
+    pub record_source_keys: bool,
+    /// Prior masked output whose pages are copied instead of re-masked when
+    /// their source page is unchanged (`--incremental`).
+    pub incremental_from: Option<PathBuf>,
+    /// Directory to write a side-by-side comparison PDF into, with each
+    /// original page on the left and its masked page on the right
+    /// (`--compare`).
+    pub compare_dir: Option<PathBuf>,
+    /// Run the whole pipeline but discard the assembled PDF instead of
+    /// writing it (`--bench`). Redaction verification, the redaction
+    /// manifest and the comparison PDF, which depend on a written output,
+    /// are skipped as well.
+    pub discard_output: bool,
+}
+
+impl JobConfig {
+    /// Whether image XObjects on `page_num` (1-based) are kept as they are,
+    /// after per-page overrides.
+    pub fn preserve_images_on(&self, page_num: u32) -> bool {
+        self.preserve_images_overrides
+            .get(&page_num)
+            .copied()
+            .unwrap_or(self.preserve_images)
+    }
+
+    /// Whether text-to-outlines is attempted on `page_num` (1-based), after
+    /// per-page overrides. Pages that do not preserve images are always
+    /// rasterized, so this is `false` for them.
+    pub fn text_to_outlines_on(&self, page_num: u32) -> bool {
+        self.preserve_images_on(page_num)
+            && self
+                .text_to_outlines_overrides
+                .get(&page_num)
+                .copied()
+                .unwrap_or(self.text_to_outlines)
+    }
+}
+
+impl Default for JobConfig {
+    /// The defaults of [`Settings`](crate::config::settings::Settings) for
+    /// every option, with empty input/output paths, no cache, no per-page
+    /// overrides and the source's /Producer left as carried over.
+    fn default() -> Self {
+        JobConfig {
+            input_path: PathBuf::new(),
+            output_path: PathBuf::new(),
+            default_color_mode: ColorMode::Rgb,
+            color_mode_overrides: std::collections::HashMap::new(),
+            dpi: 300,
+            bg_quality: 50,
+            fg_quality: 30,
+            cache_dir: None,
+            jbig2: Jbig2Options::default(),
+            jpeg_escalation: JpegEscalation::default(),
+            image_max_dpi: None,
+            image_class_dpi: ImageClassDpi::default(),
+            normalize_images_to: None,
+            resample_filter: ResampleFilter::Triangle,
+            max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+            jpeg_size_mismatch: JpegSizeMismatch::Warn,
+            max_content_stream_bytes: DEFAULT_MAX_CONTENT_STREAM_BYTES,
+            max_output_pages: None,
+            blank_page_action: BlankPageAction::Keep,
+            output_colorspace: OutputColorSpace::Rgb,
+            metadata: MetadataMode::Strip,
+            reproducible: false,
+            fix_mirror: false,
+            mirror_pages: Vec::new(),
+            retry_empty_at_dpi: None,
+            text_antialias: true,
+            overlay_redactions: None,
+            overlay_coordinates: OverlayCoordinates::Page,
+            preserve_regions: Vec::new(),
+            redaction_mark: None,
+            redaction_mark_fill: RedactionFill::Solid,
+            redaction_manifest: None,
+            minimize_blank: false,
+            flatten_forms: false,
+            preserve_images: true,
+            preserve_images_overrides: std::collections::HashMap::new(),
+            text_to_outlines: true,
+            text_to_outlines_overrides: std::collections::HashMap::new(),
+            passthrough_full_page_images: false,
+            optimize_skip_images: false,
+            share_copied_objects: true,
+            pdfa_output_intent: false,
+            line_removal: LineRemoval::default(),
+            text_region_padding: 0.0,
+            foreground_mask: ForegroundMask::Smask,
+            interpolate_output: false,
+            tag_srgb: false,
+            simplify_content: false,
+            split_content_bytes: None,
+            verify_redaction: false,
+            lang: None,
+            preserve_view: false,
+            producer: None,
+            font_substitutions: BTreeMap::new(),
+            font_fallback: None,
+            use_system_fonts: true,
+            width_source: WidthSource::Pdf,
+            merge_white_fills: false,
+            record_source_keys: false,
+            incremental_from: None,
+            compare_dir: None,
+            discard_output: false,
+        }
+    }
 }
 
 /// Result of processing a single job.
@@ -40,17 +328,65 @@ pub struct JobResult {
     pub input_path: PathBuf,
     pub output_path: PathBuf,
     pub pages_processed: usize,
+    /// Number of blank pages omitted from the output (`BlankPageAction::Drop`).
+    pub pages_dropped: usize,
+    /// Per-page layer sizes and rasterization DPI, in output order.
+    pub pages: Vec<PageReport>,
+    /// Sum of `pages[].layer_sizes`.
+    pub layer_sizes: LayerSizes,
+    /// Size of the assembled output PDF in bytes.
+    pub output_bytes: u64,
 }
 
+/// Size accounting for a single output page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageReport {
+    /// 0-based page index in the input PDF.
+    pub page_index: u32,
+    pub layer_sizes: LayerSizes,
+    /// DPI the page was rasterized at (`None` when it was not rasterized).
+    pub effective_dpi: Option<u32>,
+}
+
+/// Specks smaller than this (in inches) are ignored by bitmap blank detection.
+#[cfg(feature = "mrc")]
+const BLANK_DESPECKLE_INCHES: f64 = 1.0 / 75.0;
+
 /// Intermediate data for a page after content stream analysis (Phase A).
 struct AnalysisResult {
     page_idx: u32,
     mode: ColorMode,
     content: Vec<u8>,
     image_streams: Option<std::collections::HashMap<String, lopdf::Stream>>,
+    matte_smasks: Option<std::collections::HashMap<String, lopdf::Stream>>,
+    fill_resources: Option<FillResources>,
     fonts: Option<std::collections::HashMap<String, crate::pdf::font::ParsedFont>>,
     page_width_pts: f64,
     page_height_pts: f64,
+    page_origin_pts: (f64, f64),
+    /// The page's /UserUnit, applied when rasterizing.
+    #[cfg(feature = "mrc")]
+    user_unit: f32,
+    /// The page is drawn mirrored and must be flipped back after rasterization.
+    unmirror: bool,
+    /// Page-space rectangles to black out (from `overlay_redactions`).
+    redactions: Vec<BBox>,
+    /// Page-space rectangles whose original content is drawn back over the
+    /// rasterized page (from `preserve_regions`).
+    preserved: Vec<BBox>,
+    /// Page-level XObjects drawn entirely inside `preserved`, the only ones
+    /// copied with it.
+    preserved_xobjects: Vec<String>,
+}
+
+/// Regions of one page to draw back from the source over its rasterized page.
+struct PreservedRegions {
+    /// Page-space rectangles.
+    rects: Vec<BBox>,
+    /// XObjects to copy with the regions (see [`safe_preserved_regions`]).
+    xobjects: Vec<String>,
+    /// Origin of the page's visible box, which the output page moves to (0, 0).
+    origin: (f64, f64),
 }
 
 /// Intermediate data for a page after rendering (Phase B).
@@ -61,345 +397,1590 @@ struct RenderResult {
     bitmap: image::DynamicImage,
     content: Vec<u8>,
     image_streams: Option<std::collections::HashMap<String, lopdf::Stream>>,
+    matte_smasks: Option<std::collections::HashMap<String, lopdf::Stream>>,
+    fill_resources: Option<FillResources>,
+    fonts: Option<std::collections::HashMap<String, crate::pdf::font::ParsedFont>>,
     page_width_pts: f64,
     page_height_pts: f64,
+    page_origin_pts: (f64, f64),
+    user_unit: f32,
+    unmirror: bool,
+    redactions: Vec<BBox>,
+    preserved: Vec<BBox>,
 }
 
 /// Run a single PDF masking job through the 4-phase pipeline.
 ///
-/// Phase A: Content stream analysis (sequential, skip Skip pages)
-/// Phase A2: Text-to-outlines conversion (skip if not eligible)
-/// Phase B+C: Page rendering + MRC processing (rayon parallel)
+/// Phase A-B: Content stream analysis, text-to-outlines conversion and page
+/// rendering, page by page ([`PageIterState::prepare`], sequential)
+/// Phase C: MRC processing (rayon parallel)
 /// Phase D: PDF assembly + optimization (sequential)
 pub fn run_job(config: &JobConfig) -> crate::error::Result<JobResult> {
-    let reader = PdfReader::open(&config.input_path)?;
+    let mut reader = PdfReader::open(&config.input_path)?;
     let page_count = reader.page_count();
 
+    // Form fields are baked into the page content before analysis, so every
+    // later phase (and the rasterizer, via a flattened copy) sees their values.
+    let flattened_widgets = if config.flatten_forms {
+        reader.flatten_form_fields()?
+    } else {
+        0
+    };
+    #[cfg(feature = "mrc")]
+    let flattened_copy = match flattened_widgets {
+        0 => None,
+        _ => Some(TempPdf::write(reader.document())?),
+    };
+    #[cfg(feature = "mrc")]
+    let render_path = flattened_copy
+        .as_ref()
+        .map_or(config.input_path.as_path(), |copy| copy.path.as_path());
+
     debug!(
         input = %config.input_path.display(),
         pages = page_count,
+        flattened_widgets,
         "starting job"
     );
 
-    // Validate override page numbers are within range
-    for &page_num in config.color_mode_overrides.keys() {
-        if page_num < 1 || page_num > page_count {
-            return Err(PdfMaskError::pdf_read(format!(
-                "override page {} out of range (document has {} pages)",
-                page_num, page_count
-            )));
-        }
+    let mut state = PageIterState::new(&reader, config)?;
+    #[cfg(feature = "mrc")]
+    {
+        state.render_path = render_path.to_path_buf();
     }
 
-    // Build page_modes for all pages (convert 1-based to 0-based)
-    let page_modes: Vec<(u32, ColorMode)> = (1..=page_count)
-        .map(|p| {
-            let mode = config
-                .color_mode_overrides
-                .get(&p)
-                .copied()
-                .unwrap_or(config.default_color_mode);
-            (p - 1, mode)
-        })
-        .collect();
+    // Incremental: pages whose source key matches a page of the prior output
+    // are copied from it instead of being masked again.
+    let prior = config
+        .incremental_from
+        .as_ref()
+        .map(PdfReader::open)
+        .transpose()?;
+    if let Some(prior) = &prior {
+        state.prior_keys = prior.page_source_keys();
+    }
 
-    let cache_store = config.cache_dir.as_ref().map(CacheStore::new);
+    // Phase A-B: analysis, text-to-outlines and rendering
+    debug!("phase A-B: analyzing, converting and rendering pages");
+    let mut successful_pages: Vec<ProcessedPage> = Vec::new();
+    #[cfg(feature = "mrc")]
+    let mut rendered: Vec<(Box<RenderResult>, Option<String>)> = Vec::new();
+    let mut pages_dropped = 0;
+    for page_num in 1..=page_count {
+        match state.prepare(&reader, config, page_num)? {
+            PreparedPage::Done(Some(page)) => successful_pages.push(page),
+            PreparedPage::Done(None) => pages_dropped += 1,
+            #[cfg(feature = "mrc")]
+            PreparedPage::Rendered(pd, source_key) => rendered.push((pd, source_key)),
+        }
+    }
+    state.warn_unapplied_regions();
 
-    // Phase A: Content stream analysis
-    debug!("phase A: analyzing content streams");
-    let content_streams = phase_a_analyze(&reader, &page_modes)?;
+    // Phase C: MRC composition (rayon parallel). Pages where segmentation
+    // found no text are re-rendered (sequentially) at the retry DPI if configured.
+    #[cfg(feature = "mrc")]
+    {
+        debug!(pages = rendered.len(), "phase C: MRC composition");
+        let composed: Vec<crate::error::Result<ProcessedPage>> = rendered
+            .par_iter()
+            .map(|(pd, _)| state.compose(pd, config))
+            .collect();
+        for ((pd, source_key), page) in rendered.into_iter().zip(composed) {
+            successful_pages.push(state.finish(&pd, page?, source_key, config)?);
+        }
+    }
+    successful_pages.sort_by_key(|p| p.page_index);
+    let PageRecords {
+        redaction_marks,
+        preserved_regions,
+        overlay_rects,
+    } = state.records;
 
-    // Phase A2: Text-to-outlines conversion
-    debug!("phase A2: text-to-outlines conversion");
-    let (outlines_pages, needs_rendering) =
-        phase_a2_text_to_outlines(content_streams, config, cache_store.as_ref())?;
+    if successful_pages.is_empty() {
+        return Err(PdfMaskError::pdf_read(format!(
+            "all {} page(s) were detected as blank and dropped",
+            page_count
+        )));
+    }
 
-    // Phase B+C: Rendering and MRC composition
-    debug!(
-        rendering = needs_rendering.len(),
-        outlines = outlines_pages.len(),
-        "phase B+C: rendering and MRC composition"
-    );
+    let pages_processed = successful_pages.len();
+    if let Some(limit) = config.max_output_pages
+        && pages_processed > limit as usize
+    {
+        return Err(PdfMaskError::limit_exceeded(format!(
+            "job would write {} pages, exceeding max_output_pages ({})",
+            pages_processed, limit
+        )));
+    }
 
-    #[cfg(feature = "mrc")]
-    let successful_pages = phase_bc_render_and_mrc(
-        needs_rendering,
-        outlines_pages,
-        &page_modes,
+    // Phase D: PDF output assembly
+    debug!(pages_dropped, "phase D: PDF assembly");
+    let result = phase_d_write(
+        &reader,
+        prior.as_ref(),
+        &successful_pages,
+        &redaction_marks,
+        &preserved_regions,
         config,
-        cache_store.as_ref(),
+        pages_processed,
+        pages_dropped,
     )?;
 
-    #[cfg(not(feature = "mrc"))]
-    let successful_pages = {
-        if !needs_rendering.is_empty() {
-            let pages: Vec<u32> = needs_rendering.iter().map(|a| a.page_idx + 1).collect();
-            return Err(PdfMaskError::render(format!(
-                "text-to-outlines conversion failed for page(s) {:?} and MRC fallback \
-                 is unavailable (compiled without 'mrc' feature). Ensure fonts are \
-                 embedded in the source PDF, or rebuild with `cargo build --features mrc`.",
-                pages
-            )));
+    if config.discard_output {
+        return Ok(result);
+    }
+
+    if config.verify_redaction {
+        let checks = redaction_checks(&reader, &successful_pages, &redaction_marks)?;
+        debug!(pages = checks.len(), "verifying redaction in output");
+        if let Err(e) = verify_redaction(&config.output_path, &checks) {
+            // 漏れのある出力を正常な出力と取り違えないよう削除する
+            let _ = std::fs::remove_file(&config.output_path);
+            return Err(e);
         }
+    }
 
-        // Combine outlines_pages with skip pages
-        let mut all_pages = outlines_pages;
-        for &(page_idx, mode) in &page_modes {
-            if mode == ColorMode::Skip {
-                all_pages.push(ProcessedPage {
-                    page_index: page_idx,
-                    output: PageOutput::Skip(SkipData {
-                        page_index: page_idx,
-                    }),
-                    cache_key: String::new(),
-                });
+    if let Some(path) = &config.redaction_manifest {
+        let manifest = build_manifest(
+            &config.input_path,
+            &config.output_path,
+            &successful_pages,
+            &overlay_rects,
+        );
+        debug!(
+            path = %path.display(),
+            pages = manifest.pages.len(),
+            "writing redaction manifest"
+        );
+        write_manifest(path, &manifest)?;
+    }
+
+    if let Some(dir) = &config.compare_dir {
+        let path = comparison_path(dir, &config.output_path);
+        write_comparison(
+            &config.input_path,
+            &config.output_path,
+            &result.pages,
+            &path,
+        )?;
+    }
+    Ok(result)
+}
+
+/// Lazily process the pages of `reader` one at a time, in page order.
+///
+/// Each call to `next` analyzes and converts (or renders and composes) only
+/// the next page, so callers can pipeline the output, e.g. into a streaming
+/// writer, without materializing every page. Pages are processed as in
+/// [`run_job`], except for its document-level steps: forms are not
+/// flattened, `incremental_from` and `max_output_pages` are not applied, and
+/// nothing is written. Blank pages dropped by `blank_page_action` are not
+/// yielded. Rasterized pages are rendered from `config.input_path`.
+///
+/// A setup error (an out-of-range page override, unreadable overlay
+/// redactions, ...) is yielded as the first item. The iterator ends after
+/// the first error.
+pub fn process_pages_iter<'a>(
+    reader: &'a PdfReader,
+    config: &'a JobConfig,
+) -> impl Iterator<Item = crate::error::Result<ProcessedPage>> + 'a {
+    let (state, setup_error) = match PageIterState::new(reader, config) {
+        Ok(state) => (Some(state), None),
+        Err(e) => (None, Some(e)),
+    };
+    PageIter {
+        reader,
+        config,
+        state,
+        setup_error,
+        next_page: 1,
+    }
+}
+
+/// Iterator returned by [`process_pages_iter`].
+struct PageIter<'a> {
+    reader: &'a PdfReader,
+    config: &'a JobConfig,
+    /// `None` once the iterator has failed.
+    state: Option<PageIterState>,
+    setup_error: Option<PdfMaskError>,
+    /// Next 1-based page to process.
+    next_page: u32,
+}
+
+/// Job-wide inputs prepared once and consumed page by page, by [`run_job`]
+/// and [`PageIter`].
+struct PageIterState {
+    font_substitutions: FontSubstitutions,
+    redactions: std::collections::HashMap<u32, Vec<BBox>>,
+    preserve_regions: std::collections::HashMap<u32, Vec<BBox>>,
+    cache_store: Option<CacheStore>,
+    #[cfg(feature = "mrc")]
+    mrc_config: MrcConfig,
+    /// File rasterized pages are rendered from.
+    #[cfg(feature = "mrc")]
+    render_path: PathBuf,
+    /// Source key -> 1-based page of the prior output (`incremental_from`).
+    /// Pages with a matching key are reused instead of masked.
+    prior_keys: std::collections::HashMap<String, u32>,
+    /// Analysis results of the pages prepared so far.
+    records: PageRecords,
+}
+
+/// Per-page analysis results [`run_job`] needs after every page is processed,
+/// keyed by 0-based page.
+#[derive(Default)]
+struct PageRecords {
+    /// Redaction marks in output page space (see [`redaction_marks`]), when
+    /// `redaction_mark` or `verify_redaction` is set.
+    redaction_marks: std::collections::HashMap<u32, Vec<BBox>>,
+    preserved_regions: std::collections::HashMap<u32, PreservedRegions>,
+    /// Overlay redactions in input page space, when `redaction_manifest` is set.
+    overlay_rects: std::collections::HashMap<u32, Vec<BBox>>,
+}
+
+impl PageRecords {
+    fn record(&mut self, cs: &AnalysisResult, config: &JobConfig) {
+        if !cs.redactions.is_empty() {
+            if config.redaction_mark.is_some() || config.verify_redaction {
+                self.redaction_marks
+                    .insert(cs.page_idx, redaction_marks(cs));
+            }
+            if config.redaction_manifest.is_some() {
+                self.overlay_rects
+                    .insert(cs.page_idx, cs.redactions.clone());
             }
         }
-        all_pages.sort_by_key(|p| p.page_index);
-        all_pages
-    };
+        if !cs.preserved.is_empty() {
+            self.preserved_regions.insert(
+                cs.page_idx,
+                PreservedRegions {
+                    rects: cs.preserved.clone(),
+                    xobjects: cs.preserved_xobjects.clone(),
+                    origin: cs.page_origin_pts,
+                },
+            );
+        }
+    }
+}
 
-    let pages_processed = successful_pages.len();
+/// A page after the sequential steps of [`PageIterState::prepare`].
+enum PreparedPage {
+    /// The finished page, or `None` when it is dropped.
+    Done(Option<ProcessedPage>),
+    /// A rendered page still to compose, with its source key.
+    #[cfg(feature = "mrc")]
+    Rendered(Box<RenderResult>, Option<String>),
+}
 
-    // Phase D: PDF output assembly
-    debug!("phase D: PDF assembly");
-    phase_d_write(&reader, &successful_pages, config, pages_processed)
+impl Iterator for PageIter<'_> {
+    type Item = crate::error::Result<ProcessedPage>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.setup_error.take() {
+            return Some(Err(e));
+        }
+        let state = self.state.as_mut()?;
+        while self.next_page <= self.reader.page_count() {
+            let page_num = self.next_page;
+            self.next_page += 1;
+            match state.process(self.reader, self.config, page_num) {
+                Ok(Some(page)) => return Some(Ok(page)),
+                Ok(None) => continue,
+                Err(e) => {
+                    self.state = None;
+                    return Some(Err(e));
+                }
+            }
+        }
+        None
+    }
 }
 
-/// Phase A: Content stream analysis (sequential).
-///
-/// Reads content streams, image streams, and fonts for all non-Skip pages.
-fn phase_a_analyze(
-    reader: &PdfReader,
-    page_modes: &[(u32, ColorMode)],
-) -> crate::error::Result<Vec<AnalysisResult>> {
-    let non_skip: Vec<(u32, ColorMode)> = page_modes
-        .iter()
-        .filter(|(_, mode)| *mode != ColorMode::Skip)
-        .copied()
-        .collect();
+impl PageIterState {
+    fn new(reader: &PdfReader, config: &JobConfig) -> crate::error::Result<Self> {
+        let page_count = reader.page_count();
+        validate_page_overrides(config, page_count)?;
+        Ok(Self {
+            font_substitutions: font_substitutions(config)?,
+            redactions: overlay_redaction_map(reader, config)?,
+            preserve_regions: preserve_region_map(config, page_count)?,
+            cache_store: config.cache_dir.as_ref().map(CacheStore::new),
+            #[cfg(feature = "mrc")]
+            mrc_config: mrc_config(config),
+            #[cfg(feature = "mrc")]
+            render_path: config.input_path.clone(),
+            prior_keys: std::collections::HashMap::new(),
+            records: PageRecords::default(),
+        })
+    }
 
-    let mut content_streams: Vec<AnalysisResult> = Vec::new();
-    for &(page_idx, mode) in &non_skip {
-        let page_num = page_idx + 1;
-        let content = reader.page_content_stream(page_num)?;
-        let image_streams = if matches!(mode, ColorMode::Rgb | ColorMode::Grayscale | ColorMode::Bw)
-        {
-            let streams = reader.page_image_streams(page_num)?;
-            if streams.is_empty() {
-                None
-            } else {
-                Some(streams)
+    /// Process `page_num` (1-based). `None` means the page is dropped.
+    fn process(
+        &mut self,
+        reader: &PdfReader,
+        config: &JobConfig,
+        page_num: u32,
+    ) -> crate::error::Result<Option<ProcessedPage>> {
+        match self.prepare(reader, config, page_num)? {
+            PreparedPage::Done(page) => Ok(page),
+            #[cfg(feature = "mrc")]
+            PreparedPage::Rendered(pd, source_key) => {
+                let page = self.compose(&pd, config)?;
+                self.finish(&pd, page, source_key, config).map(Some)
             }
-        } else {
-            None
-        };
-        let fonts = if matches!(mode, ColorMode::Rgb | ColorMode::Grayscale | ColorMode::Bw) {
-            crate::pdf::font::parse_page_fonts(reader.document(), page_num).ok()
-        } else {
-            None
-        };
+        }
+    }
 
-        let (page_width_pts, page_height_pts) = reader.page_dimensions(page_num)?;
+    /// Run the sequential steps of `page_num` (1-based): content stream
+    /// analysis, blank page detection, reuse of a prior page, text-to-outlines
+    /// conversion and, failing that, rendering.
+    fn prepare(
+        &mut self,
+        reader: &PdfReader,
+        config: &JobConfig,
+        page_num: u32,
+    ) -> crate::error::Result<PreparedPage> {
+        let page_idx = page_num - 1;
+        let mode = page_mode(config, page_num);
+        if mode == ColorMode::Skip {
+            return Ok(PreparedPage::Done(Some(ProcessedPage::new(
+                page_idx,
+                PageOutput::Skip(SkipData {
+                    page_index: page_idx,
+                }),
+                String::new(),
+                None,
+            ))));
+        }
 
-        content_streams.push(AnalysisResult {
+        let cs = analyze_page(
+            reader,
             page_idx,
             mode,
-            content,
-            image_streams,
-            fonts,
-            page_width_pts,
-            page_height_pts,
+            config,
+            &self.font_substitutions,
+            &mut self.redactions,
+            &mut self.preserve_regions,
+        )?;
+        self.records.record(&cs, config);
+        if (config.blank_page_action != BlankPageAction::Keep || config.minimize_blank)
+            && let Some(blank) = blank_content(&cs, config.blank_page_action)
+        {
+            return Ok(PreparedPage::Done(kept_blank_page(
+                blank,
+                config.blank_page_action,
+            )));
+        }
+
+        // Blank pages are written from scratch and carry no source key
+        let source_key = source_page_key(reader, &cs, config)?;
+        if let Some(&prior_page_num) = source_key.as_ref().and_then(|key| self.prior_keys.get(key))
+        {
+            debug!(
+                page = page_idx,
+                prior_page_num, "incremental: reusing the prior output's page"
+            );
+            return Ok(PreparedPage::Done(Some(ProcessedPage::new(
+                page_idx,
+                PageOutput::Reused(ReusedData {
+                    page_index: page_idx,
+                    prior_page_num,
+                }),
+                String::new(),
+                None,
+            ))));
+        }
+
+        if let Some(mut page) = outline_page(&cs, config, self.cache_store.as_ref())? {
+            page.source_key = source_key;
+            return Ok(PreparedPage::Done(Some(page)));
+        }
+        self.render(cs, source_key, config)
+    }
+
+    /// Rasterize a page that could not be converted without rendering.
+    /// A bitmap found blank is finished as [`kept_blank_page`] gives it.
+    #[cfg(feature = "mrc")]
+    fn render(
+        &self,
+        cs: AnalysisResult,
+        source_key: Option<String>,
+        config: &JobConfig,
+    ) -> crate::error::Result<PreparedPage> {
+        Ok(match render_analyzed_page(cs, config, &self.render_path)? {
+            RenderedPage::Bitmap(pd) => PreparedPage::Rendered(pd, source_key),
+            RenderedPage::Blank(blank) => {
+                PreparedPage::Done(kept_blank_page(blank, config.blank_page_action))
+            }
+        })
+    }
+
+    #[cfg(not(feature = "mrc"))]
+    fn render(
+        &self,
+        cs: AnalysisResult,
+        _source_key: Option<String>,
+        _config: &JobConfig,
+    ) -> crate::error::Result<PreparedPage> {
+        Err(PdfMaskError::render(format!(
+            "text-to-outlines conversion failed for page {} and MRC fallback \
+             is unavailable (compiled without 'mrc' feature). Ensure fonts are \
+             embedded in the source PDF, or rebuild with `cargo build --features mrc`.",
+            cs.page_idx + 1
+        )))
+    }
+
+    /// Compose a rendered page at the job DPI. This is the only step
+    /// [`run_job`] runs in parallel.
+    #[cfg(feature = "mrc")]
+    fn compose(
+        &self,
+        pd: &RenderResult,
+        config: &JobConfig,
+    ) -> crate::error::Result<ProcessedPage> {
+        compose_rendered_page(
+            pd,
+            &pd.bitmap,
+            config.dpi,
+            config,
+            &self.mrc_config,
+            self.cache_store.as_ref(),
+        )
+    }
+
+    /// Finish a composed page: retry it at `retry_empty_at_dpi` when
+    /// segmentation found no text ([`retry_empty_page`]) and attach its source key.
+    #[cfg(feature = "mrc")]
+    fn finish(
+        &self,
+        pd: &RenderResult,
+        page: ProcessedPage,
+        source_key: Option<String>,
+        config: &JobConfig,
+    ) -> crate::error::Result<ProcessedPage> {
+        let mut page = retry_empty_page(
+            pd,
+            page,
+            config,
+            &self.mrc_config,
+            &self.render_path,
+            self.cache_store.as_ref(),
+        )?;
+        page.source_key = source_key;
+        Ok(page)
+    }
+
+    /// Log the overlay redactions and preserve regions no prepared page took,
+    /// which belong to Skip pages.
+    fn warn_unapplied_regions(&self) {
+        for page_num in self.redactions.keys() {
+            warn!(
+                page = page_num,
+                "overlay redactions on a skip page are not applied"
+            );
+        }
+        for page_num in self.preserve_regions.keys() {
+            debug!(
+                page = page_num,
+                "preserve regions on a skip page are ignored (the page is kept as is)"
+            );
+        }
+    }
+}
+
+/// The output page for a detected blank page, or `None` when it is dropped.
+fn kept_blank_page(blank: BlankData, action: BlankPageAction) -> Option<ProcessedPage> {
+    (action != BlankPageAction::Drop).then(|| {
+        ProcessedPage::new(
+            blank.page_index,
+            PageOutput::Blank(blank),
+            String::new(),
+            None,
+        )
+    })
+}
+
+/// Check that every per-page override names a page of the document.
+fn validate_page_overrides(config: &JobConfig, page_count: u32) -> crate::error::Result<()> {
+    for &page_num in config
+        .color_mode_overrides
+        .keys()
+        .chain(config.preserve_images_overrides.keys())
+        .chain(config.text_to_outlines_overrides.keys())
+        .chain(config.mirror_pages.iter())
+    {
+        if page_num < 1 || page_num > page_count {
+            return Err(PdfMaskError::pdf_read(format!(
+                "override page {} out of range (document has {} pages)",
+                page_num, page_count
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Color mode of `page_num` (1-based), after per-page overrides.
+fn page_mode(config: &JobConfig, page_num: u32) -> ColorMode {
+    config
+        .color_mode_overrides
+        .get(&page_num)
+        .copied()
+        .unwrap_or(config.default_color_mode)
+}
+
+/// Group `config.preserve_regions` by 1-based page, normalizing each rectangle.
+fn preserve_region_map(
+    config: &JobConfig,
+    page_count: u32,
+) -> crate::error::Result<std::collections::HashMap<u32, Vec<BBox>>> {
+    let mut preserve_regions: std::collections::HashMap<u32, Vec<BBox>> =
+        std::collections::HashMap::new();
+    for region in &config.preserve_regions {
+        if region.page < 1 || region.page > page_count {
+            return Err(PdfMaskError::pdf_read(format!(
+                "preserve region page {} out of range (document has {} pages)",
+                region.page, page_count
+            )));
+        }
+        let [x0, y0, x1, y1] = region.rect;
+        preserve_regions.entry(region.page).or_default().push(BBox {
+            x_min: x0.min(x1),
+            y_min: y0.min(y1),
+            x_max: x0.max(x1),
+            y_max: y0.max(y1),
         });
     }
-    Ok(content_streams)
+    Ok(preserve_regions)
 }
 
-/// Phase A2: Text-to-outlines conversion.
+/// Read `config.overlay_redactions` into page-space rectangles keyed by
+/// 1-based page. Pages beyond the input's last page are dropped.
+fn overlay_redaction_map(
+    reader: &PdfReader,
+    config: &JobConfig,
+) -> crate::error::Result<std::collections::HashMap<u32, Vec<BBox>>> {
+    let page_count = reader.page_count();
+    let mut redactions = match &config.overlay_redactions {
+        Some(path) => read_overlay_redactions(path)?,
+        None => std::collections::HashMap::new(),
+    };
+    redactions.retain(|&page_num, _| {
+        let in_range = page_num <= page_count;
+        if !in_range {
+            warn!(
+                page = page_num,
+                "overlay redactions beyond the input's last page are ignored"
+            );
+        }
+        in_range
+    });
+    if config.overlay_coordinates == OverlayCoordinates::Visual {
+        for (&page_num, rects) in redactions.iter_mut() {
+            let orientation = reader.page_orientation(page_num)?;
+            for rect in rects.iter_mut() {
+                *rect = orientation.from_visual(rect);
+            }
+        }
+    }
+    Ok(redactions)
+}
+
+/// Load the font substitutions used for non-embedded fonts.
+fn font_substitutions(config: &JobConfig) -> crate::error::Result<FontSubstitutions> {
+    let mut font_substitutions =
+        FontSubstitutions::load(&config.font_substitutions, config.font_fallback.as_deref())?;
+    font_substitutions.set_use_system_fonts(config.use_system_fonts);
+    Ok(font_substitutions)
+}
+
+/// Build the cache settings a page is processed with at `dpi`.
+fn cache_settings(config: &JobConfig, mode: ColorMode, dpi: u32) -> CacheSettings {
+    CacheSettings {
+        dpi,
+        fg_dpi: dpi,
+        bg_quality: config.bg_quality,
+        fg_quality: config.fg_quality,
+        color_mode: mode,
+        jbig2: config.jbig2,
+        jpeg_escalation: config.jpeg_escalation,
+        image_max_dpi: config.image_max_dpi,
+        image_class_dpi: config.image_class_dpi,
+        normalize_images_to: config.normalize_images_to,
+        resample_filter: config.resample_filter,
+        max_image_pixels: config.max_image_pixels,
+        jpeg_size_mismatch: config.jpeg_size_mismatch,
+        output_colorspace: config.output_colorspace,
+        text_antialias: config.text_antialias,
+        line_removal: config.line_removal,
+        width_source: config.width_source,
+        merge_white_fills: config.merge_white_fills,
+        font_substitutions: config.font_substitutions.clone(),
+        font_fallback: config.font_fallback.clone(),
+        use_system_fonts: config.use_system_fonts,
+    }
+}
+
+/// Compute the key recorded on each masked page for later incremental runs,
+/// when `record_source_keys` or `incremental_from` needs it.
 ///
-/// Attempts text-to-outlines for eligible pages. Pages that fail or are
-/// ineligible are returned in `needs_rendering` for bitmap-based processing.
-fn phase_a2_text_to_outlines(
-    content_streams: Vec<AnalysisResult>,
+/// The key covers the page's content stream, its resources (with the image,
+/// font and form streams they reference), its page boxes, the settings it is
+/// masked with and the options it is written with. Pages to un-mirror or
+/// redact, with preserved regions, or with `preserve_images`/`text_to_outlines`
+/// turned off, get no key: their output also depends on inputs the key does
+/// not cover.
+fn source_page_key(
+    reader: &PdfReader,
+    cs: &AnalysisResult,
     config: &JobConfig,
-    cache_store: Option<&CacheStore>,
-) -> crate::error::Result<(Vec<ProcessedPage>, Vec<AnalysisResult>)> {
-    let mut outlines_pages: Vec<ProcessedPage> = Vec::new();
-    let mut needs_rendering: Vec<AnalysisResult> = Vec::new();
-
-    for cs in content_streams {
-        let eligible = matches!(
-            cs.mode,
-            ColorMode::Rgb | ColorMode::Grayscale | ColorMode::Bw
-        ) && cs.fonts.is_some();
-
-        if eligible {
-            let cache_settings = CacheSettings {
-                dpi: config.dpi,
-                fg_dpi: config.dpi,
-                bg_quality: config.bg_quality,
-                fg_quality: config.fg_quality,
-                color_mode: cs.mode,
-            };
-            let params = ProcessPageOutlinesParams {
-                page_index: cs.page_idx,
-                content_stream: &cs.content,
-                cache_settings: &cache_settings,
-                cache_store,
-                pdf_path: &config.input_path,
-                image_streams: cs.image_streams.as_ref(),
-                fonts: cs.fonts.as_ref().unwrap(),
-                page_width_pts: cs.page_width_pts,
-                page_height_pts: cs.page_height_pts,
+) -> crate::error::Result<Option<String>> {
+    let keyed = (config.record_source_keys || config.incremental_from.is_some())
+        && !cs.unmirror
+        && cs.redactions.is_empty()
+        && cs.preserved.is_empty()
+        && config.text_to_outlines_on(cs.page_idx + 1);
+    if !keyed {
+        return Ok(None);
+    }
+    let resources = reader.page_resource_bytes(cs.page_idx + 1)?;
+    let settings = cache_settings(config, cs.mode, config.dpi);
+    let writer_settings = WriterSettings {
+        foreground_mask: config.foreground_mask,
+        interpolate_output: config.interpolate_output,
+        tag_srgb: config.tag_srgb,
+        text_region_padding: config.text_region_padding,
+        simplify_content: config.simplify_content,
+        split_content_bytes: config.split_content_bytes,
+        passthrough_full_page_images: config.passthrough_full_page_images,
+        retry_empty_at_dpi: config.retry_empty_at_dpi,
+    };
+    Ok(Some(compute_source_key(
+        &cs.content,
+        &resources,
+        &settings,
+        &writer_settings,
+        &config.input_path,
+        cs.page_idx,
+    )))
+}
+
+/// Map a page's redactions into the space of its output page.
+///
+/// Redacted pages are replaced by their rasterized bitmap, whose page has the
+/// MediaBox origin at (0, 0) and is un-mirrored like the bitmap.
+fn redaction_marks(cs: &AnalysisResult) -> Vec<BBox> {
+    let (origin_x, origin_y) = cs.page_origin_pts;
+    cs.redactions
+        .iter()
+        .map(|r| {
+            let (x_min, x_max) = if cs.unmirror {
+                (
+                    cs.page_width_pts - (r.x_max - origin_x),
+                    cs.page_width_pts - (r.x_min - origin_x),
+                )
+            } else {
+                (r.x_min - origin_x, r.x_max - origin_x)
             };
-            let result = params.process();
-            match result {
-                Ok(page) => {
-                    debug!(page = cs.page_idx, "text-to-outlines succeeded");
-                    outlines_pages.push(page);
+            BBox {
+                x_min,
+                y_min: r.y_min - origin_y,
+                x_max,
+                y_max: r.y_max - origin_y,
+            }
+        })
+        .collect()
+}
+
+/// Collect what [`verify_redaction`] checks on each output page.
+///
+/// Output pages are numbered in `successful_pages` order. Overlay redactions
+/// come from `redaction_marks` (already in output page space); images
+/// whitened under white fills keep the input page space on text-masked pages
+/// and are checked against their source image XObjects.
+fn redaction_checks(
+    reader: &PdfReader,
+    successful_pages: &[ProcessedPage],
+    redaction_marks: &std::collections::HashMap<u32, Vec<BBox>>,
+) -> crate::error::Result<Vec<RedactionCheck>> {
+    let mut checks = Vec::new();
+    for (output_idx, page) in successful_pages.iter().enumerate() {
+        let mut check = RedactionCheck {
+            output_page: output_idx as u32 + 1,
+            regions: redaction_marks
+                .get(&page.page_index)
+                .cloned()
+                .unwrap_or_default(),
+            original_images: Vec::new(),
+        };
+        if let PageOutput::TextMasked(data) = &page.output {
+            let mut source_images = None;
+            for (name, modification) in &data.modified_images {
+                if modification.redacted_regions.is_empty() {
                     continue;
                 }
-                Err(e) => {
-                    debug!(page = cs.page_idx, reason = %e, "text-to-outlines failed, falling back to rendering");
-                    needs_rendering.push(cs);
+                check
+                    .regions
+                    .extend(modification.redacted_regions.iter().cloned());
+                if source_images.is_none() {
+                    source_images = Some(reader.page_image_streams(page.page_index + 1)?);
+                }
+                if let Some(stream) = source_images.as_ref().and_then(|s| s.get(name)) {
+                    check.original_images.push(stream.clone());
                 }
             }
+        }
+        if !check.regions.is_empty() {
+            checks.push(check);
+        }
+    }
+    Ok(checks)
+}
+
+/// The blank page `cs` becomes when its content stream paints nothing.
+fn blank_content(cs: &AnalysisResult, action: BlankPageAction) -> Option<BlankData> {
+    if !is_blank_content(&cs.content) {
+        return None;
+    }
+    debug!(page = cs.page_idx, ?action, "blank page detected (content)");
+    Some(BlankData {
+        page_index: cs.page_idx,
+        page_width_pts: cs.page_width_pts,
+        page_height_pts: cs.page_height_pts,
+        fill_white: action == BlankPageAction::Keep,
+    })
+}
+
+/// Analyze one non-Skip page: read its content stream, image streams and
+/// fonts, taking its overlay redactions and preserve regions out of the maps.
+///
+/// Overlay redactions and preserve regions are keyed by 1-based page; those
+/// left over belong to Skip pages and are not applied. Non-embedded fonts are
+/// resolved with `font_substitutions` ahead of system fonts.
+fn analyze_page(
+    reader: &PdfReader,
+    page_idx: u32,
+    mode: ColorMode,
+    config: &JobConfig,
+    font_substitutions: &FontSubstitutions,
+    redactions: &mut std::collections::HashMap<u32, Vec<BBox>>,
+    preserve_regions: &mut std::collections::HashMap<u32, Vec<BBox>>,
+) -> crate::error::Result<AnalysisResult> {
+    let page_num = page_idx + 1;
+    let content = reader.page_content_stream_limited(page_num, config.max_content_stream_bytes)?;
+    let image_streams = if matches!(mode, ColorMode::Rgb | ColorMode::Grayscale | ColorMode::Bw) {
+        let streams = reader.page_image_streams(page_num)?;
+        if streams.is_empty() {
+            None
+        } else {
+            Some(streams)
+        }
+    } else {
+        None
+    };
+    let matte_smasks = if image_streams.is_some() {
+        Some(reader.page_matte_smasks(page_num)?).filter(|smasks| !smasks.is_empty())
+    } else {
+        None
+    };
+    let fill_resources = if matches!(mode, ColorMode::Rgb | ColorMode::Grayscale | ColorMode::Bw) {
+        Some(reader.page_fill_resources(page_num)?)
+    } else {
+        None
+    };
+    let fonts = if matches!(mode, ColorMode::Rgb | ColorMode::Grayscale | ColorMode::Bw) {
+        parse_page_fonts_with_substitutions(reader.document(), page_num, font_substitutions).ok()
+    } else {
+        None
+    };
+
+    let (page_width_pts, page_height_pts) = reader.page_dimensions(page_num)?;
+    let page_origin_pts = reader.page_origin(page_num)?;
+    #[cfg(feature = "mrc")]
+    let user_unit = reader.page_user_unit(page_num)?;
+    let unmirror = config.mirror_pages.contains(&page_num)
+        || (config.fix_mirror && is_horizontally_mirrored(&content).unwrap_or(false));
+    if unmirror {
+        debug!(page = page_idx, "page will be un-mirrored");
+    }
+    let page_redactions = redactions.remove(&page_num).unwrap_or_default();
+    let (preserved, preserved_xobjects) = safe_preserved_regions(
+        page_num,
+        preserve_regions.remove(&page_num).unwrap_or_default(),
+        &content,
+        fill_resources.as_ref(),
+        &page_redactions,
+        unmirror,
+    )?;
+    if contains_shading(&content).unwrap_or(false) {
+        // テキストマスク・アウトライン化ではシェーディングは元のまま残るが、
+        // 全面ラスタライズされるページではグラデーションが劣化する
+        if mode == ColorMode::Bw || unmirror || !page_redactions.is_empty() {
+            warn!(
+                page = page_num,
+                "page paints shadings (sh); full-page MRC rasterizes them and gradients may band"
+            );
         } else {
-            needs_rendering.push(cs);
+            debug!(page = page_num, "page paints shadings (sh)");
         }
     }
-    Ok((outlines_pages, needs_rendering))
+
+    Ok(AnalysisResult {
+        page_idx,
+        mode,
+        content,
+        image_streams,
+        matte_smasks,
+        fill_resources,
+        fonts,
+        page_width_pts,
+        page_height_pts,
+        page_origin_pts,
+        #[cfg(feature = "mrc")]
+        user_unit,
+        unmirror,
+        redactions: page_redactions,
+        preserved,
+        preserved_xobjects,
+    })
 }
 
-/// Phase B+C: Page rendering (sequential) and MRC processing (rayon parallel).
+/// Drop preserved regions whose source content must not reach the output,
+/// and list the page-level XObjects to copy with the rest.
 ///
-/// Renders pages that need bitmaps, then runs MRC composition in parallel.
-/// Skip pages are appended with no processing.
-#[cfg(feature = "mrc")]
-fn phase_bc_render_and_mrc(
-    needs_rendering: Vec<AnalysisResult>,
-    outlines_pages: Vec<ProcessedPage>,
-    page_modes: &[(u32, ColorMode)],
+/// The preserved content is copied unredacted, so pages with overlay
+/// redactions or white fills painted over an XObject (whose image data the
+/// copy could carry) keep no regions at all, and regions overlapping a form
+/// XObject that draws text are dropped. Pages to un-mirror keep none either,
+/// since the source content is drawn in its mirrored orientation. Only
+/// XObjects placed entirely inside a kept region are listed.
+fn safe_preserved_regions(
+    page_num: u32,
+    rects: Vec<BBox>,
+    content: &[u8],
+    fill_resources: Option<&FillResources>,
+    redactions: &[BBox],
+    unmirror: bool,
+) -> crate::error::Result<(Vec<BBox>, Vec<String>)> {
+    if rects.is_empty() {
+        return Ok((rects, Vec::new()));
+    }
+    if unmirror {
+        warn!(
+            page = page_num,
+            "preserve regions are not applied to un-mirrored pages"
+        );
+        return Ok((Vec::new(), Vec::new()));
+    }
+    let empty_resources = FillResources::default();
+    let resources = fill_resources.unwrap_or(&empty_resources);
+    let placements = extract_xobject_placements_with_resources(content, resources)?;
+    let scrubbed = extract_white_fill_rects_with_resources(content, resources)?
+        .iter()
+        .any(|wr| placements.iter().any(|p| bbox_overlaps(wr, &p.bbox)));
+    if !redactions.is_empty() || scrubbed {
+        warn!(
+            page = page_num,
+            "preserve regions are not applied to pages with redactions"
+        );
+        return Ok((Vec::new(), Vec::new()));
+    }
+    let mut text_forms: Vec<BBox> = Vec::new();
+    for placement in &placements {
+        let form = placement
+            .form_path
+            .iter()
+            .try_fold(&resources.forms, |forms, name| {
+                forms.get(name).map(|form| &form.resources.forms)
+            })
+            .and_then(|forms| forms.get(&placement.name));
+        if let Some(form) = form
+            && contains_text(&form.content)?
+        {
+            text_forms.push(placement.bbox.clone());
+        }
+    }
+    let rects: Vec<BBox> = rects
+        .into_iter()
+        .filter(|rect| {
+            let overlaps = text_forms.iter().any(|r| bbox_overlaps(r, rect));
+            if overlaps {
+                warn!(
+                    page = page_num,
+                    "preserve region overlapping text in a form is not applied"
+                );
+            }
+            !overlaps
+        })
+        .collect();
+    // A name drawn both inside and outside the regions is not copied, since
+    // every use of it would be drawn unclipped outside the region, leaking
+    // vector content.
+    let inside = |bbox: &BBox| {
+        rects.iter().any(|rect| {
+            bbox.x_min >= rect.x_min
+                && bbox.y_min >= rect.y_min
+                && bbox.x_max <= rect.x_max
+                && bbox.y_max <= rect.y_max
+        })
+    };
+    let page_level = || placements.iter().filter(|p| p.form_path.is_empty());
+    let mut xobjects: Vec<String> = Vec::new();
+    for placement in page_level() {
+        if !xobjects.contains(&placement.name)
+            && page_level()
+                .filter(|p| p.name == placement.name)
+                .all(|p| inside(&p.bbox))
+        {
+            xobjects.push(placement.name.clone());
+        }
+    }
+    Ok((rects, xobjects))
+}
+
+/// Process one analyzed page without rendering it (text-to-outlines, or
+/// [`passthrough_full_page_image`]). `None` means the page needs rendering.
+///
+/// Pages that fail or are ineligible are left for bitmap-based processing.
+/// Pages to un-mirror or redact, or with preserved regions, are always
+/// rendered, since outlines keep the original page content. So are pages
+/// with text-to-outlines turned off ([`JobConfig::text_to_outlines_on`]).
+fn outline_page(
+    cs: &AnalysisResult,
     config: &JobConfig,
     cache_store: Option<&CacheStore>,
-) -> crate::error::Result<Vec<ProcessedPage>> {
-    // --- Phase B: Page rendering (sequential, only pages needing bitmap) ---
-    let mut pages_data: Vec<RenderResult> = Vec::new();
-    for cs in needs_rendering {
-        let bitmap = render_page(&config.input_path, cs.page_idx, config.dpi)?;
-        pages_data.push(RenderResult {
-            page_idx: cs.page_idx,
-            mode: cs.mode,
-            bitmap,
-            content: cs.content,
-            image_streams: cs.image_streams,
-            page_width_pts: cs.page_width_pts,
-            page_height_pts: cs.page_height_pts,
-        });
+) -> crate::error::Result<Option<ProcessedPage>> {
+    #[cfg(feature = "mrc")]
+    if config.passthrough_full_page_images
+        && config.preserve_images_on(cs.page_idx + 1)
+        && cs.preserved.is_empty()
+        && let Some(page) = passthrough_full_page_image(cs, config)?
+    {
+        return Ok(Some(page));
+    }
+
+    let eligible = matches!(
+        cs.mode,
+        ColorMode::Rgb | ColorMode::Grayscale | ColorMode::Bw
+    ) && config.text_to_outlines_on(cs.page_idx + 1)
+        && cs.fonts.is_some()
+        && !cs.unmirror
+        && cs.redactions.is_empty()
+        && cs.preserved.is_empty();
+    let Some(fonts) = cs.fonts.as_ref().filter(|_| eligible) else {
+        return Ok(None);
+    };
+
+    let cache_settings = cache_settings(config, cs.mode, config.dpi);
+    let params = ProcessPageOutlinesParams {
+        page_index: cs.page_idx,
+        content_stream: &cs.content,
+        cache_settings: &cache_settings,
+        cache_store,
+        pdf_path: &config.input_path,
+        image_streams: cs.image_streams.as_ref(),
+        matte_smasks: cs.matte_smasks.as_ref(),
+        fill_resources: cs.fill_resources.as_ref(),
+        fonts,
+        page_width_pts: cs.page_width_pts,
+        page_height_pts: cs.page_height_pts,
+    };
+    match params.process() {
+        Ok(page) => {
+            debug!(page = cs.page_idx, "text-to-outlines succeeded");
+            Ok(Some(page))
+        }
+        Err(e) => {
+            debug!(page = cs.page_idx, reason = %e, "text-to-outlines failed, falling back to rendering");
+            Ok(None)
+        }
     }
+}
 
-    // --- Phase C: MRC processing (rayon parallel, outlines already handled) ---
-    let mrc_config = MrcConfig {
+/// MRC layer settings of a job.
+#[cfg(feature = "mrc")]
+fn mrc_config(config: &JobConfig) -> MrcConfig {
+    MrcConfig {
         bg_quality: config.bg_quality,
         fg_quality: config.fg_quality,
+        jbig2: config.jbig2,
+        jpeg_escalation: config.jpeg_escalation,
+        output_colorspace: config.output_colorspace,
+        line_removal: config.line_removal,
+    }
+}
+
+/// A page after rendering: its bitmap, or the blank page it turned out to be.
+#[cfg(feature = "mrc")]
+enum RenderedPage {
+    Bitmap(Box<RenderResult>),
+    Blank(BlankData),
+}
+
+/// Render one analyzed page at the job DPI (Phase B).
+#[cfg(feature = "mrc")]
+fn render_analyzed_page(
+    cs: AnalysisResult,
+    config: &JobConfig,
+    render_path: &Path,
+) -> crate::error::Result<RenderedPage> {
+    let despeckle_px = (config.dpi as f64 * BLANK_DESPECKLE_INCHES).round() as u32;
+    let render_options = RenderOptions {
+        text_antialias: config.text_antialias,
+        user_unit: cs.user_unit,
     };
+    let bitmap = render_page_with_options(render_path, cs.page_idx, config.dpi, &render_options)?;
+    // スキャン画像のみのページ等はレンダリング結果で白紙判定する
+    if config.blank_page_action != BlankPageAction::Keep && is_blank_bitmap(&bitmap, despeckle_px) {
+        debug!(page = cs.page_idx, "blank page detected (bitmap)");
+        return Ok(RenderedPage::Blank(BlankData {
+            page_index: cs.page_idx,
+            page_width_pts: cs.page_width_pts,
+            page_height_pts: cs.page_height_pts,
+            fill_white: false,
+        }));
+    }
+    Ok(RenderedPage::Bitmap(Box::new(RenderResult {
+        page_idx: cs.page_idx,
+        mode: cs.mode,
+        bitmap,
+        content: cs.content,
+        image_streams: cs.image_streams,
+        matte_smasks: cs.matte_smasks,
+        fill_resources: cs.fill_resources,
+        fonts: cs.fonts,
+        page_width_pts: cs.page_width_pts,
+        page_height_pts: cs.page_height_pts,
+        page_origin_pts: cs.page_origin_pts,
+        user_unit: cs.user_unit,
+        unmirror: cs.unmirror,
+        redactions: cs.redactions,
+        preserved: cs.preserved,
+    })))
+}
 
-    let processed: Vec<crate::error::Result<ProcessedPage>> = pages_data
-        .par_iter()
-        .map(|pd| {
-            let cache_settings = CacheSettings {
-                dpi: config.dpi,
-                fg_dpi: config.dpi,
-                bg_quality: config.bg_quality,
-                fg_quality: config.fg_quality,
-                color_mode: pd.mode,
-            };
-            let params = ProcessPageParams {
-                page_index: pd.page_idx,
-                bitmap: &pd.bitmap,
-                content_stream: &pd.content,
-                mrc_config: &mrc_config,
-                cache_settings: &cache_settings,
-                cache_store,
-                pdf_path: &config.input_path,
-                image_streams: pd.image_streams.as_ref(),
-                page_width_pts: pd.page_width_pts,
-                page_height_pts: pd.page_height_pts,
-            };
-            params.process()
-        })
+/// Compose a rendered page from `bitmap`, rasterized at `dpi` (Phase C).
+#[cfg(feature = "mrc")]
+fn compose_rendered_page(
+    pd: &RenderResult,
+    bitmap: &image::DynamicImage,
+    dpi: u32,
+    config: &JobConfig,
+    mrc_config: &MrcConfig,
+    cache_store: Option<&CacheStore>,
+) -> crate::error::Result<ProcessedPage> {
+    // Redactions are painted in the orientation they were marked (the page as
+    // drawn), before un-mirroring. Both move or destroy pixels, so the page
+    // must be replaced by the composed bitmap.
+    let preprocess = |image: &mut image::RgbaImage| {
+        paint_redactions(
+            image,
+            &pd.redactions,
+            pd.page_width_pts,
+            pd.page_height_pts,
+            pd.page_origin_pts,
+        );
+        if pd.unmirror {
+            image::imageops::flip_horizontal_in_place(image);
+        }
+    };
+    // Preserved regions are drawn over the whole rasterized page
+    let replace_content = pd.unmirror
+        || !pd.redactions.is_empty()
+        || !pd.preserved.is_empty()
+        || !config.preserve_images_on(pd.page_idx + 1);
+    let preprocess_tag = preprocess_tag(pd);
+    let cache_settings = cache_settings(config, pd.mode, dpi);
+    let params = ProcessPageParams {
+        page_index: pd.page_idx,
+        bitmap,
+        content_stream: &pd.content,
+        mrc_config,
+        cache_settings: &cache_settings,
+        cache_store,
+        pdf_path: &config.input_path,
+        image_streams: pd.image_streams.as_ref(),
+        matte_smasks: pd.matte_smasks.as_ref(),
+        fill_resources: pd.fill_resources.as_ref(),
+        fonts: pd.fonts.as_ref(),
+        page_width_pts: pd.page_width_pts,
+        page_height_pts: pd.page_height_pts,
+        page_origin_pts: pd.page_origin_pts,
+        preprocess: replace_content.then_some(&preprocess as &PreprocessHook<'_>),
+        preprocess_tag: replace_content.then_some(preprocess_tag.as_str()),
+        segmenter: None,
+        replace_content,
+    };
+    params.process()
+}
+
+/// Re-render and re-compose `page` at `retry_empty_at_dpi` when segmentation
+/// found no text on it, keeping the retry only if it finds some.
+#[cfg(feature = "mrc")]
+fn retry_empty_page(
+    pd: &RenderResult,
+    page: ProcessedPage,
+    config: &JobConfig,
+    mrc_config: &MrcConfig,
+    render_path: &Path,
+    cache_store: Option<&CacheStore>,
+) -> crate::error::Result<ProcessedPage> {
+    let Some(retry_dpi) = config.retry_empty_at_dpi.filter(|&dpi| dpi > config.dpi) else {
+        return Ok(page);
+    };
+    if !should_retry_at_higher_dpi(&page, &pd.content) {
+        return Ok(page);
+    }
+    debug!(
+        page = pd.page_idx,
+        dpi = retry_dpi,
+        "no text found, retrying at higher DPI"
+    );
+    let render_options = RenderOptions {
+        text_antialias: config.text_antialias,
+        user_unit: pd.user_unit,
+    };
+    let bitmap = render_page_with_options(render_path, pd.page_idx, retry_dpi, &render_options)?;
+    let retried = compose_rendered_page(pd, &bitmap, retry_dpi, config, mrc_config, cache_store)?;
+    if should_retry_at_higher_dpi(&retried, &pd.content) {
+        Ok(page)
+    } else {
+        Ok(retried)
+    }
+}
+
+/// Keep a page that only draws one full-page JPEG image, re-encoding that
+/// image with [`optimize_image_stream`] instead of rasterizing the page.
+///
+/// Returns `None` for any other page, and when the image must be converted
+/// (grayscale pages, `normalize_images_to`) but no smaller encoding exists,
+/// so that the regular pipeline handles it.
+#[cfg(feature = "mrc")]
+fn passthrough_full_page_image(
+    cs: &AnalysisResult,
+    config: &JobConfig,
+) -> crate::error::Result<Option<ProcessedPage>> {
+    if !matches!(cs.mode, ColorMode::Rgb | ColorMode::Grayscale)
+        || cs.unmirror
+        || !cs.redactions.is_empty()
+    {
+        return Ok(None);
+    }
+    let (origin_x, origin_y) = cs.page_origin_pts;
+    let page_box = BBox {
+        x_min: origin_x,
+        y_min: origin_y,
+        x_max: origin_x + cs.page_width_pts,
+        y_max: origin_y + cs.page_height_pts,
+    };
+    let Some(name) = full_page_xobject(&cs.content, &page_box)? else {
+        return Ok(None);
+    };
+    let Some(stream) = cs.image_streams.as_ref().and_then(|s| s.get(&name)) else {
+        return Ok(None);
+    };
+    if stream_filters(&stream.dict) != ["DCTDecode"] {
+        return Ok(None);
+    }
+
+    let normalize_to = config
+        .normalize_images_to
+        .or((cs.mode == ColorMode::Grayscale).then_some(ImageColorSpace::Gray));
+    let optimized = optimize_image_stream(
+        stream,
+        cs.matte_smasks.as_ref().and_then(|s| s.get(&name)),
+        config.bg_quality,
+        normalize_to,
+        config.image_class_dpi.is_enabled(),
+        config.max_image_pixels,
+        config.jpeg_size_mismatch,
+    )?;
+    if optimized.is_none() && normalize_to.is_some() {
+        return Ok(None);
+    }
+    debug!(
+        page = cs.page_idx,
+        image = %name,
+        optimized = optimized.is_some(),
+        "passing full-page image through"
+    );
+
+    let modified_images = optimized
+        .map(|image| (name, optimized_modification(image)))
+        .into_iter()
         .collect();
+    let data = TextMaskedData {
+        stripped_content_stream: cs.content.clone(),
+        text_regions: Vec::new(),
+        modified_images,
+        page_index: cs.page_idx,
+        page_width_pts: cs.page_width_pts,
+        page_height_pts: cs.page_height_pts,
+        color_mode: cs.mode,
+    };
+    Ok(Some(ProcessedPage::new(
+        cs.page_idx,
+        PageOutput::TextMasked(data),
+        String::new(),
+        None,
+    )))
+}
 
-    // Collect all results
-    let mut successful_pages: Vec<ProcessedPage> = outlines_pages;
-    for result in processed {
-        successful_pages.push(result?);
+/// Re-encode the image XObjects of a `Skip` page with [`optimize_image_stream`]
+/// (`optimize_skip_images`), returning those that got smaller by name.
+///
+/// Images that cannot be decoded (e.g. CCITT or JBIG2) are left as they are.
+#[cfg(feature = "mrc")]
+fn optimize_page_images(
+    reader: &PdfReader,
+    page_num: u32,
+    config: &JobConfig,
+) -> crate::error::Result<std::collections::HashMap<String, ImageModification>> {
+    let mut modified_images = std::collections::HashMap::new();
+    let matte_smasks = reader.page_matte_smasks(page_num)?;
+    for (name, stream) in reader.page_image_streams(page_num)? {
+        match optimize_image_stream(
+            &stream,
+            matte_smasks.get(&name),
+            config.bg_quality,
+            config.normalize_images_to,
+            config.image_class_dpi.is_enabled(),
+            config.max_image_pixels,
+            config.jpeg_size_mismatch,
+        ) {
+            Ok(Some(image)) => {
+                modified_images.insert(name, optimized_modification(image));
+            }
+            Ok(None) => {}
+            Err(e) => debug!(
+                page = page_num,
+                image = %name,
+                error = %e,
+                "keeping skip page image that could not be re-encoded"
+            ),
+        }
     }
+    debug!(
+        page = page_num,
+        optimized = modified_images.len(),
+        "optimized skip page images"
+    );
+    Ok(modified_images)
+}
 
-    // Add skip pages directly (no rendering or MRC processing needed)
-    for &(page_idx, mode) in page_modes {
-        if mode == ColorMode::Skip {
-            successful_pages.push(ProcessedPage {
-                page_index: page_idx,
-                output: PageOutput::Skip(SkipData {
-                    page_index: page_idx,
-                }),
-                cache_key: String::new(),
-            });
+/// Turn a re-encoded image into the modification that replaces the original.
+#[cfg(feature = "mrc")]
+fn optimized_modification(image: OptimizedImage) -> ImageModification {
+    ImageModification {
+        data: image.data,
+        filter: image.filter.to_string(),
+        color_space: image.color_space.to_string(),
+        bits_per_component: image.bits_per_component,
+        width: image.width,
+        height: image.height,
+        redacted_regions: Vec::new(),
+        smask: None,
+    }
+}
+
+/// A modified copy of the input written for pdfium, which renders from a file.
+/// Removed when dropped.
+#[cfg(feature = "mrc")]
+struct TempPdf {
+    path: PathBuf,
+}
+
+#[cfg(feature = "mrc")]
+impl TempPdf {
+    fn write(doc: &lopdf::Document) -> crate::error::Result<Self> {
+        static NEXT_ID: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        let id = NEXT_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let path =
+            std::env::temp_dir().join(format!("pdf_masking-{}-{}.pdf", std::process::id(), id));
+        doc.clone()
+            .save(&path)
+            .map_err(|e| PdfMaskError::pdf_write(format!("{}: {}", path.display(), e)))?;
+        Ok(TempPdf { path })
+    }
+}
+
+#[cfg(feature = "mrc")]
+impl Drop for TempPdf {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Cache tag identifying the bitmap preprocessing applied to a rendered page.
+#[cfg(feature = "mrc")]
+fn preprocess_tag(pd: &RenderResult) -> String {
+    let mut tag = String::new();
+    for r in &pd.redactions {
+        tag.push_str(&format!(
+            "redact:{},{},{},{};",
+            r.x_min, r.y_min, r.x_max, r.y_max
+        ));
+    }
+    if pd.unmirror {
+        tag.push_str("unmirror");
+    }
+    tag
+}
+
+/// Fill page-space rectangles with black on a bitmap of the whole page.
+#[cfg(feature = "mrc")]
+fn paint_redactions(
+    image: &mut image::RgbaImage,
+    rects: &[BBox],
+    page_width_pts: f64,
+    page_height_pts: f64,
+    (origin_x, origin_y): (f64, f64),
+) {
+    if page_width_pts <= 0.0 || page_height_pts <= 0.0 {
+        return;
+    }
+    let (width, height) = image.dimensions();
+    let scale_x = width as f64 / page_width_pts;
+    let scale_y = height as f64 / page_height_pts;
+    // ビットマップのY=0（上端）はページ上端。端の画素も確実に覆うよう外側に丸める
+    let to_px = |v: f64, max: u32| (v.max(0.0) as u32).min(max);
+    for r in rects {
+        let x0 = to_px(((r.x_min - origin_x) * scale_x).floor(), width);
+        let x1 = to_px(((r.x_max - origin_x) * scale_x).ceil(), width);
+        let y0 = to_px(
+            ((page_height_pts - (r.y_max - origin_y)) * scale_y).floor(),
+            height,
+        );
+        let y1 = to_px(
+            ((page_height_pts - (r.y_min - origin_y)) * scale_y).ceil(),
+            height,
+        );
+        for y in y0..y1 {
+            for x in x0..x1 {
+                image.put_pixel(x, y, image::Rgba([0, 0, 0, 255]));
+            }
         }
     }
+}
 
-    // Sort by page index for deterministic output
-    successful_pages.sort_by_key(|p| p.page_index);
+/// Whether a page may paint in color in the output.
+///
+/// Skip pages keep the source content as is, and reused pages come from a
+/// prior output whose color use is unknown, so both are treated as color.
+/// Text-masked pages follow their color mode, like the rasterized ones.
+fn page_uses_color(output: &PageOutput) -> bool {
+    match output {
+        #[cfg(feature = "mrc")]
+        PageOutput::Mrc(layers) => layers.color_mode == ColorMode::Rgb,
+        #[cfg(feature = "mrc")]
+        PageOutput::BwMask(_) => false,
+        PageOutput::TextMasked(data) => data.color_mode == ColorMode::Rgb,
+        PageOutput::Skip(_) | PageOutput::Reused(_) => true,
+        PageOutput::Blank(_) => false,
+    }
+}
 
-    Ok(successful_pages)
+/// Whether a page is replaced by its rasterized bitmap in the output.
+fn is_rasterized(output: &PageOutput) -> bool {
+    match output {
+        #[cfg(feature = "mrc")]
+        PageOutput::Mrc(_) | PageOutput::BwMask(_) => true,
+        _ => false,
+    }
 }
 
 /// Phase D: PDF assembly + optimization (sequential).
 ///
 /// Writes all processed pages into a new PDF document and optimizes it.
+/// Redaction marks (keyed by 0-based page) are drawn over their pages when
+/// `redaction_mark` is set, on top of any preserved regions drawn back over
+/// rasterized pages. Reused pages are copied from `prior`.
+#[allow(clippy::too_many_arguments)]
 fn phase_d_write(
     reader: &PdfReader,
+    prior: Option<&PdfReader>,
     successful_pages: &[ProcessedPage],
+    redaction_marks: &std::collections::HashMap<u32, Vec<BBox>>,
+    preserved_regions: &std::collections::HashMap<u32, PreservedRegions>,
     config: &JobConfig,
     pages_processed: usize,
+    pages_dropped: usize,
 ) -> crate::error::Result<JobResult> {
     let mut writer = MrcPageWriter::new();
+    writer.set_text_region_padding(config.text_region_padding);
+    writer.set_share_copied_objects(config.share_copied_objects);
+    #[cfg(feature = "mrc")]
+    {
+        writer.set_foreground_mask(config.foreground_mask);
+        writer.set_interpolate_output(config.interpolate_output);
+        writer.set_tag_srgb(config.tag_srgb);
+    }
     let mut masked_page_ids: Vec<lopdf::ObjectId> = Vec::new();
+    // Source page ID -> output page ID, for remapping the open action
+    let source_pages = reader.document().get_pages();
+    let mut page_map: std::collections::HashMap<lopdf::ObjectId, lopdf::ObjectId> =
+        std::collections::HashMap::new();
     for page in successful_pages {
-        match &page.output {
+        let page_id = match &page.output {
             #[cfg(feature = "mrc")]
             PageOutput::Mrc(layers) => {
                 let page_id = writer.write_mrc_page(layers)?;
                 masked_page_ids.push(page_id);
+                page_id
             }
             #[cfg(feature = "mrc")]
             PageOutput::BwMask(bw) => {
                 let page_id = writer.write_bw_page(bw)?;
                 masked_page_ids.push(page_id);
+                page_id
             }
             PageOutput::Skip(_) => {
                 let page_num = page.page_index + 1; // 1-based
-                writer.copy_page_from(reader.document(), page_num)?;
                 // Skip pages are NOT added to masked_page_ids (no font optimization)
+                let page_id = writer.copy_page_from(reader.document(), page_num)?;
+                #[cfg(feature = "mrc")]
+                if config.optimize_skip_images {
+                    let modified_images = optimize_page_images(reader, page_num, config)?;
+                    writer.replace_page_images(page_id, &modified_images)?;
+                }
+                page_id
             }
             PageOutput::TextMasked(data) => {
                 let page_num = page.page_index + 1;
                 let page_id = writer.write_text_masked_page(reader.document(), page_num, data)?;
                 masked_page_ids.push(page_id);
+                page_id
+            }
+            PageOutput::Blank(blank) => writer.write_blank_page(
+                blank.page_width_pts,
+                blank.page_height_pts,
+                blank.fill_white,
+            )?,
+            PageOutput::Reused(reused) => {
+                let prior = prior
+                    .ok_or_else(|| PdfMaskError::pdf_write("reused page without a prior output"))?;
+                // Already optimized in the prior run, so not added to masked_page_ids
+                writer.copy_prior_page(prior.document(), reused.prior_page_num)?
             }
+        };
+        // Pages masked in this run record their source key for later incremental runs
+        if config.record_source_keys
+            && masked_page_ids.last() == Some(&page_id)
+            && let Some(key) = &page.source_key
+        {
+            writer.set_page_source_key(page_id, key)?;
+        }
+        if let Some(&source_page_id) = source_pages.get(&(page.page_index + 1)) {
+            page_map.insert(source_page_id, page_id);
         }
+        if is_rasterized(&page.output)
+            && let Some(preserved) = preserved_regions.get(&page.page_index)
+        {
+            writer.add_preserved_regions(
+                page_id,
+                reader.document(),
+                page.page_index + 1,
+                &preserved.rects,
+                &preserved.xobjects,
+                preserved.origin,
+            )?;
+        }
+        if let (Some(color), Some(rects)) =
+            (config.redaction_mark, redaction_marks.get(&page.page_index))
+        {
+            writer.add_redaction_marks_with_fill(
+                page_id,
+                rects,
+                color,
+                config.redaction_mark_fill,
+            )?;
+        }
+    }
+
+    // 元のコンテンツストリーム（BDC/EMCマーク付きコンテンツ）を保持するページがあれば
+    // レイヤー構成（/OCProperties）も引き継ぐ。ラスタライズされたページは既定の表示状態で平坦化される。
+    let keeps_original_content = successful_pages
+        .iter()
+        .any(|page| matches!(page.output, PageOutput::Skip(_) | PageOutput::TextMasked(_)));
+    if keeps_original_content {
+        writer.copy_optional_content_from(reader.document())?;
+    } else if reader.has_optional_content() {
+        debug!(
+            input = %config.input_path.display(),
+            "optional content flattened using default layer visibility"
+        );
+    }
+
+    writer.copy_metadata_from(reader.document(), config.metadata, config.reproducible)?;
+    writer.copy_lang_from(reader.document(), config.lang.as_deref())?;
+    if let Some(producer) = &config.producer {
+        writer.set_producer(producer)?;
+    }
+    if config.preserve_view {
+        writer.copy_view_from(reader.document(), &page_map)?;
+    }
+
+    if config.pdfa_output_intent {
+        let uses_color = successful_pages
+            .iter()
+            .any(|page| page_uses_color(&page.output));
+        if uses_color && config.output_colorspace == OutputColorSpace::Cmyk {
+            // No CMYK profile is bundled, and an sRGB intent would not cover DeviceCMYK.
+            warn!(
+                input = %config.input_path.display(),
+                "pdfa_output_intent is not supported with output_colorspace: cmyk; output intent omitted"
+            );
+        } else {
+            writer.add_output_intent(if uses_color {
+                OutputIntentProfile::Srgb
+            } else {
+                OutputIntentProfile::GrayGamma22
+            })?;
+        }
+    }
+
+    if config.simplify_content {
+        crate::pdf::optimizer::simplify_content_streams(writer.document_mut(), &masked_page_ids)?;
+    }
+    if let Some(max_bytes) = config.split_content_bytes {
+        crate::pdf::optimizer::split_content_streams(
+            writer.document_mut(),
+            &masked_page_ids,
+            max_bytes,
+        )?;
     }
 
     // Run optimization on the assembled document
     crate::pdf::optimizer::optimize(writer.document_mut(), &masked_page_ids)?;
 
     let pdf_bytes = writer.save_to_bytes()?;
-    std::fs::write(&config.output_path, pdf_bytes)?;
+    let output_bytes = pdf_bytes.len() as u64;
+    if config.discard_output {
+        debug!(output_bytes, "discarding output (benchmark mode)");
+    } else {
+        std::fs::write(&config.output_path, pdf_bytes)?;
+    }
+
+    let pages: Vec<PageReport> = successful_pages
+        .iter()
+        .map(|page| PageReport {
+            page_index: page.page_index,
+            layer_sizes: page.layer_sizes,
+            effective_dpi: page.effective_dpi,
+        })
+        .collect();
+    let mut layer_sizes = LayerSizes::default();
+    for page in &pages {
+        layer_sizes += page.layer_sizes;
+    }
 
     Ok(JobResult {
         input_path: config.input_path.clone(),
         output_path: config.output_path.clone(),
         pages_processed,
+        pages_dropped,
+        pages,
+        layer_sizes,
+        output_bytes,
     })
 }
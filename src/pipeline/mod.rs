@@ -1,3 +1,7 @@
+pub mod blank_page;
+pub mod compare;
 pub mod job_runner;
 pub mod orchestrator;
 pub mod page_processor;
+pub mod redaction_manifest;
+pub mod redaction_verify;
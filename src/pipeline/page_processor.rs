@@ -4,25 +4,61 @@ use std::collections::HashMap;
 use std::path::Path;
 
 #[cfg(feature = "mrc")]
-use image::DynamicImage;
+use image::{DynamicImage, RgbaImage};
 use tracing::{debug, warn};
 
 use crate::cache::hash::{CacheSettings, compute_cache_key};
+#[cfg(feature = "mrc")]
+use crate::cache::hash::{compute_mask_cache_key, tag_cache_key};
 use crate::cache::store::CacheStore;
 use crate::config::job::ColorMode;
+use crate::error::PdfMaskError;
+#[cfg(feature = "mrc")]
+use crate::mrc::BwLayers;
 #[cfg(feature = "mrc")]
 use crate::mrc::compositor::{
-    MrcConfig, TextMaskedParams, compose, compose_bw, compose_text_masked,
+    MrcConfig, TextMaskedParams, compose_text_masked, compose_with_mask, segment_mask_jbig2,
 };
 use crate::mrc::compositor::{TextOutlinesParams, compose_text_outlines};
-use crate::mrc::{PageOutput, SkipData};
+#[cfg(feature = "mrc")]
+use crate::mrc::segmenter::{LeptonicaSegmenter, Segmenter};
+use crate::mrc::{LayerSizes, PageOutput, SkipData};
+use crate::pdf::content_stream::FillResources;
 use crate::pdf::font::ParsedFont;
+#[cfg(feature = "mrc")]
+use crate::pipeline::blank_page::has_text_or_images;
 
 /// Single page processing result.
 pub struct ProcessedPage {
     pub page_index: u32,
     pub output: PageOutput,
     pub cache_key: String,
+    /// Encoded byte size of each layer in `output`.
+    pub layer_sizes: LayerSizes,
+    /// DPI the page was rasterized at (`None` when it was not rasterized).
+    pub effective_dpi: Option<u32>,
+    /// Key of the source page, recorded on masked output pages so a later
+    /// incremental run can reuse them.
+    pub source_key: Option<String>,
+}
+
+impl ProcessedPage {
+    /// Wrap a page output, recording its layer sizes.
+    pub fn new(
+        page_index: u32,
+        output: PageOutput,
+        cache_key: String,
+        effective_dpi: Option<u32>,
+    ) -> Self {
+        ProcessedPage {
+            page_index,
+            layer_sizes: output.layer_sizes(),
+            output,
+            cache_key,
+            effective_dpi,
+            source_key: None,
+        }
+    }
 }
 
 /// Parameters for [`process_page_outlines`].
@@ -33,6 +69,12 @@ pub struct ProcessPageOutlinesParams<'a> {
     pub cache_store: Option<&'a CacheStore>,
     pub pdf_path: &'a Path,
     pub image_streams: Option<&'a HashMap<String, lopdf::Stream>>,
+    /// XObject name → its /SMask, for images whose /SMask has a /Matte (see
+    /// [`crate::pdf::reader::PdfReader::page_matte_smasks`]).
+    pub matte_smasks: Option<&'a HashMap<String, lopdf::Stream>>,
+    /// Color spaces, ExtGStates and form XObjects used to find white fills (see
+    /// [`crate::pdf::content_stream::extract_white_fill_rects_with_resources`]).
+    pub fill_resources: Option<&'a FillResources>,
     pub fonts: &'a HashMap<String, ParsedFont>,
     pub page_width_pts: f64,
     pub page_height_pts: f64,
@@ -58,6 +100,7 @@ impl ProcessPageOutlinesParams<'_> {
                 color_mode
             )));
         }
+        check_page_geometry(self.page_index, self.page_width_pts, self.page_height_pts)?;
 
         let cache_key = compute_cache_key(
             self.content_stream,
@@ -72,25 +115,33 @@ impl ProcessPageOutlinesParams<'_> {
             && !matches!(&cached, PageOutput::Skip(_))
         {
             debug!(page = self.page_index, path = "outlines", "cache hit");
-            return Ok(ProcessedPage {
-                page_index: self.page_index,
-                output: cached,
-                cache_key,
-            });
+            return Ok(ProcessedPage::new(self.page_index, cached, cache_key, None));
         }
 
         debug!(page = self.page_index, path = "outlines", "cache miss");
         // Run compose_text_outlines (no bitmap needed)
         let empty_streams = HashMap::new();
         let streams = self.image_streams.unwrap_or(&empty_streams);
+        let empty_resources = FillResources::default();
+        let fill_resources = self.fill_resources.unwrap_or(&empty_resources);
         let outlines_params = TextOutlinesParams {
             content_bytes: self.content_stream,
             fonts: self.fonts,
             image_streams: streams,
+            matte_smasks: self.matte_smasks.unwrap_or(&empty_streams),
+            fill_resources,
             page_width_pts: self.page_width_pts,
             page_height_pts: self.page_height_pts,
             color_mode,
             page_index: self.page_index,
+            image_max_dpi: self.cache_settings.image_max_dpi,
+            image_class_dpi: self.cache_settings.image_class_dpi,
+            normalize_images_to: self.cache_settings.normalize_images_to,
+            resample_filter: self.cache_settings.resample_filter,
+            max_image_pixels: self.cache_settings.max_image_pixels,
+            jpeg_size_mismatch: self.cache_settings.jpeg_size_mismatch,
+            merge_white_fills: self.cache_settings.merge_white_fills,
+            width_source: self.cache_settings.width_source,
         };
         let data = compose_text_outlines(&outlines_params)?;
         let output = PageOutput::TextMasked(data);
@@ -100,11 +151,7 @@ impl ProcessPageOutlinesParams<'_> {
             store.store(&cache_key, &output, None)?;
         }
 
-        Ok(ProcessedPage {
-            page_index: self.page_index,
-            output,
-            cache_key,
-        })
+        Ok(ProcessedPage::new(self.page_index, output, cache_key, None))
     }
 }
 
@@ -131,6 +178,8 @@ pub fn process_page_outlines(
         cache_store,
         pdf_path,
         image_streams,
+        matte_smasks: None,
+        fill_resources: None,
         fonts,
         page_width_pts,
         page_height_pts,
@@ -138,6 +187,29 @@ pub fn process_page_outlines(
     params.process()
 }
 
+/// Reject a degenerate page size (zero, negative or non-finite width or
+/// height, e.g. from a malformed MediaBox) before it reaches the
+/// pixel/point conversions, which divide by it.
+fn check_page_geometry(
+    page_index: u32,
+    page_width_pts: f64,
+    page_height_pts: f64,
+) -> crate::error::Result<()> {
+    let valid = |v: f64| v.is_finite() && v > 0.0;
+    if valid(page_width_pts) && valid(page_height_pts) {
+        return Ok(());
+    }
+    Err(PdfMaskError::pdf_read(format!(
+        "page {}: degenerate page size {page_width_pts} x {page_height_pts} pt \
+         (the MediaBox needs a positive width and height)",
+        page_index + 1
+    )))
+}
+
+/// Bitmap preprocessing hook run after rasterization and before composition.
+#[cfg(feature = "mrc")]
+pub type PreprocessHook<'a> = dyn Fn(&mut RgbaImage) + Sync + 'a;
+
 /// Parameters for [`process_page`].
 #[cfg(feature = "mrc")]
 pub struct ProcessPageParams<'a> {
@@ -149,8 +221,31 @@ pub struct ProcessPageParams<'a> {
     pub cache_store: Option<&'a CacheStore>,
     pub pdf_path: &'a Path,
     pub image_streams: Option<&'a HashMap<String, lopdf::Stream>>,
+    /// XObject name → its /SMask, for images whose /SMask has a /Matte (see
+    /// [`crate::pdf::reader::PdfReader::page_matte_smasks`]).
+    pub matte_smasks: Option<&'a HashMap<String, lopdf::Stream>>,
+    /// Color spaces, ExtGStates and form XObjects used to find white fills (see
+    /// [`crate::pdf::content_stream::extract_white_fill_rects_with_resources`]).
+    pub fill_resources: Option<&'a FillResources>,
+    /// Page fonts. When set, rotated text is cropped along its own orientation
+    /// instead of by its axis-aligned bounding box.
+    pub fonts: Option<&'a HashMap<String, ParsedFont>>,
     pub page_width_pts: f64,
     pub page_height_pts: f64,
+    /// Lower-left corner of the MediaBox in page space (non-zero for e.g. `[-10 -10 605 802]`).
+    pub page_origin_pts: (f64, f64),
+    /// Custom bitmap filter (e.g. denoise) applied before segmentation.
+    pub preprocess: Option<&'a PreprocessHook<'a>>,
+    /// Identifies the `preprocess` hook in cache keys. When a hook is set
+    /// without a tag, the cache is bypassed because its output is unknown.
+    pub preprocess_tag: Option<&'a str>,
+    /// Custom text/background segmenter. `None` uses [`LeptonicaSegmenter`].
+    /// A segmenter without a [`Segmenter::cache_tag`] bypasses the cache.
+    pub segmenter: Option<&'a dyn Segmenter>,
+    /// Compose RGB/Grayscale pages as full-page MRC so the bitmap replaces the
+    /// original content. Needed when `preprocess` moves pixels (e.g. un-mirroring),
+    /// because text-masked output overlays crops on the original content.
+    pub replace_content: bool,
 }
 
 #[cfg(feature = "mrc")]
@@ -163,33 +258,76 @@ impl ProcessPageParams<'_> {
     ///
     /// - Skip: Return empty ProcessedPage without MRC encoding
     /// - Bw: Full-page JBIG2 encoding via compose_bw
-    /// - Rgb/Grayscale: Try compose_text_masked (text-only JPEG); fallback to compose (full-page MRC) on failure
+    /// - Rgb/Grayscale: Try compose_text_masked (text-only JPEG); fallback to compose (full-page MRC) on failure.
+    ///   With `replace_content`, compose (full-page MRC) directly
     pub fn process(&self) -> crate::error::Result<ProcessedPage> {
         let color_mode = self.cache_settings.color_mode;
 
         // Skip モードはMRC処理不要
         if color_mode == ColorMode::Skip {
-            return Ok(ProcessedPage {
-                page_index: self.page_index,
-                output: PageOutput::Skip(SkipData {
+            return Ok(ProcessedPage::new(
+                self.page_index,
+                PageOutput::Skip(SkipData {
                     page_index: self.page_index,
                 }),
-                cache_key: String::new(),
-            });
+                String::new(),
+                None,
+            ));
+        }
+        check_page_geometry(self.page_index, self.page_width_pts, self.page_height_pts)?;
+        let bitmap_width = self.bitmap.width();
+        let bitmap_height = self.bitmap.height();
+        if bitmap_width == 0 || bitmap_height == 0 {
+            return Err(PdfMaskError::render(format!(
+                "page {}: rendered bitmap is empty ({bitmap_width}x{bitmap_height} px)",
+                self.page_index + 1
+            )));
         }
 
-        let cache_key = compute_cache_key(
+        let mut cache_key = compute_cache_key(
             self.content_stream,
             self.cache_settings,
             self.pdf_path,
             self.page_index,
         );
+        // The mask depends on the bitmap but not on the color mode, so it is
+        // cached separately and survives a color mode change
+        let mut mask_key = compute_mask_cache_key(
+            self.content_stream,
+            self.cache_settings,
+            self.pdf_path,
+            self.page_index,
+        );
+        // 前処理フックの出力はタグでしか識別できないため、タグ無しならキャッシュしない
+        let mut cache_store = match (self.preprocess, self.preprocess_tag) {
+            (None, _) => self.cache_store,
+            (Some(_), Some(tag)) => {
+                cache_key = tag_cache_key(&cache_key, tag);
+                mask_key = tag_cache_key(&mask_key, tag);
+                self.cache_store
+            }
+            (Some(_), None) => None,
+        };
+        // 独自の分離器も同様に、タグが無ければキャッシュしない
+        if let Some(segmenter) = self.segmenter {
+            match segmenter.cache_tag() {
+                Some(tag) => {
+                    let tag = format!("segmenter:{tag}");
+                    cache_key = tag_cache_key(&cache_key, &tag);
+                    mask_key = tag_cache_key(&mask_key, &tag);
+                }
+                None => cache_store = None,
+            }
+        }
+        let segmenter = self.segmenter.unwrap_or(&LeptonicaSegmenter);
 
-        let bitmap_width = self.bitmap.width();
-        let bitmap_height = self.bitmap.height();
+        // The bitmap may have been re-rasterized above the configured DPI
+        // (`retry_empty_at_dpi`), so derive the DPI from its actual width.
+        let effective_dpi =
+            Some((f64::from(bitmap_width) * 72.0 / self.page_width_pts).round() as u32);
 
         // Check cache first (retrieve checks bitmap dimensions internally)
-        if let Some(store) = self.cache_store
+        if let Some(store) = cache_store
             && let Some(cached) =
                 store.retrieve(&cache_key, color_mode, Some((bitmap_width, bitmap_height)))?
         {
@@ -197,18 +335,22 @@ impl ProcessPageParams<'_> {
                 PageOutput::Skip(_) => {}
                 _ => {
                     debug!(page = self.page_index, path = "mrc", "cache hit");
-                    return Ok(ProcessedPage {
-                        page_index: self.page_index,
-                        output: cached,
+                    return Ok(ProcessedPage::new(
+                        self.page_index,
+                        cached,
                         cache_key,
-                    });
+                        effective_dpi,
+                    ));
                 }
             }
         }
 
         debug!(page = self.page_index, path = "mrc", "cache miss");
         // Cache miss: run MRC composition
-        let rgba_image = self.bitmap.to_rgba8();
+        let mut rgba_image = self.bitmap.to_rgba8();
+        if let Some(preprocess) = self.preprocess {
+            preprocess(&mut rgba_image);
+        }
         let (width, height) = (rgba_image.width(), rgba_image.height());
         let rgba_data = rgba_image.into_raw();
 
@@ -217,13 +359,37 @@ impl ProcessPageParams<'_> {
 
         let output = match color_mode {
             ColorMode::Bw => {
-                let bw_layers =
-                    compose_bw(&rgba_data, width, height, page_width_pts, page_height_pts)?;
-                PageOutput::BwMask(bw_layers)
+                let mask_jbig2 =
+                    self.mask_jbig2(&rgba_data, width, height, segmenter, cache_store, &mask_key)?;
+                debug!(mask_bytes = mask_jbig2.len(), "compose BW layers");
+                PageOutput::BwMask(BwLayers {
+                    mask_jbig2,
+                    width,
+                    height,
+                    page_width_pts,
+                    page_height_pts,
+                })
+            }
+            mode @ (ColorMode::Rgb | ColorMode::Grayscale) if self.replace_content => {
+                let mask_jbig2 =
+                    self.mask_jbig2(&rgba_data, width, height, segmenter, cache_store, &mask_key)?;
+                let mrc_layers = compose_with_mask(
+                    &rgba_data,
+                    width,
+                    height,
+                    page_width_pts,
+                    page_height_pts,
+                    self.mrc_config,
+                    mode,
+                    mask_jbig2,
+                )?;
+                PageOutput::Mrc(mrc_layers)
             }
             mode @ (ColorMode::Rgb | ColorMode::Grayscale) => {
                 let empty_streams = HashMap::new();
                 let streams = self.image_streams.unwrap_or(&empty_streams);
+                let empty_resources = FillResources::default();
+                let fill_resources = self.fill_resources.unwrap_or(&empty_resources);
                 let params = TextMaskedParams {
                     content_bytes: self.content_stream,
                     rgba_data: &rgba_data,
@@ -231,9 +397,23 @@ impl ProcessPageParams<'_> {
                     bitmap_height: height,
                     page_width_pts,
                     page_height_pts,
+                    page_origin_pts: self.page_origin_pts,
                     image_streams: streams,
+                    matte_smasks: self.matte_smasks.unwrap_or(&empty_streams),
+                    fill_resources,
                     color_mode: mode,
                     page_index: self.page_index,
+                    jbig2: self.mrc_config.jbig2,
+                    line_removal: self.mrc_config.line_removal,
+                    image_max_dpi: self.cache_settings.image_max_dpi,
+                    image_class_dpi: self.cache_settings.image_class_dpi,
+                    normalize_images_to: self.cache_settings.normalize_images_to,
+                    resample_filter: self.cache_settings.resample_filter,
+                    max_image_pixels: self.cache_settings.max_image_pixels,
+                    jpeg_size_mismatch: self.cache_settings.jpeg_size_mismatch,
+                    merge_white_fills: self.cache_settings.merge_white_fills,
+                    segmenter,
+                    fonts: self.fonts,
                 };
 
                 match compose_text_masked(&params) {
@@ -244,7 +424,15 @@ impl ProcessPageParams<'_> {
                             self.page_index + 1,
                             e
                         );
-                        let mrc_layers = compose(
+                        let mask_jbig2 = self.mask_jbig2(
+                            &rgba_data,
+                            width,
+                            height,
+                            segmenter,
+                            cache_store,
+                            &mask_key,
+                        )?;
+                        let mrc_layers = compose_with_mask(
                             &rgba_data,
                             width,
                             height,
@@ -252,6 +440,7 @@ impl ProcessPageParams<'_> {
                             page_height_pts,
                             self.mrc_config,
                             mode,
+                            mask_jbig2,
                         )?;
                         PageOutput::Mrc(mrc_layers)
                     }
@@ -261,16 +450,60 @@ impl ProcessPageParams<'_> {
         };
 
         // Store in cache if available
-        if let Some(store) = self.cache_store {
+        if let Some(store) = cache_store {
             store.store(&cache_key, &output, Some((bitmap_width, bitmap_height)))?;
         }
 
-        Ok(ProcessedPage {
-            page_index: self.page_index,
+        Ok(ProcessedPage::new(
+            self.page_index,
             output,
             cache_key,
-        })
+            effective_dpi,
+        ))
     }
+
+    /// The page's JBIG2 text mask, from the mask cache when an earlier run
+    /// (possibly in another color mode) already computed it.
+    fn mask_jbig2(
+        &self,
+        rgba_data: &[u8],
+        width: u32,
+        height: u32,
+        segmenter: &dyn Segmenter,
+        cache_store: Option<&CacheStore>,
+        mask_key: &str,
+    ) -> crate::error::Result<Vec<u8>> {
+        if let Some(store) = cache_store
+            && let Some(mask_jbig2) = store.retrieve_mask(mask_key, (width, height))?
+        {
+            debug!(page = self.page_index, "mask cache hit");
+            return Ok(mask_jbig2);
+        }
+        let mask_jbig2 = segment_mask_jbig2(
+            rgba_data,
+            width,
+            height,
+            self.page_width_pts,
+            &self.mrc_config.jbig2,
+            self.mrc_config.line_removal,
+            segmenter,
+        )?;
+        if let Some(store) = cache_store {
+            store.store_mask(mask_key, &mask_jbig2, width, height)?;
+        }
+        Ok(mask_jbig2)
+    }
+}
+
+/// Whether a rendered page is worth re-rasterizing at a higher DPI.
+///
+/// True when segmentation produced a text-masked page with no text regions
+/// although the content stream draws text or images (e.g. fine print lost at
+/// a low DPI).
+#[cfg(feature = "mrc")]
+pub fn should_retry_at_higher_dpi(page: &ProcessedPage, content_stream: &[u8]) -> bool {
+    matches!(&page.output, PageOutput::TextMasked(data) if data.text_regions.is_empty())
+        && has_text_or_images(content_stream)
 }
 
 /// Process a single page (backward-compatible wrapper).
@@ -299,8 +532,58 @@ pub fn process_page(
         cache_store,
         pdf_path,
         image_streams,
+        matte_smasks: None,
+        fill_resources: None,
+        fonts: None,
         page_width_pts,
         page_height_pts,
+        page_origin_pts: (0.0, 0.0),
+        preprocess: None,
+        preprocess_tag: None,
+        segmenter: None,
+        replace_content: false,
     };
     params.process()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::hash::tests::test_settings;
+
+    #[test]
+    fn test_zero_height_page_is_rejected_with_clear_error() {
+        let settings = test_settings();
+        let fonts = HashMap::new();
+        let params = ProcessPageOutlinesParams {
+            page_index: 2,
+            content_stream: b"0 0 100 100 re f",
+            cache_settings: &settings,
+            cache_store: None,
+            pdf_path: Path::new("degenerate.pdf"),
+            image_streams: None,
+            matte_smasks: None,
+            fill_resources: None,
+            fonts: &fonts,
+            page_width_pts: 612.0,
+            page_height_pts: 0.0,
+        };
+        let err = params
+            .process()
+            .err()
+            .expect("zero-height page should fail");
+        assert!(
+            err.to_string()
+                .contains("page 3: degenerate page size 612 x 0 pt"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_page_geometry_accepts_positive_sizes_only() {
+        assert!(check_page_geometry(0, 612.0, 792.0).is_ok());
+        assert!(check_page_geometry(0, -612.0, 792.0).is_err());
+        assert!(check_page_geometry(0, 612.0, f64::NAN).is_err());
+        assert!(check_page_geometry(0, f64::INFINITY, 792.0).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+// リダクション記録: 適用したリダクションを監査用のサイドカーファイルに書き出す
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::error::PdfMaskError;
+use crate::mrc::PageOutput;
+use crate::pdf::content_stream::BBox;
+use crate::pipeline::page_processor::ProcessedPage;
+
+/// 1ジョブ分のリダクション記録
+#[derive(Debug, Serialize)]
+pub struct RedactionManifest<'a> {
+    pub input: &'a Path,
+    pub output: &'a Path,
+    /// リダクションを適用したページ（ページ番号順）。適用の無いページは含めない
+    pub pages: Vec<RedactedPage>,
+}
+
+/// 1ページ分のリダクション記録
+#[derive(Debug, Serialize)]
+pub struct RedactedPage {
+    /// 入力PDFのページ番号(1-based)
+    pub page: u32,
+    /// 黒塗りした`overlay_redactions`の矩形（入力ページの座標）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rects: Vec<BBox>,
+    /// 白色fillと重なって白塗りした画像XObjectの領域（XObject名順）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<RedactedImageRegions>,
+}
+
+/// 1つの画像XObjectで白塗りした領域
+#[derive(Debug, Serialize)]
+pub struct RedactedImageRegions {
+    /// ページリソースでのXObject名
+    pub name: String,
+    /// 白塗りした領域（ページ座標）
+    pub regions: Vec<BBox>,
+}
+
+/// 出力ページと`overlay_redactions`の矩形（0-basedページ番号→矩形）から記録を組み立てる。
+pub fn build_manifest<'a>(
+    input: &'a Path,
+    output: &'a Path,
+    pages: &[ProcessedPage],
+    overlay_rects: &HashMap<u32, Vec<BBox>>,
+) -> RedactionManifest<'a> {
+    let pages = pages
+        .iter()
+        .filter_map(|page| {
+            let rects = overlay_rects
+                .get(&page.page_index)
+                .cloned()
+                .unwrap_or_default();
+            let mut images: Vec<RedactedImageRegions> = match &page.output {
+                PageOutput::TextMasked(data) => data
+                    .modified_images
+                    .iter()
+                    .filter(|(_, modification)| !modification.redacted_regions.is_empty())
+                    .map(|(name, modification)| RedactedImageRegions {
+                        name: name.clone(),
+                        regions: modification.redacted_regions.clone(),
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            };
+            images.sort_by(|a, b| a.name.cmp(&b.name));
+            (!rects.is_empty() || !images.is_empty()).then(|| RedactedPage {
+                page: page.page_index + 1,
+                rects,
+                images,
+            })
+        })
+        .collect();
+    RedactionManifest {
+        input,
+        output,
+        pages,
+    }
+}
+
+/// 記録を`path`に書き出す。拡張子が`.yaml`/`.yml`ならYAML、それ以外はJSON。
+pub fn write_manifest(path: &Path, manifest: &RedactionManifest) -> crate::error::Result<()> {
+    let is_yaml = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml"));
+    let text = if is_yaml {
+        serde_yml::to_string(manifest).map_err(|e| e.to_string())
+    } else {
+        serde_json::to_string_pretty(manifest).map_err(|e| e.to_string())
+    }
+    .map_err(|e| PdfMaskError::pdf_write(format!("failed to serialize redaction manifest: {e}")))?;
+    std::fs::write(path, text)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mrc::SkipData;
+
+    #[test]
+    fn test_manifest_lists_only_redacted_pages() {
+        let pages: Vec<ProcessedPage> = (0..2)
+            .map(|page_index| {
+                ProcessedPage::new(
+                    page_index,
+                    PageOutput::Skip(SkipData { page_index }),
+                    String::new(),
+                    None,
+                )
+            })
+            .collect();
+        let rect = BBox {
+            x_min: 10.0,
+            y_min: 20.0,
+            x_max: 110.0,
+            y_max: 70.0,
+        };
+        let overlay_rects = HashMap::from([(1, vec![rect])]);
+
+        let manifest = build_manifest(
+            Path::new("in.pdf"),
+            Path::new("out.pdf"),
+            &pages,
+            &overlay_rects,
+        );
+        let json = serde_json::to_value(&manifest).expect("serialize");
+        assert_eq!(
+            json["pages"],
+            serde_json::json!([{
+                "page": 2,
+                "rects": [{"x_min": 10.0, "y_min": 20.0, "x_max": 110.0, "y_max": 70.0}],
+            }])
+        );
+    }
+}
@@ -0,0 +1,228 @@
+// リダクション検証: 書き出した出力PDFを読み直し、リダクションした内容が残っていないか確かめる
+
+use std::path::Path;
+
+use image::RgbImage;
+use image::imageops::FilterType;
+use tracing::debug;
+
+use crate::config::settings::DEFAULT_MAX_IMAGE_PIXELS;
+use crate::error::PdfMaskError;
+use crate::pdf::content_stream::{BBox, ImagePlacement, extract_xobject_placements_with_resources};
+use crate::pdf::image_xobject::{bbox_overlaps, decode_image_rgb};
+use crate::pdf::reader::PdfReader;
+use crate::pdf::text_extract::extract_page_text;
+
+/// 元の画像と同じ画素とみなす、チャネルあたりの平均絶対差の上限。
+/// JPEGへの再圧縮による誤差はこの範囲に収まる
+const SAME_PIXELS_TOLERANCE: f64 = 8.0;
+
+/// 元の画像と同じ縦横比とみなす比の相対誤差の上限（ダウンサンプリングの丸め分）
+const ASPECT_TOLERANCE: f64 = 0.02;
+
+/// 出力PDFの1ページで確かめるリダクション
+#[derive(Debug, Clone)]
+pub struct RedactionCheck {
+    /// 出力PDFのページ番号(1-based)
+    pub output_page: u32,
+    /// リダクションした領域（出力ページの座標）
+    pub regions: Vec<BBox>,
+    /// リダクション前の画像XObject（領域と重なっていた画像）
+    pub original_images: Vec<lopdf::Stream>,
+}
+
+/// リダクション前の画像と、デコードした画素（デコードできなければ`None`）
+struct OriginalImage<'a> {
+    data: &'a [u8],
+    pixels: Option<RgbImage>,
+}
+
+/// 出力PDFを読み直し、各ページのリダクション領域に内容が残っていないか検証する。
+///
+/// 次のいずれかが見つかれば`RedactionLeak`エラーとする:
+/// - 抽出したテキスト（単語）がリダクション領域と重なる
+/// - ページのResourcesからたどれる画像XObject（Form XObjectやタイリングパターンの中を含む）
+///   のうち、リダクション領域と重なって配置されたものが、リダクション前の画像と同じ
+///   ストリームデータのまま残っているか、デコードした画素が領域内でリダクション前の
+///   画像と同じ。フィルタや色空間を変えた再エンコード・ダウンサンプリング後も比べる
+///
+/// 配置を求められなかった画像（読み込めないフォームの中など）は、どこに描かれていても
+/// 領域と重なるものとして画像全体で比べる。たどれないフォームやパターンがあれば
+/// 検証済みとせずエラーにする。
+///
+/// フォントを解決できないテキストは抽出されないため検出できない。
+pub fn verify_redaction(output: &Path, checks: &[RedactionCheck]) -> crate::error::Result<()> {
+    let reader = PdfReader::open(output)?;
+    let mut leaks: Vec<String> = Vec::new();
+    for check in checks.iter().filter(|check| !check.regions.is_empty()) {
+        let page_num = check.output_page;
+        let overlaps_region = |bbox: &BBox| check.regions.iter().any(|r| bbox_overlaps(r, bbox));
+
+        for word in extract_page_text(reader.document(), page_num)? {
+            if overlaps_region(&word.bbox) {
+                leaks.push(format!("page {}: text {:?}", page_num, word.text));
+            }
+        }
+
+        if check.original_images.is_empty() {
+            continue;
+        }
+        let originals: Vec<OriginalImage> = check
+            .original_images
+            .iter()
+            .map(|stream| OriginalImage {
+                data: &stream.content,
+                pixels: decode_image_rgb(stream, DEFAULT_MAX_IMAGE_PIXELS).ok(),
+            })
+            .collect();
+        let images = reader.page_reachable_images(page_num).map_err(|e| {
+            PdfMaskError::redaction_leak(format!(
+                "{}: page {} cannot be verified: {}",
+                output.display(),
+                page_num,
+                e
+            ))
+        })?;
+        let content = reader.page_content_stream(page_num)?;
+        let resources = reader.page_fill_resources(page_num)?;
+        let placements = extract_xobject_placements_with_resources(&content, &resources)?;
+
+        for image in images {
+            let placed: Vec<&ImagePlacement> = placements
+                .iter()
+                .filter(|p| p.name == image.name && p.form_path == image.form_path)
+                .collect();
+            let in_regions: Vec<&ImagePlacement> = placed
+                .iter()
+                .copied()
+                .filter(|p| overlaps_region(&p.bbox))
+                .collect();
+            if !placed.is_empty() && in_regions.is_empty() {
+                continue;
+            }
+
+            let leaked = originals.iter().any(|o| o.data == image.stream.content)
+                || decode_image_rgb(&image.stream, DEFAULT_MAX_IMAGE_PIXELS).is_ok_and(|pixels| {
+                    originals
+                        .iter()
+                        .filter_map(|o| o.pixels.as_ref())
+                        .any(|original| {
+                            if placed.is_empty() {
+                                return same_pixels(original, &pixels, None);
+                            }
+                            in_regions.iter().any(|placement| {
+                                check.regions.iter().any(|region| {
+                                    pixel_rect(region, placement, pixels.width(), pixels.height())
+                                        .is_some_and(|rect| {
+                                            same_pixels(original, &pixels, Some(rect))
+                                        })
+                                })
+                            })
+                        })
+                });
+            if leaked {
+                let mut path = image.form_path;
+                path.push(image.name);
+                leaks.push(format!(
+                    "page {}: unredacted image {}",
+                    page_num,
+                    path.join("/")
+                ));
+            }
+        }
+    }
+
+    debug!(
+        output = %output.display(),
+        pages = checks.len(),
+        leaks = leaks.len(),
+        "verified redaction"
+    );
+    if leaks.is_empty() {
+        Ok(())
+    } else {
+        Err(PdfMaskError::redaction_leak(format!(
+            "{}: {}",
+            output.display(),
+            leaks.join(", ")
+        )))
+    }
+}
+
+/// ページ座標の`region`を、`placement`で描かれた`width`x`height`の画像の画素範囲
+/// `(x_min, y_min, x_max, y_max)`に変換する。重ならなければ`None`。
+fn pixel_rect(
+    region: &BBox,
+    placement: &ImagePlacement,
+    width: u32,
+    height: u32,
+) -> Option<(u32, u32, u32, u32)> {
+    let inverse = placement.ctm.inverse()?;
+    let corners = [
+        (region.x_min, region.y_min),
+        (region.x_max, region.y_min),
+        (region.x_min, region.y_max),
+        (region.x_max, region.y_max),
+    ];
+    let (mut u_min, mut u_max, mut v_min, mut v_max) = (f64::MAX, f64::MIN, f64::MAX, f64::MIN);
+    for (x, y) in corners {
+        // 画像空間は単位正方形で、画素の行は上（v = 1）から並ぶ
+        let u = inverse.a * x + inverse.c * y + inverse.e;
+        let v = inverse.b * x + inverse.d * y + inverse.f;
+        u_min = u_min.min(u);
+        u_max = u_max.max(u);
+        v_min = v_min.min(v);
+        v_max = v_max.max(v);
+    }
+    let (w, h) = (width as f64, height as f64);
+    let x_min = (u_min * w).clamp(0.0, w).floor() as u32;
+    let x_max = (u_max * w).clamp(0.0, w).ceil() as u32;
+    let y_min = ((1.0 - v_max) * h).clamp(0.0, h).floor() as u32;
+    let y_max = ((1.0 - v_min) * h).clamp(0.0, h).ceil() as u32;
+    (x_min < x_max && y_min < y_max).then_some((x_min, y_min, x_max, y_max))
+}
+
+/// `output`の画素が`rect`内（`None`なら全体）で`original`と同じかを判定する。
+///
+/// 寸法が違えば`original`を`output`の寸法に縮小してから比べる。縦横比が違う画像は
+/// 別の画像とみなす。元の画素が白（リダクションの塗り色）に近い範囲は、残っていても
+/// 隠すものが無いため同じとはみなさない。
+fn same_pixels(original: &RgbImage, output: &RgbImage, rect: Option<(u32, u32, u32, u32)>) -> bool {
+    let (width, height) = output.dimensions();
+    if width == 0 || height == 0 || original.width() == 0 || original.height() == 0 {
+        return false;
+    }
+    let aspect = |w: u32, h: u32| w as f64 / h as f64;
+    let (original_aspect, output_aspect) = (
+        aspect(original.width(), original.height()),
+        aspect(width, height),
+    );
+    if (original_aspect - output_aspect).abs() > ASPECT_TOLERANCE * output_aspect {
+        return false;
+    }
+    let resized;
+    let original = if original.dimensions() == (width, height) {
+        original
+    } else {
+        resized = image::imageops::resize(original, width, height, FilterType::Triangle);
+        &resized
+    };
+
+    let (x_min, y_min, x_max, y_max) = rect.unwrap_or((0, 0, width, height));
+    let (mut diff, mut darkness, mut samples) = (0u64, 0u64, 0u64);
+    for y in y_min..y_max.min(height) {
+        for x in x_min..x_max.min(width) {
+            let (a, b) = (original.get_pixel(x, y).0, output.get_pixel(x, y).0);
+            for channel in 0..3 {
+                diff += a[channel].abs_diff(b[channel]) as u64;
+                darkness += (255 - a[channel]) as u64;
+                samples += 1;
+            }
+        }
+    }
+    if samples == 0 {
+        return false;
+    }
+    let mean = |sum: u64| sum as f64 / samples as f64;
+    mean(diff) <= SAME_PIXELS_TOLERANCE && mean(darkness) > SAME_PIXELS_TOLERANCE
+}
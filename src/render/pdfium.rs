@@ -72,6 +72,30 @@ where
     })
 }
 
+/// Rasterization options for [`render_page_with_options`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderOptions {
+    /// Anti-alias text and vector paths (text converted to outlines is drawn
+    /// as paths). Disabling it yields hard black/white glyph edges that
+    /// binarize cleanly into the MRC text mask.
+    pub text_antialias: bool,
+    /// The page's /UserUnit (see [`PdfReader::page_user_unit`]). pdfium
+    /// reports page sizes in default user space units, so the target size is
+    /// scaled by this to keep `dpi` per physical inch.
+    ///
+    /// [`PdfReader::page_user_unit`]: crate::pdf::reader::PdfReader::page_user_unit
+    pub user_unit: f32,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            text_antialias: true,
+            user_unit: 1.0,
+        }
+    }
+}
+
 /// Renders a PDF page at the specified DPI and returns a DynamicImage.
 ///
 /// The PDF is loaded from disk, the specified page is rendered to an in-memory
@@ -94,6 +118,16 @@ pub fn render_page(
     pdf_path: &Path,
     page_index: u32,
     dpi: u32,
+) -> crate::error::Result<DynamicImage> {
+    render_page_with_options(pdf_path, page_index, dpi, &RenderOptions::default())
+}
+
+/// Renders a PDF page like [`render_page`] with explicit rasterization options.
+pub fn render_page_with_options(
+    pdf_path: &Path,
+    page_index: u32,
+    dpi: u32,
+    options: &RenderOptions,
 ) -> crate::error::Result<DynamicImage> {
     if dpi == 0 {
         return Err(crate::error::PdfMaskError::render(
@@ -117,18 +151,27 @@ pub fn render_page(
 
         let page = document.pages().get(page_index_u16)?;
 
-        // PDF default user unit: 1 point = 1/72 inch
-        // At the given DPI, each point maps to (dpi / 72) pixels
+        // One user space unit is user_unit / 72 inch (1/72 inch by default).
+        // At the given DPI, each unit maps to (user_unit * dpi / 72) pixels.
+        // The size matches `PdfReader::page_pixel_size`.
         let width_pts = page.width().value;
         let height_pts = page.height().value;
-        let width_px = (width_pts * dpi as f32 / 72.0).round() as i32;
-        let height_px = (height_pts * dpi as f32 / 72.0).round() as i32;
+        let width_px = (width_pts * options.user_unit * dpi as f32 / 72.0).round() as i32;
+        let height_px = (height_pts * options.user_unit * dpi as f32 / 72.0).round() as i32;
 
         let config = PdfRenderConfig::new()
             .set_target_width(width_px)
-            .set_target_height(height_px);
+            .set_target_height(height_px)
+            .set_text_smoothing(options.text_antialias)
+            .set_path_smoothing(options.text_antialias);
 
-        debug!(page = page_index, width_px, height_px, "rendering page");
+        debug!(
+            page = page_index,
+            width_px,
+            height_px,
+            text_antialias = options.text_antialias,
+            "rendering page"
+        );
         let bitmap = page.render_with_config(&config)?;
 
         Ok(bitmap.as_image())
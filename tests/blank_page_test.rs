@@ -0,0 +1,163 @@
+// 白紙ページ検出（blank_page_action）のテスト
+
+use std::collections::HashMap;
+
+use lopdf::{Document, Object, Stream, dictionary};
+use pdf_masking::config::job::{BlankPageAction, ColorMode};
+use pdf_masking::pipeline::job_runner::{JobConfig, run_job};
+
+/// ヘルパー: 1ページ目に線画、2ページ目は何も描画しない2ページPDFを作成する
+fn create_pdf_with_blank_page(path: &std::path::Path) {
+    let mut doc = Document::with_version("1.7");
+    let pages_id = doc.new_object_id();
+
+    let mut kids = Vec::new();
+    for content in [b"0 0 m 100 100 l S".to_vec(), b"q Q".to_vec()] {
+        let contents_id = doc.add_object(Stream::new(dictionary! {}, content));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+            "Contents" => contents_id,
+        });
+        kids.push(page_id.into());
+    }
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => 2,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.save(path).expect("failed to save test PDF");
+}
+
+/// ヘルパー: 1ページ目はSkip（そのままコピー）、2ページ目はRGB処理対象のジョブ設定
+fn job_config(
+    input: std::path::PathBuf,
+    output: std::path::PathBuf,
+    action: BlankPageAction,
+) -> JobConfig {
+    JobConfig {
+        input_path: input,
+        output_path: output,
+        color_mode_overrides: HashMap::from([(1, ColorMode::Skip)]),
+        blank_page_action: action,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_drop_blank_page_yields_single_page_output() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("duplex.pdf");
+    let output = dir.path().join("duplex_out.pdf");
+    create_pdf_with_blank_page(&input);
+
+    let config = job_config(input, output.clone(), BlankPageAction::Drop);
+    let result = run_job(&config).expect("job should succeed");
+    assert_eq!(result.pages_processed, 1);
+    assert_eq!(result.pages_dropped, 1);
+
+    let doc = Document::load(&output).expect("load output PDF");
+    let pages = doc.get_pages();
+    assert_eq!(pages.len(), 1, "blank page should be dropped");
+    let content = doc.get_page_content(pages[&1]).expect("page content");
+    assert_eq!(content, b"0 0 m 100 100 l S");
+}
+
+#[test]
+fn test_skip_blank_page_replaces_with_empty_page() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("duplex.pdf");
+    let output = dir.path().join("duplex_out.pdf");
+    create_pdf_with_blank_page(&input);
+
+    let config = job_config(input, output.clone(), BlankPageAction::Skip);
+    let result = run_job(&config).expect("job should succeed");
+    assert_eq!(result.pages_processed, 2);
+    assert_eq!(result.pages_dropped, 0);
+
+    let doc = Document::load(&output).expect("load output PDF");
+    let pages = doc.get_pages();
+    assert_eq!(pages.len(), 2, "page count should be preserved");
+    let blank = doc.get_dictionary(pages[&2]).expect("page 2");
+    assert!(!blank.has(b"Contents"), "blank page should have no content");
+    let media_box = blank
+        .get(b"MediaBox")
+        .and_then(Object::as_array)
+        .expect("MediaBox");
+    assert_eq!(media_box[2].as_float().unwrap(), 612.0);
+    assert_eq!(media_box[3].as_float().unwrap(), 792.0);
+}
+
+#[test]
+fn test_minimize_blank_writes_white_page_without_images() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("duplex.pdf");
+    let output = dir.path().join("duplex_out.pdf");
+    create_pdf_with_blank_page(&input);
+
+    let mut config = job_config(input, output.clone(), BlankPageAction::Keep);
+    config.minimize_blank = true;
+    let result = run_job(&config).expect("job should succeed");
+    assert_eq!(result.pages_processed, 2);
+    assert_eq!(result.pages_dropped, 0);
+
+    let doc = Document::load(&output).expect("load output PDF");
+    let pages = doc.get_pages();
+    assert_eq!(pages.len(), 2, "page count should be preserved");
+    let content = doc.get_page_content(pages[&2]).expect("page 2 content");
+    assert_eq!(content, b"1 g 0 0 612 792 re f");
+
+    // 画像XObject（BgImg/FgImg）は作られない
+    let blank = doc.get_dictionary(pages[&2]).expect("page 2");
+    let resources = blank
+        .get(b"Resources")
+        .and_then(Object::as_dict)
+        .expect("Resources");
+    assert!(!resources.has(b"XObject"), "got {resources:?}");
+    let image_count = doc
+        .objects
+        .values()
+        .filter_map(|obj| obj.as_stream().ok())
+        .filter(|stream| {
+            stream
+                .dict
+                .get(b"Subtype")
+                .and_then(Object::as_name)
+                .is_ok_and(|subtype| subtype == b"Image")
+        })
+        .count();
+    assert_eq!(image_count, 0, "no image XObjects should be written");
+}
+
+#[test]
+fn test_max_output_pages_counts_pages_after_dropping_blanks() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("duplex.pdf");
+    let output = dir.path().join("duplex_out.pdf");
+    create_pdf_with_blank_page(&input);
+
+    // 2ページを出力するジョブは上限1で失敗し、出力ファイルを書き出さない
+    let mut config = job_config(input.clone(), output.clone(), BlankPageAction::Skip);
+    config.max_output_pages = Some(1);
+    let Err(err) = run_job(&config) else {
+        panic!("job exceeding max_output_pages should fail");
+    };
+    assert!(err.to_string().contains("max_output_pages"), "got: {err}");
+    assert!(!output.exists(), "output should not be written");
+
+    // 白紙ページを除いた1ページなら上限内
+    let mut config = job_config(input, output.clone(), BlankPageAction::Drop);
+    config.max_output_pages = Some(1);
+    let result = run_job(&config).expect("job within the limit should succeed");
+    assert_eq!(result.pages_processed, 1);
+    assert!(output.exists());
+}
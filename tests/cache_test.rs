@@ -5,12 +5,17 @@
 // Tests for cache key computation (hash.rs) and file-system cache store (store.rs).
 // Each test verifies a specific aspect of the caching layer.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 
-use pdf_masking::cache::hash::{CacheSettings, compute_cache_key};
+use pdf_masking::cache::hash::{CacheSettings, compute_cache_key, compute_mask_cache_key};
 use pdf_masking::cache::store::CacheStore;
-use pdf_masking::config::job::ColorMode;
+use pdf_masking::config::job::{
+    ColorMode, JpegSizeMismatch, OutputColorSpace, ResampleFilter, WidthSource,
+};
+use pdf_masking::config::settings::{
+    DEFAULT_MAX_IMAGE_PIXELS, ImageClassDpi, Jbig2Options, JpegEscalation, LineRemoval,
+};
 use pdf_masking::mrc::{ImageModification, MrcLayers, PageOutput, TextMaskedData, TextRegionCrop};
 use pdf_masking::pdf::content_stream::BBox;
 use tempfile::tempdir;
@@ -27,6 +32,22 @@ fn test_compute_cache_key() {
         bg_quality: 50,
         fg_quality: 30,
         color_mode: ColorMode::Rgb,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
     };
 
     let key = compute_cache_key(content, &settings, Path::new("test.pdf"), 0);
@@ -49,6 +70,22 @@ fn test_cache_key_deterministic() {
         bg_quality: 50,
         fg_quality: 30,
         color_mode: ColorMode::Rgb,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
     };
 
     let key1 = compute_cache_key(content, &settings, Path::new("test.pdf"), 0);
@@ -66,6 +103,22 @@ fn test_cache_key_differs_with_different_content() {
         bg_quality: 50,
         fg_quality: 30,
         color_mode: ColorMode::Rgb,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
     };
 
     let key_a = compute_cache_key(b"content A", &settings, Path::new("test.pdf"), 0);
@@ -88,6 +141,22 @@ fn test_cache_key_differs_with_different_settings() {
         bg_quality: 50,
         fg_quality: 30,
         color_mode: ColorMode::Rgb,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
     };
     let settings_b = CacheSettings {
         dpi: 600,
@@ -95,6 +164,22 @@ fn test_cache_key_differs_with_different_settings() {
         bg_quality: 80,
         fg_quality: 60,
         color_mode: ColorMode::Rgb,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
     };
 
     let key_a = compute_cache_key(content, &settings_a, Path::new("test.pdf"), 0);
@@ -116,6 +201,22 @@ fn test_cache_key_differs_with_different_pdf_path() {
         bg_quality: 50,
         fg_quality: 30,
         color_mode: ColorMode::Rgb,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
     };
 
     let key_a = compute_cache_key(content, &settings, Path::new("file_a.pdf"), 0);
@@ -137,6 +238,22 @@ fn test_cache_key_differs_with_different_page_index() {
         bg_quality: 50,
         fg_quality: 30,
         color_mode: ColorMode::Rgb,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
     };
 
     let key_a = compute_cache_key(content, &settings, Path::new("test.pdf"), 0);
@@ -164,6 +281,7 @@ fn sample_layers() -> MrcLayers {
         page_width_pts: 595.276,
         page_height_pts: 841.89,
         color_mode: ColorMode::Rgb,
+        output_colorspace: OutputColorSpace::Rgb,
     }
 }
 
@@ -351,6 +469,7 @@ fn sample_text_masked_data() -> TextMaskedData {
             },
             pixel_width: 128,
             pixel_height: 100,
+            placement: None,
         }],
         modified_images: HashMap::new(),
         page_index: 0,
@@ -414,6 +533,10 @@ fn test_store_and_retrieve_text_masked_with_modified_images() {
             filter: "DCTDecode".to_string(),
             color_space: "DeviceGray".to_string(),
             bits_per_component: 8,
+            width: 40,
+            height: 30,
+            redacted_regions: Vec::new(),
+            smask: Some(vec![0x11; 20]),
         },
     );
 
@@ -448,6 +571,8 @@ fn test_store_and_retrieve_text_masked_with_modified_images() {
             assert_eq!(img.filter, "DCTDecode");
             assert_eq!(img.color_space, "DeviceGray");
             assert_eq!(img.bits_per_component, 8);
+            assert_eq!((img.width, img.height), (40, 30));
+            assert_eq!(img.smask, Some(vec![0x11; 20]));
         }
         other => panic!(
             "expected PageOutput::TextMasked, got {:?}",
@@ -489,6 +614,10 @@ fn test_text_masked_cache_files_on_disk() {
             filter: "FlateDecode".to_string(),
             color_space: "DeviceRGB".to_string(),
             bits_per_component: 8,
+            width: 40,
+            height: 30,
+            redacted_regions: Vec::new(),
+            smask: None,
         },
     );
 
@@ -504,6 +633,7 @@ fn test_text_masked_cache_files_on_disk() {
             },
             pixel_width: 50,
             pixel_height: 50,
+            placement: None,
         }],
         modified_images,
         page_index: 0,
@@ -522,3 +652,109 @@ fn test_text_masked_cache_files_on_disk() {
     assert!(key_dir.join("region_0.jbig2").exists());
     assert!(key_dir.join("modified_Im1.bin").exists());
 }
+
+// ---- mask cache tests ----
+
+/// Test that switching RGB -> Grayscale changes the page key but reuses the
+/// cached mask bytes stored under the color-mode independent mask key.
+#[test]
+fn test_mask_cache_is_shared_across_color_modes() {
+    let dir = tempdir().expect("failed to create temp dir");
+    let store = CacheStore::new(dir.path());
+    let content = b"BT /F1 12 Tf (Hello) Tj ET";
+    let rgb = CacheSettings {
+        dpi: 300,
+        fg_dpi: 150,
+        bg_quality: 50,
+        fg_quality: 30,
+        color_mode: ColorMode::Rgb,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
+    };
+    let gray = CacheSettings {
+        color_mode: ColorMode::Grayscale,
+        ..rgb.clone()
+    };
+    let path = Path::new("test.pdf");
+
+    assert_ne!(
+        compute_cache_key(content, &rgb, path, 0),
+        compute_cache_key(content, &gray, path, 0)
+    );
+    let rgb_mask_key = compute_mask_cache_key(content, &rgb, path, 0);
+    let gray_mask_key = compute_mask_cache_key(content, &gray, path, 0);
+    assert_eq!(rgb_mask_key, gray_mask_key);
+    assert_ne!(rgb_mask_key, compute_cache_key(content, &rgb, path, 0));
+
+    let mask = vec![0x97, 0x4A, 0x42, 0x32];
+    store
+        .store_mask(&rgb_mask_key, &mask, 200, 300)
+        .expect("store mask");
+    assert!(store.contains(&rgb_mask_key));
+    assert_eq!(
+        store
+            .retrieve_mask(&gray_mask_key, (200, 300))
+            .expect("retrieve mask"),
+        Some(mask)
+    );
+    // 寸法が異なればミス。マスク単体はページ出力として返さない
+    assert_eq!(
+        store
+            .retrieve_mask(&gray_mask_key, (400, 600))
+            .expect("retrieve mask"),
+        None
+    );
+    assert!(
+        store
+            .retrieve(&gray_mask_key, ColorMode::Grayscale, Some((200, 300)))
+            .expect("retrieve")
+            .is_none()
+    );
+}
+
+/// Test that a different DPI produces a different mask key.
+#[test]
+fn test_mask_cache_key_differs_with_dpi() {
+    let settings = |dpi| CacheSettings {
+        dpi,
+        fg_dpi: 150,
+        bg_quality: 50,
+        fg_quality: 30,
+        color_mode: ColorMode::Rgb,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
+    };
+    let path = Path::new("test.pdf");
+    assert_ne!(
+        compute_mask_cache_key(b"content", &settings(300), path, 0),
+        compute_mask_cache_key(b"content", &settings(600), path, 0)
+    );
+}
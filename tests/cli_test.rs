@@ -133,3 +133,473 @@ fn test_main_nonexistent_job_file() {
         "stderr should contain error message, got: {stderr}"
     );
 }
+
+// ============================================================
+// 6. Invalid override flag value produces error
+// ============================================================
+
+#[test]
+fn test_main_invalid_dpi_flag() {
+    let output = cargo_bin()
+        .args(["--dpi", "abc", "jobs.yaml"])
+        .output()
+        .expect("failed to execute binary");
+
+    assert!(
+        !output.status.success(),
+        "should exit with failure for invalid --dpi"
+    );
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("--dpi"),
+        "stderr should mention --dpi, got: {stderr}"
+    );
+}
+
+// ============================================================
+// 7. Exit codes distinguish config errors and job failures
+// ============================================================
+
+/// ヘルパー: 1ページの最小PDFを作成する
+fn write_minimal_pdf(path: &std::path::Path) {
+    use lopdf::{Document, Object, dictionary};
+
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.save(path).expect("save test PDF");
+}
+
+#[test]
+fn test_main_unparseable_job_file_exits_2() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let job_path = dir.path().join("jobs.yaml");
+    std::fs::write(&job_path, "jobs: [this is: not: valid").expect("write job file");
+
+    let output = cargo_bin()
+        .env("XDG_CONFIG_HOME", dir.path())
+        .arg(&job_path)
+        .output()
+        .expect("failed to execute binary");
+
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_main_partial_failure_exits_1_with_summary() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    write_minimal_pdf(&dir.path().join("ok.pdf"));
+    let job_path = dir.path().join("jobs.yaml");
+    std::fs::write(
+        &job_path,
+        r#"jobs:
+  - input: "ok.pdf"
+    output: "ok_out.pdf"
+    color_mode: skip
+    linearize: false
+  - input: "missing.pdf"
+    output: "missing_out.pdf"
+    color_mode: skip
+    linearize: false
+"#,
+    )
+    .expect("write job file");
+
+    let output = cargo_bin()
+        .env("XDG_CONFIG_HOME", dir.path())
+        .arg(&job_path)
+        .output()
+        .expect("failed to execute binary");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(dir.path().join("ok_out.pdf").exists());
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("2 jobs: 1 ok, 1 failed"),
+        "stderr should contain the summary line, got: {stderr}"
+    );
+}
+
+// ============================================================
+// 8. --print-config prints the resolved configuration without processing
+// ============================================================
+
+#[test]
+fn test_main_print_config_reflects_settings_override() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    std::fs::write(
+        dir.path().join("settings.yaml"),
+        "dpi: 150\nfg_quality: 40\n",
+    )
+    .expect("write settings file");
+    let job_path = dir.path().join("jobs.yaml");
+    std::fs::write(
+        &job_path,
+        r#"jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+    fg_quality: 20
+    bw_pages: "2"
+"#,
+    )
+    .expect("write job file");
+
+    let output = cargo_bin()
+        .env("XDG_CONFIG_HOME", dir.path())
+        .arg("--print-config")
+        .arg(&job_path)
+        .output()
+        .expect("failed to execute binary");
+
+    assert!(
+        output.status.success(),
+        "--print-config should succeed without an input PDF, stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!dir.path().join("out.pdf").exists(), "no job should run");
+
+    let printed: serde_yml::Value =
+        serde_yml::from_slice(&output.stdout).expect("stdout should be YAML");
+    let job = &printed[0];
+    // settings.yamlの値、ジョブの値、組み込みデフォルトがそれぞれ反映される
+    assert_eq!(job["dpi"].as_u64(), Some(150));
+    assert_eq!(job["fg_quality"].as_u64(), Some(20));
+    assert_eq!(job["bg_quality"].as_u64(), Some(50));
+    assert_eq!(job["color_mode"].as_str(), Some("rgb"));
+    assert_eq!(job["color_mode_overrides"][2].as_str(), Some("bw"));
+}
+
+#[test]
+fn test_main_quality_flag_accepts_preset_name() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let job_path = dir.path().join("jobs.yaml");
+    std::fs::write(
+        &job_path,
+        "jobs:\n  - input: \"in.pdf\"\n    output: \"out.pdf\"\n",
+    )
+    .expect("write job file");
+
+    let output = cargo_bin()
+        .env("XDG_CONFIG_HOME", dir.path())
+        .env("PDF_MASKING_FG_QUALITY", "Archival")
+        .args(["--print-config", "--bg-quality", "high"])
+        .arg(&job_path)
+        .output()
+        .expect("failed to execute binary");
+
+    assert!(
+        output.status.success(),
+        "preset names should be accepted, stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let printed: serde_yml::Value =
+        serde_yml::from_slice(&output.stdout).expect("stdout should be YAML");
+    assert_eq!(printed[0]["bg_quality"].as_u64(), Some(75));
+    assert_eq!(printed[0]["fg_quality"].as_u64(), Some(95));
+}
+
+// ============================================================
+// 9. --bench reports throughput without writing output
+// ============================================================
+
+#[test]
+fn test_main_bench_reports_throughput_without_output() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    write_minimal_pdf(&dir.path().join("in.pdf"));
+    let job_path = dir.path().join("jobs.yaml");
+    std::fs::write(
+        &job_path,
+        r#"jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+    color_mode: skip
+"#,
+    )
+    .expect("write job file");
+
+    let output = cargo_bin()
+        .env("XDG_CONFIG_HOME", dir.path())
+        .arg("--bench")
+        .arg(&job_path)
+        .output()
+        .expect("failed to execute binary");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "stderr: {stderr}");
+    assert!(
+        !dir.path().join("out.pdf").exists(),
+        "output must not be written"
+    );
+    assert!(
+        stderr.contains("bench: 1 pages") && stderr.contains("pages/s") && stderr.contains("MB/s"),
+        "stderr should contain the throughput line, got: {stderr}"
+    );
+}
+
+// ============================================================
+// 10. Output path templates
+// ============================================================
+
+#[test]
+fn test_main_output_template_expands_per_job() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let job_path = dir.path().join("jobs.yaml");
+    std::fs::write(
+        &job_path,
+        r#"jobs:
+  - input: "scan.pdf"
+    output: "{stem}-{index:02}.pdf"
+  - input: "scan.pdf"
+    output: "{stem}-{index:02}.pdf"
+"#,
+    )
+    .expect("write job file");
+
+    let output = cargo_bin()
+        .env("XDG_CONFIG_HOME", dir.path())
+        .arg("--print-config")
+        .arg(&job_path)
+        .output()
+        .expect("failed to execute binary");
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let printed: serde_yml::Value =
+        serde_yml::from_slice(&output.stdout).expect("stdout should be YAML");
+    let outputs: Vec<&str> = (0..2)
+        .map(|i| printed[i]["output"].as_str().expect("output path"))
+        .collect();
+    assert!(outputs[0].ends_with("scan-01.pdf"), "got {outputs:?}");
+    assert!(outputs[1].ends_with("scan-02.pdf"), "got {outputs:?}");
+}
+
+#[test]
+fn test_main_output_template_page_placeholder_exits_2() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let job_path = dir.path().join("jobs.yaml");
+    std::fs::write(
+        &job_path,
+        r#"jobs:
+  - input: "scan.pdf"
+    output: "{stem}-{page:03}.png"
+"#,
+    )
+    .expect("write job file");
+
+    let output = cargo_bin()
+        .env("XDG_CONFIG_HOME", dir.path())
+        .arg(&job_path)
+        .output()
+        .expect("failed to execute binary");
+
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("{page}"), "got: {stderr}");
+}
+
+// ============================================================
+// 11. --strict turns logged warnings into a failing exit code
+// ============================================================
+
+/// 壊れた埋め込みTrueType（BaseFontはシステムのDejaVuSans）でテキストを描く1ページPDF。
+/// アウトライン化のときにシステムフォントへの代替警告が出る。
+fn write_malformed_font_pdf(path: &std::path::Path) {
+    use lopdf::{Document, Object, Stream, dictionary};
+
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let font_file_id = doc.add_object(Stream::new(dictionary! {}, b"not a font".to_vec()));
+    let descriptor_id = doc.add_object(dictionary! {
+        "Type" => "FontDescriptor",
+        "FontName" => "DejaVuSans",
+        "FontFile2" => font_file_id,
+    });
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "TrueType",
+        "BaseFont" => "DejaVuSans",
+        "Encoding" => "WinAnsiEncoding",
+        "FontDescriptor" => descriptor_id,
+    });
+    let contents_id = doc.add_object(Stream::new(
+        dictionary! {},
+        b"BT /F1 24 Tf 72 700 Td (Hello) Tj ET".to_vec(),
+    ));
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "Contents" => contents_id,
+        "Resources" => dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+        },
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.save(path).expect("save test PDF");
+}
+
+#[test]
+fn test_main_strict_fails_on_font_substitution_warning() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    write_malformed_font_pdf(&dir.path().join("in.pdf"));
+    let job_path = dir.path().join("jobs.yaml");
+    std::fs::write(
+        &job_path,
+        r#"jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+    color_mode: rgb
+    linearize: false
+"#,
+    )
+    .expect("write job file");
+    // キャッシュは一時ディレクトリに書き、カレントディレクトリの.cacheを作らない
+    std::fs::write(
+        dir.path().join("settings.yaml"),
+        format!("cache_dir: '{}'\n", dir.path().join("cache").display()),
+    )
+    .expect("write settings file");
+
+    let output = cargo_bin()
+        .env("XDG_CONFIG_HOME", dir.path())
+        .arg(&job_path)
+        .output()
+        .expect("failed to execute binary");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "stderr: {stderr}");
+    assert!(
+        stderr.contains("substituting a system font") && stderr.contains("warning(s) logged"),
+        "the substitution should be reported, got: {stderr}"
+    );
+
+    // RUST_LOG=offで警告が表示されなくても--strictでは失敗する
+    let output = cargo_bin()
+        .env("XDG_CONFIG_HOME", dir.path())
+        .env("RUST_LOG", "off")
+        .arg("--strict")
+        .arg(&job_path)
+        .output()
+        .expect("failed to execute binary");
+    assert_eq!(output.status.code(), Some(4));
+    assert!(dir.path().join("out.pdf").exists());
+}
+
+#[test]
+fn test_main_strict_succeeds_without_warnings() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    write_minimal_pdf(&dir.path().join("in.pdf"));
+    let job_path = dir.path().join("jobs.yaml");
+    std::fs::write(
+        &job_path,
+        r#"jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+    color_mode: skip
+    linearize: false
+"#,
+    )
+    .expect("write job file");
+
+    let output = cargo_bin()
+        .env("XDG_CONFIG_HOME", dir.path())
+        .arg("--strict")
+        .arg(&job_path)
+        .output()
+        .expect("failed to execute binary");
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+// ============================================================
+// 12. --compare writes a side-by-side before/after PDF
+// ============================================================
+
+#[test]
+fn test_main_compare_writes_double_width_pages() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    write_minimal_pdf(&dir.path().join("in.pdf"));
+    let job_path = dir.path().join("jobs.yaml");
+    std::fs::write(
+        &job_path,
+        r#"jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+    color_mode: skip
+    linearize: false
+"#,
+    )
+    .expect("write job file");
+    let compare_dir = dir.path().join("compare");
+
+    let output = cargo_bin()
+        .env("XDG_CONFIG_HOME", dir.path())
+        .arg("--compare")
+        .arg(&compare_dir)
+        .arg(&job_path)
+        .output()
+        .expect("failed to execute binary");
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let doc =
+        lopdf::Document::load(compare_dir.join("out_compare.pdf")).expect("load comparison PDF");
+    let pages = doc.get_pages();
+    assert_eq!(pages.len(), 1);
+    let page = doc.get_dictionary(pages[&1]).expect("page dictionary");
+    let media_box: Vec<f32> = page
+        .get(b"MediaBox")
+        .and_then(lopdf::Object::as_array)
+        .expect("MediaBox")
+        .iter()
+        .map(|v| v.as_float().expect("number"))
+        .collect();
+    // 元ページ(612x792)とマスク後のページを左右に並べる
+    assert_eq!(media_box, vec![0.0, 0.0, 1224.0, 792.0]);
+
+    let content =
+        String::from_utf8_lossy(&doc.get_page_content(pages[&1]).expect("content")).into_owned();
+    assert!(
+        content.contains("/Before Do") && content.contains("612 0 cm /After Do"),
+        "comparison page should place both pages: {content}"
+    );
+}
@@ -231,6 +231,87 @@ jobs:
     assert_eq!(page_modes.get(&3), Some(&ColorMode::Skip));
 }
 
+#[test]
+fn test_resolve_page_modes_conflict_names_page_and_lists() {
+    let yaml = r#"
+jobs:
+  - input: "input.pdf"
+    output: "output.pdf"
+    bw_pages: [3]
+    skip_pages: [1, 3]
+"#;
+    let job_file: JobFile = serde_yml::from_str(yaml).expect("should parse");
+    let err = job_file.jobs[0]
+        .resolve_page_modes()
+        .expect_err("page 3 in two lists should be rejected");
+
+    let msg = err.to_string();
+    assert!(msg.contains("Page 3"), "should name the page: {msg}");
+    assert!(msg.contains("bw_pages"), "should name bw_pages: {msg}");
+    assert!(msg.contains("skip_pages"), "should name skip_pages: {msg}");
+}
+
+#[test]
+fn test_resolve_page_modes_duplicate_within_same_list_is_ok() {
+    let yaml = r#"
+jobs:
+  - input: "input.pdf"
+    output: "output.pdf"
+    bw_pages: [3, "2-4"]
+"#;
+    let job_file: JobFile = serde_yml::from_str(yaml).expect("should parse");
+    let page_modes = job_file.jobs[0]
+        .resolve_page_modes()
+        .expect("duplicates within one list are not a conflict");
+    assert_eq!(page_modes.get(&3), Some(&ColorMode::Bw));
+}
+
+#[test]
+fn test_resolve_page_modes_expands_range_in_sequence() {
+    let yaml = r#"
+jobs:
+  - input: "input.pdf"
+    output: "output.pdf"
+    grayscale_pages: ["2-4", 7]
+"#;
+    let job_file: JobFile = serde_yml::from_str(yaml).expect("should parse");
+    let job = &job_file.jobs[0];
+
+    let page_modes = job.resolve_page_modes().expect("should resolve");
+
+    assert_eq!(page_modes.len(), 4);
+    for page in [2, 3, 4, 7] {
+        assert_eq!(page_modes.get(&page), Some(&ColorMode::Grayscale));
+    }
+    assert_eq!(page_modes.get(&5), None);
+}
+
+#[test]
+fn test_reversed_range_in_sequence_is_error() {
+    let yaml = r#"
+jobs:
+  - input: "input.pdf"
+    output: "output.pdf"
+    bw_pages: ["10-5"]
+"#;
+    let result: Result<JobFile, _> = serde_yml::from_str(yaml);
+    assert!(result.is_err(), "reversed range should fail to parse");
+}
+
+#[test]
+fn test_overlapping_ranges_across_modes_conflict() {
+    let yaml = r#"
+jobs:
+  - input: "input.pdf"
+    output: "output.pdf"
+    grayscale_pages: ["50-200"]
+    skip_pages: ["190-210"]
+"#;
+    let job_file: JobFile = serde_yml::from_str(yaml).expect("should parse");
+    let result = job_file.jobs[0].resolve_page_modes();
+    assert!(result.is_err(), "overlapping ranges should conflict");
+}
+
 // ============================================================
 // 4. Settings にデフォルト color_mode を追加
 // ============================================================
@@ -287,3 +368,58 @@ jobs:
 
     assert_eq!(merged.color_mode, ColorMode::Grayscale);
 }
+
+// ============================================================
+// 6. preserve_images / text_to_outlines のページ別オーバーライド
+// ============================================================
+
+#[test]
+fn test_resolve_page_flag_overrides() {
+    let yaml = r#"
+jobs:
+  - input: "input.pdf"
+    output: "output.pdf"
+    preserve_images: false
+    preserve_images_pages: [2, 3]
+    no_text_to_outlines_pages: "4-5"
+"#;
+    let job_file: JobFile = serde_yml::from_str(yaml).expect("should parse");
+    let job = &job_file.jobs[0];
+
+    let merged = MergedConfig::new(&Settings::default(), job);
+    assert!(!merged.preserve_images);
+    assert!(merged.text_to_outlines);
+
+    let preserve = job.resolve_preserve_images_pages().expect("resolve");
+    assert_eq!(preserve.get(&2), Some(&true));
+    assert_eq!(preserve.get(&3), Some(&true));
+    assert_eq!(preserve.get(&1), None);
+
+    let outlines = job.resolve_text_to_outlines_pages().expect("resolve");
+    assert_eq!(outlines.get(&4), Some(&false));
+    assert_eq!(outlines.get(&5), Some(&false));
+    assert_eq!(outlines.len(), 2);
+}
+
+#[test]
+fn test_resolve_page_flag_overrides_conflict() {
+    let yaml = r#"
+jobs:
+  - input: "input.pdf"
+    output: "output.pdf"
+    preserve_images_pages: [1, 2]
+    no_preserve_images_pages: [2]
+"#;
+    let job_file: JobFile = serde_yml::from_str(yaml).expect("should parse");
+
+    let err = job_file.jobs[0]
+        .resolve_preserve_images_pages()
+        .expect_err("page 2 is in both lists");
+    let msg = err.to_string();
+    assert!(
+        msg.contains("Page 2")
+            && msg.contains("preserve_images_pages")
+            && msg.contains("no_preserve_images_pages"),
+        "{msg}"
+    );
+}
@@ -3,10 +3,20 @@
 use std::io::Write;
 use std::path::Path;
 
-use pdf_masking::config::job::{JobFile, parse_page_range};
-use pdf_masking::config::load_settings_for_job;
+use pdf_masking::config::job::{
+    ColorMode, ForegroundMask, ImageColorSpace, JobFile, JpegSizeMismatch, MetadataMode,
+    OutputColorSpace, ResampleFilter, WidthSource, parse_page_range,
+};
 use pdf_masking::config::merged::MergedConfig;
-use pdf_masking::config::settings::Settings;
+use pdf_masking::config::output_template::{
+    OutputTemplateValues, expand_output_template, today_utc,
+};
+use pdf_masking::config::overrides::ConfigOverrides;
+use pdf_masking::config::settings::{
+    DEFAULT_MAX_CONTENT_STREAM_BYTES, DEFAULT_MAX_IMAGE_PIXELS, DEFAULT_PRODUCER, LineRemoval,
+    Settings,
+};
+use pdf_masking::config::{load_layered_settings, load_settings_for_job};
 
 // ============================================================
 // 1. ページ範囲パーサ
@@ -102,6 +112,63 @@ dpi: 150
     assert!(settings.linearize);
 }
 
+#[test]
+fn test_settings_jbig2_options() {
+    let yaml = r#"
+jbig2:
+  tpgdon: false
+"#;
+    let settings = Settings::from_yaml(yaml).expect("should parse jbig2 options");
+    assert!(!settings.jbig2.tpgdon);
+
+    // 未指定時は従来の動作（TPGDON有効）
+    let defaults = Settings::from_yaml("{}").expect("parse empty settings");
+    assert!(defaults.jbig2.tpgdon);
+}
+
+#[test]
+fn test_settings_jbig2_rejects_template_selection() {
+    // jbig2encはGBテンプレート0固定のため、テンプレート指定は受け付けない
+    let yaml = r#"
+jbig2:
+  template: 3
+"#;
+    assert!(Settings::from_yaml(yaml).is_err());
+}
+
+#[test]
+fn test_settings_jpeg_escalation() {
+    let yaml = r#"
+jpeg_escalation:
+  min_psnr: 28.5
+  max_quality: 80
+"#;
+    let settings = Settings::from_yaml(yaml).expect("should parse jpeg escalation");
+    assert_eq!(settings.jpeg_escalation.min_psnr, Some(28.5));
+    assert_eq!(settings.jpeg_escalation.step, 10);
+    assert_eq!(settings.jpeg_escalation.max_quality, 80);
+
+    // 未指定時は品質を引き上げない
+    let defaults = Settings::from_yaml("{}").expect("parse empty settings");
+    assert_eq!(defaults.jpeg_escalation.min_psnr, None);
+}
+
+#[test]
+fn test_settings_quality_preset() {
+    let yaml = r#"
+bg_quality: high
+fg_quality: 42.6
+"#;
+    let settings = Settings::from_yaml(yaml).expect("should parse quality presets");
+    assert_eq!(settings.bg_quality, 75);
+    assert_eq!(settings.fg_quality, 43);
+
+    let result = Settings::from_yaml("bg_quality: ultra");
+    assert!(result.is_err(), "unknown preset should be rejected");
+    let result = Settings::from_yaml("fg_quality: 101");
+    assert!(result.is_err(), "quality above 100 should be rejected");
+}
+
 // ============================================================
 // 3. Job 構造体のデシリアライズ
 // ============================================================
@@ -140,6 +207,21 @@ jobs:
     assert_eq!(job.linearize, Some(false));
 }
 
+#[test]
+fn test_job_quality_preset() {
+    let yaml = r#"
+jobs:
+  - input: "input.pdf"
+    output: "output.pdf"
+    bg_quality: archival
+    fg_quality: 20
+"#;
+    let job_file: JobFile = serde_yml::from_str(yaml).expect("should parse quality preset");
+    let job = &job_file.jobs[0];
+    assert_eq!(job.bg_quality, Some(95));
+    assert_eq!(job.fg_quality, Some(20));
+}
+
 #[test]
 fn test_job_missing_required_field() {
     // inputが欠損
@@ -200,6 +282,594 @@ jobs:
     assert_eq!(merged.dpi, 300, "should fall back to settings dpi");
 }
 
+#[test]
+fn test_merge_image_max_dpi() {
+    let settings = Settings::from_yaml("image_max_dpi: 200").expect("parse settings");
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+  - input: "in2.pdf"
+    output: "out2.pdf"
+    image_max_dpi: 150
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+    let merged = MergedConfig::new(&settings, &job_file.jobs[0]);
+    assert_eq!(merged.image_max_dpi, Some(200));
+    let merged = MergedConfig::new(&settings, &job_file.jobs[1]);
+    assert_eq!(merged.image_max_dpi, Some(150));
+
+    // 未指定時はダウンサンプリングしない
+    let merged = MergedConfig::new(&Settings::default(), &job_file.jobs[0]);
+    assert_eq!(merged.image_max_dpi, None);
+}
+
+#[test]
+fn test_merge_photo_and_line_art_max_dpi() {
+    let settings =
+        Settings::from_yaml("photo_max_dpi: 100\nline_art_max_dpi: 300").expect("parse settings");
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+  - input: "in2.pdf"
+    output: "out2.pdf"
+    photo_max_dpi: 72
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+    let merged = MergedConfig::new(&settings, &job_file.jobs[0]);
+    assert_eq!(merged.image_class_dpi.photo, Some(100));
+    assert_eq!(merged.image_class_dpi.line_art, Some(300));
+    let merged = MergedConfig::new(&settings, &job_file.jobs[1]);
+    assert_eq!(merged.image_class_dpi.photo, Some(72));
+    assert_eq!(merged.image_class_dpi.line_art, Some(300));
+
+    // 未指定時は画像を分類しない
+    let merged = MergedConfig::new(&Settings::default(), &job_file.jobs[0]);
+    assert!(!merged.image_class_dpi.is_enabled());
+}
+
+#[test]
+fn test_merge_normalize_images_to() {
+    let settings = Settings::from_yaml("normalize_images_to: rgb").expect("parse settings");
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+  - input: "in2.pdf"
+    output: "out2.pdf"
+    normalize_images_to: gray
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+    let merged = MergedConfig::new(&settings, &job_file.jobs[0]);
+    assert_eq!(merged.normalize_images_to, Some(ImageColorSpace::Rgb));
+    let merged = MergedConfig::new(&settings, &job_file.jobs[1]);
+    assert_eq!(merged.normalize_images_to, Some(ImageColorSpace::Gray));
+
+    // 未指定時は元の色空間のまま
+    let merged = MergedConfig::new(&Settings::default(), &job_file.jobs[0]);
+    assert_eq!(merged.normalize_images_to, None);
+}
+
+#[test]
+fn test_merge_font_substitutions() {
+    let settings = Settings::from_yaml(
+        "font_substitutions:\n  Arial: /fonts/LiberationSans.ttf\n  Courier: /fonts/LiberationMono.ttf\nfont_fallback: /fonts/DejaVuSans.ttf\n",
+    )
+    .expect("parse settings");
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+    font_substitutions:
+      Arial: fonts/Arimo.ttf
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+    let merged = MergedConfig::new(&settings, &job_file.jobs[0]);
+
+    // ジョブの指定はフォント名単位でsettingsを上書きする
+    assert_eq!(merged.font_substitutions.len(), 2);
+    assert_eq!(merged.font_substitutions["Arial"], "fonts/Arimo.ttf");
+    assert_eq!(
+        merged.font_substitutions["Courier"],
+        "/fonts/LiberationMono.ttf"
+    );
+    assert_eq!(
+        merged.font_fallback.as_deref(),
+        Some("/fonts/DejaVuSans.ttf")
+    );
+
+    let merged = MergedConfig::new(&Settings::default(), &job_file.jobs[0]);
+    assert_eq!(merged.font_fallback, None);
+}
+
+#[test]
+fn test_merge_resample_filter() {
+    let settings = Settings::from_yaml("resample_filter: nearest").expect("parse settings");
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+  - input: "in2.pdf"
+    output: "out2.pdf"
+    resample_filter: lanczos3
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+    let merged = MergedConfig::new(&settings, &job_file.jobs[0]);
+    assert_eq!(merged.resample_filter, ResampleFilter::Nearest);
+    let merged = MergedConfig::new(&settings, &job_file.jobs[1]);
+    assert_eq!(merged.resample_filter, ResampleFilter::Lanczos3);
+
+    // 未指定時は従来どおりtriangle
+    let merged = MergedConfig::new(&Settings::default(), &job_file.jobs[0]);
+    assert_eq!(merged.resample_filter, ResampleFilter::Triangle);
+}
+
+#[test]
+fn test_merge_width_source() {
+    let settings = Settings::from_yaml("width_source: font").expect("parse settings");
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+  - input: "in2.pdf"
+    output: "out2.pdf"
+    width_source: max
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+    let merged = MergedConfig::new(&settings, &job_file.jobs[0]);
+    assert_eq!(merged.width_source, WidthSource::Font);
+    let merged = MergedConfig::new(&settings, &job_file.jobs[1]);
+    assert_eq!(merged.width_source, WidthSource::Max);
+
+    // 未指定時は仕様どおり/Widthsを使う
+    let merged = MergedConfig::new(&Settings::default(), &job_file.jobs[0]);
+    assert_eq!(merged.width_source, WidthSource::Pdf);
+}
+
+#[test]
+fn test_merge_merge_white_fills() {
+    let settings = Settings::from_yaml("merge_white_fills: true").expect("parse settings");
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+  - input: "in2.pdf"
+    output: "out2.pdf"
+    merge_white_fills: false
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+    let merged = MergedConfig::new(&settings, &job_file.jobs[0]);
+    assert!(merged.merge_white_fills);
+    let merged = MergedConfig::new(&settings, &job_file.jobs[1]);
+    assert!(!merged.merge_white_fills);
+
+    // 未指定時は白色fillをまとめない
+    let merged = MergedConfig::new(&Settings::default(), &job_file.jobs[0]);
+    assert!(!merged.merge_white_fills);
+}
+
+#[test]
+fn test_merge_foreground_mask() {
+    let settings = Settings::from_yaml("foreground_mask: stencil").expect("parse settings");
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+  - input: "in2.pdf"
+    output: "out2.pdf"
+    foreground_mask: smask
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+    let merged = MergedConfig::new(&settings, &job_file.jobs[0]);
+    assert_eq!(merged.foreground_mask, ForegroundMask::Stencil);
+    let merged = MergedConfig::new(&settings, &job_file.jobs[1]);
+    assert_eq!(merged.foreground_mask, ForegroundMask::Smask);
+
+    // 未指定時は従来どおりSMask
+    let merged = MergedConfig::new(&Settings::default(), &job_file.jobs[0]);
+    assert_eq!(merged.foreground_mask, ForegroundMask::Smask);
+}
+
+#[test]
+fn test_merge_interpolate_output() {
+    let settings = Settings::from_yaml("interpolate_output: true").expect("parse settings");
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+  - input: "in2.pdf"
+    output: "out2.pdf"
+    interpolate_output: false
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+    assert!(MergedConfig::new(&settings, &job_file.jobs[0]).interpolate_output);
+    assert!(!MergedConfig::new(&settings, &job_file.jobs[1]).interpolate_output);
+    assert!(!MergedConfig::new(&Settings::default(), &job_file.jobs[0]).interpolate_output);
+}
+
+#[test]
+fn test_merge_tag_srgb() {
+    let settings = Settings::from_yaml("tag_srgb: true").expect("parse settings");
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+  - input: "in2.pdf"
+    output: "out2.pdf"
+    tag_srgb: false
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+    assert!(MergedConfig::new(&settings, &job_file.jobs[0]).tag_srgb);
+    assert!(!MergedConfig::new(&settings, &job_file.jobs[1]).tag_srgb);
+    assert!(!MergedConfig::new(&Settings::default(), &job_file.jobs[0]).tag_srgb);
+}
+
+#[test]
+fn test_merge_simplify_content() {
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+    simplify_content: true
+  - input: "in2.pdf"
+    output: "out2.pdf"
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+    let settings = Settings::default();
+    assert!(MergedConfig::new(&settings, &job_file.jobs[0]).simplify_content);
+    assert!(!MergedConfig::new(&settings, &job_file.jobs[1]).simplify_content);
+}
+
+#[test]
+fn test_merge_record_source_keys() {
+    let settings = Settings::from_yaml("record_source_keys: true").expect("parse settings");
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+  - input: "in2.pdf"
+    output: "out2.pdf"
+    record_source_keys: false
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+    assert!(MergedConfig::new(&settings, &job_file.jobs[0]).record_source_keys);
+    assert!(!MergedConfig::new(&settings, &job_file.jobs[1]).record_source_keys);
+    assert!(!MergedConfig::new(&Settings::default(), &job_file.jobs[0]).record_source_keys);
+}
+
+#[test]
+fn test_merge_split_content_bytes() {
+    let settings = Settings::from_yaml("split_content_bytes: 1048576").expect("parse settings");
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+  - input: "in2.pdf"
+    output: "out2.pdf"
+    split_content_bytes: 65536
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+    let merged = MergedConfig::new(&settings, &job_file.jobs[0]);
+    assert_eq!(merged.split_content_bytes, Some(1_048_576));
+    let merged = MergedConfig::new(&settings, &job_file.jobs[1]);
+    assert_eq!(merged.split_content_bytes, Some(65_536));
+
+    // 未指定時は分けない
+    let merged = MergedConfig::new(&Settings::default(), &job_file.jobs[0]);
+    assert_eq!(merged.split_content_bytes, None);
+}
+
+#[test]
+fn test_merge_producer() {
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+  - input: "in2.pdf"
+    output: "out2.pdf"
+    producer: "Records Office"
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+
+    // 既定値はクレート名とバージョン
+    let merged = MergedConfig::new(&Settings::default(), &job_file.jobs[0]);
+    assert_eq!(merged.producer.as_deref(), Some(DEFAULT_PRODUCER));
+    assert!(DEFAULT_PRODUCER.starts_with("pdf_masking "));
+
+    let settings = Settings::from_yaml("producer: \"\"").expect("parse settings");
+    let merged = MergedConfig::new(&settings, &job_file.jobs[0]);
+    assert_eq!(merged.producer.as_deref(), Some(""));
+    let merged = MergedConfig::new(&settings, &job_file.jobs[1]);
+    assert_eq!(merged.producer.as_deref(), Some("Records Office"));
+
+    // nullは元の/Producerを引き継ぐ指定
+    let settings = Settings::from_yaml("producer: null").expect("parse settings");
+    assert_eq!(
+        MergedConfig::new(&settings, &job_file.jobs[0]).producer,
+        None
+    );
+}
+
+#[test]
+fn test_merge_verify_redaction() {
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+  - input: "in2.pdf"
+    output: "out2.pdf"
+    verify_redaction: false
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+    let settings = Settings::from_yaml("verify_redaction: true").expect("parse settings");
+    assert!(MergedConfig::new(&settings, &job_file.jobs[0]).verify_redaction);
+    assert!(!MergedConfig::new(&settings, &job_file.jobs[1]).verify_redaction);
+}
+
+#[test]
+fn test_merge_share_copied_objects() {
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+  - input: "in2.pdf"
+    output: "out2.pdf"
+    share_copied_objects: true
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+    assert!(MergedConfig::new(&Settings::default(), &job_file.jobs[0]).share_copied_objects);
+
+    let settings = Settings::from_yaml("share_copied_objects: false").expect("parse settings");
+    assert!(!MergedConfig::new(&settings, &job_file.jobs[0]).share_copied_objects);
+    assert!(MergedConfig::new(&settings, &job_file.jobs[1]).share_copied_objects);
+}
+
+#[test]
+fn test_merge_optimize_skip_images() {
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+  - input: "in2.pdf"
+    output: "out2.pdf"
+    optimize_skip_images: false
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+    let settings = Settings::from_yaml("optimize_skip_images: true").expect("parse settings");
+    assert!(MergedConfig::new(&settings, &job_file.jobs[0]).optimize_skip_images);
+    assert!(!MergedConfig::new(&settings, &job_file.jobs[1]).optimize_skip_images);
+}
+
+#[test]
+fn test_merge_preserve_view() {
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+  - input: "in2.pdf"
+    output: "out2.pdf"
+    preserve_view: true
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+    let settings = Settings::default();
+    assert!(!MergedConfig::new(&settings, &job_file.jobs[0]).preserve_view);
+    assert!(MergedConfig::new(&settings, &job_file.jobs[1]).preserve_view);
+}
+
+#[test]
+fn test_merge_max_image_pixels() {
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+
+    let merged = MergedConfig::new(&Settings::default(), &job_file.jobs[0]);
+    assert_eq!(merged.max_image_pixels, DEFAULT_MAX_IMAGE_PIXELS);
+
+    let settings = Settings::from_yaml("max_image_pixels: 1000000").expect("parse settings");
+    let merged = MergedConfig::new(&settings, &job_file.jobs[0]);
+    assert_eq!(merged.max_image_pixels, 1_000_000);
+}
+
+#[test]
+fn test_merge_jpeg_size_mismatch() {
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+
+    let merged = MergedConfig::new(&Settings::default(), &job_file.jobs[0]);
+    assert_eq!(merged.jpeg_size_mismatch, JpegSizeMismatch::Warn);
+
+    let settings = Settings::from_yaml("jpeg_size_mismatch: error").expect("parse settings");
+    let merged = MergedConfig::new(&settings, &job_file.jobs[0]);
+    assert_eq!(merged.jpeg_size_mismatch, JpegSizeMismatch::Error);
+}
+
+#[test]
+fn test_merge_max_content_stream_bytes() {
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+
+    let merged = MergedConfig::new(&Settings::default(), &job_file.jobs[0]);
+    assert_eq!(
+        merged.max_content_stream_bytes,
+        DEFAULT_MAX_CONTENT_STREAM_BYTES
+    );
+
+    let settings =
+        Settings::from_yaml("max_content_stream_bytes: 1048576").expect("parse settings");
+    let merged = MergedConfig::new(&settings, &job_file.jobs[0]);
+    assert_eq!(merged.max_content_stream_bytes, 1_048_576);
+}
+
+#[test]
+fn test_merge_max_output_pages() {
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+
+    let merged = MergedConfig::new(&Settings::default(), &job_file.jobs[0]);
+    assert_eq!(merged.max_output_pages, None);
+
+    let settings = Settings::from_yaml("max_output_pages: 500").expect("parse settings");
+    let merged = MergedConfig::new(&settings, &job_file.jobs[0]);
+    assert_eq!(merged.max_output_pages, Some(500));
+}
+
+#[test]
+fn test_merge_line_removal() {
+    let settings = Settings::from_yaml("remove_hlines: true").expect("parse settings");
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+  - input: "in2.pdf"
+    output: "out2.pdf"
+    remove_hlines: false
+    remove_vlines: true
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+
+    let merged = MergedConfig::new(&settings, &job_file.jobs[0]);
+    assert_eq!(
+        merged.line_removal,
+        LineRemoval {
+            horizontal: true,
+            vertical: false,
+        }
+    );
+    let merged = MergedConfig::new(&settings, &job_file.jobs[1]);
+    assert_eq!(
+        merged.line_removal,
+        LineRemoval {
+            horizontal: false,
+            vertical: true,
+        }
+    );
+}
+
+#[test]
+fn test_merge_output_colorspace() {
+    let settings = Settings::from_yaml("output_colorspace: cmyk").expect("parse settings");
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+  - input: "in2.pdf"
+    output: "out2.pdf"
+    output_colorspace: rgb
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+    let merged = MergedConfig::new(&settings, &job_file.jobs[0]);
+    assert_eq!(merged.output_colorspace, OutputColorSpace::Cmyk);
+    let merged = MergedConfig::new(&settings, &job_file.jobs[1]);
+    assert_eq!(merged.output_colorspace, OutputColorSpace::Rgb);
+
+    let merged = MergedConfig::new(&Settings::default(), &job_file.jobs[0]);
+    assert_eq!(merged.output_colorspace, OutputColorSpace::Rgb);
+}
+
+#[test]
+fn test_merge_metadata_and_reproducible() {
+    let settings =
+        Settings::from_yaml("metadata: minimal\nreproducible: true").expect("parse settings");
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+  - input: "in2.pdf"
+    output: "out2.pdf"
+    metadata: preserve
+    reproducible: false
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+    let merged = MergedConfig::new(&settings, &job_file.jobs[0]);
+    assert_eq!(merged.metadata, MetadataMode::Minimal);
+    assert!(merged.reproducible);
+    let merged = MergedConfig::new(&settings, &job_file.jobs[1]);
+    assert_eq!(merged.metadata, MetadataMode::Preserve);
+    assert!(!merged.reproducible);
+
+    let merged = MergedConfig::new(&Settings::default(), &job_file.jobs[0]);
+    assert_eq!(merged.metadata, MetadataMode::Strip);
+    assert!(!merged.reproducible);
+}
+
+#[test]
+fn test_merge_fix_mirror_and_mirror_pages() {
+    let settings = Settings::from_yaml("fix_mirror: true").expect("parse settings");
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+    mirror_pages: [2, "4-5"]
+  - input: "in2.pdf"
+    output: "out2.pdf"
+    fix_mirror: false
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+    assert_eq!(job_file.jobs[0].mirror_pages, Some(vec![2, 4, 5]));
+    assert!(MergedConfig::new(&settings, &job_file.jobs[0]).fix_mirror);
+    assert!(!MergedConfig::new(&settings, &job_file.jobs[1]).fix_mirror);
+    assert!(!MergedConfig::new(&Settings::default(), &job_file.jobs[0]).fix_mirror);
+}
+
+#[test]
+fn test_merge_retry_empty_at_dpi() {
+    let settings = Settings::from_yaml("retry_empty_at_dpi: 600").expect("parse settings");
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+  - input: "in2.pdf"
+    output: "out2.pdf"
+    retry_empty_at_dpi: 450
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+    assert_eq!(
+        MergedConfig::new(&settings, &job_file.jobs[0]).retry_empty_at_dpi,
+        Some(600)
+    );
+    assert_eq!(
+        MergedConfig::new(&settings, &job_file.jobs[1]).retry_empty_at_dpi,
+        Some(450)
+    );
+    assert_eq!(
+        MergedConfig::new(&Settings::default(), &job_file.jobs[0]).retry_empty_at_dpi,
+        None
+    );
+}
+
+#[test]
+fn test_merge_text_antialias() {
+    let settings = Settings::from_yaml("text_antialias: false").expect("parse settings");
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+  - input: "in2.pdf"
+    output: "out2.pdf"
+    text_antialias: true
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+    assert!(!MergedConfig::new(&settings, &job_file.jobs[0]).text_antialias);
+    assert!(MergedConfig::new(&settings, &job_file.jobs[1]).text_antialias);
+    assert!(MergedConfig::new(&Settings::default(), &job_file.jobs[0]).text_antialias);
+}
+
 #[test]
 fn test_merge_no_settings_uses_defaults() {
     let settings = Settings::default();
@@ -251,3 +921,203 @@ fn test_auto_detect_settings_yaml_missing() {
         "should use default when settings.yaml absent"
     );
 }
+
+#[test]
+fn test_job_dir_settings_override_global_settings() {
+    let global_dir = tempfile::tempdir().expect("create global dir");
+    let global_path = global_dir.path().join("settings.yaml");
+    std::fs::write(
+        &global_path,
+        "dpi: 600\nbg_quality: 70\nfg_quality: 40\njbig2:\n  tpgdon: false\n",
+    )
+    .expect("write global settings");
+
+    let job_dir = tempfile::tempdir().expect("create job dir");
+    std::fs::write(
+        job_dir.path().join("settings.yaml"),
+        "dpi: 200\njbig2:\n  tpgdon: true\n",
+    )
+    .expect("write job-dir settings");
+    let job_path = job_dir.path().join("jobs.yaml");
+    std::fs::File::create(&job_path).expect("create jobs.yaml");
+
+    let settings =
+        load_layered_settings(Some(&global_path), &job_path).expect("should load layered");
+    // ジョブディレクトリの値がグローバル値を上書きする
+    assert_eq!(settings.dpi, 200);
+    assert!(settings.jbig2.tpgdon);
+    // ジョブディレクトリで未指定のキーはグローバル値を引き継ぐ
+    assert_eq!(settings.bg_quality, 70);
+    assert_eq!(settings.fg_quality, 40);
+    // どちらにも無いキーは組み込みデフォルト
+    assert_eq!(settings.fg_dpi, 100);
+}
+
+#[test]
+fn test_global_settings_used_without_job_dir_settings() {
+    let global_dir = tempfile::tempdir().expect("create global dir");
+    let global_path = global_dir.path().join("settings.yaml");
+    std::fs::write(&global_path, "dpi: 600\n").expect("write global settings");
+
+    let job_dir = tempfile::tempdir().expect("create job dir");
+    let job_path = job_dir.path().join("jobs.yaml");
+    std::fs::File::create(&job_path).expect("create jobs.yaml");
+
+    let settings =
+        load_layered_settings(Some(&global_path), &job_path).expect("should load global");
+    assert_eq!(settings.dpi, 600);
+
+    // グローバル設定ファイルが存在しない場合はデフォルト
+    let missing = global_dir.path().join("missing.yaml");
+    let settings = load_layered_settings(Some(&missing), &job_path).expect("should default");
+    assert_eq!(settings.dpi, 300);
+}
+
+// ============================================================
+// 6. CLI・環境変数による上書き
+// ============================================================
+
+#[test]
+fn test_cli_dpi_overrides_yaml_dpi() {
+    let settings = Settings::from_yaml("dpi: 600").expect("parse settings");
+    let job_yaml = r#"
+jobs:
+  - input: "in.pdf"
+    output: "out.pdf"
+    dpi: 400
+"#;
+    let job_file: JobFile = serde_yml::from_str(job_yaml).expect("parse job");
+
+    let args: Vec<String> = ["--dpi", "150", "jobs.yaml"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let (overrides, job_files) = ConfigOverrides::from_args(&args).expect("parse args");
+    assert_eq!(job_files, vec!["jobs.yaml".to_string()]);
+
+    let mut merged = MergedConfig::new(&settings, &job_file.jobs[0]);
+    overrides.apply(&mut merged);
+    assert_eq!(
+        merged.dpi, 150,
+        "--dpi should win over settings and job YAML"
+    );
+    // 指定していない項目はそのまま
+    assert_eq!(merged.bg_quality, 50);
+}
+
+#[test]
+fn test_cli_overrides_take_precedence_over_env() {
+    let env = ConfigOverrides::from_env_with(|name| match name {
+        "PDF_MASKING_DPI" => Some("200".to_string()),
+        "PDF_MASKING_FG_QUALITY" => Some("40".to_string()),
+        "PDF_MASKING_COLOR_MODE" => Some("grayscale".to_string()),
+        _ => None,
+    })
+    .expect("parse env");
+
+    let args: Vec<String> = ["--dpi=150", "--color-mode", "bw"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let (cli, job_files) = ConfigOverrides::from_args(&args).expect("parse args");
+    assert!(job_files.is_empty());
+
+    let overrides = cli.or(env);
+    assert_eq!(overrides.dpi, Some(150));
+    assert_eq!(overrides.fg_quality, Some(40));
+    assert_eq!(overrides.bg_quality, None);
+    assert_eq!(overrides.color_mode, Some(ColorMode::Bw));
+}
+
+#[test]
+fn test_invalid_override_values_are_rejected() {
+    for args in [
+        vec!["--dpi", "0"],
+        vec!["--bg-quality", "101"],
+        vec!["--color-mode", "sepia"],
+        vec!["--fg-quality"],
+    ] {
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        assert!(
+            ConfigOverrides::from_args(&args).is_err(),
+            "{args:?} should be rejected"
+        );
+    }
+
+    let env = ConfigOverrides::from_env_with(|name| {
+        (name == "PDF_MASKING_DPI").then(|| "abc".to_string())
+    });
+    let err = env.expect_err("non-numeric env DPI should fail");
+    assert!(err.to_string().contains("PDF_MASKING_DPI"));
+}
+
+// ============================================================
+// 7. 出力パスのテンプレート
+// ============================================================
+
+fn template_values() -> OutputTemplateValues<'static> {
+    OutputTemplateValues {
+        stem: "scan",
+        date: "2024-06-01",
+        index: 7,
+    }
+}
+
+#[test]
+fn test_output_template_expands_placeholders() {
+    let expanded = expand_output_template(
+        "out/masked-{date}-{stem}-{index:03}.pdf",
+        &template_values(),
+    )
+    .expect("expand template");
+    assert_eq!(expanded, "out/masked-2024-06-01-scan-007.pdf");
+
+    // プレースホルダの無いパスと{{ }}のエスケープ
+    let values = template_values();
+    assert_eq!(
+        expand_output_template("plain.pdf", &values).unwrap(),
+        "plain.pdf"
+    );
+    assert_eq!(
+        expand_output_template("{{{stem}}}-{index}.pdf", &values).unwrap(),
+        "{scan}-7.pdf"
+    );
+}
+
+#[test]
+fn test_output_template_rejects_page_without_split_output() {
+    let err = expand_output_template("{stem}-{page:03}.png", &template_values())
+        .expect_err("{page} should be rejected");
+    assert!(err.to_string().contains("{page}"), "got: {err}");
+}
+
+#[test]
+fn test_output_template_rejects_malformed_placeholders() {
+    let values = template_values();
+    for template in [
+        "{name}.pdf",
+        "{stem:03}.pdf",
+        "{index:x}.pdf",
+        "{stem.pdf",
+        "a}.pdf",
+    ] {
+        assert!(
+            expand_output_template(template, &values).is_err(),
+            "{template} should be rejected"
+        );
+    }
+}
+
+#[test]
+fn test_today_utc_is_iso_date() {
+    let date = today_utc();
+    let parts: Vec<&str> = date.split('-').collect();
+    assert_eq!(parts.len(), 3, "got {date}");
+    assert_eq!(parts[0].len(), 4);
+    let month: u32 = parts[1].parse().expect("month");
+    let day: u32 = parts[2].parse().expect("day");
+    assert!(
+        (1..=12).contains(&month) && (1..=31).contains(&day),
+        "got {date}"
+    );
+}
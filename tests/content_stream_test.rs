@@ -1,8 +1,15 @@
 // Phase 2: コンテンツストリーム解析テスト
 
+use std::collections::HashMap;
+
 use pdf_masking::mrc::segmenter::PixelBBox;
+use pdf_masking::pdf::color_space::TintColorSpace;
 use pdf_masking::pdf::content_stream::{
-    Matrix, extract_white_fill_rects, extract_xobject_placements, pixel_to_page_coords,
+    BBox, ExtGStateOpacity, FillResources, FormXObject, Matrix, contains_shading,
+    extract_white_fill_rects, extract_white_fill_rects_with_color_spaces,
+    extract_white_fill_rects_with_resources, extract_xobject_placements,
+    extract_xobject_placements_with_resources, full_page_xobject, is_horizontally_mirrored,
+    pixel_to_page_coords, pixel_to_page_coords_with_origin, strip_text_and_xobjects,
     strip_text_operators,
 };
 use pdf_masking::pdf::reader::PdfReader;
@@ -530,6 +537,26 @@ fn test_strip_text_operators_preserves_non_text() {
     assert_eq!(decoded.operations[3].operator, "Q");
 }
 
+#[test]
+fn test_strip_text_and_xobjects_keeps_only_listed_xobjects() {
+    // 指定したXObjectのDoだけが残り、テキストと他のDoは除去される
+    let content = b"q /Im1 Do Q q /Im2 Do Q BT /F1 12 Tf (Text) Tj ET 0 0 10 10 re f";
+
+    let result = strip_text_and_xobjects(content, &["Im1".to_string()]).expect("strip");
+    let decoded = Content::decode(&result).expect("decode result");
+
+    let operators: Vec<&str> = decoded
+        .operations
+        .iter()
+        .map(|op| op.operator.as_str())
+        .collect();
+    assert_eq!(operators, ["q", "Do", "Q", "q", "Q", "re", "f"]);
+    assert_eq!(
+        decoded.operations[1].operands,
+        vec![Object::Name(b"Im1".to_vec())]
+    );
+}
+
 #[test]
 fn test_strip_text_operators_empty_stream() {
     // 空のコンテンツストリーム
@@ -730,6 +757,40 @@ fn test_pixel_to_page_coords_bottom_right() {
     assert_approx(bbox.y_max, 10.0); // 100 - 900*0.1 = 10
 }
 
+#[test]
+#[cfg(feature = "mrc")]
+fn test_pixel_to_page_coords_with_non_zero_origin() {
+    // MediaBox [-10 -10 605 802] → 615x812pt、1pt=1pxのビットマップ
+    let pixel_bbox = PixelBBox {
+        x: 0,
+        y: 0,
+        width: 100,
+        height: 50,
+    };
+    let bbox =
+        pixel_to_page_coords_with_origin(&pixel_bbox, (-10.0, -10.0), 615.0, 812.0, 615, 812)
+            .expect("coords");
+    // ビットマップ左上隅はページ座標 (-10, 802)
+    assert_approx(bbox.x_min, -10.0);
+    assert_approx(bbox.y_min, 752.0); // -10 + (812 - 50)
+    assert_approx(bbox.x_max, 90.0);
+    assert_approx(bbox.y_max, 802.0);
+
+    // ビットマップ全体はMediaBox全体に一致する
+    let full = PixelBBox {
+        x: 0,
+        y: 0,
+        width: 615,
+        height: 812,
+    };
+    let bbox = pixel_to_page_coords_with_origin(&full, (-10.0, -10.0), 615.0, 812.0, 615, 812)
+        .expect("coords");
+    assert_approx(bbox.x_min, -10.0);
+    assert_approx(bbox.y_min, -10.0);
+    assert_approx(bbox.x_max, 605.0);
+    assert_approx(bbox.y_max, 802.0);
+}
+
 #[test]
 #[cfg(feature = "mrc")]
 fn test_pixel_to_page_coords_zero_bitmap_rejected() {
@@ -1022,6 +1083,125 @@ fn test_white_fill_rects_multiple() {
     assert_approx(rects[1].x_min, 200.0);
 }
 
+#[test]
+fn test_white_fill_rects_devicen_resolved_through_tint_transform() {
+    // [/DeviceN [/Spot1 /Spot2] /DeviceGray {add 1 exch sub}]: tint 0,0 → gray 1（白）
+    let mut doc = Document::with_version("1.5");
+    let tint_transform = doc.add_object(Stream::new(
+        dictionary! {
+            "FunctionType" => 4,
+            "Domain" => vec![0.into(), 1.into(), 0.into(), 1.into()],
+            "Range" => vec![0.into(), 1.into()],
+        },
+        b"{ add 1 exch sub }".to_vec(),
+    ));
+    let space = Object::Array(vec![
+        Object::Name(b"DeviceN".to_vec()),
+        Object::Array(vec![
+            Object::Name(b"Spot1".to_vec()),
+            Object::Name(b"Spot2".to_vec()),
+        ]),
+        Object::Name(b"DeviceGray".to_vec()),
+        Object::Reference(tint_transform),
+    ]);
+    let space = TintColorSpace::parse(&doc, &space)
+        .expect("parse color space")
+        .expect("DeviceN is a tint color space");
+    let color_spaces = HashMap::from([("CS0".to_string(), space)]);
+
+    let white = b"/CS0 cs 0 0 scn 10 20 100 50 re f";
+    let rects = extract_white_fill_rects_with_color_spaces(white, &color_spaces).expect("extract");
+    assert_eq!(rects.len(), 1, "all-zero tints map to white");
+    assert_approx(rects[0].x_min, 10.0);
+    assert_approx(rects[0].y_max, 70.0);
+
+    // tint 1,0 → gray 0（黒）、scn無しの初期色（tint 1,1）も白ではない
+    for content in [
+        b"/CS0 cs 1 0 scn 10 20 100 50 re f".as_slice(),
+        b"/CS0 cs 10 20 100 50 re f".as_slice(),
+    ] {
+        let rects =
+            extract_white_fill_rects_with_color_spaces(content, &color_spaces).expect("extract");
+        assert!(rects.is_empty(), "non-white tints must not be detected");
+    }
+
+    // 色空間リソースが分からなければ白とみなさない
+    let rects = extract_white_fill_rects(white).expect("extract");
+    assert!(rects.is_empty(), "unknown color space is not white");
+}
+
+#[test]
+fn test_white_fill_rects_transparency_group_form_composited_as_unit() {
+    // フォーム内で/Opaque gsにより不透明へ戻して白を塗る。
+    // 呼び出し側は/Half gs（ca 0.5）でフォームを描く
+    let ext_g_states = HashMap::from([
+        (
+            "Half".to_string(),
+            ExtGStateOpacity {
+                fill_alpha: Some(0.5),
+                ..ExtGStateOpacity::default()
+            },
+        ),
+        (
+            "Opaque".to_string(),
+            ExtGStateOpacity {
+                fill_alpha: Some(1.0),
+                ..ExtGStateOpacity::default()
+            },
+        ),
+    ]);
+    let form = |transparency_group: bool| FormXObject {
+        content: b"/Opaque gs 1 g 0 0 100 100 re f".to_vec(),
+        matrix: Matrix {
+            a: 1.0,
+            b: 0.0,
+            c: 0.0,
+            d: 1.0,
+            e: 50.0,
+            f: 60.0,
+        },
+        bbox: BBox {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 100.0,
+            y_max: 100.0,
+        },
+        transparency_group,
+        resources: FillResources {
+            ext_g_states: ext_g_states.clone(),
+            ..FillResources::default()
+        },
+    };
+    let resources = |transparency_group: bool| FillResources {
+        ext_g_states: ext_g_states.clone(),
+        forms: HashMap::from([("Fm0".to_string(), form(transparency_group))]),
+        ..FillResources::default()
+    };
+    let translucent = b"/Half gs /Fm0 Do";
+
+    // 透明グループ: 呼び出し側のアルファがグループ全体にかかるため、内側のgsでは打ち消せない
+    let rects =
+        extract_white_fill_rects_with_resources(translucent, &resources(true)).expect("extract");
+    assert!(
+        rects.is_empty(),
+        "group painted at ca 0.5 does not hide what is beneath it"
+    );
+
+    // 通常のフォーム: 内側のgsが呼び出し側のアルファを上書きする
+    let rects =
+        extract_white_fill_rects_with_resources(translucent, &resources(false)).expect("extract");
+    assert_eq!(rects.len(), 1);
+
+    // 不透明に描かれたグループの白はフォームの/Matrixで配置される
+    let rects =
+        extract_white_fill_rects_with_resources(b"/Fm0 Do", &resources(true)).expect("extract");
+    assert_eq!(rects.len(), 1);
+    assert_approx(rects[0].x_min, 50.0);
+    assert_approx(rects[0].y_min, 60.0);
+    assert_approx(rects[0].x_max, 150.0);
+    assert_approx(rects[0].y_max, 160.0);
+}
+
 #[test]
 fn test_white_fill_rects_scn_gray_operator() {
     // scn オペレータでGray白色設定（1値 = DeviceGrayデフォルト）
@@ -1131,6 +1311,245 @@ fn test_white_fill_rects_path_op_after_re_clears() {
     );
 }
 
+// ============================================================
+// 6. is_horizontally_mirrored テスト
+// ============================================================
+
+#[test]
+fn test_mirrored_scan_image_is_detected() {
+    // ミラー出力のスキャナ: 画像を x 方向に反転して配置
+    let content = b"q -612 0 0 792 612 0 cm /Im0 Do Q";
+    assert!(is_horizontally_mirrored(content).expect("parse"));
+}
+
+#[test]
+fn test_mirror_set_by_outer_cm_is_detected() {
+    let content = b"-1 0 0 1 612 0 cm q 612 0 0 792 0 0 cm /Im0 Do Q 0 0 100 100 re f";
+    assert!(is_horizontally_mirrored(content).expect("parse"));
+}
+
+#[test]
+fn test_unmirrored_and_partially_mirrored_pages_are_not_detected() {
+    assert!(!is_horizontally_mirrored(b"q 612 0 0 792 0 0 cm /Im0 Do Q").expect("parse"));
+    // 反転が一部の描画だけなら（ロゴの反転等）ページ全体の反転とはみなさない
+    let mixed = b"q -100 0 0 100 100 0 cm /Im0 Do Q q 612 0 0 792 0 0 cm /Im1 Do Q";
+    assert!(!is_horizontally_mirrored(mixed).expect("parse"));
+    // 上下反転は左右反転ではない
+    assert!(!is_horizontally_mirrored(b"q 612 0 0 -792 0 792 cm /Im0 Do Q").expect("parse"));
+    // 何も描画しないページ
+    assert!(!is_horizontally_mirrored(b"-1 0 0 1 612 0 cm").expect("parse"));
+    assert!(!is_horizontally_mirrored(b"").expect("parse"));
+}
+
+// ============================================================
+// 7. contains_shading テスト
+// ============================================================
+
+#[test]
+fn test_page_with_sh_operator_is_flagged_as_shading() {
+    let content = b"q 0 0 612 792 re W n /Sh1 sh Q BT /F1 12 Tf (Hi) Tj ET";
+    assert!(contains_shading(content).expect("parse"));
+}
+
+#[test]
+fn test_page_without_sh_operator_is_not_flagged() {
+    // シェーディングパターンの名前やsc系オペレータは`sh`ではない
+    let content = b"/Pattern cs /P1 scn 0 0 100 100 re f q 100 0 0 100 0 0 cm /Im0 Do Q";
+    assert!(!contains_shading(content).expect("parse"));
+    assert!(!contains_shading(b"").expect("parse"));
+}
+
+// ============================================================
+// 8. full_page_xobject テスト
+// ============================================================
+
+const LETTER: BBox = BBox {
+    x_min: 0.0,
+    y_min: 0.0,
+    x_max: 612.0,
+    y_max: 792.0,
+};
+
+#[test]
+fn test_single_full_page_image_is_detected() {
+    let content = b"/Artifact BMC q 612 0 0 792 0 0 cm /Im0 Do Q EMC";
+    assert_eq!(
+        full_page_xobject(content, &LETTER).expect("parse"),
+        Some("Im0".to_string())
+    );
+}
+
+#[test]
+fn test_partial_or_mixed_pages_are_not_full_page_images() {
+    // ページの一部しか覆わない
+    let partial = b"q 306 0 0 792 0 0 cm /Im0 Do Q";
+    assert_eq!(full_page_xobject(partial, &LETTER).expect("parse"), None);
+    // 画像の上に描画がある
+    let drawing = b"q 612 0 0 792 0 0 cm /Im0 Do Q 0 0 10 10 re f";
+    assert_eq!(full_page_xobject(drawing, &LETTER).expect("parse"), None);
+    // 画像が2枚ある
+    let two = b"q 612 0 0 792 0 0 cm /Im0 Do Q q 612 0 0 792 0 0 cm /Im1 Do Q";
+    assert_eq!(full_page_xobject(two, &LETTER).expect("parse"), None);
+    assert_eq!(full_page_xobject(b"", &LETTER).expect("parse"), None);
+}
+
+// ============================================================
+// 9. Form XObject内のXObject配置テスト
+// ============================================================
+
+#[test]
+fn test_xobject_placements_descend_into_forms() {
+    // フォームは(100, 200)に移動して描かれ、内部で画像を50x40に拡大して(10, 20)に置く
+    let form = FormXObject {
+        content: b"q 50 0 0 40 10 20 cm /Im0 Do Q".to_vec(),
+        matrix: Matrix {
+            a: 1.0,
+            b: 0.0,
+            c: 0.0,
+            d: 1.0,
+            e: 100.0,
+            f: 200.0,
+        },
+        bbox: BBox {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 100.0,
+            y_max: 100.0,
+        },
+        transparency_group: false,
+        resources: FillResources::default(),
+    };
+    let resources = FillResources {
+        forms: HashMap::from([("Fm0".to_string(), form)]),
+        ..FillResources::default()
+    };
+    let content = b"q 2 0 0 2 0 0 cm /Fm0 Do Q";
+
+    // 従来の関数はフォームの中に立ち入らない
+    let top_level = extract_xobject_placements(content).expect("parse");
+    assert_eq!(top_level.len(), 1);
+    assert_eq!(top_level[0].name, "Fm0");
+
+    let placements = extract_xobject_placements_with_resources(content, &resources).expect("parse");
+    assert_eq!(placements.len(), 2);
+    assert_eq!(placements[0].name, "Fm0");
+    assert!(placements[0].form_path.is_empty());
+
+    // 画像のCTM = 画像のcm × フォームの/Matrix × 呼び出し時のCTM
+    let image = &placements[1];
+    assert_eq!(image.name, "Im0");
+    assert_eq!(image.form_path, vec!["Fm0".to_string()]);
+    assert_eq!(
+        image.ctm,
+        Matrix {
+            a: 100.0,
+            b: 0.0,
+            c: 0.0,
+            d: 80.0,
+            e: 220.0,
+            f: 440.0,
+        }
+    );
+    assert_approx(image.bbox.x_min, 220.0);
+    assert_approx(image.bbox.y_min, 440.0);
+    assert_approx(image.bbox.x_max, 320.0);
+    assert_approx(image.bbox.y_max, 520.0);
+}
+
+#[test]
+fn test_xobject_placements_apply_form_matrix_scale() {
+    // /Matrixで2倍に拡大するフォームの中の配置は、位置も大きさも2倍になる
+    let form = FormXObject {
+        content: b"q 10 0 0 10 5 5 cm /Im0 Do Q".to_vec(),
+        matrix: Matrix {
+            a: 2.0,
+            b: 0.0,
+            c: 0.0,
+            d: 2.0,
+            e: 0.0,
+            f: 0.0,
+        },
+        bbox: BBox {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 100.0,
+            y_max: 100.0,
+        },
+        transparency_group: false,
+        resources: FillResources::default(),
+    };
+    let (form_ctm, clip) = form.effective_transform(&Matrix::identity());
+    assert_eq!(form_ctm, form.matrix);
+    assert_approx(clip.x_max, 200.0);
+    assert_approx(clip.y_max, 200.0);
+
+    let resources = FillResources {
+        forms: HashMap::from([("Fm0".to_string(), form)]),
+        ..FillResources::default()
+    };
+    let placements =
+        extract_xobject_placements_with_resources(b"/Fm0 Do", &resources).expect("parse");
+    assert_eq!(placements.len(), 2);
+    let image = &placements[1];
+    assert_eq!(image.name, "Im0");
+    assert_eq!(
+        image.ctm,
+        Matrix {
+            a: 20.0,
+            b: 0.0,
+            c: 0.0,
+            d: 20.0,
+            e: 10.0,
+            f: 10.0,
+        }
+    );
+    assert_approx(image.bbox.x_min, 10.0);
+    assert_approx(image.bbox.y_min, 10.0);
+    assert_approx(image.bbox.x_max, 30.0);
+    assert_approx(image.bbox.y_max, 30.0);
+}
+
+#[test]
+fn test_xobject_placements_clipped_to_form_bbox() {
+    // /BBox(0..10)の外にはみ出した部分は描画されないので、配置のBBoxも切り詰める
+    let form = |content: &[u8]| FormXObject {
+        content: content.to_vec(),
+        matrix: Matrix {
+            a: 2.0,
+            b: 0.0,
+            c: 0.0,
+            d: 2.0,
+            e: 0.0,
+            f: 0.0,
+        },
+        bbox: BBox {
+            x_min: 0.0,
+            y_min: 0.0,
+            x_max: 10.0,
+            y_max: 10.0,
+        },
+        transparency_group: false,
+        resources: FillResources::default(),
+    };
+    let resources = FillResources {
+        forms: HashMap::from([
+            ("Fm0".to_string(), form(b"q 10 0 0 10 5 5 cm /Im0 Do Q")),
+            ("Fm1".to_string(), form(b"q 10 0 0 10 20 20 cm /Im1 Do Q")),
+        ]),
+        ..FillResources::default()
+    };
+    let placements =
+        extract_xobject_placements_with_resources(b"/Fm0 Do /Fm1 Do", &resources).expect("parse");
+
+    let names: Vec<&str> = placements.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["Fm0", "Im0", "Fm1"], "Im1 lies outside the /BBox");
+    let image = &placements[1];
+    assert_approx(image.bbox.x_min, 10.0);
+    assert_approx(image.bbox.y_min, 10.0);
+    assert_approx(image.bbox.x_max, 20.0);
+    assert_approx(image.bbox.y_max, 20.0);
+}
+
 // ============================================================
 // ヘルパー
 // ============================================================
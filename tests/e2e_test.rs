@@ -773,3 +773,986 @@ fn test_e2e_mixed_mode() {
     let doc = Document::load(&output_path).expect("output PDF should be loadable");
     assert_eq!(doc.get_pages().len(), 3, "output should have 3 pages");
 }
+
+// ============================================================
+// 11. E2E test: fix_mirror un-mirrors a mirrored scan
+// ============================================================
+
+/// Create a 1-page PDF (200x100 pt) whose only content is a 2x1 gray image
+/// (left pixel black, right pixel white) drawn with a horizontal flip, so the
+/// black half appears on the right.
+fn create_mirrored_scan_pdf(path: &Path) {
+    let mut doc = Document::with_version("1.4");
+
+    let image = Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => 2,
+            "Height" => 1,
+            "ColorSpace" => "DeviceGray",
+            "BitsPerComponent" => 8,
+        },
+        vec![0x00, 0xFF],
+    );
+    let image_id = doc.add_object(image);
+    let content_id = doc.add_object(Stream::new(
+        dictionary! {},
+        b"q -200 0 0 100 200 0 cm /Im0 Do Q".to_vec(),
+    ));
+
+    let pages_id = doc.new_object_id();
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 200.into(), 100.into()],
+        "Contents" => content_id,
+        "Resources" => dictionary! {
+            "XObject" => dictionary! { "Im0" => image_id },
+        },
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.save(path).expect("failed to save mirrored PDF");
+}
+
+/// Mean luminance of the left and right halves of a rendered page.
+fn half_luminance(bitmap: &image::DynamicImage) -> (f64, f64) {
+    let gray = bitmap.to_luma8();
+    let (w, h) = gray.dimensions();
+    let mean = |xs: std::ops::Range<u32>| {
+        let n = (xs.len() as u32 * h) as f64;
+        let sum: u64 = xs
+            .flat_map(|x| (0..h).map(move |y| (x, y)))
+            .map(|(x, y)| gray.get_pixel(x, y).0[0] as u64)
+            .sum();
+        sum as f64 / n
+    };
+    (mean(0..w / 2), mean(w / 2..w))
+}
+
+#[test]
+fn test_e2e_fix_mirror_unmirrors_output() {
+    let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+    if !pdfium_available() {
+        warn!("Skipping: PDFIUM_DYNAMIC_LIB_PATH not set (run inside `nix develop`)");
+        return;
+    }
+
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input_path = dir.path().join("input.pdf");
+    let output_path = dir.path().join("output.pdf");
+
+    create_mirrored_scan_pdf(&input_path);
+    write_settings_yaml(dir.path(), 72, 90);
+    write_jobs_yaml(
+        dir.path(),
+        "input.pdf",
+        "output.pdf",
+        "    fix_mirror: true\n",
+    );
+
+    // 入力は黒が右半分にある（ミラー状態）
+    let input_bitmap =
+        pdf_masking::render::pdfium::render_page(&input_path, 0, 72).expect("render input");
+    let (left, right) = half_luminance(&input_bitmap);
+    assert!(left > right, "input should be dark on the right");
+
+    let output = cargo_bin()
+        .arg(dir.path().join("jobs.yaml"))
+        .current_dir(dir.path())
+        .output()
+        .expect("failed to execute binary");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success(),
+        "CLI should succeed, stderr: {stderr}"
+    );
+
+    // 出力では黒が左半分に戻っている
+    let output_bitmap =
+        pdf_masking::render::pdfium::render_page(&output_path, 0, 72).expect("render output");
+    let (left, right) = half_luminance(&output_bitmap);
+    assert!(
+        left + 64.0 < right,
+        "output should be dark on the left (left={left}, right={right})"
+    );
+}
+
+// ============================================================
+// 12. E2E test: overlay_redactions blacks out boxes from an overlay PDF
+// ============================================================
+
+/// Create a PDF of 200x100 pt pages, each drawing `content` with optional
+/// annotations per page.
+fn create_pages_pdf(path: &Path, pages: &[(&[u8], Vec<Object>)]) {
+    let mut doc = Document::with_version("1.7");
+    let pages_id = doc.new_object_id();
+    let mut kids: Vec<Object> = Vec::new();
+    for (content, annots) in pages {
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content.to_vec()));
+        let mut page = dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 200.into(), 100.into()],
+            "Contents" => content_id,
+            "Resources" => dictionary! {},
+        };
+        if !annots.is_empty() {
+            page.set("Annots", annots.clone());
+        }
+        kids.push(doc.add_object(page).into());
+    }
+    let count = kids.len() as i64;
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => count,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.save(path).expect("failed to save PDF");
+}
+
+#[test]
+fn test_e2e_overlay_redactions_black_out_marked_box() {
+    let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+    if !pdfium_available() {
+        warn!("Skipping: PDFIUM_DYNAMIC_LIB_PATH not set (run inside `nix develop`)");
+        return;
+    }
+
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input_path = dir.path().join("input.pdf");
+    let output_path = dir.path().join("output.pdf");
+
+    // 入力: 2ページとも全面グレー
+    let gray: &[u8] = b"0.5 g 0 0 200 100 re f";
+    create_pages_pdf(&input_path, &[(gray, vec![]), (gray, vec![])]);
+    // オーバーレイ: 1ページ目の左半分に赤枠の注釈
+    let box_annot = Object::Dictionary(dictionary! {
+        "Type" => "Annot",
+        "Subtype" => "Square",
+        "Rect" => vec![0.into(), 0.into(), 100.into(), 100.into()],
+        "C" => vec![1.into(), 0.into(), 0.into()],
+    });
+    create_pages_pdf(
+        &dir.path().join("overlay.pdf"),
+        &[(b"", vec![box_annot]), (b"", vec![])],
+    );
+
+    write_settings_yaml(dir.path(), 72, 90);
+    write_jobs_yaml(
+        dir.path(),
+        "input.pdf",
+        "output.pdf",
+        "    overlay_redactions: overlay.pdf\n",
+    );
+
+    let output = cargo_bin()
+        .arg(dir.path().join("jobs.yaml"))
+        .current_dir(dir.path())
+        .output()
+        .expect("failed to execute binary");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success(),
+        "CLI should succeed, stderr: {stderr}"
+    );
+
+    let page1 =
+        pdf_masking::render::pdfium::render_page(&output_path, 0, 72).expect("render page 1");
+    let (left, right) = half_luminance(&page1);
+    assert!(
+        left < 32.0,
+        "marked box should be blacked out (left={left})"
+    );
+    assert!(
+        right > 96.0,
+        "unmarked half should stay gray (right={right})"
+    );
+
+    let page2 =
+        pdf_masking::render::pdfium::render_page(&output_path, 1, 72).expect("render page 2");
+    let (left, right) = half_luminance(&page2);
+    assert!(
+        left > 96.0 && right > 96.0,
+        "page 2 should be unaffected (left={left}, right={right})"
+    );
+}
+
+// ============================================================
+// 13. E2E test: mark_redactions draws the redaction as a visible box
+// ============================================================
+
+#[test]
+fn test_e2e_mark_redactions_draws_black_box_at_region() {
+    let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+    if !pdfium_available() {
+        warn!("Skipping: PDFIUM_DYNAMIC_LIB_PATH not set (run inside `nix develop`)");
+        return;
+    }
+
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input_path = dir.path().join("input.pdf");
+    let output_path = dir.path().join("output.pdf");
+
+    let gray: &[u8] = b"0.5 g 0 0 200 100 re f";
+    create_pages_pdf(&input_path, &[(gray, vec![])]);
+    let box_annot = Object::Dictionary(dictionary! {
+        "Type" => "Annot",
+        "Subtype" => "Square",
+        "Rect" => vec![0.into(), 0.into(), 100.into(), 100.into()],
+    });
+    create_pages_pdf(&dir.path().join("overlay.pdf"), &[(b"", vec![box_annot])]);
+
+    write_settings_yaml(dir.path(), 72, 90);
+    write_jobs_yaml(
+        dir.path(),
+        "input.pdf",
+        "output.pdf",
+        "    overlay_redactions: overlay.pdf\n    mark_redactions: true\n",
+    );
+
+    let output = cargo_bin()
+        .arg(dir.path().join("jobs.yaml"))
+        .current_dir(dir.path())
+        .output()
+        .expect("failed to execute binary");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success(),
+        "CLI should succeed, stderr: {stderr}"
+    );
+
+    // ラスタ画像の上に、リダクション領域の黒い塗りつぶし矩形が描かれる
+    let doc = Document::load(&output_path).expect("load output PDF");
+    let pages = doc.get_pages();
+    let content = doc.get_page_content(pages[&1]).expect("page content");
+    let content = String::from_utf8_lossy(&content);
+    assert!(
+        content.contains("q 0 0 0 rg 0 0 100 100 re f Q"),
+        "redaction mark missing: {content}"
+    );
+}
+
+// ============================================================
+// 14. E2E test: flatten_forms renders field values without appearances
+// ============================================================
+
+#[test]
+fn test_e2e_flatten_forms_renders_text_field_value() {
+    let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+    if !pdfium_available() {
+        warn!("Skipping: PDFIUM_DYNAMIC_LIB_PATH not set (run inside `nix develop`)");
+        return;
+    }
+
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input_path = dir.path().join("input.pdf");
+    let output_path = dir.path().join("output.pdf");
+
+    // 外観ストリームの無いテキストフィールド（値"ACME"）を左半分に置く
+    let field = Object::Dictionary(dictionary! {
+        "Type" => "Annot",
+        "Subtype" => "Widget",
+        "FT" => "Tx",
+        "T" => Object::string_literal("company"),
+        "V" => Object::string_literal("ACME"),
+        "DA" => Object::string_literal("/Helv 24 Tf 0 g"),
+        "Rect" => vec![10.into(), 30.into(), 110.into(), 70.into()],
+    });
+    create_pages_pdf(&input_path, &[(b"0 0 m 1 1 l S", vec![field])]);
+
+    write_settings_yaml(dir.path(), 72, 90);
+    write_jobs_yaml(
+        dir.path(),
+        "input.pdf",
+        "output.pdf",
+        "    flatten_forms: true\n",
+    );
+
+    let output = cargo_bin()
+        .arg(dir.path().join("jobs.yaml"))
+        .current_dir(dir.path())
+        .output()
+        .expect("failed to execute binary");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success(),
+        "CLI should succeed, stderr: {stderr}"
+    );
+
+    // 値の文字がフィールドの矩形内に描かれ、右半分は白のまま
+    let bitmap = pdf_masking::render::pdfium::render_page(&output_path, 0, 72)
+        .expect("render output")
+        .to_luma8();
+    let dark = |xs: std::ops::Range<u32>, ys: std::ops::Range<u32>| {
+        xs.flat_map(|x| ys.clone().map(move |y| (x, y)))
+            .filter(|&(x, y)| bitmap.get_pixel(x, y)[0] < 128)
+            .count()
+    };
+    let in_field = dark(10..110, 30..70);
+    assert!(
+        in_field > 50,
+        "field value should be visible ({in_field} dark px)"
+    );
+    assert_eq!(dark(120..200, 0..100), 0, "right half should stay blank");
+}
+
+// ============================================================
+// 15. E2E test: passthrough_full_page_images keeps a scanned page as is
+// ============================================================
+
+/// Create a 1-page PDF that only draws a high-quality noisy JPEG over the
+/// whole page, returning the size of the embedded JPEG.
+fn create_full_page_jpeg_pdf(path: &Path) -> usize {
+    let mut rgb = image::RgbImage::new(200, 100);
+    for (x, y, pixel) in rgb.enumerate_pixels_mut() {
+        let v = ((x * 31 + y * 17) % 97 * 2) as u8;
+        *pixel = image::Rgb([v, 255 - v, v / 2]);
+    }
+    let jpeg = pdf_masking::mrc::jpeg::encode_rgb_to_jpeg(&rgb, 100).expect("encode test JPEG");
+    let jpeg_len = jpeg.len();
+
+    let mut doc = Document::with_version("1.7");
+    let image_id = doc.add_object(Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => 200,
+            "Height" => 100,
+            "ColorSpace" => "DeviceRGB",
+            "BitsPerComponent" => 8,
+            "Filter" => "DCTDecode",
+        },
+        jpeg,
+    ));
+    let content_id = doc.add_object(Stream::new(
+        dictionary! {},
+        b"q 200 0 0 100 0 0 cm /Im0 Do Q".to_vec(),
+    ));
+    let pages_id = doc.new_object_id();
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 200.into(), 100.into()],
+        "Contents" => content_id,
+        "Resources" => dictionary! {
+            "XObject" => dictionary! { "Im0" => image_id },
+        },
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.save(path).expect("failed to save PDF");
+    jpeg_len
+}
+
+#[test]
+fn test_e2e_passthrough_full_page_images_reencodes_in_place() {
+    let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+    if !pdfium_available() {
+        warn!("Skipping: PDFIUM_DYNAMIC_LIB_PATH not set (run inside `nix develop`)");
+        return;
+    }
+
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input_path = dir.path().join("input.pdf");
+    let output_path = dir.path().join("output.pdf");
+    let original_len = create_full_page_jpeg_pdf(&input_path);
+
+    write_settings_yaml(dir.path(), 72, 30);
+    write_jobs_yaml(
+        dir.path(),
+        "input.pdf",
+        "output.pdf",
+        "    passthrough_full_page_images: true\n",
+    );
+
+    let output = cargo_bin()
+        .arg(dir.path().join("jobs.yaml"))
+        .current_dir(dir.path())
+        .output()
+        .expect("failed to execute binary");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success(),
+        "CLI should succeed, stderr: {stderr}"
+    );
+
+    // ページの描画はそのままで、MRCレイヤーは作られず画像だけが再エンコードされる
+    let doc = Document::load(&output_path).expect("output PDF should be loadable by lopdf");
+    let page_id = *doc.get_pages().get(&1).expect("page 1");
+    let content = doc.get_page_content(page_id).expect("page content");
+    let ops: Vec<String> = Content::decode(&content)
+        .expect("decode content")
+        .operations
+        .into_iter()
+        .map(|op| op.operator)
+        .collect();
+    assert_eq!(ops, vec!["q", "cm", "Do", "Q"]);
+
+    let resources = doc
+        .get_dictionary(page_id)
+        .and_then(|page| page.get(b"Resources"))
+        .and_then(|obj| doc.dereference(obj))
+        .and_then(|(_, obj)| obj.as_dict())
+        .expect("page resources");
+    let xobjects = resources
+        .get(b"XObject")
+        .and_then(|obj| doc.dereference(obj))
+        .and_then(|(_, obj)| obj.as_dict())
+        .expect("XObject dictionary");
+    assert_eq!(xobjects.len(), 1, "no MRC layers should be added");
+    let image = doc
+        .get_object(xobjects.get(b"Im0").and_then(Object::as_reference).unwrap())
+        .and_then(Object::as_stream)
+        .expect("Im0 stream");
+    assert_eq!(
+        image.dict.get(b"Filter").and_then(Object::as_name).unwrap(),
+        b"DCTDecode"
+    );
+    assert!(
+        image.content.len() < original_len,
+        "re-encoded image ({}) should be smaller than the original ({original_len})",
+        image.content.len()
+    );
+}
+
+// ============================================================
+// 16. E2E test: --incremental reuses unchanged pages from a prior output
+// ============================================================
+
+#[test]
+fn test_e2e_incremental_reuses_unchanged_pages() {
+    let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+    if !pdfium_available() {
+        warn!("Skipping: PDFIUM_DYNAMIC_LIB_PATH not set (run inside `nix develop`)");
+        return;
+    }
+
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input_path = dir.path().join("input.pdf");
+    let output_path = dir.path().join("output.pdf");
+    let prior_path = dir.path().join("prior.pdf");
+
+    let light: &[u8] = b"0.7 g 0 0 200 100 re f";
+    let mid: &[u8] = b"0.5 g 0 0 200 100 re f";
+    let dark: &[u8] = b"0.2 g 0 0 200 100 re f";
+    create_pages_pdf(
+        &input_path,
+        &[(light, vec![]), (mid, vec![]), (light, vec![])],
+    );
+    write_settings_yaml(dir.path(), 72, 50);
+    write_jobs_yaml(
+        dir.path(),
+        "input.pdf",
+        "output.pdf",
+        "    record_source_keys: true\n",
+    );
+
+    let output = cargo_bin()
+        .arg(dir.path().join("jobs.yaml"))
+        .current_dir(dir.path())
+        .output()
+        .expect("failed to execute binary");
+    assert!(
+        output.status.success(),
+        "first run should succeed, stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // 前回の出力の全ページに目印を付け、コピーされたページを見分けられるようにする
+    let mut prior = Document::load(&output_path).expect("load first output");
+    for page_id in prior.get_pages().into_values() {
+        prior
+            .get_dictionary_mut(page_id)
+            .expect("page dictionary")
+            .set("PriorRun", true);
+    }
+    prior.save(&prior_path).expect("save prior output");
+
+    // 2ページ目だけを編集する
+    create_pages_pdf(
+        &input_path,
+        &[(light, vec![]), (dark, vec![]), (light, vec![])],
+    );
+    let output = cargo_bin()
+        .arg("--incremental")
+        .arg(&prior_path)
+        .arg(dir.path().join("jobs.yaml"))
+        .current_dir(dir.path())
+        .output()
+        .expect("failed to execute binary");
+    assert!(
+        output.status.success(),
+        "incremental run should succeed, stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let doc = Document::load(&output_path).expect("load incremental output");
+    let pages = doc.get_pages();
+    assert_eq!(pages.len(), 3);
+    let reused: Vec<bool> = pages
+        .values()
+        .map(|&page_id| doc.get_dictionary(page_id).unwrap().has(b"PriorRun"))
+        .collect();
+    assert_eq!(
+        reused,
+        vec![true, false, true],
+        "only the unchanged pages 1 and 3 should come from the prior output"
+    );
+
+    let page2 = pdf_masking::render::pdfium::render_page(&output_path, 1, 72).expect("page 2");
+    let (left, right) = half_luminance(&page2);
+    assert!(
+        left < 96.0 && right < 96.0,
+        "page 2 should show the edited dark fill (left={left}, right={right})"
+    );
+}
+
+// ============================================================
+// 17. E2E test: redaction_manifest records the applied redactions
+// ============================================================
+
+#[test]
+fn test_e2e_redaction_manifest_records_overlay_rect() {
+    let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+    if !pdfium_available() {
+        warn!("Skipping: PDFIUM_DYNAMIC_LIB_PATH not set (run inside `nix develop`)");
+        return;
+    }
+
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input_path = dir.path().join("input.pdf");
+
+    let gray: &[u8] = b"0.5 g 0 0 200 100 re f";
+    create_pages_pdf(&input_path, &[(gray, vec![]), (gray, vec![])]);
+    let box_annot = Object::Dictionary(dictionary! {
+        "Type" => "Annot",
+        "Subtype" => "Square",
+        "Rect" => vec![10.into(), 20.into(), 110.into(), 70.into()],
+    });
+    create_pages_pdf(
+        &dir.path().join("overlay.pdf"),
+        &[(b"", vec![]), (b"", vec![box_annot])],
+    );
+
+    write_settings_yaml(dir.path(), 72, 90);
+    write_jobs_yaml(
+        dir.path(),
+        "input.pdf",
+        "output.pdf",
+        "    overlay_redactions: overlay.pdf\n    redaction_manifest: manifest.json\n",
+    );
+
+    let output = cargo_bin()
+        .arg(dir.path().join("jobs.yaml"))
+        .current_dir(dir.path())
+        .output()
+        .expect("failed to execute binary");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success(),
+        "CLI should succeed, stderr: {stderr}"
+    );
+
+    // 2ページ目だけが、注釈の矩形とともに記録される
+    let manifest = std::fs::read_to_string(dir.path().join("manifest.json")).expect("manifest");
+    let manifest: serde_json::Value = serde_json::from_str(&manifest).expect("parse manifest");
+    assert_eq!(
+        manifest["pages"],
+        serde_json::json!([{
+            "page": 2,
+            "rects": [{"x_min": 10.0, "y_min": 20.0, "x_max": 110.0, "y_max": 70.0}],
+        }])
+    );
+}
+
+// ============================================================
+// 18. E2E test: optimize_skip_images shrinks images on skip pages
+// ============================================================
+
+/// Create a 1-page PDF with text over a high-quality noisy JPEG, returning
+/// the size of the embedded JPEG.
+fn create_text_over_jpeg_pdf(path: &Path) -> usize {
+    let mut rgb = image::RgbImage::new(200, 100);
+    for (x, y, pixel) in rgb.enumerate_pixels_mut() {
+        let v = ((x * 31 + y * 17) % 97 * 2) as u8;
+        *pixel = image::Rgb([v, 255 - v, v / 2]);
+    }
+    let jpeg = pdf_masking::mrc::jpeg::encode_rgb_to_jpeg(&rgb, 100).expect("encode test JPEG");
+    let jpeg_len = jpeg.len();
+
+    let mut doc = Document::with_version("1.7");
+    let image_id = doc.add_object(Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => 200,
+            "Height" => 100,
+            "ColorSpace" => "DeviceRGB",
+            "BitsPerComponent" => 8,
+            "Filter" => "DCTDecode",
+        },
+        jpeg,
+    ));
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+    });
+    let content_id = doc.add_object(Stream::new(
+        dictionary! {},
+        b"q 200 0 0 100 0 0 cm /Im0 Do Q BT /F1 12 Tf 10 10 Td (Caption) Tj ET".to_vec(),
+    ));
+    let pages_id = doc.new_object_id();
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 200.into(), 100.into()],
+        "Contents" => content_id,
+        "Resources" => dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+            "XObject" => dictionary! { "Im0" => image_id },
+        },
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.save(path).expect("failed to save PDF");
+    jpeg_len
+}
+
+#[test]
+fn test_e2e_optimize_skip_images_keeps_text_and_shrinks_image() {
+    let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input_path = dir.path().join("input.pdf");
+    let output_path = dir.path().join("output.pdf");
+    let original_len = create_text_over_jpeg_pdf(&input_path);
+
+    write_settings_yaml(dir.path(), 72, 30);
+    write_jobs_yaml(
+        dir.path(),
+        "input.pdf",
+        "output.pdf",
+        "    color_mode: skip\n    optimize_skip_images: true\n",
+    );
+
+    let output = cargo_bin()
+        .arg(dir.path().join("jobs.yaml"))
+        .current_dir(dir.path())
+        .output()
+        .expect("failed to execute binary");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success(),
+        "CLI should succeed, stderr: {stderr}"
+    );
+
+    // テキストはそのまま残り、画像だけが小さく再エンコードされる
+    let doc = Document::load(&output_path).expect("output PDF should be loadable by lopdf");
+    let page_id = *doc.get_pages().get(&1).expect("page 1");
+    let content = doc.get_page_content(page_id).expect("page content");
+    let ops: Vec<String> = Content::decode(&content)
+        .expect("decode content")
+        .operations
+        .into_iter()
+        .map(|op| op.operator)
+        .collect();
+    assert!(
+        ops.contains(&"Tj".to_string()),
+        "text should be kept: {ops:?}"
+    );
+    assert!(
+        doc.get_page_fonts(page_id)
+            .expect("page fonts")
+            .contains_key(b"F1".as_slice()),
+        "font should be kept"
+    );
+
+    let resources = doc
+        .get_dictionary(page_id)
+        .and_then(|page| page.get(b"Resources"))
+        .and_then(|obj| doc.dereference(obj))
+        .and_then(|(_, obj)| obj.as_dict())
+        .expect("page resources");
+    let image = resources
+        .get(b"XObject")
+        .and_then(|obj| doc.dereference(obj))
+        .and_then(|(_, obj)| obj.as_dict())
+        .and_then(|xobjects| xobjects.get(b"Im0"))
+        .and_then(Object::as_reference)
+        .and_then(|id| doc.get_object(id))
+        .and_then(Object::as_stream)
+        .expect("Im0 stream");
+    assert!(
+        image.content.len() < original_len,
+        "re-encoded image ({}) should be smaller than the original ({original_len})",
+        image.content.len()
+    );
+}
+
+// ============================================================
+// 19. E2E test: preserve_images_pages keeps images on chosen pages only
+// ============================================================
+
+/// Create a 2-page PDF whose pages both draw a JPEG image with text over it.
+fn create_two_image_pages_pdf(path: &Path) {
+    let mut rgb = image::RgbImage::new(200, 100);
+    for (x, y, pixel) in rgb.enumerate_pixels_mut() {
+        let v = ((x * 31 + y * 17) % 97 * 2) as u8;
+        *pixel = image::Rgb([v, 255 - v, v / 2]);
+    }
+    let jpeg = pdf_masking::mrc::jpeg::encode_rgb_to_jpeg(&rgb, 90).expect("encode test JPEG");
+
+    let mut doc = Document::with_version("1.7");
+    let image_id = doc.add_object(Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => 200,
+            "Height" => 100,
+            "ColorSpace" => "DeviceRGB",
+            "BitsPerComponent" => 8,
+            "Filter" => "DCTDecode",
+        },
+        jpeg,
+    ));
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+    });
+    let pages_id = doc.new_object_id();
+    let mut kids: Vec<Object> = Vec::new();
+    for _ in 0..2 {
+        let content_id = doc.add_object(Stream::new(
+            dictionary! {},
+            b"q 200 0 0 100 0 0 cm /Im0 Do Q BT /F1 12 Tf 10 10 Td (Caption) Tj ET".to_vec(),
+        ));
+        kids.push(
+            doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => pages_id,
+                "MediaBox" => vec![0.into(), 0.into(), 200.into(), 100.into()],
+                "Contents" => content_id,
+                "Resources" => dictionary! {
+                    "Font" => dictionary! { "F1" => font_id },
+                    "XObject" => dictionary! { "Im0" => image_id },
+                },
+            })
+            .into(),
+        );
+    }
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => 2,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.save(path).expect("failed to save PDF");
+}
+
+/// Names of the XObjects in a page's resources.
+fn page_xobject_names(doc: &Document, page_num: u32) -> Vec<String> {
+    let page_id = *doc.get_pages().get(&page_num).expect("page");
+    doc.get_dictionary(page_id)
+        .and_then(|page| page.get(b"Resources"))
+        .and_then(|obj| doc.dereference(obj))
+        .and_then(|(_, obj)| obj.as_dict())
+        .and_then(|resources| resources.get(b"XObject"))
+        .and_then(|obj| doc.dereference(obj))
+        .and_then(|(_, obj)| obj.as_dict())
+        .map(|xobjects| {
+            xobjects
+                .iter()
+                .map(|(name, _)| String::from_utf8_lossy(name).into_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_e2e_preserve_images_pages_overrides_per_page() {
+    let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+    if !pdfium_available() {
+        warn!("Skipping: PDFIUM_DYNAMIC_LIB_PATH not set (run inside `nix develop`)");
+        return;
+    }
+
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input_path = dir.path().join("input.pdf");
+    let output_path = dir.path().join("output.pdf");
+    create_two_image_pages_pdf(&input_path);
+
+    write_settings_yaml(dir.path(), 72, 50);
+    write_jobs_yaml(
+        dir.path(),
+        "input.pdf",
+        "output.pdf",
+        "    preserve_images: false\n    preserve_images_pages: [2]\n",
+    );
+
+    let output = cargo_bin()
+        .arg(dir.path().join("jobs.yaml"))
+        .current_dir(dir.path())
+        .output()
+        .expect("failed to execute binary");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success(),
+        "CLI should succeed, stderr: {stderr}"
+    );
+
+    let doc = Document::load(&output_path).expect("output PDF should be loadable by lopdf");
+    // 1ページ目はページ全体をMRCレイヤーに分解し、元の画像は残らない
+    let page1 = page_xobject_names(&doc, 1);
+    assert!(page1.contains(&"BgImg".to_string()), "page 1: {page1:?}");
+    assert!(!page1.contains(&"Im0".to_string()), "page 1: {page1:?}");
+    // 2ページ目は元の画像XObjectをそのまま残す
+    let page2 = page_xobject_names(&doc, 2);
+    assert!(page2.contains(&"Im0".to_string()), "page 2: {page2:?}");
+    assert!(!page2.contains(&"BgImg".to_string()), "page 2: {page2:?}");
+}
+
+// ============================================================
+// 20. E2E test: pages with identical backgrounds share one BgImg
+// ============================================================
+
+/// Create a PDF whose pages all draw the same blue rectangle.
+fn create_identical_pages_pdf(path: &Path, num_pages: usize) {
+    let mut doc = Document::with_version("1.7");
+    let pages_id = doc.new_object_id();
+    let mut kids: Vec<Object> = Vec::new();
+    for _ in 0..num_pages {
+        let content_id = doc.add_object(Stream::new(
+            dictionary! {},
+            b"q 0 0 1 rg 100 100 300 200 re f Q".to_vec(),
+        ));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+            "Contents" => content_id,
+            "Resources" => dictionary! {},
+        });
+        kids.push(page_id.into());
+    }
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => Object::Integer(num_pages as i64),
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.save(path).expect("failed to save test PDF");
+}
+
+/// Object ID of the page's BgImg XObject.
+fn background_xobject_id(doc: &Document, page_num: u32) -> lopdf::ObjectId {
+    let page_id = *doc.get_pages().get(&page_num).expect("page");
+    doc.get_dictionary(page_id)
+        .and_then(|page| page.get(b"Resources"))
+        .and_then(|obj| doc.dereference(obj))
+        .and_then(|(_, obj)| obj.as_dict())
+        .and_then(|resources| resources.get(b"XObject"))
+        .and_then(|obj| doc.dereference(obj))
+        .and_then(|(_, obj)| obj.as_dict())
+        .and_then(|xobjects| xobjects.get(b"BgImg"))
+        .and_then(Object::as_reference)
+        .expect("page should reference a BgImg XObject")
+}
+
+#[test]
+fn test_e2e_identical_backgrounds_share_one_xobject() {
+    let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+    if !pdfium_available() {
+        warn!("Skipping: PDFIUM_DYNAMIC_LIB_PATH not set (run inside `nix develop`)");
+        return;
+    }
+
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input_path = dir.path().join("input.pdf");
+    let output_path = dir.path().join("output.pdf");
+    create_identical_pages_pdf(&input_path, 2);
+
+    write_settings_yaml(dir.path(), 72, 50);
+    write_jobs_yaml(
+        dir.path(),
+        "input.pdf",
+        "output.pdf",
+        "    preserve_images: false\n",
+    );
+
+    let output = cargo_bin()
+        .arg(dir.path().join("jobs.yaml"))
+        .current_dir(dir.path())
+        .output()
+        .expect("failed to execute binary");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success(),
+        "CLI should succeed, stderr: {stderr}"
+    );
+
+    let doc = Document::load(&output_path).expect("output PDF should be loadable by lopdf");
+    assert_eq!(
+        background_xobject_id(&doc, 1),
+        background_xobject_id(&doc, 2),
+        "pages rendering to the same background should share one BgImg"
+    );
+}
@@ -1,8 +1,9 @@
 // 埋込フォント解析テスト (RED phase)
 
 use lopdf::{Document, Object, Stream, dictionary};
-use pdf_masking::pdf::font::FontEncoding;
-use std::path::Path;
+use pdf_masking::pdf::font::{FontEncoding, FontSubstitutions, ParsedFont};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use tracing::warn;
 
 // ============================================================
@@ -167,6 +168,35 @@ fn test_nonexistent_page_returns_error() {
     assert!(result.is_err(), "page 999 should not exist");
 }
 
+#[test]
+fn test_page_without_resources_has_no_fonts() {
+    // ページにもPagesノードにもResourcesが無いPDF
+    let mut doc = Document::with_version("1.4");
+    let pages_id = doc.new_object_id();
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![Object::Reference(page_id)],
+            "Count" => 1,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+
+    let fonts = pdf_masking::pdf::font::parse_page_fonts(&doc, 1)
+        .expect("missing Resources should not be an error");
+    assert!(fonts.is_empty());
+}
+
 // ============================================================
 // 8. システムフォント解決（非埋め込みフォント）
 // ============================================================
@@ -256,6 +286,200 @@ fn test_system_font_glyph_outline_available() {
     assert!(!outline.is_empty(), "outline for 'A' should not be empty");
 }
 
+/// 1ページ・フォント1つ（F1）のPDFを作成してparse_page_fontsに渡す。
+fn parse_single_font_page(
+    font: lopdf::Dictionary,
+    font_file2: Option<Vec<u8>>,
+) -> std::collections::HashMap<String, pdf_masking::pdf::font::ParsedFont> {
+    parse_single_font_page_with(font, font_file2, &FontSubstitutions::default())
+}
+
+/// [`parse_single_font_page`]と同じだが、代替フォントの指定を渡す。
+fn parse_single_font_page_with(
+    font: lopdf::Dictionary,
+    font_file2: Option<Vec<u8>>,
+    substitutions: &FontSubstitutions,
+) -> std::collections::HashMap<String, pdf_masking::pdf::font::ParsedFont> {
+    let mut doc = Document::with_version("1.4");
+    let pages_id = doc.new_object_id();
+    let mut font = font;
+    if let Some(data) = font_file2 {
+        let file_id = doc.add_object(Stream::new(dictionary! {}, data));
+        let descriptor_id = doc.add_object(dictionary! {
+            "Type" => "FontDescriptor",
+            "FontName" => font.get(b"BaseFont").cloned().unwrap_or(Object::Null),
+            "FontFile2" => file_id,
+        });
+        font.set("FontDescriptor", descriptor_id);
+    }
+    let font_id = doc.add_object(font);
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "Resources" => dictionary! { "Font" => dictionary! { "F1" => font_id } },
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![Object::Reference(page_id)],
+            "Count" => 1,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+
+    pdf_masking::pdf::font::parse_page_fonts_with_substitutions(&doc, 1, substitutions)
+        .expect("parse fonts")
+}
+
+#[test]
+fn test_corrupt_embedded_truetype_falls_back_to_system_font() {
+    let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+    let font = |base_font: &str| {
+        dictionary! {
+            "Type" => "Font",
+            "Subtype" => "TrueType",
+            "BaseFont" => base_font,
+            "Encoding" => "WinAnsiEncoding",
+        }
+    };
+    if !parse_single_font_page(font("DejaVuSans"), None).contains_key("F1") {
+        warn!("SKIP: DejaVuSans not available as a system font");
+        return;
+    }
+
+    // sfntヘッダだけでテーブルの無い、ttf-parserが拒否するFontFile2
+    let corrupt = vec![0x00, 0x01, 0x00, 0x00, 0x00, 0x04, 0xff, 0xff];
+    let fonts = parse_single_font_page(font("ABCDEF+DejaVuSans"), Some(corrupt));
+
+    let font = fonts
+        .get("F1")
+        .expect("corrupt embedded font should fall back to a system substitute");
+    assert!(font.is_substituted(), "fallback should be recorded");
+    let gid = font
+        .char_code_to_glyph_id(0x41)
+        .expect("substitute should map 'A'");
+    assert!(font.glyph_outline(gid).is_some_and(|ops| !ops.is_empty()));
+}
+
+/// システムにインストールされたフォントファミリのファイルパス（無ければ`None`）。
+fn system_font_file(family: &str) -> Option<PathBuf> {
+    let mut db = fontdb::Database::new();
+    db.load_system_fonts();
+    let query = fontdb::Query {
+        families: &[fontdb::Family::Name(family)],
+        ..fontdb::Query::default()
+    };
+    let face = db.face(db.query(&query)?)?;
+    match &face.source {
+        fontdb::Source::File(path) => Some(path.clone()),
+        _ => None,
+    }
+}
+
+/// F1の'A'のアウトライン（比較用にDebug表現）。
+fn outline_of_a(fonts: &std::collections::HashMap<String, ParsedFont>) -> String {
+    let font = fonts.get("F1").expect("F1 should be resolved");
+    let gid = font.char_code_to_glyph_id(0x41).expect("should map 'A'");
+    format!("{:?}", font.glyph_outline(gid))
+}
+
+#[test]
+fn test_font_substitution_takes_precedence_over_system_fonts() {
+    let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+    let font = |base_font: &str| {
+        dictionary! {
+            "Type" => "Font",
+            "Subtype" => "TrueType",
+            "BaseFont" => base_font,
+            "Encoding" => "WinAnsiEncoding",
+        }
+    };
+    let Some(serif_path) = system_font_file("DejaVu Serif") else {
+        warn!("SKIP: DejaVu Serif not available as a system font");
+        return;
+    };
+    if !parse_single_font_page(font("DejaVuSans"), None).contains_key("F1") {
+        warn!("SKIP: DejaVuSans not available as a system font");
+        return;
+    }
+
+    // システムにあるDejaVuSansも、指定があれば代替フォント（DejaVu Serif）で解決する
+    let substitutions = FontSubstitutions::load(
+        &BTreeMap::from([("DejaVuSans".to_string(), serif_path.clone())]),
+        None,
+    )
+    .expect("load substitutions");
+    let system = outline_of_a(&parse_single_font_page(font("DejaVuSans"), None));
+    let substituted = outline_of_a(&parse_single_font_page_with(
+        font("DejaVuSans"),
+        None,
+        &substitutions,
+    ));
+    let serif = outline_of_a(&parse_single_font_page_with(
+        font("Unknown"),
+        None,
+        &FontSubstitutions::load(&BTreeMap::new(), Some(&serif_path)).expect("load fallback"),
+    ));
+    assert_eq!(substituted, serif);
+    assert_ne!(substituted, system);
+
+    // ファミリ名でも一致する（NotInstalled-Bold → NotInstalled）
+    let by_family = FontSubstitutions::load(
+        &BTreeMap::from([("NotInstalled".to_string(), serif_path)]),
+        None,
+    )
+    .expect("load substitutions");
+    assert!(parse_single_font_page(font("NotInstalled-Bold"), None).is_empty());
+    let fonts = parse_single_font_page_with(font("NotInstalled-Bold"), None, &by_family);
+    assert_eq!(outline_of_a(&fonts), serif);
+}
+
+#[test]
+fn test_system_fonts_disabled_leaves_unsubstituted_font_unresolved() {
+    let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+    let font = |base_font: &str| {
+        dictionary! {
+            "Type" => "Font",
+            "Subtype" => "TrueType",
+            "BaseFont" => base_font,
+            "Encoding" => "WinAnsiEncoding",
+        }
+    };
+    let Some(serif_path) = system_font_file("DejaVu Serif") else {
+        warn!("SKIP: DejaVu Serif not available as a system font");
+        return;
+    };
+
+    // システムフォントにあるDejaVuSansでも、無効時は探さずに未解決としてスキップする
+    let mut substitutions = FontSubstitutions::load(
+        &BTreeMap::from([("Substituted".to_string(), serif_path)]),
+        None,
+    )
+    .expect("load substitutions");
+    substitutions.set_use_system_fonts(false);
+    assert!(parse_single_font_page_with(font("DejaVuSans"), None, &substitutions).is_empty());
+
+    // 代替指定のあるフォントは引き続き解決する
+    let fonts = parse_single_font_page_with(font("Substituted"), None, &substitutions);
+    assert!(fonts.contains_key("F1"));
+}
+
+#[test]
+fn test_font_substitution_rejects_unreadable_file() {
+    let err = FontSubstitutions::load(
+        &BTreeMap::from([("Arial".to_string(), PathBuf::from("/nonexistent/font.ttf"))]),
+        None,
+    )
+    .expect_err("missing font file should fail");
+    assert!(err.to_string().contains("/nonexistent/font.ttf"), "{err}");
+}
+
 // ============================================================
 // 9. Type1フォント（システムフォント解決）
 // ============================================================
@@ -457,3 +681,119 @@ fn test_mmtype1_font_parsed_from_system() {
         "outline for 'A' should not be empty for MMType1"
     );
 }
+
+// ============================================================
+// ToUnicode CMap
+// ============================================================
+
+const TO_UNICODE_CMAP: &[u8] = b"/CIDInit /ProcSet findresource begin
+12 dict begin
+begincmap
+/CMapName /Adobe-Identity-UCS def
+1 begincodespacerange
+<00> <FF>
+endcodespacerange
+1 beginbfchar
+<01> <0041>
+endbfchar
+2 beginbfrange
+<10> <12> <0061>
+<20> <21> [<00660069> <0042>]
+endbfrange
+endcmap
+CMapName currentdict /CMap defineresource pop
+end
+end";
+
+#[test]
+fn test_parse_to_unicode_cmap_bfchar_and_bfrange() {
+    let map = pdf_masking::pdf::font::parse_to_unicode_cmap(TO_UNICODE_CMAP);
+
+    assert_eq!(map.get(&0x01).map(String::as_str), Some("A"));
+    assert_eq!(map.get(&0x10).map(String::as_str), Some("a"));
+    assert_eq!(map.get(&0x12).map(String::as_str), Some("c"));
+    // 配列形式は要素を順に割り当てる（合字は複数文字）
+    assert_eq!(map.get(&0x20).map(String::as_str), Some("fi"));
+    assert_eq!(map.get(&0x21).map(String::as_str), Some("B"));
+    assert_eq!(map.len(), 6);
+}
+
+/// 1バイトのASCIIと2バイトのShift_JIS風コードが混在する埋め込みCMap
+const MIXED_CODESPACE_CMAP: &[u8] = b"/CIDInit /ProcSet findresource begin
+12 dict begin
+begincmap
+2 begincodespacerange
+<00> <80>
+<8140> <9FFC>
+endcodespacerange
+1 begincidrange
+<20> <7E> 1
+endcidrange
+1 begincidchar
+<8140> 633
+endcidchar
+endcmap
+CMapName currentdict /CMap defineresource pop
+end
+end";
+
+#[test]
+fn test_cmap_encoding_splits_mixed_codespace() {
+    let encoding = pdf_masking::pdf::font::parse_cmap_encoding(MIXED_CODESPACE_CMAP)
+        .expect("CMap with codespace ranges");
+
+    // 'A'(0x41)は1バイト範囲、0x81 0x40は2バイト範囲として1文字に読む
+    let codes = encoding.bytes_to_char_codes(&[0x41, 0x81, 0x40]);
+    assert_eq!(codes, vec![0x41, 0x8140]);
+
+    // どの範囲にも入らないバイトは1バイトずつ読み飛ばす
+    let codes = encoding.bytes_to_char_codes(&[0xA0, 0x41]);
+    assert_eq!(codes, vec![0xA0, 0x41]);
+}
+
+#[test]
+fn test_cmap_encoding_without_codespace_is_rejected() {
+    let cmap = b"begincmap 1 begincidchar <41> 34 endcidchar endcmap";
+    assert!(pdf_masking::pdf::font::parse_cmap_encoding(cmap).is_none());
+}
+
+#[test]
+fn test_parsed_font_uses_to_unicode_stream() {
+    let mut doc = Document::with_version("1.4");
+    let cmap_id = doc.add_object(Stream::new(dictionary! {}, TO_UNICODE_CMAP.to_vec()));
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+        "Encoding" => "WinAnsiEncoding",
+        "ToUnicode" => cmap_id,
+    });
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "Resources" => dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+        },
+    });
+    let pages_id = doc.add_object(dictionary! {
+        "Type" => "Pages",
+        "Kids" => vec![Object::Reference(page_id)],
+        "Count" => 1,
+    });
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+
+    let fonts = pdf_masking::pdf::font::parse_page_fonts(&doc, 1).expect("parse fonts");
+    let Some(font) = fonts.get("F1") else {
+        warn!("skipping ToUnicode test: system Helvetica (F1) could not be resolved");
+        return;
+    };
+
+    // CMapの対応が優先され、無いコードはエンコーディングにフォールバックする
+    assert_eq!(font.char_code_to_unicode(0x01).as_deref(), Some("A"));
+    assert_eq!(font.char_code_to_unicode(0x10).as_deref(), Some("a"));
+    assert_eq!(font.char_code_to_unicode(0x5A).as_deref(), Some("Z"));
+}
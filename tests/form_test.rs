@@ -0,0 +1,205 @@
+// AcroFormフィールドの平坦化テスト
+
+use lopdf::{Dictionary, Document, Object, Stream, dictionary};
+use pdf_masking::config::job::ColorMode;
+use pdf_masking::pdf::form::flatten_form_fields;
+use pdf_masking::pipeline::job_runner::{JobConfig, run_job};
+
+/// ヘルパー: `widgets`が返すウィジェット注釈を持つ1ページのフォームPDFを作成する
+fn create_form_pdf(widgets: impl FnOnce(&mut Document) -> Vec<Dictionary>) -> Document {
+    let mut doc = Document::with_version("1.7");
+    let widgets = widgets(&mut doc);
+    let pages_id = doc.new_object_id();
+    let contents_id = doc.add_object(Stream::new(dictionary! {}, b"0 0 m 10 10 l S".to_vec()));
+    let page_id = doc.new_object_id();
+
+    let mut annots: Vec<Object> = Vec::new();
+    for mut widget in widgets {
+        widget.set("P", page_id);
+        annots.push(doc.add_object(widget).into());
+    }
+    doc.objects.insert(
+        page_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 200.into(), 100.into()],
+            "Contents" => contents_id,
+            "Resources" => dictionary! {},
+            "Annots" => annots.clone(),
+        }),
+    );
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+        "AcroForm" => dictionary! { "Fields" => annots },
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc
+}
+
+/// ヘルパー: 外観ストリームの無いテキストフィールド（値"ACME"）
+fn text_field_without_appearance() -> Dictionary {
+    dictionary! {
+        "Type" => "Annot",
+        "Subtype" => "Widget",
+        "FT" => "Tx",
+        "T" => Object::string_literal("company"),
+        "V" => Object::string_literal("ACME"),
+        "DA" => Object::string_literal("/Helv 20 Tf 0 g"),
+        "Rect" => vec![10.into(), 30.into(), 110.into(), 70.into()],
+    }
+}
+
+/// ページに追加された描画ストリームのXObjectを名前順に返す。
+fn flattened_form_contents(doc: &Document) -> Vec<String> {
+    let page_id = *doc.get_pages().get(&1).expect("page 1");
+    let page = doc.get_dictionary(page_id).expect("page dict");
+    let resources = page
+        .get(b"Resources")
+        .and_then(|obj| doc.dereference(obj))
+        .and_then(|(_, obj)| obj.as_dict())
+        .expect("resources");
+    let Ok(xobjects) = resources.get(b"XObject").and_then(Object::as_dict) else {
+        return Vec::new();
+    };
+    xobjects
+        .iter()
+        .filter(|(name, _)| name.starts_with(b"FlatForm"))
+        .map(|(_, obj)| {
+            let stream = doc
+                .get_object(obj.as_reference().expect("reference"))
+                .and_then(Object::as_stream)
+                .expect("form stream");
+            let content = stream
+                .decompressed_content()
+                .unwrap_or_else(|_| stream.content.clone());
+            String::from_utf8_lossy(&content).into_owned()
+        })
+        .collect()
+}
+
+#[test]
+fn test_flatten_text_field_without_appearance_bakes_value() {
+    let mut doc = create_form_pdf(|_| vec![text_field_without_appearance()]);
+
+    let flattened = flatten_form_fields(&mut doc).expect("flatten");
+    assert_eq!(flattened, 1);
+
+    let forms = flattened_form_contents(&doc);
+    assert_eq!(forms.len(), 1);
+    assert!(forms[0].contains("/Helv 20 Tf"), "{}", forms[0]);
+    assert!(forms[0].contains("(ACME) Tj"), "{}", forms[0]);
+
+    // ウィジェットとAcroFormは取り除かれ、ページの描画は元のコンテンツの後に追加される
+    let page_id = *doc.get_pages().get(&1).unwrap();
+    let page = doc.get_dictionary(page_id).unwrap();
+    assert!(!page.has(b"Annots"));
+    assert!(!doc.catalog().unwrap().has(b"AcroForm"));
+    let content = String::from_utf8(doc.get_page_content(page_id).unwrap()).unwrap();
+    assert!(content.starts_with("q\n0 0 m 10 10 l S"), "{content}");
+    // Rect (10, 30)-(110, 70) にBBox (0, 0)-(100, 40)をそのまま配置する
+    assert!(
+        content.ends_with("Q\nq 1 0 0 1 10 30 cm /FlatForm0 Do Q\n"),
+        "{content}"
+    );
+}
+
+#[test]
+fn test_flatten_checkbox_uses_current_appearance_state() {
+    let mut form = create_form_pdf(|doc| {
+        let mut appearance = |content: &[u8]| {
+            doc.add_object(Stream::new(
+                dictionary! {
+                    "Type" => "XObject",
+                    "Subtype" => "Form",
+                    "BBox" => vec![0.into(), 0.into(), 10.into(), 10.into()],
+                },
+                content.to_vec(),
+            ))
+        };
+        let on_id = appearance(b"0 g 2 2 6 6 re f");
+        let off_id = appearance(b"");
+        let checkbox = |state: &str, x: i64| {
+            dictionary! {
+                "Type" => "Annot",
+                "Subtype" => "Widget",
+                "FT" => "Btn",
+                "Rect" => vec![x.into(), 0.into(), (x + 20).into(), 20.into()],
+                "AS" => state,
+                "AP" => dictionary! {
+                    "N" => dictionary! { "Yes" => on_id, "Off" => off_id },
+                },
+            }
+        };
+        let link = dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Link",
+            "Rect" => vec![100.into(), 0.into(), 120.into(), 20.into()],
+        };
+        vec![checkbox("Yes", 0), checkbox("Off", 50), link]
+    });
+
+    let flattened = flatten_form_fields(&mut form).expect("flatten");
+    assert_eq!(flattened, 2);
+    // 各ウィジェットは/ASで選ばれた状態の外観を描く
+    assert_eq!(
+        flattened_form_contents(&form),
+        vec!["0 g 2 2 6 6 re f".to_string(), String::new()]
+    );
+
+    let page_id = *form.get_pages().get(&1).unwrap();
+    let content = String::from_utf8(form.get_page_content(page_id).unwrap()).unwrap();
+    // BBox 10x10をRect 20x20に拡大する
+    assert!(
+        content.contains("q 2 0 0 2 0 0 cm /FlatForm0 Do Q"),
+        "{content}"
+    );
+    assert!(
+        content.contains("q 2 0 0 2 50 0 cm /FlatForm1 Do Q"),
+        "{content}"
+    );
+
+    // ウィジェット以外の注釈は残る
+    let annots = form
+        .get_dictionary(page_id)
+        .unwrap()
+        .get(b"Annots")
+        .and_then(Object::as_array)
+        .expect("link annotation kept");
+    assert_eq!(annots.len(), 1);
+}
+
+#[test]
+fn test_flatten_forms_job_keeps_value_on_passthrough_page() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("form.pdf");
+    let output = dir.path().join("form_out.pdf");
+    create_form_pdf(|_| vec![text_field_without_appearance()])
+        .save(&input)
+        .expect("save form PDF");
+
+    let config = JobConfig {
+        input_path: input,
+        output_path: output.clone(),
+        default_color_mode: ColorMode::Skip,
+        flatten_forms: true,
+        ..Default::default()
+    };
+    run_job(&config).expect("job should succeed");
+
+    let doc = Document::load(&output).expect("load output PDF");
+    let forms = flattened_form_contents(&doc);
+    assert_eq!(forms.len(), 1);
+    assert!(forms[0].contains("(ACME) Tj"), "{}", forms[0]);
+    let page_id = *doc.get_pages().get(&1).unwrap();
+    assert!(!doc.get_dictionary(page_id).unwrap().has(b"Annots"));
+}
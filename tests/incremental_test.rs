@@ -0,0 +1,117 @@
+// incrementalモード（元ページキーの記録と前回出力のページの再利用）のテスト
+
+use lopdf::{Document, Object, Stream, dictionary};
+use pdf_masking::pipeline::job_runner::{JobConfig, run_job};
+
+/// ヘルパー: `pixel`の値で塗った2x2のグレー画像を描く1ページPDFを作成する
+fn create_image_pdf(path: &std::path::Path, pixel: u8) {
+    let mut doc = Document::with_version("1.7");
+    let pages_id = doc.new_object_id();
+    let image_id = doc.add_object(Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => 2,
+            "Height" => 2,
+            "ColorSpace" => "DeviceGray",
+            "BitsPerComponent" => 8,
+        },
+        vec![pixel; 4],
+    ));
+    let contents_id = doc.add_object(Stream::new(
+        dictionary! {},
+        b"q 100 0 0 100 0 0 cm /Im1 Do Q".to_vec(),
+    ));
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "Contents" => contents_id,
+        "Resources" => dictionary! {
+            "XObject" => dictionary! { "Im1" => image_id },
+        },
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.save(path).expect("failed to save test PDF");
+}
+
+/// 出力の1ページ目に/PieceInfoがあるか
+fn has_piece_info(path: &std::path::Path) -> bool {
+    let doc = Document::load(path).expect("load output PDF");
+    let page_id = doc.get_pages()[&1];
+    doc.get_dictionary(page_id)
+        .expect("page dictionary")
+        .has(b"PieceInfo")
+}
+
+#[test]
+fn test_source_keys_are_recorded_only_when_enabled() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("input.pdf");
+    let output = dir.path().join("output.pdf");
+    create_image_pdf(&input, 10);
+
+    let config = JobConfig {
+        input_path: input.clone(),
+        output_path: output.clone(),
+        ..Default::default()
+    };
+    run_job(&config).expect("run job");
+    assert!(
+        !has_piece_info(&output),
+        "masked pages should carry no source key by default"
+    );
+
+    let config = JobConfig {
+        record_source_keys: true,
+        ..config
+    };
+    run_job(&config).expect("run job");
+    assert!(has_piece_info(&output), "source key should be recorded");
+}
+
+#[test]
+fn test_incremental_remasks_page_whose_image_changed() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("input.pdf");
+    let prior = dir.path().join("prior.pdf");
+    let output = dir.path().join("output.pdf");
+    create_image_pdf(&input, 10);
+    run_job(&JobConfig {
+        input_path: input.clone(),
+        output_path: prior.clone(),
+        record_source_keys: true,
+        ..Default::default()
+    })
+    .expect("first run");
+
+    // 2回目はキーを記録しないので、/PieceInfoは前回の出力からコピーしたページにだけ残る
+    let incremental = JobConfig {
+        input_path: input.clone(),
+        output_path: output.clone(),
+        incremental_from: Some(prior),
+        ..Default::default()
+    };
+    run_job(&incremental).expect("unchanged run");
+    assert!(has_piece_info(&output), "unchanged page should be reused");
+
+    // コンテンツストリームは同じまま、画像だけを差し替える
+    create_image_pdf(&input, 200);
+    run_job(&incremental).expect("edited run");
+    assert!(
+        !has_piece_info(&output),
+        "page whose image changed should be masked again"
+    );
+}
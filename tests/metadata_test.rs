@@ -0,0 +1,345 @@
+// 文書メタデータ（/Info・XMP）の引き継ぎとreproducibleモードの相互作用テスト
+
+use lopdf::{Document, Object, Stream, dictionary};
+use pdf_masking::config::job::{ColorMode, MetadataMode};
+use pdf_masking::pipeline::job_runner::{JobConfig, run_job};
+
+/// ヘルパー: /Info（Title, Author, Producer, 日付）・XMPメタデータ・/Langと、
+/// 初期表示（/PageLayout・/PageMode・/ViewerPreferences・GoToの/OpenAction）を持つ1ページPDFを作成する
+fn create_pdf_with_metadata(path: &std::path::Path) {
+    let mut doc = Document::with_version("1.7");
+
+    let contents_id = doc.add_object(Stream::new(dictionary! {}, b"0 0 m 100 100 l S".to_vec()));
+    let pages_id = doc.new_object_id();
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "Contents" => contents_id,
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+
+    let xmp_id = doc.add_object(Stream::new(
+        dictionary! { "Type" => "Metadata", "Subtype" => "XML" },
+        b"<x:xmpmeta><xmp:CreateDate>2024-01-01</xmp:CreateDate></x:xmpmeta>".to_vec(),
+    ));
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+        "Metadata" => xmp_id,
+        "Lang" => Object::string_literal("ja-JP"),
+        "PageLayout" => "TwoColumnLeft",
+        "PageMode" => "UseThumbs",
+        "ViewerPreferences" => dictionary! { "FitWindow" => true },
+        "OpenAction" => dictionary! {
+            "S" => "GoTo",
+            "D" => vec![page_id.into(), "XYZ".into(), Object::Null, 700.into(), 2.into()],
+        },
+    });
+    doc.trailer.set("Root", catalog_id);
+
+    let info_id = doc.add_object(dictionary! {
+        "Title" => Object::string_literal("Quarterly Report"),
+        "Author" => Object::string_literal("Finance"),
+        "Producer" => Object::string_literal("Some Writer 1.0"),
+        "CreationDate" => Object::string_literal("D:20240101000000Z"),
+        "ModDate" => Object::string_literal("D:20240102000000Z"),
+    });
+    doc.trailer.set("Info", info_id);
+
+    doc.save(path).expect("failed to save test PDF");
+}
+
+/// ヘルパー: 全ページSkip（そのままコピー）のジョブ設定
+fn job_config(
+    input: &std::path::Path,
+    output: &std::path::Path,
+    metadata: MetadataMode,
+    reproducible: bool,
+) -> JobConfig {
+    JobConfig {
+        input_path: input.to_path_buf(),
+        output_path: output.to_path_buf(),
+        default_color_mode: ColorMode::Skip,
+        metadata,
+        reproducible,
+        ..Default::default()
+    }
+}
+
+/// 出力PDFの/Info辞書を取得する
+fn output_info(doc: &Document) -> Option<&lopdf::Dictionary> {
+    let info_id = doc.trailer.get(b"Info").ok()?.as_reference().ok()?;
+    doc.get_dictionary(info_id).ok()
+}
+
+#[test]
+fn test_minimal_reproducible_is_byte_stable_and_keeps_title() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("report.pdf");
+    let first = dir.path().join("first.pdf");
+    let second = dir.path().join("second.pdf");
+    create_pdf_with_metadata(&input);
+
+    run_job(&job_config(&input, &first, MetadataMode::Minimal, true)).expect("first run");
+    run_job(&job_config(&input, &second, MetadataMode::Minimal, true)).expect("second run");
+
+    let first_bytes = std::fs::read(&first).expect("read first output");
+    let second_bytes = std::fs::read(&second).expect("read second output");
+    assert_eq!(
+        first_bytes, second_bytes,
+        "outputs should be byte-identical"
+    );
+
+    let doc = Document::load(&first).expect("load output PDF");
+    let info = output_info(&doc).expect("/Info should be present");
+    assert_eq!(
+        info.get(b"Title").unwrap().as_str().unwrap(),
+        b"Quarterly Report"
+    );
+    assert_eq!(info.get(b"Author").unwrap().as_str().unwrap(), b"Finance");
+    assert!(!info.has(b"Producer"));
+    assert!(!info.has(b"CreationDate"));
+    assert!(!info.has(b"ModDate"));
+    assert!(!doc.catalog().unwrap().has(b"Metadata"));
+}
+
+#[test]
+fn test_preserve_keeps_info_and_xmp() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("report.pdf");
+    let output = dir.path().join("out.pdf");
+    create_pdf_with_metadata(&input);
+
+    run_job(&job_config(&input, &output, MetadataMode::Preserve, false)).expect("run job");
+
+    let doc = Document::load(&output).expect("load output PDF");
+    let info = output_info(&doc).expect("/Info should be present");
+    assert!(info.has(b"Producer"));
+    assert_eq!(
+        info.get(b"CreationDate").unwrap().as_str().unwrap(),
+        b"D:20240101000000Z"
+    );
+    assert!(doc.catalog().unwrap().has(b"Metadata"));
+}
+
+#[test]
+fn test_reproducible_overrides_preserve_for_dates() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("report.pdf");
+    let output = dir.path().join("out.pdf");
+    create_pdf_with_metadata(&input);
+
+    run_job(&job_config(&input, &output, MetadataMode::Preserve, true)).expect("run job");
+
+    let doc = Document::load(&output).expect("load output PDF");
+    let info = output_info(&doc).expect("/Info should be present");
+    assert!(info.has(b"Title"));
+    assert!(info.has(b"Producer"));
+    assert!(!info.has(b"CreationDate"));
+    assert!(!info.has(b"ModDate"));
+    assert!(!doc.catalog().unwrap().has(b"Metadata"));
+}
+
+#[test]
+fn test_strip_drops_all_metadata() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("report.pdf");
+    let output = dir.path().join("out.pdf");
+    create_pdf_with_metadata(&input);
+
+    run_job(&job_config(&input, &output, MetadataMode::Strip, false)).expect("run job");
+
+    let doc = Document::load(&output).expect("load output PDF");
+    assert!(output_info(&doc).is_none());
+    assert!(!doc.catalog().unwrap().has(b"Metadata"));
+}
+
+#[test]
+fn test_lang_copied_from_source_catalog() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("report.pdf");
+    let output = dir.path().join("out.pdf");
+    create_pdf_with_metadata(&input);
+
+    run_job(&job_config(&input, &output, MetadataMode::Strip, false)).expect("run job");
+
+    let doc = Document::load(&output).expect("load output PDF");
+    let lang = doc
+        .catalog()
+        .unwrap()
+        .get(b"Lang")
+        .expect("/Lang should be kept");
+    assert_eq!(lang.as_str().unwrap(), b"ja-JP");
+}
+
+#[test]
+fn test_lang_override_sets_output_catalog() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("report.pdf");
+    let output = dir.path().join("out.pdf");
+    create_pdf_with_metadata(&input);
+
+    let mut config = job_config(&input, &output, MetadataMode::Strip, false);
+    config.lang = Some("en-US".to_string());
+    run_job(&config).expect("run job");
+
+    let doc = Document::load(&output).expect("load output PDF");
+    let lang = doc
+        .catalog()
+        .unwrap()
+        .get(b"Lang")
+        .expect("/Lang should be set");
+    assert_eq!(lang.as_str().unwrap(), b"en-US");
+}
+
+#[test]
+fn test_producer_sets_output_info() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("report.pdf");
+    let first = dir.path().join("first.pdf");
+    let second = dir.path().join("second.pdf");
+    create_pdf_with_metadata(&input);
+
+    // Stripでも/Producerだけの/Infoを出力し、reproducibleでは毎回同じバイト列になる
+    let mut config = job_config(&input, &first, MetadataMode::Strip, true);
+    config.producer = Some("Records Office".to_string());
+    run_job(&config).expect("first run");
+    config.output_path = second.clone();
+    run_job(&config).expect("second run");
+    assert_eq!(
+        std::fs::read(&first).expect("read first output"),
+        std::fs::read(&second).expect("read second output"),
+        "outputs should be byte-identical"
+    );
+
+    let doc = Document::load(&first).expect("load output PDF");
+    let info = output_info(&doc).expect("/Info should be present");
+    assert_eq!(
+        info.get(b"Producer").unwrap().as_str().unwrap(),
+        b"Records Office"
+    );
+    assert!(!info.has(b"Title"));
+
+    // Preserveで引き継いだ元の/Producerも上書きする
+    let mut config = job_config(&input, &first, MetadataMode::Preserve, false);
+    config.producer = Some("Records Office".to_string());
+    run_job(&config).expect("run job");
+    let doc = Document::load(&first).expect("load output PDF");
+    let info = output_info(&doc).expect("/Info should be present");
+    assert_eq!(
+        info.get(b"Producer").unwrap().as_str().unwrap(),
+        b"Records Office"
+    );
+}
+
+#[test]
+fn test_empty_producer_removes_entry() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("report.pdf");
+    let output = dir.path().join("out.pdf");
+    create_pdf_with_metadata(&input);
+
+    let mut config = job_config(&input, &output, MetadataMode::Preserve, false);
+    config.producer = Some(String::new());
+    run_job(&config).expect("run job");
+
+    let doc = Document::load(&output).expect("load output PDF");
+    let info = output_info(&doc).expect("/Info should be present");
+    assert!(info.has(b"Title"));
+    assert!(!info.has(b"Producer"));
+
+    // /Producerしか無ければ/Info自体を出力しない
+    let mut config = job_config(&input, &output, MetadataMode::Strip, false);
+    config.producer = Some(String::new());
+    run_job(&config).expect("run job");
+    let doc = Document::load(&output).expect("load output PDF");
+    assert!(output_info(&doc).is_none());
+}
+
+#[test]
+fn test_preserve_view_keeps_page_layout_and_goto_open_action() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("report.pdf");
+    let output = dir.path().join("out.pdf");
+    create_pdf_with_metadata(&input);
+
+    let mut config = job_config(&input, &output, MetadataMode::Strip, false);
+    config.preserve_view = true;
+    run_job(&config).expect("run job");
+
+    let doc = Document::load(&output).expect("load output PDF");
+    let catalog = doc.catalog().unwrap();
+    assert_eq!(
+        catalog.get(b"PageLayout").unwrap().as_name().unwrap(),
+        b"TwoColumnLeft"
+    );
+    assert_eq!(
+        catalog.get(b"PageMode").unwrap().as_name().unwrap(),
+        b"UseThumbs"
+    );
+    let prefs = catalog
+        .get(b"ViewerPreferences")
+        .and_then(|prefs| doc.dereference(prefs))
+        .and_then(|(_, prefs)| prefs.as_dict())
+        .expect("/ViewerPreferences should be kept");
+    assert!(prefs.get(b"FitWindow").unwrap().as_bool().unwrap());
+
+    // 宛先は出力のページを指す
+    let dest = catalog
+        .get(b"OpenAction")
+        .and_then(Object::as_array)
+        .expect("/OpenAction destination");
+    assert_eq!(dest[0].as_reference().unwrap(), doc.get_pages()[&1]);
+    assert_eq!(dest[1].as_name().unwrap(), b"XYZ");
+    assert_eq!(dest[3].as_i64().unwrap(), 700);
+}
+
+#[test]
+fn test_view_not_copied_by_default() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("report.pdf");
+    let output = dir.path().join("out.pdf");
+    create_pdf_with_metadata(&input);
+
+    run_job(&job_config(&input, &output, MetadataMode::Strip, false)).expect("run job");
+
+    let doc = Document::load(&output).expect("load output PDF");
+    let catalog = doc.catalog().unwrap();
+    assert!(!catalog.has(b"PageLayout"));
+    assert!(!catalog.has(b"ViewerPreferences"));
+    assert!(!catalog.has(b"OpenAction"));
+}
+
+#[test]
+fn test_preserve_view_drops_javascript_open_action() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("report.pdf");
+    let output = dir.path().join("out.pdf");
+    create_pdf_with_metadata(&input);
+    let mut doc = Document::load(&input).expect("load input PDF");
+    doc.catalog_mut().unwrap().set(
+        "OpenAction",
+        dictionary! {
+            "S" => "JavaScript",
+            "JS" => Object::string_literal("app.alert('hi')"),
+        },
+    );
+    doc.save(&input).expect("save input PDF");
+
+    let mut config = job_config(&input, &output, MetadataMode::Strip, false);
+    config.preserve_view = true;
+    run_job(&config).expect("run job");
+
+    let doc = Document::load(&output).expect("load output PDF");
+    let catalog = doc.catalog().unwrap();
+    assert!(catalog.has(b"PageLayout"));
+    assert!(!catalog.has(b"OpenAction"));
+}
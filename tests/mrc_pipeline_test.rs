@@ -5,13 +5,21 @@
 
 use std::collections::HashMap;
 
-use pdf_masking::config::job::ColorMode;
+use lopdf::dictionary;
+use pdf_masking::config::job::{
+    ColorMode, JpegSizeMismatch, OutputColorSpace, ResampleFilter, WidthSource,
+};
+use pdf_masking::config::settings::{
+    DEFAULT_MAX_IMAGE_PIXELS, ImageClassDpi, Jbig2Options, JpegEscalation, LineRemoval,
+};
 #[cfg(feature = "mrc")]
 use pdf_masking::ffi::leptonica::Pix;
 use pdf_masking::mrc::compositor;
 use pdf_masking::mrc::jpeg;
 #[cfg(feature = "mrc")]
+use pdf_masking::mrc::segmenter::LeptonicaSegmenter;
 use pdf_masking::mrc::{jbig2, segmenter};
+use pdf_masking::pdf::content_stream::{BBox, FillResources};
 use pdf_masking::pdf::font::ParsedFont;
 
 fn load_sample_fonts() -> HashMap<String, ParsedFont> {
@@ -91,7 +99,7 @@ fn test_encode_mask_to_jbig2() {
     let mut mask = Pix::create(100, 100, 1).expect("failed to create 1-bit Pix");
     mask.set_all_pixels(1).expect("failed to set pixels");
 
-    let result = jbig2::encode_mask(&mut mask);
+    let result = jbig2::encode_mask(&mut mask, &Jbig2Options::default());
     assert!(result.is_ok(), "encode_mask failed: {:?}", result.err());
 }
 
@@ -101,10 +109,128 @@ fn test_encode_mask_to_jbig2() {
 fn test_encode_returns_non_empty() {
     let mut mask = Pix::create(100, 100, 1).expect("failed to create 1-bit Pix");
 
-    let data = jbig2::encode_mask(&mut mask).expect("encode_mask should succeed");
+    let data = jbig2::encode_mask(&mut mask, &Jbig2Options::default())
+        .expect("encode_mask should succeed");
     assert!(!data.is_empty(), "JBIG2 encoded data should not be empty");
 }
 
+/// Test that TPGDON shrinks a mask made of runs of identical rows.
+#[cfg(feature = "mrc")]
+#[test]
+fn test_encode_mask_tpgdon_shrinks_duplicate_rows() {
+    // 16行ごとに切り替わる縞模様（同じ行が続く）
+    let mask = || {
+        let mut mask = Pix::create(200, 200, 1).expect("failed to create 1-bit Pix");
+        for y in 0..200 {
+            for x in 0..200 {
+                if (y / 16 + x / 7) % 2 == 0 {
+                    mask.set_pixel(x, y, 1).expect("set pixel");
+                }
+            }
+        }
+        mask
+    };
+
+    let with_tpgdon =
+        jbig2::encode_mask(&mut mask(), &Jbig2Options { tpgdon: true }).expect("encode");
+    let without_tpgdon =
+        jbig2::encode_mask(&mut mask(), &Jbig2Options { tpgdon: false }).expect("encode");
+
+    assert!(!with_tpgdon.is_empty());
+    assert!(!without_tpgdon.is_empty());
+    assert!(
+        with_tpgdon.len() < without_tpgdon.len(),
+        "TPGDON should skip duplicate rows: {} >= {}",
+        with_tpgdon.len(),
+        without_tpgdon.len()
+    );
+}
+
+/// encode_maskの出力はPDF埋め込み形式（ファイルヘッダ無し）で、pdfiumがそのまま復号できる。
+#[cfg(feature = "mrc")]
+#[test]
+fn test_encode_mask_emits_embedded_organization_decodable_by_pdfium() {
+    use lopdf::{Document, Object, Stream};
+
+    // 左半分が黒の100x100マスク
+    let mut mask = Pix::create(100, 100, 1).expect("failed to create 1-bit Pix");
+    for y in 0..100 {
+        for x in 0..50 {
+            mask.set_pixel(x, y, 1).expect("set pixel");
+        }
+    }
+    let data = jbig2::encode_mask(&mut mask, &Jbig2Options::default()).expect("encode_mask");
+
+    assert!(
+        !data.starts_with(&jbig2::JBIG2_FILE_ID),
+        "JBIG2 file header must not be embedded in PDF"
+    );
+    // 先頭はページ情報セグメント（型48）、末尾にend of page(49)/end of file(51)は無い
+    assert_eq!(
+        data[4] & 0x3F,
+        48,
+        "first segment should be page information"
+    );
+    assert_eq!(
+        jbig2::to_embedded_organization(&data).expect("reparse"),
+        data,
+        "output should already be in embedded organization"
+    );
+
+    // 参照デコーダ（pdfium）で描画して復号結果を確かめる
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let image_id = doc.add_object(Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => 100,
+            "Height" => 100,
+            "ImageMask" => true,
+            "Decode" => vec![Object::Integer(1), Object::Integer(0)],
+            "BitsPerComponent" => 1,
+            "Filter" => "JBIG2Decode",
+        },
+        data,
+    ));
+    let content_id = doc.add_object(Stream::new(
+        dictionary! {},
+        b"q 0 g 100 0 0 100 0 0 cm /Im0 Do Q".to_vec(),
+    ));
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 100.into(), 100.into()],
+        "Contents" => content_id,
+        "Resources" => dictionary! { "XObject" => dictionary! { "Im0" => image_id } },
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+    doc.trailer.set("Root", catalog_id);
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let path = dir.path().join("jbig2.pdf");
+    doc.save(&path).expect("save PDF");
+
+    let bitmap = pdf_masking::render::pdfium::render_page(&path, 0, 72)
+        .expect("render")
+        .to_luma8();
+    assert!(
+        bitmap.get_pixel(25, 50).0[0] < 64,
+        "left half should decode black"
+    );
+    assert!(
+        bitmap.get_pixel(75, 50).0[0] > 192,
+        "right half should decode white"
+    );
+}
+
 // ---- jpeg.rs tests ----
 
 /// Test encoding a background RGBA image to JPEG format.
@@ -200,6 +326,32 @@ fn test_gray_jpeg_smaller_than_rgb() {
     );
 }
 
+/// Test that an extremely low quality on a detailed image is escalated.
+#[test]
+fn test_low_jpeg_quality_is_escalated_on_detailed_image() {
+    // Fine checkerboard with color noise: quality 1 turns it into flat blocks
+    let (width, height) = (128u32, 128u32);
+    let rgb = image::RgbImage::from_fn(width, height, |x, y| {
+        let v = if (x + y) % 2 == 0 { 30 } else { 225 };
+        image::Rgb([v, ((x * 7 + y * 13) % 256) as u8, 255 - v])
+    });
+    let escalation = JpegEscalation {
+        min_psnr: Some(25.0),
+        ..JpegEscalation::default()
+    };
+
+    let (jpeg_data, quality) =
+        jpeg::encode_rgb_to_jpeg_escalated(&rgb, 1, &escalation).expect("encode escalated");
+    assert!(quality > 1, "quality should be escalated, got {quality}");
+    assert!(quality <= escalation.max_quality);
+    assert!(jpeg_data.starts_with(&[0xFF, 0xD8]));
+
+    // Without a PSNR floor the requested quality is kept
+    let (_, quality) = jpeg::encode_rgb_to_jpeg_escalated(&rgb, 1, &JpegEscalation::default())
+        .expect("encode without escalation");
+    assert_eq!(quality, 1);
+}
+
 // ---- segmenter::extract_text_bboxes tests ----
 
 /// Test that extract_text_bboxes returns bboxes for a mask with content.
@@ -330,6 +482,46 @@ fn test_connected_component_bboxes_wrong_depth() {
     assert!(result.is_err(), "Should reject non-1-bit image");
 }
 
+/// Test that remove_lines drops a long horizontal line but keeps short strokes.
+#[test]
+fn test_remove_lines_drops_long_horizontal_line() {
+    // 300px long, 2px thick rule + a 20x20 text-like blob
+    let mut mask = Pix::create(400, 100, 1).expect("create 1-bit Pix");
+    for x in 50..350 {
+        for y in 70..72 {
+            mask.set_pixel(x, y, 1).expect("set line pixel");
+        }
+    }
+    for x in 10..30 {
+        for y in 10..30 {
+            mask.set_pixel(x, y, 1).expect("set blob pixel");
+        }
+    }
+
+    let kept = segmenter::remove_lines(&mask, 100, LineRemoval::default()).expect("no-op");
+    assert_eq!(kept.connected_component_bboxes(8).expect("cc").len(), 2);
+
+    // Vertical-only removal leaves the horizontal rule alone
+    let vertical = LineRemoval {
+        horizontal: false,
+        vertical: true,
+    };
+    let kept = segmenter::remove_lines(&mask, 100, vertical).expect("remove vlines");
+    assert_eq!(kept.connected_component_bboxes(8).expect("cc").len(), 2);
+
+    let horizontal = LineRemoval {
+        horizontal: true,
+        vertical: false,
+    };
+    let cleaned = segmenter::remove_lines(&mask, 100, horizontal).expect("remove hlines");
+    let bboxes = cleaned.connected_component_bboxes(8).expect("cc");
+    assert_eq!(
+        bboxes,
+        vec![(10, 10, 20, 20)],
+        "only the blob should remain"
+    );
+}
+
 // ---- compositor.rs tests ----
 
 /// Test the full MRC pipeline: RGBA bitmap + config -> MrcLayers.
@@ -339,6 +531,10 @@ fn test_compose_mrc_layers() {
     let config = compositor::MrcConfig {
         bg_quality: 50,
         fg_quality: 30,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        output_colorspace: OutputColorSpace::Rgb,
+        line_removal: LineRemoval::default(),
     };
 
     let result = compositor::compose(
@@ -364,6 +560,10 @@ fn test_mrc_layers_has_all_components() {
     let config = compositor::MrcConfig {
         bg_quality: 50,
         fg_quality: 30,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        output_colorspace: OutputColorSpace::Rgb,
+        line_removal: LineRemoval::default(),
     };
 
     let layers = compositor::compose(
@@ -391,6 +591,32 @@ fn test_mrc_layers_has_all_components() {
     );
 }
 
+/// Test that a page too large for leptonica to allocate is an error, not a crash.
+#[test]
+fn test_compose_oversized_bitmap_returns_error() {
+    let config = compositor::MrcConfig {
+        bg_quality: 50,
+        fg_quality: 30,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        output_colorspace: OutputColorSpace::Rgb,
+        line_removal: LineRemoval::default(),
+    };
+
+    // 30000x30000 RGBA needs 3.6 GB; the size is rejected before the data is read
+    let result = compositor::compose(
+        &[],
+        30_000,
+        30_000,
+        595.276,
+        841.89,
+        &config,
+        ColorMode::Rgb,
+    );
+    let err = result.expect_err("oversized bitmap should fail");
+    assert!(err.to_string().contains("limit"), "got: {err}");
+}
+
 // ---- compose_text_masked tests ----
 
 /// Test compose_text_masked with empty content stream.
@@ -398,6 +624,7 @@ fn test_mrc_layers_has_all_components() {
 fn test_compose_text_masked_empty_content() {
     let (data, width, height) = create_test_rgba_image();
     let image_streams = std::collections::HashMap::new();
+    let fill_resources = FillResources::default();
 
     let params = compositor::TextMaskedParams {
         content_bytes: b"",
@@ -406,9 +633,23 @@ fn test_compose_text_masked_empty_content() {
         bitmap_height: height,
         page_width_pts: 612.0,
         page_height_pts: 792.0,
+        page_origin_pts: (0.0, 0.0),
         image_streams: &image_streams,
+        matte_smasks: &HashMap::new(),
+        fill_resources: &fill_resources,
         color_mode: ColorMode::Rgb,
         page_index: 0,
+        jbig2: Jbig2Options::default(),
+        line_removal: LineRemoval::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        merge_white_fills: false,
+        segmenter: &LeptonicaSegmenter,
+        fonts: None,
     };
 
     let result = compositor::compose_text_masked(&params);
@@ -431,6 +672,7 @@ fn test_compose_text_masked_strips_text() {
     let content = b"BT /F1 12 Tf (Hello) Tj ET";
     let (data, width, height) = create_test_rgba_image();
     let image_streams = std::collections::HashMap::new();
+    let fill_resources = FillResources::default();
 
     let params = compositor::TextMaskedParams {
         content_bytes: content,
@@ -439,9 +681,23 @@ fn test_compose_text_masked_strips_text() {
         bitmap_height: height,
         page_width_pts: 612.0,
         page_height_pts: 792.0,
+        page_origin_pts: (0.0, 0.0),
         image_streams: &image_streams,
+        matte_smasks: &HashMap::new(),
+        fill_resources: &fill_resources,
         color_mode: ColorMode::Rgb,
         page_index: 2,
+        jbig2: Jbig2Options::default(),
+        line_removal: LineRemoval::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        merge_white_fills: false,
+        segmenter: &LeptonicaSegmenter,
+        fonts: None,
     };
 
     let result = compositor::compose_text_masked(&params);
@@ -462,6 +718,7 @@ fn test_compose_text_masked_strips_text() {
 fn test_compose_text_masked_grayscale() {
     let (data, width, height) = create_test_rgba_image();
     let image_streams = std::collections::HashMap::new();
+    let fill_resources = FillResources::default();
 
     let params = compositor::TextMaskedParams {
         content_bytes: b"",
@@ -470,9 +727,23 @@ fn test_compose_text_masked_grayscale() {
         bitmap_height: height,
         page_width_pts: 100.0,
         page_height_pts: 100.0,
+        page_origin_pts: (0.0, 0.0),
         image_streams: &image_streams,
+        matte_smasks: &HashMap::new(),
+        fill_resources: &fill_resources,
         color_mode: ColorMode::Grayscale,
         page_index: 1,
+        jbig2: Jbig2Options::default(),
+        line_removal: LineRemoval::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        merge_white_fills: false,
+        segmenter: &LeptonicaSegmenter,
+        fonts: None,
     };
 
     let result = compositor::compose_text_masked(&params);
@@ -493,6 +764,7 @@ fn test_compose_text_masked_grayscale() {
 fn test_compose_text_masked_valid_bboxes() {
     let (data, width, height) = create_test_rgba_image();
     let image_streams = std::collections::HashMap::new();
+    let fill_resources = FillResources::default();
 
     let params = compositor::TextMaskedParams {
         content_bytes: b"",
@@ -501,9 +773,23 @@ fn test_compose_text_masked_valid_bboxes() {
         bitmap_height: height,
         page_width_pts: 200.0,
         page_height_pts: 200.0,
+        page_origin_pts: (0.0, 0.0),
         image_streams: &image_streams,
+        matte_smasks: &HashMap::new(),
+        fill_resources: &fill_resources,
         color_mode: ColorMode::Rgb,
         page_index: 0,
+        jbig2: Jbig2Options::default(),
+        line_removal: LineRemoval::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        merge_white_fills: false,
+        segmenter: &LeptonicaSegmenter,
+        fonts: None,
     };
 
     let result = compositor::compose_text_masked(&params).expect("should succeed");
@@ -540,15 +826,26 @@ fn test_compose_text_outlines_replaces_text_with_paths() {
     // F4はWinAnsiEncoding（'A'のアウトラインあり）
     let content = b"BT /F4 12 Tf (A) Tj ET";
     let image_streams = HashMap::new();
+    let fill_resources = FillResources::default();
 
     let params = compositor::TextOutlinesParams {
         content_bytes: content,
         fonts: &fonts,
         image_streams: &image_streams,
+        matte_smasks: &HashMap::new(),
+        fill_resources: &fill_resources,
         page_width_pts: 595.276,
         page_height_pts: 841.89,
         color_mode: ColorMode::Rgb,
         page_index: 0,
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        merge_white_fills: false,
+        width_source: WidthSource::Pdf,
     };
 
     let result = compositor::compose_text_outlines(&params);
@@ -580,15 +877,26 @@ fn test_compose_text_outlines_returns_err_on_missing_font() {
     let fonts = HashMap::new(); // 空のフォントマップ
     let content = b"BT /F99 12 Tf (Hello) Tj ET";
     let image_streams = HashMap::new();
+    let fill_resources = FillResources::default();
 
     let params = compositor::TextOutlinesParams {
         content_bytes: content,
         fonts: &fonts,
         image_streams: &image_streams,
+        matte_smasks: &HashMap::new(),
+        fill_resources: &fill_resources,
         page_width_pts: 595.276,
         page_height_pts: 841.89,
         color_mode: ColorMode::Rgb,
         page_index: 0,
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        merge_white_fills: false,
+        width_source: WidthSource::Pdf,
     };
 
     let result = compositor::compose_text_outlines(&params);
@@ -601,15 +909,26 @@ fn test_compose_text_outlines_no_text() {
     let fonts = load_sample_fonts();
     let content = b"q 1 0 0 1 0 0 cm /Im1 Do Q";
     let image_streams = HashMap::new();
+    let fill_resources = FillResources::default();
 
     let params = compositor::TextOutlinesParams {
         content_bytes: content,
         fonts: &fonts,
         image_streams: &image_streams,
+        matte_smasks: &HashMap::new(),
+        fill_resources: &fill_resources,
         page_width_pts: 595.276,
         page_height_pts: 841.89,
         color_mode: ColorMode::Rgb,
         page_index: 0,
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        merge_white_fills: false,
+        width_source: WidthSource::Pdf,
     };
 
     let result = compositor::compose_text_outlines(&params);
@@ -622,6 +941,206 @@ fn test_compose_text_outlines_no_text() {
     assert!(text.contains("Do"), "should preserve Do operator");
 }
 
+/// 2x2のDeviceRGB非圧縮画像XObject
+fn raw_rgb_image(extra: lopdf::Dictionary) -> lopdf::Stream {
+    let mut dict = dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Image",
+        "Width" => 2,
+        "Height" => 2,
+        "ColorSpace" => "DeviceRGB",
+        "BitsPerComponent" => 8,
+    };
+    dict.extend(&extra);
+    lopdf::Stream::new(dict, vec![0u8; 12])
+}
+
+fn redact_with_outlines(
+    content: &[u8],
+    image_streams: &HashMap<String, lopdf::Stream>,
+) -> pdf_masking::mrc::TextMaskedData {
+    redact_with_outlines_merging(content, image_streams, false)
+}
+
+fn redact_with_outlines_merging(
+    content: &[u8],
+    image_streams: &HashMap<String, lopdf::Stream>,
+    merge_white_fills: bool,
+) -> pdf_masking::mrc::TextMaskedData {
+    // テキストを含まないのでフォントは不要
+    let fonts = HashMap::new();
+    let fill_resources = FillResources::default();
+    let params = compositor::TextOutlinesParams {
+        content_bytes: content,
+        fonts: &fonts,
+        image_streams,
+        matte_smasks: &HashMap::new(),
+        fill_resources: &fill_resources,
+        page_width_pts: 200.0,
+        page_height_pts: 200.0,
+        color_mode: ColorMode::Rgb,
+        page_index: 0,
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        merge_white_fills,
+        width_source: WidthSource::Pdf,
+    };
+    compositor::compose_text_outlines(&params).expect("compose_text_outlines")
+}
+
+/// 白色fillの部分が後から描かれる不透明な画像に隠れている画像はリダクションしないこと
+#[test]
+fn test_occluded_image_is_not_redacted() {
+    let image_streams = HashMap::from([
+        ("Im1".to_string(), raw_rgb_image(lopdf::Dictionary::new())),
+        ("Im2".to_string(), raw_rgb_image(lopdf::Dictionary::new())),
+    ]);
+    // Im1(50..150)の上の白色fill(60..90)を、後に描くIm2(55..95)が覆う
+    let content = b"q 100 0 0 100 50 50 cm /Im1 Do Q \
+        1 1 1 rg 60 60 30 30 re f \
+        q 40 0 0 40 55 55 cm /Im2 Do Q";
+
+    let data = redact_with_outlines(content, &image_streams);
+    assert!(
+        !data.modified_images.contains_key("Im1"),
+        "occluded Im1 should not be redacted"
+    );
+    assert!(data.modified_images.contains_key("Im2"));
+}
+
+/// merge_white_fillsでは、横に並んだ3つの白色fillを1つの領域にまとめてから適用すること
+#[test]
+fn test_adjacent_white_fills_merge_into_one_region() {
+    let image_streams =
+        HashMap::from([("Im1".to_string(), raw_rgb_image(lopdf::Dictionary::new()))]);
+    // Im1(50..150)の上の白いセル3つ（60..70, 70..80, 80..90 × 60..80）
+    let content = b"q 100 0 0 100 50 50 cm /Im1 Do Q \
+        1 1 1 rg 60 60 10 20 re f 70 60 10 20 re f 80 60 10 20 re f";
+
+    let separate = redact_with_outlines(content, &image_streams);
+    assert_eq!(separate.modified_images["Im1"].redacted_regions.len(), 3);
+
+    let merged = redact_with_outlines_merging(content, &image_streams, true);
+    let regions = &merged.modified_images["Im1"].redacted_regions;
+    assert_eq!(regions.len(), 1, "{regions:?}");
+    let BBox {
+        x_min,
+        y_min,
+        x_max,
+        y_max,
+    } = regions[0];
+    assert_eq!((x_min, y_min, x_max, y_max), (60.0, 60.0, 90.0, 80.0));
+}
+
+/// 描画順が逆（Im2が下）なら、白色fillと重なるIm1はリダクションされること
+#[test]
+fn test_image_drawn_above_occluder_is_redacted() {
+    let image_streams = HashMap::from([
+        ("Im1".to_string(), raw_rgb_image(lopdf::Dictionary::new())),
+        ("Im2".to_string(), raw_rgb_image(lopdf::Dictionary::new())),
+    ]);
+    let content = b"q 40 0 0 40 55 55 cm /Im2 Do Q \
+        q 100 0 0 100 50 50 cm /Im1 Do Q \
+        1 1 1 rg 60 60 30 30 re f";
+
+    let data = redact_with_outlines(content, &image_streams);
+    assert!(data.modified_images.contains_key("Im1"));
+}
+
+/// 後から描かれる画像が半透明（/SMaskあり）なら遮蔽物とみなさないこと
+#[test]
+fn test_translucent_image_does_not_occlude() {
+    let smask = lopdf::Object::Reference((99, 0));
+    let image_streams = HashMap::from([
+        ("Im1".to_string(), raw_rgb_image(lopdf::Dictionary::new())),
+        (
+            "Im2".to_string(),
+            raw_rgb_image(dictionary! { "SMask" => smask }),
+        ),
+    ]);
+    let content = b"q 100 0 0 100 50 50 cm /Im1 Do Q \
+        1 1 1 rg 60 60 30 30 re f \
+        q 40 0 0 40 55 55 cm /Im2 Do Q";
+
+    let data = redact_with_outlines(content, &image_streams);
+    assert!(data.modified_images.contains_key("Im1"));
+}
+
+/// 200x200のFlateDecode DeviceRGB画像XObject（`pixel`で各画素の色を決める）
+fn flate_rgb_image(pixel: impl Fn(u32, u32) -> [u8; 3]) -> lopdf::Stream {
+    let mut raw = Vec::with_capacity(200 * 200 * 3);
+    for y in 0..200 {
+        for x in 0..200 {
+            raw.extend_from_slice(&pixel(x, y));
+        }
+    }
+    let dict = dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Image",
+        "Width" => 200,
+        "Height" => 200,
+        "ColorSpace" => "DeviceRGB",
+        "BitsPerComponent" => 8,
+    };
+    let mut stream = lopdf::Stream::new(dict, raw);
+    stream.compress().expect("compress test image");
+    stream
+}
+
+/// 写真と線画が並ぶページでは、それぞれ種類ごとのDPI上限と形式で再エンコードすること
+#[test]
+fn test_photo_and_line_art_are_optimized_differently() {
+    // 乱数的な連続階調の写真と、白地に黒い縦縞の線画
+    let photo = flate_rgb_image(|x, y| {
+        let v = (x * 7919 + y * 104_729).wrapping_mul(2_654_435_761) >> 8;
+        [v as u8, (v >> 8) as u8, (v >> 16) as u8]
+    });
+    let line_art = flate_rgb_image(|x, _| if x % 20 < 4 { [0; 3] } else { [255; 3] });
+    let image_streams = HashMap::from([("Im1".to_string(), photo), ("Im2".to_string(), line_art)]);
+    // 100pt角に200pxずつ（実効144DPI）並べ、境目に小さな白色fillを重ねる
+    let content = b"q 100 0 0 100 0 50 cm /Im1 Do Q \
+        q 100 0 0 100 100 50 cm /Im2 Do Q \
+        1 1 1 rg 90 90 20 20 re f";
+
+    let fonts = HashMap::new();
+    let fill_resources = FillResources::default();
+    let params = compositor::TextOutlinesParams {
+        content_bytes: content,
+        fonts: &fonts,
+        image_streams: &image_streams,
+        matte_smasks: &HashMap::new(),
+        fill_resources: &fill_resources,
+        page_width_pts: 200.0,
+        page_height_pts: 200.0,
+        color_mode: ColorMode::Rgb,
+        page_index: 0,
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi {
+            photo: Some(36),
+            line_art: Some(144),
+        },
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        merge_white_fills: false,
+        width_source: WidthSource::Pdf,
+    };
+    let data = compositor::compose_text_outlines(&params).expect("compose_text_outlines");
+
+    let photo = &data.modified_images["Im1"];
+    assert_eq!(photo.filter, "DCTDecode");
+    assert_eq!((photo.width, photo.height), (50, 50));
+
+    let line_art = &data.modified_images["Im2"];
+    assert_eq!(line_art.filter, "FlateDecode");
+    assert_eq!((line_art.width, line_art.height), (200, 200));
+}
+
 // ---- crop_text_regions_jbig2 tests ----
 
 /// Test cropping a single text region as JBIG2 from a 1-bit mask.
@@ -643,7 +1162,7 @@ fn test_crop_text_regions_jbig2_single() {
         height: 50,
     }];
 
-    let result = compositor::crop_text_regions_jbig2(&mask, &bboxes);
+    let result = compositor::crop_text_regions_jbig2(&mask, &bboxes, &Jbig2Options::default());
     assert!(
         result.is_ok(),
         "crop_text_regions_jbig2 failed: {:?}",
@@ -690,7 +1209,7 @@ fn test_crop_text_regions_jbig2_multiple() {
         },
     ];
 
-    let result = compositor::crop_text_regions_jbig2(&mask, &bboxes);
+    let result = compositor::crop_text_regions_jbig2(&mask, &bboxes, &Jbig2Options::default());
     assert!(result.is_ok(), "should succeed: {:?}", result.err());
 
     let crops = result.unwrap();
@@ -707,7 +1226,7 @@ fn test_crop_text_regions_jbig2_multiple() {
 #[test]
 fn test_crop_text_regions_jbig2_empty() {
     let mask = Pix::create(200, 200, 1).expect("create 1-bit Pix");
-    let result = compositor::crop_text_regions_jbig2(&mask, &[]);
+    let result = compositor::crop_text_regions_jbig2(&mask, &[], &Jbig2Options::default());
     assert!(result.is_ok());
     let crops = result.unwrap();
     assert!(crops.is_empty(), "empty bboxes should yield empty crops");
@@ -449,3 +449,113 @@ fn test_remove_fonts_shared_resources() {
         "Masked and unmasked pages should reference different Resources objects"
     );
 }
+
+#[test]
+fn test_simplify_operations_drops_redundant_state_changes() {
+    let content = b"1 g q Q 0 0 10 10 re f 1 g 0 0 5 5 re f q 0 g Q 1 G 1 G S";
+    let ops = lopdf::content::Content::decode(content).unwrap().operations;
+    let simplified = pdf_masking::pdf::optimizer::simplify_operations(ops);
+    let encoded = lopdf::content::Content {
+        operations: simplified,
+    }
+    .encode()
+    .unwrap();
+    let expected = lopdf::content::Content::decode(b"1 g 0 0 10 10 re f 0 0 5 5 re f 1 G S")
+        .unwrap()
+        .encode()
+        .unwrap();
+    assert_eq!(encoded, expected);
+}
+
+#[test]
+fn test_simplify_operations_keeps_color_restored_by_q() {
+    // After Q the fill color is 1 g again, so the following 0 g must stay
+    let content = b"1 g q 0 g 0 0 1 1 re f Q 0 g 0 0 1 1 re f";
+    let ops = lopdf::content::Content::decode(content).unwrap().operations;
+    let original = ops.len();
+    let simplified = pdf_masking::pdf::optimizer::simplify_operations(ops);
+    assert_eq!(simplified.len(), original);
+}
+
+#[test]
+fn test_simplify_content_streams_rewrites_page_content() {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let content_id = doc.add_object(Stream::new(
+        dictionary! {},
+        b"q Q 1 g 0 0 10 10 re f 1 g 0 0 5 5 re f".to_vec(),
+    ));
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "Contents" => content_id,
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+
+    pdf_masking::pdf::optimizer::simplify_content_streams(&mut doc, &[page_id]).unwrap();
+
+    let ops = doc.get_and_decode_page_content(page_id).unwrap().operations;
+    let operators: Vec<&str> = ops.iter().map(|op| op.operator.as_str()).collect();
+    assert_eq!(operators, ["g", "re", "f", "re", "f"]);
+}
+
+#[test]
+fn test_split_content_streams_splits_large_content() {
+    let mut doc = Document::with_version("1.5");
+    let pages_id = doc.new_object_id();
+    let large: Vec<u8> = (0..5000)
+        .flat_map(|i| format!("{i} 0 m {i} 10 l S\n").into_bytes())
+        .collect();
+    let large_id = doc.add_object(Stream::new(dictionary! {}, large.clone()));
+    let small_id = doc.add_object(Stream::new(dictionary! {}, b"q Q".to_vec()));
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "Contents" => vec![large_id.into(), small_id.into()],
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+
+    pdf_masking::pdf::optimizer::split_content_streams(&mut doc, &[page_id], 4096).unwrap();
+
+    let contents = doc.get_page_contents(page_id);
+    assert!(
+        contents.len() > 2,
+        "large stream should be split: {contents:?}"
+    );
+    assert_eq!(contents.last(), Some(&small_id), "small stream is kept");
+    let pieces: Vec<Vec<u8>> = contents
+        .iter()
+        .map(|&id| {
+            doc.get_object(id)
+                .unwrap()
+                .as_stream()
+                .unwrap()
+                .content
+                .clone()
+        })
+        .collect();
+    assert!(pieces.iter().all(|piece| piece.len() <= 4096));
+    let mut expected = large.clone();
+    expected.extend_from_slice(b"q Q");
+    assert_eq!(pieces.concat(), expected);
+
+    // 分けたストリームは元と同じオペレーションとして解釈される
+    let ops = doc.get_and_decode_page_content(page_id).unwrap().operations;
+    assert_eq!(ops.len(), 5000 * 3 + 2);
+}
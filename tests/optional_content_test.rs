@@ -0,0 +1,154 @@
+// オプショナルコンテンツ（OCG / レイヤー）の保持テスト
+
+use lopdf::{Document, Object, Stream, dictionary};
+use pdf_masking::config::job::ColorMode;
+use pdf_masking::pipeline::job_runner::{JobConfig, run_job};
+
+/// ヘルパー: OCGレイヤーを1つ持ち、BDC/EMCでマークされたコンテンツを含む1ページPDFを作成する
+fn create_pdf_with_ocg(path: &std::path::Path) {
+    let mut doc = Document::with_version("1.7");
+
+    let ocg_id = doc.add_object(dictionary! {
+        "Type" => "OCG",
+        "Name" => Object::string_literal("Dimensions"),
+    });
+
+    let content = b"/OC /L1 BDC 0 0 m 100 100 l S EMC".to_vec();
+    let contents_id = doc.add_object(Stream::new(dictionary! {}, content));
+
+    let pages_id = doc.new_object_id();
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "Contents" => contents_id,
+        "Resources" => dictionary! {
+            "Properties" => dictionary! {
+                "L1" => ocg_id,
+            },
+        },
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+        "OCProperties" => dictionary! {
+            "OCGs" => vec![ocg_id.into()],
+            "D" => dictionary! {
+                "ON" => vec![ocg_id.into()],
+                "Order" => vec![ocg_id.into()],
+            },
+        },
+    });
+    doc.trailer.set("Root", catalog_id);
+
+    doc.save(path).expect("failed to save test PDF");
+}
+
+#[test]
+fn test_ocproperties_survive_passthrough_job() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("layers.pdf");
+    let output = dir.path().join("layers_out.pdf");
+    create_pdf_with_ocg(&input);
+
+    let config = JobConfig {
+        input_path: input,
+        output_path: output.clone(),
+        default_color_mode: ColorMode::Skip,
+        ..Default::default()
+    };
+    let result = run_job(&config).expect("passthrough job should succeed");
+    assert_eq!(result.pages_processed, 1);
+
+    let doc = Document::load(&output).expect("load output PDF");
+    let catalog = doc.catalog().expect("output should have a Catalog");
+    let oc = catalog
+        .get(b"OCProperties")
+        .and_then(Object::as_dict)
+        .expect("/OCProperties should be preserved");
+
+    let ocgs = oc
+        .get(b"OCGs")
+        .and_then(Object::as_array)
+        .expect("OCGs array");
+    assert_eq!(ocgs.len(), 1);
+    let ocg_id = ocgs[0].as_reference().expect("OCG should be a reference");
+    let ocg = doc.get_dictionary(ocg_id).expect("OCG object should exist");
+    assert_eq!(ocg.get(b"Name").unwrap().as_str().unwrap(), b"Dimensions");
+
+    // ページのProperties経由のOCGと/OCPropertiesのOCGは同一オブジェクトを指す
+    let page_id = *doc.get_pages().get(&1).expect("page 1");
+    let page = doc.get_dictionary(page_id).unwrap();
+    let resources = page.get(b"Resources").and_then(Object::as_dict).unwrap();
+    let properties = resources
+        .get(b"Properties")
+        .and_then(Object::as_dict)
+        .unwrap();
+    assert_eq!(
+        properties.get(b"L1").unwrap().as_reference().unwrap(),
+        ocg_id
+    );
+
+    // BDC/EMCマーク付きコンテンツもそのまま保持される
+    let content = doc.get_page_content(page_id).expect("page content");
+    let content = String::from_utf8_lossy(&content);
+    assert!(
+        content.contains("BDC"),
+        "BDC should be preserved: {content}"
+    );
+    assert!(
+        content.contains("EMC"),
+        "EMC should be preserved: {content}"
+    );
+}
+
+#[test]
+fn test_no_ocproperties_when_source_has_none() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("plain.pdf");
+    let output = dir.path().join("plain_out.pdf");
+
+    let mut doc = Document::with_version("1.7");
+    let contents_id = doc.add_object(Stream::new(dictionary! {}, Vec::new()));
+    let pages_id = doc.new_object_id();
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "Contents" => contents_id,
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.save(&input).expect("save input");
+
+    let config = JobConfig {
+        input_path: input,
+        output_path: output.clone(),
+        default_color_mode: ColorMode::Skip,
+        ..Default::default()
+    };
+    run_job(&config).expect("passthrough job should succeed");
+
+    let out = Document::load(&output).expect("load output PDF");
+    assert!(!out.catalog().unwrap().has(b"OCProperties"));
+}
@@ -0,0 +1,114 @@
+// PDF/A OutputIntentの出力テスト
+
+use std::path::{Path, PathBuf};
+
+use lopdf::{Document, Object, Stream, dictionary};
+use pdf_masking::config::job::ColorMode;
+use pdf_masking::pipeline::job_runner::{JobConfig, run_job};
+
+/// ヘルパー: 線を1本描くだけの1ページPDFを作成する
+fn create_line_pdf(path: &Path) {
+    let mut doc = Document::with_version("1.7");
+    let contents_id = doc.add_object(Stream::new(dictionary! {}, b"0 0 m 100 100 l S".to_vec()));
+    let pages_id = doc.new_object_id();
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "Contents" => contents_id,
+        "Resources" => dictionary! {},
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.save(path).expect("failed to save test PDF");
+}
+
+fn job_config(input: PathBuf, output: PathBuf, color_mode: ColorMode) -> JobConfig {
+    JobConfig {
+        input_path: input,
+        output_path: output,
+        default_color_mode: color_mode,
+        dpi: 72,
+        pdfa_output_intent: true,
+        ..Default::default()
+    }
+}
+
+/// 出力PDFのOutputIntentの(/OutputConditionIdentifier, ICCの/N, プロファイルの色空間)を返す。
+fn output_intent(path: &Path) -> (String, i64, Vec<u8>) {
+    let doc = Document::load(path).expect("load output PDF");
+    let intents = doc
+        .catalog()
+        .unwrap()
+        .get(b"OutputIntents")
+        .and_then(Object::as_array)
+        .expect("/OutputIntents should be present");
+    assert_eq!(intents.len(), 1);
+    let intent = intents[0].as_dict().expect("intent dictionary");
+    assert_eq!(intent.get(b"S").unwrap().as_name().unwrap(), b"GTS_PDFA1");
+    let identifier = intent
+        .get(b"OutputConditionIdentifier")
+        .and_then(Object::as_str)
+        .expect("identifier");
+    let icc = doc
+        .get_object(
+            intent
+                .get(b"DestOutputProfile")
+                .unwrap()
+                .as_reference()
+                .unwrap(),
+        )
+        .and_then(Object::as_stream)
+        .expect("ICC profile stream");
+    let n = icc.dict.get(b"N").and_then(Object::as_i64).expect("/N");
+    let profile = icc
+        .decompressed_content()
+        .unwrap_or_else(|_| icc.content.clone());
+    (
+        String::from_utf8_lossy(identifier).into_owned(),
+        n,
+        profile[16..20].to_vec(),
+    )
+}
+
+#[test]
+fn test_grayscale_output_embeds_gray_output_intent() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("gray.pdf");
+    let output = dir.path().join("gray_out.pdf");
+    create_line_pdf(&input);
+
+    run_job(&job_config(input, output.clone(), ColorMode::Grayscale)).expect("job");
+
+    let (identifier, n, color_space) = output_intent(&output);
+    assert_eq!(identifier, "Gray Gamma 2.2");
+    assert_eq!(n, 1);
+    assert_eq!(color_space, b"GRAY");
+}
+
+#[test]
+fn test_color_output_embeds_srgb_output_intent() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("color.pdf");
+    let output = dir.path().join("color_out.pdf");
+    create_line_pdf(&input);
+
+    // Skipページは元のコンテンツをそのまま使うため、カラーとして扱う
+    run_job(&job_config(input, output.clone(), ColorMode::Skip)).expect("job");
+
+    let (identifier, n, color_space) = output_intent(&output);
+    assert_eq!(identifier, "sRGB IEC61966-2.1");
+    assert_eq!(n, 3);
+    assert_eq!(color_space, b"RGB ");
+}
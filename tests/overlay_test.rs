@@ -0,0 +1,117 @@
+// オーバーレイPDFからのリダクション矩形読み取りのテスト
+
+use std::path::Path;
+
+use lopdf::{Document, Object, Stream, dictionary};
+use pdf_masking::pdf::overlay::read_overlay_redactions;
+
+/// 各ページのコンテンツと注釈を指定して、200x200ptのページからなるPDFを保存する。
+fn save_overlay_pdf(path: &Path, pages: &[(&[u8], Vec<Object>)]) {
+    let mut doc = Document::with_version("1.7");
+    let pages_id = doc.new_object_id();
+
+    let mut kids = Vec::new();
+    for (content, annots) in pages {
+        let content_id = doc.add_object(Stream::new(dictionary! {}, content.to_vec()));
+        let mut page = dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 200.into(), 200.into()],
+            "Contents" => content_id,
+        };
+        if !annots.is_empty() {
+            let annot_ids: Vec<Object> = annots
+                .iter()
+                .map(|annot| doc.add_object(annot.clone()).into())
+                .collect();
+            page.set("Annots", annot_ids);
+        }
+        kids.push(doc.add_object(page).into());
+    }
+
+    let count = kids.len() as i64;
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => count,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.save(path).expect("save overlay PDF");
+}
+
+fn square_annot(rect: [i64; 4]) -> Object {
+    Object::Dictionary(dictionary! {
+        "Type" => "Annot",
+        "Subtype" => "Square",
+        "Rect" => rect.iter().map(|&v| v.into()).collect::<Vec<Object>>(),
+        "C" => vec![1.into(), 0.into(), 0.into()],
+    })
+}
+
+#[test]
+fn test_overlay_annotation_marks_box_on_first_page_only() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let path = dir.path().join("overlay.pdf");
+    // /Rectの角は逆順でもよい
+    save_overlay_pdf(
+        &path,
+        &[(b"", vec![square_annot([150, 120, 50, 20])]), (b"", vec![])],
+    );
+
+    let redactions = read_overlay_redactions(&path).expect("read overlay");
+    assert_eq!(redactions.len(), 1, "only page 1 has a box: {redactions:?}");
+    let rects = &redactions[&1];
+    assert_eq!(rects.len(), 1);
+    assert_eq!(
+        (
+            rects[0].x_min,
+            rects[0].y_min,
+            rects[0].x_max,
+            rects[0].y_max
+        ),
+        (50.0, 20.0, 150.0, 120.0)
+    );
+}
+
+#[test]
+fn test_overlay_filled_rects_and_ignored_annotations() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let path = dir.path().join("overlay.pdf");
+    let link = Object::Dictionary(dictionary! {
+        "Type" => "Annot",
+        "Subtype" => "Link",
+        "Rect" => vec![0.into(), 0.into(), 10.into(), 10.into()],
+    });
+    save_overlay_pdf(
+        &path,
+        &[
+            // 白の塗りつぶし（背景）は無視し、赤の矩形だけを拾う
+            (b"1 g 0 0 200 200 re f", vec![link]),
+            (
+                b"1 g 0 0 200 200 re f q 2 0 0 2 0 0 cm 1 0 0 rg 10 20 30 40 re f Q",
+                vec![],
+            ),
+        ],
+    );
+
+    let redactions = read_overlay_redactions(&path).expect("read overlay");
+    assert!(!redactions.contains_key(&1), "got {redactions:?}");
+    let rects = &redactions[&2];
+    assert_eq!(rects.len(), 1);
+    assert_eq!(
+        (
+            rects[0].x_min,
+            rects[0].y_min,
+            rects[0].x_max,
+            rects[0].y_max
+        ),
+        (20.0, 40.0, 80.0, 120.0)
+    );
+}
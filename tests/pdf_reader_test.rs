@@ -1,4 +1,9 @@
 use lopdf::{Document, Object, Stream, dictionary};
+use pdf_masking::pdf::content_stream::{
+    BBox, FillResources, extract_white_fill_rects_with_resources, extract_xobject_placements,
+    extract_xobject_placements_visual,
+};
+use pdf_masking::pdf::image_xobject::bbox_overlaps;
 use pdf_masking::pdf::reader::PdfReader;
 
 /// ヘルパー: 指定されたMediaBoxを持つ最小限のPDFドキュメントを作成する
@@ -134,6 +139,43 @@ fn test_page_dimensions_with_non_zero_origin() {
     assert_eq!(height, 792.0);
 }
 
+#[test]
+fn test_page_origin_negative_lower_left() {
+    // 左下隅が負のMediaBox [-10 -10 605 802]
+    let media_box = vec![
+        Object::Integer(-10),
+        Object::Integer(-10),
+        Object::Integer(605),
+        Object::Integer(802),
+    ];
+    let mut doc = create_test_pdf_with_media_box(media_box);
+
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    doc.save(temp_file.path()).unwrap();
+
+    let reader = PdfReader::open(temp_file.path()).unwrap();
+    assert_eq!(reader.page_origin(1).unwrap(), (-10.0, -10.0));
+    assert_eq!(reader.page_dimensions(1).unwrap(), (615.0, 812.0));
+}
+
+#[test]
+fn test_page_origin_real_values() {
+    // 実数のMediaBox（原点 0.0）
+    let media_box = vec![
+        Object::Real(0.0),
+        Object::Real(0.0),
+        Object::Real(595.276),
+        Object::Real(841.89),
+    ];
+    let mut doc = create_test_pdf_with_media_box(media_box);
+
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    doc.save(temp_file.path()).unwrap();
+
+    let reader = PdfReader::open(temp_file.path()).unwrap();
+    assert_eq!(reader.page_origin(1).unwrap(), (0.0, 0.0));
+}
+
 #[test]
 fn test_page_dimensions_inherited_from_parent() {
     // MediaBoxが親Pagesノードから継承される場合
@@ -155,6 +197,57 @@ fn test_page_dimensions_inherited_from_parent() {
     assert_eq!(height, 792.0);
 }
 
+#[test]
+fn test_page_dimensions_inherited_through_intermediate_pages_node() {
+    // ルートPagesノードだけが間接参照のMediaBoxを持ち、中間Pagesノード経由で継承する
+    let mut doc = Document::with_version("1.7");
+    let root_pages_id = doc.new_object_id();
+    let mid_pages_id = doc.new_object_id();
+    let media_box_id = doc.add_object(vec![
+        Object::Integer(-10),
+        Object::Integer(-20),
+        Object::Real(410.0),
+        Object::Integer(580),
+    ]);
+
+    let contents_id = doc.add_object(Stream::new(dictionary! {}, vec![]));
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => mid_pages_id,
+        "Contents" => contents_id,
+    });
+    doc.objects.insert(
+        mid_pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Parent" => root_pages_id,
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+    doc.objects.insert(
+        root_pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![mid_pages_id.into()],
+            "Count" => 1,
+            "MediaBox" => media_box_id,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => root_pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    doc.save(temp_file.path()).unwrap();
+
+    let reader = PdfReader::open(temp_file.path()).unwrap();
+    assert_eq!(reader.page_dimensions(1).unwrap(), (420.0, 600.0));
+    assert_eq!(reader.page_origin(1).unwrap(), (-10.0, -20.0));
+}
+
 #[test]
 fn test_page_dimensions_error_on_zero_dimensions() {
     // 幅がゼロのMediaBox
@@ -179,6 +272,51 @@ fn test_page_dimensions_error_on_zero_dimensions() {
     );
 }
 
+#[test]
+fn test_zero_height_media_box_is_rejected_with_page_number() {
+    let media_box = vec![
+        Object::Integer(0),
+        Object::Integer(100),
+        Object::Integer(612),
+        Object::Integer(100), // height = 0
+    ];
+    let mut doc = create_test_pdf_with_media_box(media_box);
+
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    doc.save(temp_file.path()).unwrap();
+
+    let reader = PdfReader::open(temp_file.path()).unwrap();
+    for err in [
+        reader.page_dimensions(1).unwrap_err(),
+        reader.page_pixel_size(1, 300).unwrap_err(),
+    ] {
+        assert!(
+            err.to_string().contains("Invalid MediaBox on page 1"),
+            "{err}"
+        );
+    }
+}
+
+#[test]
+fn test_page_pixel_size_error_on_subpixel_page() {
+    // 0.4pt幅のページは72dpiで0pxに丸められる
+    let media_box = vec![
+        Object::Integer(0),
+        Object::Integer(0),
+        Object::Real(0.4),
+        Object::Integer(792),
+    ];
+    let mut doc = create_test_pdf_with_media_box(media_box);
+
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    doc.save(temp_file.path()).unwrap();
+
+    let reader = PdfReader::open(temp_file.path()).unwrap();
+    let err = reader.page_pixel_size(1, 72).unwrap_err();
+    assert!(err.to_string().contains("smaller than one pixel"), "{err}");
+    assert!(reader.page_pixel_size(1, 300).is_ok());
+}
+
 #[test]
 fn test_page_dimensions_error_on_negative_dimensions() {
     // 負の寸法を持つMediaBox（座標が逆転）
@@ -312,3 +450,455 @@ fn test_page_dimensions_error_on_missing_media_box() {
         "error should mention MediaBox not found"
     );
 }
+
+#[test]
+fn test_page_pixel_size_letter_at_300_dpi() {
+    // Letter サイズ（612 × 792 pt）を300 DPIでラスタライズ
+    let media_box = vec![
+        Object::Integer(0),
+        Object::Integer(0),
+        Object::Integer(612),
+        Object::Integer(792),
+    ];
+    let mut doc = create_test_pdf_with_media_box(media_box);
+
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    doc.save(temp_file.path()).unwrap();
+
+    let reader = PdfReader::open(temp_file.path()).unwrap();
+    let (width_px, height_px) = reader.page_pixel_size(1, 300).unwrap();
+
+    assert_eq!(width_px, 2550);
+    assert_eq!(height_px, 3300);
+}
+
+#[test]
+fn test_page_pixel_size_with_inherited_rotate_and_user_unit() {
+    // 親Pagesノードから/Rotate 90を継承し、ページに/UserUnit 2を持つ場合
+    let media_box = vec![
+        Object::Integer(0),
+        Object::Integer(0),
+        Object::Integer(612),
+        Object::Integer(792),
+    ];
+    let mut doc = create_test_pdf_with_media_box(media_box);
+    let page_id = *doc.get_pages().get(&1).unwrap();
+    let pages_id = doc
+        .get_dictionary(page_id)
+        .unwrap()
+        .get(b"Parent")
+        .unwrap()
+        .as_reference()
+        .unwrap();
+    doc.get_dictionary_mut(pages_id)
+        .unwrap()
+        .set("Rotate", Object::Integer(90));
+    doc.get_dictionary_mut(page_id)
+        .unwrap()
+        .set("UserUnit", Object::Integer(2));
+
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    doc.save(temp_file.path()).unwrap();
+
+    let reader = PdfReader::open(temp_file.path()).unwrap();
+    let (width_px, height_px) = reader.page_pixel_size(1, 72).unwrap();
+
+    // 回転で幅と高さが入れ替わり、UserUnitで2倍になる
+    assert_eq!(width_px, 1584);
+    assert_eq!(height_px, 1224);
+}
+
+#[test]
+fn test_page_pixel_size_clipped_to_crop_box() {
+    // MediaBoxより小さいCropBoxでは、ラスタライズ範囲と原点がCropBoxに合わせられる
+    let media_box = vec![
+        Object::Integer(0),
+        Object::Integer(0),
+        Object::Integer(612),
+        Object::Integer(792),
+    ];
+    let mut doc = create_test_pdf_with_media_box(media_box);
+    let page_id = doc.page_iter().next().unwrap();
+    doc.get_dictionary_mut(page_id).unwrap().set(
+        "CropBox",
+        vec![36.into(), 72.into(), 576.into(), 720.into()],
+    );
+
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    doc.save(temp_file.path()).unwrap();
+
+    let reader = PdfReader::open(temp_file.path()).unwrap();
+    assert_eq!(reader.page_dimensions(1).unwrap(), (540.0, 648.0));
+    assert_eq!(reader.page_origin(1).unwrap(), (36.0, 72.0));
+    assert_eq!(reader.page_pixel_size(1, 72).unwrap(), (540, 648));
+}
+
+#[test]
+fn test_crop_box_outside_media_box_falls_back_to_media_box() {
+    // MediaBoxからはみ出すCropBoxは共通部分に、重ならないCropBoxは無視する
+    let media_box = vec![
+        Object::Integer(0),
+        Object::Integer(0),
+        Object::Integer(612),
+        Object::Integer(792),
+    ];
+    let mut doc = create_test_pdf_with_media_box(media_box);
+    let page_id = doc.page_iter().next().unwrap();
+    doc.get_dictionary_mut(page_id).unwrap().set(
+        "CropBox",
+        vec![(-20).into(), 100.into(), 300.into(), 900.into()],
+    );
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    doc.save(temp_file.path()).unwrap();
+    let reader = PdfReader::open(temp_file.path()).unwrap();
+    assert_eq!(reader.page_dimensions(1).unwrap(), (300.0, 692.0));
+    assert_eq!(reader.page_origin(1).unwrap(), (0.0, 100.0));
+
+    doc.get_dictionary_mut(page_id).unwrap().set(
+        "CropBox",
+        vec![700.into(), 800.into(), 900.into(), 1000.into()],
+    );
+    doc.save(temp_file.path()).unwrap();
+    let reader = PdfReader::open(temp_file.path()).unwrap();
+    assert_eq!(reader.page_dimensions(1).unwrap(), (612.0, 792.0));
+}
+
+#[test]
+fn test_visual_redaction_on_rotated_page_lands_on_intended_image() {
+    // /Rotate 90のページ: Im1はページ空間の左上、Im2は右下に描かれる。
+    // 表示上はIm1が右上に見えるので、見た目の右上に置いた矩形はIm1だけに重なる
+    let media_box = vec![
+        Object::Integer(0),
+        Object::Integer(0),
+        Object::Integer(612),
+        Object::Integer(792),
+    ];
+    let mut doc = create_test_pdf_with_media_box(media_box);
+    let page_id = doc.page_iter().next().unwrap();
+    doc.get_dictionary_mut(page_id)
+        .unwrap()
+        .set("Rotate", Object::Integer(90));
+    let content = b"q 100 0 0 50 50 700 cm /Im1 Do Q q 100 0 0 50 450 50 cm /Im2 Do Q".to_vec();
+    let content_id = doc.add_object(Stream::new(dictionary! {}, content.clone()));
+    doc.get_dictionary_mut(page_id)
+        .unwrap()
+        .set("Contents", content_id);
+
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    doc.save(temp_file.path()).unwrap();
+    let reader = PdfReader::open(temp_file.path()).unwrap();
+    let orientation = reader.page_orientation(1).unwrap();
+    assert_eq!(orientation.rotate, 90);
+
+    let visual_rect = BBox {
+        x_min: 690.0,
+        y_min: 450.0,
+        x_max: 760.0,
+        y_max: 570.0,
+    };
+    let page_rect = orientation.from_visual(&visual_rect);
+    let placements = extract_xobject_placements(&content).unwrap();
+    let hits: Vec<&str> = placements
+        .iter()
+        .filter(|p| bbox_overlaps(&p.bbox, &page_rect))
+        .map(|p| p.name.as_str())
+        .collect();
+    assert_eq!(hits, ["Im1"]);
+
+    // 表示上の座標での配置: Im1は右上(700..750, 462..562)に来る
+    let visual =
+        extract_xobject_placements_visual(&content, &FillResources::default(), &orientation)
+            .unwrap();
+    let im1 = &visual[0].bbox;
+    assert_eq!(
+        (im1.x_min, im1.y_min, im1.x_max, im1.y_max),
+        (700.0, 462.0, 750.0, 562.0)
+    );
+    let back = orientation.from_visual(im1);
+    assert_eq!(
+        (back.x_min, back.y_min, back.x_max, back.y_max),
+        (50.0, 700.0, 150.0, 750.0)
+    );
+}
+
+#[test]
+fn test_page_pixel_size_error_on_zero_dpi() {
+    let media_box = vec![
+        Object::Integer(0),
+        Object::Integer(0),
+        Object::Integer(612),
+        Object::Integer(792),
+    ];
+    let mut doc = create_test_pdf_with_media_box(media_box);
+
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    doc.save(temp_file.path()).unwrap();
+
+    let reader = PdfReader::open(temp_file.path()).unwrap();
+    assert!(reader.page_pixel_size(1, 0).is_err());
+}
+
+#[test]
+fn test_page_content_stream_run_length_decode() {
+    // RunLengthDecodeで圧縮されたコンテンツストリーム
+    let media_box = vec![
+        Object::Integer(0),
+        Object::Integer(0),
+        Object::Integer(612),
+        Object::Integer(792),
+    ];
+    let mut doc = create_test_pdf_with_media_box(media_box);
+    let page_id = *doc.get_pages().get(&1).unwrap();
+    let contents_id = doc
+        .get_dictionary(page_id)
+        .unwrap()
+        .get(b"Contents")
+        .unwrap()
+        .as_reference()
+        .unwrap();
+
+    // "0 0 m" (literal 5バイト) + " " (繰り返し) + "S" + EOD
+    let mut encoded = vec![4u8];
+    encoded.extend_from_slice(b"0 0 m");
+    encoded.extend_from_slice(&[255, b' ', 0, b'S', 128]);
+    doc.objects.insert(
+        contents_id,
+        Object::Stream(Stream::new(
+            dictionary! { "Filter" => "RunLengthDecode" },
+            encoded,
+        )),
+    );
+
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    doc.save(temp_file.path()).unwrap();
+
+    let reader = PdfReader::open(temp_file.path()).unwrap();
+    let content = reader.page_content_stream(1).unwrap();
+    assert_eq!(content, b"0 0 m  S");
+}
+
+#[test]
+fn test_page_content_stream_limited_rejects_oversized_stream() {
+    // 64 KiBに展開されるFlateDecodeのコンテンツストリーム
+    let media_box = vec![
+        Object::Integer(0),
+        Object::Integer(0),
+        Object::Integer(612),
+        Object::Integer(792),
+    ];
+    let mut doc = create_test_pdf_with_media_box(media_box);
+    let page_id = *doc.get_pages().get(&1).unwrap();
+    let contents_id = doc
+        .get_dictionary(page_id)
+        .unwrap()
+        .get(b"Contents")
+        .unwrap()
+        .as_reference()
+        .unwrap();
+
+    let mut stream = Stream::new(
+        dictionary! {},
+        "0 0 m 10 10 l S\n".repeat(4096).into_bytes(),
+    );
+    stream.compress().unwrap();
+    doc.objects.insert(contents_id, Object::Stream(stream));
+
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    doc.save(temp_file.path()).unwrap();
+
+    let reader = PdfReader::open(temp_file.path()).unwrap();
+    let err = reader
+        .page_content_stream_limited(1, 4096)
+        .expect_err("must exceed limit");
+    assert!(
+        matches!(err, pdf_masking::error::PdfMaskError::LimitExceeded(_)),
+        "{err}"
+    );
+    assert!(
+        err.to_string().contains("max_content_stream_bytes"),
+        "{err}"
+    );
+
+    let content = reader.page_content_stream_limited(1, 1 << 20).unwrap();
+    assert_eq!(content.len(), 16 * 4096);
+}
+
+#[test]
+fn test_page_rotation_inherited_from_pages_node() {
+    // /Rotate 90 を親Pagesノードで宣言し、ページ自身は/Rotateを持たない
+    let media_box = vec![
+        Object::Integer(0),
+        Object::Integer(0),
+        Object::Integer(612),
+        Object::Integer(792),
+    ];
+    let mut doc = create_test_pdf_with_media_box(media_box);
+    let page_id = *doc.get_pages().get(&1).unwrap();
+    let pages_id = doc
+        .get_dictionary(page_id)
+        .unwrap()
+        .get(b"Parent")
+        .unwrap()
+        .as_reference()
+        .unwrap();
+    doc.get_dictionary_mut(pages_id)
+        .unwrap()
+        .set("Rotate", Object::Integer(-270));
+
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    doc.save(temp_file.path()).unwrap();
+
+    let reader = PdfReader::open(temp_file.path()).unwrap();
+    assert_eq!(reader.page_rotation(1).unwrap(), 90);
+    let (width_px, height_px) = reader.page_pixel_size(1, 72).unwrap();
+    assert_eq!((width_px, height_px), (792, 612));
+}
+
+#[test]
+fn test_preflight_reports_lzw_image_and_type3_font() {
+    let mut doc = Document::with_version("1.7");
+    let pages_id = doc.new_object_id();
+
+    let image_id = doc.add_object(Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => 1,
+            "Height" => 1,
+            "ColorSpace" => "DeviceGray",
+            "BitsPerComponent" => 8,
+            "Filter" => "LZWDecode",
+        },
+        vec![0x80, 0x0B, 0x60, 0x50, 0x22, 0x0C, 0x0C, 0x85, 0x01],
+    ));
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type3",
+        "FontBBox" => vec![0.into(), 0.into(), 1000.into(), 1000.into()],
+        "FontMatrix" => vec![0.001.into(), 0.into(), 0.into(), 0.001.into(), 0.into(), 0.into()],
+        "CharProcs" => dictionary! {},
+        "Encoding" => dictionary! { "Type" => "Encoding", "Differences" => vec![] },
+        "FirstChar" => 0,
+        "LastChar" => 0,
+        "Widths" => vec![0.into()],
+    });
+    let content = b"q 10 0 0 10 0 0 cm /Im0 Do Q BT /T3 12 Tf (a) Tj ET";
+    let contents_id = doc.add_object(Stream::new(dictionary! {}, content.to_vec()));
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 200.into(), 200.into()],
+        "Contents" => contents_id,
+        "Resources" => dictionary! {
+            "XObject" => dictionary! { "Im0" => image_id },
+            "Font" => dictionary! { "T3" => font_id },
+        },
+    });
+    // 非対応の機能を持たない2ページ目
+    let plain_contents_id = doc.add_object(Stream::new(dictionary! {}, b"0 0 m".to_vec()));
+    let plain_page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 200.into(), 200.into()],
+        "Contents" => plain_contents_id,
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into(), plain_page_id.into()],
+            "Count" => 2,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    doc.save(temp_file.path()).unwrap();
+
+    let preflight = PdfReader::open(temp_file.path())
+        .unwrap()
+        .preflight()
+        .unwrap();
+    assert_eq!(preflight.unsupported_image_filters, vec!["LZWDecode"]);
+    assert_eq!(preflight.unsupported_fonts.len(), 1);
+    assert_eq!(preflight.unsupported_fonts[0].subtype, "Type3");
+    assert_eq!(preflight.pages_needing_pdfium, vec![1]);
+    assert!(!preflight.encrypted);
+    assert!(!preflight.has_optional_content);
+    assert!(!preflight.has_forms);
+    assert!(!preflight.has_javascript);
+}
+
+#[test]
+fn test_page_fill_resources_reads_transparency_group_form() {
+    let mut doc = Document::with_version("1.7");
+    let pages_id = doc.new_object_id();
+
+    // 透明グループのフォーム。中身はフォーム自身のExtGStateで不透明に白を塗る
+    let form_id = doc.add_object(Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Form",
+            "BBox" => vec![0.into(), 0.into(), 100.into(), 100.into()],
+            "Matrix" => vec![1.into(), 0.into(), 0.into(), 1.into(), 50.into(), 60.into()],
+            "Group" => dictionary! { "S" => "Transparency", "I" => true },
+            "Resources" => dictionary! {
+                "ExtGState" => dictionary! { "Opaque" => dictionary! { "ca" => 1 } },
+            },
+        },
+        b"/Opaque gs 1 g -10 -10 200 200 re f".to_vec(),
+    ));
+    let contents_id = doc.add_object(Stream::new(dictionary! {}, b"/Fm0 Do".to_vec()));
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 200.into(), 200.into()],
+        "Contents" => contents_id,
+        "Resources" => dictionary! {
+            "XObject" => dictionary! { "Fm0" => form_id },
+            "ExtGState" => dictionary! {
+                "Half" => dictionary! { "ca" => 0.5, "BM" => "Normal" },
+            },
+        },
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    doc.save(temp_file.path()).unwrap();
+
+    let reader = PdfReader::open(temp_file.path()).unwrap();
+    let resources = reader.page_fill_resources(1).unwrap();
+    let half = resources.ext_g_states["Half"];
+    assert_eq!(half.fill_alpha, Some(0.5));
+    assert_eq!(half.blend, Some(false));
+    assert_eq!(half.soft_mask, None);
+    let form = &resources.forms["Fm0"];
+    assert!(form.transparency_group);
+    assert_eq!((form.matrix.e, form.matrix.f), (50.0, 60.0));
+    assert_eq!(form.resources.ext_g_states["Opaque"].fill_alpha, Some(1.0));
+
+    // フォームの白はMatrixで配置され、/BBoxでクリップされる
+    let content = reader.page_content_stream(1).unwrap();
+    let rects = extract_white_fill_rects_with_resources(&content, &resources).unwrap();
+    assert_eq!(rects.len(), 1);
+    let r = &rects[0];
+    assert_eq!(
+        (r.x_min, r.y_min, r.x_max, r.y_max),
+        (50.0, 60.0, 150.0, 160.0)
+    );
+}
@@ -3,8 +3,8 @@
 // Phase 7: PDF構築（MRC → PDF）テスト
 
 use lopdf::{Document, Object, dictionary};
-use pdf_masking::config::job::ColorMode;
-use pdf_masking::mrc::MrcLayers;
+use pdf_masking::config::job::{ColorMode, ForegroundMask, OutputColorSpace};
+use pdf_masking::mrc::{MrcLayers, jpeg};
 use pdf_masking::pdf::content_stream::BBox;
 use pdf_masking::pdf::image_xobject::bbox_overlaps;
 use pdf_masking::pdf::writer::MrcPageWriter;
@@ -90,6 +90,7 @@ fn test_write_mrc_page() {
         page_width_pts: 595.276,
         page_height_pts: 841.89,
         color_mode: ColorMode::Rgb,
+        output_colorspace: OutputColorSpace::Rgb,
     };
 
     let mut writer = MrcPageWriter::new();
@@ -126,6 +127,277 @@ fn test_write_mrc_page() {
     );
 }
 
+#[test]
+fn test_write_mrc_page_cmyk() {
+    // CMYK出力のMRCページが読み込め、背景・前景がDeviceCMYKを宣言することを検証する。
+    let rgb = image::RgbImage::from_pixel(64, 48, image::Rgb([200, 40, 40]));
+    let layers = MrcLayers {
+        background_jpeg: jpeg::encode_rgb_to_cmyk_jpeg(&rgb, 50).expect("encode bg"),
+        foreground_jpeg: jpeg::encode_rgb_to_cmyk_jpeg(&rgb, 30).expect("encode fg"),
+        mask_jbig2: vec![0x97, 0x4A, 0x42, 0x32],
+        width: 64,
+        height: 48,
+        page_width_pts: 595.276,
+        page_height_pts: 841.89,
+        color_mode: ColorMode::Rgb,
+        output_colorspace: OutputColorSpace::Cmyk,
+    };
+
+    let mut writer = MrcPageWriter::new();
+    let page_id = writer.write_mrc_page(&layers).expect("write CMYK MRC page");
+    let pdf_bytes = writer.save_to_bytes().expect("save to bytes");
+    let doc = Document::load_mem(&pdf_bytes).expect("load PDF from memory");
+    assert_eq!(doc.get_pages().len(), 1);
+
+    let resources_ref = doc
+        .get_dictionary(page_id)
+        .and_then(|d| d.get(b"Resources"))
+        .and_then(Object::as_reference)
+        .expect("Resources ref");
+    let xobjects = doc
+        .get_dictionary(resources_ref)
+        .and_then(|r| r.get(b"XObject"))
+        .and_then(Object::as_dict)
+        .expect("XObject dict");
+
+    for name in [&b"BgImg"[..], b"FgImg"] {
+        let image_ref = xobjects
+            .get(name)
+            .and_then(Object::as_reference)
+            .expect("image ref");
+        let stream = doc
+            .get_object(image_ref)
+            .and_then(Object::as_stream)
+            .expect("image stream");
+        assert_eq!(
+            stream
+                .dict
+                .get(b"ColorSpace")
+                .and_then(Object::as_name)
+                .ok(),
+            Some(&b"DeviceCMYK"[..])
+        );
+        let decode = stream
+            .dict
+            .get(b"Decode")
+            .and_then(Object::as_array)
+            .expect("Decode array");
+        assert_eq!(decode.len(), 8);
+
+        // 埋め込まれたJPEGは単体でも元の色に復号できる
+        let decoded = image::load_from_memory(&stream.content).expect("decode CMYK JPEG");
+        let pixel = decoded.to_rgb8().get_pixel(10, 10).0;
+        for (got, want) in pixel.iter().zip([200u8, 40, 40]) {
+            assert!(got.abs_diff(want) <= 8, "{:?}", pixel);
+        }
+    }
+}
+
+#[test]
+fn test_write_mrc_page_with_stencil_foreground_mask() {
+    // ステンシル形式では、マスクがImageMaskとして前景画像の/Maskに置かれ、
+    // コンテンツは背景→前景の順に描くことを検証する。
+    let layers = MrcLayers {
+        background_jpeg: vec![0xFF, 0xD8, 0xFF, 0xE0],
+        foreground_jpeg: vec![0xFF, 0xD8, 0xFF, 0xE1],
+        mask_jbig2: vec![0x97, 0x4A, 0x42, 0x32],
+        width: 640,
+        height: 480,
+        page_width_pts: 595.276,
+        page_height_pts: 841.89,
+        color_mode: ColorMode::Rgb,
+        output_colorspace: OutputColorSpace::Rgb,
+    };
+
+    let mut writer = MrcPageWriter::new();
+    writer.set_foreground_mask(ForegroundMask::Stencil);
+    let page_id = writer.write_mrc_page(&layers).expect("write MRC page");
+    let pdf_bytes = writer.save_to_bytes().expect("save to bytes");
+    let doc = Document::load_mem(&pdf_bytes).expect("load PDF from memory");
+    assert_eq!(doc.get_pages().len(), 1);
+
+    let content = doc
+        .get_and_decode_page_content(page_id)
+        .expect("decode page content");
+    let drawn: Vec<&[u8]> = content
+        .operations
+        .iter()
+        .filter(|op| op.operator == "Do")
+        .filter_map(|op| op.operands.first().and_then(|o| o.as_name().ok()))
+        .collect();
+    assert_eq!(drawn, [&b"BgImg"[..], b"FgImg"]);
+
+    let resources_ref = doc
+        .get_dictionary(page_id)
+        .and_then(|d| d.get(b"Resources"))
+        .and_then(Object::as_reference)
+        .expect("Resources ref");
+    let fg_ref = doc
+        .get_dictionary(resources_ref)
+        .and_then(|r| r.get(b"XObject"))
+        .and_then(Object::as_dict)
+        .and_then(|x| x.get(b"FgImg"))
+        .and_then(Object::as_reference)
+        .expect("FgImg ref");
+    let fg = doc
+        .get_object(fg_ref)
+        .and_then(Object::as_stream)
+        .expect("FgImg stream");
+    assert!(
+        fg.dict.get(b"SMask").is_err(),
+        "stencil layout has no SMask"
+    );
+
+    let mask_ref = fg
+        .dict
+        .get(b"Mask")
+        .and_then(Object::as_reference)
+        .expect("FgImg /Mask ref");
+    let mask = doc
+        .get_object(mask_ref)
+        .and_then(Object::as_stream)
+        .expect("mask stream");
+    assert_eq!(
+        mask.dict.get(b"ImageMask").and_then(Object::as_bool).ok(),
+        Some(true)
+    );
+    assert!(
+        mask.dict.get(b"ColorSpace").is_err(),
+        "ImageMask must not declare a ColorSpace"
+    );
+    assert_eq!(
+        mask.dict.get(b"Decode").and_then(Object::as_array).ok(),
+        Some(&vec![Object::Integer(1), Object::Integer(0)])
+    );
+}
+
+#[test]
+fn test_write_mrc_page_interpolate_output() {
+    // interpolate_output有効時は背景・前景に/Interpolate trueが付き、既定では付かない
+    let layers = MrcLayers {
+        background_jpeg: vec![0xFF, 0xD8, 0xFF, 0xE0],
+        foreground_jpeg: vec![0xFF, 0xD8, 0xFF, 0xE1],
+        mask_jbig2: vec![0x97, 0x4A, 0x42, 0x32],
+        width: 640,
+        height: 480,
+        page_width_pts: 595.276,
+        page_height_pts: 841.89,
+        color_mode: ColorMode::Rgb,
+        output_colorspace: OutputColorSpace::Rgb,
+    };
+
+    for interpolate in [true, false] {
+        let mut writer = MrcPageWriter::new();
+        writer.set_interpolate_output(interpolate);
+        let page_id = writer.write_mrc_page(&layers).expect("write MRC page");
+        let pdf_bytes = writer.save_to_bytes().expect("save to bytes");
+        let doc = Document::load_mem(&pdf_bytes).expect("load PDF from memory");
+
+        let resources_ref = doc
+            .get_dictionary(page_id)
+            .and_then(|d| d.get(b"Resources"))
+            .and_then(Object::as_reference)
+            .expect("Resources ref");
+        let xobjects = doc
+            .get_dictionary(resources_ref)
+            .and_then(|r| r.get(b"XObject"))
+            .and_then(Object::as_dict)
+            .expect("XObject dict");
+        for name in [&b"BgImg"[..], b"FgImg"] {
+            let stream = xobjects
+                .get(name)
+                .and_then(Object::as_reference)
+                .and_then(|id| doc.get_object(id))
+                .and_then(Object::as_stream)
+                .expect("image stream");
+            let flag = stream
+                .dict
+                .get(b"Interpolate")
+                .and_then(Object::as_bool)
+                .ok();
+            assert_eq!(flag, interpolate.then_some(true), "{:?}", name);
+        }
+    }
+}
+
+#[test]
+fn test_write_mrc_page_tag_srgb() {
+    // tag_srgb有効時はRGBの背景・前景が共有のsRGB ICCBased色空間を参照する
+    let rgb_layers = |seed: u8| MrcLayers {
+        background_jpeg: vec![0xFF, 0xD8, 0xFF, seed],
+        foreground_jpeg: vec![0xFF, 0xD8, 0xFF, seed.wrapping_add(1)],
+        mask_jbig2: vec![0x97, 0x4A, 0x42, 0x32],
+        width: 640,
+        height: 480,
+        page_width_pts: 595.276,
+        page_height_pts: 841.89,
+        color_mode: ColorMode::Rgb,
+        output_colorspace: OutputColorSpace::Rgb,
+    };
+    let gray_layers = MrcLayers {
+        color_mode: ColorMode::Grayscale,
+        ..rgb_layers(0x10)
+    };
+
+    let mut writer = MrcPageWriter::new();
+    writer.set_tag_srgb(true);
+    let page_ids = [
+        writer
+            .write_mrc_page(&rgb_layers(0xE0))
+            .expect("write page 1"),
+        writer
+            .write_mrc_page(&rgb_layers(0xE2))
+            .expect("write page 2"),
+        writer.write_mrc_page(&gray_layers).expect("write page 3"),
+    ];
+    let pdf_bytes = writer.save_to_bytes().expect("save to bytes");
+    let doc = Document::load_mem(&pdf_bytes).expect("load PDF from memory");
+
+    let color_space_of = |page_id, name: &[u8]| -> Object {
+        let resources_ref = doc
+            .get_dictionary(page_id)
+            .and_then(|d| d.get(b"Resources"))
+            .and_then(Object::as_reference)
+            .expect("Resources ref");
+        doc.get_dictionary(resources_ref)
+            .and_then(|r| r.get(b"XObject"))
+            .and_then(Object::as_dict)
+            .and_then(|x| x.get(name))
+            .and_then(Object::as_reference)
+            .and_then(|id| doc.get_object(id))
+            .and_then(Object::as_stream)
+            .and_then(|s| s.dict.get(b"ColorSpace"))
+            .expect("ColorSpace")
+            .clone()
+    };
+
+    let mut profile_ids = Vec::new();
+    for page_id in &page_ids[..2] {
+        for name in [&b"BgImg"[..], b"FgImg"] {
+            let color_space = color_space_of(*page_id, name);
+            let array = color_space.as_array().expect("ICCBased array");
+            assert_eq!(array[0].as_name().ok(), Some(&b"ICCBased"[..]));
+            profile_ids.push(array[1].as_reference().expect("profile ref"));
+        }
+    }
+    profile_ids.dedup();
+    assert_eq!(profile_ids.len(), 1, "profile should be embedded once");
+    let profile = doc
+        .get_object(profile_ids[0])
+        .and_then(Object::as_stream)
+        .expect("profile stream");
+    assert_eq!(
+        profile.dict.get(b"N").and_then(Object::as_i64).ok(),
+        Some(3)
+    );
+
+    // グレースケールのページはDeviceGrayのまま
+    assert_eq!(
+        color_space_of(page_ids[2], b"BgImg").as_name().ok(),
+        Some(&b"DeviceGray"[..])
+    );
+}
+
 // ============================================================
 // 1b. write_text_masked_page テスト
 // ============================================================
@@ -176,6 +448,7 @@ fn test_write_text_masked_page_basic() {
             },
             pixel_width: 128,
             pixel_height: 100,
+            placement: None,
         }],
         modified_images: HashMap::new(),
         page_index: 0,
@@ -332,6 +605,7 @@ fn test_write_text_masked_page_jbig2_properties() {
             },
             pixel_width: 128,
             pixel_height: 100,
+            placement: None,
         }],
         modified_images: HashMap::new(),
         page_index: 0,
@@ -503,6 +777,10 @@ fn test_write_text_masked_page_with_modified_images() {
             filter: "DCTDecode".to_string(),
             color_space: "DeviceGray".to_string(),
             bits_per_component: 8,
+            width: 25,
+            height: 25,
+            redacted_regions: Vec::new(),
+            smask: None,
         },
     );
 
@@ -562,6 +840,13 @@ fn test_write_text_masked_page_with_modified_images() {
         .and_then(lopdf::Object::as_name)
         .expect("ColorSpace");
     assert_eq!(cs, b"DeviceGray", "ColorSpace should be updated");
+
+    // ダウンサンプリング後の寸法が反映されていること
+    assert_eq!(im1_stream.dict.get(b"Width").unwrap().as_i64().unwrap(), 25);
+    assert_eq!(
+        im1_stream.dict.get(b"Height").unwrap().as_i64().unwrap(),
+        25
+    );
 }
 
 // ============================================================
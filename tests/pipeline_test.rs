@@ -1,15 +1,26 @@
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 use image::{DynamicImage, RgbaImage};
 use lopdf::dictionary;
 use pdf_masking::cache::hash::CacheSettings;
 use pdf_masking::cache::store::CacheStore;
-use pdf_masking::config::job::ColorMode;
+use pdf_masking::config::job::{
+    ColorMode, JpegSizeMismatch, OutputColorSpace, ResampleFilter, WidthSource,
+};
+use pdf_masking::config::settings::{
+    DEFAULT_MAX_IMAGE_PIXELS, ImageClassDpi, Jbig2Options, JpegEscalation, LineRemoval,
+};
+use pdf_masking::error::Result;
+use pdf_masking::ffi::leptonica::Pix;
 use pdf_masking::mrc::PageOutput;
 use pdf_masking::mrc::compositor::MrcConfig;
+use pdf_masking::mrc::segmenter::Segmenter;
 use pdf_masking::pipeline::job_runner::JobConfig;
 use pdf_masking::pipeline::orchestrator::run_all_jobs;
-use pdf_masking::pipeline::page_processor::{process_page, process_page_outlines};
+use pdf_masking::pipeline::page_processor::{
+    ProcessPageParams, process_page, process_page_outlines, should_retry_at_higher_dpi,
+};
 
 #[test]
 fn test_process_page_cache_miss() {
@@ -18,6 +29,10 @@ fn test_process_page_cache_miss() {
     let mrc_config = MrcConfig {
         bg_quality: 50,
         fg_quality: 30,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        output_colorspace: OutputColorSpace::Rgb,
+        line_removal: LineRemoval::default(),
     };
     let cache_settings = CacheSettings {
         dpi: 300,
@@ -25,6 +40,22 @@ fn test_process_page_cache_miss() {
         bg_quality: 50,
         fg_quality: 30,
         color_mode: ColorMode::Rgb,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
     };
 
     let result = process_page(
@@ -65,6 +96,10 @@ fn test_process_page_cache_hit() {
     let mrc_config = MrcConfig {
         bg_quality: 50,
         fg_quality: 30,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        output_colorspace: OutputColorSpace::Rgb,
+        line_removal: LineRemoval::default(),
     };
     let cache_settings = CacheSettings {
         dpi: 300,
@@ -72,6 +107,22 @@ fn test_process_page_cache_hit() {
         bg_quality: 50,
         fg_quality: 30,
         color_mode: ColorMode::Rgb,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
     };
 
     // First call: cache miss, should compose and store
@@ -132,6 +183,10 @@ fn test_process_page_text_masked_with_image_streams() {
     let mrc_config = MrcConfig {
         bg_quality: 50,
         fg_quality: 30,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        output_colorspace: OutputColorSpace::Rgb,
+        line_removal: LineRemoval::default(),
     };
     let cache_settings = CacheSettings {
         dpi: 300,
@@ -139,6 +194,22 @@ fn test_process_page_text_masked_with_image_streams() {
         bg_quality: 50,
         fg_quality: 30,
         color_mode: ColorMode::Rgb,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
     };
 
     // 画像XObjectを持つストリームマップ
@@ -197,6 +268,10 @@ fn test_process_page_text_masked_without_image_streams() {
     let mrc_config = MrcConfig {
         bg_quality: 50,
         fg_quality: 30,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        output_colorspace: OutputColorSpace::Rgb,
+        line_removal: LineRemoval::default(),
     };
     let cache_settings = CacheSettings {
         dpi: 300,
@@ -204,6 +279,22 @@ fn test_process_page_text_masked_without_image_streams() {
         bg_quality: 50,
         fg_quality: 30,
         color_mode: ColorMode::Rgb,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
     };
 
     let result = process_page(
@@ -249,12 +340,9 @@ fn test_job_config_creation() {
     let config = JobConfig {
         input_path: PathBuf::from("input.pdf"),
         output_path: PathBuf::from("output.pdf"),
-        default_color_mode: ColorMode::Rgb,
         color_mode_overrides: overrides.clone(),
-        dpi: 300,
-        bg_quality: 50,
-        fg_quality: 30,
         cache_dir: Some(PathBuf::from(".cache")),
+        ..Default::default()
     };
 
     assert_eq!(config.input_path, Path::new("input.pdf"));
@@ -282,6 +370,10 @@ fn test_process_page_text_masked_cache_roundtrip() {
     let mrc_config = MrcConfig {
         bg_quality: 50,
         fg_quality: 30,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        output_colorspace: OutputColorSpace::Rgb,
+        line_removal: LineRemoval::default(),
     };
     let cache_settings = CacheSettings {
         dpi: 300,
@@ -289,6 +381,22 @@ fn test_process_page_text_masked_cache_roundtrip() {
         bg_quality: 50,
         fg_quality: 30,
         color_mode: ColorMode::Rgb,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
     };
 
     let mut image_streams = HashMap::new();
@@ -379,6 +487,22 @@ fn test_process_page_outlines_produces_text_masked() {
         bg_quality: 50,
         fg_quality: 30,
         color_mode: ColorMode::Rgb,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
     };
 
     let result = process_page_outlines(
@@ -439,6 +563,22 @@ fn test_process_page_outlines_accepts_bw_mode() {
         bg_quality: 50,
         fg_quality: 30,
         color_mode: ColorMode::Bw,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
     };
 
     let result = process_page_outlines(
@@ -493,6 +633,22 @@ fn test_process_page_outlines_error_on_missing_font() {
         bg_quality: 50,
         fg_quality: 30,
         color_mode: ColorMode::Rgb,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
     };
 
     let result = process_page_outlines(
@@ -529,6 +685,22 @@ fn test_process_page_outlines_cache_roundtrip() {
         bg_quality: 50,
         fg_quality: 30,
         color_mode: ColorMode::Rgb,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
     };
 
     // 1回目: cache miss
@@ -574,6 +746,10 @@ fn test_process_page_succeeds_with_any_content_stream() {
     let mrc_config = MrcConfig {
         bg_quality: 50,
         fg_quality: 30,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        output_colorspace: OutputColorSpace::Rgb,
+        line_removal: LineRemoval::default(),
     };
     let cache_settings = CacheSettings {
         dpi: 300,
@@ -581,6 +757,22 @@ fn test_process_page_succeeds_with_any_content_stream() {
         bg_quality: 50,
         fg_quality: 30,
         color_mode: ColorMode::Rgb,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
     };
 
     let result = process_page(
@@ -612,6 +804,461 @@ fn test_process_page_succeeds_with_any_content_stream() {
     );
 }
 
+/// 前処理フックで反転したビットマップは異なるレイヤーを生成する。
+/// タグ無しのフックはキャッシュを使わない。
+#[test]
+fn test_process_page_preprocess_hook_changes_layers() {
+    let tmp_dir = tempfile::tempdir().expect("create temp dir");
+    let cache_store = CacheStore::new(tmp_dir.path());
+
+    let mut rgba = RgbaImage::from_pixel(200, 200, image::Rgba([255, 255, 255, 255]));
+    for y in 80..120 {
+        for x in 20..180 {
+            rgba.put_pixel(x, y, image::Rgba([0, 0, 0, 255]));
+        }
+    }
+    let img = DynamicImage::ImageRgba8(rgba);
+    let mrc_config = MrcConfig {
+        bg_quality: 50,
+        fg_quality: 30,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        output_colorspace: OutputColorSpace::Rgb,
+        line_removal: LineRemoval::default(),
+    };
+    let cache_settings = CacheSettings {
+        dpi: 300,
+        fg_dpi: 100,
+        bg_quality: 50,
+        fg_quality: 30,
+        color_mode: ColorMode::Rgb,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
+    };
+    let invert = |image: &mut RgbaImage| image::imageops::invert(image);
+
+    let mut params = ProcessPageParams {
+        page_index: 0,
+        bitmap: &img,
+        content_stream: b"",
+        mrc_config: &mrc_config,
+        cache_settings: &cache_settings,
+        cache_store: Some(&cache_store),
+        pdf_path: Path::new("test.pdf"),
+        image_streams: None,
+        matte_smasks: None,
+        fill_resources: None,
+        fonts: None,
+        page_width_pts: 200.0,
+        page_height_pts: 200.0,
+        page_origin_pts: (0.0, 0.0),
+        replace_content: false,
+        preprocess: None,
+        preprocess_tag: None,
+        segmenter: None,
+    };
+    let plain = params.process().expect("process without hook");
+
+    params.preprocess = Some(&invert);
+    let inverted = params.process().expect("process with hook");
+    assert_ne!(
+        format!("{:?}", plain.output),
+        format!("{:?}", inverted.output),
+        "inverted bitmap should produce different layers"
+    );
+
+    params.preprocess_tag = Some("invert");
+    let tagged = params.process().expect("process with tagged hook");
+    assert_ne!(tagged.cache_key, plain.cache_key);
+    assert_eq!(
+        format!("{:?}", tagged.output),
+        format!("{:?}", inverted.output)
+    );
+}
+
+/// 固定の矩形をテキストとして返す分離器
+struct FixedMaskSegmenter {
+    calls: std::sync::atomic::AtomicUsize,
+}
+
+impl Segmenter for FixedMaskSegmenter {
+    fn segment_text_mask(&self, _rgba_data: &[u8], width: u32, height: u32) -> Result<Pix> {
+        self.calls
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let mut mask = Pix::create(width, height, 1)?;
+        for y in 30..50 {
+            for x in 20..60 {
+                mask.set_pixel(x, y, 1)?;
+            }
+        }
+        Ok(mask)
+    }
+}
+
+/// 独自の分離器を渡すと、白紙のビットマップでもその分離器のマスクがテキスト領域になる。
+/// タグの無い分離器ではキャッシュを使わない。
+#[test]
+fn test_process_page_uses_custom_segmenter() {
+    let tmp_dir = tempfile::tempdir().expect("create temp dir");
+    let cache_store = CacheStore::new(tmp_dir.path());
+    let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+        200,
+        200,
+        image::Rgba([255, 255, 255, 255]),
+    ));
+    let mrc_config = MrcConfig {
+        bg_quality: 50,
+        fg_quality: 30,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        output_colorspace: OutputColorSpace::Rgb,
+        line_removal: LineRemoval::default(),
+    };
+    let cache_settings = CacheSettings {
+        dpi: 72,
+        fg_dpi: 72,
+        bg_quality: 50,
+        fg_quality: 30,
+        color_mode: ColorMode::Rgb,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
+    };
+    let custom = FixedMaskSegmenter {
+        calls: std::sync::atomic::AtomicUsize::new(0),
+    };
+
+    let mut params = ProcessPageParams {
+        page_index: 0,
+        bitmap: &img,
+        content_stream: b"",
+        mrc_config: &mrc_config,
+        cache_settings: &cache_settings,
+        cache_store: Some(&cache_store),
+        pdf_path: Path::new("test.pdf"),
+        image_streams: None,
+        matte_smasks: None,
+        fill_resources: None,
+        fonts: None,
+        page_width_pts: 200.0,
+        page_height_pts: 200.0,
+        page_origin_pts: (0.0, 0.0),
+        preprocess: None,
+        preprocess_tag: None,
+        segmenter: None,
+        replace_content: false,
+    };
+    let PageOutput::TextMasked(plain) = params.process().expect("default segmenter").output else {
+        panic!("expected text-masked output");
+    };
+    assert!(plain.text_regions.is_empty(), "white page has no text");
+
+    params.segmenter = Some(&custom);
+    let PageOutput::TextMasked(data) = params.process().expect("custom segmenter").output else {
+        panic!("expected text-masked output");
+    };
+    assert_eq!(custom.calls.load(std::sync::atomic::Ordering::Relaxed), 1);
+    assert_eq!(data.text_regions.len(), 1);
+    let region = &data.text_regions[0];
+    assert_eq!((region.pixel_width, region.pixel_height), (40, 20));
+    // 1px = 1pt。ビットマップ上端からy=30..50はページ座標150..170
+    assert_eq!(region.bbox_points.x_min, 20.0);
+    assert_eq!(region.bbox_points.y_min, 150.0);
+}
+
+/// タグ付きの[`FixedMaskSegmenter`]。キャッシュを使う。
+struct TaggedMaskSegmenter {
+    inner: FixedMaskSegmenter,
+}
+
+impl Segmenter for TaggedMaskSegmenter {
+    fn segment_text_mask(&self, rgba_data: &[u8], width: u32, height: u32) -> Result<Pix> {
+        self.inner.segment_text_mask(rgba_data, width, height)
+    }
+
+    fn cache_tag(&self) -> Option<&str> {
+        Some("fixed-mask")
+    }
+}
+
+/// カラーモードをRGBからグレースケールに変えて再処理すると、ページ全体の
+/// キャッシュはミスするが、マスクはキャッシュから再利用され分離器は呼ばれない。
+#[test]
+fn test_color_mode_change_reuses_cached_mask() {
+    let tmp_dir = tempfile::tempdir().expect("create temp dir");
+    let cache_store = CacheStore::new(tmp_dir.path());
+    let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+        200,
+        200,
+        image::Rgba([255, 255, 255, 255]),
+    ));
+    let mrc_config = MrcConfig {
+        bg_quality: 50,
+        fg_quality: 30,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        output_colorspace: OutputColorSpace::Rgb,
+        line_removal: LineRemoval::default(),
+    };
+    let mut cache_settings = CacheSettings {
+        dpi: 72,
+        fg_dpi: 72,
+        bg_quality: 50,
+        fg_quality: 30,
+        color_mode: ColorMode::Rgb,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
+    };
+    let segmenter = TaggedMaskSegmenter {
+        inner: FixedMaskSegmenter {
+            calls: std::sync::atomic::AtomicUsize::new(0),
+        },
+    };
+
+    let process = |settings: &CacheSettings| {
+        ProcessPageParams {
+            page_index: 0,
+            bitmap: &img,
+            content_stream: b"",
+            mrc_config: &mrc_config,
+            cache_settings: settings,
+            cache_store: Some(&cache_store),
+            pdf_path: Path::new("test.pdf"),
+            image_streams: None,
+            matte_smasks: None,
+            fill_resources: None,
+            fonts: None,
+            page_width_pts: 200.0,
+            page_height_pts: 200.0,
+            page_origin_pts: (0.0, 0.0),
+            preprocess: None,
+            preprocess_tag: None,
+            segmenter: Some(&segmenter),
+            replace_content: true,
+        }
+        .process()
+        .expect("process page")
+    };
+
+    let rgb = process(&cache_settings);
+    cache_settings.color_mode = ColorMode::Grayscale;
+    let gray = process(&cache_settings);
+
+    let (PageOutput::Mrc(rgb_layers), PageOutput::Mrc(gray_layers)) = (&rgb.output, &gray.output)
+    else {
+        panic!("expected full-page MRC output");
+    };
+    assert_ne!(rgb.cache_key, gray.cache_key);
+    assert_eq!(gray_layers.color_mode, ColorMode::Grayscale);
+    assert_eq!(gray_layers.mask_jbig2, rgb_layers.mask_jbig2);
+    assert_eq!(
+        segmenter
+            .inner
+            .calls
+            .load(std::sync::atomic::Ordering::Relaxed),
+        1,
+        "the grayscale run should reuse the cached mask"
+    );
+}
+
+/// 全面MRCのページは背景JPEGのバイト数をレイヤーサイズとして報告し、
+/// ビットマップの幅から実効DPIを求める。
+#[test]
+fn test_process_page_reports_layer_sizes_and_effective_dpi() {
+    let mut rgba = RgbaImage::from_pixel(200, 200, image::Rgba([255, 255, 255, 255]));
+    for y in 80..120 {
+        for x in 20..180 {
+            rgba.put_pixel(x, y, image::Rgba([0, 0, 0, 255]));
+        }
+    }
+    let img = DynamicImage::ImageRgba8(rgba);
+    let mrc_config = MrcConfig {
+        bg_quality: 50,
+        fg_quality: 30,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        output_colorspace: OutputColorSpace::Rgb,
+        line_removal: LineRemoval::default(),
+    };
+    let cache_settings = CacheSettings {
+        dpi: 144,
+        fg_dpi: 100,
+        bg_quality: 50,
+        fg_quality: 30,
+        color_mode: ColorMode::Rgb,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
+    };
+
+    let params = ProcessPageParams {
+        page_index: 0,
+        bitmap: &img,
+        content_stream: b"",
+        mrc_config: &mrc_config,
+        cache_settings: &cache_settings,
+        cache_store: None,
+        pdf_path: Path::new("test.pdf"),
+        image_streams: None,
+        matte_smasks: None,
+        fill_resources: None,
+        fonts: None,
+        page_width_pts: 100.0,
+        page_height_pts: 100.0,
+        page_origin_pts: (0.0, 0.0),
+        preprocess: None,
+        preprocess_tag: None,
+        segmenter: None,
+        replace_content: true,
+    };
+    let page = params.process().expect("process page");
+
+    let PageOutput::Mrc(layers) = &page.output else {
+        panic!("expected full-page MRC output");
+    };
+    assert_eq!(page.layer_sizes.bg, layers.background_jpeg.len());
+    assert_eq!(page.layer_sizes.fg, layers.foreground_jpeg.len());
+    assert_eq!(page.layer_sizes.mask, layers.mask_jbig2.len());
+    // 100pt幅に200px → 144dpi
+    assert_eq!(page.effective_dpi, Some(144));
+}
+
+/// 低DPIのビットマップでテキストが見つからなければ再試行対象となり、
+/// 高DPIで再ラスタライズしたビットマップではテキスト領域が見つかる。
+#[test]
+fn test_retry_at_higher_dpi_finds_text_missed_at_low_dpi() {
+    let content_stream = b"BT /F1 2 Tf 72 72 Td (fine print) Tj ET";
+    let mrc_config = MrcConfig {
+        bg_quality: 50,
+        fg_quality: 30,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        output_colorspace: OutputColorSpace::Rgb,
+        line_removal: LineRemoval::default(),
+    };
+    let cache_settings = CacheSettings {
+        dpi: 300,
+        fg_dpi: 100,
+        bg_quality: 50,
+        fg_quality: 30,
+        color_mode: ColorMode::Rgb,
+        jbig2: Jbig2Options::default(),
+        jpeg_escalation: JpegEscalation::default(),
+        image_max_dpi: None,
+        image_class_dpi: ImageClassDpi::default(),
+        normalize_images_to: None,
+        resample_filter: ResampleFilter::Triangle,
+        max_image_pixels: DEFAULT_MAX_IMAGE_PIXELS,
+        jpeg_size_mismatch: JpegSizeMismatch::Warn,
+        output_colorspace: OutputColorSpace::Rgb,
+        text_antialias: true,
+        line_removal: LineRemoval::default(),
+        width_source: WidthSource::Pdf,
+        merge_white_fills: false,
+        font_substitutions: BTreeMap::new(),
+        font_fallback: None,
+        use_system_fonts: true,
+    };
+    let process = |img: &DynamicImage| {
+        process_page(
+            0,
+            img,
+            content_stream,
+            &mrc_config,
+            &cache_settings,
+            None,
+            Path::new("test.pdf"),
+            None,
+            200.0,
+            200.0,
+        )
+        .expect("process page")
+    };
+
+    // 低DPI: 細かい文字がつぶれて白紙になった想定
+    let low = DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+        50,
+        50,
+        image::Rgba([255, 255, 255, 255]),
+    ));
+    let low_page = process(&low);
+    assert!(should_retry_at_higher_dpi(&low_page, content_stream));
+    // テキストも画像も描画しないページは再試行しない
+    assert!(!should_retry_at_higher_dpi(&low_page, b"0 0 m 10 10 l S"));
+
+    // 高DPI: 文字が判読できる解像度で描画された想定
+    let mut rgba = RgbaImage::from_pixel(200, 200, image::Rgba([255, 255, 255, 255]));
+    for y in 80..120 {
+        for x in 20..180 {
+            rgba.put_pixel(x, y, image::Rgba([0, 0, 0, 255]));
+        }
+    }
+    let high_page = process(&DynamicImage::ImageRgba8(rgba));
+    match &high_page.output {
+        PageOutput::TextMasked(data) => assert!(!data.text_regions.is_empty()),
+        PageOutput::Mrc(_) => {}
+        other => panic!(
+            "expected TextMasked or Mrc, got {:?}",
+            std::mem::discriminant(other)
+        ),
+    }
+    assert!(!should_retry_at_higher_dpi(&high_page, content_stream));
+}
+
 #[test]
 fn test_run_all_jobs_empty() {
     let jobs: Vec<JobConfig> = vec![];
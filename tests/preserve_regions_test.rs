@@ -0,0 +1,321 @@
+// preserve_regions（矩形内をベクターのまま残す部分ラスタライズ）のテスト
+
+use lopdf::{Document, Object, Stream, dictionary};
+use pdf_masking::config::job::PreserveRegion;
+#[cfg(feature = "mrc")]
+use pdf_masking::pdf::text_extract::extract_page_text;
+use pdf_masking::pipeline::job_runner::{JobConfig, run_job};
+
+/// 左下の青い正方形（ロゴ）と、右上のテキストを描く1ページPDFを作成する
+fn create_logo_pdf(path: &std::path::Path) {
+    let mut doc = Document::with_version("1.7");
+    let pages_id = doc.new_object_id();
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+        "Encoding" => "WinAnsiEncoding",
+    });
+    let content = b"q 0 0 1 rg 50 50 100 100 re f Q \
+        BT /F1 24 Tf 300 700 Td (Confidential) Tj ET"
+        .to_vec();
+    let contents_id = doc.add_object(Stream::new(dictionary! {}, content));
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "Contents" => contents_id,
+        "Resources" => dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+        },
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.save(path).expect("failed to save test PDF");
+}
+
+fn job_config(
+    input: std::path::PathBuf,
+    output: std::path::PathBuf,
+    preserve_regions: Vec<PreserveRegion>,
+) -> JobConfig {
+    JobConfig {
+        input_path: input,
+        output_path: output,
+        dpi: 150,
+        preserve_regions,
+        ..Default::default()
+    }
+}
+
+#[cfg(feature = "mrc")]
+#[test]
+fn test_preserved_region_keeps_vectors_and_rasterizes_text() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("logo.pdf");
+    let output = dir.path().join("logo_out.pdf");
+    create_logo_pdf(&input);
+
+    let region = PreserveRegion {
+        page: 1,
+        rect: [40.0, 40.0, 160.0, 160.0],
+    };
+    run_job(&job_config(input, output.clone(), vec![region])).expect("job should succeed");
+
+    let doc = Document::load(&output).expect("load output PDF");
+    let page_id = doc.get_pages()[&1];
+    let content =
+        String::from_utf8_lossy(&doc.get_page_content(page_id).expect("content")).into_owned();
+    assert!(
+        content.contains("/BgImg Do"),
+        "page should be rasterized: {content}"
+    );
+    assert!(
+        content.contains("40 40 120 120 re W n"),
+        "preserved content should be clipped to the region: {content}"
+    );
+
+    let resources = doc
+        .get_dictionary(page_id)
+        .and_then(|page| page.get(b"Resources"))
+        .and_then(|r| doc.dereference(r))
+        .and_then(|(_, r)| r.as_dict())
+        .expect("Resources");
+    let form = resources
+        .get(b"XObject")
+        .and_then(|x| doc.dereference(x))
+        .and_then(|(_, x)| x.as_dict())
+        .and_then(|x| x.get(b"Preserved"))
+        .and_then(Object::as_reference)
+        .and_then(|id| doc.get_object(id))
+        .and_then(Object::as_stream)
+        .expect("Preserved form XObject");
+    let form_content = String::from_utf8_lossy(
+        &form
+            .decompressed_content()
+            .unwrap_or_else(|_| form.content.clone()),
+    )
+    .into_owned();
+    assert!(
+        form_content.contains("50 50 100 100 re"),
+        "vector logo should survive: {form_content}"
+    );
+    assert!(
+        !form_content.contains("BT"),
+        "text should not be copied into the preserved form: {form_content}"
+    );
+    let form_resources = form
+        .dict
+        .get(b"Resources")
+        .and_then(|r| doc.dereference(r))
+        .and_then(|(_, r)| r.as_dict())
+        .expect("Preserved form Resources");
+    assert!(
+        form_resources.get(b"Font").is_err(),
+        "fonts should not be copied into the preserved form"
+    );
+
+    let words = extract_page_text(&doc, 1).expect("extract text");
+    assert!(
+        words.iter().all(|w| !w.text.contains("Confidential")),
+        "text outside the region should be rasterized, got {words:?}"
+    );
+}
+
+/// 白色fillで塗りつぶした画像（リダクション済み）を、保持領域内に描く1ページPDFを作成する
+fn create_scrubbed_image_pdf(path: &std::path::Path, pixels: &[u8]) {
+    let mut doc = Document::with_version("1.7");
+    let pages_id = doc.new_object_id();
+    let image_id = doc.add_object(Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => 8,
+            "Height" => 8,
+            "ColorSpace" => "DeviceRGB",
+            "BitsPerComponent" => 8,
+        },
+        pixels.to_vec(),
+    ));
+    let content = b"q 0 0 1 rg 50 50 20 20 re f Q \
+        q 80 0 0 80 60 60 cm /Im1 Do Q \
+        q 1 g 60 60 80 80 re f Q"
+        .to_vec();
+    let contents_id = doc.add_object(Stream::new(dictionary! {}, content));
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "Contents" => contents_id,
+        "Resources" => dictionary! {
+            "XObject" => dictionary! { "Im1" => image_id },
+        },
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.save(path).expect("failed to save test PDF");
+}
+
+#[cfg(feature = "mrc")]
+#[test]
+fn test_preserved_region_does_not_leak_redacted_image() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("scrubbed.pdf");
+    let output = dir.path().join("scrubbed_out.pdf");
+    // 他のデータと紛れない画素列
+    let pixels: Vec<u8> = (0..8 * 8 * 3).map(|i| (i * 37 % 251) as u8).collect();
+    create_scrubbed_image_pdf(&input, &pixels);
+
+    let region = PreserveRegion {
+        page: 1,
+        rect: [40.0, 40.0, 160.0, 160.0],
+    };
+    run_job(&job_config(input, output.clone(), vec![region])).expect("job should succeed");
+
+    let file = std::fs::read(&output).expect("read output PDF");
+    assert!(
+        !file.windows(pixels.len()).any(|w| w == pixels.as_slice()),
+        "redacted image bytes should not reach the output file"
+    );
+    let doc = Document::load(&output).expect("load output PDF");
+    for object in doc.objects.values() {
+        if let Object::Stream(stream) = object {
+            let data = stream
+                .decompressed_content()
+                .unwrap_or_else(|_| stream.content.clone());
+            assert!(
+                !data.windows(pixels.len()).any(|w| w == pixels.as_slice()),
+                "redacted image data should not reach any output stream"
+            );
+        }
+    }
+}
+
+/// 保持領域内を、テキストを描くタイリングパターンで塗る1ページPDFを作成する
+fn create_text_pattern_pdf(path: &std::path::Path) {
+    let mut doc = Document::with_version("1.7");
+    let pages_id = doc.new_object_id();
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+        "Encoding" => "WinAnsiEncoding",
+    });
+    let pattern_id = doc.add_object(Stream::new(
+        dictionary! {
+            "Type" => "Pattern",
+            "PatternType" => 1,
+            "PaintType" => 1,
+            "TilingType" => 1,
+            "BBox" => vec![0.into(), 0.into(), 100.into(), 20.into()],
+            "XStep" => 100,
+            "YStep" => 20,
+            "Resources" => dictionary! {
+                "Font" => dictionary! { "F1" => font_id },
+            },
+        },
+        b"BT /F1 10 Tf 0 5 Td (PatternSecret) Tj ET".to_vec(),
+    ));
+    let content = b"q 0 0 1 rg 50 50 20 20 re f Q \
+        q /Pattern cs /P1 scn 80 80 60 60 re f Q"
+        .to_vec();
+    let contents_id = doc.add_object(Stream::new(dictionary! {}, content));
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "Contents" => contents_id,
+        "Resources" => dictionary! {
+            "Pattern" => dictionary! { "P1" => pattern_id },
+        },
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.save(path).expect("failed to save test PDF");
+}
+
+#[cfg(feature = "mrc")]
+#[test]
+fn test_preserved_region_does_not_copy_text_pattern() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("pattern.pdf");
+    let output = dir.path().join("pattern_out.pdf");
+    create_text_pattern_pdf(&input);
+
+    let region = PreserveRegion {
+        page: 1,
+        rect: [40.0, 40.0, 160.0, 160.0],
+    };
+    run_job(&job_config(input, output.clone(), vec![region])).expect("job should succeed");
+
+    let doc = Document::load(&output).expect("load output PDF");
+    for object in doc.objects.values() {
+        if let Object::Stream(stream) = object {
+            let data = stream
+                .decompressed_content()
+                .unwrap_or_else(|_| stream.content.clone());
+            assert!(
+                !data
+                    .windows(b"PatternSecret".len())
+                    .any(|w| w == b"PatternSecret"),
+                "text drawn by the pattern should not reach any output stream"
+            );
+        }
+    }
+    let words = extract_page_text(&doc, 1).expect("extract text");
+    assert!(
+        words.iter().all(|w| !w.text.contains("PatternSecret")),
+        "pattern text should be rasterized, got {words:?}"
+    );
+}
+
+#[test]
+fn test_preserve_region_page_out_of_range_is_rejected() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("logo.pdf");
+    let output = dir.path().join("logo_out.pdf");
+    create_logo_pdf(&input);
+
+    let region = PreserveRegion {
+        page: 2,
+        rect: [40.0, 40.0, 160.0, 160.0],
+    };
+    let err = run_job(&job_config(input, output.clone(), vec![region]))
+        .err()
+        .expect("out-of-range page should fail");
+    assert!(err.to_string().contains("preserve region page 2"), "{err}");
+    assert!(!output.exists());
+}
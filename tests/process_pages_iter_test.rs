@@ -0,0 +1,108 @@
+// process_pages_iter（ページを1枚ずつ遅延処理するAPI）のテスト
+
+use lopdf::{Document, Object, Stream, dictionary};
+use pdf_masking::config::job::ColorMode;
+use pdf_masking::mrc::PageOutput;
+use pdf_masking::pdf::reader::PdfReader;
+use pdf_masking::pipeline::job_runner::{JobConfig, process_pages_iter};
+
+/// 各ページに矩形を1つ描くPDFを作成する（テキスト無し）。
+/// `contents[i]`が i+1 ページ目のコンテンツストリームになる。
+fn create_pdf(path: &std::path::Path, contents: &[Vec<u8>]) {
+    let mut doc = Document::with_version("1.7");
+    let pages_id = doc.new_object_id();
+    let mut kids = Vec::new();
+    for content in contents {
+        let contents_id = doc.add_object(Stream::new(dictionary! {}, content.clone()));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+            "Contents" => contents_id,
+            "Resources" => dictionary! {},
+        });
+        kids.push(page_id.into());
+    }
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Count" => kids.len() as i64,
+            "Kids" => kids,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc.save(path).expect("failed to save test PDF");
+}
+
+fn job_config(input: std::path::PathBuf, max_content_stream_bytes: u64) -> JobConfig {
+    JobConfig {
+        input_path: input.clone(),
+        output_path: input.with_extension("out.pdf"),
+        dpi: 150,
+        max_content_stream_bytes,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_iterator_yields_pages_one_at_a_time_in_order() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("three.pdf");
+    let rect = b"0 0 1 rg 50 50 100 100 re f".to_vec();
+    create_pdf(&input, &[rect.clone(), rect.clone(), rect]);
+
+    let mut config = job_config(input.clone(), 1 << 20);
+    config.color_mode_overrides.insert(2, ColorMode::Skip);
+    let reader = PdfReader::open(&input).expect("open input");
+    let mut pages = process_pages_iter(&reader, &config);
+
+    let first = pages.next().expect("page 1").expect("page 1 succeeds");
+    assert_eq!(first.page_index, 0);
+    assert!(matches!(first.output, PageOutput::TextMasked(_)));
+    let second = pages.next().expect("page 2").expect("page 2 succeeds");
+    assert_eq!(second.page_index, 1);
+    assert!(matches!(second.output, PageOutput::Skip(_)));
+    let third = pages.next().expect("page 3").expect("page 3 succeeds");
+    assert_eq!(third.page_index, 2);
+    assert!(pages.next().is_none());
+}
+
+/// 後続ページのエラーは、先行ページを返した後でそのページの順番に来る。
+#[test]
+fn test_iterator_processes_pages_lazily_and_stops_at_first_error() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("oversized.pdf");
+    let rect = b"0 0 1 rg 50 50 100 100 re f".to_vec();
+    let oversized = rect.repeat(100);
+    create_pdf(&input, &[rect.clone(), oversized, rect]);
+
+    let config = job_config(input.clone(), 1024);
+    let reader = PdfReader::open(&input).expect("open input");
+    let mut pages = process_pages_iter(&reader, &config);
+
+    let first = pages.next().expect("page 1").expect("page 1 succeeds");
+    assert_eq!(first.page_index, 0);
+    assert!(pages.next().expect("page 2").is_err());
+    assert!(pages.next().is_none(), "the iterator ends after an error");
+}
+
+#[test]
+fn test_iterator_yields_setup_error_first() {
+    let dir = tempfile::tempdir().expect("create temp dir");
+    let input = dir.path().join("one.pdf");
+    create_pdf(&input, &[b"0 0 1 rg 50 50 100 100 re f".to_vec()]);
+
+    let mut config = job_config(input.clone(), 1 << 20);
+    config.color_mode_overrides.insert(5, ColorMode::Skip);
+    let reader = PdfReader::open(&input).expect("open input");
+    let mut pages = process_pages_iter(&reader, &config);
+
+    let err = pages.next().expect("setup error").err().expect("error");
+    assert!(err.to_string().contains("override page 5"), "{err}");
+    assert!(pages.next().is_none());
+}
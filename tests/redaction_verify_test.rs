@@ -0,0 +1,241 @@
+// 出力PDFのリダクション検証のテスト
+
+use std::io::Write;
+
+use lopdf::{Document, Object, Stream, dictionary};
+use pdf_masking::error::PdfMaskError;
+use pdf_masking::pdf::content_stream::BBox;
+use pdf_masking::pdf::font::parse_page_fonts;
+use pdf_masking::pipeline::redaction_verify::{RedactionCheck, verify_redaction};
+use tracing::warn;
+
+const ORIGINAL_PIXELS: &[u8] = &[0, 0, 0, 255, 255, 255, 0, 0, 0, 255, 255, 255];
+const REDACTED_PIXELS: &[u8] = &[0, 0, 0, 255, 255, 255, 255, 255, 255, 255, 255, 255];
+
+/// 2x2のDeviceRGB画像XObject（非圧縮）
+fn image_stream(data: &[u8]) -> Stream {
+    Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Image",
+            "Width" => 2,
+            "Height" => 2,
+            "ColorSpace" => "DeviceRGB",
+            "BitsPerComponent" => 8,
+        },
+        data.to_vec(),
+    )
+}
+
+/// 2x2のDeviceRGB画像Im1を(50, 50)-(150, 150)に描き、`text`を(60, 120)に描く1ページのPDFを保存する。
+fn save_output_pdf(image_data: &[u8], text: Option<&str>) -> tempfile::NamedTempFile {
+    let mut content = b"q 100 0 0 100 50 50 cm /Im1 Do Q".to_vec();
+    if let Some(text) = text {
+        content.extend_from_slice(format!(" BT /F1 12 Tf 60 120 Td ({text}) Tj ET").as_bytes());
+    }
+    save_pdf(content, |doc| {
+        let image_id = doc.add_object(image_stream(image_data));
+        dictionary! { "Im1" => image_id }
+    })
+}
+
+/// `content`を描く1ページのPDFを保存する。XObjectリソースは`xobjects`で作る。
+fn save_pdf(
+    content: Vec<u8>,
+    xobjects: impl FnOnce(&mut Document) -> lopdf::Dictionary,
+) -> tempfile::NamedTempFile {
+    let mut doc = Document::with_version("1.4");
+    let xobjects = xobjects(&mut doc);
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+        "Encoding" => "WinAnsiEncoding",
+    });
+    let content_id = doc.add_object(Stream::new(dictionary! {}, content));
+    let pages_id = doc.new_object_id();
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 200.into(), 200.into()],
+        "Contents" => content_id,
+        "Resources" => dictionary! {
+            "XObject" => xobjects,
+            "Font" => dictionary! { "F1" => font_id },
+        },
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![Object::Reference(page_id)],
+            "Count" => 1,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+
+    let file = tempfile::NamedTempFile::new().expect("create temp file");
+    doc.save(file.path()).expect("save PDF");
+    file
+}
+
+/// 2x2の画像を(50, 50)-(150, 150)に描くForm XObject Fm1を置いた1ページのPDFを保存する。
+fn save_form_pdf(image_data: &[u8]) -> tempfile::NamedTempFile {
+    save_pdf(b"q /Fm1 Do Q".to_vec(), |doc| {
+        let image_id = doc.add_object(image_stream(image_data));
+        let form_id = doc.add_object(Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Form",
+                "BBox" => vec![0.into(), 0.into(), 200.into(), 200.into()],
+                "Resources" => dictionary! {
+                    "XObject" => dictionary! { "Im1" => image_id },
+                },
+            },
+            b"q 100 0 0 100 50 50 cm /Im1 Do Q".to_vec(),
+        ));
+        dictionary! { "Fm1" => form_id }
+    })
+}
+
+fn check(regions: Vec<BBox>) -> RedactionCheck {
+    RedactionCheck {
+        output_page: 1,
+        regions,
+        original_images: vec![image_stream(ORIGINAL_PIXELS)],
+    }
+}
+
+/// 画像の左下の黒い画素を含む領域
+fn dark_region() -> BBox {
+    BBox {
+        x_min: 50.0,
+        y_min: 50.0,
+        x_max: 100.0,
+        y_max: 100.0,
+    }
+}
+
+fn image_region() -> BBox {
+    BBox {
+        x_min: 100.0,
+        y_min: 50.0,
+        x_max: 150.0,
+        y_max: 100.0,
+    }
+}
+
+#[test]
+fn test_verify_passes_when_image_was_redacted() {
+    let output = save_output_pdf(REDACTED_PIXELS, None);
+    verify_redaction(output.path(), &[check(vec![image_region()])])
+        .expect("redacted image should pass");
+}
+
+#[test]
+fn test_verify_detects_unredacted_image() {
+    let output = save_output_pdf(ORIGINAL_PIXELS, None);
+    let err = verify_redaction(output.path(), &[check(vec![image_region()])])
+        .expect_err("original image data should be detected");
+    assert!(matches!(err, PdfMaskError::RedactionLeak(_)), "got {err:?}");
+    assert!(err.to_string().contains("Im1"), "got {err}");
+}
+
+#[test]
+fn test_verify_ignores_original_image_outside_regions() {
+    let output = save_output_pdf(ORIGINAL_PIXELS, None);
+    let outside = BBox {
+        x_min: 160.0,
+        y_min: 160.0,
+        x_max: 190.0,
+        y_max: 190.0,
+    };
+    verify_redaction(output.path(), &[check(vec![outside])]).expect("no overlap, no leak");
+}
+
+#[test]
+fn test_verify_detects_text_in_region() {
+    let output = save_output_pdf(REDACTED_PIXELS, Some("Secret"));
+    let doc = Document::load(output.path()).expect("load PDF");
+    if !parse_page_fonts(&doc, 1)
+        .expect("parse fonts")
+        .contains_key("F1")
+    {
+        warn!("skipping text leak test: system Helvetica (F1) could not be resolved");
+        return;
+    }
+
+    let text_region = BBox {
+        x_min: 55.0,
+        y_min: 110.0,
+        x_max: 140.0,
+        y_max: 140.0,
+    };
+    let err = verify_redaction(output.path(), &[check(vec![text_region])])
+        .expect_err("text in the region should be detected");
+    assert!(err.to_string().contains("Secret"), "got {err}");
+}
+
+#[test]
+fn test_verify_detects_unredacted_image_inside_form() {
+    let output = save_form_pdf(ORIGINAL_PIXELS);
+    let err = verify_redaction(output.path(), &[check(vec![dark_region()])])
+        .expect_err("original image drawn by a form should be detected");
+    assert!(err.to_string().contains("Fm1/Im1"), "got {err}");
+
+    let output = save_form_pdf(REDACTED_PIXELS);
+    verify_redaction(output.path(), &[check(vec![dark_region()])])
+        .expect("redacted image drawn by a form should pass");
+}
+
+#[test]
+fn test_verify_detects_reencoded_original_image() {
+    // 元の画素をFlateDecodeで圧縮し直しただけの画像
+    let output = save_pdf(b"q 100 0 0 100 50 50 cm /Im1 Do Q".to_vec(), |doc| {
+        let mut encoder =
+            flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(ORIGINAL_PIXELS).expect("compress image");
+        let mut image = image_stream(&encoder.finish().expect("compress image"));
+        image.dict.set("Filter", "FlateDecode");
+        let image_id = doc.add_object(image);
+        dictionary! { "Im1" => image_id }
+    });
+    let err = verify_redaction(output.path(), &[check(vec![dark_region()])])
+        .expect_err("re-encoded original pixels should be detected");
+    assert!(err.to_string().contains("Im1"), "got {err}");
+
+    let output = save_output_pdf(REDACTED_PIXELS, None);
+    verify_redaction(output.path(), &[check(vec![dark_region()])])
+        .expect("whitened region should pass");
+}
+
+#[test]
+fn test_verify_fails_when_form_cannot_be_inspected() {
+    // 自分自身をリソースに持つフォームはネストの上限を超える
+    let output = save_pdf(b"q /Fm1 Do Q".to_vec(), |doc| {
+        let form_id = doc.new_object_id();
+        doc.objects.insert(
+            form_id,
+            Object::Stream(Stream::new(
+                dictionary! {
+                    "Type" => "XObject",
+                    "Subtype" => "Form",
+                    "BBox" => vec![0.into(), 0.into(), 200.into(), 200.into()],
+                    "Resources" => dictionary! {
+                        "XObject" => dictionary! { "Fm1" => form_id },
+                    },
+                },
+                b"q /Fm1 Do Q".to_vec(),
+            )),
+        );
+        dictionary! { "Fm1" => form_id }
+    });
+    let err = verify_redaction(output.path(), &[check(vec![dark_region()])])
+        .expect_err("an uninspectable form should not verify");
+    assert!(matches!(err, PdfMaskError::RedactionLeak(_)), "got {err:?}");
+    assert!(err.to_string().contains("cannot be verified"), "got {err}");
+}
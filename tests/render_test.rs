@@ -161,3 +161,146 @@ fn test_render_invalid_page_index() {
         "render_page should fail for an out-of-range page index"
     );
 }
+
+// ---- Test 7: Text anti-aliasing toggle ----
+
+/// Create a 1-page PDF (100x100 pt) drawing a single Helvetica glyph.
+fn create_glyph_pdf(dir: &tempfile::TempDir) -> PathBuf {
+    use lopdf::{Document, Object, Stream, dictionary};
+
+    let mut doc = Document::with_version("1.4");
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+    });
+    let content_id = doc.add_object(Stream::new(
+        dictionary! {},
+        b"BT /F1 60 Tf 20 25 Td (g) Tj ET".to_vec(),
+    ));
+    let pages_id = doc.new_object_id();
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 100.into(), 100.into()],
+        "Contents" => content_id,
+        "Resources" => dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+        },
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+
+    let path = dir.path().join("glyph.pdf");
+    doc.save(&path).expect("failed to save glyph PDF");
+    path
+}
+
+/// Number of connected components in the text mask produced from a rendering.
+fn mask_component_count(image: &image::DynamicImage) -> usize {
+    let rgba = image.to_rgba8();
+    let mask =
+        pdf_masking::mrc::segmenter::segment_text_mask(rgba.as_raw(), rgba.width(), rgba.height())
+            .expect("segment text mask");
+    mask.connected_component_bboxes(8)
+        .expect("connected components")
+        .len()
+}
+
+/// With text anti-aliasing off the glyph is drawn with hard edges (no gray
+/// fringe) and binarizes into no more mask components than the anti-aliased
+/// rendering of the same glyph.
+#[test]
+fn test_render_text_antialias_toggle() {
+    use pdf_masking::render::pdfium::{RenderOptions, render_page_with_options};
+
+    let dir = tempfile::tempdir().expect("failed to create temp dir");
+    let pdf_path = create_glyph_pdf(&dir);
+
+    let aa_on = render_page_with_options(
+        &pdf_path,
+        0,
+        144,
+        &RenderOptions {
+            text_antialias: true,
+            ..Default::default()
+        },
+    )
+    .expect("render with anti-aliasing");
+    let aa_off = render_page_with_options(
+        &pdf_path,
+        0,
+        144,
+        &RenderOptions {
+            text_antialias: false,
+            ..Default::default()
+        },
+    )
+    .expect("render without anti-aliasing");
+
+    let gray_pixels = |image: &image::DynamicImage| {
+        image
+            .to_luma8()
+            .pixels()
+            .filter(|p| p.0[0] != 0 && p.0[0] != 255)
+            .count()
+    };
+    assert!(
+        gray_pixels(&aa_on) > 0,
+        "anti-aliased glyph should have gray edges"
+    );
+    assert_eq!(
+        gray_pixels(&aa_off),
+        0,
+        "hard-edged glyph should be pure black/white"
+    );
+
+    let on_count = mask_component_count(&aa_on);
+    let off_count = mask_component_count(&aa_off);
+    assert!(off_count >= 1, "glyph should produce a mask component");
+    assert!(
+        off_count <= on_count,
+        "hard edges should not add mask components (on: {on_count}, off: {off_count})"
+    );
+}
+
+// ---- /UserUnit ----
+
+/// A rotated page with /UserUnit renders to exactly `PdfReader::page_pixel_size`.
+#[test]
+fn test_render_page_size_matches_page_pixel_size_with_user_unit() {
+    use pdf_masking::pdf::reader::PdfReader;
+    use pdf_masking::render::pdfium::{RenderOptions, render_page_with_options};
+
+    let dir = tempfile::tempdir().expect("failed to create temp dir");
+    let pdf_path = create_test_pdf(&dir);
+    let mut doc = lopdf::Document::load(&pdf_path).expect("load test PDF");
+    let page_id = doc.get_pages()[&1];
+    let page = doc.get_dictionary_mut(page_id).expect("page dictionary");
+    page.set("Rotate", lopdf::Object::Integer(90));
+    page.set("UserUnit", lopdf::Object::Integer(2));
+    doc.save(&pdf_path).expect("save test PDF");
+
+    let reader = PdfReader::open(&pdf_path).expect("open test PDF");
+    let expected = reader.page_pixel_size(1, 150).expect("page pixel size");
+    let options = RenderOptions {
+        user_unit: reader.page_user_unit(1).expect("page user unit"),
+        ..Default::default()
+    };
+    let image = render_page_with_options(&pdf_path, 0, 150, &options).expect("render page");
+
+    // 792 x 612 units after rotation, 2/72 inch each, at 150 DPI
+    assert_eq!(expected, (3300, 2550));
+    assert_eq!((image.width(), image.height()), expected);
+}
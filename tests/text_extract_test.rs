@@ -0,0 +1,102 @@
+// ページテキスト抽出のテスト
+
+use lopdf::{Document, Object, Stream, dictionary};
+use pdf_masking::pdf::font::parse_page_fonts;
+use pdf_masking::pdf::text_extract::extract_page_text;
+use tracing::warn;
+
+fn create_helvetica_doc(content: &[u8]) -> Document {
+    let mut doc = Document::with_version("1.4");
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+        "Encoding" => "WinAnsiEncoding",
+    });
+    let content_id = doc.add_object(Stream::new(dictionary! {}, content.to_vec()));
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "Contents" => content_id,
+        "Resources" => dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+        },
+    });
+    let pages_id = doc.add_object(dictionary! {
+        "Type" => "Pages",
+        "Kids" => vec![Object::Reference(page_id)],
+        "Count" => 1,
+    });
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc
+}
+
+/// システムフォントが無い環境ではF1が解決できないため、テストをスキップするか判定する。
+fn font_unresolved(doc: &Document) -> bool {
+    let fonts = parse_page_fonts(doc, 1).expect("parse fonts");
+    if fonts.contains_key("F1") {
+        return false;
+    }
+    warn!("skipping text extraction test: system Helvetica (F1) could not be resolved");
+    true
+}
+
+#[test]
+fn test_extract_hello_word_with_bbox() {
+    let doc = create_helvetica_doc(b"BT /F1 24 Tf 100 700 Td (Hello) Tj ET");
+    if font_unresolved(&doc) {
+        return;
+    }
+
+    let words = extract_page_text(&doc, 1).expect("extract text");
+    assert_eq!(words.len(), 1, "got {words:?}");
+    assert_eq!(words[0].text, "Hello");
+
+    let bbox = &words[0].bbox;
+    assert!((bbox.x_min - 100.0).abs() < 1e-6, "x_min: {}", bbox.x_min);
+    // 5文字・24ptなら幅は数十pt程度
+    let width = bbox.x_max - bbox.x_min;
+    assert!(width > 24.0 && width < 24.0 * 5.0, "width: {width}");
+    // ベースライン(700)をまたぎ、高さはフォントサイズ程度
+    assert!(bbox.y_min < 700.0 && bbox.y_max > 700.0, "bbox: {bbox:?}");
+    let height = bbox.y_max - bbox.y_min;
+    assert!(height > 12.0 && height < 48.0, "height: {height}");
+}
+
+#[test]
+fn test_extract_splits_words_on_spaces_and_tj_gaps() {
+    let doc =
+        create_helvetica_doc(b"BT /F1 12 Tf 72 700 Td [(Hello) -1000 (big)] TJ (  world) Tj ET");
+    if font_unresolved(&doc) {
+        return;
+    }
+
+    let words = extract_page_text(&doc, 1).expect("extract text");
+    let texts: Vec<&str> = words.iter().map(|w| w.text.as_str()).collect();
+    assert_eq!(texts, ["Hello", "big", "world"]);
+    assert!(words[0].bbox.x_max < words[1].bbox.x_min);
+    assert!(words[1].bbox.x_max < words[2].bbox.x_min);
+}
+
+#[test]
+fn test_extract_joins_adjacent_show_operations() {
+    // 1文字ずつ別のTjで描画されても1単語として扱う
+    let doc = create_helvetica_doc(b"BT /F1 12 Tf 72 700 Td (He) Tj (llo) Tj ET");
+    if font_unresolved(&doc) {
+        return;
+    }
+
+    let words = extract_page_text(&doc, 1).expect("extract text");
+    assert_eq!(words.len(), 1, "got {words:?}");
+    assert_eq!(words[0].text, "Hello");
+}
+
+#[test]
+fn test_extract_page_not_found() {
+    let doc = create_helvetica_doc(b"");
+    assert!(extract_page_text(&doc, 2).is_err());
+}
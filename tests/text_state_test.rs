@@ -1,6 +1,9 @@
 // テキスト状態パーサのテスト (RED phase)
 
+use lopdf::{Document, Object, Stream, dictionary};
+use pdf_masking::pdf::font::parse_page_fonts;
 use pdf_masking::pdf::text_state::{FillColor, parse_content_operations};
+use tracing::warn;
 
 // ============================================================
 // 1. 基本的なテキストブロック解析
@@ -307,3 +310,221 @@ fn test_bt_without_tf_uses_default() {
     // フォント未設定の場合、空文字列
     assert!(result.text_commands[0].font_name.is_empty());
 }
+
+#[test]
+fn test_quote_operator_sets_spacing_and_advances_line() {
+    // aw ac string " = aw Tw ac Tc T* string Tj
+    let content = b"BT /F1 12 Tf 14 TL 0 700 Td 3 1.5 (AB) \" ET";
+    let result = parse_content_operations(content, None).expect("should parse");
+
+    assert_eq!(result.text_commands.len(), 1);
+    let cmd = &result.text_commands[0];
+    assert!((cmd.word_spacing - 3.0).abs() < 1e-6);
+    assert!((cmd.char_spacing - 1.5).abs() < 1e-6);
+    assert!((cmd.text_matrix.f - 686.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_quote_operator_malformed_operands_do_not_panic() {
+    // 非数値のaw/acは間隔に反映しない。文字列は表示する
+    let content = b"BT /F1 12 Tf 2 Tw 1 Tc /Bad (x) (AB) \" ET";
+    let result = parse_content_operations(content, None).expect("should parse");
+    assert_eq!(result.text_commands.len(), 1);
+    assert!((result.text_commands[0].word_spacing - 2.0).abs() < 1e-6);
+    assert!((result.text_commands[0].char_spacing - 1.0).abs() < 1e-6);
+
+    // オペランド不足・過多、空文字列、文字列でないオペランド
+    for content in [
+        &b"BT /F1 12 Tf \" ET"[..],
+        b"BT /F1 12 Tf (AB) \" ET",
+        b"BT /F1 12 Tf 1 2 3 (AB) \" ET",
+        b"BT /F1 12 Tf 1 2 () \" ET",
+        b"BT /F1 12 Tf 1 2 3 \" ET",
+    ] {
+        let result = parse_content_operations(content, None).expect("should parse");
+        assert!(
+            result.text_commands.is_empty(),
+            "nothing should be shown for {:?}",
+            String::from_utf8_lossy(content)
+        );
+    }
+}
+
+// ============================================================
+// 7. テキストのページ空間バウンディングボックス
+// ============================================================
+
+/// ヘルパー: Type1 Helvetica（非埋め込み）を F1 として持つ1ページPDFを作成する
+fn create_helvetica_doc(content: &[u8]) -> Document {
+    let mut doc = Document::with_version("1.4");
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Helvetica",
+        "Encoding" => "WinAnsiEncoding",
+    });
+    let content_id = doc.add_object(Stream::new(dictionary! {}, content.to_vec()));
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "Contents" => content_id,
+        "Resources" => dictionary! {
+            "Font" => dictionary! { "F1" => font_id },
+        },
+    });
+    let pages_id = doc.add_object(dictionary! {
+        "Type" => "Pages",
+        "Kids" => vec![Object::Reference(page_id)],
+        "Count" => 1,
+    });
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc
+}
+
+#[test]
+fn test_page_bbox_height_matches_scaled_ascent_descent() {
+    let content = b"BT /F1 24 Tf 100 700 Td (Hello) Tj ET";
+    let doc = create_helvetica_doc(content);
+    let fonts = parse_page_fonts(&doc, 1).expect("parse fonts");
+
+    // システムフォントが無い環境ではF1が解決できないためスキップする
+    let Some(font) = fonts.get("F1") else {
+        warn!("skipping page_bbox test: system Helvetica (F1) could not be resolved");
+        return;
+    };
+
+    let result = parse_content_operations(content, Some(&fonts)).expect("should parse");
+    assert_eq!(result.text_commands.len(), 1);
+    let bbox = result.text_commands[0].page_bbox(font);
+
+    let expected_height = (font.ascent() - font.descent()) / 1000.0 * 24.0;
+    assert!(
+        ((bbox.y_max - bbox.y_min) - expected_height).abs() < 1e-6,
+        "bbox height should be scaled ascent+descent: got {}, expected {}",
+        bbox.y_max - bbox.y_min,
+        expected_height
+    );
+    assert!((bbox.y_min - (700.0 + font.descent() / 1000.0 * 24.0)).abs() < 1e-6);
+    assert!((bbox.x_min - 100.0).abs() < 1e-6);
+
+    // 幅は各グリフ幅の合計
+    let expected_width: f64 = b"Hello"
+        .iter()
+        .map(|&c| font.glyph_width(c as u16) / 1000.0 * 24.0)
+        .sum();
+    assert!(((bbox.x_max - bbox.x_min) - expected_width).abs() < 1e-6);
+}
+
+#[test]
+fn test_page_bbox_applies_horizontal_scaling_and_ctm() {
+    let content = b"2 0 0 2 10 20 cm BT /F1 10 Tf 50 Tz (AB) Tj ET";
+    let doc = create_helvetica_doc(content);
+    let fonts = parse_page_fonts(&doc, 1).expect("parse fonts");
+
+    let Some(font) = fonts.get("F1") else {
+        warn!("skipping page_bbox test: system Helvetica (F1) could not be resolved");
+        return;
+    };
+
+    let result = parse_content_operations(content, Some(&fonts)).expect("should parse");
+    let bbox = result.text_commands[0].page_bbox(font);
+
+    // Tz 50 で幅は半分、CTMで2倍
+    let expected_width = (font.glyph_width(0x41) + font.glyph_width(0x42)) / 1000.0 * 10.0;
+    assert!(((bbox.x_max - bbox.x_min) - expected_width).abs() < 1e-6);
+    assert!((bbox.x_min - 10.0).abs() < 1e-6);
+    let expected_height = (font.ascent() - font.descent()) / 1000.0 * 10.0 * 2.0;
+    assert!(((bbox.y_max - bbox.y_min) - expected_height).abs() < 1e-6);
+}
+
+// ============================================================
+// 8. アウトラインの無いフォント（幅専用）
+// ============================================================
+
+/// ヘルパー: /Widthsだけを持つType3フォント（'A','B'とも幅500）を F3 として持つ1ページPDFを作成する
+fn create_type3_doc(content: &[u8]) -> Document {
+    let mut doc = Document::with_version("1.4");
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type3",
+        "FontBBox" => vec![0.into(), 0.into(), 500.into(), 700.into()],
+        "FontMatrix" => vec![0.001.into(), 0.into(), 0.into(), 0.001.into(), 0.into(), 0.into()],
+        "CharProcs" => dictionary! {},
+        "Encoding" => dictionary! { "Differences" => vec![65.into(), "a".into(), "b".into()] },
+        "FirstChar" => 65,
+        "LastChar" => 66,
+        "Widths" => vec![500.into(), 500.into()],
+    });
+    let content_id = doc.add_object(Stream::new(dictionary! {}, content.to_vec()));
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        "Contents" => content_id,
+        "Resources" => dictionary! {
+            "Font" => dictionary! { "F3" => font_id },
+        },
+    });
+    let pages_id = doc.add_object(dictionary! {
+        "Type" => "Pages",
+        "Kids" => vec![Object::Reference(page_id)],
+        "Count" => 1,
+    });
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    doc
+}
+
+#[test]
+fn test_width_only_font_advances_following_text() {
+    let content = b"BT /F3 10 Tf 100 700 Td (AB) Tj (A) Tj ET";
+    let doc = create_type3_doc(content);
+    let fonts = parse_page_fonts(&doc, 1).expect("parse fonts");
+    let font = fonts.get("F3").expect("Type3 font is kept as widths only");
+    assert!(!font.has_outlines());
+
+    let result = parse_content_operations(content, Some(&fonts)).expect("should parse");
+    assert_eq!(result.text_commands.len(), 2);
+    // 2文字 × 500/1000 × 10pt = 10pt 進んだ位置から次のTjが始まる
+    let second = &result.text_commands[1].text_matrix;
+    assert!((second.e - 110.0).abs() < 1e-6, "e: {}", second.e);
+    assert!((second.f - 700.0).abs() < 1e-6, "f: {}", second.f);
+}
+
+#[test]
+fn test_page_quad_keeps_rotation_of_rotated_text() {
+    // 45度回転したTj
+    let content =
+        b"BT /F3 10 Tf 0.70710678 0.70710678 -0.70710678 0.70710678 100 100 Tm (AB) Tj ET";
+    let doc = create_type3_doc(content);
+    let fonts = parse_page_fonts(&doc, 1).expect("parse fonts");
+    let font = fonts.get("F3").expect("Type3 font is kept as widths only");
+
+    let result = parse_content_operations(content, Some(&fonts)).expect("should parse");
+    let cmd = &result.text_commands[0];
+    let quad = cmd.page_quad(font);
+    assert!(!quad.is_axis_aligned(), "quad should be rotated: {quad:?}");
+
+    // 字送り方向の辺は45度傾いた長さ10pt（2文字 × 500/1000 × 10pt）
+    assert!((quad.a - quad.b).abs() < 1e-6, "{quad:?}");
+    assert!((quad.a.hypot(quad.b) - 10.0).abs() < 1e-6, "{quad:?}");
+    let height = (font.ascent() - font.descent()) / 1000.0 * 10.0;
+    assert!((quad.c.hypot(quad.d) - height).abs() < 1e-6, "{quad:?}");
+
+    // 軸平行のbboxはquadに外接する
+    let bbox = cmd.page_bbox(font);
+    let corners = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)];
+    for (u, v) in corners {
+        let x = quad.a * u + quad.c * v + quad.e;
+        let y = quad.b * u + quad.d * v + quad.f;
+        assert!(x >= bbox.x_min - 1e-9 && x <= bbox.x_max + 1e-9);
+        assert!(y >= bbox.y_min - 1e-9 && y <= bbox.y_max + 1e-9);
+    }
+    assert!(bbox.x_max - bbox.x_min > 10.0 * std::f64::consts::FRAC_1_SQRT_2);
+}
@@ -2,9 +2,12 @@
 
 use std::collections::HashMap;
 
+use lopdf::dictionary;
+use pdf_masking::config::job::WidthSource;
 use pdf_masking::pdf::font::{FontEncoding, ParsedFont};
 use pdf_masking::pdf::text_to_outlines::{
-    convert_text_to_outlines, extract_char_codes_for_encoding, parse_tj_entries_for_encoding,
+    convert_text_to_outlines, convert_text_to_outlines_with_width_source,
+    extract_char_codes_for_encoding, parse_tj_entries_for_encoding,
 };
 use tracing::warn;
 
@@ -100,6 +103,16 @@ fn test_sample_pdf_converts_with_system_fonts() {
     );
 }
 
+#[test]
+fn test_quote_operator_malformed_operands_without_font() {
+    // 空文字列や不正なオペランドの " は何も描画しないため、フォントが無くても成功する
+    let content = b"BT /F99 12 Tf 1 2 () \" /Bad (x) () \" (AB) \" ET";
+    let fonts = HashMap::new();
+
+    let result = convert_text_to_outlines(content, &fonts, false);
+    assert!(result.is_ok(), "unexpected error: {:?}", result.err());
+}
+
 // ============================================================
 // 3. CIDフォント（IdentityH）の2バイト文字コード
 // ============================================================
@@ -275,3 +288,291 @@ fn test_multiple_text_blocks() {
         m_count
     );
 }
+
+// ============================================================
+// 5. 水平スケーリング（Tz）
+// ============================================================
+
+/// 非埋め込みフォントを1つ持つ1ページのPDFからフォントを解決する。
+fn load_system_font(base_font: &str) -> HashMap<String, ParsedFont> {
+    load_system_font_with_dict(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "TrueType",
+        "BaseFont" => base_font,
+        "Encoding" => "WinAnsiEncoding",
+    })
+}
+
+/// フォント辞書をF1として持つ1ページPDFからフォントを解決する。
+fn load_system_font_with_dict(font_dict: lopdf::Dictionary) -> HashMap<String, ParsedFont> {
+    use lopdf::{Document, Object, Stream};
+
+    let mut doc = Document::with_version("1.7");
+    let pages_id = doc.new_object_id();
+    let font_id = doc.add_object(font_dict);
+    let content_id = doc.add_object(Stream::new(dictionary! {}, Vec::new()));
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), 200.into(), 200.into()],
+        "Contents" => content_id,
+        "Resources" => dictionary! { "Font" => dictionary! { "F1" => font_id } },
+    });
+    doc.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    pdf_masking::pdf::font::parse_page_fonts(&doc, 1).unwrap_or_default()
+}
+
+/// パス演算子（m/l/c）の座標をすべて集める。
+fn path_points(path: &str) -> Vec<(f64, f64)> {
+    let tokens: Vec<&str> = path.split_whitespace().collect();
+    let mut points = Vec::new();
+    for (i, &token) in tokens.iter().enumerate() {
+        let operands = match token {
+            "m" | "l" => 2,
+            "c" => 6,
+            _ => continue,
+        };
+        for j in (i - operands..i).step_by(2) {
+            points.push((
+                tokens[j].parse::<f64>().expect("numeric operand"),
+                tokens[j + 1].parse::<f64>().expect("numeric operand"),
+            ));
+        }
+    }
+    points
+}
+
+/// パス演算子（m/l/c）の座標からx方向の幅を求める。
+fn path_x_extent(path: &str) -> f64 {
+    let xs: Vec<f64> = path_points(path).into_iter().map(|(x, _)| x).collect();
+    let min = xs.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = xs.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    max - min
+}
+
+#[test]
+fn test_horizontal_scaling_narrows_glyph_shapes() {
+    let fonts = load_system_font("DejaVuSans");
+    if !fonts.contains_key("F1") {
+        warn!("SKIP: DejaVuSans not resolved — system font not available");
+        return;
+    }
+
+    let full = convert_text_to_outlines(b"BT /F1 100 Tf 100 Tz (H) Tj ET", &fonts, false)
+        .expect("convert at 100 Tz");
+    let half = convert_text_to_outlines(b"BT /F1 100 Tf 50 Tz (H) Tj ET", &fonts, false)
+        .expect("convert at 50 Tz");
+
+    // 送り幅だけでなくグリフの形状自体が半分の幅になる
+    let full_width = path_x_extent(&String::from_utf8_lossy(&full));
+    let half_width = path_x_extent(&String::from_utf8_lossy(&half));
+    assert!(full_width > 0.0, "glyph should produce a path");
+    assert!(
+        (half_width / full_width - 0.5).abs() < 0.01,
+        "50 Tz glyph should be half as wide: {half_width} vs {full_width}"
+    );
+}
+
+#[test]
+fn test_text_rise_raises_glyph_shapes() {
+    let fonts = load_system_font("DejaVuSans");
+    if !fonts.contains_key("F1") {
+        warn!("SKIP: DejaVuSans not resolved — system font not available");
+        return;
+    }
+
+    // 上付きの脚注記号: Tsはテキスト空間の値なので、Tmの2倍拡大を受けて8pt上がる
+    let convert = |content: &[u8]| {
+        let path = convert_text_to_outlines(content, &fonts, false).expect("convert");
+        path_points(&String::from_utf8_lossy(&path))
+    };
+    let baseline = convert(b"BT /F1 10 Tf 2 0 0 2 100 100 Tm 0 Ts (1) Tj ET");
+    let raised = convert(b"BT /F1 10 Tf 2 0 0 2 100 100 Tm 4 Ts (1) Tj ET");
+
+    assert!(!baseline.is_empty(), "glyph should produce a path");
+    assert_eq!(baseline.len(), raised.len());
+    for ((x0, y0), (x1, y1)) in baseline.iter().zip(&raised) {
+        assert!((x1 - x0).abs() < 0.01, "x should not move: {x0} vs {x1}");
+        assert!(
+            (y1 - y0 - 8.0).abs() < 0.01,
+            "4 Ts glyph should sit 8pt higher: {y0} vs {y1}"
+        );
+    }
+}
+
+/// 'H','I'の/Widthsをわざと10倍（5000）にしたDejaVuSansをF1として解決する。
+fn load_font_with_wrong_widths() -> HashMap<String, ParsedFont> {
+    load_system_font_with_dict(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "TrueType",
+        "BaseFont" => "DejaVuSans",
+        "Encoding" => "WinAnsiEncoding",
+        "FirstChar" => 72,
+        "LastChar" => 73,
+        "Widths" => vec![5000.into(), 5000.into()],
+    })
+}
+
+#[test]
+fn test_width_source_font_ignores_wrong_widths() {
+    let correct = load_system_font("DejaVuSans");
+    let wrong = load_font_with_wrong_widths();
+    if !correct.contains_key("F1") || !wrong.contains_key("F1") {
+        warn!("SKIP: DejaVuSans not resolved — system font not available");
+        return;
+    }
+    let content = b"BT /F1 10 Tf 100 700 Td (HI) Tj ET";
+
+    // /Widthsを省略したフォントはフォント自身のadvanceで字送りする
+    let expected = convert_text_to_outlines(content, &correct, false).expect("convert reference");
+    let by_pdf = convert_text_to_outlines(content, &wrong, false).expect("convert with /Widths");
+    let by_font =
+        convert_text_to_outlines_with_width_source(content, &wrong, false, WidthSource::Font)
+            .expect("convert with font advances");
+    let by_max =
+        convert_text_to_outlines_with_width_source(content, &wrong, false, WidthSource::Max)
+            .expect("convert with the larger width");
+
+    assert_ne!(by_pdf, expected, "wrong /Widths should spread the text");
+    assert_eq!(
+        String::from_utf8_lossy(&by_font),
+        String::from_utf8_lossy(&expected)
+    );
+    assert_eq!(by_max, by_pdf, "max should keep the wider /Widths");
+}
+
+// ============================================================
+// 6. アウトラインの無いフォント（幅専用）
+// ============================================================
+
+/// /Widthsだけを持つType3フォント（'A','B'とも幅500）をF3として解決する。
+fn load_type3_font() -> HashMap<String, ParsedFont> {
+    use lopdf::{Document, Object, Stream};
+
+    let mut doc = Document::with_version("1.7");
+    let font_id = doc.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type3",
+        "FontBBox" => vec![0.into(), 0.into(), 500.into(), 700.into()],
+        "FontMatrix" => vec![0.001.into(), 0.into(), 0.into(), 0.001.into(), 0.into(), 0.into()],
+        "CharProcs" => dictionary! {},
+        "FirstChar" => 65,
+        "LastChar" => 66,
+        "Widths" => vec![500.into(), 500.into()],
+    });
+    let content_id = doc.add_object(Stream::new(dictionary! {}, Vec::new()));
+    let page_id = doc.add_object(dictionary! {
+        "Type" => "Page",
+        "MediaBox" => vec![0.into(), 0.into(), 200.into(), 200.into()],
+        "Contents" => content_id,
+        "Resources" => dictionary! { "Font" => dictionary! { "F3" => font_id } },
+    });
+    let pages_id = doc.add_object(dictionary! {
+        "Type" => "Pages",
+        "Kids" => vec![Object::Reference(page_id)],
+        "Count" => 1,
+    });
+    let catalog_id = doc.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    doc.trailer.set("Root", catalog_id);
+    pdf_masking::pdf::font::parse_page_fonts(&doc, 1).expect("parse fonts")
+}
+
+#[test]
+fn test_invisible_width_only_text_keeps_following_text_position() {
+    let mut fonts = load_system_font("DejaVuSans");
+    if !fonts.contains_key("F1") {
+        warn!("SKIP: DejaVuSans not resolved — system font not available");
+        return;
+    }
+    fonts.extend(load_type3_font());
+
+    // 不可視のType3テキスト(2文字 × 500/1000 × 10pt)の後に描くHは、x=110から描いた場合と一致する
+    let after_type3 = convert_text_to_outlines(
+        b"BT /F3 10 Tf 3 Tr 100 700 Td (AB) Tj 0 Tr /F1 10 Tf (H) Tj ET",
+        &fonts,
+        false,
+    )
+    .expect("invisible width-only text should not fail the conversion");
+    let expected = convert_text_to_outlines(b"BT /F1 10 Tf 110 700 Td (H) Tj ET", &fonts, false)
+        .expect("convert reference");
+    assert!(!expected.is_empty());
+    assert_eq!(
+        String::from_utf8_lossy(&after_type3),
+        String::from_utf8_lossy(&expected)
+    );
+}
+
+#[test]
+fn test_visible_width_only_text_returns_error() {
+    // グリフを描けない可視テキストは、消さずにフォールバックさせる
+    let fonts = load_type3_font();
+    let result = convert_text_to_outlines(b"BT /F3 10 Tf 100 700 Td (AB) Tj ET", &fonts, false);
+    assert!(result.is_err(), "visible Type3 text should fall back");
+}
+
+// ============================================================
+// 7. クリップモードのテキスト（Tr 7）
+// ============================================================
+
+#[test]
+fn test_clip_text_clips_following_image() {
+    let fonts = load_system_font("DejaVuSans");
+    if !fonts.contains_key("F1") {
+        warn!("SKIP: DejaVuSans not resolved — system font not available");
+        return;
+    }
+    let content =
+        b"q 2 0 0 2 10 10 cm BT /F1 48 Tf 7 Tr 0 0 Td (AB) Tj ET 100 0 0 50 0 0 cm /Im0 Do Q";
+
+    let output = convert_text_to_outlines(content, &fonts, false).expect("convert clip text");
+    let ops = lopdf::content::Content::decode(&output)
+        .expect("output should be a valid content stream")
+        .operations;
+    let operators: Vec<&str> = ops.iter().map(|op| op.operator.as_str()).collect();
+
+    let clip = operators
+        .iter()
+        .position(|&op| op == "W")
+        .expect("clip operator W expected");
+    let image = operators
+        .iter()
+        .position(|&op| op == "Do")
+        .expect("image should be kept");
+    assert!(
+        clip < image,
+        "clip must come before the image: {operators:?}"
+    );
+    assert_eq!(operators[clip + 1], "n", "clip path should not be painted");
+    // グリフのパスはq/Qの内側、cmの後にあり、画像と同じ範囲でクリップする
+    let first_path = operators.iter().position(|&op| op == "m").unwrap();
+    assert!(operators[..first_path].contains(&"cm"));
+    assert_eq!(operators.last(), Some(&"Q"));
+    assert_eq!(operators.iter().filter(|&&op| op == "Q").count(), 1);
+    // Tr 7のテキストは塗らない
+    assert!(
+        !operators.contains(&"f"),
+        "clip-only text should not be filled"
+    );
+
+    // クリップのパスはユーザー空間（cm適用前）の座標で書かれる: 48ptの文字は高さ50未満
+    let first_move = &ops[first_path];
+    let y = first_move.operands[1]
+        .as_float()
+        .unwrap_or_else(|_| first_move.operands[1].as_i64().expect("number") as f32);
+    assert!((-20.0..50.0).contains(&y), "unexpected clip coordinate {y}");
+}